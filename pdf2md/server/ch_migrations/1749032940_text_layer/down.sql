@@ -0,0 +1,8 @@
+ALTER TABLE file_chunks
+DROP COLUMN IF EXISTS extraction_method;
+
+ALTER TABLE file_tasks
+DROP COLUMN IF EXISTS ocr_pages;
+
+ALTER TABLE file_tasks
+DROP COLUMN IF EXISTS text_layer_pages;
@@ -0,0 +1,8 @@
+ALTER TABLE file_tasks
+ADD COLUMN IF NOT EXISTS text_layer_pages Array(UInt32);
+
+ALTER TABLE file_tasks
+ADD COLUMN IF NOT EXISTS ocr_pages Array(UInt32);
+
+ALTER TABLE file_chunks
+ADD COLUMN IF NOT EXISTS extraction_method String DEFAULT 'llm';
@@ -0,0 +1,6 @@
+ALTER TABLE file_tasks
+DROP COLUMN IF EXISTS api_key_id;
+
+DROP TABLE IF EXISTS page_usage;
+
+DROP TABLE IF EXISTS api_keys;
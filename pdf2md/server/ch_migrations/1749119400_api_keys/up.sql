@@ -0,0 +1,25 @@
+CREATE TABLE IF NOT EXISTS api_keys (
+    id String,
+    name String,
+    key_hash String,
+    created_at DateTime,
+    revoked_at Nullable(DateTime),
+) ENGINE = MergeTree()
+ORDER BY (id);
+
+CREATE TABLE IF NOT EXISTS page_usage (
+    api_key_id String,
+    task_id String,
+    page UInt32,
+    extraction_method String,
+    prompt_tokens UInt64,
+    completion_tokens UInt64,
+    total_tokens UInt64,
+    created_at DateTime,
+) ENGINE = MergeTree()
+ORDER BY (api_key_id, created_at)
+PARTITION BY
+    (toYYYYMM(created_at));
+
+ALTER TABLE file_tasks
+ADD COLUMN IF NOT EXISTS api_key_id String;
@@ -6,7 +6,12 @@ use actix_web::{
 };
 use chm::tools::migrations::{run_pending_migrations, SetupArgs};
 use errors::{custom_json_error_handler, ErrorResponseBody};
-use routes::{create_task::create_task, get_task::get_task, jinja_templates};
+use routes::{
+    api_keys::{create_api_key, get_api_keys, get_usage, revoke_api_key},
+    create_task::create_task,
+    get_task::get_task,
+    jinja_templates,
+};
 use utoipa::{
     openapi::security::{ApiKey, ApiKeyValue, SecurityScheme},
     Modify, OpenApi,
@@ -87,6 +92,7 @@ pub async fn main() -> std::io::Result<()> {
     modifiers(&SecurityAddon),
     tags(
         (name = "Task", description = "Task operations. Allow you to interact with tasks."),
+        (name = "API Key", description = "API key operations. Allow you to manage API keys and see their usage."),
     ))]
     struct ApiDoc;
 
@@ -173,6 +179,15 @@ pub async fn main() -> std::io::Result<()> {
             .service(utoipa_actix_web::scope("/api/task").configure(|config| {
                 config.service(create_task).service(get_task);
             }))
+            .service(utoipa_actix_web::scope("/api/key").configure(|config| {
+                config
+                    .service(create_api_key)
+                    .service(get_api_keys)
+                    .service(revoke_api_key);
+            }))
+            .service(utoipa_actix_web::scope("/api/usage").configure(|config| {
+                config.service(get_usage);
+            }))
             .service(utoipa_actix_web::scope("/health").configure(|config| {
                 config.service(health_check);
             }))
@@ -1,16 +1,38 @@
-use crate::{errors::ServiceError, get_env};
+use crate::{
+    errors::ServiceError, get_env, models::RedisPool, operators::api_keys::get_cached_api_key_id,
+};
 use actix_web::{
     dev::{Payload, Service, ServiceRequest, ServiceResponse, Transform},
-    FromRequest, HttpMessage, HttpRequest,
+    web, FromRequest, HttpMessage, HttpRequest,
 };
 use futures::future::LocalBoxFuture;
+use sha2::{Digest, Sha256};
 use std::{
     future::{ready, Ready},
     rc::Rc,
 };
 
 #[derive(Clone, Debug)]
-pub struct ApiKey;
+pub struct ApiKey {
+    /// Id of the key in the API key store, None for the admin key set by `API_KEY`
+    pub id: Option<uuid::Uuid>,
+}
+
+impl ApiKey {
+    pub fn is_admin(&self) -> bool {
+        self.id.is_none()
+    }
+
+    /// The id recorded on tasks and usage created with this key, empty for the admin key
+    pub fn owner_id(&self) -> String {
+        self.id.map(|id| id.to_string()).unwrap_or_default()
+    }
+
+    /// The admin key can access every task, other keys only the tasks they created
+    pub fn can_access(&self, owner_id: &str) -> bool {
+        self.is_admin() || self.owner_id() == owner_id
+    }
+}
 
 impl FromRequest for ApiKey {
     type Error = ServiceError;
@@ -21,12 +43,42 @@ impl FromRequest for ApiKey {
         let ext = req.extensions();
 
         match ext.get::<ApiKey>() {
-            Some(_) => ready(Ok(Self)),
+            Some(api_key) => ready(Ok(api_key.clone())),
             None => ready(Err(ServiceError::Unauthorized)),
         }
     }
 }
 
+/// Extractor for routes which manage API keys and therefore require the admin key
+#[derive(Clone, Debug)]
+pub struct AdminApiKey;
+
+impl FromRequest for AdminApiKey {
+    type Error = ServiceError;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    #[inline]
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        let ext = req.extensions();
+
+        match ext.get::<ApiKey>() {
+            Some(api_key) if api_key.is_admin() => ready(Ok(Self)),
+            Some(_) => ready(Err(ServiceError::Forbidden)),
+            None => ready(Err(ServiceError::Unauthorized)),
+        }
+    }
+}
+
+/// Compares the sent key with the admin key in constant time. Both are hashed first such that
+/// the comparison does not reveal the length of the admin key either.
+fn is_admin_api_key(authorization: &str, admin_api_key: &str) -> bool {
+    Sha256::digest(authorization.as_bytes())
+        .iter()
+        .zip(Sha256::digest(admin_api_key.as_bytes()).iter())
+        .fold(0, |diff, (a, b)| diff | (a ^ b))
+        == 0
+}
+
 pub struct ApiKeyMiddlewareFactory;
 
 impl<S, B> Transform<S, ServiceRequest> for ApiKeyMiddlewareFactory
@@ -54,7 +106,7 @@ pub struct ApiKeyMiddleware<S> {
 
 impl<S, B> Service<ServiceRequest> for ApiKeyMiddleware<S>
 where
-    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = actix_web::Error>,
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = actix_web::Error> + 'static,
     S::Future: 'static,
 {
     type Response = ServiceResponse<B>;
@@ -69,20 +121,73 @@ where
     }
 
     fn call(&self, req: ServiceRequest) -> Self::Future {
-        let api_key = get_env!("API_KEY", "API_KEY should be set");
-        if req
-            .headers()
-            .get("Authorization")
-            .is_some_and(|v| v == api_key)
-        {
-            req.extensions_mut().insert(ApiKey);
-        }
-
-        let future = self.service.call(req);
+        let service = Rc::clone(&self.service);
 
         Box::pin(async move {
-            let response = future.await?;
+            let admin_api_key = get_env!("API_KEY", "API_KEY should be set");
+            let authorization = req
+                .headers()
+                .get("Authorization")
+                .and_then(|v| v.to_str().ok())
+                .map(|v| v.to_string());
+
+            if let Some(authorization) = authorization {
+                if is_admin_api_key(&authorization, admin_api_key) {
+                    req.extensions_mut().insert(ApiKey { id: None });
+                } else if let (Some(clickhouse_client), Some(redis_pool)) = (
+                    req.app_data::<web::Data<clickhouse::Client>>().cloned(),
+                    req.app_data::<web::Data<RedisPool>>().cloned(),
+                ) {
+                    match get_cached_api_key_id(&authorization, &clickhouse_client, &redis_pool)
+                        .await
+                    {
+                        Ok(Some(api_key_id)) => {
+                            if let Ok(id) = api_key_id.parse() {
+                                req.extensions_mut().insert(ApiKey { id: Some(id) });
+                            }
+                        }
+                        Ok(None) => {}
+                        Err(err) => log::error!("Failed to check api key {:?}", err),
+                    }
+                }
+            }
+
+            let response = service.call(req).await?;
             Ok(response)
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn api_key_can_access_only_its_own_tasks() {
+        let id = uuid::Uuid::new_v4();
+        let api_key = ApiKey { id: Some(id) };
+
+        assert!(!api_key.is_admin());
+        assert!(api_key.can_access(&id.to_string()));
+        assert!(!api_key.can_access(&uuid::Uuid::new_v4().to_string()));
+        assert!(!api_key.can_access(""));
+    }
+
+    #[test]
+    fn admin_api_key_can_access_every_task() {
+        let api_key = ApiKey { id: None };
+
+        assert!(api_key.is_admin());
+        assert_eq!(api_key.owner_id(), "");
+        assert!(api_key.can_access(""));
+        assert!(api_key.can_access(&uuid::Uuid::new_v4().to_string()));
+    }
+
+    #[test]
+    fn is_admin_api_key_matches_exact_key_only() {
+        assert!(is_admin_api_key("admin", "admin"));
+        assert!(!is_admin_api_key("admin ", "admin"));
+        assert!(!is_admin_api_key("admi", "admin"));
+        assert!(!is_admin_api_key("", "admin"));
+    }
+}
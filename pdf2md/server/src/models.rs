@@ -113,6 +113,10 @@ pub struct UploadFileReqPayload {
     pub chunkr_api_key: Option<String>,
     /// The request payload to use for the Chunkr API create task endpoint.
     pub chunkr_create_task_req_payload: Option<CreateFormWithoutFile>,
+    /// Whether to take the text of pages directly from the PDF's text layer. Only pages with no or garbled text are sent to the LLM. If Chunkr is used, this makes `Auto` the default `ocr_strategy` such that Chunkr only runs OCR on those pages. Default is true.
+    pub use_text_layer: Option<bool>,
+    /// Minimum score between 0 and 1 a page's text layer must reach to be used instead of the LLM. The score is the share of the page's text which looks like readable words. Default is 0.85.
+    pub text_layer_min_score: Option<f32>,
 }
 
 #[derive(Debug)]
@@ -165,6 +169,33 @@ impl From<UploadFileReqPayload> for ChunkingParams {
     }
 }
 
+#[derive(serde::Deserialize, serde::Serialize, Clone, Debug, ToSchema, Display, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ExtractionMethod {
+    /// The page's text was taken directly from the PDF's text layer.
+    #[display("text_layer")]
+    TextLayer,
+    /// The page was rendered to an image and converted by the LLM.
+    #[display("llm")]
+    Llm,
+    /// The page was converted by Chunkr.
+    #[display("chunkr")]
+    Chunkr,
+}
+
+impl std::str::FromStr for ExtractionMethod {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text_layer" => Ok(ExtractionMethod::TextLayer),
+            "llm" => Ok(ExtractionMethod::Llm),
+            "chunkr" => Ok(ExtractionMethod::Chunkr),
+            _ => Err(format!("Unknown extraction method: {}", s)),
+        }
+    }
+}
+
 #[derive(Debug, serde::Serialize, serde::Deserialize, clickhouse::Row, Clone)]
 pub struct FileTaskClickhouse {
     pub id: String,
@@ -177,6 +208,10 @@ pub struct FileTaskClickhouse {
     pub created_at: OffsetDateTime,
     pub chunkr_task_id: String,
     pub chunkr_api_key: Option<String>,
+    pub text_layer_pages: Vec<u32>,
+    pub ocr_pages: Vec<u32>,
+    /// Id of the API key which created the task, empty for the admin key
+    pub api_key_id: String,
 }
 
 #[derive(Debug, serde::Serialize, serde::Deserialize, clickhouse::Row, Clone)]
@@ -188,6 +223,7 @@ pub struct ChunkClickhouse {
     pub usage: String,
     #[serde(with = "clickhouse::serde::time::datetime")]
     pub created_at: OffsetDateTime,
+    pub extraction_method: String,
 }
 
 #[derive(Debug, serde::Serialize, serde::Deserialize, Clone, ToSchema)]
@@ -199,6 +235,8 @@ pub struct PdfToMdPage {
     pub page_num: u32,
     pub usage: serde_json::Value,
     pub created_at: String,
+    /// How the content of the page was extracted.
+    pub extraction_method: ExtractionMethod,
 }
 
 impl From<ChunkClickhouse> for PdfToMdPage {
@@ -211,6 +249,7 @@ impl From<ChunkClickhouse> for PdfToMdPage {
             page_num: c.page,
             usage: serde_json::from_str(&c.usage).unwrap(),
             created_at: c.created_at.to_string(),
+            extraction_method: c.extraction_method.parse().unwrap_or(ExtractionMethod::Llm),
         }
     }
 }
@@ -265,6 +304,7 @@ impl From<TaskResponse> for Vec<PdfToMdPage> {
                     page_num,
                     usage: serde_json::json!({}),
                     created_at: response.created_at.to_string(),
+                    extraction_method: ExtractionMethod::Chunkr,
                 })
                 .collect()
         } else {
@@ -273,6 +313,114 @@ impl From<TaskResponse> for Vec<PdfToMdPage> {
     }
 }
 
+#[derive(Debug, serde::Serialize, serde::Deserialize, clickhouse::Row, Clone)]
+pub struct ApiKeyClickhouse {
+    pub id: String,
+    pub name: String,
+    pub key_hash: String,
+    #[serde(with = "clickhouse::serde::time::datetime")]
+    pub created_at: OffsetDateTime,
+    #[serde(with = "clickhouse::serde::time::datetime::option")]
+    pub revoked_at: Option<OffsetDateTime>,
+}
+
+#[derive(Debug, serde::Serialize, serde::Deserialize, Clone, ToSchema)]
+pub struct CreateApiKeyReqPayload {
+    /// Name to identify the key by, e.g. the team it is given to.
+    pub name: String,
+}
+
+#[derive(Debug, serde::Serialize, serde::Deserialize, Clone, ToSchema)]
+pub struct ApiKeyDTO {
+    pub id: String,
+    pub name: String,
+    pub created_at: String,
+    pub revoked_at: Option<String>,
+}
+
+impl From<ApiKeyClickhouse> for ApiKeyDTO {
+    fn from(api_key: ApiKeyClickhouse) -> Self {
+        ApiKeyDTO {
+            id: api_key.id,
+            name: api_key.name,
+            created_at: api_key.created_at.to_string(),
+            revoked_at: api_key.revoked_at.map(|revoked_at| revoked_at.to_string()),
+        }
+    }
+}
+
+#[derive(Debug, serde::Serialize, serde::Deserialize, Clone, ToSchema)]
+pub struct CreateApiKeyResponse {
+    /// The key to send in the Authorization header. It is not stored and cannot be retrieved again.
+    pub api_key: String,
+    #[serde(flatten)]
+    pub key: ApiKeyDTO,
+}
+
+#[derive(Debug, serde::Serialize, serde::Deserialize, Clone, ToSchema)]
+pub struct GetApiKeysResponse {
+    pub api_keys: Vec<ApiKeyDTO>,
+}
+
+/// A page processed for an API key, along with the LLM tokens spent on it
+#[derive(Debug, serde::Serialize, serde::Deserialize, clickhouse::Row, Clone)]
+pub struct PageUsageClickhouse {
+    pub api_key_id: String,
+    pub task_id: String,
+    pub page: u32,
+    pub extraction_method: String,
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+    pub total_tokens: u64,
+    #[serde(with = "clickhouse::serde::time::datetime")]
+    pub created_at: OffsetDateTime,
+}
+
+impl PageUsageClickhouse {
+    pub fn from_page(api_key_id: &str, page: &ChunkClickhouse) -> Self {
+        let usage = serde_json::from_str::<serde_json::Value>(&page.usage).unwrap_or_default();
+        let tokens = |key: &str| usage.get(key).and_then(|v| v.as_u64()).unwrap_or(0);
+
+        PageUsageClickhouse {
+            api_key_id: api_key_id.to_string(),
+            task_id: page.task_id.clone(),
+            page: page.page,
+            extraction_method: page.extraction_method.clone(),
+            prompt_tokens: tokens("prompt_tokens"),
+            completion_tokens: tokens("completion_tokens"),
+            total_tokens: tokens("total_tokens"),
+            created_at: page.created_at,
+        }
+    }
+}
+
+#[derive(Debug, serde::Serialize, serde::Deserialize, Clone)]
+pub struct GetUsageRequest {
+    /// Id of the key to get usage for. Only the admin key may see the usage of other keys.
+    pub api_key_id: Option<String>,
+    /// Only count pages processed at or after this time, e.g. `2025-06-01` or `2025-06-01T00:00:00Z`.
+    pub start_date: Option<String>,
+    /// Only count pages processed before this time.
+    pub end_date: Option<String>,
+}
+
+#[derive(Debug, serde::Serialize, serde::Deserialize, clickhouse::Row, Clone, ToSchema)]
+pub struct ApiKeyUsage {
+    /// Id of the API key, empty for the admin key
+    pub api_key_id: String,
+    pub pages_processed: u64,
+    pub text_layer_pages: u64,
+    pub llm_pages: u64,
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+    pub total_tokens: u64,
+}
+
+#[derive(Debug, serde::Serialize, serde::Deserialize, Clone, ToSchema)]
+pub struct GetUsageResponse {
+    pub usage: Vec<ApiKeyUsage>,
+}
+
 #[derive(Debug, serde::Serialize, serde::Deserialize, Clone)]
 pub struct GetTaskRequest {
     pub pagination_token: Option<u32>,
@@ -290,6 +438,10 @@ pub struct GetTaskResponse {
     pub created_at: String,
     pub pages: Option<Vec<PdfToMdPage>>,
     pub pagination_token: Option<u32>,
+    /// Pages whose content was taken from the PDF's text layer.
+    pub text_layer_pages: Vec<u32>,
+    /// Pages which were converted by the LLM because they had no or garbled text.
+    pub ocr_pages: Vec<u32>,
 }
 
 impl GetTaskResponse {
@@ -304,6 +456,8 @@ impl GetTaskResponse {
             created_at: task.created_at.to_string(),
             pagination_token: None,
             pages: None,
+            text_layer_pages: task.text_layer_pages,
+            ocr_pages: task.ocr_pages,
         }
     }
 
@@ -322,6 +476,8 @@ impl GetTaskResponse {
             created_at: task.created_at.to_string(),
             pagination_token: pages.last().map(|c| c.page),
             pages: Some(pages.into_iter().map(PdfToMdPage::from).collect()),
+            text_layer_pages: task.text_layer_pages,
+            ocr_pages: task.ocr_pages,
         }
     }
 
@@ -347,6 +503,8 @@ impl GetTaskResponse {
             created_at: task.created_at.to_string(),
             pagination_token: None,
             pages: Some(pages),
+            text_layer_pages: task.text_layer_pages,
+            ocr_pages: task.ocr_pages,
         }
     }
 }
@@ -402,3 +560,47 @@ impl From<String> for FileTaskStatus {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn page(usage: &str) -> ChunkClickhouse {
+        ChunkClickhouse {
+            id: uuid::Uuid::new_v4().to_string(),
+            task_id: "task".to_string(),
+            content: "content".to_string(),
+            page: 3,
+            usage: usage.to_string(),
+            created_at: OffsetDateTime::UNIX_EPOCH,
+            extraction_method: ExtractionMethod::Llm.to_string(),
+        }
+    }
+
+    #[test]
+    fn page_usage_from_page_reads_token_counts() {
+        let usage = PageUsageClickhouse::from_page(
+            "key",
+            &page(r#"{"prompt_tokens": 120, "completion_tokens": 30, "total_tokens": 150}"#),
+        );
+
+        assert_eq!(usage.api_key_id, "key");
+        assert_eq!(usage.task_id, "task");
+        assert_eq!(usage.page, 3);
+        assert_eq!(usage.extraction_method, "llm");
+        assert_eq!(usage.prompt_tokens, 120);
+        assert_eq!(usage.completion_tokens, 30);
+        assert_eq!(usage.total_tokens, 150);
+        assert_eq!(usage.created_at, OffsetDateTime::UNIX_EPOCH);
+    }
+
+    #[test]
+    fn page_usage_from_page_defaults_missing_tokens_to_zero() {
+        for usage in ["", "{}", "not json", r#"{"total_tokens": "many"}"#] {
+            let usage = PageUsageClickhouse::from_page("key", &page(usage));
+            assert_eq!(usage.prompt_tokens, 0);
+            assert_eq!(usage.completion_tokens, 0);
+            assert_eq!(usage.total_tokens, 0);
+        }
+    }
+}
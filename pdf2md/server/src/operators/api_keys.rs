@@ -0,0 +1,385 @@
+use crate::{
+    errors::ServiceError,
+    models::{
+        ApiKeyClickhouse, ApiKeyUsage, ChunkClickhouse, ExtractionMethod, FileTaskClickhouse,
+        PageUsageClickhouse, PdfToMdPage, RedisPool,
+    },
+};
+use s3::creds::time::OffsetDateTime;
+use sha2::{Digest, Sha256};
+
+/// How long a looked up key is cached in redis. Revoking a key drops it from the cache.
+const API_KEY_CACHE_TTL_SECS: u64 = 60;
+/// Outlives the 30 day TTL of the task tables so a task is never counted twice while it can
+/// still be polled.
+const USAGE_RECORDED_TTL_SECS: u64 = 31 * 24 * 60 * 60;
+
+fn api_key_cache_key(key_hash: &str) -> String {
+    format!("api_key:{}", key_hash)
+}
+
+pub fn hash_api_key(api_key: &str) -> String {
+    format!("{:x}", Sha256::digest(api_key.as_bytes()))
+}
+
+/// Creates a new API key. Only the hash of the key is stored, the plaintext key is returned
+/// alongside it and cannot be recovered afterwards.
+pub async fn create_api_key(
+    name: String,
+    clickhouse_client: &clickhouse::Client,
+) -> Result<(ApiKeyClickhouse, String), ServiceError> {
+    let api_key = format!("pdf2md-{}", uuid::Uuid::new_v4().simple());
+
+    let api_key_row = ApiKeyClickhouse {
+        id: uuid::Uuid::new_v4().to_string(),
+        name,
+        key_hash: hash_api_key(&api_key),
+        created_at: OffsetDateTime::now_utc(),
+        revoked_at: None,
+    };
+
+    let mut api_key_inserter = clickhouse_client.insert("api_keys").map_err(|e| {
+        log::error!("Error getting api_key_inserter: {:?}", e);
+        ServiceError::InternalServerError(format!("Error inserting api key: {:?}", e))
+    })?;
+
+    api_key_inserter.write(&api_key_row).await.map_err(|e| {
+        log::error!("Error inserting api key: {:?}", e);
+        ServiceError::InternalServerError(format!("Error inserting api key: {:?}", e))
+    })?;
+
+    api_key_inserter.end().await.map_err(|e| {
+        log::error!("Error terminating connection: {:?}", e);
+        ServiceError::InternalServerError(format!("Error inserting api key: {:?}", e))
+    })?;
+
+    Ok((api_key_row, api_key))
+}
+
+/// Looks up the key sent by a caller, ignoring revoked keys
+pub async fn get_api_key_by_value(
+    api_key: &str,
+    clickhouse_client: &clickhouse::Client,
+) -> Result<Option<ApiKeyClickhouse>, ServiceError> {
+    clickhouse_client
+        .query("SELECT ?fields FROM api_keys WHERE key_hash = ? AND revoked_at IS NULL LIMIT 1")
+        .bind(hash_api_key(api_key))
+        .fetch_optional()
+        .await
+        .map_err(|err| {
+            log::error!("Failed to get api key {:?}", err);
+            ServiceError::InternalServerError("Failed to get api key".to_string())
+        })
+}
+
+/// Looks up the id of the key sent by a caller, caching it in redis for a short time such that
+/// not every request has to query clickhouse. Unknown keys are not cached.
+pub async fn get_cached_api_key_id(
+    api_key: &str,
+    clickhouse_client: &clickhouse::Client,
+    redis_pool: &RedisPool,
+) -> Result<Option<String>, ServiceError> {
+    let cache_key = api_key_cache_key(&hash_api_key(api_key));
+
+    let mut redis_conn = redis_pool.get().await.map_err(|e| {
+        log::error!("Failed to get redis connection: {:?}", e);
+        ServiceError::InternalServerError("Failed to get redis connection".to_string())
+    })?;
+
+    match redis::cmd("GET")
+        .arg(&cache_key)
+        .query_async::<Option<String>>(&mut *redis_conn)
+        .await
+    {
+        Ok(Some(api_key_id)) => return Ok(Some(api_key_id)),
+        Ok(None) => {}
+        Err(err) => log::error!("Failed to get cached api key {:?}", err),
+    }
+
+    let Some(api_key) = get_api_key_by_value(api_key, clickhouse_client).await? else {
+        return Ok(None);
+    };
+
+    if let Err(err) = redis::cmd("SET")
+        .arg(&cache_key)
+        .arg(&api_key.id)
+        .arg("EX")
+        .arg(API_KEY_CACHE_TTL_SECS)
+        .query_async::<()>(&mut *redis_conn)
+        .await
+    {
+        log::error!("Failed to cache api key {:?}", err);
+    }
+
+    Ok(Some(api_key.id))
+}
+
+pub async fn get_api_keys(
+    clickhouse_client: &clickhouse::Client,
+) -> Result<Vec<ApiKeyClickhouse>, ServiceError> {
+    clickhouse_client
+        .query("SELECT ?fields FROM api_keys ORDER BY created_at DESC")
+        .fetch_all()
+        .await
+        .map_err(|err| {
+            log::error!("Failed to get api keys {:?}", err);
+            ServiceError::BadRequest("Failed to get api keys".to_string())
+        })
+}
+
+pub async fn revoke_api_key(
+    api_key_id: uuid::Uuid,
+    clickhouse_client: &clickhouse::Client,
+    redis_pool: &RedisPool,
+) -> Result<(), ServiceError> {
+    let api_key: Option<ApiKeyClickhouse> = clickhouse_client
+        .query("SELECT ?fields FROM api_keys WHERE id = ?")
+        .bind(api_key_id)
+        .fetch_optional()
+        .await
+        .map_err(|err| {
+            log::error!("Failed to get api key {:?}", err);
+            ServiceError::BadRequest("Failed to get api key".to_string())
+        })?;
+
+    let api_key = match api_key {
+        None => return Err(ServiceError::NotFound("API key not found".to_string())),
+        Some(api_key) if api_key.revoked_at.is_some() => return Ok(()),
+        Some(api_key) => api_key,
+    };
+
+    clickhouse_client
+        .query(
+            "ALTER TABLE api_keys UPDATE revoked_at = now() WHERE id = ? SETTINGS mutations_sync = 2",
+        )
+        .bind(api_key_id)
+        .execute()
+        .await
+        .map_err(|err| {
+            log::error!("Failed to revoke api key {:?}", err);
+            ServiceError::BadRequest("Failed to revoke api key".to_string())
+        })?;
+
+    let mut redis_conn = redis_pool.get().await.map_err(|e| {
+        log::error!("Failed to get redis connection: {:?}", e);
+        ServiceError::InternalServerError("Failed to get redis connection".to_string())
+    })?;
+
+    redis::cmd("DEL")
+        .arg(api_key_cache_key(&api_key.key_hash))
+        .query_async::<()>(&mut *redis_conn)
+        .await
+        .map_err(|err| {
+            log::error!("Failed to drop cached api key {:?}", err);
+            ServiceError::InternalServerError("Failed to drop cached api key".to_string())
+        })
+}
+
+/// Records the given pages as processed for the key which created their task
+pub async fn insert_page_usage(
+    api_key_id: &str,
+    pages: &[ChunkClickhouse],
+    clickhouse_client: &clickhouse::Client,
+) -> Result<(), ServiceError> {
+    let usage = pages
+        .iter()
+        .map(|page| PageUsageClickhouse::from_page(api_key_id, page))
+        .collect::<Vec<PageUsageClickhouse>>();
+
+    insert_page_usage_rows(&usage, clickhouse_client).await
+}
+
+/// Records the pages converted by Chunkr for a task once it has succeeded. Chunkr tasks are
+/// only seen when they are polled, so a redis marker makes sure repeated polls count the pages
+/// once.
+pub async fn insert_chunkr_page_usage(
+    task: &FileTaskClickhouse,
+    pages: &[PdfToMdPage],
+    clickhouse_client: &clickhouse::Client,
+    redis_pool: &RedisPool,
+) -> Result<(), ServiceError> {
+    let mut redis_conn = redis_pool.get().await.map_err(|e| {
+        log::error!("Failed to get redis connection: {:?}", e);
+        ServiceError::InternalServerError("Failed to get redis connection".to_string())
+    })?;
+
+    let usage_key = format!("{}:usage_recorded", task.id);
+    let first_completion = redis::cmd("SET")
+        .arg(&usage_key)
+        .arg(1)
+        .arg("NX")
+        .arg("EX")
+        .arg(USAGE_RECORDED_TTL_SECS)
+        .query_async::<Option<String>>(&mut *redis_conn)
+        .await
+        .map_err(|err| {
+            log::error!("Failed to mark chunkr usage as recorded {:?}", err);
+            ServiceError::InternalServerError("Failed to record chunkr usage".to_string())
+        })?
+        .is_some();
+
+    if !first_completion {
+        return Ok(());
+    }
+
+    let created_at = OffsetDateTime::now_utc();
+    let usage = pages
+        .iter()
+        .map(|page| PageUsageClickhouse {
+            api_key_id: task.api_key_id.clone(),
+            task_id: task.id.clone(),
+            page: page.page_num,
+            extraction_method: ExtractionMethod::Chunkr.to_string(),
+            prompt_tokens: 0,
+            completion_tokens: 0,
+            total_tokens: 0,
+            created_at,
+        })
+        .collect::<Vec<PageUsageClickhouse>>();
+
+    if let Err(err) = insert_page_usage_rows(&usage, clickhouse_client).await {
+        let _ = redis::cmd("DEL")
+            .arg(&usage_key)
+            .query_async::<()>(&mut *redis_conn)
+            .await;
+        return Err(err);
+    }
+
+    Ok(())
+}
+
+async fn insert_page_usage_rows(
+    usage: &[PageUsageClickhouse],
+    clickhouse_client: &clickhouse::Client,
+) -> Result<(), ServiceError> {
+    if usage.is_empty() {
+        return Ok(());
+    }
+
+    let mut usage_inserter = clickhouse_client.insert("page_usage").map_err(|e| {
+        log::error!("Error getting usage_inserter: {:?}", e);
+        ServiceError::InternalServerError(format!("Error inserting page usage: {:?}", e))
+    })?;
+
+    for page_usage in usage {
+        usage_inserter.write(page_usage).await.map_err(|e| {
+            log::error!("Error inserting page usage: {:?}", e);
+            ServiceError::InternalServerError(format!("Error inserting page usage: {:?}", e))
+        })?;
+    }
+
+    usage_inserter.end().await.map_err(|e| {
+        log::error!("Error terminating connection: {:?}", e);
+        ServiceError::InternalServerError(format!("Error inserting page usage: {:?}", e))
+    })?;
+
+    Ok(())
+}
+
+/// Sums the pages processed and LLM tokens spent per key, optionally for a single key and
+/// within a time range
+pub async fn get_api_key_usage(
+    api_key_id: Option<String>,
+    start_date: Option<String>,
+    end_date: Option<String>,
+    clickhouse_client: &clickhouse::Client,
+) -> Result<Vec<ApiKeyUsage>, ServiceError> {
+    let (filters, binds) = api_key_usage_filters(api_key_id, start_date, end_date);
+
+    let mut query = clickhouse_client.query(&format!(
+        "SELECT
+            api_key_id,
+            count() AS pages_processed,
+            countIf(extraction_method = 'text_layer') AS text_layer_pages,
+            countIf(extraction_method = 'llm') AS llm_pages,
+            sum(prompt_tokens) AS prompt_tokens,
+            sum(completion_tokens) AS completion_tokens,
+            sum(total_tokens) AS total_tokens
+        FROM page_usage
+        WHERE 1 = 1{filters}
+        GROUP BY api_key_id
+        ORDER BY api_key_id",
+        filters = filters
+    ));
+
+    for value in binds {
+        query = query.bind(value);
+    }
+
+    query.fetch_all().await.map_err(|err| {
+        log::error!("Failed to get api key usage {:?}", err);
+        ServiceError::BadRequest("Failed to get api key usage".to_string())
+    })
+}
+
+/// Builds the `WHERE` conditions of the usage query along with the values to bind, in order
+fn api_key_usage_filters(
+    api_key_id: Option<String>,
+    start_date: Option<String>,
+    end_date: Option<String>,
+) -> (String, Vec<String>) {
+    let mut filters = String::new();
+    if api_key_id.is_some() {
+        filters.push_str(" AND api_key_id = ?");
+    }
+    if start_date.is_some() {
+        filters.push_str(" AND created_at >= parseDateTimeBestEffort(?)");
+    }
+    if end_date.is_some() {
+        filters.push_str(" AND created_at < parseDateTimeBestEffort(?)");
+    }
+
+    let binds = [api_key_id, start_date, end_date]
+        .into_iter()
+        .flatten()
+        .collect();
+
+    (filters, binds)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hash_api_key_is_hex_sha256() {
+        assert_eq!(
+            hash_api_key("abc"),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        assert_ne!(hash_api_key("pdf2md-a"), hash_api_key("pdf2md-b"));
+    }
+
+    #[test]
+    fn api_key_usage_filters_bind_in_order() {
+        assert_eq!(
+            api_key_usage_filters(None, None, None),
+            (String::new(), vec![])
+        );
+
+        let (filters, binds) = api_key_usage_filters(
+            Some("key".to_string()),
+            None,
+            Some("2025-07-01".to_string()),
+        );
+        assert_eq!(
+            filters,
+            " AND api_key_id = ? AND created_at < parseDateTimeBestEffort(?)"
+        );
+        assert_eq!(binds, vec!["key".to_string(), "2025-07-01".to_string()]);
+
+        let (filters, binds) = api_key_usage_filters(
+            None,
+            Some("2025-06-01".to_string()),
+            Some("2025-07-01".to_string()),
+        );
+        assert_eq!(
+            filters,
+            " AND created_at >= parseDateTimeBestEffort(?) AND created_at < parseDateTimeBestEffort(?)"
+        );
+        assert_eq!(
+            binds,
+            vec!["2025-06-01".to_string(), "2025-07-01".to_string()]
+        );
+    }
+}
@@ -465,6 +465,7 @@ pub async fn create_chunkr_task(
     file_base64: &str,
     api_key: Option<&str>,
     chunkr_create_task_req_payload: Option<CreateFormWithoutFile>,
+    default_ocr_strategy: OcrStrategy,
 ) -> Result<TaskResponse, ServiceError> {
     let client = reqwest::Client::new();
     let (api_url, api_key) = get_chunkr_credentials(api_key)?;
@@ -476,7 +477,7 @@ pub async fn create_chunkr_task(
             chunk_processing: None,
             expires_in: None,
             high_resolution: Some(false),
-            ocr_strategy: Some(OcrStrategy::All),
+            ocr_strategy: Some(default_ocr_strategy.clone()),
             pipeline: Some(PipelineType::Chunkr),
             segment_processing: None,
             segmentation_strategy: Some(SegmentationStrategy::LayoutAnalysis),
@@ -494,7 +495,7 @@ pub async fn create_chunkr_task(
             chunk_processing: payload.chunk_processing,
             expires_in: payload.expires_in,
             high_resolution: payload.high_resolution,
-            ocr_strategy: payload.ocr_strategy,
+            ocr_strategy: payload.ocr_strategy.or(Some(default_ocr_strategy.clone())),
             pipeline: payload.pipeline,
             segment_processing: payload.segment_processing,
             segmentation_strategy: payload.segmentation_strategy,
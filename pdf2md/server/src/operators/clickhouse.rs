@@ -4,6 +4,7 @@ use crate::{
         ChunkClickhouse, ChunkingTask, FileTaskClickhouse, FileTaskStatus, RedisPool,
         WebhookPayloadData,
     },
+    operators::api_keys::insert_page_usage,
 };
 
 pub async fn insert_task(
@@ -67,6 +68,16 @@ pub async fn insert_page(
 
     let prev_task = get_task(task.id, clickhouse_client).await?;
 
+    if let Err(err) = insert_page_usage(
+        &prev_task.api_key_id,
+        std::slice::from_ref(&page),
+        clickhouse_client,
+    )
+    .await
+    {
+        log::error!("Failed to record page usage {:?}", err);
+    }
+
     log::info!(
         "processed {} of {} pages",
         total_pages_processed,
@@ -93,6 +104,101 @@ pub async fn insert_page(
     Ok(WebhookPayloadData::from_tasks(task, page))
 }
 
+/// Inserts the pages taken from a task's text layer and counts them as processed, completing the
+/// task if no pages are left for the LLM. Returns the webhook payload for each page.
+pub async fn insert_text_layer_pages(
+    task_id: uuid::Uuid,
+    pages: Vec<ChunkClickhouse>,
+    total_pages: u32,
+    clickhouse_client: &clickhouse::Client,
+    redis_connection: &mut redis::aio::MultiplexedConnection,
+) -> Result<Vec<WebhookPayloadData>, ServiceError> {
+    if pages.is_empty() {
+        return Ok(vec![]);
+    }
+
+    let mut page_inserter = clickhouse_client.insert("file_chunks").map_err(|e| {
+        log::error!("Error getting page_inserter: {:?}", e);
+        ServiceError::InternalServerError(format!("Error getting page_inserter: {:?}", e))
+    })?;
+
+    for page in pages.iter() {
+        page_inserter.write(page).await.map_err(|e| {
+            log::error!("Error inserting page: {:?}", e);
+            ServiceError::InternalServerError(format!("Error inserting page: {:?}", e))
+        })?;
+    }
+
+    page_inserter.end().await.map_err(|e| {
+        log::error!("Error terminating connection: {:?}", e);
+        ServiceError::InternalServerError(format!("Error inserting task: {:?}", e))
+    })?;
+
+    let total_pages_processed = redis::cmd("incrby")
+        .arg(format!("{}:count", task_id))
+        .arg(pages.len())
+        .query_async::<u32>(redis_connection)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to count text layer pages: {:?}", e);
+            ServiceError::InternalServerError("Failed to count text layer pages".to_string())
+        })?;
+
+    let task = update_task_status(
+        task_id,
+        FileTaskStatus::ChunkingFile(total_pages_processed),
+        clickhouse_client,
+    )
+    .await?;
+    let task = if total_pages_processed >= total_pages {
+        update_task_status(task_id, FileTaskStatus::Completed, clickhouse_client).await?
+    } else {
+        task
+    };
+
+    if let Err(err) = insert_page_usage(&task.api_key_id, &pages, clickhouse_client).await {
+        log::error!("Failed to record text layer page usage {:?}", err);
+    }
+
+    Ok(pages
+        .into_iter()
+        .map(|page| WebhookPayloadData::from_tasks(task.clone(), page))
+        .collect())
+}
+
+/// Records which pages of a task are taken from the text layer and which are sent to the LLM
+pub async fn update_task_extraction_methods(
+    task_id: uuid::Uuid,
+    text_layer_pages: &[u32],
+    ocr_pages: &[u32],
+    clickhouse_client: &clickhouse::Client,
+) -> Result<(), ServiceError> {
+    let format_pages = |pages: &[u32]| {
+        pages
+            .iter()
+            .map(|page| page.to_string())
+            .collect::<Vec<String>>()
+            .join(",")
+    };
+
+    clickhouse_client
+        .query(&format!(
+            "ALTER TABLE file_tasks UPDATE
+                text_layer_pages = [{text_layer_pages}],
+                ocr_pages = [{ocr_pages}]
+            WHERE id = '{task_id}'",
+            text_layer_pages = format_pages(text_layer_pages),
+            ocr_pages = format_pages(ocr_pages),
+            task_id = task_id
+        ))
+        .execute()
+        .await
+        .map_err(|err| {
+            log::error!("Failed to update task extraction methods {:?}", err);
+            ServiceError::BadRequest("Failed to update task extraction methods".to_string())
+        })
+}
+
 pub async fn update_task_status(
     task_id: uuid::Uuid,
     status: FileTaskStatus,
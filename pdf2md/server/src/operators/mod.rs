@@ -1,6 +1,8 @@
+pub mod api_keys;
 pub mod chunkr;
 pub mod clickhouse;
 pub mod pdf_chunk;
 pub mod redis;
 pub mod s3;
+pub mod text_layer;
 pub mod webhook_template;
@@ -3,7 +3,7 @@ use crate::operators::clickhouse::get_last_page;
 use crate::{
     errors::ServiceError,
     get_env,
-    models::{ChunkClickhouse, ChunkingParams, ChunkingTask},
+    models::{ChunkClickhouse, ChunkingParams, ChunkingTask, ExtractionMethod},
     operators::{clickhouse::insert_page, webhook_template::send_webhook},
 };
 use base64::Engine;
@@ -171,6 +171,7 @@ async fn get_markdown_from_image(
         page,
         usage: metadata.to_string(),
         created_at: OffsetDateTime::now_utc(),
+        extraction_method: ExtractionMethod::Llm.to_string(),
     })
 }
 
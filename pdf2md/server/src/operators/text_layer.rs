@@ -0,0 +1,357 @@
+use crate::errors::ServiceError;
+use regex::Regex;
+use std::sync::LazyLock;
+
+/// Pages scoring below this are treated as garbled and sent to the provider instead
+pub const DEFAULT_MIN_TEXT_LAYER_SCORE: f32 = 0.85;
+
+/// Pages with fewer alphanumeric characters than this are treated as having no text layer
+const MIN_TEXT_LAYER_CHARS: usize = 10;
+
+static PAGE_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?s)<page[^>]*>(.*?)</page>").unwrap());
+
+static BLOCK_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?s)<block[^>]*>(.*?)</block>").unwrap());
+
+static LINE_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?s)<line[^>]*>(.*?)</line>").unwrap());
+
+static WORD_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r#"(?s)<word xMin="[-\d.]+" yMin="([-\d.]+)" xMax="[-\d.]+" yMax="([-\d.]+)">(.*?)</word>"#,
+    )
+    .unwrap()
+});
+
+static NUMERIC_ENTITY_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"&#(x?)([0-9a-fA-F]+);").unwrap());
+
+#[derive(Debug, Clone)]
+pub struct TextLayerPage {
+    pub page_num: u32,
+    pub markdown: String,
+    /// How much of the page's text looks like real words, between 0 and 1
+    pub score: f32,
+    pub alphanumeric_chars: usize,
+}
+
+impl TextLayerPage {
+    pub fn is_extractable(&self, min_score: f32) -> bool {
+        self.alphanumeric_chars >= MIN_TEXT_LAYER_CHARS && self.score >= min_score
+    }
+}
+
+struct TextLayerLine {
+    text: String,
+    height: f32,
+}
+
+/// Extracts the embedded text of every page of a PDF with poppler's `pdftotext`, keeping the
+/// blocks it detects as paragraphs and turning lines set noticeably larger than the body text
+/// into headings.
+pub async fn extract_text_layer(
+    task_id: uuid::Uuid,
+    file_data: &[u8],
+) -> Result<Vec<TextLayerPage>, ServiceError> {
+    let file_path = std::env::temp_dir().join(format!("{}-text-layer.pdf", task_id));
+    tokio::fs::write(&file_path, file_data)
+        .await
+        .map_err(|err| {
+            ServiceError::InternalServerError(format!("Failed to write PDF file {:?}", err))
+        })?;
+
+    let output = tokio::process::Command::new("pdftotext")
+        .arg("-bbox-layout")
+        .arg(&file_path)
+        .arg("-")
+        .kill_on_drop(true)
+        .output()
+        .await;
+
+    let _ = tokio::fs::remove_file(&file_path).await;
+
+    let output = output.map_err(|err| {
+        ServiceError::InternalServerError(format!("Failed to run pdftotext {:?}", err))
+    })?;
+
+    if !output.status.success() {
+        return Err(ServiceError::BadRequest(format!(
+            "pdftotext failed to extract text {}",
+            String::from_utf8_lossy(&output.stderr)
+        )));
+    }
+
+    Ok(parse_bbox_layout(&String::from_utf8_lossy(&output.stdout)))
+}
+
+fn parse_bbox_layout(bbox_layout: &str) -> Vec<TextLayerPage> {
+    PAGE_RE
+        .captures_iter(bbox_layout)
+        .enumerate()
+        .map(|(i, page)| {
+            let blocks = BLOCK_RE
+                .captures_iter(&page[1])
+                .map(|block| {
+                    LINE_RE
+                        .captures_iter(&block[1])
+                        .filter_map(|line| {
+                            let words = WORD_RE
+                                .captures_iter(&line[1])
+                                .map(|word| {
+                                    let y_min = word[1].parse::<f32>().unwrap_or_default();
+                                    let y_max = word[2].parse::<f32>().unwrap_or_default();
+                                    (decode_entities(&word[3]), (y_max - y_min).max(0.0))
+                                })
+                                .collect::<Vec<(String, f32)>>();
+
+                            if words.is_empty() {
+                                return None;
+                            }
+
+                            Some(TextLayerLine {
+                                text: words
+                                    .iter()
+                                    .map(|(text, _)| text.as_str())
+                                    .collect::<Vec<&str>>()
+                                    .join(" "),
+                                height: words.iter().map(|(_, height)| height).sum::<f32>()
+                                    / words.len() as f32,
+                            })
+                        })
+                        .collect::<Vec<TextLayerLine>>()
+                })
+                .filter(|lines| !lines.is_empty())
+                .collect::<Vec<Vec<TextLayerLine>>>();
+
+            let text = blocks
+                .iter()
+                .flatten()
+                .map(|line| line.text.as_str())
+                .collect::<Vec<&str>>()
+                .join("\n");
+
+            TextLayerPage {
+                page_num: (i + 1) as u32,
+                markdown: blocks_to_markdown(&blocks),
+                score: extractability_score(&text),
+                alphanumeric_chars: text.chars().filter(|c| c.is_alphanumeric()).count(),
+            }
+        })
+        .collect()
+}
+
+fn blocks_to_markdown(blocks: &[Vec<TextLayerLine>]) -> String {
+    let mut line_heights = blocks
+        .iter()
+        .flatten()
+        .map(|line| line.height)
+        .collect::<Vec<f32>>();
+    line_heights.sort_by(|a, b| a.total_cmp(b));
+    let body_height = line_heights
+        .get(line_heights.len() / 2)
+        .copied()
+        .unwrap_or_default();
+
+    blocks
+        .iter()
+        .map(|lines| {
+            let block_text_len = lines.iter().map(|line| line.text.len()).sum::<usize>();
+            let min_height = lines
+                .iter()
+                .map(|line| line.height)
+                .fold(f32::MAX, f32::min);
+
+            if body_height > 0.0 && lines.len() <= 2 && block_text_len < 200 {
+                let ratio = min_height / body_height;
+                let heading_level = if ratio >= 1.6 {
+                    Some("#")
+                } else if ratio >= 1.3 {
+                    Some("##")
+                } else if ratio >= 1.15 {
+                    Some("###")
+                } else {
+                    None
+                };
+
+                if let Some(heading_level) = heading_level {
+                    return format!(
+                        "{} {}",
+                        heading_level,
+                        lines
+                            .iter()
+                            .map(|line| line.text.as_str())
+                            .collect::<Vec<&str>>()
+                            .join(" ")
+                    );
+                }
+            }
+
+            join_block_lines(lines)
+        })
+        .collect::<Vec<String>>()
+        .join("\n\n")
+}
+
+/// Joins the lines of a paragraph, undoing hyphenation at line ends and keeping list items on
+/// their own lines
+fn join_block_lines(lines: &[TextLayerLine]) -> String {
+    let mut paragraph = String::new();
+
+    for line in lines {
+        let text = line.text.trim();
+        let list_item = text
+            .strip_prefix(['•', '▪', '◦', '●', '–'])
+            .map(|item| format!("- {}", item.trim_start()));
+
+        if paragraph.is_empty() {
+            paragraph.push_str(list_item.as_deref().unwrap_or(text));
+        } else if let Some(list_item) = list_item {
+            paragraph.push('\n');
+            paragraph.push_str(&list_item);
+        } else if paragraph.ends_with('-') && text.chars().next().is_some_and(|c| c.is_lowercase())
+        {
+            paragraph.pop();
+            paragraph.push_str(text);
+        } else {
+            paragraph.push(' ');
+            paragraph.push_str(text);
+        }
+    }
+
+    paragraph
+}
+
+fn decode_entities(text: &str) -> String {
+    if !text.contains('&') {
+        return text.to_string();
+    }
+
+    let text = NUMERIC_ENTITY_RE.replace_all(text, |captures: &regex::Captures| {
+        let radix = if captures[1].is_empty() { 10 } else { 16 };
+        u32::from_str_radix(&captures[2], radix)
+            .ok()
+            .and_then(char::from_u32)
+            .map(|c| c.to_string())
+            .unwrap_or_default()
+    });
+
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+/// Scores how readable the extracted text of a page is. Text extracted from fonts without a
+/// proper unicode mapping comes out as replacement, private use or otherwise unusual characters
+/// and as words made mostly of symbols, both of which lower the score.
+pub fn extractability_score(text: &str) -> f32 {
+    let chars = text
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .collect::<Vec<char>>();
+    if chars.is_empty() {
+        return 0.0;
+    }
+
+    let readable_chars = chars
+        .iter()
+        .filter(|c| {
+            c.is_alphanumeric() && !('\u{E000}'..='\u{F8FF}').contains(*c)
+                || ".,;:!?'\"()[]{}-–—/\\%&$€£¥@#*+=<>|_~`^°§•·…“”‘’".contains(**c)
+        })
+        .count();
+
+    let words = text.split_whitespace().collect::<Vec<&str>>();
+    let readable_words = words
+        .iter()
+        .filter(|word| {
+            let alphanumeric = word.chars().filter(|c| c.is_alphanumeric()).count();
+            alphanumeric * 2 >= word.chars().count() && word.chars().count() <= 40
+        })
+        .count();
+
+    (readable_chars as f32 / chars.len() as f32) * (readable_words as f32 / words.len() as f32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn line(text: &str) -> TextLayerLine {
+        TextLayerLine {
+            text: text.to_string(),
+            height: 10.0,
+        }
+    }
+
+    #[test]
+    fn extractability_score_separates_readable_and_garbled_text() {
+        assert_eq!(extractability_score(""), 0.0);
+        assert_eq!(extractability_score("The quick brown fox."), 1.0);
+        assert!(extractability_score("\u{E001}\u{E002}\u{E003} \u{FFFD}\u{FFFD}") < 0.1);
+        assert!(
+            extractability_score("Quarterly results ¤¤¤¤¤ ÿþ¤¤") < DEFAULT_MIN_TEXT_LAYER_SCORE
+        );
+    }
+
+    #[test]
+    fn parse_bbox_layout_builds_pages_with_headings() {
+        let bbox_layout = r#"<doc>
+<page width="612" height="792">
+<flow><block xMin="0" yMin="0" xMax="100" yMax="20">
+<line xMin="0" yMin="0" xMax="100" yMax="20">
+<word xMin="0" yMin="0" xMax="50" yMax="20">Annual</word>
+<word xMin="50" yMin="0" xMax="100" yMax="20">Report</word>
+</line>
+</block>
+<block xMin="0" yMin="30" xMax="100" yMax="60">
+<line xMin="0" yMin="30" xMax="100" yMax="40">
+<word xMin="0" yMin="30" xMax="50" yMax="40">Revenue</word>
+<word xMin="50" yMin="30" xMax="100" yMax="40">grew&#44;</word>
+</line>
+<line xMin="0" yMin="40" xMax="100" yMax="50">
+<word xMin="0" yMin="40" xMax="50" yMax="50">costs</word>
+<word xMin="50" yMin="40" xMax="100" yMax="50">fell &quot;margins&quot;</word>
+</line>
+<line xMin="0" yMin="50" xMax="100" yMax="60">
+<word xMin="0" yMin="50" xMax="50" yMax="60">improved.</word>
+</line>
+</block></flow>
+</page>
+<page width="612" height="792">
+</page>
+</doc>"#;
+
+        let pages = parse_bbox_layout(bbox_layout);
+        assert_eq!(pages.len(), 2);
+
+        assert_eq!(pages[0].page_num, 1);
+        assert_eq!(
+            pages[0].markdown,
+            "# Annual Report\n\nRevenue grew, costs fell \"margins\" improved."
+        );
+        assert!(pages[0].is_extractable(DEFAULT_MIN_TEXT_LAYER_SCORE));
+
+        assert_eq!(pages[1].page_num, 2);
+        assert!(pages[1].markdown.is_empty());
+        assert!(!pages[1].is_extractable(DEFAULT_MIN_TEXT_LAYER_SCORE));
+    }
+
+    #[test]
+    fn join_block_lines_undoes_hyphenation_and_keeps_list_items() {
+        assert_eq!(
+            join_block_lines(&[line("The extrac-"), line("tion works"), line("well.")]),
+            "The extraction works well."
+        );
+        assert_eq!(
+            join_block_lines(&[line("Steps:"), line("• First"), line("• Second")]),
+            "Steps:\n- First\n- Second"
+        );
+        assert_eq!(
+            join_block_lines(&[line("Well-"), line("Known")]),
+            "Well- Known"
+        );
+    }
+}
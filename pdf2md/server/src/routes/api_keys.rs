@@ -0,0 +1,168 @@
+use crate::{
+    errors::{ErrorResponseBody, ServiceError},
+    middleware::api_key_middleware::{AdminApiKey, ApiKey},
+    models::{
+        self, ApiKeyDTO, CreateApiKeyResponse, GetApiKeysResponse, GetUsageRequest,
+        GetUsageResponse, RedisPool,
+    },
+};
+use actix_web::{delete, get, post, web, HttpResponse};
+
+/// Create an API Key
+///
+/// This endpoint creates a new API key which can be used to create and retrieve tasks. The key is only returned in this response. Requires the admin key.
+#[utoipa::path(
+    post,
+    path = "/key",
+    tag = "API Key",
+    context_path = "/api",
+    request_body(content = models::CreateApiKeyReqPayload, description = "JSON request payload to create a new API key", content_type = "application/json"),
+    responses(
+        (status = 200, description = "JSON response payload containing the created API key", body = models::CreateApiKeyResponse),
+        (status = 400, description = "Error typically due to deserialization issues", body = ErrorResponseBody),
+        (status = 403, description = "The request was not made with the admin key", body = ErrorResponseBody),
+    ),
+    security(
+        ("api_key" = [])
+    )
+)]
+#[post("")]
+async fn create_api_key(
+    req: web::Json<models::CreateApiKeyReqPayload>,
+    clickhouse_client: web::Data<clickhouse::Client>,
+    _admin_api_key: AdminApiKey,
+) -> Result<HttpResponse, ServiceError> {
+    let name = req.into_inner().name;
+    if name.trim().is_empty() {
+        return Err(ServiceError::BadRequest(
+            "API key name must not be empty".to_string(),
+        ));
+    }
+
+    let (api_key, plaintext_key) =
+        crate::operators::api_keys::create_api_key(name, &clickhouse_client).await?;
+
+    Ok(HttpResponse::Ok().json(CreateApiKeyResponse {
+        api_key: plaintext_key,
+        key: ApiKeyDTO::from(api_key),
+    }))
+}
+
+/// List API Keys
+///
+/// This endpoint lists every API key, including revoked ones. Requires the admin key.
+#[utoipa::path(
+    get,
+    path = "/key",
+    tag = "API Key",
+    context_path = "/api",
+    responses(
+        (status = 200, description = "JSON response payload containing the API keys", body = models::GetApiKeysResponse),
+        (status = 403, description = "The request was not made with the admin key", body = ErrorResponseBody),
+    ),
+    security(
+        ("api_key" = [])
+    )
+)]
+#[get("")]
+async fn get_api_keys(
+    clickhouse_client: web::Data<clickhouse::Client>,
+    _admin_api_key: AdminApiKey,
+) -> Result<HttpResponse, ServiceError> {
+    let api_keys = crate::operators::api_keys::get_api_keys(&clickhouse_client).await?;
+
+    Ok(HttpResponse::Ok().json(GetApiKeysResponse {
+        api_keys: api_keys.into_iter().map(ApiKeyDTO::from).collect(),
+    }))
+}
+
+/// Revoke an API Key
+///
+/// This endpoint revokes an API key so it can no longer be used. Tasks and usage recorded for the key are kept. Requires the admin key.
+#[utoipa::path(
+    delete,
+    path = "/key/{api_key_id}",
+    tag = "API Key",
+    context_path = "/api",
+    params(
+        ("api_key_id" = uuid::Uuid, Path, description = "The id of the API key you want to revoke."),
+    ),
+    responses(
+        (status = 204, description = "Confirmation that the API key was revoked"),
+        (status = 403, description = "The request was not made with the admin key", body = ErrorResponseBody),
+        (status = 404, description = "API key not found", body = ErrorResponseBody),
+    ),
+    security(
+        ("api_key" = [])
+    )
+)]
+#[delete("/{api_key_id}")]
+async fn revoke_api_key(
+    api_key_id: web::Path<uuid::Uuid>,
+    clickhouse_client: web::Data<clickhouse::Client>,
+    redis_pool: web::Data<RedisPool>,
+    _admin_api_key: AdminApiKey,
+) -> Result<HttpResponse, ServiceError> {
+    crate::operators::api_keys::revoke_api_key(
+        api_key_id.into_inner(),
+        &clickhouse_client,
+        &redis_pool,
+    )
+    .await?;
+
+    Ok(HttpResponse::NoContent().finish())
+}
+
+/// Get Usage
+///
+/// This endpoint returns the pages processed and LLM tokens used per API key. Requests made with an API key only see the usage of that key, the admin key sees every key unless `api_key_id` is set.
+#[utoipa::path(
+    get,
+    path = "/usage",
+    tag = "API Key",
+    context_path = "/api",
+    params(
+        ("api_key_id" = Option<String>, Query, description = "The id of the API key to get usage for."),
+        ("start_date" = Option<String>, Query, description = "Only count pages processed at or after this time."),
+        ("end_date" = Option<String>, Query, description = "Only count pages processed before this time."),
+    ),
+    responses(
+        (status = 200, description = "JSON response payload containing the usage per API key", body = models::GetUsageResponse),
+        (status = 400, description = "Error typically due to deserialization issues", body = ErrorResponseBody),
+        (status = 403, description = "The usage of a different API key was requested", body = ErrorResponseBody),
+    ),
+    security(
+        ("api_key" = [])
+    )
+)]
+#[get("")]
+async fn get_usage(
+    data: web::Query<GetUsageRequest>,
+    clickhouse_client: web::Data<clickhouse::Client>,
+    api_key: ApiKey,
+) -> Result<HttpResponse, ServiceError> {
+    let data = data.into_inner();
+
+    let api_key_id = if api_key.is_admin() {
+        data.api_key_id
+    } else {
+        if data
+            .api_key_id
+            .as_ref()
+            .is_some_and(|api_key_id| !api_key.can_access(api_key_id))
+        {
+            return Err(ServiceError::Forbidden);
+        }
+        Some(api_key.owner_id())
+    };
+
+    let usage = crate::operators::api_keys::get_api_key_usage(
+        api_key_id,
+        data.start_date,
+        data.end_date,
+        &clickhouse_client,
+    )
+    .await?;
+
+    Ok(HttpResponse::Ok().json(GetUsageResponse { usage }))
+}
@@ -2,6 +2,7 @@ use crate::{
     errors::{ErrorResponseBody, ServiceError},
     middleware::api_key_middleware::ApiKey,
     models::{self, CreateFileTaskResponse, FileTask, FileTaskStatus, Provider, RedisPool},
+    operators::chunkr::OcrStrategy,
 };
 use actix_web::{post, web, HttpResponse};
 use s3::creds::time::OffsetDateTime;
@@ -28,7 +29,7 @@ async fn create_task(
     req: web::Json<models::UploadFileReqPayload>,
     redis_pool: web::Data<RedisPool>,
     clickhouse_client: web::Data<clickhouse::Client>,
-    _api_key: ApiKey,
+    api_key: ApiKey,
 ) -> Result<HttpResponse, actix_web::Error> {
     let upload_file_data = req.into_inner();
     let provider = upload_file_data.provider.clone().unwrap_or(Provider::LLM);
@@ -43,6 +44,9 @@ async fn create_task(
         created_at: OffsetDateTime::now_utc(),
         chunkr_task_id: "".to_string(),
         chunkr_api_key: upload_file_data.chunkr_api_key.clone(),
+        text_layer_pages: vec![],
+        ocr_pages: vec![],
+        api_key_id: api_key.owner_id(),
     };
 
     let task: FileTask = FileTask {
@@ -87,6 +91,11 @@ async fn create_task(
                 &task.upload_file_data.base64_file,
                 task.upload_file_data.chunkr_api_key.as_deref(),
                 task.upload_file_data.chunkr_create_task_req_payload.clone(),
+                if task.upload_file_data.use_text_layer.unwrap_or(true) {
+                    OcrStrategy::Auto
+                } else {
+                    OcrStrategy::All
+                },
             )
             .await
             .map_err(|err| ServiceError::BadRequest(err.to_string()))?;
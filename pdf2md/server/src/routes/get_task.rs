@@ -1,14 +1,18 @@
 use crate::{
     errors::{ErrorResponseBody, ServiceError},
     middleware::api_key_middleware::ApiKey,
-    models::{self, GetTaskRequest, Provider},
-    operators::s3::{get_aws_bucket, get_signed_url},
+    models::{self, GetTaskRequest, PdfToMdPage, Provider, RedisPool},
+    operators::{
+        api_keys::insert_chunkr_page_usage,
+        chunkr::Status,
+        s3::{get_aws_bucket, get_signed_url},
+    },
 };
 use actix_web::{get, web, HttpResponse};
 
 /// Retieve a File Task by ID
 ///
-/// This endpoint retrieves a task by its id. The task is returned along with the pages that have been created, if the file chunking has been completed.
+/// This endpoint retrieves a task by its id. Only tasks created with the API key used for the request can be retrieved, except when using the admin key. The task is returned along with the pages that have been created, if the file chunking has been completed.
 #[utoipa::path(
     get,
     path = "/task/{task_id}",
@@ -22,6 +26,7 @@ use actix_web::{get, web, HttpResponse};
     responses(
         (status = 200, description = "JSON response payload containing the created pages", body = models::GetTaskResponse),
         (status = 400, description = "Error typically due to deserialization issues", body = ErrorResponseBody),
+        (status = 404, description = "Task not found or created by a different API key", body = ErrorResponseBody),
     ),
     security(
         ("api_key" = [])
@@ -32,10 +37,14 @@ async fn get_task(
     task_id: web::Path<uuid::Uuid>,
     data: web::Query<GetTaskRequest>,
     clickhouse_client: web::Data<clickhouse::Client>,
-    _api_key: ApiKey,
+    redis_pool: web::Data<RedisPool>,
+    api_key: ApiKey,
 ) -> Result<HttpResponse, ServiceError> {
     let task_id = task_id.into_inner();
     let task = crate::operators::clickhouse::get_task(task_id, &clickhouse_client).await?;
+    if !api_key.can_access(&task.api_key_id) {
+        return Err(ServiceError::NotFound("Task not found".to_string()));
+    }
     let provider = task
         .provider
         .parse::<Provider>()
@@ -50,6 +59,14 @@ async fn get_task(
             .map_err(|err| {
                 ServiceError::BadRequest(format!("Error getting task from Chunkr: {}", err))
             })?;
+            if matches!(chunkr_task.status, Status::Succeeded) {
+                let pages = Vec::<PdfToMdPage>::from(chunkr_task.clone());
+                if let Err(err) =
+                    insert_chunkr_page_usage(&task, &pages, &clickhouse_client, &redis_pool).await
+                {
+                    log::error!("Failed to record chunkr page usage {:?}", err);
+                }
+            }
             Ok(
                 HttpResponse::Ok().json(models::GetTaskResponse::new_with_chunkr(
                     task.clone(),
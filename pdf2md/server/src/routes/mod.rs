@@ -1,3 +1,4 @@
+pub mod api_keys;
 pub mod create_task;
 pub mod get_task;
 pub mod jinja_templates;
@@ -4,10 +4,17 @@ use pdf2image::PDF;
 use pdf2md_server::{
     errors::ServiceError,
     get_env,
-    models::{self, FileTask, FileTaskStatus},
-    operators::{clickhouse::update_task_status, redis::listen_to_redis, s3::get_aws_bucket},
+    models::{self, ChunkClickhouse, ExtractionMethod, FileTask, FileTaskStatus},
+    operators::{
+        clickhouse::{insert_text_layer_pages, update_task_extraction_methods, update_task_status},
+        redis::listen_to_redis,
+        s3::get_aws_bucket,
+        text_layer::{extract_text_layer, TextLayerPage, DEFAULT_MIN_TEXT_LAYER_SCORE},
+        webhook_template::send_webhook,
+    },
     process_task_with_retry,
 };
+use s3::creds::time::OffsetDateTime;
 use signal_hook::consts::SIGTERM;
 use std::{
     io::Cursor,
@@ -122,17 +129,42 @@ pub async fn chunk_pdf(
             ServiceError::BadRequest("Could not upload file to S3".to_string())
         })?;
 
+    let text_layer = if task.upload_file_data.use_text_layer.unwrap_or(true) {
+        match extract_text_layer(task.id, &decoded_file_data).await {
+            Ok(text_layer) => text_layer,
+            Err(err) => {
+                log::warn!(
+                    "Could not extract text layer, converting every page with the LLM {:?}",
+                    err
+                );
+                vec![]
+            }
+        }
+    } else {
+        vec![]
+    };
+
     let pdf = PDF::from_bytes(decoded_file_data)
         .map_err(|err| ServiceError::BadRequest(format!("Failed to open PDF file {:?}", err)))?;
 
-    let pages = pdf
-        .render(pdf2image::Pages::All, None)
-        .map_err(|err| ServiceError::BadRequest(format!("Failed to render PDF file {:?}", err)))?
-        .into_iter()
-        .skip(1);
-
     let num_pages = pdf.page_count();
 
+    let min_score = task
+        .upload_file_data
+        .text_layer_min_score
+        .unwrap_or(DEFAULT_MIN_TEXT_LAYER_SCORE);
+    let text_layer_pages = text_layer
+        .into_iter()
+        .filter(|page| page.page_num <= num_pages && page.is_extractable(min_score))
+        .collect::<Vec<TextLayerPage>>();
+    let text_layer_page_nums = text_layer_pages
+        .iter()
+        .map(|page| page.page_num)
+        .collect::<Vec<u32>>();
+    let ocr_pages = (1..=num_pages)
+        .filter(|page_num| !text_layer_page_nums.contains(page_num))
+        .collect::<Vec<u32>>();
+
     update_task_status(
         task.id,
         FileTaskStatus::ProcessingFile(num_pages),
@@ -140,9 +172,64 @@ pub async fn chunk_pdf(
     )
     .await?;
 
-    // Process each chunk
-    for (i, page) in pages.enumerate() {
-        let file_name = format!("{}page{}.jpeg", task.id, i + 1);
+    update_task_extraction_methods(
+        task.id,
+        &text_layer_page_nums,
+        &ocr_pages,
+        &clickhouse_client,
+    )
+    .await?;
+
+    log::info!(
+        "Using the text layer for {} of {} pages",
+        text_layer_page_nums.len(),
+        num_pages
+    );
+
+    let webhook_payloads = insert_text_layer_pages(
+        task.id,
+        text_layer_pages
+            .into_iter()
+            .map(|page| ChunkClickhouse {
+                id: uuid::Uuid::new_v4().to_string(),
+                task_id: task.id.to_string(),
+                content: page.markdown,
+                page: page.page_num,
+                usage: serde_json::json!({ "text_layer_score": page.score }).to_string(),
+                created_at: OffsetDateTime::now_utc(),
+                extraction_method: ExtractionMethod::TextLayer.to_string(),
+            })
+            .collect(),
+        num_pages,
+        &clickhouse_client,
+        &mut redis_connection,
+    )
+    .await?;
+
+    for data in webhook_payloads {
+        send_webhook(
+            task.upload_file_data.webhook_url.clone(),
+            task.upload_file_data.webhook_payload_template.clone(),
+            data,
+        )
+        .await?;
+    }
+
+    // Only pages without a usable text layer are rendered and sent to the LLM
+    for page_num in ocr_pages.iter().copied() {
+        let page = pdf
+            .render(pdf2image::Pages::Single(page_num), None)
+            .map_err(|err| {
+                ServiceError::BadRequest(format!("Failed to render PDF file {:?}", err))
+            })?
+            .into_iter()
+            .next()
+            .ok_or(ServiceError::BadRequest(format!(
+                "Failed to render page {} of PDF file",
+                page_num
+            )))?;
+
+        let file_name = format!("{}page{}.jpeg", task.id, page_num);
         let mut buffer = Vec::new();
         page.write_to(&mut Cursor::new(&mut buffer), image::ImageFormat::Jpeg)
             .map_err(|err| {
@@ -159,7 +246,7 @@ pub async fn chunk_pdf(
         let chunking_task = serde_json::to_string(&models::ChunkingTask {
             id: task.id,
             file_name,
-            page_num: (i + 1) as u32,
+            page_num,
             params: task.upload_file_data.clone().into(),
             attempt_number: 0,
         })
@@ -172,7 +259,7 @@ pub async fn chunk_pdf(
             .await
             .map_err(|err| ServiceError::BadRequest(err.to_string()))?;
 
-        log::info!("Uploaded page {} of {} to S3", i + 1, num_pages);
+        log::info!("Uploaded page {} of {} to S3", page_num, num_pages);
     }
 
     Ok(())
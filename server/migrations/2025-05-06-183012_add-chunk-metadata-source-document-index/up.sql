@@ -0,0 +1,2 @@
+-- Your SQL goes here
+CREATE INDEX IF NOT EXISTS idx_chunk_metadata_source_document ON chunk_metadata (dataset_id, (metadata->>'source_document'), (metadata->'source_position'));
@@ -0,0 +1,14 @@
+-- Your SQL goes here
+CREATE EXTENSION IF NOT EXISTS btree_gin;
+
+CREATE TABLE IF NOT EXISTS chunk_fingerprints (
+    chunk_id UUID PRIMARY KEY REFERENCES chunk_metadata(id) ON DELETE CASCADE,
+    dataset_id UUID NOT NULL REFERENCES datasets(id) ON DELETE CASCADE,
+    content_hash TEXT NOT NULL,
+    simhash BIGINT NOT NULL,
+    simhash_bands INTEGER[] NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_chunk_fingerprints_dataset_id_content_hash ON chunk_fingerprints (dataset_id, content_hash);
+CREATE INDEX IF NOT EXISTS idx_chunk_fingerprints_dataset_id_simhash_bands ON chunk_fingerprints USING GIN (dataset_id, simhash_bands);
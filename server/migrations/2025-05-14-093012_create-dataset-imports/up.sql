@@ -0,0 +1,18 @@
+-- Your SQL goes here
+CREATE TABLE IF NOT EXISTS dataset_imports (
+    id UUID PRIMARY KEY,
+    dataset_id UUID NOT NULL REFERENCES datasets(id) ON DELETE CASCADE,
+    status TEXT NOT NULL DEFAULT 'pending',
+    format TEXT NOT NULL,
+    export_id UUID,
+    source_url TEXT,
+    vectors_reused BOOLEAN,
+    groups_imported BIGINT NOT NULL DEFAULT 0,
+    files_imported BIGINT NOT NULL DEFAULT 0,
+    chunks_imported BIGINT NOT NULL DEFAULT 0,
+    error TEXT,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_dataset_imports_dataset_id ON dataset_imports (dataset_id);
@@ -0,0 +1,17 @@
+-- Your SQL goes here
+CREATE TABLE IF NOT EXISTS dataset_reindexes (
+    id UUID PRIMARY KEY,
+    dataset_id UUID NOT NULL REFERENCES datasets(id) ON DELETE CASCADE,
+    status TEXT NOT NULL DEFAULT 'pending',
+    mode JSONB NOT NULL,
+    from_collection TEXT NOT NULL,
+    to_collection TEXT NOT NULL,
+    points_total BIGINT,
+    points_migrated BIGINT NOT NULL DEFAULT 0,
+    points_failed BIGINT NOT NULL DEFAULT 0,
+    error TEXT,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_dataset_reindexes_dataset_id ON dataset_reindexes (dataset_id);
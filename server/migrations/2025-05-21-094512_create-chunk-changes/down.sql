@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+DROP TABLE IF EXISTS change_webhook_deliveries;
+DROP TABLE IF EXISTS change_webhooks;
+DROP TABLE IF EXISTS chunk_changes;
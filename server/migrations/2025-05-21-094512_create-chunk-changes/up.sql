@@ -0,0 +1,43 @@
+-- Your SQL goes here
+CREATE TABLE IF NOT EXISTS chunk_changes (
+    seq BIGSERIAL PRIMARY KEY,
+    dataset_id UUID NOT NULL REFERENCES datasets(id) ON DELETE CASCADE,
+    operation TEXT NOT NULL,
+    chunk_id UUID,
+    tracking_id TEXT,
+    changed_fields TEXT[] NOT NULL DEFAULT '{}',
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_chunk_changes_dataset_id_seq ON chunk_changes (dataset_id, seq);
+
+CREATE TABLE IF NOT EXISTS change_webhooks (
+    id UUID PRIMARY KEY,
+    dataset_id UUID NOT NULL REFERENCES datasets(id) ON DELETE CASCADE,
+    url TEXT NOT NULL,
+    secret TEXT NOT NULL,
+    operations TEXT[],
+    active BOOLEAN NOT NULL DEFAULT TRUE,
+    last_delivered_seq BIGINT NOT NULL DEFAULT 0,
+    consecutive_failures INTEGER NOT NULL DEFAULT 0,
+    next_attempt_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_change_webhooks_dataset_id ON change_webhooks (dataset_id);
+CREATE INDEX IF NOT EXISTS idx_change_webhooks_next_attempt_at ON change_webhooks (next_attempt_at) WHERE active;
+
+CREATE TABLE IF NOT EXISTS change_webhook_deliveries (
+    id UUID PRIMARY KEY,
+    webhook_id UUID NOT NULL REFERENCES change_webhooks(id) ON DELETE CASCADE,
+    first_seq BIGINT NOT NULL,
+    last_seq BIGINT NOT NULL,
+    attempt INTEGER NOT NULL,
+    success BOOLEAN NOT NULL,
+    status_code INTEGER,
+    error TEXT,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_change_webhook_deliveries_webhook_id_created_at ON change_webhook_deliveries (webhook_id, created_at);
@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+DROP TABLE IF EXISTS event_webhook_deliveries;
+DROP TABLE IF EXISTS event_webhooks;
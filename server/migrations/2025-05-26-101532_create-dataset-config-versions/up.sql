@@ -0,0 +1,13 @@
+-- Your SQL goes here
+CREATE TABLE IF NOT EXISTS dataset_config_versions (
+    id UUID PRIMARY KEY,
+    dataset_id UUID NOT NULL REFERENCES datasets(id) ON DELETE CASCADE,
+    version INTEGER NOT NULL,
+    server_configuration JSONB NOT NULL,
+    diff JSONB NOT NULL,
+    source TEXT NOT NULL,
+    changed_by UUID,
+    restored_version INTEGER,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE (dataset_id, version)
+);
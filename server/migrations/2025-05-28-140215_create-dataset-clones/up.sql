@@ -0,0 +1,18 @@
+-- Your SQL goes here
+CREATE TABLE IF NOT EXISTS dataset_clones (
+    id UUID PRIMARY KEY,
+    dataset_id UUID NOT NULL REFERENCES datasets(id) ON DELETE CASCADE,
+    source_dataset_id UUID NOT NULL,
+    status TEXT NOT NULL DEFAULT 'pending',
+    filter JSONB,
+    group_ids UUID[],
+    vectors_reused BOOLEAN,
+    groups_cloned BIGINT NOT NULL DEFAULT 0,
+    files_cloned BIGINT NOT NULL DEFAULT 0,
+    chunks_cloned BIGINT NOT NULL DEFAULT 0,
+    error TEXT,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_dataset_clones_dataset_id ON dataset_clones (dataset_id);
@@ -0,0 +1,8 @@
+-- This file should undo anything in `up.sql`
+DROP TRIGGER IF EXISTS chunk_group_parent_cycle_check ON chunk_group;
+
+DROP FUNCTION IF EXISTS check_chunk_group_parent_cycle();
+
+DROP INDEX IF EXISTS idx_chunk_group_parent_id;
+
+ALTER TABLE chunk_group DROP COLUMN IF EXISTS parent_id;
@@ -0,0 +1,33 @@
+-- Your SQL goes here
+ALTER TABLE chunk_group ADD COLUMN parent_id UUID REFERENCES chunk_group(id) ON DELETE SET NULL;
+
+CREATE INDEX IF NOT EXISTS idx_chunk_group_parent_id ON chunk_group(parent_id);
+
+CREATE OR REPLACE FUNCTION check_chunk_group_parent_cycle()
+RETURNS TRIGGER AS $$
+BEGIN
+    IF NEW.parent_id IS NULL THEN
+        RETURN NEW;
+    END IF;
+
+    IF NEW.parent_id = NEW.id OR EXISTS (
+        WITH RECURSIVE ancestors AS (
+            SELECT id, parent_id FROM chunk_group WHERE id = NEW.parent_id
+            UNION
+            SELECT chunk_group.id, chunk_group.parent_id
+            FROM chunk_group
+            JOIN ancestors ON chunk_group.id = ancestors.parent_id
+        )
+        SELECT 1 FROM ancestors WHERE ancestors.id = NEW.id
+    ) THEN
+        RAISE EXCEPTION 'Setting parent % on chunk_group % would create a cycle', NEW.parent_id, NEW.id;
+    END IF;
+
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER chunk_group_parent_cycle_check
+BEFORE INSERT OR UPDATE OF parent_id ON chunk_group
+FOR EACH ROW
+EXECUTE FUNCTION check_chunk_group_parent_cycle();
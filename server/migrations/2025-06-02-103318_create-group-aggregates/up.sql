@@ -0,0 +1,22 @@
+-- Your SQL goes here
+CREATE TABLE IF NOT EXISTS group_aggregates (
+    id UUID PRIMARY KEY,
+    dataset_id UUID NOT NULL REFERENCES datasets(id) ON DELETE CASCADE,
+    name TEXT NOT NULL,
+    field TEXT NOT NULL,
+    aggregation TEXT NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE (dataset_id, name)
+);
+
+CREATE TABLE IF NOT EXISTS group_aggregate_values (
+    group_id UUID NOT NULL REFERENCES chunk_group(id) ON DELETE CASCADE,
+    aggregate_id UUID NOT NULL REFERENCES group_aggregates(id) ON DELETE CASCADE,
+    dataset_id UUID NOT NULL,
+    value DOUBLE PRECISION,
+    updated_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (group_id, aggregate_id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_group_aggregate_values_aggregate_id_value ON group_aggregate_values (aggregate_id, value);
@@ -0,0 +1,7 @@
+-- This file should undo anything in `up.sql`
+DROP TABLE IF EXISTS file_versions;
+
+DROP INDEX IF EXISTS idx_files_dataset_id_tracking_id;
+
+ALTER TABLE files DROP COLUMN IF EXISTS version;
+ALTER TABLE files DROP COLUMN IF EXISTS tracking_id;
@@ -0,0 +1,20 @@
+-- Your SQL goes here
+ALTER TABLE files ADD COLUMN IF NOT EXISTS tracking_id TEXT;
+ALTER TABLE files ADD COLUMN IF NOT EXISTS version INTEGER NOT NULL DEFAULT 1;
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_files_dataset_id_tracking_id ON files (dataset_id, tracking_id);
+
+CREATE TABLE IF NOT EXISTS file_versions (
+    id UUID PRIMARY KEY,
+    file_id UUID NOT NULL REFERENCES files(id) ON DELETE CASCADE,
+    dataset_id UUID NOT NULL,
+    version INTEGER NOT NULL,
+    file_name TEXT NOT NULL,
+    size BIGINT NOT NULL,
+    metadata JSONB,
+    link TEXT,
+    time_stamp TIMESTAMP,
+    tag_set TEXT[],
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE (file_id, version)
+);
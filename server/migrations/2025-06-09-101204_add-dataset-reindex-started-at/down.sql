@@ -0,0 +1,2 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE dataset_reindexes DROP COLUMN IF EXISTS started_at;
@@ -0,0 +1,2 @@
+-- Your SQL goes here
+ALTER TABLE dataset_reindexes ADD COLUMN IF NOT EXISTS started_at TIMESTAMP;
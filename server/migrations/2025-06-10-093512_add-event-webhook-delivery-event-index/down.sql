@@ -0,0 +1,2 @@
+-- This file should undo anything in `up.sql`
+DROP INDEX IF EXISTS idx_event_webhook_deliveries_webhook_id_event_id;
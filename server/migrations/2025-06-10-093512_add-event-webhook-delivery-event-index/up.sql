@@ -0,0 +1,2 @@
+-- Your SQL goes here
+CREATE UNIQUE INDEX IF NOT EXISTS idx_event_webhook_deliveries_webhook_id_event_id ON event_webhook_deliveries (webhook_id, event_id);
@@ -0,0 +1,28 @@
+-- Your SQL goes here
+CREATE OR REPLACE FUNCTION check_chunk_group_parent_cycle()
+RETURNS TRIGGER AS $$
+BEGIN
+    IF NEW.parent_id IS NULL THEN
+        RETURN NEW;
+    END IF;
+
+    -- Serializes parent changes within the dataset so concurrent updates cannot each pass the
+    -- check and create a cycle together
+    PERFORM pg_advisory_xact_lock(hashtextextended('chunk_group_parent:' || NEW.dataset_id::text, 0));
+
+    IF NEW.parent_id = NEW.id OR EXISTS (
+        WITH RECURSIVE ancestors AS (
+            SELECT id, parent_id FROM chunk_group WHERE id = NEW.parent_id
+            UNION
+            SELECT chunk_group.id, chunk_group.parent_id
+            FROM chunk_group
+            JOIN ancestors ON chunk_group.id = ancestors.parent_id
+        )
+        SELECT 1 FROM ancestors WHERE ancestors.id = NEW.id
+    ) THEN
+        RAISE EXCEPTION 'Setting parent % on chunk_group % would create a cycle', NEW.parent_id, NEW.id;
+    END IF;
+
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
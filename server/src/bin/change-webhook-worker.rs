@@ -0,0 +1,109 @@
+use diesel_async::pooled_connection::{AsyncDieselConnectionManager, ManagerConfig};
+use signal_hook::consts::SIGTERM;
+use std::error::Error;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use trieve_server::{
+    establish_connection, get_env,
+    operators::change_feed_operator::{
+        delete_chunk_changes_before_query, deliver_change_webhook, lease_due_change_webhooks_query,
+    },
+};
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn Error>> {
+    dotenvy::dotenv().ok();
+    env_logger::builder()
+        .target(env_logger::Target::Stdout)
+        .filter_level(log::LevelFilter::Info)
+        .init();
+
+    let database_url = get_env!("DATABASE_URL", "DATABASE_URL is not set");
+
+    let mut config = ManagerConfig::default();
+    config.custom_setup = Box::new(establish_connection);
+
+    let mgr = AsyncDieselConnectionManager::<diesel_async::AsyncPgConnection>::new_with_config(
+        database_url,
+        config,
+    );
+
+    let pool = diesel_async::pooled_connection::deadpool::Pool::builder(mgr)
+        .max_size(10)
+        .build()
+        .expect("Failed to create diesel_async pool");
+
+    let web_pool = actix_web::web::Data::new(pool.clone());
+
+    let concurrency: i64 = std::env::var("CHANGE_WEBHOOK_CONCURRENCY")
+        .unwrap_or("8".to_string())
+        .parse()
+        .unwrap_or(8);
+    let retention_days: i64 = std::env::var("CHUNK_CHANGE_RETENTION_DAYS")
+        .unwrap_or("30".to_string())
+        .parse()
+        .unwrap_or(30);
+
+    let should_terminate = Arc::new(AtomicBool::new(false));
+    signal_hook::flag::register(SIGTERM, Arc::clone(&should_terminate))
+        .expect("Failed to register shutdown hook");
+
+    let mut last_cleanup: Option<std::time::Instant> = None;
+
+    log::info!("Starting change webhook worker");
+
+    loop {
+        if should_terminate.load(Ordering::Relaxed) {
+            log::info!("Shutting down");
+            break;
+        }
+
+        if last_cleanup.map_or(true, |last_cleanup| {
+            last_cleanup.elapsed() > std::time::Duration::from_secs(60 * 60)
+        }) {
+            let before = chrono::Utc::now().naive_local() - chrono::Duration::days(retention_days);
+            match delete_chunk_changes_before_query(before, web_pool.clone()).await {
+                Ok(deleted) => {
+                    log::info!("Deleted {} chunk changes older than {}", deleted, before)
+                }
+                Err(err) => log::error!("Failed to delete old chunk changes: {:?}", err),
+            }
+            last_cleanup = Some(std::time::Instant::now());
+        }
+
+        // Leases outlast the 10 second request timeout so no other worker picks up a webhook
+        // while a delivery to it is in flight
+        let webhooks =
+            match lease_due_change_webhooks_query(concurrency, 60, web_pool.clone()).await {
+                Ok(webhooks) => webhooks,
+                Err(err) => {
+                    log::error!("Failed to lease change webhooks: {:?}", err);
+                    tokio::time::sleep(std::time::Duration::from_secs(5)).await;
+                    continue;
+                }
+            };
+
+        if webhooks.is_empty() {
+            tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+            continue;
+        }
+
+        let deliveries = webhooks.into_iter().map(|webhook| {
+            let webhook_id = webhook.id;
+            let web_pool = web_pool.clone();
+            async move {
+                if let Err(err) = deliver_change_webhook(webhook, web_pool).await {
+                    log::error!(
+                        "Failed to deliver changes to webhook {}: {:?}",
+                        webhook_id,
+                        err
+                    );
+                }
+            }
+        });
+
+        futures::future::join_all(deliveries).await;
+    }
+
+    Ok(())
+}
@@ -2,31 +2,49 @@ use actix_web::web;
 use broccoli_queue::{error::BroccoliError, queue::BroccoliQueue};
 use diesel_async::pooled_connection::{AsyncDieselConnectionManager, ManagerConfig};
 use itertools::Itertools;
+use qdrant_client::qdrant::Condition;
 use signal_hook::consts::SIGTERM;
+use std::collections::HashMap;
 use std::error::Error;
-use std::io::Read;
 use std::sync::atomic::AtomicBool;
 use std::sync::Arc;
 use trieve_server::{
-    data::models::{Pool, UnifiedId},
+    data::models::{
+        ChunkGroup, DatasetAndOrgWithSubAndPlan, DatasetClone, DatasetCloneStatus,
+        DatasetConfiguration, EventType, ExportedChunk, Pool, UnifiedId, WorkerEvent,
+    },
+    errors::ServiceError,
     establish_connection, get_env,
     handlers::{
         auth_handler::AdminOnly,
         chunk_handler::{
-            create_chunk, ChunkReqPayload, CreateBatchChunkReqPayload, CreateChunkReqPayloadEnum,
-            FullTextBoost, SemanticBoost,
+            create_chunk, ChunkFilter, CreateBatchChunkReqPayload, CreateChunkReqPayloadEnum,
         },
         dataset_handler::CloneDatasetMessage,
-        file_handler::{upload_file_helper, UploadFileReqPayload},
     },
     operators::{
-        chunk_operator::scroll_chunks_with_boosts_and_groups,
+        clickhouse_operator::{ClickHouseEvent, EventQueue},
+        clone_operator::{
+            create_dataset_clone_query, get_dataset_clone_query, get_files_to_clone_query,
+            get_groups_to_clone_query, update_dataset_clone_progress_query,
+            update_dataset_clone_status_query,
+        },
         dataset_operator::get_dataset_and_organization_from_dataset_id_query,
-        file_operator::get_file_query,
-        group_operator::{create_group_from_file_query, create_groups_query},
+        export_operator::get_exported_chunks_from_point_ids_query,
+        file_operator::get_aws_bucket,
+        import_operator::{
+            can_reuse_exported_vectors, check_import_chunk_limit_query,
+            exported_chunk_to_chunk_req_payload, import_chunks_with_vectors_query,
+            import_file_query, import_groups_query,
+        },
+        qdrant_operator::{get_point_vectors_query, scroll_dataset_points},
+        search_operator::assemble_qdrant_filter,
     },
 };
 
+const GROUP_BATCH_SIZE: usize = 500;
+const CHUNK_BATCH_SIZE: u64 = 120;
+
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn Error>> {
     dotenvy::dotenv().ok();
@@ -58,6 +76,34 @@ async fn main() -> Result<(), Box<dyn Error>> {
         .parse()
         .unwrap_or(2);
 
+    let mut event_queue = if std::env::var("USE_ANALYTICS")
+        .unwrap_or("false".to_string())
+        .parse()
+        .unwrap_or(false)
+    {
+        log::info!("Analytics enabled");
+
+        let clickhouse_client = clickhouse::Client::default()
+            .with_url(
+                std::env::var("CLICKHOUSE_URL").unwrap_or("http://localhost:8123".to_string()),
+            )
+            .with_user(std::env::var("CLICKHOUSE_USER").unwrap_or("default".to_string()))
+            .with_password(std::env::var("CLICKHOUSE_PASSWORD").unwrap_or("".to_string()))
+            .with_database(std::env::var("CLICKHOUSE_DATABASE").unwrap_or("default".to_string()))
+            .with_option("async_insert", "1")
+            .with_option("wait_for_async_insert", "0");
+
+        let mut event_queue = EventQueue::new(clickhouse_client.clone());
+        event_queue.start_service();
+        event_queue
+    } else {
+        log::info!("Analytics disabled");
+        EventQueue::default()
+    };
+    event_queue.start_webhook_service();
+
+    let web_event_queue = web::Data::new(event_queue);
+
     let should_terminate = Arc::new(AtomicBool::new(false));
     signal_hook::flag::register(SIGTERM, Arc::clone(&should_terminate))
         .expect("Failed to register shutdown hook");
@@ -75,7 +121,12 @@ async fn main() -> Result<(), Box<dyn Error>> {
 
     broccoli_queue
         .process_messages("clone_dataset", None, None, move |msg| {
-            clone_dataset_worker(msg.payload, web_broccoli_queue.clone(), web_pool.clone())
+            clone_dataset_worker(
+                msg.payload,
+                web_broccoli_queue.clone(),
+                web_pool.clone(),
+                web_event_queue.clone(),
+            )
         })
         .await?;
 
@@ -86,156 +137,326 @@ async fn clone_dataset_worker(
     msg: CloneDatasetMessage,
     broccoli_queue: web::Data<BroccoliQueue>,
     web_pool: web::Data<Pool>,
+    event_queue: web::Data<EventQueue>,
 ) -> Result<(), BroccoliError> {
-    log::info!("Cloning dataset: {:?}", msg.dataset_to_clone);
-    let dataset_to_clone = get_dataset_and_organization_from_dataset_id_query(
-        UnifiedId::TrieveUuid(msg.dataset_to_clone),
+    log::info!(
+        "Cloning dataset {:?} into {:?}",
+        msg.dataset_to_clone,
+        msg.new_dataset
+    );
+
+    let mut dataset_clone = match msg.clone_id {
+        Some(clone_id) => get_dataset_clone_query(clone_id, web_pool.clone()).await,
+        // Messages queued before clones were tracked clone the entire dataset
+        None => {
+            create_dataset_clone_query(
+                DatasetClone::from_details(msg.new_dataset, msg.dataset_to_clone, None, None),
+                web_pool.clone(),
+            )
+            .await
+        }
+    }
+    .map_err(|e| BroccoliError::Job(e.to_string()))?;
+
+    update_dataset_clone_status_query(
+        dataset_clone.id,
+        DatasetCloneStatus::Processing,
         None,
         web_pool.clone(),
     )
     .await
     .map_err(|e| BroccoliError::Job(e.to_string()))?;
 
-    let new_dataset = get_dataset_and_organization_from_dataset_id_query(
-        UnifiedId::TrieveUuid(msg.new_dataset),
+    let clone_result =
+        clone_dataset(&mut dataset_clone, web_pool.clone(), broccoli_queue.clone()).await;
+
+    let (status, error, event_type) = match clone_result {
+        Ok(()) => (
+            DatasetCloneStatus::Completed,
+            None,
+            EventType::DatasetCloneCompleted {
+                clone_id: dataset_clone.id,
+                source_dataset_id: dataset_clone.source_dataset_id,
+                vectors_reused: dataset_clone.vectors_reused.unwrap_or(false),
+                groups_cloned: dataset_clone.groups_cloned,
+                files_cloned: dataset_clone.files_cloned,
+                chunks_cloned: dataset_clone.chunks_cloned,
+            },
+        ),
+        Err(err) => {
+            log::error!("Failed to clone dataset: {:?}", err);
+            (
+                DatasetCloneStatus::Failed,
+                Some(err.to_string()),
+                EventType::DatasetCloneFailed {
+                    clone_id: dataset_clone.id,
+                    source_dataset_id: dataset_clone.source_dataset_id,
+                    error: err.to_string(),
+                },
+            )
+        }
+    };
+
+    update_dataset_clone_status_query(dataset_clone.id, status, error, web_pool.clone())
+        .await
+        .map_err(|e| BroccoliError::Job(e.to_string()))?;
+
+    event_queue
+        .send(ClickHouseEvent::WorkerEvent(
+            WorkerEvent::from_details(dataset_clone.dataset_id, None, event_type).into(),
+        ))
+        .await;
+
+    log::info!("Cloned dataset: {:?}", msg.new_dataset);
+    Ok(())
+}
+
+struct CloneState {
+    source_dataset_id: uuid::Uuid,
+    target_dataset_id: uuid::Uuid,
+    target_organization_id: uuid::Uuid,
+    bucket: s3::Bucket,
+    /// Groups created in the new dataset keyed by their id in the source dataset
+    groups: HashMap<uuid::Uuid, ChunkGroup>,
+}
+
+async fn clone_dataset(
+    dataset_clone: &mut DatasetClone,
+    web_pool: web::Data<Pool>,
+    broccoli_queue: web::Data<BroccoliQueue>,
+) -> Result<(), ServiceError> {
+    let source_dataset = get_dataset_and_organization_from_dataset_id_query(
+        UnifiedId::TrieveUuid(dataset_clone.source_dataset_id),
         None,
         web_pool.clone(),
     )
-    .await
-    .map_err(|e| BroccoliError::Job(e.to_string()))?;
+    .await?;
+    let target_dataset = get_dataset_and_organization_from_dataset_id_query(
+        UnifiedId::TrieveUuid(dataset_clone.dataset_id),
+        None,
+        web_pool.clone(),
+    )
+    .await?;
+
+    let source_config =
+        DatasetConfiguration::from_json(source_dataset.dataset.server_configuration.clone());
+    let target_config =
+        DatasetConfiguration::from_json(target_dataset.dataset.server_configuration.clone());
+    if source_config.QDRANT_ONLY {
+        return Err(ServiceError::BadRequest(
+            "Chunks of datasets which only store chunks in qdrant cannot be cloned".to_string(),
+        ));
+    }
+
+    let reuse_vectors = can_reuse_exported_vectors(&source_config, &target_config);
+    log::info!(
+        "Clone {:?} will {} the source vectors",
+        dataset_clone.id,
+        if reuse_vectors { "reuse" } else { "re-embed" }
+    );
+    dataset_clone.vectors_reused = Some(reuse_vectors);
+
+    let mut state = CloneState {
+        source_dataset_id: source_dataset.dataset.id,
+        target_dataset_id: target_dataset.dataset.id,
+        target_organization_id: target_dataset.organization.organization.id,
+        bucket: get_aws_bucket()?,
+        groups: HashMap::new(),
+    };
+
+    let filter: Option<ChunkFilter> = dataset_clone
+        .filter
+        .clone()
+        .map(serde_json::from_value)
+        .transpose()
+        .map_err(|err| ServiceError::BadRequest(format!("Invalid clone filter: {}", err)))?;
+    let mut qdrant_filter = assemble_qdrant_filter(
+        filter,
+        None,
+        None,
+        source_dataset.dataset.id,
+        web_pool.clone(),
+    )
+    .await?;
 
-    let mut chunk_cursor = Some(uuid::Uuid::nil());
-    while let Some(offset_id) = chunk_cursor {
-        log::info!("Scrolling chunks from offset: {:?}", offset_id);
-        let (chunk_metadatas_with_boosts_and_groups, next_offset_id) =
-            scroll_chunks_with_boosts_and_groups(
+    if let Some(group_ids) = dataset_clone.group_ids.clone() {
+        // Only the requested groups are cloned, so chunks keep just their memberships in them
+        for group_ids in group_ids.chunks(GROUP_BATCH_SIZE) {
+            clone_groups(
+                dataset_clone,
+                &mut state,
+                group_ids.to_vec(),
                 web_pool.clone(),
-                dataset_to_clone.dataset.id,
-                120,
-                Some(offset_id),
             )
-            .await
-            .map_err(|e| BroccoliError::Job(e.to_string()))?;
+            .await?;
+        }
 
-        let groups_to_create = chunk_metadatas_with_boosts_and_groups
-            .iter()
-            .flat_map(|(_, _, groups)| groups)
-            .flatten()
-            .unique_by(|g| g.id)
-            .collect::<Vec<_>>();
+        qdrant_filter.must.push(Condition::matches(
+            "group_ids",
+            group_ids
+                .iter()
+                .map(|group_id| group_id.to_string())
+                .collect::<Vec<String>>(),
+        ));
+    }
 
-        let chunk_req_payloads: Vec<ChunkReqPayload> = chunk_metadatas_with_boosts_and_groups
+    let mut point_offset = None;
+    loop {
+        let (points, next_offset) = scroll_dataset_points(
+            CHUNK_BATCH_SIZE,
+            point_offset,
+            None,
+            source_config.clone(),
+            qdrant_filter.clone(),
+        )
+        .await?;
+
+        let point_ids = points
             .iter()
-            .map(|(chunk_metadata, boosts, groups)| {
-                let group_tracking_ids: Option<Vec<String>> = groups.as_ref().map(|groups| {
-                    groups
-                        .iter()
-                        .map(|g| g.tracking_id.clone().unwrap_or(g.id.to_string()))
-                        .collect::<Vec<_>>()
-                });
-
-                ChunkReqPayload {
-                    // TODO: add semantic and fulltext content
-                    // We don't currently store this
-                    chunk_html: chunk_metadata.chunk_html.clone(),
-                    link: chunk_metadata.link.clone(),
-                    tag_set: chunk_metadata
-                        .tag_set
-                        .as_ref()
-                        .map(|ts| ts.iter().map(|t| t.clone().unwrap_or_default()).collect()),
-                    num_value: chunk_metadata.num_value,
-                    metadata: chunk_metadata.metadata.clone(),
-                    tracking_id: chunk_metadata.tracking_id.clone(),
-                    upsert_by_tracking_id: Some(true),
-                    group_tracking_ids,
-                    time_stamp: chunk_metadata.time_stamp.map(|ts| ts.to_string()),
-                    location: chunk_metadata.location,
-                    image_urls: chunk_metadata
-                        .image_urls
-                        .as_ref()
-                        .map(|urls| urls.iter().map(|u| u.clone().unwrap_or_default()).collect()),
-                    weight: Some(chunk_metadata.weight),
-                    fulltext_boost: boosts.as_ref().and_then(|b| {
-                        b.fulltext_boost_phrase.as_ref().map(|f| FullTextBoost {
-                            phrase: f.clone(),
-                            boost_factor: b.fulltext_boost_factor.unwrap_or(0.0),
-                        })
-                    }),
-                    semantic_boost: boosts.as_ref().and_then(|b| {
-                        b.semantic_boost_phrase.as_ref().map(|s| SemanticBoost {
-                            phrase: s.clone(),
-                            distance_factor: b.semantic_boost_factor.unwrap_or(0.0) as f32,
-                        })
-                    }),
-                    ..Default::default()
-                }
+            .map(|point| point.point_id)
+            .collect::<Vec<uuid::Uuid>>();
+        let mut exported_chunks =
+            get_exported_chunks_from_point_ids_query(point_ids.clone(), web_pool.clone()).await?;
+        let mut point_vectors = if reuse_vectors {
+            get_point_vectors_query(point_ids.clone(), source_config.clone()).await?
+        } else {
+            HashMap::new()
+        };
+
+        let chunks = point_ids
+            .into_iter()
+            .filter_map(|point_id| {
+                let mut exported_chunk = exported_chunks.remove(&point_id)?;
+                exported_chunk.vectors = point_vectors.remove(&point_id).unwrap_or_default();
+                Some(exported_chunk)
             })
-            .collect();
-
-        if !groups_to_create.is_empty() {
-            let created_groups = create_groups_query(
-                groups_to_create
-                    .iter()
-                    .map(|g| g.clone_group(new_dataset.dataset.id))
-                    .collect(),
-                true,
+            .collect::<Vec<ExportedChunk>>();
+
+        if dataset_clone.group_ids.is_none() {
+            let missing_group_ids = chunks
+                .iter()
+                .flat_map(|chunk| chunk.group_ids.iter().copied())
+                .filter(|group_id| !state.groups.contains_key(group_id))
+                .unique()
+                .collect::<Vec<uuid::Uuid>>();
+            clone_groups(
+                dataset_clone,
+                &mut state,
+                missing_group_ids,
                 web_pool.clone(),
             )
-            .await
-            .map_err(|e| BroccoliError::Job(e.to_string()))?;
-
-            for group in groups_to_create.into_iter().filter(|g| g.file_id.is_some()) {
-                // Each groups has a unique file associated with it so we don't need to worry abt
-                // creating the same file multiple times
-
-                let file_id = group.file_id.unwrap();
-                let file = get_file_query(
-                    file_id,
-                    3600,
-                    dataset_to_clone.dataset.id,
-                    None,
-                    web_pool.clone(),
-                )
-                .await
-                .map_err(|e| BroccoliError::Job(e.to_string()))?;
-
-                let file_data = ureq::get(file.s3_url.as_str())
-                    .call()
-                    .map_err(|e| BroccoliError::Job(e.to_string()))?;
-                let mut file_data_vec = Vec::new();
-                file_data
-                    .into_reader()
-                    .read_to_end(&mut file_data_vec)
-                    .unwrap();
-
-                let new_file_id = upload_file_helper(
-                    UploadFileReqPayload {
-                        file_name: file.file_name.clone(),
-                        metadata: file.metadata,
-                        link: file.link,
-                        time_stamp: file.time_stamp.map(|ts| ts.to_string()),
-                        tag_set: file.tag_set,
-                        ..Default::default()
-                    },
-                    file_data_vec,
-                    web_pool.clone(),
-                    new_dataset.clone(),
-                )
-                .await
-                .map_err(|e| BroccoliError::Job(e.to_string()))?;
-
-                let new_group = created_groups.iter().find(|g| {
-                    g.tracking_id == group.tracking_id
-                        || g.tracking_id == Some(group.id.to_string())
-                });
-
-                if let Some(new_group) = new_group {
-                    create_group_from_file_query(new_group.id, new_file_id, web_pool.clone())
-                        .await
-                        .map_err(|e| BroccoliError::Job(e.to_string()))?;
-                }
-            }
+            .await?;
+        }
+
+        clone_chunks(
+            dataset_clone,
+            &state,
+            chunks,
+            reuse_vectors,
+            &target_dataset,
+            target_config.clone(),
+            web_pool.clone(),
+            broccoli_queue.clone(),
+        )
+        .await?;
+
+        match next_offset {
+            Some(next_offset) if !points.is_empty() => point_offset = Some(next_offset),
+            _ => break,
+        }
+    }
+
+    update_dataset_clone_progress_query(dataset_clone, web_pool).await
+}
+
+/// Creates the given source groups in the new dataset and copies the files they were created from.
+async fn clone_groups(
+    dataset_clone: &mut DatasetClone,
+    state: &mut CloneState,
+    group_ids: Vec<uuid::Uuid>,
+    web_pool: web::Data<Pool>,
+) -> Result<(), ServiceError> {
+    if group_ids.is_empty() {
+        return Ok(());
+    }
+
+    let groups =
+        get_groups_to_clone_query(group_ids, state.source_dataset_id, web_pool.clone()).await?;
+    let file_ids_by_group_id: HashMap<uuid::Uuid, uuid::Uuid> = groups
+        .iter()
+        .filter_map(|group| Some((group.id, group.file_id?)))
+        .collect();
+
+    let cloned_groups =
+        import_groups_query(groups, state.target_dataset_id, web_pool.clone()).await?;
+
+    let mut group_ids_by_file_id = HashMap::new();
+    for (source_group_id, group) in cloned_groups {
+        if let Some(file_id) = file_ids_by_group_id.get(&source_group_id) {
+            group_ids_by_file_id.insert(*file_id, group.id);
         }
+        state.groups.insert(source_group_id, group);
+        dataset_clone.groups_cloned += 1;
+    }
+
+    let files = get_files_to_clone_query(
+        group_ids_by_file_id.keys().copied().collect(),
+        state.source_dataset_id,
+        web_pool.clone(),
+    )
+    .await?;
+    for file in files {
+        let group_id = group_ids_by_file_id.get(&file.id).copied();
+        if import_file_query(
+            file,
+            state.target_dataset_id,
+            state.target_organization_id,
+            group_id,
+            &state.bucket,
+            web_pool.clone(),
+        )
+        .await?
+        {
+            dataset_clone.files_cloned += 1;
+        }
+    }
+
+    update_dataset_clone_progress_query(dataset_clone, web_pool).await
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn clone_chunks(
+    dataset_clone: &mut DatasetClone,
+    state: &CloneState,
+    chunks: Vec<ExportedChunk>,
+    reuse_vectors: bool,
+    target_dataset: &DatasetAndOrgWithSubAndPlan,
+    target_config: DatasetConfiguration,
+    web_pool: web::Data<Pool>,
+    broccoli_queue: web::Data<BroccoliQueue>,
+) -> Result<(), ServiceError> {
+    if chunks.is_empty() {
+        return Ok(());
+    }
+
+    check_import_chunk_limit_query(target_dataset, chunks.len(), web_pool.clone()).await?;
+
+    if reuse_vectors {
+        let cloned_chunks = import_chunks_with_vectors_query(
+            chunks,
+            &state.groups,
+            target_dataset.dataset.id,
+            target_config,
+            web_pool.clone(),
+        )
+        .await?;
+        dataset_clone.chunks_cloned += cloned_chunks as i64;
+    } else {
+        let chunk_req_payloads = chunks
+            .into_iter()
+            .map(|chunk| exported_chunk_to_chunk_req_payload(chunk, &state.groups))
+            .collect::<Vec<_>>();
+        let chunk_count = chunk_req_payloads.len();
 
         create_chunk(
             web::Json(CreateChunkReqPayloadEnum::Batch(
@@ -243,15 +464,14 @@ async fn clone_dataset_worker(
             )),
             web_pool.clone(),
             AdminOnly::default(),
-            broccoli_queue.clone(),
-            new_dataset.clone(),
+            broccoli_queue,
+            target_dataset.clone(),
         )
         .await
-        .map_err(|e| BroccoliError::Job(e.to_string()))?;
+        .map_err(|err| ServiceError::BadRequest(err.to_string()))?;
 
-        chunk_cursor = next_offset_id;
+        dataset_clone.chunks_cloned += chunk_count as i64;
     }
 
-    log::info!("Cloned dataset: {:?}", msg.new_dataset);
-    Ok(())
+    update_dataset_clone_progress_query(dataset_clone, web_pool).await
 }
@@ -461,7 +461,7 @@ async fn parse_chunks_with_firecrawl(
             crawl_req.crawl_options.body_remove_strings.clone(),
         );
 
-        for chunk in chunked_html {
+        for (i, chunk) in chunked_html.into_iter().enumerate() {
             let heading = chunk.0.clone();
             let chunk_html = chunk.1.clone();
 
@@ -473,6 +473,8 @@ async fn parse_chunks_with_firecrawl(
             let mut metadata = json!({
                 "url": page_link.clone(),
                 "hierarchy": chunk.0.clone(),
+                "source_document": page_link.clone(),
+                "source_position": i,
             });
 
             let mut semantic_boost_phrase = heading.clone();
@@ -1135,7 +1137,7 @@ async fn main() -> Result<(), Box<dyn Error>> {
     signal_hook::flag::register(SIGTERM, Arc::clone(&should_terminate))
         .expect("Failed to register shutdown hook");
 
-    let event_queue = if std::env::var("USE_ANALYTICS")
+    let mut event_queue = if std::env::var("USE_ANALYTICS")
         .unwrap_or("false".to_string())
         .parse()
         .unwrap_or(false)
@@ -1159,6 +1161,7 @@ async fn main() -> Result<(), Box<dyn Error>> {
         log::info!("Analytics disabled");
         EventQueue::default()
     };
+    event_queue.start_webhook_service();
     let web_event_queue = actix_web::web::Data::new(event_queue);
 
     let broccoli_queue = BroccoliQueue::builder(redis_url)
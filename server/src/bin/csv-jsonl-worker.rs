@@ -1,26 +1,28 @@
 use broccoli_queue::queue::BroccoliQueue;
-use chrono::{DateTime, NaiveDateTime};
 use diesel_async::pooled_connection::{AsyncDieselConnectionManager, ManagerConfig};
+use parquet::file::{reader::FileReader, serialized_reader::SerializedFileReader};
 use signal_hook::consts::SIGTERM;
 use std::sync::{
     atomic::{AtomicBool, Ordering},
     Arc,
 };
+use tokio::io::AsyncWriteExt;
 use tokio_stream::StreamExt;
 use trieve_server::{
     data::models::{
-        self, ChunkGroup, ChunkReqPayloadFields, ChunkReqPayloadMapping, CsvJsonlWorkerMessage,
-        GeoInfo, GeoTypes,
+        self, ChunkGroup, CsvJsonlFileFormat, CsvJsonlRowFailure, CsvJsonlWorkerMessage,
+        SpreadsheetOptions,
     },
     errors::ServiceError,
     establish_connection, get_env,
-    handlers::{
-        chunk_handler::{ChunkReqPayload, FullTextBoost, SemanticBoost},
-        file_handler::UploadFileReqPayload,
-    },
+    handlers::{chunk_handler::ChunkReqPayload, file_handler::UploadFileReqPayload},
     operators::{
         chunk_operator::create_chunk_metadata,
         clickhouse_operator::{ClickHouseEvent, EventQueue},
+        csv_jsonl_operator::{
+            parse_csv_jsonl_line, read_parquet_row_group, read_spreadsheet_rows,
+            CsvJsonlRowConverter,
+        },
         file_operator::{create_file_query, get_csvjsonl_aws_bucket},
         group_operator::{create_group_from_file_query, create_groups_query},
     },
@@ -73,7 +75,7 @@ fn main() {
 
             let web_redis_pool = actix_web::web::Data::new(redis_pool);
 
-            let event_queue = if std::env::var("USE_ANALYTICS")
+            let mut event_queue = if std::env::var("USE_ANALYTICS")
                 .unwrap_or("false".to_string())
                 .parse()
                 .unwrap_or(false)
@@ -100,6 +102,7 @@ fn main() {
                 log::info!("Analytics disabled");
                 EventQueue::default()
             };
+            event_queue.start_webhook_service();
 
             let web_event_queue = actix_web::web::Data::new(event_queue);
 
@@ -280,18 +283,340 @@ async fn file_worker(
             }
         };
 
-        let _ = process_csv_jsonl_file(
+        let file_id = csv_jsonl_worker_message.file_id;
+        let dataset_id = csv_jsonl_worker_message.dataset_id;
+        if let Err(err) = process_csv_jsonl_file(
             csv_jsonl_worker_message,
             web_pool.clone(),
+            event_queue.clone(),
             broccoli_queue.clone(),
         )
-        .await;
+        .await
+        {
+            log::error!("Failed to process file {}: {:?}", file_id, err);
+            event_queue
+                .send(ClickHouseEvent::WorkerEvent(
+                    models::WorkerEvent::from_details(
+                        dataset_id,
+                        None,
+                        models::EventType::CsvJsonlProcessingFailed {
+                            file_id,
+                            error: err.to_string(),
+                        },
+                    )
+                    .into(),
+                ))
+                .await;
+        }
+    }
+}
+
+const CHUNK_BATCH_SIZE: usize = 120;
+const MAX_REPORTED_ROW_FAILURES: usize = 100;
+
+/// Converts rows into chunks and pushes them to the ingestion queue in batches, keeping track of rows which could not be processed.
+struct ChunkBatcher<'a> {
+    row_converter: &'a CsvJsonlRowConverter,
+    group_id: uuid::Uuid,
+    dataset_id: uuid::Uuid,
+    broccoli_queue: BroccoliQueue,
+    chunk_req_payloads: Vec<ChunkReqPayload>,
+    chunks_created: usize,
+    total_failed_rows: usize,
+    failed_rows: Vec<CsvJsonlRowFailure>,
+}
+
+impl<'a> ChunkBatcher<'a> {
+    fn new(
+        row_converter: &'a CsvJsonlRowConverter,
+        group_id: uuid::Uuid,
+        dataset_id: uuid::Uuid,
+        broccoli_queue: BroccoliQueue,
+    ) -> Self {
+        ChunkBatcher {
+            row_converter,
+            group_id,
+            dataset_id,
+            broccoli_queue,
+            chunk_req_payloads: vec![],
+            chunks_created: 0,
+            total_failed_rows: 0,
+            failed_rows: vec![],
+        }
+    }
+
+    fn record_failure(&mut self, row: usize, error: String) {
+        log::warn!("Failed to process row {}: {}", row, error);
+        self.total_failed_rows += 1;
+        if self.failed_rows.len() < MAX_REPORTED_ROW_FAILURES {
+            self.failed_rows.push(CsvJsonlRowFailure { row, error });
+        }
+    }
+
+    async fn push_row(
+        &mut self,
+        row: usize,
+        object: Result<serde_json::Value, String>,
+    ) -> Result<(), ServiceError> {
+        let object = match object {
+            Ok(object) => object,
+            Err(error) => {
+                self.record_failure(row, error);
+                return Ok(());
+            }
+        };
+
+        if let Some(chunk_req_payload) = self.row_converter.convert(object, Some(self.group_id)) {
+            self.chunk_req_payloads.push(chunk_req_payload);
+        }
+
+        if self.chunk_req_payloads.len() >= CHUNK_BATCH_SIZE {
+            self.flush().await?;
+        }
+
+        Ok(())
+    }
+
+    async fn flush(&mut self) -> Result<(), ServiceError> {
+        if self.chunk_req_payloads.is_empty() {
+            return Ok(());
+        }
+
+        let (upsert_chunk_ingestion_message, upsert_chunk_metadatas) =
+            create_chunk_metadata(self.chunk_req_payloads.clone(), self.dataset_id)?;
+
+        if !upsert_chunk_metadatas.is_empty() {
+            log::info!(
+                "Pushing chunk ingestion message to redis {:?}",
+                upsert_chunk_metadatas.len()
+            );
+            self.broccoli_queue
+                .publish(
+                    "ingestion",
+                    Some(self.dataset_id.to_string()),
+                    &upsert_chunk_ingestion_message,
+                    None,
+                )
+                .await
+                .map_err(|err| ServiceError::BadRequest(err.to_string()))?;
+        }
+
+        self.chunks_created += upsert_chunk_metadatas.len();
+        self.chunk_req_payloads.clear();
+
+        Ok(())
+    }
+}
+
+/// Streams a CSV or JSONL file line by line. Returns the number of bytes read.
+async fn process_csv_jsonl_lines(
+    bucket: &s3::Bucket,
+    file_id: uuid::Uuid,
+    chunk_batcher: &mut ChunkBatcher<'_>,
+) -> Result<usize, ServiceError> {
+    let mut response_data_stream = bucket
+        .get_object_stream(file_id.to_string())
+        .await
+        .map_err(|err| {
+            log::error!("Failed to get object stream: {:?}", err);
+            ServiceError::InternalServerError("Failed to get object stream".to_string())
+        })?;
+
+    let mut columns = vec![];
+    let mut line = String::new();
+    let mut line_number = 0;
+    let mut bytes: bytes::BytesMut = bytes::BytesMut::new();
+    let mut byte_count = 0;
+    while let Some(chunk) = response_data_stream.bytes().next().await {
+        let chunk_bytes = chunk.map_err(|err| {
+            log::error!("Failed to get chunk from stream: {:?}", err);
+            ServiceError::InternalServerError("Failed to get chunk from stream".to_string())
+        })?;
+        bytes.extend_from_slice(&chunk_bytes);
+        let chunk = match String::from_utf8(bytes.to_vec()) {
+            Ok(chunk) => {
+                bytes.clear();
+                chunk
+            }
+            Err(_) => {
+                log::info!(
+                    "Failed to convert bytes chunk to utf8, continuing with bytes append..."
+                );
+                continue;
+            }
+        };
+
+        byte_count += chunk.len();
+
+        let chunk_lines = chunk.split_inclusive('\n');
+        for chunk_line in chunk_lines {
+            if chunk_line.is_empty() {
+                continue;
+            }
+
+            if chunk_line.ends_with('\n') {
+                line.push_str(chunk_line.trim_end_matches('\n'));
+                line_number += 1;
+                if let Some(object) = parse_csv_jsonl_line(&line, &mut columns).transpose() {
+                    chunk_batcher.push_row(line_number, object).await?;
+                }
+                line.clear();
+            } else {
+                line.push_str(chunk_line);
+            }
+        }
+    }
+
+    if !line.trim().is_empty() {
+        line_number += 1;
+        if let Some(object) = parse_csv_jsonl_line(&line, &mut columns).transpose() {
+            chunk_batcher.push_row(line_number, object).await?;
+        }
+    }
+
+    Ok(byte_count)
+}
+
+/// Downloads a Parquet file to a temporary file and reads it one row group at a time so only a single row group is held in memory. Returns the size of the file in bytes.
+async fn process_parquet_file(
+    bucket: &s3::Bucket,
+    file_id: uuid::Uuid,
+    chunk_batcher: &mut ChunkBatcher<'_>,
+) -> Result<usize, ServiceError> {
+    let mut response_data_stream = bucket
+        .get_object_stream(file_id.to_string())
+        .await
+        .map_err(|err| {
+            log::error!("Failed to get object stream: {:?}", err);
+            ServiceError::InternalServerError("Failed to get object stream".to_string())
+        })?;
+
+    let temp_file_path = std::env::temp_dir().join(format!("{}.parquet", file_id));
+    let mut temp_file = tokio::fs::File::create(&temp_file_path)
+        .await
+        .map_err(|err| {
+            log::error!("Failed to create temporary file: {:?}", err);
+            ServiceError::InternalServerError("Failed to create temporary file".to_string())
+        })?;
+
+    let mut byte_count = 0;
+    let download_result: Result<(), ServiceError> = async {
+        while let Some(chunk) = response_data_stream.bytes().next().await {
+            let chunk_bytes = chunk.map_err(|err| {
+                log::error!("Failed to get chunk from stream: {:?}", err);
+                ServiceError::InternalServerError("Failed to get chunk from stream".to_string())
+            })?;
+            byte_count += chunk_bytes.len();
+            temp_file.write_all(&chunk_bytes).await.map_err(|err| {
+                log::error!("Failed to write to temporary file: {:?}", err);
+                ServiceError::InternalServerError("Failed to write to temporary file".to_string())
+            })?;
+        }
+
+        temp_file.flush().await.map_err(|err| {
+            log::error!("Failed to flush temporary file: {:?}", err);
+            ServiceError::InternalServerError("Failed to write to temporary file".to_string())
+        })
+    }
+    .await;
+
+    let process_result = match download_result {
+        Ok(()) => process_parquet_row_groups(&temp_file_path, chunk_batcher).await,
+        Err(err) => Err(err),
+    };
+
+    if let Err(err) = tokio::fs::remove_file(&temp_file_path).await {
+        log::error!("Failed to remove temporary file: {:?}", err);
+    }
+
+    process_result.map(|_| byte_count)
+}
+
+async fn process_parquet_row_groups(
+    path: &std::path::Path,
+    chunk_batcher: &mut ChunkBatcher<'_>,
+) -> Result<(), ServiceError> {
+    let path = path.to_path_buf();
+    let reader = actix_web::web::block(move || {
+        let file = std::fs::File::open(path).map_err(|err| {
+            log::error!("Failed to open temporary file: {:?}", err);
+            ServiceError::InternalServerError("Failed to open temporary file".to_string())
+        })?;
+        SerializedFileReader::new(file)
+            .map_err(|err| ServiceError::BadRequest(format!("Invalid parquet file: {}", err)))
+    })
+    .await
+    .map_err(|err| ServiceError::InternalServerError(format!("Thread error {:?}", err)))??;
+    let reader = std::sync::Arc::new(reader);
+
+    let mut row_number = 0;
+    for row_group_index in 0..reader.metadata().num_row_groups() {
+        let row_group_reader = reader.clone();
+        let rows = match actix_web::web::block(move || {
+            read_parquet_row_group(&row_group_reader, row_group_index)
+        })
+        .await
+        .map_err(|err| ServiceError::InternalServerError(format!("Thread error {:?}", err)))?
+        {
+            Ok(rows) => rows,
+            Err(err) => {
+                let row_count = reader
+                    .metadata()
+                    .row_group(row_group_index)
+                    .num_rows()
+                    .max(0) as usize;
+                chunk_batcher.record_failure(row_number + 1, err.to_string());
+                row_number += row_count;
+                continue;
+            }
+        };
+
+        for row in rows {
+            row_number += 1;
+            chunk_batcher.push_row(row_number, row).await?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Reads the selected sheet of an XLSX or ODS file. Spreadsheets have to be read into memory in full. Returns the size of the file in bytes.
+async fn process_spreadsheet_file(
+    bucket: &s3::Bucket,
+    file_id: uuid::Uuid,
+    spreadsheet_options: &SpreadsheetOptions,
+    chunk_batcher: &mut ChunkBatcher<'_>,
+) -> Result<usize, ServiceError> {
+    let file_data = bucket
+        .get_object(file_id.to_string())
+        .await
+        .map_err(|err| {
+            log::error!("Failed to get object: {:?}", err);
+            ServiceError::InternalServerError("Failed to get object".to_string())
+        })?
+        .as_slice()
+        .to_vec();
+    let byte_count = file_data.len();
+
+    let spreadsheet_options = spreadsheet_options.clone();
+    let rows =
+        actix_web::web::block(move || read_spreadsheet_rows(file_data, &spreadsheet_options))
+            .await
+            .map_err(|err| {
+                ServiceError::InternalServerError(format!("Thread error {:?}", err))
+            })??;
+
+    for (row, object) in rows {
+        chunk_batcher.push_row(row, Ok(object)).await?;
     }
+
+    Ok(byte_count)
 }
 
 async fn process_csv_jsonl_file(
     csv_jsonl_worker_message: CsvJsonlWorkerMessage,
     web_pool: actix_web::web::Data<models::Pool>,
+    event_queue: actix_web::web::Data<EventQueue>,
     broccoli_queue: BroccoliQueue,
 ) -> Result<Option<uuid::Uuid>, ServiceError> {
     // get_object_stream from s3
@@ -301,18 +626,11 @@ async fn process_csv_jsonl_file(
     })?;
 
     log::info!(
-        "Getting object stream for file id: {}",
+        "Processing {} file id: {}",
+        csv_jsonl_worker_message.file_format,
         csv_jsonl_worker_message.file_id
     );
 
-    let mut response_data_stream = bucket
-        .get_object_stream(csv_jsonl_worker_message.file_id.to_string())
-        .await
-        .map_err(|err| {
-            log::error!("Failed to get object stream: {:?}", err);
-            ServiceError::InternalServerError("Failed to get object stream".to_string())
-        })?;
-
     let chunk_group = ChunkGroup::from_details(
         Some(
             csv_jsonl_worker_message
@@ -351,166 +669,83 @@ async fn process_csv_jsonl_file(
 
     log::info!("Group created with id: {:?}", group_id);
 
-    let mut columns = vec![];
-    let mut line = String::new();
-    let mut bytes: bytes::BytesMut = bytes::BytesMut::new();
-    let mut byte_count = 0;
-    let mut chunk_req_payloads: Vec<ChunkReqPayload> = vec![];
-    while let Some(chunk) = response_data_stream.bytes().next().await {
-        let chunk_bytes = chunk.map_err(|err| {
-            log::error!("Failed to get chunk from stream: {:?}", err);
-            ServiceError::InternalServerError("Failed to get chunk from stream".to_string())
-        })?;
-        bytes.extend_from_slice(&chunk_bytes);
-        let chunk = match String::from_utf8(bytes.to_vec()) {
-            Ok(chunk) => {
-                bytes.clear();
-                chunk
-            }
-            Err(_) => {
-                log::info!(
-                    "Failed to convert bytes chunk to utf8, continuing with bytes append..."
-                );
-                continue;
-            }
-        };
-
-        byte_count += chunk.len();
-
-        let chunk_lines = chunk.split_inclusive('\n');
-        for chunk_line in chunk_lines {
-            if chunk_line.is_empty() {
-                continue;
-            }
+    let row_converter = CsvJsonlRowConverter::new(
+        csv_jsonl_worker_message
+            .create_presigned_put_url_data
+            .conversion_options(),
+    )?;
 
-            if chunk_line.ends_with('\n') {
-                line.push_str(chunk_line.trim_end_matches('\n'));
-                let object = match serde_json::from_str::<serde_json::Value>(&line) {
-                    Ok(object) => Some(object),
-                    Err(_) => {
-                        if columns.is_empty() {
-                            columns = parse_csv_line(&line);
-                            None
-                        } else {
-                            let mut new_object = serde_json::Map::new();
-                            let mut line_vals_iter = parse_csv_line(&line).into_iter();
-                            for column in columns.iter() {
-                                let key = column.trim();
-                                let value: serde_json::Value = match line_vals_iter.next() {
-                                    Some(val) => match val.trim() {
-                                        "null" => serde_json::Value::Null,
-                                        "None" => serde_json::Value::Null,
-                                        val => {
-                                            let number_value =
-                                                if let Ok(int_val) = val.parse::<i64>() {
-                                                    serde_json::Number::from_f64(int_val as f64)
-                                                        .map(serde_json::Value::Number)
-                                                } else if let Ok(float_val) = val.parse::<f64>() {
-                                                    serde_json::Number::from_f64(float_val)
-                                                        .map(serde_json::Value::Number)
-                                                } else {
-                                                    None
-                                                };
-
-                                            let bool_value = match val.to_lowercase().as_str() {
-                                                "true" => Some(serde_json::Value::Bool(true)),
-                                                "false" => Some(serde_json::Value::Bool(false)),
-                                                _ => None,
-                                            };
-
-                                            if let Some(int_value) = number_value {
-                                                int_value
-                                            } else if let Some(bool_value) = bool_value {
-                                                bool_value
-                                            } else {
-                                                serde_json::Value::String(val.to_string())
-                                            }
-                                        }
-                                    },
-                                    None => serde_json::Value::Null,
-                                };
-                                new_object.insert(key.to_string(), value);
-                            }
-
-                            Some(serde_json::Value::Object(new_object))
-                        }
-                    }
-                };
-
-                if let Some(object) = object {
-                    let chunk_req_payload = convert_value_to_chunkreqpayload(
-                        object,
-                        Some(group_id),
-                        csv_jsonl_worker_message
-                            .create_presigned_put_url_data
-                            .mappings
-                            .clone()
-                            .map(|mappings| mappings.0.clone())
-                            .unwrap_or_default(),
-                        csv_jsonl_worker_message
-                            .create_presigned_put_url_data
-                            .fulltext_boost_factor,
-                        csv_jsonl_worker_message
-                            .create_presigned_put_url_data
-                            .semantic_boost_factor,
-                    );
-                    chunk_req_payloads.push(chunk_req_payload);
-
-                    if chunk_req_payloads.len() >= 120 {
-                        let (upsert_chunk_ingestion_message, upsert_chunk_metadatas) =
-                            create_chunk_metadata(
-                                chunk_req_payloads.clone(),
-                                csv_jsonl_worker_message.dataset_id,
-                            )?;
+    let mut chunk_batcher = ChunkBatcher::new(
+        &row_converter,
+        group_id,
+        csv_jsonl_worker_message.dataset_id,
+        broccoli_queue,
+    );
 
-                        if !upsert_chunk_metadatas.is_empty() {
-                            log::info!(
-                                "Pushing chunk ingestion message to redis {:?}",
-                                upsert_chunk_metadatas.len()
-                            );
-                            broccoli_queue
-                                .publish(
-                                    "ingestion",
-                                    Some(csv_jsonl_worker_message.dataset_id.to_string()),
-                                    &upsert_chunk_ingestion_message,
-                                    None,
-                                )
-                                .await
-                                .map_err(|err| ServiceError::BadRequest(err.to_string()))?;
-                        }
-                        chunk_req_payloads.clear();
-                    }
-                }
-                line.clear();
-            } else {
-                line.push_str(chunk_line);
-            }
+    let byte_count = match csv_jsonl_worker_message.file_format {
+        CsvJsonlFileFormat::Csv | CsvJsonlFileFormat::Jsonl => {
+            process_csv_jsonl_lines(
+                &bucket,
+                csv_jsonl_worker_message.file_id,
+                &mut chunk_batcher,
+            )
+            .await?
         }
-    }
-
-    if !chunk_req_payloads.is_empty() {
-        let (upsert_chunk_ingestion_message, upsert_chunk_metadatas) = create_chunk_metadata(
-            chunk_req_payloads.clone(),
-            csv_jsonl_worker_message.dataset_id,
-        )?;
+        CsvJsonlFileFormat::Parquet => {
+            process_parquet_file(
+                &bucket,
+                csv_jsonl_worker_message.file_id,
+                &mut chunk_batcher,
+            )
+            .await?
+        }
+        CsvJsonlFileFormat::Xlsx | CsvJsonlFileFormat::Ods => {
+            process_spreadsheet_file(
+                &bucket,
+                csv_jsonl_worker_message.file_id,
+                &csv_jsonl_worker_message
+                    .create_presigned_put_url_data
+                    .spreadsheet_options
+                    .clone()
+                    .unwrap_or_default(),
+                &mut chunk_batcher,
+            )
+            .await?
+        }
+    };
+    chunk_batcher.flush().await?;
 
-        if !upsert_chunk_metadatas.is_empty() {
-            log::info!(
-                "Pushing chunk ingestion message to redis after while {:?}",
-                upsert_chunk_metadatas.len()
-            );
-            broccoli_queue
-                .publish(
-                    "ingestion",
-                    Some(csv_jsonl_worker_message.dataset_id.to_string()),
-                    &upsert_chunk_ingestion_message,
+    if chunk_batcher.total_failed_rows > 0 {
+        event_queue
+            .send(ClickHouseEvent::WorkerEvent(
+                models::WorkerEvent::from_details(
+                    csv_jsonl_worker_message.dataset_id,
                     None,
+                    models::EventType::CsvJsonlRowsFailed {
+                        file_id: csv_jsonl_worker_message.file_id,
+                        total_failed_rows: chunk_batcher.total_failed_rows,
+                        failed_rows: chunk_batcher.failed_rows.clone(),
+                    },
                 )
-                .await
-                .map_err(|err| ServiceError::BadRequest(err.to_string()))?;
-        }
+                .into(),
+            ))
+            .await;
     }
 
+    event_queue
+        .send(ClickHouseEvent::WorkerEvent(
+            models::WorkerEvent::from_details(
+                csv_jsonl_worker_message.dataset_id,
+                None,
+                models::EventType::CsvJsonlProcessingCompleted {
+                    file_id: csv_jsonl_worker_message.file_id,
+                    chunks_created: chunk_batcher.chunks_created,
+                },
+            )
+            .into(),
+        ))
+        .await;
+
     let file_size_mb = (byte_count as f64 / 1024.0 / 1024.0).round() as i64;
     let created_file = create_file_query(
         csv_jsonl_worker_message.file_id,
@@ -544,6 +779,10 @@ async fn process_csv_jsonl_file(
             base64_file: "".to_string(),
             chunkr_create_task_req_payload: None,
             webhook_url: None,
+            chunking_strategy: None,
+            spreadsheet_chunking: None,
+            target_file_id: None,
+            file_tracking_id: None,
         },
         csv_jsonl_worker_message.dataset_id,
         web_pool.clone(),
@@ -559,255 +798,3 @@ async fn process_csv_jsonl_file(
 
     Ok(None)
 }
-
-fn parse_csv_line(line: &str) -> Vec<String> {
-    let mut fields = Vec::new();
-    let mut current_field = String::new();
-    let mut in_quotes = false;
-    let mut chars = line.chars().peekable();
-
-    while let Some(c) = chars.next() {
-        match c {
-            '"' => {
-                if in_quotes {
-                    if chars.peek() == Some(&'"') {
-                        current_field.push('"');
-                        chars.next();
-                    } else {
-                        in_quotes = false;
-                    }
-                } else {
-                    in_quotes = true;
-                }
-            }
-            ',' => {
-                if in_quotes {
-                    current_field.push(',');
-                } else {
-                    fields.push(current_field.clone());
-                    current_field.clear();
-                }
-            }
-            _ => {
-                current_field.push(c);
-            }
-        }
-    }
-
-    fields.push(current_field);
-
-    fields
-}
-
-fn convert_value_to_chunkreqpayload(
-    value: serde_json::Value,
-    group_id: Option<uuid::Uuid>,
-    mappings: Vec<ChunkReqPayloadMapping>,
-    fulltext_boost_factor: Option<f64>,
-    semantic_boost_factor: Option<f64>,
-) -> ChunkReqPayload {
-    let cleaned_value = match value.clone() {
-        serde_json::Value::Object(obj) => {
-            let mut new_obj = serde_json::Map::new();
-            for (key, value) in obj {
-                if value.is_null() {
-                    continue;
-                }
-                new_obj.insert(key, value);
-            }
-            serde_json::Value::Object(new_obj)
-        }
-        _ => value.clone(),
-    };
-    let chunk_html = serde_json::to_string(&cleaned_value).ok();
-    let mut chunk_req_payload = ChunkReqPayload {
-        chunk_html,
-        semantic_content: None,
-        fulltext_content: None,
-        link: None,
-        tag_set: None,
-        num_value: None,
-        metadata: Some(value.clone()),
-        tracking_id: None,
-        upsert_by_tracking_id: Some(true),
-        group_ids: Some(group_id.into_iter().collect()),
-        group_tracking_ids: None,
-        time_stamp: None,
-        location: None,
-        image_urls: None,
-        weight: None,
-        split_avg: None,
-        convert_html_to_text: None,
-        fulltext_boost: None,
-        semantic_boost: None,
-        high_priority: None,
-    };
-
-    let mut boost_phrase = String::new();
-    let mut lat: Option<GeoTypes> = None;
-    let mut lon: Option<GeoTypes> = None;
-
-    for mapping in mappings {
-        match mapping.chunk_req_payload_field {
-            ChunkReqPayloadFields::Link => {
-                let _ = value
-                    .get(mapping.csv_jsonl_field)
-                    .and_then(|val| val.as_str())
-                    .map(|val| val.to_string())
-                    .map(|val| chunk_req_payload.link = Some(val));
-            }
-            ChunkReqPayloadFields::TagSet => {
-                let mut cur_tag_set = chunk_req_payload.tag_set.clone().unwrap_or_default();
-                if let Some(val) = value.get(mapping.csv_jsonl_field) {
-                    if let Some(arr) = val.as_array() {
-                        for tag in arr {
-                            if let Some(tag) = tag.as_str() {
-                                cur_tag_set.push(tag.to_string());
-                            }
-                        }
-                    } else if let Some(arr) = get_array_from_string(val.as_str().unwrap_or("")) {
-                        for tag in arr {
-                            cur_tag_set.push(tag);
-                        }
-                    } else if let Some(val) = val.as_str() {
-                        cur_tag_set.push(val.to_string());
-                    }
-                }
-
-                chunk_req_payload.tag_set = Some(cur_tag_set);
-            }
-            ChunkReqPayloadFields::NumValue => {
-                let _ = value
-                    .get(mapping.csv_jsonl_field)
-                    .and_then(|val| val.as_f64())
-                    .map(|val| chunk_req_payload.num_value = Some(val));
-            }
-            ChunkReqPayloadFields::TrackingId => {
-                let _ = value
-                    .get(mapping.csv_jsonl_field)
-                    .map(|val| chunk_req_payload.tracking_id = Some(val.to_string()));
-            }
-            ChunkReqPayloadFields::GroupTrackingIds => {
-                let mut cur_group_tracking_ids = chunk_req_payload
-                    .group_tracking_ids
-                    .clone()
-                    .unwrap_or_default();
-                if let Some(val) = value.get(mapping.csv_jsonl_field) {
-                    if let Some(arr) = val.as_array() {
-                        for url in arr {
-                            if let Some(url) = url.as_str() {
-                                cur_group_tracking_ids.push(url.to_string());
-                            }
-                        }
-                    } else if let Some(arr) = get_array_from_string(val.as_str().unwrap_or("")) {
-                        for url in arr {
-                            cur_group_tracking_ids.push(url);
-                        }
-                    } else if let Some(val) = val.as_str() {
-                        cur_group_tracking_ids.push(val.to_string());
-                    }
-                }
-
-                chunk_req_payload.group_tracking_ids = Some(cur_group_tracking_ids);
-            }
-            ChunkReqPayloadFields::TimeStamp => {
-                if let Some(val) = value.get(mapping.csv_jsonl_field) {
-                    match val {
-                        serde_json::Value::String(val) => {
-                            let _ = val
-                                .parse::<NaiveDateTime>()
-                                .map(|val| chunk_req_payload.time_stamp = Some(val.to_string()));
-                        }
-                        serde_json::Value::Number(val) => {
-                            let _ = val.as_i64().map(|val| {
-                                chunk_req_payload.time_stamp =
-                                    DateTime::from_timestamp(val, 0).map(|val| {
-                                        val.with_timezone(&chrono::Local).naive_local().to_string()
-                                    })
-                            });
-                        }
-                        _ => {}
-                    }
-                }
-            }
-            ChunkReqPayloadFields::Lat => {
-                let _ = value
-                    .get(mapping.csv_jsonl_field)
-                    .and_then(|val| val.as_f64())
-                    .map(|val| lat = Some(GeoTypes::Float(val)));
-            }
-            ChunkReqPayloadFields::Lon => {
-                let _ = value
-                    .get(mapping.csv_jsonl_field)
-                    .and_then(|val| val.as_f64())
-                    .map(|val| lon = Some(GeoTypes::Float(val)));
-            }
-            ChunkReqPayloadFields::ImageUrls => {
-                let mut cur_image_urls = chunk_req_payload.image_urls.clone().unwrap_or_default();
-                if let Some(val) = value.get(mapping.csv_jsonl_field) {
-                    if let Some(arr) = val.as_array() {
-                        for url in arr {
-                            if let Some(url) = url.as_str() {
-                                cur_image_urls.push(url.to_string());
-                            }
-                        }
-                    } else if let Some(arr) = get_array_from_string(val.as_str().unwrap_or("")) {
-                        for url in arr {
-                            cur_image_urls.push(url);
-                        }
-                    } else if let Some(val) = val.as_str() {
-                        cur_image_urls.push(val.to_string());
-                    }
-                }
-
-                chunk_req_payload.image_urls = Some(cur_image_urls);
-            }
-            ChunkReqPayloadFields::Weight => {
-                let _ = value
-                    .get(mapping.csv_jsonl_field)
-                    .and_then(|val| val.as_f64())
-                    .map(|val| chunk_req_payload.weight = Some(val));
-            }
-            ChunkReqPayloadFields::BoostPhrase => {
-                let mut cur_boost_phrase = boost_phrase.clone();
-                if let Some(val) = value.get(mapping.csv_jsonl_field) {
-                    if let Some(val) = val.as_str() {
-                        cur_boost_phrase.push_str(format!(" {}", val).as_str());
-                    }
-                }
-
-                boost_phrase = cur_boost_phrase;
-            }
-        }
-    }
-
-    if let Some(fulltext_boost_factor) = fulltext_boost_factor {
-        chunk_req_payload.fulltext_boost = Some(FullTextBoost {
-            phrase: boost_phrase.clone(),
-            boost_factor: fulltext_boost_factor,
-        });
-    }
-    if let Some(semantic_boost_factor) = semantic_boost_factor {
-        chunk_req_payload.semantic_boost = Some(SemanticBoost {
-            phrase: boost_phrase,
-            distance_factor: semantic_boost_factor as f32,
-        });
-    }
-    if let Some(lat) = lat {
-        if let Some(lon) = lon {
-            chunk_req_payload.location = Some(GeoInfo { lat, lon });
-        }
-    }
-
-    chunk_req_payload
-}
-
-fn get_array_from_string(string: &str) -> Option<Vec<String>> {
-    if string.starts_with('[') && string.ends_with(']') {
-        let line = &string[1..string.len() - 1];
-        let values = parse_csv_line(line);
-        Some(values)
-    } else {
-        None
-    }
-}
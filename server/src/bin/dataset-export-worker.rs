@@ -0,0 +1,243 @@
+use actix_web::web;
+use broccoli_queue::{error::BroccoliError, queue::BroccoliQueue};
+use diesel_async::pooled_connection::{AsyncDieselConnectionManager, ManagerConfig};
+use signal_hook::consts::SIGTERM;
+use std::error::Error;
+use std::sync::atomic::AtomicBool;
+use std::sync::Arc;
+use trieve_server::{
+    data::models::{
+        DatasetExport, DatasetExportMessage, DatasetExportStatus, EventType, Pool, UnifiedId,
+        WorkerEvent,
+    },
+    errors::ServiceError,
+    establish_connection, get_env,
+    operators::{
+        clickhouse_operator::{ClickHouseEvent, EventQueue},
+        dataset_operator::get_dataset_and_organization_from_dataset_id_query,
+        export_operator::{
+            get_dataset_export_query, get_dataset_export_s3_key,
+            update_dataset_export_status_query, write_dataset_export,
+        },
+        file_operator::get_aws_bucket,
+    },
+};
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn Error>> {
+    dotenvy::dotenv().ok();
+    env_logger::builder()
+        .target(env_logger::Target::Stdout)
+        .filter_level(log::LevelFilter::Info)
+        .init();
+
+    let database_url = get_env!("DATABASE_URL", "DATABASE_URL is not set");
+
+    let mut config = ManagerConfig::default();
+    config.custom_setup = Box::new(establish_connection);
+
+    let mgr = AsyncDieselConnectionManager::<diesel_async::AsyncPgConnection>::new_with_config(
+        database_url,
+        config,
+    );
+
+    let pool = diesel_async::pooled_connection::deadpool::Pool::builder(mgr)
+        .max_size(3)
+        .build()
+        .expect("Failed to create diesel_async pool");
+
+    let web_pool = actix_web::web::Data::new(pool.clone());
+
+    let redis_url = get_env!("REDIS_URL", "REDIS_URL is not set");
+    let redis_connections: u32 = std::env::var("REDIS_CONNECTIONS")
+        .unwrap_or("2".to_string())
+        .parse()
+        .unwrap_or(2);
+
+    let mut event_queue = if std::env::var("USE_ANALYTICS")
+        .unwrap_or("false".to_string())
+        .parse()
+        .unwrap_or(false)
+    {
+        log::info!("Analytics enabled");
+
+        let clickhouse_client = clickhouse::Client::default()
+            .with_url(
+                std::env::var("CLICKHOUSE_URL").unwrap_or("http://localhost:8123".to_string()),
+            )
+            .with_user(std::env::var("CLICKHOUSE_USER").unwrap_or("default".to_string()))
+            .with_password(std::env::var("CLICKHOUSE_PASSWORD").unwrap_or("".to_string()))
+            .with_database(std::env::var("CLICKHOUSE_DATABASE").unwrap_or("default".to_string()))
+            .with_option("async_insert", "1")
+            .with_option("wait_for_async_insert", "0");
+
+        let mut event_queue = EventQueue::new(clickhouse_client.clone());
+        event_queue.start_service();
+        event_queue
+    } else {
+        log::info!("Analytics disabled");
+        EventQueue::default()
+    };
+    event_queue.start_webhook_service();
+
+    let web_event_queue = web::Data::new(event_queue);
+
+    let should_terminate = Arc::new(AtomicBool::new(false));
+    signal_hook::flag::register(SIGTERM, Arc::clone(&should_terminate))
+        .expect("Failed to register shutdown hook");
+
+    let broccoli_queue = BroccoliQueue::builder(redis_url)
+        .pool_connections(redis_connections.try_into().unwrap())
+        .failed_message_retry_strategy(Default::default())
+        .build()
+        .await
+        .expect("Failed to create broccoli queue");
+
+    log::info!("Starting dataset export worker");
+
+    broccoli_queue
+        .process_messages("dataset_export", None, None, move |msg| {
+            dataset_export_worker(msg.payload, web_pool.clone(), web_event_queue.clone())
+        })
+        .await?;
+
+    Ok(())
+}
+
+async fn dataset_export_worker(
+    msg: DatasetExportMessage,
+    web_pool: web::Data<Pool>,
+    event_queue: web::Data<EventQueue>,
+) -> Result<(), BroccoliError> {
+    log::info!(
+        "Exporting dataset {:?} as {:?}",
+        msg.dataset_id,
+        msg.export_id
+    );
+
+    let dataset_export = get_dataset_export_query(msg.export_id, msg.dataset_id, web_pool.clone())
+        .await
+        .map_err(|e| BroccoliError::Job(e.to_string()))?;
+
+    update_dataset_export_status_query(
+        dataset_export.id,
+        DatasetExportStatus::Processing,
+        None,
+        None,
+        web_pool.clone(),
+    )
+    .await
+    .map_err(|e| BroccoliError::Job(e.to_string()))?;
+
+    match export_dataset(&dataset_export, web_pool.clone()).await {
+        Ok((s3_key, chunks_exported)) => {
+            update_dataset_export_status_query(
+                dataset_export.id,
+                DatasetExportStatus::Completed,
+                Some(s3_key),
+                None,
+                web_pool.clone(),
+            )
+            .await
+            .map_err(|e| BroccoliError::Job(e.to_string()))?;
+
+            log::info!(
+                "Exported {} chunks for export {:?}",
+                chunks_exported,
+                dataset_export.id
+            );
+
+            event_queue
+                .send(ClickHouseEvent::WorkerEvent(
+                    WorkerEvent::from_details(
+                        dataset_export.dataset_id,
+                        None,
+                        EventType::DatasetExportCompleted {
+                            export_id: dataset_export.id,
+                            chunks_exported,
+                        },
+                    )
+                    .into(),
+                ))
+                .await;
+        }
+        Err(err) => {
+            log::error!("Failed to export dataset: {:?}", err);
+
+            update_dataset_export_status_query(
+                dataset_export.id,
+                DatasetExportStatus::Failed,
+                None,
+                Some(err.to_string()),
+                web_pool.clone(),
+            )
+            .await
+            .map_err(|e| BroccoliError::Job(e.to_string()))?;
+
+            event_queue
+                .send(ClickHouseEvent::WorkerEvent(
+                    WorkerEvent::from_details(
+                        dataset_export.dataset_id,
+                        None,
+                        EventType::DatasetExportFailed {
+                            export_id: dataset_export.id,
+                            error: err.to_string(),
+                        },
+                    )
+                    .into(),
+                ))
+                .await;
+        }
+    }
+
+    Ok(())
+}
+
+async fn export_dataset(
+    dataset_export: &DatasetExport,
+    web_pool: web::Data<Pool>,
+) -> Result<(String, i64), ServiceError> {
+    let dataset_org_plan_sub = get_dataset_and_organization_from_dataset_id_query(
+        UnifiedId::TrieveUuid(dataset_export.dataset_id),
+        None,
+        web_pool.clone(),
+    )
+    .await?;
+
+    let s3_key = get_dataset_export_s3_key(dataset_export);
+    let temp_file_path = std::env::temp_dir().join(s3_key.replace('/', "_"));
+
+    let export_result = async {
+        let chunks_exported = write_dataset_export(
+            dataset_export,
+            &dataset_org_plan_sub,
+            &temp_file_path,
+            web_pool.clone(),
+        )
+        .await?;
+
+        let mut export_file = tokio::fs::File::open(&temp_file_path)
+            .await
+            .map_err(|err| {
+                log::error!("Failed to open export file: {:?}", err);
+                ServiceError::InternalServerError("Failed to open export file".to_string())
+            })?;
+
+        get_aws_bucket()?
+            .put_object_stream(&mut export_file, &s3_key)
+            .await
+            .map_err(|err| {
+                log::error!("Failed to upload export to s3: {:?}", err);
+                ServiceError::InternalServerError("Failed to upload export to s3".to_string())
+            })?;
+
+        Ok(chunks_exported)
+    }
+    .await;
+
+    if let Err(err) = tokio::fs::remove_file(&temp_file_path).await {
+        log::error!("Failed to remove export file: {:?}", err);
+    }
+
+    export_result.map(|chunks_exported| (s3_key, chunks_exported))
+}
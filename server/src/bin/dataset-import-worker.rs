@@ -0,0 +1,552 @@
+use actix_web::web;
+use broccoli_queue::{error::BroccoliError, queue::BroccoliQueue};
+use diesel_async::pooled_connection::{AsyncDieselConnectionManager, ManagerConfig};
+use signal_hook::consts::SIGTERM;
+use std::collections::HashMap;
+use std::error::Error;
+use std::path::Path;
+use std::sync::atomic::AtomicBool;
+use std::sync::Arc;
+use tokio::io::AsyncWriteExt;
+use tokio_stream::StreamExt;
+use trieve_server::{
+    data::models::{
+        ChunkGroup, ChunkGroupAndFileId, DatasetAndOrgWithSubAndPlan, DatasetConfiguration,
+        DatasetExportFormat, DatasetExportRecord, DatasetImport, DatasetImportMessage,
+        DatasetImportStatus, EventType, ExportedChunk, Pool, UnifiedId, WorkerEvent,
+    },
+    errors::ServiceError,
+    establish_connection, get_env,
+    handlers::{
+        auth_handler::AdminOnly,
+        chunk_handler::{create_chunk, CreateBatchChunkReqPayload, CreateChunkReqPayloadEnum},
+    },
+    operators::{
+        clickhouse_operator::{ClickHouseEvent, EventQueue},
+        dataset_operator::get_dataset_and_organization_from_dataset_id_query,
+        export_operator::get_dataset_export_for_organization_query,
+        file_operator::get_aws_bucket,
+        import_operator::{
+            can_reuse_exported_vectors, check_import_chunk_limit_query,
+            exported_chunk_to_chunk_req_payload, get_dataset_import_query,
+            import_chunks_with_vectors_query, import_file_query, import_groups_query,
+            open_dataset_export_records, read_dataset_export_record_batch, resolve_import_url,
+            update_dataset_import_progress_query, update_dataset_import_status_query,
+        },
+    },
+};
+
+const GROUP_BATCH_SIZE: usize = 500;
+const CHUNK_BATCH_SIZE: usize = 120;
+const RECORD_BATCH_SIZE: usize = 500;
+const DEFAULT_MAX_IMPORT_ARCHIVE_BYTES: u64 = 10 * 1024 * 1024 * 1024;
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn Error>> {
+    dotenvy::dotenv().ok();
+    env_logger::builder()
+        .target(env_logger::Target::Stdout)
+        .filter_level(log::LevelFilter::Info)
+        .init();
+
+    let database_url = get_env!("DATABASE_URL", "DATABASE_URL is not set");
+
+    let mut config = ManagerConfig::default();
+    config.custom_setup = Box::new(establish_connection);
+
+    let mgr = AsyncDieselConnectionManager::<diesel_async::AsyncPgConnection>::new_with_config(
+        database_url,
+        config,
+    );
+
+    let pool = diesel_async::pooled_connection::deadpool::Pool::builder(mgr)
+        .max_size(3)
+        .build()
+        .expect("Failed to create diesel_async pool");
+
+    let web_pool = actix_web::web::Data::new(pool.clone());
+
+    let redis_url = get_env!("REDIS_URL", "REDIS_URL is not set");
+    let redis_connections: u32 = std::env::var("REDIS_CONNECTIONS")
+        .unwrap_or("2".to_string())
+        .parse()
+        .unwrap_or(2);
+
+    let mut event_queue = if std::env::var("USE_ANALYTICS")
+        .unwrap_or("false".to_string())
+        .parse()
+        .unwrap_or(false)
+    {
+        log::info!("Analytics enabled");
+
+        let clickhouse_client = clickhouse::Client::default()
+            .with_url(
+                std::env::var("CLICKHOUSE_URL").unwrap_or("http://localhost:8123".to_string()),
+            )
+            .with_user(std::env::var("CLICKHOUSE_USER").unwrap_or("default".to_string()))
+            .with_password(std::env::var("CLICKHOUSE_PASSWORD").unwrap_or("".to_string()))
+            .with_database(std::env::var("CLICKHOUSE_DATABASE").unwrap_or("default".to_string()))
+            .with_option("async_insert", "1")
+            .with_option("wait_for_async_insert", "0");
+
+        let mut event_queue = EventQueue::new(clickhouse_client.clone());
+        event_queue.start_service();
+        event_queue
+    } else {
+        log::info!("Analytics disabled");
+        EventQueue::default()
+    };
+    event_queue.start_webhook_service();
+
+    let web_event_queue = web::Data::new(event_queue);
+
+    let should_terminate = Arc::new(AtomicBool::new(false));
+    signal_hook::flag::register(SIGTERM, Arc::clone(&should_terminate))
+        .expect("Failed to register shutdown hook");
+
+    let broccoli_queue = BroccoliQueue::builder(redis_url)
+        .pool_connections(redis_connections.try_into().unwrap())
+        .failed_message_retry_strategy(Default::default())
+        .build()
+        .await
+        .expect("Failed to create broccoli queue");
+
+    let web_broccoli_queue = web::Data::new(broccoli_queue.clone());
+
+    log::info!("Starting dataset import worker");
+
+    broccoli_queue
+        .process_messages("dataset_import", None, None, move |msg| {
+            dataset_import_worker(
+                msg.payload,
+                web_pool.clone(),
+                web_broccoli_queue.clone(),
+                web_event_queue.clone(),
+            )
+        })
+        .await?;
+
+    Ok(())
+}
+
+async fn dataset_import_worker(
+    msg: DatasetImportMessage,
+    web_pool: web::Data<Pool>,
+    broccoli_queue: web::Data<BroccoliQueue>,
+    event_queue: web::Data<EventQueue>,
+) -> Result<(), BroccoliError> {
+    log::info!(
+        "Importing into dataset {:?} for import {:?}",
+        msg.dataset_id,
+        msg.import_id
+    );
+
+    let mut dataset_import =
+        get_dataset_import_query(msg.import_id, msg.dataset_id, web_pool.clone())
+            .await
+            .map_err(|e| BroccoliError::Job(e.to_string()))?;
+
+    update_dataset_import_status_query(
+        dataset_import.id,
+        DatasetImportStatus::Processing,
+        None,
+        web_pool.clone(),
+    )
+    .await
+    .map_err(|e| BroccoliError::Job(e.to_string()))?;
+
+    let import_result = import_dataset(
+        &mut dataset_import,
+        web_pool.clone(),
+        broccoli_queue.clone(),
+    )
+    .await;
+
+    let (status, error, event_type) = match import_result {
+        Ok(()) => (
+            DatasetImportStatus::Completed,
+            None,
+            EventType::DatasetImportCompleted {
+                import_id: dataset_import.id,
+                vectors_reused: dataset_import.vectors_reused.unwrap_or(false),
+                groups_imported: dataset_import.groups_imported,
+                files_imported: dataset_import.files_imported,
+                chunks_imported: dataset_import.chunks_imported,
+            },
+        ),
+        Err(err) => {
+            log::error!("Failed to import dataset: {:?}", err);
+            (
+                DatasetImportStatus::Failed,
+                Some(err.to_string()),
+                EventType::DatasetImportFailed {
+                    import_id: dataset_import.id,
+                    error: err.to_string(),
+                },
+            )
+        }
+    };
+
+    update_dataset_import_status_query(dataset_import.id, status, error, web_pool.clone())
+        .await
+        .map_err(|e| BroccoliError::Job(e.to_string()))?;
+
+    event_queue
+        .send(ClickHouseEvent::WorkerEvent(
+            WorkerEvent::from_details(dataset_import.dataset_id, None, event_type).into(),
+        ))
+        .await;
+
+    Ok(())
+}
+
+async fn download_import_archive(
+    dataset_import: &DatasetImport,
+    dataset_org_plan_sub: &DatasetAndOrgWithSubAndPlan,
+    path: &Path,
+    web_pool: web::Data<Pool>,
+) -> Result<(), ServiceError> {
+    let mut temp_file = tokio::fs::File::create(path).await.map_err(|err| {
+        log::error!("Failed to create import file: {:?}", err);
+        ServiceError::InternalServerError("Failed to create import file".to_string())
+    })?;
+
+    if let Some(export_id) = dataset_import.export_id {
+        let dataset_export = get_dataset_export_for_organization_query(
+            export_id,
+            dataset_org_plan_sub.organization.organization.id,
+            web_pool,
+        )
+        .await?;
+        let s3_key = dataset_export.s3_key.ok_or(ServiceError::BadRequest(
+            "Export has not completed yet".to_string(),
+        ))?;
+
+        let mut response_data_stream =
+            get_aws_bucket()?
+                .get_object_stream(s3_key)
+                .await
+                .map_err(|err| {
+                    log::error!("Failed to get export object stream: {:?}", err);
+                    ServiceError::InternalServerError(
+                        "Failed to get export object stream".to_string(),
+                    )
+                })?;
+
+        while let Some(chunk) = response_data_stream.bytes().next().await {
+            let chunk_bytes = chunk.map_err(|err| {
+                log::error!("Failed to get chunk from stream: {:?}", err);
+                ServiceError::InternalServerError("Failed to get chunk from stream".to_string())
+            })?;
+            temp_file.write_all(&chunk_bytes).await.map_err(|err| {
+                log::error!("Failed to write to import file: {:?}", err);
+                ServiceError::InternalServerError("Failed to write to import file".to_string())
+            })?;
+        }
+    } else if let Some(source_url) = dataset_import.source_url.as_ref() {
+        let max_archive_bytes = std::env::var("MAX_IMPORT_ARCHIVE_BYTES")
+            .ok()
+            .and_then(|max_bytes| max_bytes.parse::<u64>().ok())
+            .unwrap_or(DEFAULT_MAX_IMPORT_ARCHIVE_BYTES);
+
+        // The url is re-validated here and its addresses pinned so a DNS change after the import was created cannot point it at an internal service
+        let (parsed_url, addrs) = resolve_import_url(source_url).await?;
+        let mut client_builder =
+            reqwest::Client::builder().redirect(reqwest::redirect::Policy::none());
+        if let Some(domain) = parsed_url.domain() {
+            client_builder = client_builder.resolve_to_addrs(domain, &addrs);
+        }
+        let client = client_builder.build().map_err(|err| {
+            log::error!("Failed to build import download client: {:?}", err);
+            ServiceError::InternalServerError("Failed to download import archive".to_string())
+        })?;
+
+        let mut response = client
+            .get(parsed_url)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(|err| {
+                ServiceError::BadRequest(format!("Failed to download import archive: {}", err))
+            })?;
+
+        if response.status().is_redirection() {
+            return Err(ServiceError::BadRequest(
+                "Import url must not redirect".to_string(),
+            ));
+        }
+        if response
+            .content_length()
+            .is_some_and(|content_length| content_length > max_archive_bytes)
+        {
+            return Err(ServiceError::BadRequest(format!(
+                "Import archive is larger than {} bytes",
+                max_archive_bytes
+            )));
+        }
+
+        let mut downloaded_bytes: u64 = 0;
+        while let Some(chunk_bytes) = response.chunk().await.map_err(|err| {
+            ServiceError::BadRequest(format!("Failed to download import archive: {}", err))
+        })? {
+            downloaded_bytes += chunk_bytes.len() as u64;
+            if downloaded_bytes > max_archive_bytes {
+                return Err(ServiceError::BadRequest(format!(
+                    "Import archive is larger than {} bytes",
+                    max_archive_bytes
+                )));
+            }
+            temp_file.write_all(&chunk_bytes).await.map_err(|err| {
+                log::error!("Failed to write to import file: {:?}", err);
+                ServiceError::InternalServerError("Failed to write to import file".to_string())
+            })?;
+        }
+    } else {
+        return Err(ServiceError::BadRequest(
+            "Import has neither an export_id nor a source_url".to_string(),
+        ));
+    }
+
+    temp_file.flush().await.map_err(|err| {
+        log::error!("Failed to flush import file: {:?}", err);
+        ServiceError::InternalServerError("Failed to write to import file".to_string())
+    })
+}
+
+async fn import_dataset(
+    dataset_import: &mut DatasetImport,
+    web_pool: web::Data<Pool>,
+    broccoli_queue: web::Data<BroccoliQueue>,
+) -> Result<(), ServiceError> {
+    let dataset_org_plan_sub = get_dataset_and_organization_from_dataset_id_query(
+        UnifiedId::TrieveUuid(dataset_import.dataset_id),
+        None,
+        web_pool.clone(),
+    )
+    .await?;
+
+    let temp_file_path = std::env::temp_dir().join(format!("dataset-import-{}", dataset_import.id));
+
+    let import_result = async {
+        download_import_archive(
+            dataset_import,
+            &dataset_org_plan_sub,
+            &temp_file_path,
+            web_pool.clone(),
+        )
+        .await?;
+
+        import_records(
+            dataset_import,
+            &dataset_org_plan_sub,
+            &temp_file_path,
+            web_pool.clone(),
+            broccoli_queue.clone(),
+        )
+        .await
+    }
+    .await;
+
+    if let Err(err) = tokio::fs::remove_file(&temp_file_path).await {
+        log::error!("Failed to remove import file: {:?}", err);
+    }
+
+    import_result
+}
+
+struct ImportState {
+    target_config: DatasetConfiguration,
+    reuse_vectors: Option<bool>,
+    groups: HashMap<uuid::Uuid, ChunkGroup>,
+    group_ids_by_file_id: HashMap<uuid::Uuid, uuid::Uuid>,
+    pending_groups: Vec<ChunkGroupAndFileId>,
+    pending_chunks: Vec<ExportedChunk>,
+}
+
+async fn import_records(
+    dataset_import: &mut DatasetImport,
+    dataset_org_plan_sub: &DatasetAndOrgWithSubAndPlan,
+    path: &Path,
+    web_pool: web::Data<Pool>,
+    broccoli_queue: web::Data<BroccoliQueue>,
+) -> Result<(), ServiceError> {
+    let dataset_id = dataset_org_plan_sub.dataset.id;
+    let bucket = get_aws_bucket()?;
+    let mut state = ImportState {
+        target_config: DatasetConfiguration::from_json(
+            dataset_org_plan_sub.dataset.server_configuration.clone(),
+        ),
+        reuse_vectors: None,
+        groups: HashMap::new(),
+        group_ids_by_file_id: HashMap::new(),
+        pending_groups: vec![],
+        pending_chunks: vec![],
+    };
+
+    let mut records = open_dataset_export_records(
+        path.to_path_buf(),
+        DatasetExportFormat::from(dataset_import.format.clone()),
+    )
+    .await?;
+
+    loop {
+        let (next_records, batch) =
+            read_dataset_export_record_batch(records, RECORD_BATCH_SIZE).await?;
+        records = next_records;
+        if batch.is_empty() {
+            break;
+        }
+
+        for record in batch {
+            match record? {
+                DatasetExportRecord::Dataset {
+                    server_configuration,
+                    ..
+                } => {
+                    let source_config = DatasetConfiguration::from(server_configuration);
+                    let reuse_vectors =
+                        can_reuse_exported_vectors(&source_config, &state.target_config);
+                    log::info!(
+                        "Import {:?} will {} the exported vectors",
+                        dataset_import.id,
+                        if reuse_vectors { "reuse" } else { "re-embed" }
+                    );
+                    state.reuse_vectors = Some(reuse_vectors);
+                }
+                DatasetExportRecord::Group(group) => {
+                    state.pending_groups.push(group);
+                    if state.pending_groups.len() >= GROUP_BATCH_SIZE {
+                        flush_groups(dataset_import, &mut state, dataset_id, web_pool.clone())
+                            .await?;
+                    }
+                }
+                DatasetExportRecord::File(file) => {
+                    flush_groups(dataset_import, &mut state, dataset_id, web_pool.clone()).await?;
+
+                    let group_id = state.group_ids_by_file_id.get(&file.id).copied();
+                    if import_file_query(
+                        file,
+                        dataset_id,
+                        dataset_org_plan_sub.organization.organization.id,
+                        group_id,
+                        &bucket,
+                        web_pool.clone(),
+                    )
+                    .await?
+                    {
+                        dataset_import.files_imported += 1;
+                    }
+                }
+                DatasetExportRecord::Chunk(chunk) => {
+                    flush_groups(dataset_import, &mut state, dataset_id, web_pool.clone()).await?;
+
+                    state.pending_chunks.push(*chunk);
+                    if state.pending_chunks.len() >= CHUNK_BATCH_SIZE {
+                        flush_chunks(
+                            dataset_import,
+                            &mut state,
+                            dataset_org_plan_sub,
+                            web_pool.clone(),
+                            broccoli_queue.clone(),
+                        )
+                        .await?;
+                    }
+                }
+            }
+        }
+    }
+
+    flush_groups(dataset_import, &mut state, dataset_id, web_pool.clone()).await?;
+    flush_chunks(
+        dataset_import,
+        &mut state,
+        dataset_org_plan_sub,
+        web_pool.clone(),
+        broccoli_queue,
+    )
+    .await?;
+
+    dataset_import.vectors_reused = Some(state.reuse_vectors.unwrap_or(false));
+    update_dataset_import_progress_query(dataset_import, web_pool).await
+}
+
+async fn flush_groups(
+    dataset_import: &mut DatasetImport,
+    state: &mut ImportState,
+    dataset_id: uuid::Uuid,
+    web_pool: web::Data<Pool>,
+) -> Result<(), ServiceError> {
+    if state.pending_groups.is_empty() {
+        return Ok(());
+    }
+
+    let groups = std::mem::take(&mut state.pending_groups);
+    let file_ids_by_group_id: HashMap<uuid::Uuid, uuid::Uuid> = groups
+        .iter()
+        .filter_map(|group| Some((group.id, group.file_id?)))
+        .collect();
+
+    let imported_groups = import_groups_query(groups, dataset_id, web_pool.clone()).await?;
+    for (exported_group_id, group) in imported_groups {
+        if let Some(file_id) = file_ids_by_group_id.get(&exported_group_id) {
+            state.group_ids_by_file_id.insert(*file_id, group.id);
+        }
+        state.groups.insert(exported_group_id, group);
+        dataset_import.groups_imported += 1;
+    }
+
+    update_dataset_import_progress_query(dataset_import, web_pool).await
+}
+
+async fn flush_chunks(
+    dataset_import: &mut DatasetImport,
+    state: &mut ImportState,
+    dataset_org_plan_sub: &DatasetAndOrgWithSubAndPlan,
+    web_pool: web::Data<Pool>,
+    broccoli_queue: web::Data<BroccoliQueue>,
+) -> Result<(), ServiceError> {
+    if state.pending_chunks.is_empty() {
+        return Ok(());
+    }
+
+    let chunks = std::mem::take(&mut state.pending_chunks);
+    // Archives without a dataset record carry no embedding configuration to compare against
+    let reuse_vectors = *state.reuse_vectors.get_or_insert(false);
+
+    check_import_chunk_limit_query(dataset_org_plan_sub, chunks.len(), web_pool.clone()).await?;
+
+    if reuse_vectors {
+        let imported_chunks = import_chunks_with_vectors_query(
+            chunks,
+            &state.groups,
+            dataset_org_plan_sub.dataset.id,
+            state.target_config.clone(),
+            web_pool.clone(),
+        )
+        .await?;
+        dataset_import.chunks_imported += imported_chunks as i64;
+    } else {
+        let chunk_req_payloads = chunks
+            .into_iter()
+            .map(|chunk| exported_chunk_to_chunk_req_payload(chunk, &state.groups))
+            .collect::<Vec<_>>();
+        let chunk_count = chunk_req_payloads.len();
+
+        create_chunk(
+            web::Json(CreateChunkReqPayloadEnum::Batch(
+                CreateBatchChunkReqPayload(chunk_req_payloads),
+            )),
+            web_pool.clone(),
+            AdminOnly::default(),
+            broccoli_queue,
+            dataset_org_plan_sub.clone(),
+        )
+        .await
+        .map_err(|err| ServiceError::BadRequest(err.to_string()))?;
+
+        dataset_import.chunks_imported += chunk_count as i64;
+    }
+
+    dataset_import.vectors_reused = Some(reuse_vectors);
+    update_dataset_import_progress_query(dataset_import, web_pool).await
+}
@@ -6,10 +6,11 @@ use std::sync::{
     Arc,
 };
 use trieve_server::{
-    data::models::{self, DatasetConfiguration},
+    data::models::{self, ChunkChangeOperation, DatasetConfiguration, NewChunkChange},
     errors::ServiceError,
     establish_connection, get_env,
     operators::{
+        change_feed_operator::insert_chunk_changes_query,
         chunk_operator::bulk_delete_chunks_query,
         clickhouse_operator::{ClickHouseEvent, EventQueue},
         dataset_operator::{
@@ -17,6 +18,8 @@ use trieve_server::{
             get_deleted_dataset_by_id_query, ChunkDeleteMessage, DatasetDeleteMessage,
             DeleteMessage,
         },
+        group_aggregate_operator::refresh_group_aggregates_query,
+        group_centroid_operator::mark_group_centroids_stale_query,
         organization_operator::{
             delete_actual_organization_query, get_soft_deleted_datasets_for_organization,
         },
@@ -74,7 +77,7 @@ fn main() {
             signal_hook::flag::register(SIGTERM, Arc::clone(&should_terminate))
                 .expect("Failed to register shutdown hook");
 
-            let event_queue = if std::env::var("USE_ANALYTICS")
+            let mut event_queue = if std::env::var("USE_ANALYTICS")
                 .unwrap_or("false".to_string())
                 .parse()
                 .unwrap_or(false)
@@ -101,6 +104,7 @@ fn main() {
                 log::info!("Analytics disabled");
                 EventQueue::default()
             };
+            event_queue.start_webhook_service();
 
             let web_event_queue = actix_web::web::Data::new(event_queue);
 
@@ -230,8 +234,12 @@ async fn delete_worker(
                 }
             }
             DeleteMessage::ChunkDelete(chunk_delete_message) => {
-                if let Err(err) =
-                    bulk_delete_chunks(web_pool.clone(), chunk_delete_message.clone()).await
+                if let Err(err) = bulk_delete_chunks(
+                    web_pool.clone(),
+                    redis_pool.clone(),
+                    chunk_delete_message.clone(),
+                )
+                .await
                 {
                     let _ = readd_error_to_queue(
                         DeleteMessage::ChunkDelete(chunk_delete_message),
@@ -310,6 +318,18 @@ pub async fn delete_or_clear_dataset(
             err
         })?;
 
+        insert_chunk_changes_query(
+            vec![NewChunkChange::from_details(
+                delete_worker_message.dataset_id,
+                ChunkChangeOperation::Clear,
+                None,
+                None,
+                vec![],
+            )],
+            web_pool.clone(),
+        )
+        .await?;
+
         log::info!(
             "Cleared all chunks for dataset: {:?}",
             delete_worker_message.dataset_id
@@ -379,6 +399,7 @@ pub async fn delete_or_clear_dataset(
 
 pub async fn bulk_delete_chunks(
     web_pool: actix_web::web::Data<models::Pool>,
+    redis_pool: actix_web::web::Data<models::RedisPool>,
     chunk_delete_message: ChunkDeleteMessage,
 ) -> Result<(), ServiceError> {
     log::info!(
@@ -390,11 +411,11 @@ pub async fn bulk_delete_chunks(
         .map_err(|err| ServiceError::BadRequest(format!("Failed to get dataset: {:?}", err)))?;
     let dataset_config = DatasetConfiguration::from_json(dataset.server_configuration);
 
-    bulk_delete_chunks_query(
+    let deleted_group_ids = bulk_delete_chunks_query(
         Some(chunk_delete_message.filter),
         chunk_delete_message.deleted_at,
         chunk_delete_message.dataset_id,
-        dataset_config,
+        dataset_config.clone(),
         web_pool.clone(),
     )
     .await
@@ -403,6 +424,20 @@ pub async fn bulk_delete_chunks(
         err
     })?;
 
+    refresh_group_aggregates_query(
+        chunk_delete_message.dataset_id,
+        deleted_group_ids.clone(),
+        web_pool.clone(),
+    )
+    .await?;
+    mark_group_centroids_stale_query(
+        &dataset_config,
+        chunk_delete_message.dataset_id,
+        deleted_group_ids,
+        redis_pool,
+    )
+    .await?;
+
     log::info!(
         "Bulk deleted chunks for dataset: {:?}",
         chunk_delete_message.dataset_id
@@ -271,7 +271,7 @@ async fn main() -> Result<(), Box<dyn Error>> {
 
     let web_pool = actix_web::web::Data::new(pool.clone());
 
-    let event_queue = if std::env::var("USE_ANALYTICS")
+    let mut event_queue = if std::env::var("USE_ANALYTICS")
         .unwrap_or("false".to_string())
         .parse()
         .unwrap_or(false)
@@ -295,6 +295,7 @@ async fn main() -> Result<(), Box<dyn Error>> {
         log::info!("Analytics disabled");
         EventQueue::default()
     };
+    event_queue.start_webhook_service();
 
     let redis_url = get_env!("REDIS_URL", "REDIS_URL is not set");
 
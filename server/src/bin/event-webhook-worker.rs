@@ -0,0 +1,161 @@
+use diesel_async::pooled_connection::{AsyncDieselConnectionManager, ManagerConfig};
+use signal_hook::consts::SIGTERM;
+use std::error::Error;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use trieve_server::{
+    establish_connection, get_env,
+    operators::event_webhook_operator::{
+        create_queued_event_webhook_deliveries_query, delete_event_webhook_deliveries_before_query,
+        deliver_event_webhook, lease_due_event_webhook_deliveries_query,
+        requeue_processing_event_webhook_events_query,
+    },
+};
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn Error>> {
+    dotenvy::dotenv().ok();
+    env_logger::builder()
+        .target(env_logger::Target::Stdout)
+        .filter_level(log::LevelFilter::Info)
+        .init();
+
+    let database_url = get_env!("DATABASE_URL", "DATABASE_URL is not set");
+
+    let mut config = ManagerConfig::default();
+    config.custom_setup = Box::new(establish_connection);
+
+    let mgr = AsyncDieselConnectionManager::<diesel_async::AsyncPgConnection>::new_with_config(
+        database_url,
+        config,
+    );
+
+    let pool = diesel_async::pooled_connection::deadpool::Pool::builder(mgr)
+        .max_size(10)
+        .build()
+        .expect("Failed to create diesel_async pool");
+
+    let web_pool = actix_web::web::Data::new(pool.clone());
+
+    let redis_url = get_env!("REDIS_URL", "REDIS_URL is not set");
+    let redis_connections: u32 = std::env::var("REDIS_CONNECTIONS")
+        .unwrap_or("2".to_string())
+        .parse()
+        .unwrap_or(2);
+
+    let redis_manager =
+        bb8_redis::RedisConnectionManager::new(redis_url).expect("Failed to connect to redis");
+
+    let redis_pool = bb8_redis::bb8::Pool::builder()
+        .max_size(redis_connections)
+        .connection_timeout(std::time::Duration::from_secs(2))
+        .build(redis_manager)
+        .await
+        .expect("Failed to create redis pool");
+
+    let web_redis_pool = actix_web::web::Data::new(redis_pool);
+
+    let concurrency: i64 = std::env::var("EVENT_WEBHOOK_CONCURRENCY")
+        .unwrap_or("8".to_string())
+        .parse()
+        .unwrap_or(8);
+    let retention_days: i64 = std::env::var("EVENT_WEBHOOK_DELIVERY_RETENTION_DAYS")
+        .unwrap_or("30".to_string())
+        .parse()
+        .unwrap_or(30);
+
+    let should_terminate = Arc::new(AtomicBool::new(false));
+    signal_hook::flag::register(SIGTERM, Arc::clone(&should_terminate))
+        .expect("Failed to register shutdown hook");
+
+    let mut last_cleanup: Option<std::time::Instant> = None;
+
+    log::info!("Starting event webhook worker");
+
+    match requeue_processing_event_webhook_events_query(web_redis_pool.clone()).await {
+        Ok(requeued) if requeued > 0 => {
+            log::info!("Requeued {} unprocessed worker events", requeued)
+        }
+        Ok(_) => {}
+        Err(err) => log::error!("Failed to requeue unprocessed worker events: {:?}", err),
+    }
+
+    loop {
+        if should_terminate.load(Ordering::Relaxed) {
+            log::info!("Shutting down");
+            break;
+        }
+
+        if last_cleanup.map_or(true, |last_cleanup| {
+            last_cleanup.elapsed() > std::time::Duration::from_secs(60 * 60)
+        }) {
+            let before = chrono::Utc::now().naive_local() - chrono::Duration::days(retention_days);
+            match delete_event_webhook_deliveries_before_query(before, web_pool.clone()).await {
+                Ok(deleted) => {
+                    log::info!(
+                        "Deleted {} event webhook deliveries older than {}",
+                        deleted,
+                        before
+                    )
+                }
+                Err(err) => log::error!("Failed to delete old event webhook deliveries: {:?}", err),
+            }
+            last_cleanup = Some(std::time::Instant::now());
+        }
+
+        let queued_events = match create_queued_event_webhook_deliveries_query(
+            500,
+            web_redis_pool.clone(),
+            web_pool.clone(),
+        )
+        .await
+        {
+            Ok(queued_events) => queued_events,
+            Err(err) => {
+                log::error!(
+                    "Failed to create queued event webhook deliveries: {:?}",
+                    err
+                );
+                0
+            }
+        };
+
+        // Leases outlast the 10 second request timeout so no other worker picks up a delivery
+        // while it is in flight
+        let deliveries =
+            match lease_due_event_webhook_deliveries_query(concurrency, 60, web_pool.clone()).await
+            {
+                Ok(deliveries) => deliveries,
+                Err(err) => {
+                    log::error!("Failed to lease event webhook deliveries: {:?}", err);
+                    tokio::time::sleep(std::time::Duration::from_secs(5)).await;
+                    continue;
+                }
+            };
+
+        if deliveries.is_empty() {
+            if queued_events == 0 {
+                tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+            }
+            continue;
+        }
+
+        let attempts = deliveries.into_iter().map(|delivery| {
+            let delivery_id = delivery.id;
+            let web_pool = web_pool.clone();
+            async move {
+                if let Err(err) = deliver_event_webhook(delivery, web_pool).await {
+                    log::error!(
+                        "Failed to deliver event webhook delivery {}: {:?}",
+                        delivery_id,
+                        err
+                    );
+                }
+            }
+        });
+
+        futures::future::join_all(attempts).await;
+    }
+
+    Ok(())
+}
@@ -11,13 +11,22 @@ use std::{
     sync::{atomic::AtomicBool, Arc},
 };
 use trieve_server::{
-    data::models::{self, ChunkGroup, FileWorkerMessage},
+    data::models::{self, ChunkGroup, DatasetConfiguration, FileWorkerMessage},
     establish_connection, get_env,
     handlers::chunk_handler::ChunkReqPayload,
     operators::{
+        chunking_operator::{chunk_document, metadata_with_heading_path, DocumentFormat},
         clickhouse_operator::{ClickHouseEvent, EventQueue},
-        dataset_operator::get_dataset_and_organization_from_dataset_id_query,
-        file_operator::{create_file_chunks, get_aws_bucket, preprocess_file_to_chunks},
+        dataset_operator::{
+            get_dataset_and_organization_from_dataset_id_query, get_dataset_by_id_query,
+        },
+        extraction_operator::extract_file_sections,
+        file_operator::{
+            create_file_chunks, delete_removed_file_chunks_query, get_aws_bucket,
+            get_file_group_id_query, get_previous_file_chunks_query,
+            preprocess_extracted_file_to_chunks, preprocess_file_to_chunks,
+            release_file_reprocess_query, PreviousFileChunks,
+        },
         group_operator::{create_group_from_file_query, create_groups_query},
     },
 };
@@ -67,7 +76,19 @@ async fn main() -> Result<(), Box<dyn Error>> {
         .parse()
         .unwrap_or(2);
 
-    let event_queue = if std::env::var("USE_ANALYTICS")
+    let redis_manager =
+        bb8_redis::RedisConnectionManager::new(redis_url).expect("Failed to connect to redis");
+
+    let redis_pool = bb8_redis::bb8::Pool::builder()
+        .max_size(redis_connections)
+        .connection_timeout(std::time::Duration::from_secs(2))
+        .build(redis_manager)
+        .await
+        .expect("Failed to create redis pool");
+
+    let web_redis_pool = actix_web::web::Data::new(redis_pool);
+
+    let mut event_queue = if std::env::var("USE_ANALYTICS")
         .unwrap_or("false".to_string())
         .parse()
         .unwrap_or(false)
@@ -91,6 +112,7 @@ async fn main() -> Result<(), Box<dyn Error>> {
         log::info!("Analytics disabled");
         EventQueue::default()
     };
+    event_queue.start_webhook_service();
 
     let web_event_queue = actix_web::web::Data::new(event_queue);
 
@@ -116,11 +138,13 @@ async fn main() -> Result<(), Box<dyn Error>> {
             {
                 let queue = queue.clone();
                 let web_pool = web_pool.clone();
+                let web_redis_pool = web_redis_pool.clone();
                 let web_event_queue = web_event_queue.clone();
                 move |msg| {
                     file_worker(
                         msg.payload,
                         web_pool.clone(),
+                        web_redis_pool.clone(),
                         web_event_queue.clone(),
                         (*queue).clone(),
                     )
@@ -148,11 +172,50 @@ async fn main() -> Result<(), Box<dyn Error>> {
 async fn file_worker(
     message: FileWorkerMessage,
     web_pool: actix_web::web::Data<models::Pool>,
+    redis_pool: actix_web::web::Data<models::RedisPool>,
     event_queue: actix_web::web::Data<EventQueue>,
     broccoli_queue: BroccoliQueue,
 ) -> Result<(), BroccoliError> {
-    match upload_file(message.clone(), web_pool.clone(), broccoli_queue.clone()).await {
+    // Chunks created before now are replaced once the new chunks of the file have been ingested
+    let reprocessed_at = chrono::Utc::now().naive_utc();
+    let mut previous_chunks = if message.reprocess {
+        Some(get_previous_file_chunks_query(message.file_id, web_pool.clone()).await?)
+    } else {
+        None
+    };
+
+    let upload_result = upload_file(
+        message.clone(),
+        previous_chunks.as_mut(),
+        web_pool.clone(),
+        redis_pool.clone(),
+        event_queue.clone(),
+        broccoli_queue.clone(),
+    )
+    .await;
+
+    // The new chunks are pending by now and keep blocking other reprocesses until ingested
+    if message.reprocess {
+        release_file_reprocess_query(message.file_id, redis_pool.clone()).await?;
+    }
+
+    match upload_result {
         Ok(pages) => {
+            if let Some(previous_chunks) =
+                previous_chunks.filter(|_| message.upload_file_data.create_chunks.unwrap_or(true))
+            {
+                let dataset = get_dataset_by_id_query(message.dataset_id, web_pool.clone()).await?;
+                delete_removed_file_chunks_query(
+                    message.file_id,
+                    previous_chunks,
+                    reprocessed_at,
+                    dataset,
+                    web_pool.clone(),
+                    redis_pool.clone(),
+                )
+                .await?;
+            }
+
             event_queue
                 .send(ClickHouseEvent::WorkerEvent(
                     models::WorkerEvent::from_details(
@@ -287,9 +350,50 @@ async fn send_webhook(
     Ok(())
 }
 
+async fn extract_html_with_tika(file_data: Vec<u8>) -> Result<String, BroccoliError> {
+    let tika_url = std::env::var("TIKA_URL")
+        .expect("TIKA_URL must be set")
+        .to_string();
+
+    let tika_client = reqwest::Client::new();
+    log::info!("Sending file to tika");
+    let tika_response = tika_client
+        .put(format!("{}/tika", tika_url))
+        .header("Accept", "text/html")
+        .body(file_data)
+        .send()
+        .await
+        .map_err(|err| {
+            log::error!("Could not send file to tika {:?}", err);
+            BroccoliError::Job("Could not send file to tika".to_string())
+        })?;
+    log::info!("Got response from tika");
+
+    let tike_html_converted_file_bytes = tika_response
+        .bytes()
+        .await
+        .map_err(|err| {
+            log::error!("Could not get tika response bytes {:?}", err);
+            BroccoliError::Job("Could not get tika response bytes".to_string())
+        })?
+        .to_vec();
+
+    let html_content = String::from_utf8_lossy(&tike_html_converted_file_bytes).to_string();
+    if html_content.is_empty() {
+        return Err(BroccoliError::Job(
+            "Could not parse file with tika".to_string(),
+        ));
+    }
+
+    Ok(html_content)
+}
+
 async fn upload_file(
     file_worker_message: FileWorkerMessage,
+    mut previous_chunks: Option<&mut PreviousFileChunks>,
     web_pool: actix_web::web::Data<models::Pool>,
+    redis_pool: actix_web::web::Data<models::RedisPool>,
+    event_queue: actix_web::web::Data<EventQueue>,
     broccoli_queue: BroccoliQueue,
 ) -> Result<Option<u64>, BroccoliError> {
     log::info!(
@@ -319,7 +423,16 @@ async fn upload_file(
     )
     .await?;
 
-    let group_id = if !file_worker_message
+    let existing_group_id = if file_worker_message.reprocess {
+        get_file_group_id_query(file_id, web_pool.clone()).await?
+    } else {
+        None
+    };
+
+    let group_id = if existing_group_id.is_some() {
+        log::info!("Reusing group of reprocessed file");
+        existing_group_id
+    } else if !file_worker_message
         .upload_file_data
         .pdf2md_options
         .as_ref()
@@ -612,7 +725,45 @@ async fn upload_file(
                             semantic_boost: None,
                             high_priority: None,
                         };
-                        new_chunks.push(create_chunk_data);
+                        match file_worker_message
+                            .upload_file_data
+                            .chunking_strategy
+                            .as_ref()
+                        {
+                            Some(chunking_strategy) => {
+                                let dataset_config = DatasetConfiguration::from_json(
+                                    dataset_org_plan_sub.dataset.server_configuration.clone(),
+                                );
+
+                                let document_chunks = chunk_document(
+                                    &page.content,
+                                    DocumentFormat::Markdown,
+                                    chunking_strategy,
+                                    &dataset_config,
+                                )
+                                .await
+                                .map_err(|err| {
+                                    log::error!("Could not chunk page {} {:?}", page.page_num, err);
+                                    BroccoliError::Job(format!("Could not chunk page {:?}", err))
+                                })?;
+
+                                for (i, document_chunk) in document_chunks.into_iter().enumerate() {
+                                    new_chunks.push(ChunkReqPayload {
+                                        chunk_html: Some(document_chunk.content),
+                                        metadata: metadata_with_heading_path(
+                                            create_chunk_data.metadata.clone(),
+                                            &document_chunk.heading_path,
+                                        ),
+                                        tracking_id: create_chunk_data
+                                            .tracking_id
+                                            .clone()
+                                            .map(|tracking_id| format!("{}|{}", tracking_id, i)),
+                                        ..create_chunk_data.clone()
+                                    });
+                                }
+                            }
+                            None => new_chunks.push(create_chunk_data),
+                        }
                     }
                 }
 
@@ -631,7 +782,9 @@ async fn upload_file(
                         new_chunks.clone(),
                         dataset_org_plan_sub.clone(),
                         group_id,
+                        previous_chunks.as_deref_mut(),
                         web_pool.clone(),
+                        redis_pool.clone(),
                         broccoli_queue.clone(),
                     )
                     .await?;
@@ -665,47 +818,60 @@ async fn upload_file(
                     current_response.pages_processed = processed_pages.len() as u32;
                     send_webhook(webhook_url, &current_response).await?;
                 }
+
+                event_queue
+                    .send(ClickHouseEvent::WorkerEvent(
+                        models::WorkerEvent::from_details(
+                            file_worker_message.dataset_id,
+                            Some(file_worker_message.organization_id),
+                            models::EventType::Pdf2MdOcrCompleted {
+                                file_id,
+                                task_id,
+                                pages_processed: processed_pages.len() as u64,
+                            },
+                        )
+                        .into(),
+                    ))
+                    .await;
             }
         }
 
         return Ok(Some(total_pages as u64));
     }
 
-    let tika_url = std::env::var("TIKA_URL")
-        .expect("TIKA_URL must be set")
-        .to_string();
-
-    let tika_client = reqwest::Client::new();
-    log::info!("Sending file to tika");
-    let tika_response = tika_client
-        .put(format!("{}/tika", tika_url))
-        .header("Accept", "text/html")
-        .body(file_data.clone())
-        .send()
-        .await
-        .map_err(|err| {
-            log::error!("Could not send file to tika {:?}", err);
-            BroccoliError::Job("Could not send file to tika".to_string())
-        })?;
-    log::info!("Got response from tika");
-
-    let tike_html_converted_file_bytes = tika_response
-        .bytes()
-        .await
-        .map_err(|err| {
-            log::error!("Could not get tika response bytes {:?}", err);
-            BroccoliError::Job("Could not get tika response bytes".to_string())
-        })?
-        .to_vec();
+    let native_sections = match extract_file_sections(
+        &file_name,
+        &file_data,
+        file_worker_message
+            .upload_file_data
+            .spreadsheet_chunking
+            .unwrap_or_default(),
+    ) {
+        Ok(sections) => sections,
+        Err(err) => {
+            log::warn!(
+                "Could not extract {:?} natively, falling back to tika {:?}",
+                file_name,
+                err
+            );
+            None
+        }
+    };
 
-    let html_content = String::from_utf8_lossy(&tike_html_converted_file_bytes).to_string();
-    if html_content.is_empty() {
+    let (file_content, document_format) = match native_sections.as_ref() {
+        Some(sections) => (sections.join("\n\n"), DocumentFormat::Markdown),
+        None => (
+            extract_html_with_tika(file_data).await?,
+            DocumentFormat::Html,
+        ),
+    };
+    if file_content.trim().is_empty() {
         return Err(BroccoliError::Job(
-            "Could not parse file with tika".to_string(),
+            "Could not extract any text from file".to_string(),
         ));
     }
 
-    log::info!("Successfully converted file bytes to html string");
+    log::info!("Successfully extracted text from file");
 
     let dataset_org_plan_sub = get_dataset_and_organization_from_dataset_id_query(
         models::UnifiedId::TrieveUuid(file_worker_message.dataset_id),
@@ -714,14 +880,14 @@ async fn upload_file(
     )
     .await?;
 
-    // If chunk splitting turned off, create only a single chunk using html_content
+    // If chunk splitting turned off, create only a single chunk using file_content
     if file_worker_message
         .upload_file_data
         .split_avg
         .unwrap_or(false)
     {
         let chunk = ChunkReqPayload {
-            chunk_html: Some(html_content.clone()),
+            chunk_html: Some(file_content.clone()),
             semantic_content: None,
             fulltext_content: None,
             link: file_worker_message.upload_file_data.link.clone(),
@@ -752,16 +918,96 @@ async fn upload_file(
             vec![chunk],
             dataset_org_plan_sub.clone(),
             group_id,
+            previous_chunks.as_deref_mut(),
             web_pool.clone(),
+            redis_pool.clone(),
             broccoli_queue.clone(),
         )
         .await?;
         return Ok(None);
     }
 
-    let Ok(chunk_htmls) =
-        preprocess_file_to_chunks(html_content, file_worker_message.upload_file_data.clone())
-    else {
+    if let Some(chunking_strategy) = file_worker_message
+        .upload_file_data
+        .chunking_strategy
+        .as_ref()
+    {
+        let dataset_config = DatasetConfiguration::from_json(
+            dataset_org_plan_sub.dataset.server_configuration.clone(),
+        );
+
+        let document_chunks = chunk_document(
+            &file_content,
+            document_format,
+            chunking_strategy,
+            &dataset_config,
+        )
+        .await
+        .map_err(|err| {
+            log::error!("Could not chunk file {:?} {:?}", file_name, err);
+            BroccoliError::Job(format!("Could not chunk file {:?}", err))
+        })?;
+
+        let chunks = document_chunks
+            .into_iter()
+            .enumerate()
+            .map(|(i, document_chunk)| ChunkReqPayload {
+                chunk_html: Some(document_chunk.content),
+                semantic_content: None,
+                fulltext_content: None,
+                link: file_worker_message.upload_file_data.link.clone(),
+                tag_set: file_worker_message.upload_file_data.tag_set.clone(),
+                metadata: metadata_with_heading_path(
+                    file_worker_message.upload_file_data.metadata.clone(),
+                    &document_chunk.heading_path,
+                ),
+                group_ids: None,
+                group_tracking_ids: None,
+                location: None,
+                tracking_id: file_worker_message
+                    .upload_file_data
+                    .group_tracking_id
+                    .clone()
+                    .map(|tracking_id| format!("{}|{}", tracking_id, i)),
+                upsert_by_tracking_id: None,
+                time_stamp: file_worker_message.upload_file_data.time_stamp.clone(),
+                weight: None,
+                split_avg: None,
+                convert_html_to_text: None,
+                image_urls: None,
+                num_value: None,
+                fulltext_boost: None,
+                semantic_boost: None,
+                high_priority: None,
+            })
+            .collect::<Vec<_>>();
+
+        create_file_chunks(
+            file_worker_message.file_id,
+            file_worker_message.upload_file_data,
+            chunks,
+            dataset_org_plan_sub,
+            group_id,
+            previous_chunks.as_deref_mut(),
+            web_pool.clone(),
+            redis_pool.clone(),
+            broccoli_queue.clone(),
+        )
+        .await?;
+
+        return Ok(None);
+    }
+
+    let chunk_htmls = match native_sections {
+        Some(sections) => preprocess_extracted_file_to_chunks(
+            sections,
+            file_worker_message.upload_file_data.clone(),
+        ),
+        None => {
+            preprocess_file_to_chunks(file_content, file_worker_message.upload_file_data.clone())
+        }
+    };
+    let Ok(chunk_htmls) = chunk_htmls else {
         log::error!("Could not parse file into chunks {:?}", file_name);
         return Err(BroccoliError::Job("Could not parse file".to_string()));
     };
@@ -803,7 +1049,9 @@ async fn upload_file(
         chunks,
         dataset_org_plan_sub,
         group_id,
+        previous_chunks.as_deref_mut(),
         web_pool.clone(),
+        redis_pool.clone(),
         broccoli_queue.clone(),
     )
     .await?;
@@ -1,3 +1,4 @@
+use broccoli_queue::queue::BroccoliQueue;
 use diesel_async::pooled_connection::{AsyncDieselConnectionManager, ManagerConfig};
 use signal_hook::consts::SIGTERM;
 use std::sync::{
@@ -10,7 +11,17 @@ use trieve_server::{
     establish_connection, get_env,
     operators::{
         clickhouse_operator::{ClickHouseEvent, EventQueue},
-        group_operator::{update_grouped_chunks_query, GroupUpdateMessage},
+        group_aggregate_operator::{
+            refresh_stale_group_aggregates_query, STALE_GROUP_AGGREGATES_KEY,
+        },
+        group_centroid_operator::{
+            refresh_group_centroids_query, refresh_stale_group_centroids_query,
+            STALE_GROUP_CENTROIDS_KEY,
+        },
+        group_operator::{
+            requeue_processing_stale_groups_query, update_grouped_chunks_query, GroupUpdateMessage,
+        },
+        group_summary_operator::refresh_settled_group_summaries_query,
     },
 };
 use trieve_server::{
@@ -53,7 +64,7 @@ fn main() {
                 .parse()
                 .unwrap_or(2);
 
-            let redis_manager = bb8_redis::RedisConnectionManager::new(redis_url)
+            let redis_manager = bb8_redis::RedisConnectionManager::new(redis_url.clone())
                 .expect("Failed to connect to redis");
 
             let redis_pool = bb8_redis::bb8::Pool::builder()
@@ -65,7 +76,15 @@ fn main() {
 
             let web_redis_pool = actix_web::web::Data::new(redis_pool);
 
-            let event_queue = if std::env::var("USE_ANALYTICS")
+            let broccoli_queue = BroccoliQueue::builder(redis_url)
+                .pool_connections(redis_connections.try_into().unwrap())
+                .failed_message_retry_strategy(Default::default())
+                .build()
+                .await
+                .expect("Failed to create broccoli queue");
+            let web_broccoli_queue = actix_web::web::Data::new(broccoli_queue);
+
+            let mut event_queue = if std::env::var("USE_ANALYTICS")
                 .unwrap_or("false".to_string())
                 .parse()
                 .unwrap_or(false)
@@ -92,6 +111,7 @@ fn main() {
                 log::info!("Analytics disabled");
                 EventQueue::default()
             };
+            event_queue.start_webhook_service();
 
             let web_event_queue = actix_web::web::Data::new(event_queue);
 
@@ -99,7 +119,14 @@ fn main() {
             signal_hook::flag::register(SIGTERM, Arc::clone(&should_terminate))
                 .expect("Failed to register shutdown hook");
             let web_redis_pool = web_redis_pool.clone();
-            grupdate_worker(should_terminate, web_redis_pool, web_pool, web_event_queue).await;
+            grupdate_worker(
+                should_terminate,
+                web_redis_pool,
+                web_pool,
+                web_event_queue,
+                web_broccoli_queue,
+            )
+            .await;
         });
 }
 
@@ -108,6 +135,7 @@ async fn grupdate_worker(
     redis_pool: actix_web::web::Data<models::RedisPool>,
     web_pool: actix_web::web::Data<models::Pool>,
     event_queue: actix_web::web::Data<EventQueue>,
+    broccoli_queue: actix_web::web::Data<BroccoliQueue>,
 ) {
     log::info!("Starting grupdate worker service thread");
     let mut redis_conn_sleep = std::time::Duration::from_secs(1);
@@ -139,12 +167,44 @@ async fn grupdate_worker(
         opt_redis_connection.expect("Failed to get redis connection outside of loop");
     let mut broken_pipe_sleep = std::time::Duration::from_secs(10);
 
+    for key in [STALE_GROUP_AGGREGATES_KEY, STALE_GROUP_CENTROIDS_KEY] {
+        if let Err(err) = requeue_processing_stale_groups_query(key, redis_pool.clone()).await {
+            log::error!("Failed to requeue stale groups of {}: {:?}", key, err);
+        }
+    }
+
     loop {
         if should_terminate.load(Ordering::Relaxed) {
             log::info!("Shutting down");
             break;
         }
 
+        match refresh_stale_group_aggregates_query(500, redis_pool.clone(), web_pool.clone()).await
+        {
+            Ok(0) => {}
+            Ok(refreshed) => log::info!("Refreshed aggregates of {} groups", refreshed),
+            Err(err) => log::error!("Failed to refresh stale group aggregates: {:?}", err),
+        }
+
+        match refresh_stale_group_centroids_query(100, redis_pool.clone(), web_pool.clone()).await {
+            Ok(0) => {}
+            Ok(refreshed) => log::info!("Refreshed centroids of {} groups", refreshed),
+            Err(err) => log::error!("Failed to refresh stale group centroids: {:?}", err),
+        }
+
+        match refresh_settled_group_summaries_query(
+            20,
+            redis_pool.clone(),
+            web_pool.clone(),
+            broccoli_queue.clone(),
+        )
+        .await
+        {
+            Ok(0) => {}
+            Ok(summarized) => log::info!("Summarized {} groups", summarized),
+            Err(err) => log::error!("Failed to summarize settled groups: {:?}", err),
+        }
+
         let payload_result: Result<Vec<String>, redis::RedisError> = redis::cmd("brpoplpush")
             .arg("group_update_queue")
             .arg("group_update_processing")
@@ -221,6 +281,24 @@ async fn grupdate_worker(
         {
             Ok(_) => {
                 log::info!("Updated group {}", group_update_msg.group.id);
+
+                // The centroid's payload carries the group's tag_set
+                if service_config.GROUP_CENTROIDS.is_some() {
+                    if let Err(err) = refresh_group_centroids_query(
+                        group_update_msg.dataset_id,
+                        vec![group_update_msg.group.id],
+                        web_pool.clone(),
+                    )
+                    .await
+                    {
+                        log::error!(
+                            "Failed to refresh centroid of group {}: {:?}",
+                            group_update_msg.group.id,
+                            err
+                        );
+                    }
+                }
+
                 event_queue
                     .send(ClickHouseEvent::WorkerEvent(
                         WorkerEvent::from_details(
@@ -8,18 +8,21 @@ use futures_util::StreamExt;
 use itertools::{izip, Itertools};
 use qdrant_client::qdrant::{PointStruct, Vector};
 use signal_hook::consts::SIGTERM;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::error::Error;
 use std::sync::{atomic::AtomicBool, Arc};
 use tracing_subscriber::{prelude::*, EnvFilter, Layer};
 use trieve_server::data::models::{
-    self, ChunkBoost, ChunkData, ChunkGroup, ChunkMetadata, DatasetConfiguration,
+    self, ChunkBoost, ChunkData, ChunkGroup, ChunkMetadata, DatasetConfiguration, DuplicateChunk,
     PagefindIndexWorkerMessage, QdrantPayload, WorkerEvent,
 };
 use trieve_server::errors::ServiceError;
 use trieve_server::handlers::chunk_handler::{
     BulkUploadIngestionMessage, FullTextBoost, SemanticBoost, UploadIngestionMessage,
 };
+use trieve_server::operators::change_feed_operator::{
+    get_ingested_chunk_changes, insert_chunk_changes_query,
+};
 use trieve_server::operators::chunk_operator::{
     bulk_insert_chunk_metadata_query, bulk_revert_insert_chunk_metadata_query,
     get_row_count_for_organization_id_query, insert_chunk_boost, insert_chunk_metadata_query,
@@ -29,9 +32,18 @@ use trieve_server::operators::clickhouse_operator::{ClickHouseEvent, EventQueue}
 use trieve_server::operators::dataset_operator::{
     get_dataset_and_organization_from_dataset_id_query, get_dataset_by_id_query,
 };
+use trieve_server::operators::dedup_operator::{
+    apply_dedup_policy, insert_chunk_fingerprints_query,
+};
+use trieve_server::operators::file_operator::complete_pending_file_chunks_query;
+use trieve_server::operators::group_aggregate_operator::refresh_group_aggregates_query;
+use trieve_server::operators::group_centroid_operator::mark_group_centroids_stale_query;
 use trieve_server::operators::group_operator::{
     create_groups_query, get_group_ids_from_tracking_ids_query, get_groups_from_group_ids_query,
 };
+use trieve_server::operators::group_summary_operator::{
+    is_group_summary_tracking_id, mark_group_summaries_stale_query,
+};
 use trieve_server::operators::model_operator::{
     count_tokens, get_bm25_embeddings, get_dense_vectors, get_sparse_vectors,
 };
@@ -124,7 +136,7 @@ async fn main() -> Result<(), Box<dyn Error>> {
         .build()
         .await?;
 
-    let event_queue = if std::env::var("USE_ANALYTICS")
+    let mut event_queue = if std::env::var("USE_ANALYTICS")
         .unwrap_or("false".to_string())
         .parse()
         .unwrap_or(false)
@@ -148,6 +160,7 @@ async fn main() -> Result<(), Box<dyn Error>> {
         log::info!("Analytics disabled");
         EventQueue::default()
     };
+    event_queue.start_webhook_service();
 
     let web_event_queue = actix_web::web::Data::new(event_queue);
     let queue_name = std::env::var("INGESTION_QUEUE_NAME").unwrap_or("ingestion".to_string());
@@ -157,6 +170,7 @@ async fn main() -> Result<(), Box<dyn Error>> {
         .expect("Failed to register shutdown hook");
 
     let ingestion_web_pool = web_pool.clone();
+    let ingestion_event_queue = web_event_queue.clone();
 
     log::info!("Starting ingestion service thread");
 
@@ -167,7 +181,8 @@ async fn main() -> Result<(), Box<dyn Error>> {
             Some(ConsumeOptionsBuilder::new().fairness(true).build()),
             move |msg| {
                 let pool = ingestion_web_pool.clone();
-                async move { ingestion_worker(msg.payload, pool.clone()).await }
+                let event_queue = ingestion_event_queue.clone();
+                async move { ingestion_worker(msg.payload, pool.clone(), event_queue).await }
             },
             {
                 let web_pool = web_pool.clone();
@@ -202,7 +217,7 @@ async fn main() -> Result<(), Box<dyn Error>> {
                             }
                         };
                         let dataset_config =
-                            DatasetConfiguration::from_json(dataset.server_configuration);
+                            DatasetConfiguration::from_json(dataset.server_configuration.clone());
 
                         if dataset_config.PAGEFIND_ENABLED {
                             let pagefind_worker_message = PagefindIndexWorkerMessage {
@@ -238,6 +253,111 @@ async fn main() -> Result<(), Box<dyn Error>> {
                             log::info!("Queue'd dataset for pagefind indexing");
                         }
 
+                        if dataset_config.GROUP_CENTROIDS.is_some()
+                            || dataset_config.GROUP_SUMMARIES.is_some()
+                        {
+                            let mut group_ids = msg
+                                .payload
+                                .ingestion_messages
+                                .iter()
+                                .flat_map(|message| {
+                                    message.chunk.group_ids.clone().unwrap_or_default()
+                                })
+                                .collect::<Vec<uuid::Uuid>>();
+
+                            let group_tracking_ids = msg
+                                .payload
+                                .ingestion_messages
+                                .iter()
+                                .flat_map(|message| {
+                                    message.chunk.group_tracking_ids.clone().unwrap_or_default()
+                                })
+                                .unique()
+                                .collect::<Vec<String>>();
+
+                            if !group_tracking_ids.is_empty() {
+                                group_ids.extend(
+                                    get_group_ids_from_tracking_ids_query(
+                                        group_tracking_ids,
+                                        msg.payload.dataset_id,
+                                        web_pool.clone(),
+                                    )
+                                    .await
+                                    .map_err(|err| BroccoliError::Job(err.to_string()))?
+                                    .into_iter()
+                                    .map(|(group_id, _)| group_id),
+                                );
+                            }
+
+                            let group_ids = group_ids.into_iter().unique().collect::<Vec<_>>();
+
+                            mark_group_centroids_stale_query(
+                                &dataset_config,
+                                msg.payload.dataset_id,
+                                group_ids.clone(),
+                                actix_web::web::Data::new(redis_pool.clone()),
+                            )
+                            .await
+                            .map_err(|err| BroccoliError::Job(err.to_string()))?;
+
+                            // Upserting a group's summary chunk must not queue the group to be
+                            // summarized again
+                            let only_group_summaries =
+                                msg.payload.ingestion_messages.iter().all(|message| {
+                                    is_group_summary_tracking_id(
+                                        message.chunk.tracking_id.as_deref(),
+                                    )
+                                });
+
+                            if !only_group_summaries {
+                                mark_group_summaries_stale_query(
+                                    &dataset_config,
+                                    msg.payload.dataset_id,
+                                    group_ids,
+                                    actix_web::web::Data::new(redis_pool.clone()),
+                                )
+                                .await
+                                .map_err(|err| BroccoliError::Job(err.to_string()))?;
+                            }
+                        }
+
+                        // Chunks replaced by a reprocessed file are removed once all of the file's
+                        // new chunks have been ingested
+                        let file_chunk_ids = msg
+                            .payload
+                            .ingestion_messages
+                            .iter()
+                            .filter_map(|message| {
+                                let file_id = message
+                                    .chunk
+                                    .metadata
+                                    .as_ref()?
+                                    .get("source_document")?
+                                    .as_str()?
+                                    .parse::<uuid::Uuid>()
+                                    .ok()?;
+                                Some((file_id, message.ingest_specific_chunk_metadata.id))
+                            })
+                            .into_group_map();
+
+                        for (file_id, chunk_ids) in file_chunk_ids {
+                            if let Err(err) = complete_pending_file_chunks_query(
+                                file_id,
+                                chunk_ids,
+                                dataset.clone(),
+                                web_pool.clone(),
+                                actix_web::web::Data::new(redis_pool.clone()),
+                            )
+                            .await
+                            {
+                                log::error!(
+                                    "Failed to remove chunks replaced in file {}: {:?}",
+                                    file_id,
+                                    err
+                                );
+                            }
+                        }
+
                         let tokens_ingested = msg
                             .payload
                             .ingestion_messages
@@ -292,6 +412,7 @@ async fn main() -> Result<(), Box<dyn Error>> {
 async fn ingestion_worker(
     ingestion_message: BulkUploadIngestionMessage,
     web_pool: actix_web::web::Data<models::Pool>,
+    event_queue: actix_web::web::Data<EventQueue>,
 ) -> Result<(), BroccoliError> {
     log::info!("Selecting dataset for ingestion message");
     let dataset_result: Result<models::Dataset, ServiceError> =
@@ -314,21 +435,41 @@ async fn ingestion_worker(
 
     let reqwest_client = reqwest::Client::new();
 
-    bulk_upload_chunks(
+    let duplicate_chunks = bulk_upload_chunks(
         ingestion_message.clone(),
         dataset_config.clone(),
         web_pool.clone(),
         reqwest_client.clone(),
     )
-    .await
+    .await?;
+
+    if !duplicate_chunks.is_empty() {
+        log::info!("Skipped {} duplicate chunks", duplicate_chunks.len());
+        event_queue
+            .send(ClickHouseEvent::WorkerEvent(
+                WorkerEvent::from_details(
+                    ingestion_message.dataset_id,
+                    Some(dataset.organization_id),
+                    models::EventType::DuplicateChunksSkipped {
+                        duplicates: duplicate_chunks,
+                    },
+                )
+                .into(),
+            ))
+            .await;
+    }
+
+    Ok(())
 }
 
+/// Returns the chunks which were not ingested because the dataset's dedup policy matched them to
+/// an existing chunk
 pub async fn bulk_upload_chunks(
     payload: BulkUploadIngestionMessage,
     dataset_config: DatasetConfiguration,
     web_pool: actix_web::web::Data<models::Pool>,
     reqwest_client: reqwest::Client,
-) -> Result<(), BroccoliError> {
+) -> Result<Vec<DuplicateChunk>, BroccoliError> {
     let unlimited = std::env::var("UNLIMITED").unwrap_or("false".to_string());
     if unlimited == "false" && !dataset_config.QDRANT_ONLY {
         log::info!("Getting dataset, organization, and its plan+subscription information for dataset_id: {:?}", payload.dataset_id);
@@ -540,29 +681,100 @@ pub async fn bulk_upload_chunks(
         .filter(|data| !data.content.is_empty())
         .collect();
 
+    let (ingestion_data, duplicate_chunks, deduped_dense_vectors) =
+        match dataset_config.DEDUP_POLICY.as_ref() {
+            Some(dedup_policy) => {
+                log::info!("Applying dedup policy to {} chunks", ingestion_data.len());
+                let dedup_result = apply_dedup_policy(
+                    ingestion_data,
+                    dedup_policy,
+                    payload.dataset_id,
+                    &dataset_config,
+                    reqwest_client.clone(),
+                    web_pool.clone(),
+                )
+                .await?;
+
+                (
+                    dedup_result.chunks,
+                    dedup_result.duplicates,
+                    dedup_result.dense_vectors,
+                )
+            }
+            None => (ingestion_data, vec![], None),
+        };
+
+    let fingerprint_chunks = dataset_config.DEDUP_POLICY.is_some()
+        && !dataset_config.QDRANT_ONLY
+        && !payload.only_qdrant.unwrap_or(false);
+
     if split_average_being_used {
         log::info!(
             "Uploading {} chunks one by one due to split_avg",
             ingestion_data.len()
         );
 
-        let mut chunk_ids = vec![];
-        for (message, ingestion_data) in izip!(payload.ingestion_messages, ingestion_data) {
+        let kept_chunk_ids = ingestion_data
+            .iter()
+            .map(|data| data.chunk_metadata.id)
+            .collect::<HashSet<uuid::Uuid>>();
+        let ingestion_messages = payload
+            .ingestion_messages
+            .into_iter()
+            .filter(|message| kept_chunk_ids.contains(&message.ingest_specific_chunk_metadata.id));
+
+        let mut uploaded_chunks = vec![];
+        for (mut message, ingestion_data) in izip!(ingestion_messages, ingestion_data) {
+            if dataset_config.DEDUP_POLICY.is_some() {
+                // Carry over the metadata flags and merged tags and groups set by the dedup policy
+                message.chunk.metadata = ingestion_data.chunk_metadata.metadata.clone();
+                message.chunk.tag_set = ingestion_data
+                    .chunk_metadata
+                    .tag_set
+                    .clone()
+                    .map(|tag_set| tag_set.into_iter().flatten().collect());
+                message.chunk.group_ids = ingestion_data.group_ids.clone();
+            }
+
             let upload_chunk_result = upload_chunk(
                 message,
                 dataset_config.clone(),
-                ingestion_data,
+                ingestion_data.clone(),
                 web_pool.clone(),
                 reqwest_client.clone(),
             )
             .await;
 
             if let Ok(chunk_uuid) = upload_chunk_result {
-                chunk_ids.push(chunk_uuid);
+                let mut uploaded_chunk = ingestion_data;
+                uploaded_chunk.chunk_metadata.id = chunk_uuid;
+                uploaded_chunks.push(uploaded_chunk);
             }
         }
 
-        return Ok(());
+        if fingerprint_chunks {
+            insert_chunk_fingerprints_query(&uploaded_chunks, payload.dataset_id, web_pool.clone())
+                .await?;
+        }
+
+        refresh_group_aggregates_query(
+            payload.dataset_id,
+            uploaded_chunks
+                .iter()
+                .flat_map(|chunk| chunk.group_ids.clone().unwrap_or_default())
+                .unique()
+                .collect(),
+            web_pool.clone(),
+        )
+        .await?;
+
+        insert_chunk_changes_query(
+            get_ingested_chunk_changes(payload.dataset_id, &uploaded_chunks),
+            web_pool.clone(),
+        )
+        .await?;
+
+        return Ok(duplicate_chunks);
     }
 
     let qdrant_only = dataset_config.QDRANT_ONLY;
@@ -582,7 +794,7 @@ pub async fn bulk_upload_chunks(
 
     if inserted_chunk_metadatas.is_empty() {
         // All collisions
-        return Ok(());
+        return Ok(duplicate_chunks);
     }
 
     // Only embed the things we get returned from here, this reduces the number of times we embed data that are just duplicates
@@ -604,8 +816,10 @@ pub async fn bulk_upload_chunks(
         .unique()
         .collect();
 
-    let embedding_vectors = match dataset_config.SEMANTIC_ENABLED {
-        true => {
+    let embedding_vectors = match (dataset_config.SEMANTIC_ENABLED, deduped_dense_vectors) {
+        // The dedup policy's vector similarity check already embedded these chunks
+        (true, Some(dense_vectors)) => dense_vectors.into_iter().map(Some).collect(),
+        (true, None) => {
             log::info!(
                 "Creating embeddings for {} chunks",
                 embedding_content_and_boosts.len()
@@ -639,7 +853,7 @@ pub async fn bulk_upload_chunks(
             }?;
             vectors.into_iter().map(Some).collect()
         }
-        false => vec![None; embedding_content_and_boosts.len()],
+        (false, _) => vec![None; embedding_content_and_boosts.len()],
     };
 
     let fulltext_content_and_boosts: Vec<(String, Option<FullTextBoost>, Option<SemanticBoost>)> =
@@ -824,6 +1038,15 @@ pub async fn bulk_upload_chunks(
         return Err(err);
     }
 
+    if fingerprint_chunks {
+        insert_chunk_fingerprints_query(
+            &inserted_chunk_metadatas,
+            payload.dataset_id,
+            web_pool.clone(),
+        )
+        .await?;
+    }
+
     if qdrant_only {
         log::info!(
             "Updating dataset chunk count by {}",
@@ -837,8 +1060,25 @@ pub async fn bulk_upload_chunks(
         .await?;
     }
 
+    refresh_group_aggregates_query(
+        payload.dataset_id,
+        inserted_chunk_metadatas
+            .iter()
+            .flat_map(|chunk| chunk.group_ids.clone().unwrap_or_default())
+            .unique()
+            .collect(),
+        web_pool.clone(),
+    )
+    .await?;
+
+    insert_chunk_changes_query(
+        get_ingested_chunk_changes(payload.dataset_id, &inserted_chunk_metadatas),
+        web_pool.clone(),
+    )
+    .await?;
+
     log::info!("----- Finished inserting batch of chunks ------");
-    Ok(())
+    Ok(duplicate_chunks)
 }
 
 async fn upload_chunk(
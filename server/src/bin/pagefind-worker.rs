@@ -62,7 +62,7 @@ fn main() {
 
             let web_redis_pool = actix_web::web::Data::new(redis_pool);
 
-            let event_queue = if std::env::var("USE_ANALYTICS")
+            let mut event_queue = if std::env::var("USE_ANALYTICS")
                 .unwrap_or("false".to_string())
                 .parse()
                 .unwrap_or(false)
@@ -89,6 +89,7 @@ fn main() {
                 log::info!("Analytics disabled");
                 EventQueue::default()
             };
+            event_queue.start_webhook_service();
 
             let web_event_queue = actix_web::web::Data::new(event_queue);
 
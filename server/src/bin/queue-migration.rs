@@ -80,6 +80,7 @@ async fn main() -> Result<(), ServiceError> {
                         embedding_base_url: new_embedding_base_url.to_string(),
                         embedding_size: new_embedding_size,
                     },
+                    reindex_id: None,
                 })
                 .expect("Failed to serialze MigratePoint message")
             })
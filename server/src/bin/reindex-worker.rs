@@ -1,19 +1,38 @@
 use std::collections::HashMap;
 
+use actix_web::web;
+use broccoli_queue::{error::BroccoliError, queue::BroccoliQueue};
+use diesel_async::pooled_connection::{AsyncDieselConnectionManager, ManagerConfig};
 use itertools::{izip, Itertools};
-use qdrant_client::qdrant::{PointStruct, Vector};
+use qdrant_client::qdrant::{Condition, Filter, PointStruct, Vector};
 #[allow(deprecated)]
 use qdrant_client::{
     qdrant::{self, GetPointsBuilder, PointId, RetrievedPoint, UpsertPointsBuilder},
     Qdrant,
 };
 use trieve_server::{
-    data::models::{DatasetConfiguration, MigratePointMessage, MigrationMode},
+    data::models::{
+        DatasetConfiguration, DatasetReindex, DatasetReindexMessage, DatasetReindexStatus,
+        EventType, MigratePointMessage, MigrationMode, Pool, RedisPool, WorkerEvent,
+    },
     errors::ServiceError,
-    get_env,
+    establish_connection, get_env,
     operators::{
+        clickhouse_operator::{ClickHouseEvent, EventQueue},
+        dataset_operator::get_dataset_by_id_query,
         model_operator::{get_bm25_embeddings, get_dense_vectors, get_sparse_vectors},
-        qdrant_operator::{bulk_upsert_qdrant_points_query, get_qdrant_connection},
+        qdrant_operator::{
+            delete_points_from_qdrant, get_qdrant_connection, scroll_qdrant_collection_ids,
+        },
+        reindex_operator::{
+            add_dataset_reindex_progress_query, apply_dataset_reindex_configuration_query,
+            delete_dataset_points_from_collection_query, delete_dataset_reindex_points_query,
+            delete_orphaned_dataset_points_query, finish_dataset_reindex_query,
+            get_chunk_point_ids_updated_between_query, get_dataset_reindex_by_id_query,
+            get_dataset_reindex_query, migrate_dataset_group_centroids_query,
+            set_dataset_reindex_total_query, start_dataset_reindex_query,
+            update_dataset_reindex_status_query,
+        },
     },
 };
 
@@ -39,6 +58,80 @@ async fn main() -> Result<(), ServiceError> {
         .expect("Failed to create redis pool");
 
     let redis_pool = actix_web::web::Data::new(redis_pool);
+
+    let database_url = get_env!("DATABASE_URL", "DATABASE_URL is not set");
+
+    let mut config = ManagerConfig::default();
+    config.custom_setup = Box::new(establish_connection);
+
+    let mgr = AsyncDieselConnectionManager::<diesel_async::AsyncPgConnection>::new_with_config(
+        database_url,
+        config,
+    );
+
+    let pool = diesel_async::pooled_connection::deadpool::Pool::builder(mgr)
+        .max_size(3)
+        .build()
+        .expect("Failed to create diesel_async pool");
+
+    let web_pool = actix_web::web::Data::new(pool.clone());
+
+    let mut event_queue = if std::env::var("USE_ANALYTICS")
+        .unwrap_or("false".to_string())
+        .parse()
+        .unwrap_or(false)
+    {
+        log::info!("Analytics enabled");
+
+        let clickhouse_client = clickhouse::Client::default()
+            .with_url(
+                std::env::var("CLICKHOUSE_URL").unwrap_or("http://localhost:8123".to_string()),
+            )
+            .with_user(std::env::var("CLICKHOUSE_USER").unwrap_or("default".to_string()))
+            .with_password(std::env::var("CLICKHOUSE_PASSWORD").unwrap_or("".to_string()))
+            .with_database(std::env::var("CLICKHOUSE_DATABASE").unwrap_or("default".to_string()))
+            .with_option("async_insert", "1")
+            .with_option("wait_for_async_insert", "0");
+
+        let mut event_queue = EventQueue::new(clickhouse_client.clone());
+        event_queue.start_service();
+        event_queue
+    } else {
+        log::info!("Analytics disabled");
+        EventQueue::default()
+    };
+    event_queue.start_webhook_service();
+
+    let web_event_queue = web::Data::new(event_queue);
+
+    let broccoli_queue = BroccoliQueue::builder(redis_url)
+        .pool_connections(2)
+        .failed_message_retry_strategy(Default::default())
+        .build()
+        .await
+        .expect("Failed to create broccoli queue");
+
+    // Reindexes started through the API are split into migration messages by this task, which
+    // the loop below then processes like any other migration.
+    let reindex_pool = web_pool.clone();
+    let reindex_redis_pool = redis_pool.clone();
+    let reindex_event_queue = web_event_queue.clone();
+    tokio::spawn(async move {
+        if let Err(err) = broccoli_queue
+            .process_messages("dataset_reindex", None, None, move |msg| {
+                queue_dataset_reindex(
+                    msg.payload,
+                    reindex_pool.clone(),
+                    reindex_redis_pool.clone(),
+                    reindex_event_queue.clone(),
+                )
+            })
+            .await
+        {
+            log::error!("Dataset reindex queue stopped: {:?}", err);
+        }
+    });
+
     let mut redis_connection = redis_pool
         .get()
         .await
@@ -112,86 +205,36 @@ async fn main() -> Result<(), ServiceError> {
             continue;
         }
 
-        let qdrant_client = get_qdrant_connection(
-            Some(get_env!("QDRANT_URL", "QDRANT_URL should be set")),
-            Some(get_env!("QDRANT_API_KEY", "QDRANT_API_KEY should be set")),
-        )
-        .await?;
-
-        // Get all points in message including Payload & Friends
-
-        let result = match migration_message.mode {
-            MigrationMode::BM25 { average_len, k, b } => {
-                let points = qdrant_client
-                    .get_points(
-                        GetPointsBuilder::new(
-                            migration_message.from_collection.clone(),
-                            migration_message
-                                .qdrant_point_ids
-                                .iter()
-                                .map(|uuid| PointId::from(uuid.to_string()))
-                                .collect_vec(),
-                        )
-                        .with_payload(true)
-                        .with_vectors(true)
-                        .build(),
-                    )
-                    .await
-                    .map_err(|_err| {
-                        ServiceError::BadRequest("Failed to search_points from qdrant".to_string())
-                    })?
-                    .result;
-
-                migrate_bm25(
-                    qdrant_client,
-                    points,
-                    migration_message.to_collection,
-                    average_len,
-                    b,
-                    k,
-                )
-                .await
-            }
-            MigrationMode::Reembed {
-                embedding_model_name,
-                embedding_base_url,
-                embedding_size,
-            } => {
-                let points = qdrant_client
-                    .get_points(
-                        GetPointsBuilder::new(
-                            migration_message.from_collection.clone(),
-                            migration_message
-                                .qdrant_point_ids
-                                .iter()
-                                .map(|uuid| PointId::from(uuid.to_string()))
-                                .collect_vec(),
-                        )
-                        .with_payload(true)
-                        .with_vectors(false)
-                        .build(),
-                    )
-                    .await
-                    .map_err(|_err| {
-                        ServiceError::BadRequest("Failed to search_points from qdrant".to_string())
-                    })?
-                    .result;
-                reembed_points(
-                    points,
-                    embedding_model_name,
-                    embedding_base_url,
-                    embedding_size,
-                )
-                .await
+        if let Some(reindex_id) = migration_message.reindex_id {
+            match get_dataset_reindex_by_id_query(reindex_id, web_pool.clone()).await {
+                Ok(dataset_reindex)
+                    if dataset_reindex.status == DatasetReindexStatus::Processing.to_string() => {}
+                _ => {
+                    log::info!(
+                        "Skipping points of reindex {:?} which is no longer processing",
+                        reindex_id
+                    );
+                    continue;
+                }
             }
-        };
+        }
 
-        match result {
+        let result = migrate_points(
+            &migration_message.qdrant_point_ids,
+            migration_message.from_collection.clone(),
+            migration_message.to_collection.clone(),
+            migration_message.mode.clone(),
+        )
+        .await;
+
+        let points_in_message = migration_message.qdrant_point_ids.len() as i64;
+        let (points_migrated, points_failed) = match result {
             Ok(()) => {
                 log::info!(
                     "Succesfully Migrated {} Points",
                     migration_message.qdrant_point_ids.len()
                 );
+                (points_in_message, 0)
             }
             Err(e) => {
                 log::error!(
@@ -199,10 +242,449 @@ async fn main() -> Result<(), ServiceError> {
                     e,
                     serialized_message.clone()
                 );
-                redis::cmd("lpush")
+                let _ = redis::cmd("lpush")
                     .arg("collection_migration_error")
-                    .arg(serialized_message);
+                    .arg(serialized_message)
+                    .query_async::<redis::aio::MultiplexedConnection, ()>(&mut *redis_connection)
+                    .await;
+                (0, points_in_message)
+            }
+        };
+
+        if let Some(reindex_id) = migration_message.reindex_id {
+            // A reindex cancelled while these points were migrating already had its target
+            // collection cleaned up, so the points written by this message are removed here.
+            if let Ok(dataset_reindex) =
+                get_dataset_reindex_by_id_query(reindex_id, web_pool.clone()).await
+            {
+                if dataset_reindex.status == DatasetReindexStatus::Cancelled.to_string()
+                    && dataset_reindex.from_collection != dataset_reindex.to_collection
+                {
+                    if let Err(err) = delete_points_from_qdrant(
+                        migration_message.qdrant_point_ids.clone(),
+                        dataset_reindex.to_collection,
+                    )
+                    .await
+                    {
+                        log::error!("Failed to remove points of cancelled reindex {:?}", err);
+                    }
+                    continue;
+                }
+            }
+
+            if let Err(err) = add_dataset_reindex_progress_query(
+                reindex_id,
+                points_migrated,
+                points_failed,
+                web_pool.clone(),
+            )
+            .await
+            {
+                log::error!("Failed to record reindex progress {:?}", err);
+                continue;
+            }
+
+            finalize_dataset_reindex(
+                reindex_id,
+                web_pool.clone(),
+                redis_pool.clone(),
+                web_event_queue.clone(),
+            )
+            .await;
+        }
+    }
+}
+
+async fn queue_dataset_reindex(
+    msg: DatasetReindexMessage,
+    web_pool: web::Data<Pool>,
+    redis_pool: web::Data<RedisPool>,
+    event_queue: web::Data<EventQueue>,
+) -> Result<(), BroccoliError> {
+    let dataset_reindex =
+        get_dataset_reindex_query(msg.reindex_id, msg.dataset_id, web_pool.clone())
+            .await
+            .map_err(|e| BroccoliError::Job(e.to_string()))?;
+
+    // Cancelling a reindex queues it again so the points it already wrote get removed
+    if dataset_reindex.status == DatasetReindexStatus::Cancelled.to_string() {
+        log::info!(
+            "Removing the migrated points of cancelled reindex {:?}",
+            dataset_reindex.id
+        );
+        return delete_dataset_reindex_points_query(&dataset_reindex)
+            .await
+            .map_err(|e| BroccoliError::Job(e.to_string()));
+    }
+
+    if !start_dataset_reindex_query(dataset_reindex.id, web_pool.clone())
+        .await
+        .map_err(|e| BroccoliError::Job(e.to_string()))?
+    {
+        log::info!(
+            "Reindex {:?} is {}, not queueing its points",
+            dataset_reindex.id,
+            dataset_reindex.status
+        );
+        return Ok(());
+    }
+
+    let queue_result = async {
+        let mode = dataset_reindex.migration_mode()?;
+        let mut points_total = 0;
+        let mut offset = Some(uuid::Uuid::nil().to_string());
+
+        while let Some(cur_offset) = offset.clone() {
+            let current_reindex =
+                get_dataset_reindex_by_id_query(dataset_reindex.id, web_pool.clone()).await?;
+            if current_reindex.status == DatasetReindexStatus::Cancelled.to_string() {
+                log::info!("Reindex {:?} was cancelled", dataset_reindex.id);
+                return Ok(None);
+            }
+
+            let (qdrant_point_ids, new_offset) = scroll_qdrant_collection_ids(
+                dataset_reindex.from_collection.clone(),
+                Some(cur_offset),
+                Some(1000),
+                Some(Filter::must([Condition::matches(
+                    "dataset_id",
+                    dataset_reindex.dataset_id.to_string(),
+                )])),
+            )
+            .await?;
+
+            if !qdrant_point_ids.is_empty() {
+                let messages: Vec<String> = qdrant_point_ids
+                    .chunks(120)
+                    .map(|qdrant_point_ids| {
+                        serde_json::to_string(&MigratePointMessage {
+                            qdrant_point_ids: qdrant_point_ids.to_vec(),
+                            from_collection: dataset_reindex.from_collection.clone(),
+                            to_collection: dataset_reindex.to_collection.clone(),
+                            mode: mode.clone(),
+                            reindex_id: Some(dataset_reindex.id),
+                        })
+                        .expect("Failed to serialze MigratePoint message")
+                    })
+                    .collect();
+
+                let mut conn = redis_pool.get().await.map_err(|_| {
+                    ServiceError::BadRequest("Failed to get redis connection".to_string())
+                })?;
+
+                redis::cmd("lpush")
+                    .arg("collection_migration")
+                    .arg(&messages)
+                    .query_async::<redis::aio::MultiplexedConnection, ()>(&mut *conn)
+                    .await
+                    .map_err(|_| {
+                        ServiceError::BadRequest("Failed to send message to redis".to_string())
+                    })?;
+            }
+
+            points_total += qdrant_point_ids.len() as i64;
+            offset = new_offset.filter(|new_offset| !new_offset.is_empty());
+        }
+
+        Ok(Some(points_total)) as Result<Option<i64>, ServiceError>
+    }
+    .await;
+
+    match queue_result {
+        Ok(Some(points_total)) => {
+            log::info!(
+                "Queued {} points for reindex {:?}",
+                points_total,
+                dataset_reindex.id
+            );
+
+            set_dataset_reindex_total_query(dataset_reindex.id, points_total, web_pool.clone())
+                .await
+                .map_err(|e| BroccoliError::Job(e.to_string()))?;
+
+            finalize_dataset_reindex(dataset_reindex.id, web_pool, redis_pool, event_queue).await;
+        }
+        Ok(None) => {}
+        Err(err) => {
+            log::error!("Failed to queue reindex: {:?}", err);
+
+            update_dataset_reindex_status_query(
+                dataset_reindex.id,
+                DatasetReindexStatus::Failed,
+                Some(err.to_string()),
+                web_pool.clone(),
+            )
+            .await
+            .map_err(|e| BroccoliError::Job(e.to_string()))?;
+
+            if let Err(err) = delete_dataset_reindex_points_query(&dataset_reindex).await {
+                log::error!("Failed to remove points of failed reindex {:?}", err);
+            }
+
+            event_queue
+                .send(ClickHouseEvent::WorkerEvent(
+                    WorkerEvent::from_details(
+                        dataset_reindex.dataset_id,
+                        None,
+                        EventType::DatasetReindexFailed {
+                            reindex_id: dataset_reindex.id,
+                            error: err.to_string(),
+                        },
+                    )
+                    .into(),
+                ))
+                .await;
+        }
+    }
+
+    Ok(())
+}
+
+/// Flips the dataset configuration once every point of a reindex has been processed and removes
+/// the dataset's points from the collection it no longer uses.
+async fn finalize_dataset_reindex(
+    reindex_id: uuid::Uuid,
+    web_pool: web::Data<Pool>,
+    redis_pool: web::Data<RedisPool>,
+    event_queue: web::Data<EventQueue>,
+) {
+    let dataset_reindex = match finish_dataset_reindex_query(reindex_id, web_pool.clone()).await {
+        Ok(Some(dataset_reindex)) => dataset_reindex,
+        Ok(None) => return,
+        Err(err) => {
+            log::error!("Failed to finish reindex {:?}", err);
+            return;
+        }
+    };
+
+    if dataset_reindex.status == DatasetReindexStatus::Failed.to_string() {
+        if let Err(err) = delete_dataset_reindex_points_query(&dataset_reindex).await {
+            log::error!("Failed to remove points of failed reindex {:?}", err);
+        }
+
+        event_queue
+            .send(ClickHouseEvent::WorkerEvent(
+                WorkerEvent::from_details(
+                    dataset_reindex.dataset_id,
+                    None,
+                    EventType::DatasetReindexFailed {
+                        reindex_id: dataset_reindex.id,
+                        error: dataset_reindex.error.clone().unwrap_or_default(),
+                    },
+                )
+                .into(),
+            ))
+            .await;
+        return;
+    }
+
+    let applied_at =
+        match apply_dataset_reindex_configuration_query(&dataset_reindex, web_pool.clone()).await {
+            Ok(applied_at) => applied_at,
+            Err(err) => {
+                log::error!("Failed to apply reindex configuration {:?}", err);
+
+                if let Err(err) = delete_dataset_reindex_points_query(&dataset_reindex).await {
+                    log::error!("Failed to remove points of failed reindex {:?}", err);
+                }
+
+                let _ = update_dataset_reindex_status_query(
+                    dataset_reindex.id,
+                    DatasetReindexStatus::Failed,
+                    Some(err.to_string()),
+                    web_pool.clone(),
+                )
+                .await;
+
+                event_queue
+                    .send(ClickHouseEvent::WorkerEvent(
+                        WorkerEvent::from_details(
+                            dataset_reindex.dataset_id,
+                            None,
+                            EventType::DatasetReindexFailed {
+                                reindex_id: dataset_reindex.id,
+                                error: err.to_string(),
+                            },
+                        )
+                        .into(),
+                    ))
+                    .await;
+                return;
             }
+        };
+
+    if let Err(err) =
+        replay_dataset_reindex_writes(&dataset_reindex, applied_at, web_pool.clone()).await
+    {
+        log::error!(
+            "Failed to replay writes made during reindex {:?}: {:?}",
+            dataset_reindex.id,
+            err
+        );
+    }
+
+    if let Err(err) =
+        migrate_dataset_group_centroids_query(&dataset_reindex, web_pool.clone(), redis_pool).await
+    {
+        log::error!(
+            "Failed to migrate group centroids of reindex {:?}: {:?}",
+            dataset_reindex.id,
+            err
+        );
+    }
+
+    if dataset_reindex.from_collection != dataset_reindex.to_collection {
+        if let Err(err) = delete_dataset_points_from_collection_query(
+            dataset_reindex.dataset_id,
+            dataset_reindex.from_collection.clone(),
+        )
+        .await
+        {
+            log::error!(
+                "Failed to remove reindexed points from {}: {:?}",
+                dataset_reindex.from_collection,
+                err
+            );
+        }
+    }
+
+    log::info!("Completed reindex {:?}", dataset_reindex.id);
+
+    event_queue
+        .send(ClickHouseEvent::WorkerEvent(
+            WorkerEvent::from_details(
+                dataset_reindex.dataset_id,
+                None,
+                EventType::DatasetReindexCompleted {
+                    reindex_id: dataset_reindex.id,
+                    points_migrated: dataset_reindex.points_migrated,
+                },
+            )
+            .into(),
+        ))
+        .await;
+}
+
+/// Chunks written while a reindex was running only reached the old vectors. Once the dataset
+/// points at the new vectors, the chunks created or updated between the start of the reindex and
+/// the switch are migrated again and points of chunks deleted in the meantime are removed.
+async fn replay_dataset_reindex_writes(
+    dataset_reindex: &DatasetReindex,
+    applied_at: chrono::NaiveDateTime,
+    web_pool: web::Data<Pool>,
+) -> Result<(), ServiceError> {
+    let started_at = match dataset_reindex.started_at {
+        Some(started_at) => started_at,
+        None => return Ok(()),
+    };
+    let mode = dataset_reindex.migration_mode()?;
+
+    // Writes are found through postgres, which does not hold the chunks of qdrant only datasets
+    let dataset = get_dataset_by_id_query(dataset_reindex.dataset_id, web_pool.clone()).await?;
+    if DatasetConfiguration::from_json(dataset.server_configuration).QDRANT_ONLY {
+        return Ok(());
+    }
+
+    let mut offset = None;
+    loop {
+        let (qdrant_point_ids, next_offset) = get_chunk_point_ids_updated_between_query(
+            dataset_reindex.dataset_id,
+            started_at,
+            applied_at,
+            offset,
+            120,
+            web_pool.clone(),
+        )
+        .await?;
+
+        if qdrant_point_ids.is_empty() {
+            break;
+        }
+
+        log::info!(
+            "Replaying {} points written during reindex {:?}",
+            qdrant_point_ids.len(),
+            dataset_reindex.id
+        );
+        migrate_points(
+            &qdrant_point_ids,
+            dataset_reindex.from_collection.clone(),
+            dataset_reindex.to_collection.clone(),
+            mode.clone(),
+        )
+        .await?;
+
+        offset = next_offset;
+    }
+
+    if dataset_reindex.from_collection != dataset_reindex.to_collection {
+        delete_orphaned_dataset_points_query(
+            dataset_reindex.dataset_id,
+            dataset_reindex.to_collection.clone(),
+            web_pool,
+        )
+        .await?;
+    }
+
+    Ok(())
+}
+
+/// Reads the points from `from_collection` and writes them to `to_collection` with the vectors
+/// of the migration mode.
+async fn migrate_points(
+    qdrant_point_ids: &[uuid::Uuid],
+    from_collection: String,
+    to_collection: String,
+    mode: MigrationMode,
+) -> Result<(), ServiceError> {
+    let qdrant_client = get_qdrant_connection(
+        Some(get_env!("QDRANT_URL", "QDRANT_URL should be set")),
+        Some(get_env!("QDRANT_API_KEY", "QDRANT_API_KEY should be set")),
+    )
+    .await?;
+
+    // Get all points in message including Payload & Friends
+    let points = qdrant_client
+        .get_points(
+            GetPointsBuilder::new(
+                from_collection,
+                qdrant_point_ids
+                    .iter()
+                    .map(|uuid| PointId::from(uuid.to_string()))
+                    .collect_vec(),
+            )
+            .with_payload(true)
+            .with_vectors(true)
+            .build(),
+        )
+        .await
+        .map_err(|_err| {
+            ServiceError::BadRequest("Failed to search_points from qdrant".to_string())
+        })?
+        .result;
+
+    if points.is_empty() {
+        return Ok(());
+    }
+
+    match mode {
+        MigrationMode::BM25 { average_len, k, b } => {
+            migrate_bm25(qdrant_client, points, to_collection, average_len, b, k).await
+        }
+        MigrationMode::Reembed {
+            embedding_model_name,
+            embedding_base_url,
+            embedding_size,
+        } => {
+            reembed_points(
+                qdrant_client,
+                points,
+                to_collection,
+                embedding_model_name,
+                embedding_base_url,
+                embedding_size,
+            )
+            .await
         }
     }
 }
@@ -272,7 +754,9 @@ pub async fn migrate_bm25(
 
 #[allow(clippy::field_reassign_with_default)]
 pub async fn reembed_points(
+    qdrant_client: Qdrant,
     points: Vec<RetrievedPoint>,
+    to_collection: String,
     embedding_model_name: String,
     embedding_base_url: String,
     embedding_size: usize,
@@ -334,50 +818,63 @@ pub async fn reembed_points(
         0.0,
     );
 
-    let new_points = izip!(
-        points.clone().iter(),
-        embedding_vectors.iter(),
-        splade_vectors.iter(),
-        bm25_vectors.iter()
-    )
-    .map(|(point, embedding_vector, splade_vector, bm25_vector)| {
-        let vector_name = match embedding_vector.len() {
-            384 => "384_vectors",
-            512 => "512_vectors",
-            768 => "768_vectors",
-            1024 => "1024_vectors",
-            3072 => "3072_vectors",
-            1536 => "1536_vectors",
-            _ => "768_vectors",
+    let new_points =
+        izip!(
+            points.clone().iter(),
+            embedding_vectors.iter(),
+            splade_vectors.iter(),
+            bm25_vectors.iter()
+        )
+        .map(|(point, embedding_vector, splade_vector, bm25_vector)| {
+            let vector_name = match embedding_vector.len() {
+                384 => "384_vectors",
+                512 => "512_vectors",
+                768 => "768_vectors",
+                1024 => "1024_vectors",
+                3072 => "3072_vectors",
+                1536 => "1536_vectors",
+                _ => "768_vectors",
+            };
+
+            // Keep the BM25 vector computed with the dataset's own parameters when the point has one.
+            let existing_bm25_vector = match &point.vectors {
+            Some(qdrant::Vectors {
+                vectors_options:
+                    Some(qdrant::vectors::VectorsOptions::Vectors(qdrant::NamedVectors { vectors })),
+            }) => vectors.get("bm25_vectors").cloned(),
+            _ => None,
         };
 
         let vector_payload = HashMap::from([
-            (
-                "sparse_vectors".to_string(),
-                Vector::from(splade_vector.clone()),
-            ),
-            (
-                vector_name.to_string(),
-                Vector::from(embedding_vector.clone()),
-            ),
-            (
-                "bm25_vectors".to_string(),
-                Vector::from(bm25_vector.clone()),
-            ),
-        ]);
-
-        PointStruct::new(
-            point
-                .id
-                .clone()
-                .unwrap_or(PointId::from(uuid::Uuid::new_v4().to_string())),
-            vector_payload,
-            point.payload.clone(),
-        )
-    })
-    .collect::<Vec<PointStruct>>();
+                (
+                    "sparse_vectors".to_string(),
+                    Vector::from(splade_vector.clone()),
+                ),
+                (
+                    vector_name.to_string(),
+                    Vector::from(embedding_vector.clone()),
+                ),
+                (
+                    "bm25_vectors".to_string(),
+                    existing_bm25_vector.unwrap_or(Vector::from(bm25_vector.clone())),
+                ),
+            ]);
+
+            PointStruct::new(
+                point
+                    .id
+                    .clone()
+                    .unwrap_or(PointId::from(uuid::Uuid::new_v4().to_string())),
+                vector_payload,
+                point.payload.clone(),
+            )
+        })
+        .collect::<Vec<PointStruct>>();
 
-    bulk_upsert_qdrant_points_query(new_points, mock_dataset_config).await?;
+    qdrant_client
+        .upsert_points(UpsertPointsBuilder::new(to_collection, new_points))
+        .await
+        .map_err(|e| ServiceError::BadRequest(format!("Failed to upsert points {:?}", e)))?;
 
     Ok(())
 }
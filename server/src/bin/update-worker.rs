@@ -2,14 +2,26 @@ use actix_web::web;
 use broccoli_queue::error::BroccoliError;
 use broccoli_queue::queue::BroccoliQueue;
 use diesel_async::pooled_connection::{AsyncDieselConnectionManager, ManagerConfig};
-use trieve_server::data::models::{ChunkBoost, EventType, WorkerEvent};
+use itertools::Itertools;
+use trieve_server::data::models::{
+    ChunkBoost, ChunkChangeOperation, EventType, NewChunkChange, WorkerEvent,
+};
 use trieve_server::errors::ServiceError;
 use trieve_server::handlers::group_handler::dataset_owns_group;
+use trieve_server::operators::change_feed_operator::{
+    get_chunk_fields_changed, get_chunk_fields_set, insert_chunk_changes_query,
+};
 use trieve_server::operators::chunk_operator::{
-    update_chunk_boost_query, update_chunk_metadata_query,
+    get_metadata_from_id_query, update_chunk_boost_query, update_chunk_metadata_query,
 };
 use trieve_server::operators::clickhouse_operator::ClickHouseEvent;
 use trieve_server::operators::dataset_operator::get_dataset_config_query;
+use trieve_server::operators::dedup_operator::update_chunk_fingerprint_query;
+use trieve_server::operators::group_aggregate_operator::{
+    get_group_ids_for_chunks_query, refresh_group_aggregates_query,
+};
+use trieve_server::operators::group_centroid_operator::mark_group_centroids_stale_query;
+use trieve_server::operators::group_summary_operator::mark_group_summaries_stale_query;
 use trieve_server::operators::model_operator::{
     get_bm25_embeddings, get_dense_vector, get_sparse_vectors,
 };
@@ -18,8 +30,10 @@ use trieve_server::operators::qdrant_operator::update_qdrant_point_query;
 
 use std::error::Error;
 use trieve_server::{
-    data::models::Pool, establish_connection, get_env,
-    handlers::chunk_handler::UpdateIngestionMessage, operators::clickhouse_operator::EventQueue,
+    data::models::{Pool, RedisPool},
+    establish_connection, get_env,
+    handlers::chunk_handler::UpdateIngestionMessage,
+    operators::clickhouse_operator::EventQueue,
 };
 
 #[tokio::main]
@@ -47,7 +61,7 @@ async fn main() -> Result<(), Box<dyn Error>> {
 
     let web_pool = actix_web::web::Data::new(pool.clone());
 
-    let event_queue = if std::env::var("USE_ANALYTICS")
+    let mut event_queue = if std::env::var("USE_ANALYTICS")
         .unwrap_or("false".to_string())
         .parse()
         .unwrap_or(false)
@@ -71,6 +85,7 @@ async fn main() -> Result<(), Box<dyn Error>> {
         log::info!("Analytics disabled");
         EventQueue::default()
     };
+    event_queue.start_webhook_service();
 
     let redis_url = get_env!("REDIS_URL", "REDIS_URL is not set");
 
@@ -79,6 +94,18 @@ async fn main() -> Result<(), Box<dyn Error>> {
         .parse()
         .unwrap_or(2);
 
+    let redis_manager =
+        bb8_redis::RedisConnectionManager::new(redis_url).expect("Failed to connect to redis");
+
+    let redis_pool = bb8_redis::bb8::Pool::builder()
+        .max_size(redis_connections)
+        .connection_timeout(std::time::Duration::from_secs(2))
+        .build(redis_manager)
+        .await
+        .expect("Failed to create redis pool");
+
+    let web_redis_pool = actix_web::web::Data::new(redis_pool);
+
     let queue = BroccoliQueue::builder(redis_url)
         .pool_connections(redis_connections.try_into().unwrap())
         .failed_message_retry_strategy(Default::default())
@@ -93,7 +120,8 @@ async fn main() -> Result<(), Box<dyn Error>> {
             {
                 move |msg| {
                     let pool = web_pool.clone();
-                    async move { update_chunk(msg.payload, pool.clone()).await }
+                    let redis_pool = web_redis_pool.clone();
+                    async move { update_chunk(msg.payload, pool.clone(), redis_pool).await }
                 }
             },
             {
@@ -147,6 +175,7 @@ async fn main() -> Result<(), Box<dyn Error>> {
 async fn update_chunk(
     payload: UpdateIngestionMessage,
     pool: web::Data<Pool>,
+    redis_pool: web::Data<RedisPool>,
 ) -> Result<(), BroccoliError> {
     let dataset_config = get_dataset_config_query(payload.dataset_id, pool.clone()).await?;
     let content = match payload.convert_html_to_text.unwrap_or(true) {
@@ -170,6 +199,26 @@ async fn update_chunk(
 
     let chunk_metadata = payload.chunk_metadata.clone();
 
+    let prev_chunk_metadata =
+        get_metadata_from_id_query(chunk_metadata.id, payload.dataset_id, pool.clone())
+            .await
+            .ok();
+    let mut changed_fields = match prev_chunk_metadata {
+        Some(prev_chunk_metadata) => {
+            get_chunk_fields_changed(&prev_chunk_metadata, &chunk_metadata.clone().into())
+        }
+        None => get_chunk_fields_set(&chunk_metadata.clone().into(), false),
+    };
+    if payload.group_ids.is_some() {
+        changed_fields.push("group_ids".to_string());
+    }
+    if payload.fulltext_boost.is_some() {
+        changed_fields.push("fulltext_boost".to_string());
+    }
+    if payload.semantic_boost.is_some() {
+        changed_fields.push("semantic_boost".to_string());
+    }
+
     let embedding_vector = match dataset_config.SEMANTIC_ENABLED {
         true => {
             let embedding = get_dense_vector(
@@ -206,7 +255,7 @@ async fn update_chunk(
         && std::env::var("BM25_ACTIVE").unwrap_or("false".to_string()) == "true"
     {
         let vecs = get_bm25_embeddings(
-            vec![(content, payload.fulltext_boost.clone())],
+            vec![(content.clone(), payload.fulltext_boost.clone())],
             dataset_config.BM25_AVG_LEN,
             dataset_config.BM25_B,
             dataset_config.BM25_K,
@@ -217,6 +266,9 @@ async fn update_chunk(
         None
     };
 
+    let prev_group_ids =
+        get_group_ids_for_chunks_query(vec![chunk_metadata.id], pool.clone()).await?;
+
     if let Some(group_ids) = payload.group_ids {
         let mut chunk_group_ids: Vec<uuid::Uuid> = vec![];
         for group_id in group_ids {
@@ -242,7 +294,7 @@ async fn update_chunk(
             payload.dataset_id,
             splade_vector,
             bm25_vector,
-            dataset_config,
+            dataset_config.clone(),
             pool.clone(),
         )
         .await
@@ -264,13 +316,62 @@ async fn update_chunk(
             payload.dataset_id,
             splade_vector,
             bm25_vector,
-            dataset_config,
+            dataset_config.clone(),
             pool.clone(),
         )
         .await
         .map_err(|err| ServiceError::BadRequest(err.to_string()))?;
     }
 
+    // Dedup matches against fingerprints, so they have to follow the chunk's content. Updates
+    // always carry the chunk's content, re-fingerprinting unchanged content is a no-op.
+    if dataset_config.DEDUP_POLICY.is_some() && !dataset_config.QDRANT_ONLY {
+        update_chunk_fingerprint_query(
+            payload.chunk_metadata.id,
+            payload.dataset_id,
+            &content,
+            pool.clone(),
+        )
+        .await?;
+    }
+
+    // Groups the chunk was removed from and groups it is now in both need their aggregates and
+    // centroids recomputed
+    let cur_group_ids =
+        get_group_ids_for_chunks_query(vec![payload.chunk_metadata.id], pool.clone()).await?;
+    let changed_group_ids = prev_group_ids
+        .iter()
+        .filter(|group_id| !cur_group_ids.contains(group_id))
+        .chain(
+            cur_group_ids
+                .iter()
+                .filter(|group_id| !prev_group_ids.contains(group_id)),
+        )
+        .copied()
+        .collect::<Vec<uuid::Uuid>>();
+    let affected_group_ids = prev_group_ids
+        .into_iter()
+        .chain(cur_group_ids)
+        .unique()
+        .collect::<Vec<uuid::Uuid>>();
+    refresh_group_aggregates_query(payload.dataset_id, affected_group_ids.clone(), pool.clone())
+        .await?;
+    mark_group_centroids_stale_query(
+        &dataset_config,
+        payload.dataset_id,
+        affected_group_ids,
+        redis_pool.clone(),
+    )
+    .await?;
+    // Summaries only depend on which chunks are in the group
+    mark_group_summaries_stale_query(
+        &dataset_config,
+        payload.dataset_id,
+        changed_group_ids,
+        redis_pool,
+    )
+    .await?;
+
     // If boosts are changed, reflect changes to chunk_boosts table
     if payload.fulltext_boost.is_some() || payload.semantic_boost.is_some() {
         update_chunk_boost_query(
@@ -281,10 +382,22 @@ async fn update_chunk(
                 semantic_boost_phrase: payload.semantic_boost.clone().map(|x| x.phrase),
                 semantic_boost_factor: payload.semantic_boost.map(|x| x.distance_factor as f64),
             },
-            pool,
+            pool.clone(),
         )
         .await?;
     }
 
+    insert_chunk_changes_query(
+        vec![NewChunkChange::from_details(
+            payload.dataset_id,
+            ChunkChangeOperation::Update,
+            Some(payload.chunk_metadata.id),
+            payload.chunk_metadata.tracking_id.clone(),
+            changed_fields,
+        )],
+        pool,
+    )
+    .await?;
+
     Ok(())
 }
@@ -445,7 +445,7 @@ pub struct ChunkMetadata {
     pub id: uuid::Uuid,
     /// Link to the chunk, should be a URL
     pub link: Option<String>,
-    #[serde(skip_serializing)]
+    #[serde(skip_serializing, default = "uuid::Uuid::new_v4")]
     pub qdrant_point_id: uuid::Uuid,
     /// Timestamp of the creation of the chunk
     pub created_at: chrono::NaiveDateTime,
@@ -778,6 +778,7 @@ impl From<ChunkMetadataWithScore> for ScoreChunk {
             chunk: NewChunkMetadataTypes::Metadata(val.into()),
             highlights: None,
             score,
+            expanded_content: None,
         }
     }
 }
@@ -896,6 +897,21 @@ pub struct ScoreChunk {
     pub chunk: NewChunkMetadataTypes,
     pub highlights: Option<Vec<String>>,
     pub score: f32,
+    /// The chunk together with its neighboring chunks from the same document. Only present when a context_window is specified.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expanded_content: Option<ExpandedContent>,
+}
+
+#[derive(Serialize, Deserialize, Debug, ToSchema, Clone, PartialEq)]
+#[schema(example = json!({
+    "chunk_ids": ["d290f1ee-6c54-4b01-90e6-d701748f0851", "e3e3e3e3-e3e3-e3e3-e3e3-e3e3e3e3e3e3"],
+    "content": "<p>Previous paragraph</p>\n\n<p>Some HTML content</p>"
+}))]
+pub struct ExpandedContent {
+    /// Ids of the chunks in the window in document order. Results whose windows overlap share the same merged window.
+    pub chunk_ids: Vec<uuid::Uuid>,
+    /// The chunk_html of the chunks in the window joined in document order.
+    pub content: String,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
@@ -993,6 +1009,7 @@ impl From<ScoreChunkDTO> for ScoreChunk {
             chunk: score_chunk_dto.metadata[0].clone().into(),
             highlights: score_chunk_dto.highlights,
             score: score_chunk_dto.score as f32,
+            expanded_content: None,
         }
     }
 }
@@ -1843,6 +1860,7 @@ pub struct ChunkGroup {
     pub tracking_id: Option<String>,
     pub metadata: Option<serde_json::Value>,
     pub tag_set: Option<Vec<Option<String>>>,
+    pub parent_id: Option<uuid::Uuid>,
 }
 
 impl ChunkGroup {
@@ -1864,6 +1882,7 @@ impl ChunkGroup {
             tracking_id,
             metadata,
             tag_set,
+            parent_id: None,
         }
     }
 
@@ -1886,6 +1905,7 @@ impl ChunkGroup {
             tracking_id,
             metadata,
             tag_set,
+            parent_id: None,
         }
     }
 }
@@ -1910,6 +1930,7 @@ pub struct ChunkGroupAndFileId {
     pub tag_set: Option<Vec<Option<String>>>,
     pub metadata: Option<serde_json::Value>,
     pub file_id: Option<uuid::Uuid>,
+    pub parent_id: Option<uuid::Uuid>,
     pub created_at: chrono::NaiveDateTime,
     pub updated_at: chrono::NaiveDateTime,
 }
@@ -1926,6 +1947,7 @@ impl From<ChunkGroupAndFileId> for ChunkGroup {
             metadata: group.metadata,
             created_at: group.created_at,
             updated_at: group.updated_at,
+            parent_id: group.parent_id,
         }
     }
 }
@@ -1941,6 +1963,7 @@ impl ChunkGroupAndFileId {
             tag_set: group.tag_set,
             metadata: group.metadata,
             file_id,
+            parent_id: group.parent_id,
             created_at: group.created_at,
             updated_at: group.updated_at,
         }
@@ -1957,6 +1980,7 @@ impl ChunkGroupAndFileId {
             metadata: self.metadata.clone(),
             created_at: self.created_at,
             updated_at: self.updated_at,
+            parent_id: None,
         }
     }
 }
@@ -2057,21 +2081,156 @@ pub enum ChunkReqPayloadFields {
     Weight,
     #[serde(rename = "boost_phrase")]
     BoostPhrase,
+    #[serde(rename = "semantic_boost_phrase")]
+    SemanticBoostPhrase,
+    #[serde(rename = "chunk_html")]
+    ChunkHtml,
+    #[serde(rename = "semantic_content")]
+    SemanticContent,
+    #[serde(rename = "fulltext_content")]
+    FulltextContent,
+    #[serde(rename = "metadata")]
+    Metadata,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+/// Coerce the value of a column or field before it is mapped. Values which cannot be coerced are skipped.
+pub enum CsvJsonlFieldTransform {
+    /// Parse the value as an integer.
+    Int,
+    /// Parse the value as a float.
+    Float,
+    /// Parse the value as a boolean. Accepts true/false, yes/no and 1/0.
+    Bool,
+    /// Parse the value as a date and emit it as an ISO 8601 date time without timezone. If `format` is not specified, common date formats and unix timestamps are detected automatically.
+    Date {
+        /// chrono strftime format string to parse the value with, e.g. `%d/%m/%Y`.
+        format: Option<String>,
+    },
+    /// Split the value into a list of strings. Defaults to splitting on `,`.
+    List { delimiter: Option<String> },
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+#[schema(example = json!({
+    "csv_jsonl_field": "price",
+    "chunk_req_payload_field": "metadata",
+    "metadata_key": "pricing.amount",
+    "transform": { "type": "float" }
+}))]
 /// Express a mapping between a column or field in a CSV or JSONL field and a key in the ChunkReqPayload created for each row or object.
 pub struct ChunkReqPayloadMapping {
-    /// The column or field in the CSV or JSONL file that you want to map to a key in the ChunkReqPayload
+    /// The column or field in the CSV or JSONL file that you want to map to a key in the ChunkReqPayload. Can be omitted when `template` is specified.
+    #[serde(default)]
     pub csv_jsonl_field: String,
     /// The key in the ChunkReqPayload that you want to map the column or field to.
     pub chunk_req_payload_field: ChunkReqPayloadFields,
+    /// Jinja template rendered with the row's columns or fields to compose the value from several of them, e.g. `<h1>{{ title }}</h1><p>{{ body }}</p>`. Columns with names which are not valid identifiers can be accessed with `{{ row["Column Name"] }}`. Takes precedence over `csv_jsonl_field`.
+    pub template: Option<String>,
+    /// Only used with the `metadata` field. The key the value will be set to in the chunk's metadata. Nested keys can be specified with dots, e.g. `product.price`. Defaults to `csv_jsonl_field`.
+    pub metadata_key: Option<String>,
+    /// Coerce the value of the column or field to a different type before it is mapped.
+    pub transform: Option<CsvJsonlFieldTransform>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
-/// Specify all of the mappings between columns or fields in a CSV or JSONL file and keys in the ChunkReqPayload. Array fields like tag_set, image_urls, and group_tracking_ids can have multiple mappings. Boost phrase can also have multiple mappings which get concatenated. Other fields can only have one mapping and only the last mapping will be used.
+/// Specify all of the mappings between columns or fields in a CSV or JSONL file and keys in the ChunkReqPayload. Array fields like tag_set, image_urls, and group_tracking_ids can have multiple mappings. Boost phrase can also have multiple mappings which get concatenated. Metadata can have one mapping per key; if no metadata mappings are specified, the whole row is used as the metadata. Other fields can only have one mapping and only the last mapping will be used.
 pub struct ChunkReqPayloadMappings(pub Vec<ChunkReqPayloadMapping>);
 
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, ToSchema, PartialEq, Eq, Default, Display)]
+#[serde(rename_all = "snake_case")]
+/// Format of a file uploaded for bulk chunk creation. CSV and JSONL files are read line by line, Parquet files are read one row group at a time and XLSX/ODS files are read one sheet at a time.
+pub enum CsvJsonlFileFormat {
+    #[default]
+    #[display(fmt = "csv")]
+    Csv,
+    #[display(fmt = "jsonl")]
+    Jsonl,
+    #[display(fmt = "parquet")]
+    Parquet,
+    #[display(fmt = "xlsx")]
+    Xlsx,
+    #[display(fmt = "ods")]
+    Ods,
+}
+
+impl CsvJsonlFileFormat {
+    /// Infers the format from the extension of the file name, falling back to CSV.
+    pub fn from_file_name(file_name: &str) -> Self {
+        let extension = file_name
+            .rsplit_once('.')
+            .map(|(_, extension)| extension.to_lowercase())
+            .unwrap_or_default();
+
+        match extension.as_str() {
+            "jsonl" | "ndjson" | "json" => CsvJsonlFileFormat::Jsonl,
+            "parquet" | "pq" => CsvJsonlFileFormat::Parquet,
+            "xlsx" | "xlsm" | "xlsb" | "xls" => CsvJsonlFileFormat::Xlsx,
+            "ods" => CsvJsonlFileFormat::Ods,
+            _ => CsvJsonlFileFormat::Csv,
+        }
+    }
+
+    pub fn is_spreadsheet(&self) -> bool {
+        matches!(self, CsvJsonlFileFormat::Xlsx | CsvJsonlFileFormat::Ods)
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default, ToSchema)]
+#[schema(example = json!({
+    "sheet_name": "Products",
+    "header_row": 2
+}))]
+/// Options for reading XLSX and ODS files.
+pub struct SpreadsheetOptions {
+    /// Name of the sheet to read. Takes precedence over `sheet_index`.
+    pub sheet_name: Option<String>,
+    /// Zero-based index of the sheet to read. Defaults to the first sheet.
+    pub sheet_index: Option<usize>,
+    /// One-based number of the row containing the column names. Rows above it are ignored. Defaults to 1.
+    pub header_row: Option<usize>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema, PartialEq)]
+/// A row or object of a bulk upload file which could not be turned into a chunk.
+pub struct CsvJsonlRowFailure {
+    /// One-based number of the row, line or record in the file.
+    pub row: usize,
+    /// Reason the row could not be processed.
+    pub error: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, ToSchema, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum CsvJsonlRowFilterOperator {
+    Eq,
+    Ne,
+    Gt,
+    Gte,
+    Lt,
+    Lte,
+    Contains,
+    Exists,
+    NotExists,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+#[schema(example = json!({
+    "field": "status",
+    "operator": "eq",
+    "value": "published"
+}))]
+/// Condition a row or object must satisfy to be turned into a chunk.
+pub struct CsvJsonlRowFilter {
+    /// The column or field in the CSV or JSONL file to compare.
+    pub field: String,
+    /// The comparison to apply. `gt`, `gte`, `lt` and `lte` compare numerically, `contains` checks for a substring or an array element, `exists` and `not_exists` check for a non-null value and ignore `value`.
+    pub operator: CsvJsonlRowFilterOperator,
+    /// The value to compare against.
+    pub value: Option<serde_json::Value>,
+}
+
 #[derive(
     Debug, Default, Serialize, Deserialize, Selectable, Queryable, Insertable, Clone, ToSchema,
 )]
@@ -2086,6 +2245,8 @@ pub struct ChunkReqPayloadMappings(pub Vec<ChunkReqPayloadMapping>);
     "link": "https://trieve.ai",
     "time_stamp": "2021-01-01 00:00:00.000",
     "dataset_id": "e3e3e3e3-e3e3-e3e3-e3e3-e3e3e3e3e3e3",
+    "tracking_id": "handbook",
+    "version": 1,
 }))]
 #[diesel(table_name = files)]
 pub struct File {
@@ -2099,6 +2260,14 @@ pub struct File {
     pub dataset_id: uuid::Uuid,
     pub tag_set: Option<Vec<Option<String>>>,
     pub size: i64,
+    #[serde(default)]
+    pub tracking_id: Option<String>,
+    #[serde(default = "default_file_version")]
+    pub version: i32,
+}
+
+fn default_file_version() -> i32 {
+    1
 }
 
 impl File {
@@ -2130,6 +2299,57 @@ impl File {
                     .naive_local()
             }),
             dataset_id,
+            tracking_id: None,
+            version: 1,
+        }
+    }
+}
+
+/// A previous version of a file. The contents of the version are stored in S3 under the id of the version.
+#[derive(Debug, Serialize, Deserialize, Selectable, Queryable, Insertable, Clone, ToSchema)]
+#[schema(example = json!({
+    "id": "e3e3e3e3-e3e3-e3e3-e3e3-e3e3e3e3e3e3",
+    "file_id": "e3e3e3e3-e3e3-e3e3-e3e3-e3e3e3e3e3e3",
+    "dataset_id": "e3e3e3e3-e3e3-e3e3-e3e3-e3e3e3e3e3e3",
+    "version": 1,
+    "file_name": "file.txt",
+    "size": 1000,
+    "metadata": {"key": "value"},
+    "link": "https://trieve.ai",
+    "time_stamp": "2021-01-01 00:00:00.000",
+    "tag_set": ["tag1", "tag2"],
+    "created_at": "2021-01-01 00:00:00.000",
+}))]
+#[diesel(table_name = file_versions)]
+pub struct FileVersion {
+    pub id: uuid::Uuid,
+    pub file_id: uuid::Uuid,
+    pub dataset_id: uuid::Uuid,
+    pub version: i32,
+    pub file_name: String,
+    pub size: i64,
+    pub metadata: Option<serde_json::Value>,
+    pub link: Option<String>,
+    pub time_stamp: Option<chrono::NaiveDateTime>,
+    pub tag_set: Option<Vec<Option<String>>>,
+    pub created_at: chrono::NaiveDateTime,
+}
+
+impl FileVersion {
+    /// Snapshots the current state of a file as a version
+    pub fn from_file(file: &File) -> Self {
+        FileVersion {
+            id: uuid::Uuid::new_v4(),
+            file_id: file.id,
+            dataset_id: file.dataset_id,
+            version: file.version,
+            file_name: file.file_name.clone(),
+            size: file.size,
+            metadata: file.metadata.clone(),
+            link: file.link.clone(),
+            time_stamp: file.time_stamp,
+            tag_set: file.tag_set.clone(),
+            created_at: chrono::Utc::now().naive_local(),
         }
     }
 }
@@ -2220,6 +2440,8 @@ pub struct FileDTO {
     pub link: Option<String>,
     pub time_stamp: Option<chrono::NaiveDateTime>,
     pub tag_set: Option<Vec<String>>,
+    pub tracking_id: Option<String>,
+    pub version: i32,
 }
 
 impl From<File> for FileDTO {
@@ -2237,6 +2459,8 @@ impl From<File> for FileDTO {
             tag_set: file
                 .tag_set
                 .map(|tags| tags.into_iter().flatten().collect()),
+            tracking_id: file.tracking_id,
+            version: file.version,
         }
     }
 }
@@ -2311,6 +2535,12 @@ pub enum EventType {
     },
     #[display(fmt = "file_upload_failed")]
     FileUploadFailed { file_id: uuid::Uuid, error: String },
+    #[display(fmt = "pdf2md_ocr_completed")]
+    Pdf2MdOcrCompleted {
+        file_id: uuid::Uuid,
+        task_id: uuid::Uuid,
+        pages_processed: u64,
+    },
     #[display(fmt = "chunks_uploaded")]
     ChunksUploaded {
         chunk_ids: Vec<uuid::Uuid>,
@@ -2367,6 +2597,60 @@ pub enum EventType {
         file_id: uuid::Uuid,
         chunks_created: usize,
     },
+    #[display(fmt = "csv_jsonl_rows_failed")]
+    CsvJsonlRowsFailed {
+        file_id: uuid::Uuid,
+        total_failed_rows: usize,
+        failed_rows: Vec<CsvJsonlRowFailure>,
+    },
+    #[display(fmt = "dataset_clone_completed")]
+    DatasetCloneCompleted {
+        clone_id: uuid::Uuid,
+        source_dataset_id: uuid::Uuid,
+        vectors_reused: bool,
+        groups_cloned: i64,
+        files_cloned: i64,
+        chunks_cloned: i64,
+    },
+    #[display(fmt = "dataset_clone_failed")]
+    DatasetCloneFailed {
+        clone_id: uuid::Uuid,
+        source_dataset_id: uuid::Uuid,
+        error: String,
+    },
+    #[display(fmt = "dataset_export_completed")]
+    DatasetExportCompleted {
+        export_id: uuid::Uuid,
+        chunks_exported: i64,
+    },
+    #[display(fmt = "dataset_export_failed")]
+    DatasetExportFailed {
+        export_id: uuid::Uuid,
+        error: String,
+    },
+    #[display(fmt = "dataset_import_completed")]
+    DatasetImportCompleted {
+        import_id: uuid::Uuid,
+        vectors_reused: bool,
+        groups_imported: i64,
+        files_imported: i64,
+        chunks_imported: i64,
+    },
+    #[display(fmt = "dataset_import_failed")]
+    DatasetImportFailed {
+        import_id: uuid::Uuid,
+        error: String,
+    },
+    #[display(fmt = "dataset_reindex_completed")]
+    DatasetReindexCompleted {
+        reindex_id: uuid::Uuid,
+        points_migrated: i64,
+    },
+    #[display(fmt = "dataset_reindex_failed")]
+    DatasetReindexFailed {
+        reindex_id: uuid::Uuid,
+        error: String,
+    },
     #[display(fmt = "video_uploaded")]
     VideoUploaded {
         video_id: String,
@@ -2387,6 +2671,27 @@ pub enum EventType {
     EtlCompleted,
     #[display(fmt = "etl_failed")]
     EtlFailed { error: String },
+    #[display(fmt = "duplicate_chunks_skipped")]
+    DuplicateChunksSkipped { duplicates: Vec<DuplicateChunk> },
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, ToSchema, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum DuplicateMatch {
+    Exact,
+    NearDuplicate,
+    Vector,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+pub struct DuplicateChunk {
+    /// Id the skipped chunk would have been created with
+    pub chunk_id: uuid::Uuid,
+    pub tracking_id: Option<String>,
+    /// Id of the existing chunk it duplicates
+    pub duplicate_of: uuid::Uuid,
+    pub match_type: DuplicateMatch,
+    pub action: DedupAction,
 }
 
 impl EventType {
@@ -2394,6 +2699,7 @@ impl EventType {
         vec![
             EventTypeRequest::FileUploaded,
             EventTypeRequest::FileUploadFailed,
+            EventTypeRequest::Pdf2MdOcrCompleted,
             EventTypeRequest::ChunksUploaded,
             EventTypeRequest::ChunkActionFailed,
             EventTypeRequest::ChunkUpdated,
@@ -2408,6 +2714,15 @@ impl EventType {
             EventTypeRequest::CsvJsonlProcessingFailed,
             EventTypeRequest::CsvJsonlProcessingCheckpoint,
             EventTypeRequest::CsvJsonlProcessingCompleted,
+            EventTypeRequest::CsvJsonlRowsFailed,
+            EventTypeRequest::DatasetCloneCompleted,
+            EventTypeRequest::DatasetCloneFailed,
+            EventTypeRequest::DatasetExportCompleted,
+            EventTypeRequest::DatasetExportFailed,
+            EventTypeRequest::DatasetImportCompleted,
+            EventTypeRequest::DatasetImportFailed,
+            EventTypeRequest::DatasetReindexCompleted,
+            EventTypeRequest::DatasetReindexFailed,
             EventTypeRequest::VideoUploaded,
             EventTypeRequest::PagefindIndexingStarted,
             EventTypeRequest::PagefindIndexingFinished,
@@ -2415,6 +2730,7 @@ impl EventType {
             EventTypeRequest::EtlCompleted,
             EventTypeRequest::EtlFailed,
             EventTypeRequest::ChunkUpdateFailed,
+            EventTypeRequest::DuplicateChunksSkipped,
         ]
     }
 }
@@ -2711,6 +3027,9 @@ pub struct DatasetConfiguration {
     pub PAGEFIND_ENABLED: bool,
     pub AIMON_RERANKER_TASK_DEFINITION: String,
     pub TOOL_CONFIGURATION: ToolConfiguration,
+    pub DEDUP_POLICY: Option<DedupPolicy>,
+    pub GROUP_CENTROIDS: Option<GroupCentroidOptions>,
+    pub GROUP_SUMMARIES: Option<GroupSummaryOptions>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
@@ -2830,6 +3149,12 @@ pub struct DatasetConfigurationDTO {
     /// The tool configuration to use for the dataset
     pub TOOL_CONFIGURATION: Option<ToolConfiguration>,
     pub AIMON_RERANKER_TASK_DEFINITION: Option<String>,
+    /// Policy the ingestion worker applies to chunks which duplicate content already in the dataset
+    pub DEDUP_POLICY: Option<DedupPolicy>,
+    /// Have the group update worker maintain a centroid vector for each group, used to find similar groups and to search over groups without fetching their chunks
+    pub GROUP_CENTROIDS: Option<GroupCentroidOptions>,
+    /// Have the group update worker generate an LLM summary of each group once its chunks stop changing, stored as a summary chunk in the group and in the group's metadata
+    pub GROUP_SUMMARIES: Option<GroupSummaryOptions>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
@@ -2846,6 +3171,123 @@ pub struct ToolConfiguration {
     pub query_tool_options: Option<QueryToolOptions>,
 }
 
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, ToSchema, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum DedupAction {
+    /// Do not ingest the duplicate
+    Skip,
+    /// Do not ingest the duplicate, but add its tags and groups to the chunk it duplicates
+    Merge,
+    /// Ingest the duplicate with `duplicate_of` and `duplicate_match` set in its metadata
+    Flag,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+#[schema(example = json!({
+    "exact": true,
+    "simhash_max_distance": 3,
+    "vector_similarity_threshold": 0.97,
+    "action": "skip",
+}))]
+/// Near-duplicate detection applied by the ingestion worker. Chunks are compared against each other
+/// within a batch and against chunks previously ingested while a policy was set. Chunks upserted by
+/// tracking_id are never treated as duplicates.
+pub struct DedupPolicy {
+    /// Match chunks whose normalized text is identical to an existing chunk. Defaults to true.
+    pub exact: Option<bool>,
+    /// Match chunks whose 64 bit SimHash differs from an existing chunk's by at most this many bits. Must be between 0 and 3. Near-duplicate matching is off if not specified.
+    pub simhash_max_distance: Option<u32>,
+    /// Match chunks whose embedding scores at least this high against an existing point in the dataset's distance metric. Requires SEMANTIC_ENABLED. Vector matching is off if not specified.
+    pub vector_similarity_threshold: Option<f32>,
+    /// What to do with a chunk once it is found to be a duplicate.
+    pub action: DedupAction,
+}
+
+impl DedupPolicy {
+    pub fn validate(&self) -> Result<(), ServiceError> {
+        if self
+            .simhash_max_distance
+            .is_some_and(|distance| distance > 3)
+        {
+            return Err(ServiceError::BadRequest(
+                "DEDUP_POLICY.simhash_max_distance must be between 0 and 3".to_string(),
+            ));
+        }
+
+        if self
+            .vector_similarity_threshold
+            .is_some_and(|threshold| !threshold.is_finite())
+        {
+            return Err(ServiceError::BadRequest(
+                "DEDUP_POLICY.vector_similarity_threshold must be a finite number".to_string(),
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+#[schema(example = json!({
+    "weighted": true,
+}))]
+/// A group's centroid is the mean of the dense vectors of the chunks in it. Centroids require
+/// SEMANTIC_ENABLED and are stored in a separate Qdrant collection from the chunks.
+pub struct GroupCentroidOptions {
+    /// Weight each chunk's vector by the chunk's `weight` when averaging. Chunks without a positive weight count as 1. Defaults to false.
+    pub weighted: Option<bool>,
+}
+
+impl GroupCentroidOptions {
+    pub fn validate(&self, dataset_config: &DatasetConfiguration) -> Result<(), ServiceError> {
+        if !dataset_config.SEMANTIC_ENABLED {
+            return Err(ServiceError::BadRequest(
+                "GROUP_CENTROIDS requires SEMANTIC_ENABLED".to_string(),
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+#[schema(example = json!({
+    "prompt": "Summarize the following document in 3-5 sentences:",
+    "max_chunks": 50,
+    "settle_seconds": 60,
+}))]
+/// Summaries are generated for the groups of uploaded files and crawled pages with the dataset's
+/// LLM settings from the chunks in the group, in document order. A summary is stored as a chunk in
+/// the group with `group_summary` set in its metadata, and under `summary` in the group's
+/// metadata. The summary chunk is left out of the group's aggregates and centroid. Summaries are
+/// regenerated when chunks are added to or removed from the group, failed summaries are retried
+/// with backoff up to 5 times.
+pub struct GroupSummaryOptions {
+    /// Prompt the group's chunks are appended to. Defaults to asking for a short overview of the document.
+    pub prompt: Option<String>,
+    /// Model to generate summaries with. Defaults to the dataset's LLM_DEFAULT_MODEL.
+    pub model: Option<String>,
+    /// Maximum number of chunks, from the start of the group, to summarize. Must be between 1 and 500. Defaults to 50.
+    pub max_chunks: Option<u64>,
+    /// Seconds the group's chunks must go unchanged before its summary is regenerated, so that files and crawls which are still ingesting are only summarized once. Defaults to 60.
+    pub settle_seconds: Option<u64>,
+}
+
+impl GroupSummaryOptions {
+    pub fn validate(&self) -> Result<(), ServiceError> {
+        if self
+            .max_chunks
+            .is_some_and(|max_chunks| max_chunks == 0 || max_chunks > 500)
+        {
+            return Err(ServiceError::BadRequest(
+                "GROUP_SUMMARIES.max_chunks must be between 1 and 500".to_string(),
+            ));
+        }
+
+        Ok(())
+    }
+}
+
 impl From<DatasetConfigurationDTO> for DatasetConfiguration {
     fn from(dto: DatasetConfigurationDTO) -> Self {
         DatasetConfiguration {
@@ -2912,6 +3354,9 @@ impl From<DatasetConfigurationDTO> for DatasetConfiguration {
                     min_price_option_description: Some("The minimum price or page to filter by".to_string()),
                 }),
             }),
+            DEDUP_POLICY: dto.DEDUP_POLICY,
+            GROUP_CENTROIDS: dto.GROUP_CENTROIDS,
+            GROUP_SUMMARIES: dto.GROUP_SUMMARIES,
         }
     }
 }
@@ -2965,6 +3410,9 @@ impl From<DatasetConfiguration> for DatasetConfigurationDTO {
             PAGEFIND_ENABLED: Some(config.PAGEFIND_ENABLED),
             AIMON_RERANKER_TASK_DEFINITION: Some(config.AIMON_RERANKER_TASK_DEFINITION),
             TOOL_CONFIGURATION: Some(config.TOOL_CONFIGURATION),
+            DEDUP_POLICY: config.DEDUP_POLICY,
+            GROUP_CENTROIDS: config.GROUP_CENTROIDS,
+            GROUP_SUMMARIES: config.GROUP_SUMMARIES,
         }
     }
 }
@@ -3021,6 +3469,9 @@ impl Default for DatasetConfiguration {
                     min_price_option_description: Some("The minimum price to filter by".to_string()),
                 }),
             },
+            DEDUP_POLICY: None,
+            GROUP_CENTROIDS: None,
+            GROUP_SUMMARIES: None,
             }
     }
 }
@@ -3357,6 +3808,15 @@ impl DatasetConfiguration {
                         min_price_option_description: Some("The minimum price to filter by".to_string()),
                     }),
                 }),
+            DEDUP_POLICY: configuration
+                .get("DEDUP_POLICY")
+                .and_then(|v| serde_json::from_value(v.clone()).ok()),
+            GROUP_CENTROIDS: configuration
+                .get("GROUP_CENTROIDS")
+                .and_then(|v| serde_json::from_value(v.clone()).ok()),
+            GROUP_SUMMARIES: configuration
+                .get("GROUP_SUMMARIES")
+                .and_then(|v| serde_json::from_value(v.clone()).ok()),
         }
     }
 
@@ -3404,6 +3864,9 @@ impl DatasetConfiguration {
             "PAGEFIND_ENABLED": self.PAGEFIND_ENABLED,
             "AIMON_RERANKER_TASK_DEFINITION": self.AIMON_RERANKER_TASK_DEFINITION,
             "TOOL_CONFIGURATION": self.TOOL_CONFIGURATION,
+            "DEDUP_POLICY": self.DEDUP_POLICY,
+            "GROUP_CENTROIDS": self.GROUP_CENTROIDS,
+            "GROUP_SUMMARIES": self.GROUP_SUMMARIES,
         })
     }
 }
@@ -3732,6 +4195,18 @@ impl DatasetConfigurationDTO {
                 .TOOL_CONFIGURATION
                 .clone()
                 .unwrap_or(curr_dataset_config.TOOL_CONFIGURATION),
+            DEDUP_POLICY: self
+                .DEDUP_POLICY
+                .clone()
+                .or(curr_dataset_config.DEDUP_POLICY),
+            GROUP_CENTROIDS: self
+                .GROUP_CENTROIDS
+                .clone()
+                .or(curr_dataset_config.GROUP_CENTROIDS),
+            GROUP_SUMMARIES: self
+                .GROUP_SUMMARIES
+                .clone()
+                .or(curr_dataset_config.GROUP_SUMMARIES),
         }
     }
 }
@@ -4633,6 +5108,8 @@ impl ApiKeyRequestParams {
             only_include_docs_used: payload.only_include_docs_used,
             number_of_messages_to_include: payload.number_of_messages_to_include,
             model: payload.model,
+            context_window: payload.context_window,
+            include_group_summaries: payload.include_group_summaries,
         }
     }
 
@@ -4686,6 +5163,7 @@ impl ApiKeyRequestParams {
             user_id: payload.user_id,
             typo_options: self.typo_options.or(payload.typo_options),
             metadata: payload.metadata,
+            context_window: payload.context_window,
         }
     }
 
@@ -4751,6 +5229,7 @@ impl ApiKeyRequestParams {
             sort_options: payload.sort_options,
             metadata: payload.metadata,
             scoring_options: payload.scoring_options,
+            group_level: payload.group_level,
         }
     }
 
@@ -5235,6 +5714,11 @@ pub struct FileWorkerMessage {
     pub organization_id: uuid::Uuid,
     pub upload_file_data: UploadFileReqPayload,
     pub attempt_number: u8,
+    /// Set when an existing file is being reprocessed or has a new version. The file's group is
+    /// reused, chunks whose content did not change are kept and the other chunks it held before
+    /// are replaced by the newly created ones.
+    #[serde(default)]
+    pub reprocess: bool,
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
@@ -5242,6 +5726,8 @@ pub struct CsvJsonlWorkerMessage {
     pub file_id: uuid::Uuid,
     pub dataset_id: uuid::Uuid,
     pub create_presigned_put_url_data: CreatePresignedUrlForCsvJsonlReqPayload,
+    #[serde(default)]
+    pub file_format: CsvJsonlFileFormat,
     pub created_at: chrono::NaiveDateTime,
     pub attempt_number: u8,
 }
@@ -5253,93 +5739,532 @@ pub struct PagefindIndexWorkerMessage {
     pub attempt_number: u8,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
-#[serde(untagged)]
-pub enum RangeCondition {
-    Float(f64),
-    Int(i64),
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, ToSchema, PartialEq, Eq, Default, Display)]
+#[serde(rename_all = "snake_case")]
+/// File format of a dataset export. JSONL exports contain one record per line. Parquet exports contain one row per record with `record_type`, `id` and `record` columns where `record` holds the same JSON as the JSONL export.
+pub enum DatasetExportFormat {
+    #[default]
+    #[display(fmt = "jsonl")]
+    Jsonl,
+    #[display(fmt = "parquet")]
+    Parquet,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone, ToSchema, Default)]
-#[schema(example = json!({
-    "gte": 0.0,
-    "lte": 1.0,
-    "gt": 0.0,
-    "lt": 1.0
-}))]
-pub struct Range {
-    // gte is the lower bound of the range. This is inclusive.
-    pub gte: Option<RangeCondition>,
-    // lte is the upper bound of the range. This is inclusive.
-    pub lte: Option<RangeCondition>,
-    // gt is the lower bound of the range. This is exclusive.
-    pub gt: Option<RangeCondition>,
-    // lt is the upper bound of the range. This is exclusive.
-    pub lt: Option<RangeCondition>,
+impl From<String> for DatasetExportFormat {
+    fn from(format: String) -> Self {
+        match format.as_str() {
+            "parquet" => DatasetExportFormat::Parquet,
+            _ => DatasetExportFormat::Jsonl,
+        }
+    }
 }
 
-/// DateRange is a JSON object which can be used to filter chunks by a range of dates. This leverages the time_stamp field on chunks in your dataset. You can specify this if you want values in a certain range. You must provide ISO 8601 combined date and time without timezone.
-#[derive(Serialize, Deserialize, Debug, Clone, ToSchema, Default)]
-#[schema(example = json!({
-    "gte": "2021-01-01 00:00:00.000",
-    "lte": "2021-01-01 00:00:00.000",
-    "gt": "2021-01-01 00:00:00.000",
-    "lt": "2021-01-01 00:00:00.000"
-}))]
-pub struct DateRange {
-    // gte is ISO8601 time for the lower bound of the range. This is inclusive.
-    pub gte: Option<String>,
-    // lte is ISO8601 time for the upper bound of the range. This is inclusive.
-    pub lte: Option<String>,
-    // gt is ISO8601 time for the lower bound of the range. This is exclusive.
-    pub gt: Option<String>,
-    // lt is ISO8601 time for the upper bound of the range. This is exclusive.
-    pub lt: Option<String>,
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, ToSchema, PartialEq, Eq, Display)]
+#[serde(rename_all = "snake_case")]
+pub enum DatasetExportStatus {
+    #[display(fmt = "pending")]
+    Pending,
+    #[display(fmt = "processing")]
+    Processing,
+    #[display(fmt = "completed")]
+    Completed,
+    #[display(fmt = "failed")]
+    Failed,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
-#[serde(untagged)]
-pub enum MatchCondition {
-    Text(String),
-    Integer(i64),
-    Float(f64),
+impl From<String> for DatasetExportStatus {
+    fn from(status: String) -> Self {
+        match status.as_str() {
+            "processing" => DatasetExportStatus::Processing,
+            "completed" => DatasetExportStatus::Completed,
+            "failed" => DatasetExportStatus::Failed,
+            _ => DatasetExportStatus::Pending,
+        }
+    }
 }
 
-impl MatchCondition {
-    #[allow(clippy::inherent_to_string)]
-    pub fn to_string(&self) -> String {
-        match self {
-            MatchCondition::Text(text) => text.clone(),
-            MatchCondition::Integer(int) => int.to_string(),
-            MatchCondition::Float(float) => float.to_string(),
+#[derive(Debug, Serialize, Deserialize, Queryable, Insertable, Selectable, Clone, ToSchema)]
+#[schema(example = json!({
+    "id": "e3e3e3e3-e3e3-e3e3-e3e3-e3e3e3e3e3e3",
+    "dataset_id": "e3e3e3e3-e3e3-e3e3-e3e3-e3e3e3e3e3e3",
+    "status": "completed",
+    "format": "jsonl",
+    "filter": null,
+    "s3_key": "exports/e3e3e3e3-e3e3-e3e3-e3e3-e3e3e3e3e3e3/e3e3e3e3-e3e3-e3e3-e3e3-e3e3e3e3e3e3.jsonl",
+    "chunks_exported": 1000,
+    "error": null,
+    "created_at": "2021-01-01 00:00:00.000",
+    "updated_at": "2021-01-01 00:00:00.000",
+}))]
+#[diesel(table_name = dataset_exports)]
+pub struct DatasetExport {
+    /// Unique identifier of the export
+    pub id: uuid::Uuid,
+    /// Id of the dataset being exported
+    pub dataset_id: uuid::Uuid,
+    /// One of pending, processing, completed or failed
+    pub status: String,
+    /// Either jsonl or parquet
+    pub format: String,
+    /// The ChunkFilter used to select the exported chunks, if any
+    pub filter: Option<serde_json::Value>,
+    /// Key of the export in the S3 bucket once it has completed
+    pub s3_key: Option<String>,
+    /// Number of chunks written to the export so far
+    pub chunks_exported: i64,
+    /// Reason the export failed, if it did
+    pub error: Option<String>,
+    pub created_at: chrono::NaiveDateTime,
+    pub updated_at: chrono::NaiveDateTime,
+}
+
+impl DatasetExport {
+    pub fn from_details(
+        dataset_id: uuid::Uuid,
+        format: DatasetExportFormat,
+        filter: Option<ChunkFilter>,
+    ) -> Self {
+        DatasetExport {
+            id: uuid::Uuid::new_v4(),
+            dataset_id,
+            status: DatasetExportStatus::Pending.to_string(),
+            format: format.to_string(),
+            filter: filter.and_then(|filter| serde_json::to_value(filter).ok()),
+            s3_key: None,
+            chunks_exported: 0,
+            error: None,
+            created_at: chrono::Utc::now().naive_local(),
+            updated_at: chrono::Utc::now().naive_local(),
         }
     }
+}
 
-    pub fn to_i64(&self) -> i64 {
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DatasetExportMessage {
+    pub export_id: uuid::Uuid,
+    pub dataset_id: uuid::Uuid,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ExportedSparseVector {
+    pub indices: Vec<u32>,
+    pub values: Vec<f32>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct ExportedPointVectors {
+    /// Dense vectors keyed by their qdrant vector name, e.g. `1536_vectors`
+    pub dense_vectors: HashMap<String, Vec<f32>>,
+    /// Sparse vectors keyed by their qdrant vector name, e.g. `sparse_vectors` or `bm25_vectors`
+    pub sparse_vectors: HashMap<String, ExportedSparseVector>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ExportedChunk {
+    pub chunk: ChunkMetadata,
+    pub boost: Option<ChunkBoost>,
+    pub group_ids: Vec<uuid::Uuid>,
+    #[serde(flatten)]
+    pub vectors: ExportedPointVectors,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(tag = "record_type", rename_all = "snake_case")]
+/// A single record of a dataset export. The dataset record is always written first, followed by the groups, files and finally the chunks.
+pub enum DatasetExportRecord {
+    Dataset {
+        dataset: Dataset,
+        server_configuration: DatasetConfigurationDTO,
+    },
+    Group(ChunkGroupAndFileId),
+    File(File),
+    Chunk(Box<ExportedChunk>),
+}
+
+impl DatasetExportRecord {
+    pub fn record_type(&self) -> &'static str {
         match self {
-            MatchCondition::Text(text) => text.parse().unwrap_or_default(),
-            MatchCondition::Integer(int) => *int,
-            MatchCondition::Float(float) => *float as i64,
+            DatasetExportRecord::Dataset { .. } => "dataset",
+            DatasetExportRecord::Group(_) => "group",
+            DatasetExportRecord::File(_) => "file",
+            DatasetExportRecord::Chunk(_) => "chunk",
         }
     }
 
-    pub fn to_f64(&self) -> f64 {
+    pub fn id(&self) -> uuid::Uuid {
         match self {
-            MatchCondition::Text(text) => text.parse().unwrap_or_default(),
-            MatchCondition::Integer(int) => *int as f64,
-            MatchCondition::Float(float) => *float,
+            DatasetExportRecord::Dataset { dataset, .. } => dataset.id,
+            DatasetExportRecord::Group(group) => group.id,
+            DatasetExportRecord::File(file) => file.id,
+            DatasetExportRecord::Chunk(chunk) => chunk.chunk.id,
         }
     }
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
-pub struct LocationBoundingBox {
-    pub top_left: GeoInfo,
-    pub bottom_right: GeoInfo,
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, ToSchema, PartialEq, Eq, Display)]
+#[serde(rename_all = "snake_case")]
+pub enum DatasetImportStatus {
+    #[display(fmt = "pending")]
+    Pending,
+    #[display(fmt = "processing")]
+    Processing,
+    #[display(fmt = "completed")]
+    Completed,
+    #[display(fmt = "failed")]
+    Failed,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
-pub struct LocationRadius {
+#[derive(Debug, Serialize, Deserialize, Queryable, Insertable, Selectable, Clone, ToSchema)]
+#[schema(example = json!({
+    "id": "e3e3e3e3-e3e3-e3e3-e3e3-e3e3e3e3e3e3",
+    "dataset_id": "e3e3e3e3-e3e3-e3e3-e3e3-e3e3e3e3e3e3",
+    "status": "completed",
+    "format": "jsonl",
+    "export_id": "e3e3e3e3-e3e3-e3e3-e3e3-e3e3e3e3e3e3",
+    "source_url": null,
+    "vectors_reused": true,
+    "groups_imported": 10,
+    "files_imported": 2,
+    "chunks_imported": 1000,
+    "error": null,
+    "created_at": "2021-01-01 00:00:00.000",
+    "updated_at": "2021-01-01 00:00:00.000",
+}))]
+#[diesel(table_name = dataset_imports)]
+pub struct DatasetImport {
+    /// Unique identifier of the import
+    pub id: uuid::Uuid,
+    /// Id of the dataset being imported into
+    pub dataset_id: uuid::Uuid,
+    /// One of pending, processing, completed or failed
+    pub status: String,
+    /// Either jsonl or parquet
+    pub format: String,
+    /// Id of the export being imported when importing from this deployment
+    pub export_id: Option<uuid::Uuid>,
+    /// Url the export archive is downloaded from when importing from another deployment
+    pub source_url: Option<String>,
+    /// Whether the exported vectors were upserted directly or the chunks were re-embedded. Set once the dataset record has been read.
+    pub vectors_reused: Option<bool>,
+    pub groups_imported: i64,
+    pub files_imported: i64,
+    pub chunks_imported: i64,
+    /// Reason the import failed, if it did
+    pub error: Option<String>,
+    pub created_at: chrono::NaiveDateTime,
+    pub updated_at: chrono::NaiveDateTime,
+}
+
+impl DatasetImport {
+    pub fn from_details(
+        dataset_id: uuid::Uuid,
+        format: DatasetExportFormat,
+        export_id: Option<uuid::Uuid>,
+        source_url: Option<String>,
+    ) -> Self {
+        DatasetImport {
+            id: uuid::Uuid::new_v4(),
+            dataset_id,
+            status: DatasetImportStatus::Pending.to_string(),
+            format: format.to_string(),
+            export_id,
+            source_url,
+            vectors_reused: None,
+            groups_imported: 0,
+            files_imported: 0,
+            chunks_imported: 0,
+            error: None,
+            created_at: chrono::Utc::now().naive_local(),
+            updated_at: chrono::Utc::now().naive_local(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DatasetImportMessage {
+    pub import_id: uuid::Uuid,
+    pub dataset_id: uuid::Uuid,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, ToSchema, PartialEq, Eq, Display)]
+#[serde(rename_all = "snake_case")]
+pub enum DatasetCloneStatus {
+    #[display(fmt = "pending")]
+    Pending,
+    #[display(fmt = "processing")]
+    Processing,
+    #[display(fmt = "completed")]
+    Completed,
+    #[display(fmt = "failed")]
+    Failed,
+}
+
+#[derive(Debug, Serialize, Deserialize, Queryable, Insertable, Selectable, Clone, ToSchema)]
+#[schema(example = json!({
+    "id": "e3e3e3e3-e3e3-e3e3-e3e3-e3e3e3e3e3e3",
+    "dataset_id": "e3e3e3e3-e3e3-e3e3-e3e3-e3e3e3e3e3e3",
+    "source_dataset_id": "e3e3e3e3-e3e3-e3e3-e3e3-e3e3e3e3e3e3",
+    "status": "completed",
+    "filter": null,
+    "group_ids": null,
+    "vectors_reused": true,
+    "groups_cloned": 10,
+    "files_cloned": 2,
+    "chunks_cloned": 1000,
+    "error": null,
+    "created_at": "2021-01-01 00:00:00.000",
+    "updated_at": "2021-01-01 00:00:00.000",
+}))]
+#[diesel(table_name = dataset_clones)]
+pub struct DatasetClone {
+    /// Unique identifier of the clone
+    pub id: uuid::Uuid,
+    /// Id of the dataset being cloned into
+    pub dataset_id: uuid::Uuid,
+    /// Id of the dataset being cloned from
+    pub source_dataset_id: uuid::Uuid,
+    /// One of pending, processing, completed or failed
+    pub status: String,
+    /// The ChunkFilter used to select the cloned chunks, if any
+    pub filter: Option<serde_json::Value>,
+    /// Ids of the groups in the source dataset whose chunks were cloned, if the clone was restricted to groups
+    pub group_ids: Option<Vec<uuid::Uuid>>,
+    /// Whether the source vectors were copied directly or the chunks were re-embedded. Set once the clone has started processing.
+    pub vectors_reused: Option<bool>,
+    pub groups_cloned: i64,
+    pub files_cloned: i64,
+    pub chunks_cloned: i64,
+    /// Reason the clone failed, if it did
+    pub error: Option<String>,
+    pub created_at: chrono::NaiveDateTime,
+    pub updated_at: chrono::NaiveDateTime,
+}
+
+impl DatasetClone {
+    pub fn from_details(
+        dataset_id: uuid::Uuid,
+        source_dataset_id: uuid::Uuid,
+        filter: Option<ChunkFilter>,
+        group_ids: Option<Vec<uuid::Uuid>>,
+    ) -> Self {
+        DatasetClone {
+            id: uuid::Uuid::new_v4(),
+            dataset_id,
+            source_dataset_id,
+            status: DatasetCloneStatus::Pending.to_string(),
+            filter: filter.and_then(|filter| serde_json::to_value(filter).ok()),
+            group_ids,
+            vectors_reused: None,
+            groups_cloned: 0,
+            files_cloned: 0,
+            chunks_cloned: 0,
+            error: None,
+            created_at: chrono::Utc::now().naive_local(),
+            updated_at: chrono::Utc::now().naive_local(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, ToSchema, PartialEq, Eq, Display)]
+#[serde(rename_all = "snake_case")]
+/// How the values of a field across the chunks in a group are combined into a single value for the group. `any` is 1.0 if the field is truthy on any chunk in the group and 0.0 otherwise.
+pub enum GroupAggregateFunction {
+    #[display(fmt = "min")]
+    Min,
+    #[display(fmt = "max")]
+    Max,
+    #[display(fmt = "avg")]
+    Avg,
+    #[display(fmt = "sum")]
+    Sum,
+    #[display(fmt = "any")]
+    Any,
+}
+
+impl GroupAggregateFunction {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "min" => Some(GroupAggregateFunction::Min),
+            "max" => Some(GroupAggregateFunction::Max),
+            "avg" => Some(GroupAggregateFunction::Avg),
+            "sum" => Some(GroupAggregateFunction::Sum),
+            "any" => Some(GroupAggregateFunction::Any),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Queryable, Insertable, Selectable, Clone, ToSchema)]
+#[schema(example = json!({
+    "id": "e3e3e3e3-e3e3-e3e3-e3e3-e3e3e3e3e3e3",
+    "dataset_id": "e3e3e3e3-e3e3-e3e3-e3e3-e3e3e3e3e3e3",
+    "name": "min_price",
+    "field": "metadata.price",
+    "aggregation": "min",
+    "created_at": "2021-01-01 00:00:00.000",
+    "updated_at": "2021-01-01 00:00:00.000",
+}))]
+#[diesel(table_name = group_aggregates)]
+/// A value computed for every group in a dataset from a field of the group's chunks. Group aggregates can be used to filter and sort groups with the `group_aggregates.<name>` field.
+pub struct GroupAggregate {
+    pub id: uuid::Uuid,
+    pub dataset_id: uuid::Uuid,
+    /// Name used to reference the aggregate in filters and sort options. Unique within the dataset.
+    pub name: String,
+    /// Chunk field the aggregate is computed over. Either `num_value` or a `metadata.` prefixed key.
+    pub field: String,
+    /// One of min, max, avg, sum or any
+    pub aggregation: String,
+    pub created_at: chrono::NaiveDateTime,
+    pub updated_at: chrono::NaiveDateTime,
+}
+
+impl GroupAggregate {
+    pub fn from_details(
+        dataset_id: uuid::Uuid,
+        name: String,
+        field: String,
+        aggregation: GroupAggregateFunction,
+    ) -> Self {
+        GroupAggregate {
+            id: uuid::Uuid::new_v4(),
+            dataset_id,
+            name,
+            field,
+            aggregation: aggregation.to_string(),
+            created_at: chrono::Utc::now().naive_local(),
+            updated_at: chrono::Utc::now().naive_local(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Queryable, Insertable, Selectable, Clone, ToSchema)]
+#[schema(example = json!({
+    "id": "e3e3e3e3-e3e3-e3e3-e3e3-e3e3e3e3e3e3",
+    "organization_id": "e3e3e3e3-e3e3-e3e3-e3e3-e3e3e3e3e3e3",
+    "name": "production",
+    "dataset_id": "e3e3e3e3-e3e3-e3e3-e3e3-e3e3e3e3e3e3",
+    "previous_dataset_id": null,
+    "created_at": "2021-01-01 00:00:00.000",
+    "updated_at": "2021-01-01 00:00:00.000",
+}))]
+#[diesel(table_name = dataset_aliases)]
+pub struct DatasetAlias {
+    /// Unique identifier of the alias
+    pub id: uuid::Uuid,
+    /// Id of the organization the alias belongs to
+    pub organization_id: uuid::Uuid,
+    /// Name of the alias. It can be used anywhere a dataset tracking id is accepted, including the TR-Dataset header.
+    pub name: String,
+    /// Id of the dataset the alias currently points at
+    pub dataset_id: uuid::Uuid,
+    /// Id of the dataset the alias pointed at before the last swap. Swapping without a dataset id flips back to it.
+    pub previous_dataset_id: Option<uuid::Uuid>,
+    pub created_at: chrono::NaiveDateTime,
+    pub updated_at: chrono::NaiveDateTime,
+}
+
+impl DatasetAlias {
+    pub fn from_details(organization_id: uuid::Uuid, name: String, dataset_id: uuid::Uuid) -> Self {
+        DatasetAlias {
+            id: uuid::Uuid::new_v4(),
+            organization_id,
+            name,
+            dataset_id,
+            previous_dataset_id: None,
+            created_at: chrono::Utc::now().naive_local(),
+            updated_at: chrono::Utc::now().naive_local(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+#[serde(untagged)]
+pub enum RangeCondition {
+    Float(f64),
+    Int(i64),
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema, Default)]
+#[schema(example = json!({
+    "gte": 0.0,
+    "lte": 1.0,
+    "gt": 0.0,
+    "lt": 1.0
+}))]
+pub struct Range {
+    // gte is the lower bound of the range. This is inclusive.
+    pub gte: Option<RangeCondition>,
+    // lte is the upper bound of the range. This is inclusive.
+    pub lte: Option<RangeCondition>,
+    // gt is the lower bound of the range. This is exclusive.
+    pub gt: Option<RangeCondition>,
+    // lt is the upper bound of the range. This is exclusive.
+    pub lt: Option<RangeCondition>,
+}
+
+/// DateRange is a JSON object which can be used to filter chunks by a range of dates. This leverages the time_stamp field on chunks in your dataset. You can specify this if you want values in a certain range. You must provide ISO 8601 combined date and time without timezone.
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema, Default)]
+#[schema(example = json!({
+    "gte": "2021-01-01 00:00:00.000",
+    "lte": "2021-01-01 00:00:00.000",
+    "gt": "2021-01-01 00:00:00.000",
+    "lt": "2021-01-01 00:00:00.000"
+}))]
+pub struct DateRange {
+    // gte is ISO8601 time for the lower bound of the range. This is inclusive.
+    pub gte: Option<String>,
+    // lte is ISO8601 time for the upper bound of the range. This is inclusive.
+    pub lte: Option<String>,
+    // gt is ISO8601 time for the lower bound of the range. This is exclusive.
+    pub gt: Option<String>,
+    // lt is ISO8601 time for the upper bound of the range. This is exclusive.
+    pub lt: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+#[serde(untagged)]
+pub enum MatchCondition {
+    Text(String),
+    Integer(i64),
+    Float(f64),
+}
+
+impl MatchCondition {
+    #[allow(clippy::inherent_to_string)]
+    pub fn to_string(&self) -> String {
+        match self {
+            MatchCondition::Text(text) => text.clone(),
+            MatchCondition::Integer(int) => int.to_string(),
+            MatchCondition::Float(float) => float.to_string(),
+        }
+    }
+
+    pub fn to_i64(&self) -> i64 {
+        match self {
+            MatchCondition::Text(text) => text.parse().unwrap_or_default(),
+            MatchCondition::Integer(int) => *int,
+            MatchCondition::Float(float) => *float as i64,
+        }
+    }
+
+    pub fn to_f64(&self) -> f64 {
+        match self {
+            MatchCondition::Text(text) => text.parse().unwrap_or_default(),
+            MatchCondition::Integer(int) => *int as f64,
+            MatchCondition::Float(float) => *float,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct LocationBoundingBox {
+    pub top_left: GeoInfo,
+    pub bottom_right: GeoInfo,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct LocationRadius {
     pub center: GeoInfo,
     pub radius: f64,
 }
@@ -5383,7 +6308,7 @@ pub struct HasChunkIDCondition {
 }))]
 /// FieldCondition is a JSON object which can be used to filter chunks by a field. This is useful for when you want to filter chunks by arbitrary metadata. To access fields inside of the metadata that you provide with the card, prefix the field name with `metadata.`.
 pub struct FieldCondition {
-    /// Field is the name of the field to filter on. Commonly used fields are `timestamp`, `link`, `tag_set`, `location`, `num_value`, `group_ids`, and `group_tracking_ids`. The field value will be used to check for an exact substring match on the metadata values for each existing chunk. This is useful for when you want to filter chunks by arbitrary metadata. To access fields inside of the metadata that you provide with the card, prefix the field name with `metadata.`.
+    /// Field is the name of the field to filter on. Commonly used fields are `timestamp`, `link`, `tag_set`, `location`, `num_value`, `group_ids`, and `group_tracking_ids`. Use `descendant_of_group_ids` or `descendant_of_group_tracking_ids` with match_any to match chunks in any of the given groups or any group nested beneath them. Use `group_aggregates.<name>` with range, match_any or boolean to match chunks in groups whose group aggregate value satisfies the condition. The field value will be used to check for an exact substring match on the metadata values for each existing chunk. This is useful for when you want to filter chunks by arbitrary metadata. To access fields inside of the metadata that you provide with the card, prefix the field name with `metadata.`.
     pub field: String,
     /// Match any lets you pass in an array of values that will return results if any of the items match. The match value will be used to check for an exact substring match on the metadata values for each existing chunk. If both match_all and match_any are provided, the match_any condition will be used.
     #[serde(alias = "match")]
@@ -6984,6 +7909,17 @@ pub struct ChunkBoost {
     pub semantic_boost_factor: Option<f64>,
 }
 
+#[derive(Debug, Serialize, Deserialize, Selectable, Queryable, Insertable, Clone)]
+#[diesel(table_name = chunk_fingerprints)]
+pub struct ChunkFingerprint {
+    pub chunk_id: uuid::Uuid,
+    pub dataset_id: uuid::Uuid,
+    pub content_hash: String,
+    pub simhash: i64,
+    pub simhash_bands: Vec<i32>,
+    pub created_at: chrono::NaiveDateTime,
+}
+
 #[derive(AsChangeset)]
 #[diesel(table_name = chunk_boosts)]
 pub struct ChunkBoostChangeset {
@@ -8589,6 +9525,9 @@ pub enum EventTypeRequest {
     FileUploaded,
     #[display(fmt = "file_upload_failed")]
     FileUploadFailed,
+    #[display(fmt = "pdf2md_ocr_completed")]
+    #[serde(rename = "pdf2md_ocr_completed")]
+    Pdf2MdOcrCompleted,
     #[display(fmt = "chunks_uploaded")]
     ChunksUploaded,
     #[display(fmt = "chunk_action_failed")]
@@ -8621,6 +9560,24 @@ pub enum EventTypeRequest {
     CsvJsonlProcessingCheckpoint,
     #[display(fmt = "csv_jsonl_processing_completed")]
     CsvJsonlProcessingCompleted,
+    #[display(fmt = "csv_jsonl_rows_failed")]
+    CsvJsonlRowsFailed,
+    #[display(fmt = "dataset_clone_completed")]
+    DatasetCloneCompleted,
+    #[display(fmt = "dataset_clone_failed")]
+    DatasetCloneFailed,
+    #[display(fmt = "dataset_export_completed")]
+    DatasetExportCompleted,
+    #[display(fmt = "dataset_export_failed")]
+    DatasetExportFailed,
+    #[display(fmt = "dataset_import_completed")]
+    DatasetImportCompleted,
+    #[display(fmt = "dataset_import_failed")]
+    DatasetImportFailed,
+    #[display(fmt = "dataset_reindex_completed")]
+    DatasetReindexCompleted,
+    #[display(fmt = "dataset_reindex_failed")]
+    DatasetReindexFailed,
     #[display(fmt = "video_uploaded")]
     VideoUploaded,
     #[display(fmt = "pagefind_indexing_started")]
@@ -8633,6 +9590,8 @@ pub enum EventTypeRequest {
     EtlCompleted,
     #[display(fmt = "etl_failed")]
     EtlFailed,
+    #[display(fmt = "duplicate_chunks_skipped")]
+    DuplicateChunksSkipped,
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
@@ -8655,11 +9614,499 @@ pub struct MigratePointMessage {
     pub to_collection: String,
     pub from_collection: String,
     pub mode: MigrationMode,
+    /// Set when the migration was started through the reindex API so progress can be tracked.
+    #[serde(default)]
+    pub reindex_id: Option<uuid::Uuid>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, ToSchema, PartialEq, Eq, Display)]
+#[serde(rename_all = "snake_case")]
+pub enum DatasetReindexStatus {
+    #[display(fmt = "pending")]
+    Pending,
+    #[display(fmt = "processing")]
+    Processing,
+    #[display(fmt = "completed")]
+    Completed,
+    #[display(fmt = "failed")]
+    Failed,
+    #[display(fmt = "cancelled")]
+    Cancelled,
+}
+
+#[derive(Debug, Serialize, Deserialize, Queryable, Insertable, Selectable, Clone, ToSchema)]
+#[schema(example = json!({
+    "id": "e3e3e3e3-e3e3-e3e3-e3e3-e3e3e3e3e3e3",
+    "dataset_id": "e3e3e3e3-e3e3-e3e3-e3e3-e3e3e3e3e3e3",
+    "status": "processing",
+    "mode": {
+        "Reembed": {
+            "embedding_model_name": "text-embedding-3-large",
+            "embedding_base_url": "https://api.openai.com/v1",
+            "embedding_size": 3072
+        }
+    },
+    "from_collection": "1536_vectors",
+    "to_collection": "3072_vectors",
+    "points_total": 10000,
+    "points_migrated": 2400,
+    "points_failed": 0,
+    "error": null,
+    "created_at": "2021-01-01 00:00:00.000",
+    "updated_at": "2021-01-01 00:00:00.000",
+    "started_at": "2021-01-01 00:00:05.000",
+}))]
+#[diesel(table_name = dataset_reindexes)]
+pub struct DatasetReindex {
+    /// Unique identifier of the reindex
+    pub id: uuid::Uuid,
+    /// Id of the dataset being reindexed
+    pub dataset_id: uuid::Uuid,
+    /// One of pending, processing, completed, failed or cancelled
+    pub status: String,
+    /// The migration being run, either a re-embedding or a BM25 recomputation
+    pub mode: serde_json::Value,
+    /// Collection the points are read from
+    pub from_collection: String,
+    /// Collection the migrated points are written to
+    pub to_collection: String,
+    /// Number of points to migrate. Set once every point has been queued.
+    pub points_total: Option<i64>,
+    pub points_migrated: i64,
+    pub points_failed: i64,
+    /// Reason the reindex failed, if it did
+    pub error: Option<String>,
+    pub created_at: chrono::NaiveDateTime,
+    pub updated_at: chrono::NaiveDateTime,
+    /// When the worker started queueing the points. Writes made after this are replayed into the new vectors once the migration completes.
+    pub started_at: Option<chrono::NaiveDateTime>,
+}
+
+impl DatasetReindex {
+    pub fn from_details(
+        dataset_id: uuid::Uuid,
+        mode: &MigrationMode,
+        from_collection: String,
+        to_collection: String,
+    ) -> Self {
+        DatasetReindex {
+            id: uuid::Uuid::new_v4(),
+            dataset_id,
+            status: DatasetReindexStatus::Pending.to_string(),
+            mode: serde_json::to_value(mode).unwrap_or_default(),
+            from_collection,
+            to_collection,
+            points_total: None,
+            points_migrated: 0,
+            points_failed: 0,
+            error: None,
+            created_at: chrono::Utc::now().naive_local(),
+            updated_at: chrono::Utc::now().naive_local(),
+            started_at: None,
+        }
+    }
+
+    pub fn migration_mode(&self) -> Result<MigrationMode, ServiceError> {
+        serde_json::from_value(self.mode.clone()).map_err(|_| {
+            ServiceError::InternalServerError("Failed to parse reindex mode".to_string())
+        })
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DatasetReindexMessage {
+    pub reindex_id: uuid::Uuid,
+    pub dataset_id: uuid::Uuid,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, ToSchema, PartialEq, Eq, Display)]
+#[serde(rename_all = "snake_case")]
+pub enum ChunkChangeOperation {
+    #[display(fmt = "create")]
+    Create,
+    #[display(fmt = "upsert")]
+    Upsert,
+    #[display(fmt = "update")]
+    Update,
+    #[display(fmt = "delete")]
+    Delete,
+    #[display(fmt = "clear")]
+    Clear,
+}
+
+#[derive(Debug, Serialize, Deserialize, Queryable, Selectable, Clone, ToSchema)]
+#[schema(example = json!({
+    "seq": 1042,
+    "dataset_id": "e3e3e3e3-e3e3-e3e3-e3e3-e3e3e3e3e3e3",
+    "operation": "update",
+    "chunk_id": "e3e3e3e3-e3e3-e3e3-e3e3-e3e3e3e3e3e3",
+    "tracking_id": "product-123",
+    "changed_fields": ["chunk_html", "tag_set"],
+    "created_at": "2021-01-01 00:00:00.000",
+}))]
+#[diesel(table_name = chunk_changes)]
+pub struct ChunkChange {
+    /// Sequence number of the change. Increases with every change and is used to resume the change feed.
+    pub seq: i64,
+    pub dataset_id: uuid::Uuid,
+    /// One of create, upsert, update, delete or clear. A clear change has no chunk_id and means every chunk in the dataset was deleted.
+    pub operation: String,
+    pub chunk_id: Option<uuid::Uuid>,
+    pub tracking_id: Option<String>,
+    /// Fields which were set on create or upsert, or which changed on update
+    pub changed_fields: Vec<String>,
+    pub created_at: chrono::NaiveDateTime,
+}
+
+#[derive(Debug, Insertable, Clone)]
+#[diesel(table_name = chunk_changes)]
+pub struct NewChunkChange {
+    pub dataset_id: uuid::Uuid,
+    pub operation: String,
+    pub chunk_id: Option<uuid::Uuid>,
+    pub tracking_id: Option<String>,
+    pub changed_fields: Vec<String>,
+    pub created_at: chrono::NaiveDateTime,
+}
+
+impl NewChunkChange {
+    pub fn from_details(
+        dataset_id: uuid::Uuid,
+        operation: ChunkChangeOperation,
+        chunk_id: Option<uuid::Uuid>,
+        tracking_id: Option<String>,
+        changed_fields: Vec<String>,
+    ) -> Self {
+        NewChunkChange {
+            dataset_id,
+            operation: operation.to_string(),
+            chunk_id,
+            tracking_id,
+            changed_fields,
+            created_at: chrono::Utc::now().naive_local(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Queryable, Insertable, Selectable, Clone, ToSchema)]
+#[schema(example = json!({
+    "id": "e3e3e3e3-e3e3-e3e3-e3e3-e3e3e3e3e3e3",
+    "dataset_id": "e3e3e3e3-e3e3-e3e3-e3e3-e3e3e3e3e3e3",
+    "url": "https://example.com/trieve/changes",
+    "operations": ["create", "update", "delete"],
+    "active": true,
+    "last_delivered_seq": 1042,
+    "consecutive_failures": 0,
+    "next_attempt_at": "2021-01-01 00:00:00.000",
+    "created_at": "2021-01-01 00:00:00.000",
+    "updated_at": "2021-01-01 00:00:00.000",
+}))]
+#[diesel(table_name = change_webhooks)]
+pub struct ChangeWebhook {
+    pub id: uuid::Uuid,
+    pub dataset_id: uuid::Uuid,
+    /// Url the changes are POSTed to
+    pub url: String,
+    /// Key used to sign deliveries. Only returned when the webhook is created.
+    #[serde(skip_serializing, default)]
+    pub secret: String,
+    /// Operations which are delivered. All operations are delivered if not set.
+    pub operations: Option<Vec<String>>,
+    /// Whether changes are being delivered. Webhooks are deactivated after too many consecutive failed deliveries.
+    pub active: bool,
+    /// Sequence number of the last change which was delivered or skipped
+    pub last_delivered_seq: i64,
+    pub consecutive_failures: i32,
+    /// Time of the next delivery attempt, pushed back after each failed delivery
+    pub next_attempt_at: chrono::NaiveDateTime,
+    pub created_at: chrono::NaiveDateTime,
+    pub updated_at: chrono::NaiveDateTime,
+}
+
+impl ChangeWebhook {
+    pub fn from_details(
+        dataset_id: uuid::Uuid,
+        url: String,
+        secret: String,
+        operations: Option<Vec<ChunkChangeOperation>>,
+        last_delivered_seq: i64,
+    ) -> Self {
+        ChangeWebhook {
+            id: uuid::Uuid::new_v4(),
+            dataset_id,
+            url,
+            secret,
+            operations: operations.map(|operations| {
+                operations
+                    .iter()
+                    .map(|operation| operation.to_string())
+                    .collect()
+            }),
+            active: true,
+            last_delivered_seq,
+            consecutive_failures: 0,
+            next_attempt_at: chrono::Utc::now().naive_local(),
+            created_at: chrono::Utc::now().naive_local(),
+            updated_at: chrono::Utc::now().naive_local(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Queryable, Insertable, Selectable, Clone, ToSchema)]
+#[schema(example = json!({
+    "id": "e3e3e3e3-e3e3-e3e3-e3e3-e3e3e3e3e3e3",
+    "webhook_id": "e3e3e3e3-e3e3-e3e3-e3e3-e3e3e3e3e3e3",
+    "first_seq": 1000,
+    "last_seq": 1042,
+    "attempt": 1,
+    "success": true,
+    "status_code": 200,
+    "error": null,
+    "created_at": "2021-01-01 00:00:00.000",
+}))]
+#[diesel(table_name = change_webhook_deliveries)]
+pub struct ChangeWebhookDelivery {
+    pub id: uuid::Uuid,
+    pub webhook_id: uuid::Uuid,
+    /// Sequence number of the first change in the delivery
+    pub first_seq: i64,
+    /// Sequence number of the last change in the delivery
+    pub last_seq: i64,
+    /// Attempt number for this batch of changes, starting at 1
+    pub attempt: i32,
+    pub success: bool,
+    /// HTTP status code returned by the webhook url, if it responded
+    pub status_code: Option<i32>,
+    pub error: Option<String>,
+    pub created_at: chrono::NaiveDateTime,
+}
+
+impl ChangeWebhookDelivery {
+    pub fn from_details(
+        webhook_id: uuid::Uuid,
+        first_seq: i64,
+        last_seq: i64,
+        attempt: i32,
+        status_code: Option<i32>,
+        error: Option<String>,
+    ) -> Self {
+        ChangeWebhookDelivery {
+            id: uuid::Uuid::new_v4(),
+            webhook_id,
+            first_seq,
+            last_seq,
+            attempt,
+            success: error.is_none(),
+            status_code,
+            error,
+            created_at: chrono::Utc::now().naive_local(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Queryable, Insertable, Selectable, Clone, ToSchema)]
+#[schema(example = json!({
+    "id": "e3e3e3e3-e3e3-e3e3-e3e3-e3e3e3e3e3e3",
+    "dataset_id": "e3e3e3e3-e3e3-e3e3-e3e3-e3e3e3e3e3e3",
+    "url": "https://example.com/trieve/events",
+    "event_types": ["file_uploaded", "file_upload_failed", "crawl_completed"],
+    "active": true,
+    "created_at": "2021-01-01 00:00:00.000",
+    "updated_at": "2021-01-01 00:00:00.000",
+}))]
+#[diesel(table_name = event_webhooks)]
+pub struct EventWebhook {
+    pub id: uuid::Uuid,
+    pub dataset_id: uuid::Uuid,
+    /// Url the events are POSTed to
+    pub url: String,
+    /// Key used to sign deliveries. Only returned when the webhook is created.
+    #[serde(skip_serializing, default)]
+    pub secret: String,
+    /// Event types which are delivered to the webhook
+    pub event_types: Vec<String>,
+    /// Whether new events are queued for delivery to the webhook
+    pub active: bool,
+    pub created_at: chrono::NaiveDateTime,
+    pub updated_at: chrono::NaiveDateTime,
+}
+
+impl EventWebhook {
+    pub fn from_details(
+        dataset_id: uuid::Uuid,
+        url: String,
+        secret: String,
+        event_types: Vec<EventTypeRequest>,
+    ) -> Self {
+        EventWebhook {
+            id: uuid::Uuid::new_v4(),
+            dataset_id,
+            url,
+            secret,
+            event_types: event_types
+                .iter()
+                .map(|event_type| event_type.to_string())
+                .collect(),
+            active: true,
+            created_at: chrono::Utc::now().naive_local(),
+            updated_at: chrono::Utc::now().naive_local(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema, Display, Clone, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum EventWebhookDeliveryStatus {
+    #[display(fmt = "pending")]
+    Pending,
+    #[display(fmt = "delivered")]
+    Delivered,
+    #[display(fmt = "failed")]
+    Failed,
+}
+
+#[derive(Debug, Serialize, Deserialize, Queryable, Insertable, Selectable, Clone, ToSchema)]
+#[schema(example = json!({
+    "id": "e3e3e3e3-e3e3-e3e3-e3e3-e3e3e3e3e3e3",
+    "webhook_id": "e3e3e3e3-e3e3-e3e3-e3e3-e3e3e3e3e3e3",
+    "event_id": "e3e3e3e3-e3e3-e3e3-e3e3-e3e3e3e3e3e3",
+    "event_type": "file_uploaded",
+    "payload": {"event_type": "file_uploaded", "event_data": {"file_id": "e3e3e3e3-e3e3-e3e3-e3e3-e3e3e3e3e3e3"}},
+    "status": "delivered",
+    "attempts": 1,
+    "next_attempt_at": "2021-01-01 00:00:00.000",
+    "last_status_code": 200,
+    "last_error": null,
+    "created_at": "2021-01-01 00:00:00.000",
+    "updated_at": "2021-01-01 00:00:00.000",
+}))]
+#[diesel(table_name = event_webhook_deliveries)]
+pub struct EventWebhookDelivery {
+    pub id: uuid::Uuid,
+    pub webhook_id: uuid::Uuid,
+    /// Id of the worker event, stable across retries so receivers can deduplicate deliveries
+    pub event_id: uuid::Uuid,
+    pub event_type: String,
+    /// Body which is POSTed to the webhook url
+    pub payload: serde_json::Value,
+    /// One of `pending`, `delivered` or `failed`. Failed deliveries have exhausted their retries.
+    pub status: String,
+    pub attempts: i32,
+    /// Time of the next delivery attempt while the delivery is pending
+    pub next_attempt_at: chrono::NaiveDateTime,
+    /// HTTP status code returned by the webhook url on the last attempt, if it responded
+    pub last_status_code: Option<i32>,
+    pub last_error: Option<String>,
+    pub created_at: chrono::NaiveDateTime,
+    pub updated_at: chrono::NaiveDateTime,
+}
+
+impl EventWebhookDelivery {
+    pub fn from_details(
+        webhook_id: uuid::Uuid,
+        event_id: uuid::Uuid,
+        event_type: String,
+        payload: serde_json::Value,
+    ) -> Self {
+        EventWebhookDelivery {
+            id: uuid::Uuid::new_v4(),
+            webhook_id,
+            event_id,
+            event_type,
+            payload,
+            status: EventWebhookDeliveryStatus::Pending.to_string(),
+            attempts: 0,
+            next_attempt_at: chrono::Utc::now().naive_local(),
+            last_status_code: None,
+            last_error: None,
+            created_at: chrono::Utc::now().naive_local(),
+            updated_at: chrono::Utc::now().naive_local(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema, Display, Clone, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum DatasetConfigVersionSource {
+    /// The configuration the dataset had before its first recorded change
+    #[display(fmt = "baseline")]
+    Baseline,
+    #[display(fmt = "update_dataset")]
+    UpdateDataset,
+    #[display(fmt = "update_org_dataset_configs")]
+    UpdateOrgDatasetConfigs,
+    #[display(fmt = "rollback")]
+    Rollback,
+    #[display(fmt = "reindex")]
+    Reindex,
+}
+
+#[derive(Debug, Serialize, Deserialize, Queryable, Insertable, Selectable, Clone, ToSchema)]
+#[schema(example = json!({
+    "id": "e3e3e3e3-e3e3-e3e3-e3e3-e3e3e3e3e3e3",
+    "dataset_id": "e3e3e3e3-e3e3-e3e3-e3e3-e3e3e3e3e3e3",
+    "version": 3,
+    "server_configuration": {
+        "EMBEDDING_SIZE": 1536,
+        "RAG_PROMPT": "Use the following retrieved documents to respond briefly and accurately:",
+    },
+    "diff": {
+        "RAG_PROMPT": {
+            "from": "Use the following retrieved documents to respond:",
+            "to": "Use the following retrieved documents to respond briefly and accurately:",
+        },
+    },
+    "source": "update_dataset",
+    "changed_by": "e3e3e3e3-e3e3-e3e3-e3e3-e3e3e3e3e3e3",
+    "restored_version": null,
+    "created_at": "2021-01-01 00:00:00.000",
+}))]
+#[diesel(table_name = dataset_config_versions)]
+pub struct DatasetConfigVersion {
+    pub id: uuid::Uuid,
+    pub dataset_id: uuid::Uuid,
+    /// Version number, starting at 1 and increasing by 1 with each change
+    pub version: i32,
+    /// The dataset's configuration after the change
+    pub server_configuration: serde_json::Value,
+    /// Keys which changed, each with the value `from` before and `to` after the change. Api keys are redacted.
+    pub diff: serde_json::Value,
+    /// What made the change. One of `baseline`, `update_dataset`, `update_org_dataset_configs`, `rollback` or `reindex`.
+    pub source: String,
+    /// Id of the user who made the change, if it was made by a user
+    pub changed_by: Option<uuid::Uuid>,
+    /// Version which was restored, if the change was a rollback
+    pub restored_version: Option<i32>,
+    pub created_at: chrono::NaiveDateTime,
+}
+
+impl DatasetConfigVersion {
+    pub fn from_details(
+        dataset_id: uuid::Uuid,
+        version: i32,
+        server_configuration: serde_json::Value,
+        diff: serde_json::Value,
+        source: DatasetConfigVersionSource,
+        changed_by: Option<uuid::Uuid>,
+        restored_version: Option<i32>,
+    ) -> Self {
+        DatasetConfigVersion {
+            id: uuid::Uuid::new_v4(),
+            dataset_id,
+            version,
+            server_configuration,
+            diff,
+            source: source.to_string(),
+            changed_by,
+            restored_version,
+            created_at: chrono::Utc::now().naive_local(),
+        }
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, ToSchema, Clone)]
 pub struct SortByField {
-    /// Field to sort by. This has to be a numeric field with a Qdrant `Range` index on it. i.e. num_value and timestamp
+    /// Field to sort by. This has to be a numeric field with a Qdrant `Range` index on it. i.e. num_value and timestamp. When searching over groups, this can also be `group_aggregates.<name>` to sort the groups by the value of a group aggregate.
     pub field: String,
     /// Direction to sort by
     pub direction: Option<SortOrder>,
@@ -8970,6 +10417,17 @@ pub struct HighlightOptions {
     pub post_tag: Option<String>,
 }
 
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema, Default)]
+/// Context Window lets you expand each result with the chunks around it in the document it was created from. Only chunks created from files or crawls record their position in the document, other results are returned without expanded content.
+pub struct ContextWindow {
+    /// Number of chunks before each result to include. If not specified, this defaults to 1.
+    pub before: Option<u32>,
+    /// Number of chunks after each result to include. If not specified, this defaults to 1.
+    pub after: Option<u32>,
+    /// Set parent_section to true to include every chunk under the same heading path as the result instead of a fixed number of neighbors. Falls back to before and after for chunks without a heading path. If not specified, this defaults to false.
+    pub parent_section: Option<bool>,
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone, ToSchema, Default)]
 /// Typo Options lets you specify different methods to correct typos in the query. If not specified, typos will not be corrected.
 pub struct TypoOptions {
@@ -9290,6 +10748,7 @@ impl<'de> Deserialize<'de> for SearchChunksReqPayload {
             user_id: Option<String>,
             typo_options: Option<TypoOptions>,
             metadata: Option<serde_json::Value>,
+            context_window: Option<ContextWindow>,
             #[serde(flatten)]
             other: std::collections::HashMap<String, serde_json::Value>,
         }
@@ -9323,6 +10782,7 @@ impl<'de> Deserialize<'de> for SearchChunksReqPayload {
             remove_stop_words: helper.remove_stop_words,
             user_id: helper.user_id,
             typo_options: helper.typo_options,
+            context_window: helper.context_window,
         })
     }
 }
@@ -9475,6 +10935,7 @@ impl<'de> Deserialize<'de> for SearchOverGroupsReqPayload {
             sort_options: Option<SortOptions>,
             scoring_options: Option<ScoringOptions>,
             metadata: Option<serde_json::Value>,
+            group_level: Option<u32>,
             #[serde(flatten)]
             other: std::collections::HashMap<String, serde_json::Value>,
         }
@@ -9506,6 +10967,7 @@ impl<'de> Deserialize<'de> for SearchOverGroupsReqPayload {
             sort_options,
             remove_stop_words: helper.remove_stop_words,
             user_id: helper.user_id,
+            group_level: helper.group_level,
             scoring_options: helper.scoring_options,
         })
     }
@@ -9547,6 +11009,8 @@ impl<'de> Deserialize<'de> for CreateMessageReqPayload {
             pub only_include_docs_used: Option<bool>,
             pub number_of_messages_to_include: Option<u64>,
             pub model: Option<String>,
+            pub context_window: Option<ContextWindow>,
+            pub include_group_summaries: Option<bool>,
         }
 
         let mut helper = Helper::deserialize(deserializer)?;
@@ -9590,6 +11054,8 @@ impl<'de> Deserialize<'de> for CreateMessageReqPayload {
             only_include_docs_used: helper.only_include_docs_used,
             number_of_messages_to_include: helper.number_of_messages_to_include,
             model: helper.model,
+            context_window: helper.context_window,
+            include_group_summaries: helper.include_group_summaries,
         })
     }
 }
@@ -9627,6 +11093,8 @@ impl<'de> Deserialize<'de> for RegenerateMessageReqPayload {
             pub only_include_docs_used: Option<bool>,
             pub number_of_messages_to_include: Option<u64>,
             pub model: Option<String>,
+            pub context_window: Option<ContextWindow>,
+            pub include_group_summaries: Option<bool>,
         }
 
         let mut helper = Helper::deserialize(deserializer)?;
@@ -9667,6 +11135,8 @@ impl<'de> Deserialize<'de> for RegenerateMessageReqPayload {
             only_include_docs_used: helper.only_include_docs_used,
             number_of_messages_to_include: helper.number_of_messages_to_include,
             model: helper.model,
+            context_window: helper.context_window,
+            include_group_summaries: helper.include_group_summaries,
         })
     }
 }
@@ -9708,6 +11178,8 @@ impl<'de> Deserialize<'de> for EditMessageReqPayload {
             pub only_include_docs_used: Option<bool>,
             pub number_of_messages_to_include: Option<u64>,
             pub model: Option<String>,
+            pub context_window: Option<ContextWindow>,
+            pub include_group_summaries: Option<bool>,
         }
 
         let mut helper = Helper::deserialize(deserializer)?;
@@ -9752,6 +11224,8 @@ impl<'de> Deserialize<'de> for EditMessageReqPayload {
             only_include_docs_used: helper.only_include_docs_used,
             number_of_messages_to_include: helper.number_of_messages_to_include,
             model: helper.model,
+            context_window: helper.context_window,
+            include_group_summaries: helper.include_group_summaries,
         })
     }
 }
@@ -1,5 +1,35 @@
 // @generated automatically by Diesel CLI.
 
+diesel::table! {
+    change_webhook_deliveries (id) {
+        id -> Uuid,
+        webhook_id -> Uuid,
+        first_seq -> Int8,
+        last_seq -> Int8,
+        attempt -> Int4,
+        success -> Bool,
+        status_code -> Nullable<Int4>,
+        error -> Nullable<Text>,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    change_webhooks (id) {
+        id -> Uuid,
+        dataset_id -> Uuid,
+        url -> Text,
+        secret -> Text,
+        operations -> Nullable<Array<Text>>,
+        active -> Bool,
+        last_delivered_seq -> Int8,
+        consecutive_failures -> Int4,
+        next_attempt_at -> Timestamp,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+    }
+}
+
 diesel::table! {
     chunk_boosts (chunk_id) {
         chunk_id -> Uuid,
@@ -10,6 +40,29 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    chunk_changes (seq) {
+        seq -> Int8,
+        dataset_id -> Uuid,
+        operation -> Text,
+        chunk_id -> Nullable<Uuid>,
+        tracking_id -> Nullable<Text>,
+        changed_fields -> Array<Text>,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    chunk_fingerprints (chunk_id) {
+        chunk_id -> Uuid,
+        dataset_id -> Uuid,
+        content_hash -> Text,
+        simhash -> Int8,
+        simhash_bands -> Array<Int4>,
+        created_at -> Timestamp,
+    }
+}
+
 diesel::table! {
     chunk_group (id) {
         id -> Uuid,
@@ -21,6 +74,7 @@ diesel::table! {
         tracking_id -> Nullable<Text>,
         metadata -> Nullable<Jsonb>,
         tag_set -> Nullable<Array<Nullable<Text>>>,
+        parent_id -> Nullable<Uuid>,
     }
 }
 
@@ -76,6 +130,50 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    dataset_aliases (id) {
+        id -> Uuid,
+        organization_id -> Uuid,
+        name -> Text,
+        dataset_id -> Uuid,
+        previous_dataset_id -> Nullable<Uuid>,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    dataset_clones (id) {
+        id -> Uuid,
+        dataset_id -> Uuid,
+        source_dataset_id -> Uuid,
+        status -> Text,
+        filter -> Nullable<Jsonb>,
+        group_ids -> Nullable<Array<Uuid>>,
+        vectors_reused -> Nullable<Bool>,
+        groups_cloned -> Int8,
+        files_cloned -> Int8,
+        chunks_cloned -> Int8,
+        error -> Nullable<Text>,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    dataset_config_versions (id) {
+        id -> Uuid,
+        dataset_id -> Uuid,
+        version -> Int4,
+        server_configuration -> Jsonb,
+        diff -> Jsonb,
+        source -> Text,
+        changed_by -> Nullable<Uuid>,
+        restored_version -> Nullable<Int4>,
+        created_at -> Timestamp,
+    }
+}
+
 diesel::table! {
     dataset_event_counts (id) {
         id -> Uuid,
@@ -84,6 +182,21 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    dataset_exports (id) {
+        id -> Uuid,
+        dataset_id -> Uuid,
+        status -> Text,
+        format -> Text,
+        filter -> Nullable<Jsonb>,
+        s3_key -> Nullable<Text>,
+        chunks_exported -> Int8,
+        error -> Nullable<Text>,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+    }
+}
+
 diesel::table! {
     dataset_group_counts (id) {
         id -> Uuid,
@@ -92,6 +205,42 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    dataset_imports (id) {
+        id -> Uuid,
+        dataset_id -> Uuid,
+        status -> Text,
+        format -> Text,
+        export_id -> Nullable<Uuid>,
+        source_url -> Nullable<Text>,
+        vectors_reused -> Nullable<Bool>,
+        groups_imported -> Int8,
+        files_imported -> Int8,
+        chunks_imported -> Int8,
+        error -> Nullable<Text>,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    dataset_reindexes (id) {
+        id -> Uuid,
+        dataset_id -> Uuid,
+        status -> Text,
+        mode -> Jsonb,
+        from_collection -> Text,
+        to_collection -> Text,
+        points_total -> Nullable<Int8>,
+        points_migrated -> Int8,
+        points_failed -> Int8,
+        error -> Nullable<Text>,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+        started_at -> Nullable<Timestamp>,
+    }
+}
+
 diesel::table! {
     dataset_tags (id) {
         id -> Uuid,
@@ -121,6 +270,52 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    event_webhook_deliveries (id) {
+        id -> Uuid,
+        webhook_id -> Uuid,
+        event_id -> Uuid,
+        event_type -> Text,
+        payload -> Jsonb,
+        status -> Text,
+        attempts -> Int4,
+        next_attempt_at -> Timestamp,
+        last_status_code -> Nullable<Int4>,
+        last_error -> Nullable<Text>,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    event_webhooks (id) {
+        id -> Uuid,
+        dataset_id -> Uuid,
+        url -> Text,
+        secret -> Text,
+        event_types -> Array<Text>,
+        active -> Bool,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    file_versions (id) {
+        id -> Uuid,
+        file_id -> Uuid,
+        dataset_id -> Uuid,
+        version -> Int4,
+        file_name -> Text,
+        size -> Int8,
+        metadata -> Nullable<Jsonb>,
+        link -> Nullable<Text>,
+        time_stamp -> Nullable<Timestamp>,
+        tag_set -> Nullable<Array<Nullable<Text>>>,
+        created_at -> Timestamp,
+    }
+}
+
 diesel::table! {
     files (id) {
         id -> Uuid,
@@ -133,6 +328,30 @@ diesel::table! {
         dataset_id -> Uuid,
         tag_set -> Nullable<Array<Nullable<Text>>>,
         size -> Int8,
+        tracking_id -> Nullable<Text>,
+        version -> Int4,
+    }
+}
+
+diesel::table! {
+    group_aggregate_values (group_id, aggregate_id) {
+        group_id -> Uuid,
+        aggregate_id -> Uuid,
+        dataset_id -> Uuid,
+        value -> Nullable<Float8>,
+        updated_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    group_aggregates (id) {
+        id -> Uuid,
+        dataset_id -> Uuid,
+        name -> Text,
+        field -> Text,
+        aggregation -> Text,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
     }
 }
 
@@ -352,7 +571,12 @@ diesel::table! {
     }
 }
 
+diesel::joinable!(change_webhook_deliveries -> change_webhooks (webhook_id));
+diesel::joinable!(change_webhooks -> datasets (dataset_id));
 diesel::joinable!(chunk_boosts -> chunk_metadata (chunk_id));
+diesel::joinable!(chunk_changes -> datasets (dataset_id));
+diesel::joinable!(chunk_fingerprints -> chunk_metadata (chunk_id));
+diesel::joinable!(chunk_fingerprints -> datasets (dataset_id));
 diesel::joinable!(chunk_group -> datasets (dataset_id));
 diesel::joinable!(chunk_group_bookmarks -> chunk_group (group_id));
 diesel::joinable!(chunk_group_bookmarks -> chunk_metadata (chunk_metadata_id));
@@ -360,11 +584,23 @@ diesel::joinable!(chunk_metadata -> datasets (dataset_id));
 diesel::joinable!(chunk_metadata_tags -> chunk_metadata (chunk_metadata_id));
 diesel::joinable!(chunk_metadata_tags -> dataset_tags (tag_id));
 diesel::joinable!(crawl_requests -> datasets (dataset_id));
+diesel::joinable!(dataset_aliases -> organizations (organization_id));
+diesel::joinable!(dataset_clones -> datasets (dataset_id));
+diesel::joinable!(dataset_config_versions -> datasets (dataset_id));
 diesel::joinable!(dataset_event_counts -> datasets (dataset_uuid));
+diesel::joinable!(dataset_exports -> datasets (dataset_id));
+diesel::joinable!(dataset_imports -> datasets (dataset_id));
+diesel::joinable!(dataset_reindexes -> datasets (dataset_id));
 diesel::joinable!(dataset_tags -> datasets (dataset_id));
 diesel::joinable!(dataset_usage_counts -> datasets (dataset_id));
 diesel::joinable!(datasets -> organizations (organization_id));
+diesel::joinable!(event_webhook_deliveries -> event_webhooks (webhook_id));
+diesel::joinable!(event_webhooks -> datasets (dataset_id));
+diesel::joinable!(file_versions -> files (file_id));
 diesel::joinable!(files -> datasets (dataset_id));
+diesel::joinable!(group_aggregate_values -> chunk_group (group_id));
+diesel::joinable!(group_aggregate_values -> group_aggregates (aggregate_id));
+diesel::joinable!(group_aggregates -> datasets (dataset_id));
 diesel::joinable!(groups_from_files -> chunk_group (group_id));
 diesel::joinable!(groups_from_files -> files (file_id));
 diesel::joinable!(messages -> datasets (dataset_id));
@@ -382,18 +618,33 @@ diesel::joinable!(user_organizations -> organizations (organization_id));
 diesel::joinable!(user_organizations -> users (user_id));
 
 diesel::allow_tables_to_appear_in_same_query!(
+    change_webhook_deliveries,
+    change_webhooks,
     chunk_boosts,
+    chunk_changes,
+    chunk_fingerprints,
     chunk_group,
     chunk_group_bookmarks,
     chunk_metadata,
     chunk_metadata_tags,
     crawl_requests,
+    dataset_aliases,
+    dataset_clones,
+    dataset_config_versions,
     dataset_event_counts,
+    dataset_exports,
     dataset_group_counts,
+    dataset_imports,
+    dataset_reindexes,
     dataset_tags,
     dataset_usage_counts,
     datasets,
+    event_webhook_deliveries,
+    event_webhooks,
+    file_versions,
     files,
+    group_aggregate_values,
+    group_aggregates,
     groups_from_files,
     invitations,
     messages,
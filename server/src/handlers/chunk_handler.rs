@@ -2,34 +2,51 @@ use super::auth_handler::{AdminOnly, LoggedUser};
 #[cfg(not(feature = "hallucination-detection"))]
 use crate::data::models::DummyHallucinationScore;
 use crate::data::models::{
-    escape_quotes, ChatMessageProxy, ChunkMetadata, ChunkMetadataStringTagSet, ChunkMetadataTypes,
-    ChunkMetadataWithScore, ConditionType, ContextOptions, CountSearchMethod,
-    DatasetAndOrgWithSubAndPlan, DatasetConfiguration, GeoInfo, HighlightOptions, ImageConfig,
-    IngestSpecificChunkMetadata, MultiQuery, Pool, QdrantChunkMetadata, QueryTypes,
+    escape_quotes, ChatMessageProxy, ChunkChangeOperation, ChunkMetadata,
+    ChunkMetadataStringTagSet, ChunkMetadataTypes, ChunkMetadataWithScore, ConditionType,
+    ContextOptions, ContextWindow, CountSearchMethod, Dataset, DatasetAndOrgWithSubAndPlan,
+    DatasetConfiguration, GeoInfo, HighlightOptions, ImageConfig, IngestSpecificChunkMetadata,
+    MultiQuery, NewChunkChange, OrganizationWithSubAndPlan, Pool, QdrantChunkMetadata, QueryTypes,
     RagQueryEventClickhouse, RecommendType, RecommendationEventClickhouse, RecommendationStrategy,
     RedisPool, RoleProxy, ScoreChunk, ScoreChunkDTO, SearchMethod, SearchModalities,
     SearchQueryEventClickhouse, SlimChunkMetadataWithScore, SortByField, SortOptions, TypoOptions,
-    UnifiedId, UpdateSpecificChunkMetadata,
+    UnifiedId, UpdateSpecificChunkMetadata, UserApiKey,
 };
 use crate::errors::ServiceError;
 use crate::get_env;
 use crate::middleware::api_version::APIVersion;
+use crate::middleware::auth_middleware::verify_api_key_dataset_access;
+use crate::operators::change_feed_operator::{
+    get_chunk_fields_changed, insert_chunk_changes_query,
+};
 use crate::operators::chunk_operator::get_metadata_from_id_query;
 use crate::operators::clickhouse_operator::{get_latency_from_header, ClickHouseEvent, EventQueue};
 use crate::operators::dataset_operator::{
-    get_dataset_usage_query, ChunkDeleteMessage, DeleteMessage,
+    get_dataset_and_organization_from_dataset_id_query, get_dataset_usage_query,
+    ChunkDeleteMessage, DeleteMessage,
+};
+use crate::operators::group_aggregate_operator::{
+    get_group_ids_for_chunks_query, mark_group_aggregates_stale_query,
 };
+use crate::operators::group_centroid_operator::mark_group_centroids_stale_query;
+use crate::operators::group_summary_operator::mark_group_summaries_stale_query;
 use crate::operators::message_operator::get_text_from_audio;
 use crate::operators::model_operator::{count_message_tokens, count_tokens};
 use crate::operators::parse_operator::convert_html_to_text;
 use crate::operators::qdrant_operator::{
     point_ids_exists_in_qdrant, recommend_qdrant_query, scroll_dataset_points,
+    set_qdrant_point_payload_query,
 };
 use crate::operators::search_operator::{
     assemble_qdrant_filter, autocomplete_chunks_query, count_chunks_query, parse_query,
-    search_chunks_query, search_hybrid_chunks, ParsedQuery, ParsedQueryTypes,
+    search_chunks_query, search_federated_chunks_query, search_hybrid_chunks,
+    FederatedSearchDataset, ParsedQuery, ParsedQueryTypes,
+};
+use crate::operators::{
+    chunk_operator::*,
+    chunking_operator::{self, ChunkingStrategy},
+    crawl_operator,
 };
-use crate::operators::{chunk_operator::*, crawl_operator};
 use actix::Arbiter;
 use actix_web::web::Bytes;
 use actix_web::{web, HttpResponse};
@@ -592,6 +609,7 @@ pub async fn create_chunk(
 pub async fn delete_chunk(
     chunk_id: web::Path<uuid::Uuid>,
     pool: web::Data<Pool>,
+    redis_pool: web::Data<RedisPool>,
     _user: AdminOnly,
     dataset_org_plan_sub: DatasetAndOrgWithSubAndPlan,
 ) -> Result<HttpResponse, actix_web::Error> {
@@ -599,6 +617,9 @@ pub async fn delete_chunk(
         DatasetConfiguration::from_json(dataset_org_plan_sub.dataset.server_configuration.clone());
 
     let chunk_id = chunk_id.into_inner();
+    let dataset_id = dataset_org_plan_sub.dataset.id;
+
+    let group_ids = get_group_ids_for_chunks_query(vec![chunk_id], pool.clone()).await?;
 
     let deleted_at = chrono::Utc::now().naive_utc();
 
@@ -607,9 +628,19 @@ pub async fn delete_chunk(
         deleted_at,
         dataset_org_plan_sub.dataset,
         pool,
-        dataset_config,
+        dataset_config.clone(),
+    )
+    .await?;
+
+    mark_group_aggregates_stale_query(dataset_id, group_ids.clone(), redis_pool.clone()).await?;
+    mark_group_centroids_stale_query(
+        &dataset_config,
+        dataset_id,
+        group_ids.clone(),
+        redis_pool.clone(),
     )
     .await?;
+    mark_group_summaries_stale_query(&dataset_config, dataset_id, group_ids, redis_pool).await?;
 
     Ok(HttpResponse::NoContent().finish())
 }
@@ -697,6 +728,7 @@ pub async fn bulk_delete_chunk(
 pub async fn delete_chunk_by_tracking_id(
     tracking_id: web::Path<String>,
     pool: web::Data<Pool>,
+    redis_pool: web::Data<RedisPool>,
     _user: AdminOnly,
     dataset_org_plan_sub: DatasetAndOrgWithSubAndPlan,
 ) -> Result<HttpResponse, actix_web::Error> {
@@ -708,6 +740,8 @@ pub async fn delete_chunk_by_tracking_id(
     let chunk_metadata =
         get_metadata_from_tracking_id_query(tracking_id_inner, dataset_id, pool.clone()).await?;
 
+    let group_ids = get_group_ids_for_chunks_query(vec![chunk_metadata.id], pool.clone()).await?;
+
     let deleted_at = chrono::Utc::now().naive_utc();
 
     delete_chunk_metadata_query(
@@ -715,10 +749,20 @@ pub async fn delete_chunk_by_tracking_id(
         deleted_at,
         dataset_org_plan_sub.dataset,
         pool,
-        dataset_config,
+        dataset_config.clone(),
     )
     .await?;
 
+    mark_group_aggregates_stale_query(dataset_id, group_ids.clone(), redis_pool.clone()).await?;
+    mark_group_centroids_stale_query(
+        &dataset_config,
+        dataset_id,
+        group_ids.clone(),
+        redis_pool.clone(),
+    )
+    .await?;
+    mark_group_summaries_stale_query(&dataset_config, dataset_id, group_ids, redis_pool).await?;
+
     Ok(HttpResponse::NoContent().finish())
 }
 
@@ -1059,6 +1103,248 @@ pub async fn update_chunk_by_tracking_id(
     Ok(HttpResponse::NoContent().finish())
 }
 
+/// A single RFC 6902 JSON patch operation. Paths are JSON pointers relative to the chunk's metadata object.
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+#[serde(tag = "op", rename_all = "lowercase")]
+pub enum JsonPatchOperation {
+    Add {
+        path: String,
+        value: serde_json::Value,
+    },
+    Remove {
+        path: String,
+    },
+    Replace {
+        path: String,
+        value: serde_json::Value,
+    },
+    Move {
+        from: String,
+        path: String,
+    },
+    Copy {
+        from: String,
+        path: String,
+    },
+    Test {
+        path: String,
+        value: serde_json::Value,
+    },
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+#[schema(example = json!({
+    "tracking_id": "sku-1234",
+    "metadata_merge_patch": {"price": 19.99, "discontinued": null},
+    "metadata_json_patch": [
+        {"op": "replace", "path": "/inventory/sf", "value": 3}
+    ],
+    "num_value": 19.99,
+    "tag_set_add": ["on-sale"],
+    "tag_set_remove": ["full-price"],
+}))]
+pub struct PatchChunkReqPayload {
+    /// Id of the chunk you want to patch. You can provide either the chunk_id or the tracking_id. If both are provided, the chunk_id will be used.
+    pub chunk_id: Option<uuid::Uuid>,
+    /// Tracking_id of the chunk you want to patch. This is required to match an existing chunk if chunk_id is not provided.
+    pub tracking_id: Option<String>,
+    /// RFC 7396 JSON merge patch applied to the chunk's existing metadata. Keys set to null are removed. Applied before `metadata_json_patch`.
+    pub metadata_merge_patch: Option<serde_json::Value>,
+    /// RFC 6902 JSON patch operations applied to the chunk's existing metadata. If any operation fails, including a `test` operation, nothing is updated.
+    pub metadata_json_patch: Option<Vec<JsonPatchOperation>>,
+    /// Tags to add to the chunk's existing tag_set.
+    pub tag_set_add: Option<Vec<String>>,
+    /// Tags to remove from the chunk's existing tag_set.
+    pub tag_set_remove: Option<Vec<String>>,
+    /// Num value to set on the chunk. If not provided, the existing num_value will be kept.
+    pub num_value: Option<f64>,
+    /// Weight to set on the chunk. If not provided, the existing weight will be kept.
+    pub weight: Option<f64>,
+    /// Link to set on the chunk. If not provided, the existing link will be kept.
+    pub link: Option<String>,
+    /// Time_stamp should be an ISO 8601 combined date and time without timezone. If not provided, the existing time_stamp will be kept.
+    pub time_stamp: Option<String>,
+    /// Location to set on the chunk. If not provided, the existing location will be kept.
+    pub location: Option<GeoInfo>,
+    /// Image urls to set on the chunk. If not provided, the existing image_urls will be kept.
+    pub image_urls: Option<Vec<String>>,
+}
+
+/// Patch Chunk
+///
+/// Partially update a chunk without re-embedding it. Only metadata, tags, num_value, weight, link, time_stamp, location and image_urls can be patched, so the chunk's vectors are left as-is and only its payload is updated. This is much cheaper than the Update Chunk route and is designed for high frequency changes like price and inventory updates. Auth'ed user or api key must have an admin or owner role for the specified dataset's organization.
+#[utoipa::path(
+    patch,
+    path = "/chunk",
+    context_path = "/api",
+    tag = "Chunk",
+    request_body(content = PatchChunkReqPayload, description = "JSON request payload to patch a chunk", content_type = "application/json"),
+    responses(
+        (status = 200, description = "The chunk after the patch was applied", body = ChunkMetadata),
+        (status = 400, description = "Service error relating to patching the chunk, likely due to an invalid or failing patch operation", body = ErrorResponseBody),
+        (status = 404, description = "Chunk not found", body = ErrorResponseBody),
+    ),
+    params(
+        ("TR-Dataset" = uuid::Uuid, Header, description = "The dataset id or tracking_id to use for the request. We assume you intend to use an id if the value is a valid uuid."),
+    ),
+    security(
+        ("ApiKey" = ["admin"]),
+    )
+)]
+#[tracing::instrument(skip_all)]
+pub async fn patch_chunk(
+    patch_chunk_data: web::Json<PatchChunkReqPayload>,
+    pool: web::Data<Pool>,
+    redis_pool: web::Data<RedisPool>,
+    _user: AdminOnly,
+    dataset_org_plan_sub: DatasetAndOrgWithSubAndPlan,
+) -> Result<HttpResponse, actix_web::Error> {
+    let dataset_id = dataset_org_plan_sub.dataset.id;
+    let dataset_config =
+        DatasetConfiguration::from_json(dataset_org_plan_sub.dataset.server_configuration.clone());
+    let patch_chunk_data = patch_chunk_data.into_inner();
+
+    let mut chunk_metadata = if let Some(chunk_id) = patch_chunk_data.chunk_id {
+        get_metadata_from_id_query(chunk_id, dataset_id, pool.clone()).await?
+    } else if let Some(tracking_id) = patch_chunk_data.tracking_id.clone() {
+        get_metadata_from_tracking_id_query(tracking_id, dataset_id, pool.clone()).await?
+    } else {
+        return Err(ServiceError::BadRequest(
+            "Either chunk_id or tracking_id must be provided to patch a chunk".into(),
+        )
+        .into());
+    };
+    let prev_chunk_metadata = chunk_metadata.clone();
+
+    let mut qdrant_payload = serde_json::Map::new();
+    let metadata_patched = patch_chunk_data.metadata_merge_patch.is_some()
+        || patch_chunk_data.metadata_json_patch.is_some();
+    let num_value_patched = patch_chunk_data.num_value.is_some();
+
+    if metadata_patched {
+        let mut metadata = chunk_metadata.metadata.clone().unwrap_or(json!({}));
+        if let Some(merge_patch) = patch_chunk_data.metadata_merge_patch.as_ref() {
+            apply_json_merge_patch(&mut metadata, merge_patch);
+        }
+        if let Some(operations) = patch_chunk_data.metadata_json_patch.as_ref() {
+            apply_json_patch(&mut metadata, operations)?;
+        }
+
+        qdrant_payload.insert("metadata".to_string(), metadata.clone());
+        chunk_metadata.metadata = Some(metadata);
+    }
+
+    let tag_set =
+        if patch_chunk_data.tag_set_add.is_some() || patch_chunk_data.tag_set_remove.is_some() {
+            let tags_to_remove = patch_chunk_data.tag_set_remove.unwrap_or_default();
+            let tag_set = chunk_metadata
+                .tag_set
+                .clone()
+                .unwrap_or_default()
+                .into_iter()
+                .flatten()
+                .chain(patch_chunk_data.tag_set_add.unwrap_or_default())
+                .filter(|tag| !tags_to_remove.contains(tag))
+                .unique()
+                .map(Some)
+                .collect::<Vec<Option<String>>>();
+
+            qdrant_payload.insert("tag_set".to_string(), json!(tag_set));
+            Some(tag_set)
+        } else {
+            None
+        };
+
+    if let Some(num_value) = patch_chunk_data.num_value {
+        qdrant_payload.insert("num_value".to_string(), json!(num_value));
+        chunk_metadata.num_value = Some(num_value);
+    }
+
+    if let Some(weight) = patch_chunk_data.weight {
+        qdrant_payload.insert("weight".to_string(), json!(weight));
+        chunk_metadata.weight = weight;
+    }
+
+    if let Some(link) = patch_chunk_data.link {
+        qdrant_payload.insert("link".to_string(), json!(link));
+        chunk_metadata.link = Some(link);
+    }
+
+    if let Some(time_stamp) = patch_chunk_data.time_stamp {
+        let time_stamp = time_stamp
+            .parse::<DateTimeUtc>()
+            .map_err(|_| ServiceError::BadRequest("Invalid timestamp format".to_string()))?
+            .0
+            .with_timezone(&chrono::Local)
+            .naive_local();
+
+        qdrant_payload.insert("time_stamp".to_string(), json!(time_stamp.timestamp()));
+        chunk_metadata.time_stamp = Some(time_stamp);
+    }
+
+    if let Some(location) = patch_chunk_data.location {
+        qdrant_payload.insert("location".to_string(), json!(location));
+        chunk_metadata.location = Some(location);
+    }
+
+    if let Some(image_urls) = patch_chunk_data.image_urls {
+        let image_urls = image_urls.into_iter().map(Some).collect::<Vec<_>>();
+        qdrant_payload.insert("image_urls".to_string(), json!(image_urls));
+        chunk_metadata.image_urls = Some(image_urls);
+    }
+
+    if qdrant_payload.is_empty() {
+        return Err(ServiceError::BadRequest("No fields to patch were provided".into()).into());
+    }
+
+    let qdrant_point_id = chunk_metadata.qdrant_point_id;
+    // A tag_set of None makes the query keep and return the chunk's existing tags
+    chunk_metadata.tag_set = tag_set;
+
+    let updated_chunk =
+        update_chunk_metadata_query(chunk_metadata, None, dataset_id, pool.clone()).await?;
+
+    insert_chunk_changes_query(
+        vec![NewChunkChange::from_details(
+            dataset_id,
+            ChunkChangeOperation::Update,
+            Some(updated_chunk.id),
+            updated_chunk.tracking_id.clone(),
+            get_chunk_fields_changed(&prev_chunk_metadata, &updated_chunk),
+        )],
+        pool.clone(),
+    )
+    .await?;
+
+    set_qdrant_point_payload_query(
+        qdrant_point_id,
+        serde_json::Value::Object(qdrant_payload),
+        dataset_config.clone(),
+    )
+    .await?;
+
+    // Aggregates are computed from num_value and metadata, so the chunk's groups need theirs
+    // recomputed
+    if metadata_patched || num_value_patched {
+        let group_ids = get_group_ids_for_chunks_query(vec![updated_chunk.id], pool).await?;
+        mark_group_aggregates_stale_query(dataset_id, group_ids.clone(), redis_pool.clone())
+            .await?;
+        if metadata_patched {
+            mark_group_centroids_stale_query(
+                &dataset_config,
+                dataset_id,
+                group_ids.clone(),
+                redis_pool.clone(),
+            )
+            .await?;
+            mark_group_summaries_stale_query(&dataset_config, dataset_id, group_ids, redis_pool)
+                .await?;
+        }
+    }
+
+    Ok(HttpResponse::Ok().json(updated_chunk))
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
 #[schema(example = json!({
     "must": [
@@ -1156,6 +1442,8 @@ pub struct SearchChunksReqPayload {
     pub metadata: Option<serde_json::Value>,
     /// Typo options lets you specify different methods to handle typos in the search query. If not specified, this defaults to no typo handling.
     pub typo_options: Option<TypoOptions>,
+    /// Context window lets you expand each result with the chunks around it in the document it was created from. The expanded content is only returned for API version V2. If not specified, results are not expanded.
+    pub context_window: Option<ContextWindow>,
 }
 
 impl Default for SearchChunksReqPayload {
@@ -1178,6 +1466,7 @@ impl Default for SearchChunksReqPayload {
             user_id: None,
             typo_options: None,
             metadata: None,
+            context_window: None,
         }
     }
 }
@@ -1227,6 +1516,57 @@ pub fn is_audio(query: QueryTypes) -> bool {
     matches!(query, QueryTypes::Single(SearchModalities::Audio { .. }))
 }
 
+async fn parse_search_query(
+    data: &SearchChunksReqPayload,
+    dataset: &Dataset,
+) -> Result<ParsedQueryTypes, ServiceError> {
+    match data.query.clone() {
+        QueryTypes::Single(query) => Ok(ParsedQueryTypes::Single(
+            parse_query(
+                query.clone(),
+                dataset,
+                data.use_quote_negated_terms,
+                data.remove_stop_words,
+            )
+            .await?,
+        )),
+        QueryTypes::Multi(query) => {
+            let parsed_queries = futures::future::join_all(query.into_iter().map(|multi_query| {
+                let value = dataset.clone();
+                async move {
+                    let parsed_query = parse_query(
+                        multi_query.query.clone(),
+                        &value,
+                        data.use_quote_negated_terms,
+                        data.remove_stop_words,
+                    )
+                    .await?;
+                    Ok((parsed_query, multi_query.weight))
+                        as Result<(ParsedQuery, f32), ServiceError>
+                }
+            }))
+            .await
+            .into_iter()
+            .collect::<Result<Vec<_>, _>>()?;
+            Ok(ParsedQueryTypes::Multi(parsed_queries))
+        }
+    }
+}
+
+fn get_parsed_query_string(parsed_query: &ParsedQueryTypes) -> String {
+    match parsed_query {
+        ParsedQueryTypes::Single(query) => query.query.clone(),
+        ParsedQueryTypes::Multi(ref query) => serde_json::to_string(
+            &query
+                .clone()
+                .into_iter()
+                .map(Into::into)
+                .collect::<Vec<MultiQuery>>(),
+        )
+        .unwrap_or_default(),
+    }
+}
+
 /// Search
 ///
 /// This route provides the primary search functionality for the API. It can be used to search for chunks by semantic similarity, full-text similarity, or a combination of both. Results' `chunk_html` values will be modified with `<mark><b>` or custom specified tags for sub-sentence highlighting.
@@ -1263,49 +1603,9 @@ pub async fn search_chunks(
 
     let mut data = data.into_inner();
 
-    let parsed_query = match data.query.clone() {
-        QueryTypes::Single(query) => ParsedQueryTypes::Single(
-            parse_query(
-                query.clone(),
-                &dataset_org_plan_sub.dataset,
-                data.use_quote_negated_terms,
-                data.remove_stop_words,
-            )
-            .await?,
-        ),
-        QueryTypes::Multi(query) => {
-            let parsed_queries = futures::future::join_all(query.into_iter().map(|multi_query| {
-                let value = dataset_org_plan_sub.dataset.clone();
-                async move {
-                    let parsed_query = parse_query(
-                        multi_query.query.clone(),
-                        &value,
-                        data.use_quote_negated_terms,
-                        data.remove_stop_words,
-                    )
-                    .await?;
-                    Ok((parsed_query, multi_query.weight))
-                        as Result<(ParsedQuery, f32), ServiceError>
-                }
-            }))
-            .await
-            .into_iter()
-            .collect::<Result<Vec<_>, _>>()?;
-            ParsedQueryTypes::Multi(parsed_queries)
-        }
-    };
+    let parsed_query = parse_search_query(&data, &dataset_org_plan_sub.dataset).await?;
 
-    let query = match &parsed_query {
-        ParsedQueryTypes::Single(query) => query.query.clone(),
-        ParsedQueryTypes::Multi(ref query) => serde_json::to_string(
-            &query
-                .clone()
-                .into_iter()
-                .map(Into::into)
-                .collect::<Vec<MultiQuery>>(),
-        )
-        .unwrap_or_default(),
-    };
+    let query = get_parsed_query_string(&parsed_query);
 
     if query.is_empty() {
         return Err(ServiceError::BadRequest("Query cannot be empty".to_string()).into());
@@ -1320,7 +1620,7 @@ pub async fn search_chunks(
             search_hybrid_chunks(
                 data.clone(),
                 parsed_query.to_parsed_query()?,
-                pool,
+                pool.clone(),
                 redis_pool,
                 dataset_org_plan_sub.dataset.clone(),
                 &dataset_config,
@@ -1332,7 +1632,7 @@ pub async fn search_chunks(
             search_chunks_query(
                 data.clone(),
                 parsed_query,
-                pool,
+                pool.clone(),
                 redis_pool,
                 dataset_org_plan_sub.dataset.clone(),
                 &dataset_config,
@@ -1384,6 +1684,18 @@ pub async fn search_chunks(
     timer.add("send_to_clickhouse");
 
     if api_version == APIVersion::V2 {
+        let mut search_response = result_chunks.into_v2(search_id);
+        if let Some(context_window) = data.context_window.as_ref() {
+            expand_score_chunks_with_context_query(
+                &mut search_response.chunks,
+                context_window,
+                dataset_org_plan_sub.dataset.id,
+                pool,
+            )
+            .await?;
+            timer.add("expand_context_window");
+        }
+
         if is_audio(data.query.clone()) {
             return Ok(HttpResponse::Ok()
                 .insert_header((Timer::header_key(), timer.header_value()))
@@ -1391,11 +1703,11 @@ pub async fn search_chunks(
                     "X-TR-Query",
                     query.replace(|c: char| c.is_ascii_control(), ""),
                 ))
-                .json(SearchResponseTypes::V2(result_chunks.into_v2(search_id))));
+                .json(SearchResponseTypes::V2(search_response)));
         } else {
             return Ok(HttpResponse::Ok()
                 .insert_header((Timer::header_key(), timer.header_value()))
-                .json(SearchResponseTypes::V2(result_chunks.into_v2(search_id))));
+                .json(SearchResponseTypes::V2(search_response)));
         }
     }
 
@@ -1404,6 +1716,232 @@ pub async fn search_chunks(
         .json(result_chunks))
 }
 
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+#[schema(example = json!({
+    "datasets": ["d290f1ee-6c54-4b01-90e6-d701748f0851", "docs-dataset"],
+    "search": {
+        "search_type": "semantic",
+        "query": "Some search query",
+        "page_size": 10,
+    },
+    "rerank": true
+}))]
+pub struct FederatedSearchReqPayload {
+    /// Ids or tracking ids of the datasets to search. Every dataset must belong to the organization in the TR-Organization header and be accessible to the API key used. At most 20 datasets can be searched in one request.
+    pub datasets: Vec<String>,
+    /// The search to run against each dataset. Page and page_size are applied within each dataset, and page_size also limits the merged result set. Context windows are not supported.
+    pub search: SearchChunksReqPayload,
+    /// If true, the merged results are reranked with the cross encoder configured for the first dataset. Otherwise results are ordered by their score normalized within their dataset. Defaults to false.
+    pub rerank: Option<bool>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct FederatedScoreChunk {
+    /// Id of the dataset the chunk was found in.
+    pub dataset_id: uuid::Uuid,
+    #[serde(flatten)]
+    pub score_chunk: ScoreChunk,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct FederatedSearchResponseBody {
+    pub id: uuid::Uuid,
+    pub chunks: Vec<FederatedScoreChunk>,
+}
+
+const MAX_FEDERATED_SEARCH_DATASETS: usize = 20;
+
+/// Federated Search
+///
+/// Search across several datasets of an organization in one request. The query is embedded once for every group of datasets sharing an embedding model, each dataset is searched, scores are normalized per dataset and the results are merged. Each result is tagged with the dataset it was found in.
+#[utoipa::path(
+    post,
+    path = "/chunk/federated_search",
+    context_path = "/api",
+    tag = "Chunk",
+    request_body(content = FederatedSearchReqPayload, description = "JSON request payload to search for chunks across several datasets", content_type = "application/json"),
+    responses(
+        (status = 200, description = "Merged chunks from all of the requested datasets", body = FederatedSearchResponseBody),
+        (status = 400, description = "Service error relating to searching", body = ErrorResponseBody),
+        (status = 401, description = "The API key cannot access one of the requested datasets", body = ErrorResponseBody),
+    ),
+    params(
+        ("TR-Organization" = uuid::Uuid, Header, description = "The organization id to use for the request"),
+    ),
+    security(
+        ("ApiKey" = ["readonly"]),
+    )
+)]
+#[tracing::instrument(skip_all)]
+pub async fn federated_search_chunks(
+    data: web::Json<FederatedSearchReqPayload>,
+    _user: LoggedUser,
+    user_api_key: Option<web::ReqData<UserApiKey>>,
+    org_with_plan_and_sub: OrganizationWithSubAndPlan,
+    pool: web::Data<Pool>,
+    event_queue: web::Data<EventQueue>,
+    redis_pool: web::Data<RedisPool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let data = data.into_inner();
+    let organization_id = org_with_plan_and_sub.organization.id;
+
+    if data.datasets.is_empty() {
+        return Err(
+            ServiceError::BadRequest("At least one dataset must be specified".to_string()).into(),
+        );
+    }
+
+    if data.datasets.len() > MAX_FEDERATED_SEARCH_DATASETS {
+        return Err(ServiceError::BadRequest(format!(
+            "At most {} datasets can be searched at once",
+            MAX_FEDERATED_SEARCH_DATASETS
+        ))
+        .into());
+    }
+
+    if data.search.context_window.is_some() {
+        return Err(ServiceError::BadRequest(
+            "Context windows are not supported for federated search".to_string(),
+        )
+        .into());
+    }
+
+    let mut timer = Timer::new();
+
+    let mut datasets: Vec<Dataset> = vec![];
+    for requested_dataset in data.datasets.iter() {
+        let unified_id = match requested_dataset.parse::<uuid::Uuid>() {
+            Ok(dataset_id) => UnifiedId::TrieveUuid(dataset_id),
+            Err(_) => UnifiedId::TrackingId(requested_dataset.clone()),
+        };
+
+        let dataset = get_dataset_and_organization_from_dataset_id_query(
+            unified_id,
+            Some(organization_id),
+            pool.clone(),
+        )
+        .await?
+        .dataset;
+
+        if dataset.organization_id != organization_id {
+            return Err(ServiceError::Unauthorized.into());
+        }
+
+        if let Some(user_api_key) = user_api_key.as_ref() {
+            if !verify_api_key_dataset_access(user_api_key, dataset.id, organization_id) {
+                return Err(ServiceError::Unauthorized.into());
+            }
+        }
+
+        if !datasets.iter().any(|existing| existing.id == dataset.id) {
+            datasets.push(dataset);
+        }
+    }
+
+    timer.add("resolved datasets");
+
+    let mut search_data = data.search.clone();
+    search_data.score_threshold = search_data
+        .score_threshold
+        .filter(|threshold| *threshold != 0.0);
+
+    let federated_datasets = futures::future::try_join_all(datasets.into_iter().map(|dataset| {
+        let search_data = search_data.clone();
+        async move {
+            let parsed_query = parse_search_query(&search_data, &dataset).await?;
+            let config = DatasetConfiguration::from_json(dataset.server_configuration.clone());
+
+            Ok(FederatedSearchDataset {
+                dataset,
+                config,
+                data: search_data,
+                parsed_query,
+            }) as Result<FederatedSearchDataset, ServiceError>
+        }
+    }))
+    .await?;
+
+    let query = get_parsed_query_string(&federated_datasets[0].parsed_query);
+    if query.is_empty() {
+        return Err(ServiceError::BadRequest("Query cannot be empty".to_string()).into());
+    }
+
+    timer.add("parsed queries");
+
+    let analytics_datasets = federated_datasets
+        .iter()
+        .filter(|federated_dataset| !federated_dataset.config.DISABLE_ANALYTICS)
+        .map(|federated_dataset| federated_dataset.dataset.id)
+        .collect_vec();
+
+    let result_chunks = search_federated_chunks_query(
+        federated_datasets,
+        data.rerank.unwrap_or(false),
+        search_data.page_size.unwrap_or(10),
+        pool,
+        redis_pool,
+        &mut timer,
+    )
+    .await?;
+
+    timer.add("federated_search_chunks");
+
+    let search_id = uuid::Uuid::new_v4();
+
+    for dataset_id in analytics_datasets {
+        let dataset_results = result_chunks
+            .iter()
+            .filter(|result_chunk| result_chunk.dataset_id == dataset_id)
+            .collect_vec();
+
+        let clickhouse_event = SearchQueryEventClickhouse {
+            id: uuid::Uuid::new_v4(),
+            search_type: String::from("search"),
+            organization_id,
+            tokens: count_tokens(&query),
+            query: query.clone(),
+            request_params: serde_json::to_string(&data).unwrap_or_default(),
+            latency: get_latency_from_header(timer.header_value()),
+            top_score: dataset_results
+                .first()
+                .map(|x| x.score_chunk.score as f32)
+                .unwrap_or(0.0),
+            results: dataset_results
+                .iter()
+                .map(|x| {
+                    let mut json = serde_json::to_value(&x.score_chunk).unwrap_or_default();
+                    escape_quotes(&mut json);
+                    json.to_string()
+                })
+                .collect(),
+            metadata: serde_json::to_string(&search_data.metadata).unwrap_or_default(),
+            dataset_id,
+            created_at: time::OffsetDateTime::now_utc(),
+            query_rating: String::from(""),
+            user_id: search_data.user_id.clone().unwrap_or_default(),
+        };
+
+        event_queue
+            .send(ClickHouseEvent::SearchQueryEvent(clickhouse_event))
+            .await;
+    }
+
+    timer.add("send_to_clickhouse");
+
+    Ok(HttpResponse::Ok()
+        .insert_header((Timer::header_key(), timer.header_value()))
+        .json(FederatedSearchResponseBody {
+            id: search_id,
+            chunks: result_chunks
+                .into_iter()
+                .map(|result_chunk| FederatedScoreChunk {
+                    dataset_id: result_chunk.dataset_id,
+                    score_chunk: result_chunk.score_chunk.into(),
+                })
+                .collect(),
+        }))
+}
+
 #[derive(Serialize, Clone, Debug, ToSchema)]
 #[schema(example = json!({
     "search_type": "semantic",
@@ -1508,6 +2046,7 @@ impl From<AutocompleteReqPayload> for SearchChunksReqPayload {
             user_id: autocomplete_data.user_id,
             typo_options: autocomplete_data.typo_options,
             metadata: autocomplete_data.metadata,
+            context_window: None,
         }
     }
 }
@@ -1843,6 +2382,7 @@ impl From<CountChunksReqPayload> for SearchChunksReqPayload {
             user_id: None,
             typo_options: None,
             metadata: None,
+            context_window: None,
         }
     }
 }
@@ -2421,6 +2961,7 @@ pub async fn get_recommended_chunks(
                 chunk: chunk_metadata.into(),
                 highlights: None,
                 score,
+                expanded_content: None,
             }
         })
         .collect::<Vec<ScoreChunk>>();
@@ -3070,6 +3611,8 @@ pub struct ChunkHtmlContentReqPayload {
     pub heading_remove_strings: Option<Vec<String>>,
     /// Text strings to remove from body when creating chunks for each page
     pub body_remove_strings: Option<Vec<String>>,
+    /// Chunking strategy used to split the content. If not specified, the content is split on heading html tags. Strategies which need the embedding model of a dataset (token_window and semantic) use the dataset from the TR-Dataset header if one is provided and the default embedding model otherwise.
+    pub chunking_strategy: Option<ChunkingStrategy>,
 }
 
 #[derive(Debug, Serialize, Deserialize, ToSchema)]
@@ -3096,7 +3639,7 @@ pub struct SplitHtmlResponse {
     "body": "This is the body of the content"
 }))]
 pub struct ChunkedContent {
-    /// The headings of the content in order of when they appear
+    /// The headings of the content in order of when they appear. When a chunking_strategy is used, this is the path of headings the content was found under.
     pub headings: Vec<String>,
     /// The body of the content
     pub body: String,
@@ -3105,8 +3648,8 @@ pub struct ChunkedContent {
 /// Split HTML Content into Chunks
 ///
 /// This endpoint receives a single html string and splits it into chunks based on the headings and
-/// body content. The headings are split based on heading html tags. chunk_html has a maximum size
-/// of 256Kb.
+/// body content. The headings are split based on heading html tags unless a chunking_strategy is
+/// specified. chunk_html has a maximum size of 256Kb.
 #[utoipa::path(
     post,
     path = "/chunk/split",
@@ -3127,6 +3670,7 @@ pub struct ChunkedContent {
 #[tracing::instrument(skip_all)]
 pub async fn split_html_content(
     body: web::Json<ChunkHtmlContentReqPayload>,
+    dataset_org_plan_sub: Option<DatasetAndOrgWithSubAndPlan>,
 ) -> Result<HttpResponse, ServiceError> {
     if body.chunk_html.len() >= 262_144 {
         return Err(ServiceError::PayloadTooLarge(
@@ -3134,6 +3678,49 @@ pub async fn split_html_content(
         ));
     }
 
+    if let Some(chunking_strategy) = body.chunking_strategy.as_ref() {
+        let dataset_config = match dataset_org_plan_sub {
+            Some(dataset_org_plan_sub) => {
+                DatasetConfiguration::from_json(dataset_org_plan_sub.dataset.server_configuration)
+            }
+            None => DatasetConfiguration::from_json(serde_json::json!({})),
+        };
+
+        let chunks = chunking_operator::chunk_document(
+            &body.chunk_html,
+            chunking_operator::DocumentFormat::Html,
+            chunking_strategy,
+            &dataset_config,
+        )
+        .await?;
+
+        return Ok(HttpResponse::Ok().json(SplitHtmlResponse {
+            chunks: chunks
+                .into_iter()
+                .map(|chunk| {
+                    let mut headings = chunk.heading_path;
+                    let mut content = chunk.content;
+                    if let Some(heading_remove_strings) = &body.heading_remove_strings {
+                        headings.iter_mut().for_each(|heading| {
+                            heading_remove_strings.iter().for_each(|remove_string| {
+                                *heading = heading.replace(remove_string, "");
+                            });
+                        });
+                    }
+                    if let Some(body_remove_strings) = &body.body_remove_strings {
+                        body_remove_strings.iter().for_each(|remove_string| {
+                            content = content.replace(remove_string, "");
+                        });
+                    }
+                    ChunkedContent {
+                        headings,
+                        body: content,
+                    }
+                })
+                .collect(),
+        }));
+    }
+
     let chunked_content = crawl_operator::chunk_html(
         &body.chunk_html,
         body.heading_remove_strings.clone(),
@@ -1,13 +1,37 @@
 use super::auth_handler::{AdminOnly, LoggedUser, OwnerOnly};
+use super::chunk_handler::ChunkFilter;
 use crate::{
     data::models::{
-        Dataset, DatasetAndOrgWithSubAndPlan, DatasetConfiguration, DatasetConfigurationDTO,
-        DatasetDTO, OrganizationWithSubAndPlan, PagefindIndexWorkerMessage, Pool, RedisPool,
+        ChangeWebhook, ChangeWebhookDelivery, ChunkChange, ChunkChangeOperation, Dataset,
+        DatasetAlias, DatasetAndOrgWithSubAndPlan, DatasetClone, DatasetConfigVersion,
+        DatasetConfigVersionSource, DatasetConfiguration, DatasetConfigurationDTO, DatasetDTO,
+        DatasetExport, DatasetExportFormat, DatasetExportMessage, DatasetExportStatus,
+        DatasetImport, DatasetImportMessage, DatasetReindex, DatasetReindexMessage,
+        EventTypeRequest, EventWebhook, EventWebhookDelivery, EventWebhookDeliveryStatus,
+        MigrationMode, OrganizationWithSubAndPlan, PagefindIndexWorkerMessage, Pool, RedisPool,
+        UserRole,
     },
     errors::ServiceError,
     get_env,
+    middleware::auth_middleware::get_role_for_org,
     operators::{
+        change_feed_operator::{
+            create_change_webhook_query, delete_change_webhook_query, generate_webhook_secret,
+            get_change_webhook_deliveries_query, get_change_webhook_query,
+            get_change_webhooks_query, get_chunk_changes_query, get_latest_chunk_change_seq_query,
+            resolve_webhook_url, update_change_webhook_query,
+        },
         chunk_operator::get_chunk_queue_length,
+        clone_operator::{create_dataset_clone_query, get_latest_dataset_clone_query},
+        dataset_alias_operator::{
+            create_dataset_alias_query, delete_dataset_alias_query,
+            get_dataset_alias_names_in_use_query, get_dataset_aliases_query,
+            swap_dataset_alias_query,
+        },
+        dataset_config_version_operator::{
+            check_reindex_required_config_changes, get_dataset_config_diff,
+            get_dataset_config_version_query, get_dataset_config_versions_query,
+        },
         dataset_operator::{
             clear_dataset_by_dataset_id_query, create_dataset_query, create_datasets_query,
             get_dataset_by_id_query, get_dataset_by_tracking_id_query, get_dataset_usage_query,
@@ -17,12 +41,32 @@ use crate::{
         dittofeed_operator::{
             send_ditto_event, DittoDatasetCreated, DittoTrackProperties, DittoTrackRequest,
         },
-        file_operator::get_file_queue_length,
+        event_webhook_operator::{
+            create_event_webhook_query, delete_event_webhook_query,
+            get_event_webhook_deliveries_query, get_event_webhook_query, get_event_webhooks_query,
+            send_test_event_webhook, update_event_webhook_query,
+        },
+        export_operator::{
+            create_dataset_export_query, get_dataset_export_for_organization_query,
+            get_dataset_export_query,
+        },
+        file_operator::{get_aws_bucket, get_file_queue_length},
+        group_centroid_operator::mark_dataset_group_centroids_stale_query,
+        import_operator::{
+            create_dataset_import_query, get_dataset_import_query, resolve_import_url,
+        },
         organization_operator::{get_org_dataset_count, get_org_from_id_query},
+        qdrant_operator::{get_qdrant_collection_from_dataset_config, get_qdrant_collections},
+        reindex_operator::{
+            apply_migration_mode, cancel_dataset_reindex_query, create_dataset_reindex_query,
+            get_active_dataset_reindex_query, get_dataset_reindex_eta, get_dataset_reindex_query,
+        },
+        search_operator::assemble_qdrant_filter,
     },
 };
-use actix_web::{web, FromRequest, HttpMessage, HttpResponse};
+use actix_web::{web, web::Bytes, FromRequest, HttpMessage, HttpRequest, HttpResponse};
 use broccoli_queue::queue::BroccoliQueue;
+use futures::stream;
 use serde::{Deserialize, Serialize};
 use serde_json::json;
 use std::future::{ready, Ready};
@@ -103,12 +147,31 @@ impl FromRequest for OrganizationWithSubAndPlan {
 pub struct CreateDatasetReqPayload {
     /// Name of the dataset.
     pub dataset_name: String,
-    /// Optional tracking ID for the dataset. Can be used to track the dataset in external systems. Must be unique within the organization. Strongly recommended to not use a valid uuid value as that will not work with the TR-Dataset header.
+    /// Optional tracking ID for the dataset. Can be used to track the dataset in external systems. Must be unique within the organization and cannot be the name of a dataset alias. Strongly recommended to not use a valid uuid value as that will not work with the TR-Dataset header.
     pub tracking_id: Option<String>,
     /// The configuration of the dataset. See the example request payload for the potential keys which can be set. It is possible to break your dataset's functionality by erroneously setting this field. We recommend setting through creating a dataset at dashboard.trieve.ai and managing it's settings there.
     pub server_configuration: Option<DatasetConfigurationDTO>,
 }
 
+/// Dataset tracking ids and alias names are resolved from the same TR-Dataset header value, so a tracking id cannot reuse the name of an alias.
+async fn check_tracking_ids_not_aliases(
+    tracking_ids: Vec<String>,
+    organization_id: uuid::Uuid,
+    pool: web::Data<Pool>,
+) -> Result<(), ServiceError> {
+    let alias_names =
+        get_dataset_alias_names_in_use_query(tracking_ids, organization_id, pool).await?;
+
+    if !alias_names.is_empty() {
+        return Err(ServiceError::BadRequest(format!(
+            "The tracking id {} is already used as a dataset alias in this organization",
+            alias_names.join(", ")
+        )));
+    }
+
+    Ok(())
+}
+
 async fn create_dataset_helper(
     data: CreateDatasetReqPayload,
     pool: web::Data<Pool>,
@@ -117,14 +180,40 @@ async fn create_dataset_helper(
 ) -> Result<Dataset, ServiceError> {
     let org_id = org_with_sub_and_plan.organization.id;
 
+    if let Some(dedup_policy) = data
+        .server_configuration
+        .as_ref()
+        .and_then(|config| config.DEDUP_POLICY.as_ref())
+    {
+        dedup_policy.validate()?;
+    }
+
+    let dataset_config: DatasetConfiguration = data
+        .server_configuration
+        .clone()
+        .map(|c| c.into())
+        .unwrap_or_default();
+
+    if let Some(group_centroids) = dataset_config.GROUP_CENTROIDS.as_ref() {
+        group_centroids.validate(&dataset_config)?;
+    }
+
+    if let Some(group_summaries) = dataset_config.GROUP_SUMMARIES.as_ref() {
+        group_summaries.validate()?;
+    }
+
+    check_tracking_ids_not_aliases(
+        data.tracking_id.clone().into_iter().collect(),
+        org_id,
+        pool.clone(),
+    )
+    .await?;
+
     let dataset = Dataset::from_details(
         data.dataset_name.clone(),
         org_id,
         data.tracking_id.clone(),
-        data.server_configuration
-            .clone()
-            .map(|c| c.into())
-            .unwrap_or_default(),
+        dataset_config,
     );
 
     let d = create_dataset_query(dataset.clone(), pool.clone()).await?;
@@ -239,13 +328,15 @@ pub struct UpdateDatasetReqPayload {
     pub dataset_name: Option<String>,
     /// The configuration of the dataset. See the example request payload for the potential keys which can be set. It is possible to break your dataset's functionality by erroneously updating this field. We recommend updating through the settings panel for your dataset at dashboard.trieve.ai.
     pub server_configuration: Option<DatasetConfigurationDTO>,
-    /// Optional new tracking ID for the dataset. Can be used to track the dataset in external systems. Must be unique within the organization. If not provided, the tracking ID will not be updated. Strongly recommended to not use a valid uuid value as that will not work with the TR-Dataset header.
+    /// Optional new tracking ID for the dataset. Can be used to track the dataset in external systems. Must be unique within the organization and cannot be the name of a dataset alias. If not provided, the tracking ID will not be updated. Strongly recommended to not use a valid uuid value as that will not work with the TR-Dataset header.
     pub new_tracking_id: Option<String>,
+    /// Changes to keys the dataset's vectors were computed with (EMBEDDING_SIZE, DISTANCE_METRIC, EMBEDDING_MODEL_NAME, BM25_B, BM25_K and BM25_AVG_LEN) are rejected unless this is true, as the dataset needs to be reindexed for search to work with the new values. Prefer the reindex API, which migrates the vectors before applying the change. Defaults to false.
+    pub acknowledge_reindex_required: Option<bool>,
 }
 
 /// Update Dataset by ID or Tracking ID
 ///
-/// One of id or tracking_id must be provided. The auth'ed user must be an owner of the organization to update a dataset.
+/// One of id or tracking_id must be provided. Every change to the server_configuration is recorded as a configuration version which can be listed, diffed and rolled back. The auth'ed user must be an owner of the organization to update a dataset.
 #[utoipa::path(
     put,
     path = "/dataset",
@@ -267,7 +358,8 @@ pub struct UpdateDatasetReqPayload {
 pub async fn update_dataset(
     data: web::Json<UpdateDatasetReqPayload>,
     pool: web::Data<Pool>,
-    _user: OwnerOnly,
+    redis_pool: web::Data<RedisPool>,
+    user: OwnerOnly,
     org_with_plan_and_sub: OrganizationWithSubAndPlan,
 ) -> Result<HttpResponse, ServiceError> {
     let curr_dataset = if let Some(dataset_id) = data.dataset_id {
@@ -285,20 +377,60 @@ pub async fn update_dataset(
         ));
     };
 
+    if let Some(dedup_policy) = data
+        .server_configuration
+        .as_ref()
+        .and_then(|config| config.DEDUP_POLICY.as_ref())
+    {
+        dedup_policy.validate()?;
+    }
+
     let curr_dataset_config = DatasetConfiguration::from_json(curr_dataset.server_configuration);
+    let new_dataset_config = data
+        .server_configuration
+        .clone()
+        .map(|c| c.from_curr_dataset(curr_dataset_config.clone()))
+        .unwrap_or(curr_dataset_config.clone());
+
+    check_reindex_required_config_changes(
+        &curr_dataset_config,
+        &new_dataset_config,
+        data.acknowledge_reindex_required.unwrap_or(false),
+    )?;
+
+    if let Some(group_centroids) = new_dataset_config.GROUP_CENTROIDS.as_ref() {
+        group_centroids.validate(&new_dataset_config)?;
+    }
+
+    if let Some(group_summaries) = new_dataset_config.GROUP_SUMMARIES.as_ref() {
+        group_summaries.validate()?;
+    }
+
+    check_tracking_ids_not_aliases(
+        data.new_tracking_id.clone().into_iter().collect(),
+        org_with_plan_and_sub.organization.id,
+        pool.clone(),
+    )
+    .await?;
 
     let d = update_dataset_query(
         curr_dataset.id,
         data.dataset_name.clone().unwrap_or(curr_dataset.name),
-        data.server_configuration
-            .clone()
-            .map(|c| c.from_curr_dataset(curr_dataset_config.clone()))
-            .unwrap_or(curr_dataset_config),
+        new_dataset_config.clone(),
         data.new_tracking_id.clone(),
+        DatasetConfigVersionSource::UpdateDataset,
+        Some(user.0.id),
+        None,
         pool.clone(),
     )
     .await?;
 
+    // Groups created before centroids were enabled need theirs computed
+    if curr_dataset_config.GROUP_CENTROIDS.is_none() {
+        mark_dataset_group_centroids_stale_query(&new_dataset_config, d.id, pool, redis_pool)
+            .await?;
+    }
+
     Ok(HttpResponse::Ok().json(d))
 }
 
@@ -383,10 +515,18 @@ pub struct CloneDatasetRequest {
     pub dataset_to_clone: uuid::Uuid,
     /// Name of the dataset.
     pub dataset_name: String,
-    /// Optional tracking ID for the dataset. Can be used to track the dataset in external systems. Must be unique within the organization. Strongly recommended to not use a valid uuid value as that will not work with the TR-Dataset header.
+    /// Optional tracking ID for the dataset. Can be used to track the dataset in external systems. Must be unique within the organization and cannot be the name of a dataset alias. Strongly recommended to not use a valid uuid value as that will not work with the TR-Dataset header.
     pub tracking_id: Option<String>,
     /// Parameter to Clone Chunks from the original dataset to the new dataset. defaults to true.
     pub clone_chunks: Option<bool>,
+    /// Only clone the chunks matching this filter. Defaults to cloning every chunk.
+    pub filter: Option<ChunkFilter>,
+    /// Only clone these groups and the chunks in them. Can be combined with `filter` to clone the matching chunks within the groups.
+    pub group_ids: Option<Vec<uuid::Uuid>>,
+    /// Organization to create the new dataset in. Defaults to the organization in the TR-Organization header. The auth'ed user must be an owner of it.
+    pub target_organization_id: Option<uuid::Uuid>,
+    /// Configuration values which override the cloned dataset's configuration. Chunks are only re-embedded if the overrides change the embedding model, size or distance metric; otherwise their vectors are copied as-is.
+    pub server_configuration: Option<DatasetConfigurationDTO>,
 }
 
 impl From<CloneDatasetRequest> for CreateDatasetReqPayload {
@@ -394,7 +534,7 @@ impl From<CloneDatasetRequest> for CreateDatasetReqPayload {
         CreateDatasetReqPayload {
             dataset_name: request.dataset_name,
             tracking_id: request.tracking_id,
-            server_configuration: None,
+            server_configuration: request.server_configuration,
         }
     }
 }
@@ -403,11 +543,14 @@ impl From<CloneDatasetRequest> for CreateDatasetReqPayload {
 pub struct CloneDatasetMessage {
     pub dataset_to_clone: uuid::Uuid,
     pub new_dataset: uuid::Uuid,
+    /// Id of the clone tracking the progress. Messages queued before clones were tracked do not have one.
+    #[serde(default)]
+    pub clone_id: Option<uuid::Uuid>,
 }
 
 /// Clone Dataset
 ///
-/// Clones a dataset and creates a new dataset with the same configuration and chunks. The auth'ed user must be an owner of the organization to clone a dataset.
+/// Clones a dataset and creates a new dataset with the same configuration and chunks. A subset of the chunks can be cloned with `filter` or `group_ids`, the new dataset can be created in another organization with `target_organization_id` and configuration values can be overridden with `server_configuration`. Progress can be followed with the clone status route using the new dataset. The auth'ed user must be an admin of the cloned dataset's organization and an owner of the organization the new dataset is created in.
 #[utoipa::path(
     post,
     path = "/dataset/clone",
@@ -434,13 +577,58 @@ pub async fn clone_dataset(
     let dataset_uuid_to_clone = data.dataset_to_clone;
     let og_dataset = get_dataset_by_id_query(dataset_uuid_to_clone, pool.clone()).await?;
 
+    if get_role_for_org(&user.0, &og_dataset.organization_id)
+        .map_or(true, |role| role < UserRole::Admin)
+    {
+        return Err(ServiceError::Forbidden);
+    }
+
+    let org_id = data
+        .target_organization_id
+        .unwrap_or(org_with_sub_and_plan.organization.id);
+    if get_role_for_org(&user.0, &org_id).map_or(true, |role| role < UserRole::Owner) {
+        return Err(ServiceError::Forbidden);
+    }
+
     let clone_chunks = data.clone_chunks.unwrap_or(true);
-    let mut create_dataset_payload = CreateDatasetReqPayload::from(data);
+    let filter = data.filter.clone();
+    let group_ids = data.group_ids.clone();
+    if group_ids
+        .as_ref()
+        .is_some_and(|group_ids| group_ids.is_empty())
+    {
+        return Err(ServiceError::BadRequest(
+            "group_ids must contain at least one group".to_string(),
+        ));
+    }
 
-    create_dataset_payload.server_configuration =
-        Some(DatasetConfiguration::from_json(og_dataset.server_configuration.clone()).into());
+    let mut og_dataset_config =
+        DatasetConfiguration::from_json(og_dataset.server_configuration.clone());
+    if clone_chunks && og_dataset_config.QDRANT_ONLY {
+        return Err(ServiceError::BadRequest(
+            "Chunks of datasets which only store chunks in qdrant cannot be cloned".to_string(),
+        ));
+    }
+    if clone_chunks && filter.is_some() {
+        // Surfaces an invalid filter now rather than as a failed clone
+        assemble_qdrant_filter(filter.clone(), None, None, og_dataset.id, pool.clone()).await?;
+    }
+
+    // API keys stay with the organization which set them unless the request supplies new ones
+    if og_dataset.organization_id != org_id {
+        og_dataset_config.LLM_API_KEY = String::new();
+        og_dataset_config.RERANKER_API_KEY = String::new();
+    }
+
+    let mut create_dataset_payload = CreateDatasetReqPayload::from(data);
+    create_dataset_payload.server_configuration = Some(
+        create_dataset_payload
+            .server_configuration
+            .map(|config| config.from_curr_dataset(og_dataset_config.clone()))
+            .unwrap_or(og_dataset_config)
+            .into(),
+    );
 
-    let org_id = org_with_sub_and_plan.organization.id;
     let organization_sub_plan = get_org_from_id_query(org_id, pool.clone()).await?;
 
     let unlimited = std::env::var("UNLIMITED").unwrap_or("false".to_string());
@@ -449,6 +637,7 @@ pub async fn clone_dataset(
         if dataset_count
             >= organization_sub_plan
                 .plan
+                .clone()
                 .unwrap_or_default()
                 .dataset_count()
         {
@@ -461,15 +650,22 @@ pub async fn clone_dataset(
     let new_dataset = create_dataset_helper(
         create_dataset_payload,
         pool.clone(),
-        org_with_sub_and_plan.clone(),
+        organization_sub_plan,
         user,
     )
     .await?;
 
     if clone_chunks {
+        let dataset_clone = create_dataset_clone_query(
+            DatasetClone::from_details(new_dataset.id, dataset_uuid_to_clone, filter, group_ids),
+            pool.clone(),
+        )
+        .await?;
+
         let message = CloneDatasetMessage {
             dataset_to_clone: dataset_uuid_to_clone,
             new_dataset: new_dataset.id,
+            clone_id: Some(dataset_clone.id),
         };
 
         broccoli_queue
@@ -481,6 +677,37 @@ pub async fn clone_dataset(
     Ok(HttpResponse::Ok().json(new_dataset))
 }
 
+/// Get Dataset Clone Status
+///
+/// Returns the status and progress of the clone which created the dataset. The auth'ed user must be an admin of the organization to get the clone status.
+#[utoipa::path(
+    get,
+    path = "/dataset/clone_status",
+    context_path = "/api",
+    tag = "Dataset",
+    responses(
+        (status = 200, description = "The clone which created the dataset", body = DatasetClone),
+        (status = 400, description = "Service error relating to getting the clone", body = ErrorResponseBody),
+        (status = 404, description = "Dataset was not created by a clone", body = ErrorResponseBody),
+    ),
+    params(
+        ("TR-Dataset" = uuid::Uuid, Header, description = "The id or tracking_id of the dataset created by the clone. We assume you intend to use an id if the value is a valid uuid."),
+    ),
+    security(
+        ("ApiKey" = ["admin"]),
+    )
+)]
+pub async fn get_dataset_clone_status(
+    dataset_org_plan_sub: DatasetAndOrgWithSubAndPlan,
+    _user: AdminOnly,
+    pool: web::Data<Pool>,
+) -> Result<HttpResponse, ServiceError> {
+    let dataset_clone =
+        get_latest_dataset_clone_query(dataset_org_plan_sub.dataset.id, pool).await?;
+
+    Ok(HttpResponse::Ok().json(dataset_clone))
+}
+
 /// Clear Dataset
 ///
 /// Removes all chunks, files, and groups from the dataset while retaining the analytics and dataset itself. The auth'ed user must be an owner of the organization to clear a dataset.
@@ -626,204 +853,1812 @@ pub async fn get_pagefind_index_for_dataset(
     }))
 }
 
-/// Delete Dataset by Tracking ID
+#[derive(Serialize, Deserialize, Debug, ToSchema, Clone)]
+#[schema(example = json!({
+    "format": "jsonl",
+    "filter": {
+        "must": [
+            {
+                "field": "tag_set",
+                "match_all": ["A", "B"],
+            },
+        ],
+    },
+}))]
+pub struct CreateDatasetExportReqPayload {
+    /// The file format of the export. Defaults to jsonl.
+    pub format: Option<DatasetExportFormat>,
+    /// Optional filter to only export the chunks matching it. When set, only the groups containing those chunks and the files behind those groups are exported.
+    pub filter: Option<ChunkFilter>,
+}
+
+/// Export Dataset
 ///
-/// Auth'ed user must be an owner of the organization to delete a dataset.
+/// Starts an asynchronous export of the dataset to JSONL or Parquet. The export contains the dataset and its configuration, its groups, its files and every chunk matching the optional filter along with its boosts, group memberships and dense and sparse vectors. Poll the export with the returned id to get a download url once it has completed. The auth'ed user must be an admin of the organization to export a dataset.
 #[utoipa::path(
-    delete,
-    path = "/dataset/tracking_id/{tracking_id}",
+    post,
+    path = "/dataset/export",
     context_path = "/api",
     tag = "Dataset",
+    request_body(content = CreateDatasetExportReqPayload, description = "JSON request payload to export a dataset", content_type = "application/json"),
     responses(
-        (status = 204, description = "Dataset deleted successfully"),
-        (status = 400, description = "Service error relating to deleting the dataset", body = ErrorResponseBody),
-        (status = 404, description = "Dataset not found", body = ErrorResponseBody)
+        (status = 200, description = "Dataset export started", body = DatasetExport),
+        (status = 400, description = "Service error relating to starting the export", body = ErrorResponseBody),
     ),
     params(
         ("TR-Dataset" = uuid::Uuid, Header, description = "The dataset id or tracking_id to use for the request. We assume you intend to use an id if the value is a valid uuid."),
-        ("tracking_id" = String, Path, description = "The tracking id of the dataset you want to delete."),
     ),
     security(
-        ("ApiKey" = ["owner"]),
+        ("ApiKey" = ["admin"]),
     )
 )]
-pub async fn delete_dataset_by_tracking_id(
-    tracking_id: web::Path<String>,
+pub async fn create_dataset_export(
+    data: web::Json<CreateDatasetExportReqPayload>,
+    dataset_org_plan_sub: DatasetAndOrgWithSubAndPlan,
+    _user: AdminOnly,
     pool: web::Data<Pool>,
-    redis_pool: web::Data<RedisPool>,
-    _user: OwnerOnly,
-    org_with_plan_and_sub: OrganizationWithSubAndPlan,
+    broccoli_queue: web::Data<BroccoliQueue>,
 ) -> Result<HttpResponse, ServiceError> {
-    let dataset = get_dataset_by_tracking_id_query(
-        tracking_id.into_inner(),
-        org_with_plan_and_sub.organization.id,
+    let data = data.into_inner();
+    let dataset_id = dataset_org_plan_sub.dataset.id;
+
+    let dataset_config =
+        DatasetConfiguration::from_json(dataset_org_plan_sub.dataset.server_configuration);
+    if dataset_config.QDRANT_ONLY {
+        return Err(ServiceError::BadRequest(
+            "Datasets which only store chunks in qdrant cannot be exported".to_string(),
+        ));
+    }
+
+    let dataset_export = create_dataset_export_query(
+        DatasetExport::from_details(dataset_id, data.format.unwrap_or_default(), data.filter),
         pool.clone(),
     )
     .await?;
 
-    let config = DatasetConfiguration::from_json(dataset.server_configuration);
+    let message = DatasetExportMessage {
+        export_id: dataset_export.id,
+        dataset_id,
+    };
 
-    soft_delete_dataset_by_id_query(dataset.id, config, pool, redis_pool).await?;
+    broccoli_queue
+        .publish("dataset_export", None, &message, None)
+        .await
+        .map_err(|err| ServiceError::BadRequest(err.to_string()))?;
 
-    Ok(HttpResponse::NoContent().finish())
+    Ok(HttpResponse::Ok().json(dataset_export))
 }
 
-/// Get Dataset By ID
+#[derive(Serialize, Deserialize, Debug, ToSchema, Clone)]
+pub struct DatasetExportResponse {
+    pub export: DatasetExport,
+    /// Signed url to download the export from. Only set once the export has completed and valid for 24 hours.
+    pub download_url: Option<String>,
+}
+
+/// Get Dataset Export
 ///
-/// Auth'ed user or api key must have an admin or owner role for the specified dataset's organization.
+/// Returns the status of a dataset export along with a signed download url once it has completed. The auth'ed user must be an admin of the organization to get a dataset export.
 #[utoipa::path(
     get,
-    path = "/dataset/{dataset_id}",
+    path = "/dataset/export/{export_id}",
     context_path = "/api",
     tag = "Dataset",
     responses(
-        (status = 200, description = "Dataset retrieved successfully", body = Dataset),
-        (status = 400, description = "Service error relating to retrieving the dataset", body = ErrorResponseBody),
-        (status = 404, description = "Dataset not found", body = ErrorResponseBody)
+        (status = 200, description = "The dataset export", body = DatasetExportResponse),
+        (status = 400, description = "Service error relating to getting the export", body = ErrorResponseBody),
+        (status = 404, description = "Dataset export not found", body = ErrorResponseBody),
     ),
     params(
         ("TR-Dataset" = uuid::Uuid, Header, description = "The dataset id or tracking_id to use for the request. We assume you intend to use an id if the value is a valid uuid."),
-        ("dataset_id" = uuid, Path, description = "The id of the dataset you want to retrieve."),
+        ("export_id" = uuid::Uuid, Path, description = "The id of the export you want to retrieve."),
     ),
     security(
         ("ApiKey" = ["admin"]),
     )
 )]
-pub async fn get_dataset(
-    pool: web::Data<Pool>,
-    dataset_id: web::Path<uuid::Uuid>,
+pub async fn get_dataset_export(
+    export_id: web::Path<uuid::Uuid>,
+    dataset_org_plan_sub: DatasetAndOrgWithSubAndPlan,
     _user: AdminOnly,
+    pool: web::Data<Pool>,
 ) -> Result<HttpResponse, ServiceError> {
-    let mut dataset = get_dataset_by_id_query(dataset_id.into_inner(), pool).await?;
+    let export = get_dataset_export_query(
+        export_id.into_inner(),
+        dataset_org_plan_sub.dataset.id,
+        pool,
+    )
+    .await?;
 
-    dataset.server_configuration = json!(DatasetConfiguration::from_json(
-        dataset.server_configuration
-    ));
+    let download_url = match export.s3_key.as_ref() {
+        Some(s3_key) if export.status == DatasetExportStatus::Completed.to_string() => Some(
+            get_aws_bucket()?
+                .presign_get(s3_key, 86400, None)
+                .await
+                .map_err(|err| {
+                    log::error!("Could not get presigned url for export {:?}", err);
+                    ServiceError::InternalServerError(
+                        "Could not get presigned url for export".to_string(),
+                    )
+                })?,
+        ),
+        _ => None,
+    };
 
-    Ok(HttpResponse::Ok().json(dataset))
+    Ok(HttpResponse::Ok().json(DatasetExportResponse {
+        export,
+        download_url,
+    }))
 }
 
-/// Get Usage By Dataset ID
+#[derive(Serialize, Deserialize, Debug, ToSchema, Clone)]
+#[schema(example = json!({
+    "export_id": "e3e3e3e3-e3e3-e3e3-e3e3-e3e3e3e3e3e3",
+}))]
+pub struct CreateDatasetImportReqPayload {
+    /// Id of a completed export of any dataset in the same organization. Either this or `url` must be provided.
+    pub export_id: Option<uuid::Uuid>,
+    /// Https url to download an export archive from, e.g. the `download_url` of an export on another Trieve deployment. The url must resolve to a public address and redirects are not followed. Either this or `export_id` must be provided.
+    pub url: Option<String>,
+    /// The file format of the archive at `url`. Inferred from the url's extension if not provided. Ignored when importing by `export_id`.
+    pub format: Option<DatasetExportFormat>,
+}
+
+/// Import Dataset
 ///
-/// Auth'ed user or api key must have an admin or owner role for the specified dataset's organization.
+/// Starts an asynchronous import of an export archive into the dataset. Groups, files and chunks are recreated preserving their tracking ids and existing chunks with the same tracking id are updated. When the exporting dataset used the same embedding model, size and distance metric the exported vectors are upserted directly, otherwise the chunks are re-embedded. Files are only recreated when the exported file still exists in a dataset of the same organization, as archives do not contain file contents. The auth'ed user must be an admin of the organization to import into a dataset.
 #[utoipa::path(
-    get,
-    path = "/dataset/usage/{dataset_id}",
+    post,
+    path = "/dataset/import",
     context_path = "/api",
     tag = "Dataset",
+    request_body(content = CreateDatasetImportReqPayload, description = "JSON request payload to import into a dataset", content_type = "application/json"),
     responses(
-        (status = 200, description = "Dataset usage retrieved successfully", body = DatasetUsageCount),
-        (status = 400, description = "Service error relating to retrieving the dataset usage", body = ErrorResponseBody),
-        (status = 404, description = "Dataset not found", body = ErrorResponseBody)
+        (status = 200, description = "Dataset import started", body = DatasetImport),
+        (status = 400, description = "Service error relating to starting the import", body = ErrorResponseBody),
+        (status = 404, description = "Dataset export not found", body = ErrorResponseBody),
     ),
     params(
         ("TR-Dataset" = uuid::Uuid, Header, description = "The dataset id or tracking_id to use for the request. We assume you intend to use an id if the value is a valid uuid."),
-        ("dataset_id" = uuid, Path, description = "The id of the dataset you want to retrieve usage for."),
     ),
     security(
         ("ApiKey" = ["admin"]),
     )
 )]
-pub async fn get_usage_by_dataset_id(
-    pool: web::Data<Pool>,
-    dataset_id: web::Path<uuid::Uuid>,
+pub async fn create_dataset_import(
+    data: web::Json<CreateDatasetImportReqPayload>,
+    dataset_org_plan_sub: DatasetAndOrgWithSubAndPlan,
     _user: AdminOnly,
+    pool: web::Data<Pool>,
+    broccoli_queue: web::Data<BroccoliQueue>,
 ) -> Result<HttpResponse, ServiceError> {
-    let usage = get_dataset_usage_query(dataset_id.into_inner(), pool)
+    let data = data.into_inner();
+    let dataset_id = dataset_org_plan_sub.dataset.id;
+
+    let dataset_import = match (data.export_id, data.url) {
+        (Some(export_id), None) => {
+            let dataset_export = get_dataset_export_for_organization_query(
+                export_id,
+                dataset_org_plan_sub.organization.organization.id,
+                pool.clone(),
+            )
+            .await?;
+            if dataset_export.status != DatasetExportStatus::Completed.to_string() {
+                return Err(ServiceError::BadRequest(
+                    "Only completed exports can be imported".to_string(),
+                ));
+            }
+
+            DatasetImport::from_details(
+                dataset_id,
+                DatasetExportFormat::from(dataset_export.format),
+                Some(export_id),
+                None,
+            )
+        }
+        (None, Some(url)) => {
+            let (parsed_url, _) = resolve_import_url(&url).await?;
+            let format = data
+                .format
+                .unwrap_or(if parsed_url.path().ends_with(".parquet") {
+                    DatasetExportFormat::Parquet
+                } else {
+                    DatasetExportFormat::Jsonl
+                });
+
+            DatasetImport::from_details(dataset_id, format, None, Some(url))
+        }
+        _ => {
+            return Err(ServiceError::BadRequest(
+                "Exactly one of export_id or url must be provided".to_string(),
+            ))
+        }
+    };
+
+    let dataset_import = create_dataset_import_query(dataset_import, pool.clone()).await?;
+
+    let message = DatasetImportMessage {
+        import_id: dataset_import.id,
+        dataset_id,
+    };
+
+    broccoli_queue
+        .publish("dataset_import", None, &message, None)
         .await
-        .map_err(|e| ServiceError::InternalServerError(e.to_string()))?;
+        .map_err(|err| ServiceError::BadRequest(err.to_string()))?;
 
-    Ok(HttpResponse::Ok().json(usage))
+    Ok(HttpResponse::Ok().json(dataset_import))
 }
 
-/// Get Dataset by Tracking ID
+/// Get Dataset Import
 ///
-/// Auth'ed user or api key must have an admin or owner role for the specified dataset's organization.
+/// Returns the status and progress of a dataset import. The auth'ed user must be an admin of the organization to get a dataset import.
 #[utoipa::path(
     get,
-    path = "/dataset/tracking_id/{tracking_id}",
+    path = "/dataset/import/{import_id}",
     context_path = "/api",
     tag = "Dataset",
     responses(
-        (status = 200, description = "Dataset retrieved successfully", body = Dataset),
-        (status = 400, description = "Service error relating to retrieving the dataset", body = ErrorResponseBody),
-        (status = 404, description = "Dataset not found", body = ErrorResponseBody)
+        (status = 200, description = "The dataset import", body = DatasetImport),
+        (status = 400, description = "Service error relating to getting the import", body = ErrorResponseBody),
+        (status = 404, description = "Dataset import not found", body = ErrorResponseBody),
     ),
     params(
-        ("TR-Organization" = uuid::Uuid, Header, description = "The organization id to use for the request"),
-        ("tracking_id" = String, Path, description = "The tracking id of the dataset you want to retrieve."),
+        ("TR-Dataset" = uuid::Uuid, Header, description = "The dataset id or tracking_id to use for the request. We assume you intend to use an id if the value is a valid uuid."),
+        ("import_id" = uuid::Uuid, Path, description = "The id of the import you want to retrieve."),
     ),
     security(
         ("ApiKey" = ["admin"]),
     )
 )]
-pub async fn get_dataset_by_tracking_id(
-    tracking_id: web::Path<String>,
-    pool: web::Data<Pool>,
+pub async fn get_dataset_import(
+    import_id: web::Path<uuid::Uuid>,
+    dataset_org_plan_sub: DatasetAndOrgWithSubAndPlan,
     _user: AdminOnly,
-    org_with_plan_and_sub: OrganizationWithSubAndPlan,
+    pool: web::Data<Pool>,
 ) -> Result<HttpResponse, ServiceError> {
-    let mut dataset = get_dataset_by_tracking_id_query(
-        tracking_id.into_inner(),
-        org_with_plan_and_sub.organization.id,
+    let dataset_import = get_dataset_import_query(
+        import_id.into_inner(),
+        dataset_org_plan_sub.dataset.id,
         pool,
     )
-    .await
-    .map_err(|e| ServiceError::InternalServerError(e.to_string()))?;
-
-    dataset.server_configuration = json!(DatasetConfiguration::from_json(
-        dataset.server_configuration
-    ));
+    .await?;
 
-    Ok(HttpResponse::Ok().json(dataset))
+    Ok(HttpResponse::Ok().json(dataset_import))
 }
 
-#[derive(Debug, Deserialize, Serialize, ToSchema, Default)]
-pub struct GetDatasetsPagination {
-    pub limit: Option<i64>,
-    pub offset: Option<i64>,
+#[derive(Serialize, Deserialize, Debug, ToSchema, Clone)]
+#[schema(example = json!({
+    "embedding_model_name": "text-embedding-3-large",
+    "embedding_base_url": "https://api.openai.com/v1",
+    "embedding_size": 3072,
+}))]
+pub struct CreateDatasetReindexReqPayload {
+    /// Embedding model to re-embed the dataset with. Defaults to the current model if another embedding field is set.
+    pub embedding_model_name: Option<String>,
+    /// Base url of the embedding server. Defaults to the current one if another embedding field is set.
+    pub embedding_base_url: Option<String>,
+    /// Size of the vectors the new model produces. Must be one of 384, 512, 768, 1024, 1536 or 3072. Defaults to the current size if another embedding field is set.
+    pub embedding_size: Option<usize>,
+    /// New BM25 average document length. Cannot be combined with the embedding fields.
+    pub bm25_avg_len: Option<f32>,
+    /// New BM25 b parameter. Cannot be combined with the embedding fields.
+    pub bm25_b: Option<f32>,
+    /// New BM25 k parameter. Cannot be combined with the embedding fields.
+    pub bm25_k: Option<f32>,
 }
 
-/// Get Datasets from Organization
+const REINDEX_EMBEDDING_SIZES: [usize; 6] = [384, 512, 768, 1024, 1536, 3072];
+
+/// Reindex Dataset
 ///
-/// Auth'ed user or api key must have an admin or owner role for the specified dataset's organization.
+/// Switches the embedding model and size or the BM25 parameters of a dataset. The dataset's points are migrated in the background and the dataset configuration is updated automatically once every point has been migrated. Re-embedded points are written to the collection for the new vector size, so search keeps using the old vectors until the switch. Chunks created, updated or deleted while the reindex runs are replayed into the new vectors right after the switch. Datasets which only store chunks in qdrant cannot be re-embedded. BM25 vectors are recomputed in place. Only one reindex can run per dataset at a time. Auth'ed user must be an owner of the organization to reindex a dataset.
 #[utoipa::path(
-    get,
-    path = "/dataset/organization/{organization_id}",
+    post,
+    path = "/dataset/reindex",
     context_path = "/api",
     tag = "Dataset",
+    request_body(content = CreateDatasetReindexReqPayload, description = "JSON request payload to reindex a dataset", content_type = "application/json"),
     responses(
-        (status = 200, description = "Datasets retrieved successfully", body = Vec<DatasetAndUsage>),
-        (status = 400, description = "Service error relating to retrieving the dataset", body = ErrorResponseBody),
-        (status = 404, description = "Could not find organization", body = ErrorResponseBody)
+        (status = 200, description = "The queued reindex", body = DatasetReindex),
+        (status = 400, description = "Service error relating to reindexing the dataset", body = ErrorResponseBody),
     ),
     params(
-        ("TR-Organization" = uuid::Uuid, Header, description = "The organization id to use for the request"),
-        ("organization_id" = uuid, Path, description = "id of the organization you want to retrieve datasets for"),
-        ("limit" = Option<i64>, Query, description = "The number of records to return"),
-        ("offset" = Option<i64>, Query, description = "The number of records to skip"),
+        ("TR-Dataset" = uuid::Uuid, Header, description = "The dataset id or tracking_id to use for the request. We assume you intend to use an id if the value is a valid uuid."),
     ),
     security(
-        ("ApiKey" = ["admin"]),
+        ("ApiKey" = ["owner"]),
     )
 )]
-pub async fn get_datasets_from_organization(
-    organization_id: web::Path<uuid::Uuid>,
-    pagination: web::Query<GetDatasetsPagination>,
-    user: AdminOnly,
+pub async fn create_dataset_reindex(
+    data: web::Json<CreateDatasetReindexReqPayload>,
+    dataset_org_plan_sub: DatasetAndOrgWithSubAndPlan,
+    _user: OwnerOnly,
     pool: web::Data<Pool>,
-) -> Result<HttpResponse, actix_web::Error> {
-    let pagination = pagination.into_inner();
-    let organization_id = organization_id.into_inner();
-    user.0
-        .user_orgs
-        .iter()
-        .find(|org| org.organization_id == organization_id)
-        .ok_or(ServiceError::Forbidden)?;
-
-    // If offset is set, limit must also be set and vice versa
-    if pagination.offset.is_some() != pagination.limit.is_some() {
-        return Err(ServiceError::BadRequest(
+    broccoli_queue: web::Data<BroccoliQueue>,
+) -> Result<HttpResponse, ServiceError> {
+    let data = data.into_inner();
+    let dataset_id = dataset_org_plan_sub.dataset.id;
+    let dataset_config =
+        DatasetConfiguration::from_json(dataset_org_plan_sub.dataset.server_configuration.clone());
+
+    let reembed = data.embedding_model_name.is_some()
+        || data.embedding_base_url.is_some()
+        || data.embedding_size.is_some();
+    let recompute_bm25 =
+        data.bm25_avg_len.is_some() || data.bm25_b.is_some() || data.bm25_k.is_some();
+
+    let mode = match (reembed, recompute_bm25) {
+        (true, true) => {
+            return Err(ServiceError::BadRequest(
+                "Embedding and BM25 parameters cannot be changed in the same reindex".to_string(),
+            ))
+        }
+        (false, false) => {
+            return Err(ServiceError::BadRequest(
+                "Either embedding or BM25 parameters must be specified".to_string(),
+            ))
+        }
+        (true, false) => MigrationMode::Reembed {
+            embedding_model_name: data
+                .embedding_model_name
+                .unwrap_or(dataset_config.EMBEDDING_MODEL_NAME.clone()),
+            embedding_base_url: data
+                .embedding_base_url
+                .unwrap_or(dataset_config.EMBEDDING_BASE_URL.clone()),
+            embedding_size: data.embedding_size.unwrap_or(dataset_config.EMBEDDING_SIZE),
+        },
+        (false, true) => MigrationMode::BM25 {
+            average_len: data.bm25_avg_len.unwrap_or(dataset_config.BM25_AVG_LEN),
+            k: data.bm25_k.unwrap_or(dataset_config.BM25_K),
+            b: data.bm25_b.unwrap_or(dataset_config.BM25_B),
+        },
+    };
+
+    let target_config = apply_migration_mode(dataset_config.clone(), &mode);
+    let from_collection = get_qdrant_collection_from_dataset_config(&dataset_config);
+    let to_collection = get_qdrant_collection_from_dataset_config(&target_config);
+
+    if let MigrationMode::Reembed { embedding_size, .. } = &mode {
+        if dataset_config.QDRANT_ONLY {
+            return Err(ServiceError::BadRequest(
+                "Datasets which only store chunks in qdrant cannot be re-embedded".to_string(),
+            ));
+        }
+
+        if !REINDEX_EMBEDDING_SIZES.contains(embedding_size) {
+            return Err(ServiceError::BadRequest(format!(
+                "Embedding size must be one of {:?}",
+                REINDEX_EMBEDDING_SIZES
+            )));
+        }
+
+        if target_config.EMBEDDING_MODEL_NAME == dataset_config.EMBEDDING_MODEL_NAME
+            && target_config.EMBEDDING_BASE_URL == dataset_config.EMBEDDING_BASE_URL
+            && target_config.EMBEDDING_SIZE == dataset_config.EMBEDDING_SIZE
+        {
+            return Err(ServiceError::BadRequest(
+                "The dataset already uses this embedding model".to_string(),
+            ));
+        }
+
+        // Vectors of the same size share a collection, so re-embedding in place would mix old
+        // and new vectors in search results until the migration finishes.
+        if from_collection == to_collection {
+            return Err(ServiceError::BadRequest(
+                "Re-embedding requires a different embedding size than the dataset currently uses"
+                    .to_string(),
+            ));
+        }
+
+        if !get_qdrant_collections().await?.contains(&to_collection) {
+            return Err(ServiceError::BadRequest(format!(
+                "No collection exists for {} dimensional vectors",
+                embedding_size
+            )));
+        }
+    }
+
+    if get_active_dataset_reindex_query(dataset_id, pool.clone())
+        .await?
+        .is_some()
+    {
+        return Err(ServiceError::BadRequest(
+            "A reindex is already running for this dataset".to_string(),
+        ));
+    }
+
+    let dataset_reindex = create_dataset_reindex_query(
+        DatasetReindex::from_details(dataset_id, &mode, from_collection, to_collection),
+        pool,
+    )
+    .await?;
+
+    let message = DatasetReindexMessage {
+        reindex_id: dataset_reindex.id,
+        dataset_id,
+    };
+
+    broccoli_queue
+        .publish("dataset_reindex", None, &message, None)
+        .await
+        .map_err(|err| ServiceError::BadRequest(err.to_string()))?;
+
+    Ok(HttpResponse::Ok().json(dataset_reindex))
+}
+
+#[derive(Serialize, Deserialize, Debug, ToSchema, Clone)]
+pub struct DatasetReindexResponse {
+    pub reindex: DatasetReindex,
+    /// Estimated seconds until the migration finishes, based on its throughput so far. Only present while the reindex is processing.
+    pub eta_seconds: Option<i64>,
+}
+
+/// Get Dataset Reindex
+///
+/// Returns the progress of a dataset reindex: the points migrated and failed out of the total, an estimate of the time left and any error. The auth'ed user must be an admin of the organization to get a dataset reindex.
+#[utoipa::path(
+    get,
+    path = "/dataset/reindex/{reindex_id}",
+    context_path = "/api",
+    tag = "Dataset",
+    responses(
+        (status = 200, description = "The dataset reindex", body = DatasetReindexResponse),
+        (status = 400, description = "Service error relating to getting the reindex", body = ErrorResponseBody),
+        (status = 404, description = "Dataset reindex not found", body = ErrorResponseBody),
+    ),
+    params(
+        ("TR-Dataset" = uuid::Uuid, Header, description = "The dataset id or tracking_id to use for the request. We assume you intend to use an id if the value is a valid uuid."),
+        ("reindex_id" = uuid::Uuid, Path, description = "The id of the reindex you want to retrieve."),
+    ),
+    security(
+        ("ApiKey" = ["admin"]),
+    )
+)]
+pub async fn get_dataset_reindex(
+    reindex_id: web::Path<uuid::Uuid>,
+    dataset_org_plan_sub: DatasetAndOrgWithSubAndPlan,
+    _user: AdminOnly,
+    pool: web::Data<Pool>,
+) -> Result<HttpResponse, ServiceError> {
+    let dataset_reindex = get_dataset_reindex_query(
+        reindex_id.into_inner(),
+        dataset_org_plan_sub.dataset.id,
+        pool,
+    )
+    .await?;
+
+    Ok(HttpResponse::Ok().json(DatasetReindexResponse {
+        eta_seconds: get_dataset_reindex_eta(&dataset_reindex),
+        reindex: dataset_reindex,
+    }))
+}
+
+/// Cancel Dataset Reindex
+///
+/// Stops a reindex which has not finished yet. Points which were not migrated yet are skipped, vectors already written by a re-embedding are removed and the dataset configuration is left unchanged. BM25 vectors which were already recomputed are not restored. Auth'ed user must be an owner of the organization to cancel a dataset reindex.
+#[utoipa::path(
+    delete,
+    path = "/dataset/reindex/{reindex_id}",
+    context_path = "/api",
+    tag = "Dataset",
+    responses(
+        (status = 200, description = "The cancelled reindex", body = DatasetReindex),
+        (status = 400, description = "The reindex does not exist or has already finished", body = ErrorResponseBody),
+    ),
+    params(
+        ("TR-Dataset" = uuid::Uuid, Header, description = "The dataset id or tracking_id to use for the request. We assume you intend to use an id if the value is a valid uuid."),
+        ("reindex_id" = uuid::Uuid, Path, description = "The id of the reindex you want to cancel."),
+    ),
+    security(
+        ("ApiKey" = ["owner"]),
+    )
+)]
+pub async fn cancel_dataset_reindex(
+    reindex_id: web::Path<uuid::Uuid>,
+    dataset_org_plan_sub: DatasetAndOrgWithSubAndPlan,
+    _user: OwnerOnly,
+    pool: web::Data<Pool>,
+    broccoli_queue: web::Data<BroccoliQueue>,
+) -> Result<HttpResponse, ServiceError> {
+    let dataset_reindex = cancel_dataset_reindex_query(
+        reindex_id.into_inner(),
+        dataset_org_plan_sub.dataset.id,
+        pool,
+    )
+    .await?;
+
+    // The reindex worker removes the points the cancelled reindex already wrote
+    let message = DatasetReindexMessage {
+        reindex_id: dataset_reindex.id,
+        dataset_id: dataset_reindex.dataset_id,
+    };
+
+    broccoli_queue
+        .publish("dataset_reindex", None, &message, None)
+        .await
+        .map_err(|err| ServiceError::BadRequest(err.to_string()))?;
+
+    Ok(HttpResponse::Ok().json(dataset_reindex))
+}
+
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+pub struct StreamChunkChangesQuery {
+    /// Only stream changes with a sequence number greater than this. Use 0 to replay every retained change. Defaults to the latest change, so only new changes are streamed.
+    pub after_seq: Option<i64>,
+}
+
+const CHUNK_CHANGE_STREAM_PAGE_SIZE: i64 = 100;
+const CHUNK_CHANGE_STREAM_POLL_INTERVAL: std::time::Duration = std::time::Duration::from_secs(1);
+const CHUNK_CHANGE_STREAM_HEARTBEAT_POLLS: u32 = 15;
+
+/// Stream Chunk Changes
+///
+/// Streams every create, upsert, update and delete of the dataset's chunks as server-sent events, in the order they happened. Each event's id is the change's sequence number. Reconnecting clients resume after the last event they received by sending it in the `Last-Event-ID` header, which takes precedence over `after_seq`. Changes are delivered at least once, so consumers should handle repeats. The auth'ed user must be an admin of the organization to stream chunk changes.
+#[utoipa::path(
+    get,
+    path = "/dataset/changes",
+    context_path = "/api",
+    tag = "Dataset",
+    responses(
+        (status = 200, description = "Server-sent events, one `chunk_change` event per change", content_type = "text/event-stream", body = ChunkChange),
+        (status = 400, description = "Service error relating to streaming the changes", body = ErrorResponseBody),
+    ),
+    params(
+        ("TR-Dataset" = uuid::Uuid, Header, description = "The dataset id or tracking_id to use for the request. We assume you intend to use an id if the value is a valid uuid."),
+        ("Last-Event-ID" = Option<i64>, Header, description = "Sequence number of the last change the client received"),
+        ("after_seq" = Option<i64>, Query, description = "Only stream changes with a sequence number greater than this. Defaults to the latest change."),
+    ),
+    security(
+        ("ApiKey" = ["admin"]),
+    )
+)]
+pub async fn stream_chunk_changes(
+    query: web::Query<StreamChunkChangesQuery>,
+    req: HttpRequest,
+    dataset_org_plan_sub: DatasetAndOrgWithSubAndPlan,
+    _user: AdminOnly,
+    pool: web::Data<Pool>,
+) -> Result<HttpResponse, ServiceError> {
+    let dataset_id = dataset_org_plan_sub.dataset.id;
+
+    let last_event_id = req
+        .headers()
+        .get("Last-Event-ID")
+        .and_then(|header| header.to_str().ok())
+        .and_then(|header| header.trim().parse::<i64>().ok());
+
+    let after_seq = match last_event_id.or(query.after_seq) {
+        Some(after_seq) => after_seq,
+        None => get_latest_chunk_change_seq_query(dataset_id, pool.clone()).await?,
+    };
+
+    let change_stream = stream::unfold(after_seq, move |after_seq| {
+        let pool = pool.clone();
+        async move {
+            let mut idle_polls = 0;
+            loop {
+                let changes = match get_chunk_changes_query(
+                    dataset_id,
+                    after_seq,
+                    CHUNK_CHANGE_STREAM_PAGE_SIZE,
+                    pool.clone(),
+                )
+                .await
+                {
+                    Ok(changes) => changes,
+                    Err(err) => return Some((Err(actix_web::Error::from(err)), after_seq)),
+                };
+
+                if let Some(last_change) = changes.last() {
+                    let last_seq = last_change.seq;
+                    let events = changes
+                        .iter()
+                        .map(|change| {
+                            format!(
+                                "id: {}\nevent: chunk_change\ndata: {}\n\n",
+                                change.seq,
+                                serde_json::to_string(change).unwrap_or_default()
+                            )
+                        })
+                        .collect::<String>();
+
+                    return Some((Ok(Bytes::from(events)), last_seq));
+                }
+
+                idle_polls += 1;
+                if idle_polls >= CHUNK_CHANGE_STREAM_HEARTBEAT_POLLS {
+                    return Some((Ok(Bytes::from(": keep-alive\n\n")), after_seq));
+                }
+
+                tokio::time::sleep(CHUNK_CHANGE_STREAM_POLL_INTERVAL).await;
+            }
+        }
+    });
+
+    Ok(HttpResponse::Ok()
+        .content_type("text/event-stream")
+        .insert_header(("Cache-Control", "no-cache"))
+        .insert_header(("X-Accel-Buffering", "no"))
+        .streaming(change_stream))
+}
+
+#[derive(Serialize, Deserialize, Debug, ToSchema, Clone)]
+#[schema(example = json!({
+    "url": "https://example.com/trieve/changes",
+    "operations": ["create", "update", "delete"],
+}))]
+pub struct CreateChangeWebhookReqPayload {
+    /// Url which batches of changes are POSTed to. It must use https and resolve to a public address, redirects are not followed.
+    pub url: String,
+    /// Operations to deliver. All operations are delivered if not specified.
+    pub operations: Option<Vec<ChunkChangeOperation>>,
+    /// Only deliver changes with a sequence number greater than this. Use 0 to deliver every retained change. Defaults to the latest change, so only new changes are delivered.
+    pub after_seq: Option<i64>,
+}
+
+#[derive(Serialize, Deserialize, Debug, ToSchema, Clone)]
+pub struct CreateChangeWebhookResponse {
+    pub webhook: ChangeWebhook,
+    /// Key used to sign deliveries. Each delivery has an `X-Trieve-Signature` header of `sha256=` followed by the hex encoded HMAC-SHA256 of `{X-Trieve-Timestamp}.{body}`. The secret is only returned once.
+    pub secret: String,
+}
+
+/// Create Change Webhook
+///
+/// Registers a url which the dataset's chunk changes are delivered to. Changes are POSTed in order in batches of up to 100, signed with the secret returned in the response. A batch is redelivered with exponential backoff until the url responds with a 2xx status, and the webhook is deactivated after 20 failed deliveries in a row. The auth'ed user must be an admin of the organization to create a change webhook.
+#[utoipa::path(
+    post,
+    path = "/dataset/change_webhooks",
+    context_path = "/api",
+    tag = "Dataset",
+    request_body(content = CreateChangeWebhookReqPayload, description = "JSON request payload to create a change webhook", content_type = "application/json"),
+    responses(
+        (status = 200, description = "The created webhook and its signing secret", body = CreateChangeWebhookResponse),
+        (status = 400, description = "Service error relating to creating the webhook", body = ErrorResponseBody),
+    ),
+    params(
+        ("TR-Dataset" = uuid::Uuid, Header, description = "The dataset id or tracking_id to use for the request. We assume you intend to use an id if the value is a valid uuid."),
+    ),
+    security(
+        ("ApiKey" = ["admin"]),
+    )
+)]
+pub async fn create_change_webhook(
+    data: web::Json<CreateChangeWebhookReqPayload>,
+    dataset_org_plan_sub: DatasetAndOrgWithSubAndPlan,
+    _user: AdminOnly,
+    pool: web::Data<Pool>,
+) -> Result<HttpResponse, ServiceError> {
+    let data = data.into_inner();
+    let dataset_id = dataset_org_plan_sub.dataset.id;
+
+    resolve_webhook_url(&data.url).await?;
+
+    let after_seq = match data.after_seq {
+        Some(after_seq) => after_seq,
+        None => get_latest_chunk_change_seq_query(dataset_id, pool.clone()).await?,
+    };
+
+    let secret = generate_webhook_secret();
+    let webhook = create_change_webhook_query(
+        ChangeWebhook::from_details(
+            dataset_id,
+            data.url,
+            secret.clone(),
+            data.operations,
+            after_seq,
+        ),
+        pool,
+    )
+    .await?;
+
+    Ok(HttpResponse::Ok().json(CreateChangeWebhookResponse { webhook, secret }))
+}
+
+/// Get Change Webhooks
+///
+/// Lists the change webhooks registered for the dataset along with their delivery state. The auth'ed user must be an admin of the organization to list change webhooks.
+#[utoipa::path(
+    get,
+    path = "/dataset/change_webhooks",
+    context_path = "/api",
+    tag = "Dataset",
+    responses(
+        (status = 200, description = "The dataset's change webhooks", body = Vec<ChangeWebhook>),
+        (status = 400, description = "Service error relating to getting the webhooks", body = ErrorResponseBody),
+    ),
+    params(
+        ("TR-Dataset" = uuid::Uuid, Header, description = "The dataset id or tracking_id to use for the request. We assume you intend to use an id if the value is a valid uuid."),
+    ),
+    security(
+        ("ApiKey" = ["admin"]),
+    )
+)]
+pub async fn get_change_webhooks(
+    dataset_org_plan_sub: DatasetAndOrgWithSubAndPlan,
+    _user: AdminOnly,
+    pool: web::Data<Pool>,
+) -> Result<HttpResponse, ServiceError> {
+    let webhooks = get_change_webhooks_query(dataset_org_plan_sub.dataset.id, pool).await?;
+
+    Ok(HttpResponse::Ok().json(webhooks))
+}
+
+#[derive(Serialize, Deserialize, Debug, ToSchema, Clone)]
+#[schema(example = json!({
+    "active": true,
+}))]
+pub struct UpdateChangeWebhookReqPayload {
+    /// New url to deliver changes to. It must use https and resolve to a public address, redirects are not followed.
+    pub url: Option<String>,
+    /// Operations to deliver
+    pub operations: Option<Vec<ChunkChangeOperation>>,
+    /// Set to true to reactivate a webhook which was deactivated after failed deliveries, or false to pause deliveries. Undelivered changes are kept while the webhook is inactive.
+    pub active: Option<bool>,
+}
+
+/// Update Change Webhook
+///
+/// Changes the url, operations or active state of a change webhook. Reactivating a webhook resets its failure count and retries delivery immediately. The auth'ed user must be an admin of the organization to update a change webhook.
+#[utoipa::path(
+    put,
+    path = "/dataset/change_webhooks/{webhook_id}",
+    context_path = "/api",
+    tag = "Dataset",
+    request_body(content = UpdateChangeWebhookReqPayload, description = "JSON request payload to update a change webhook", content_type = "application/json"),
+    responses(
+        (status = 200, description = "The updated webhook", body = ChangeWebhook),
+        (status = 400, description = "Service error relating to updating the webhook", body = ErrorResponseBody),
+        (status = 404, description = "Change webhook not found", body = ErrorResponseBody),
+    ),
+    params(
+        ("TR-Dataset" = uuid::Uuid, Header, description = "The dataset id or tracking_id to use for the request. We assume you intend to use an id if the value is a valid uuid."),
+        ("webhook_id" = uuid::Uuid, Path, description = "The id of the webhook you want to update."),
+    ),
+    security(
+        ("ApiKey" = ["admin"]),
+    )
+)]
+pub async fn update_change_webhook(
+    webhook_id: web::Path<uuid::Uuid>,
+    data: web::Json<UpdateChangeWebhookReqPayload>,
+    dataset_org_plan_sub: DatasetAndOrgWithSubAndPlan,
+    _user: AdminOnly,
+    pool: web::Data<Pool>,
+) -> Result<HttpResponse, ServiceError> {
+    let data = data.into_inner();
+
+    if let Some(webhook_url) = &data.url {
+        resolve_webhook_url(webhook_url).await?;
+    }
+
+    let webhook = update_change_webhook_query(
+        webhook_id.into_inner(),
+        dataset_org_plan_sub.dataset.id,
+        data.url,
+        data.operations.map(|operations| {
+            operations
+                .iter()
+                .map(|operation| operation.to_string())
+                .collect()
+        }),
+        data.active,
+        pool,
+    )
+    .await?;
+
+    Ok(HttpResponse::Ok().json(webhook))
+}
+
+/// Delete Change Webhook
+///
+/// Stops delivering changes to the webhook and deletes its delivery log. The auth'ed user must be an admin of the organization to delete a change webhook.
+#[utoipa::path(
+    delete,
+    path = "/dataset/change_webhooks/{webhook_id}",
+    context_path = "/api",
+    tag = "Dataset",
+    responses(
+        (status = 204, description = "Change webhook deleted"),
+        (status = 400, description = "Service error relating to deleting the webhook", body = ErrorResponseBody),
+        (status = 404, description = "Change webhook not found", body = ErrorResponseBody),
+    ),
+    params(
+        ("TR-Dataset" = uuid::Uuid, Header, description = "The dataset id or tracking_id to use for the request. We assume you intend to use an id if the value is a valid uuid."),
+        ("webhook_id" = uuid::Uuid, Path, description = "The id of the webhook you want to delete."),
+    ),
+    security(
+        ("ApiKey" = ["admin"]),
+    )
+)]
+pub async fn delete_change_webhook(
+    webhook_id: web::Path<uuid::Uuid>,
+    dataset_org_plan_sub: DatasetAndOrgWithSubAndPlan,
+    _user: AdminOnly,
+    pool: web::Data<Pool>,
+) -> Result<HttpResponse, ServiceError> {
+    delete_change_webhook_query(
+        webhook_id.into_inner(),
+        dataset_org_plan_sub.dataset.id,
+        pool,
+    )
+    .await?;
+
+    Ok(HttpResponse::NoContent().finish())
+}
+
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+pub struct GetChangeWebhookDeliveriesQuery {
+    /// Page number to return, 1-indexed. Default is 1.
+    pub page: Option<i64>,
+    /// Number of deliveries to return per page. Default is 20, maximum is 100.
+    pub page_size: Option<i64>,
+}
+
+/// Get Change Webhook Deliveries
+///
+/// Returns the delivery log of a change webhook, most recent first. Each entry records the range of changes sent, the attempt number, the status code returned and any error. The auth'ed user must be an admin of the organization to get change webhook deliveries.
+#[utoipa::path(
+    get,
+    path = "/dataset/change_webhooks/{webhook_id}/deliveries",
+    context_path = "/api",
+    tag = "Dataset",
+    responses(
+        (status = 200, description = "The webhook's deliveries", body = Vec<ChangeWebhookDelivery>),
+        (status = 400, description = "Service error relating to getting the deliveries", body = ErrorResponseBody),
+        (status = 404, description = "Change webhook not found", body = ErrorResponseBody),
+    ),
+    params(
+        ("TR-Dataset" = uuid::Uuid, Header, description = "The dataset id or tracking_id to use for the request. We assume you intend to use an id if the value is a valid uuid."),
+        ("webhook_id" = uuid::Uuid, Path, description = "The id of the webhook you want the deliveries of."),
+        ("page" = Option<i64>, Query, description = "Page number to return, 1-indexed. Default is 1."),
+        ("page_size" = Option<i64>, Query, description = "Number of deliveries to return per page. Default is 20, maximum is 100."),
+    ),
+    security(
+        ("ApiKey" = ["admin"]),
+    )
+)]
+pub async fn get_change_webhook_deliveries(
+    webhook_id: web::Path<uuid::Uuid>,
+    query: web::Query<GetChangeWebhookDeliveriesQuery>,
+    dataset_org_plan_sub: DatasetAndOrgWithSubAndPlan,
+    _user: AdminOnly,
+    pool: web::Data<Pool>,
+) -> Result<HttpResponse, ServiceError> {
+    let webhook = get_change_webhook_query(
+        webhook_id.into_inner(),
+        dataset_org_plan_sub.dataset.id,
+        pool.clone(),
+    )
+    .await?;
+
+    let deliveries = get_change_webhook_deliveries_query(
+        webhook.id,
+        query.page.unwrap_or(1),
+        query.page_size.unwrap_or(20).clamp(1, 100),
+        pool,
+    )
+    .await?;
+
+    Ok(HttpResponse::Ok().json(deliveries))
+}
+
+#[derive(Serialize, Deserialize, Debug, ToSchema, Clone)]
+#[schema(example = json!({
+    "url": "https://example.com/trieve/events",
+    "event_types": ["file_uploaded", "file_upload_failed", "csv_jsonl_processing_completed"],
+}))]
+pub struct CreateEventWebhookReqPayload {
+    /// Url which events are POSTed to. It must use https and resolve to a public address, redirects are not followed.
+    pub url: String,
+    /// Event types to deliver. These are the same event types returned by the get events route.
+    pub event_types: Vec<EventTypeRequest>,
+}
+
+#[derive(Serialize, Deserialize, Debug, ToSchema, Clone)]
+pub struct CreateEventWebhookResponse {
+    pub webhook: EventWebhook,
+    /// Key used to sign deliveries. Each delivery has an `X-Trieve-Signature` header of `sha256=` followed by the hex encoded HMAC-SHA256 of `{X-Trieve-Timestamp}.{body}`. The secret is only returned once.
+    pub secret: String,
+}
+
+/// Create Event Webhook
+///
+/// Registers a url which the dataset's worker events, such as file processing, csv/jsonl import, crawl, group update and deletion outcomes, are delivered to. Each event is POSTed individually, signed with the secret returned in the response, and retried with exponential backoff up to 10 times until the url responds with a 2xx status. The `X-Trieve-Event-Id` header is the same across retries so receivers can deduplicate deliveries. The auth'ed user must be an admin of the organization to create an event webhook.
+#[utoipa::path(
+    post,
+    path = "/dataset/event_webhooks",
+    context_path = "/api",
+    tag = "Dataset",
+    request_body(content = CreateEventWebhookReqPayload, description = "JSON request payload to create an event webhook", content_type = "application/json"),
+    responses(
+        (status = 200, description = "The created webhook and its signing secret", body = CreateEventWebhookResponse),
+        (status = 400, description = "Service error relating to creating the webhook", body = ErrorResponseBody),
+    ),
+    params(
+        ("TR-Dataset" = uuid::Uuid, Header, description = "The dataset id or tracking_id to use for the request. We assume you intend to use an id if the value is a valid uuid."),
+    ),
+    security(
+        ("ApiKey" = ["admin"]),
+    )
+)]
+pub async fn create_event_webhook(
+    data: web::Json<CreateEventWebhookReqPayload>,
+    dataset_org_plan_sub: DatasetAndOrgWithSubAndPlan,
+    _user: AdminOnly,
+    pool: web::Data<Pool>,
+) -> Result<HttpResponse, ServiceError> {
+    let data = data.into_inner();
+
+    resolve_webhook_url(&data.url).await?;
+
+    if data.event_types.is_empty() {
+        return Err(ServiceError::BadRequest(
+            "At least one event type must be specified".to_string(),
+        ));
+    }
+
+    let secret = generate_webhook_secret();
+    let webhook = create_event_webhook_query(
+        EventWebhook::from_details(
+            dataset_org_plan_sub.dataset.id,
+            data.url,
+            secret.clone(),
+            data.event_types,
+        ),
+        pool,
+    )
+    .await?;
+
+    Ok(HttpResponse::Ok().json(CreateEventWebhookResponse { webhook, secret }))
+}
+
+/// Get Event Webhooks
+///
+/// Lists the event webhooks registered for the dataset. The auth'ed user must be an admin of the organization to list event webhooks.
+#[utoipa::path(
+    get,
+    path = "/dataset/event_webhooks",
+    context_path = "/api",
+    tag = "Dataset",
+    responses(
+        (status = 200, description = "The dataset's event webhooks", body = Vec<EventWebhook>),
+        (status = 400, description = "Service error relating to getting the webhooks", body = ErrorResponseBody),
+    ),
+    params(
+        ("TR-Dataset" = uuid::Uuid, Header, description = "The dataset id or tracking_id to use for the request. We assume you intend to use an id if the value is a valid uuid."),
+    ),
+    security(
+        ("ApiKey" = ["admin"]),
+    )
+)]
+pub async fn get_event_webhooks(
+    dataset_org_plan_sub: DatasetAndOrgWithSubAndPlan,
+    _user: AdminOnly,
+    pool: web::Data<Pool>,
+) -> Result<HttpResponse, ServiceError> {
+    let webhooks = get_event_webhooks_query(dataset_org_plan_sub.dataset.id, pool).await?;
+
+    Ok(HttpResponse::Ok().json(webhooks))
+}
+
+#[derive(Serialize, Deserialize, Debug, ToSchema, Clone)]
+#[schema(example = json!({
+    "event_types": ["file_uploaded", "file_upload_failed"],
+}))]
+pub struct UpdateEventWebhookReqPayload {
+    /// New url to deliver events to. It must use https and resolve to a public address, redirects are not followed.
+    pub url: Option<String>,
+    /// Event types to deliver
+    pub event_types: Option<Vec<EventTypeRequest>>,
+    /// Set to false to stop queueing events for the webhook. Pending deliveries are dropped while the webhook is inactive.
+    pub active: Option<bool>,
+}
+
+/// Update Event Webhook
+///
+/// Changes the url, event types or active state of an event webhook. The auth'ed user must be an admin of the organization to update an event webhook.
+#[utoipa::path(
+    put,
+    path = "/dataset/event_webhooks/{webhook_id}",
+    context_path = "/api",
+    tag = "Dataset",
+    request_body(content = UpdateEventWebhookReqPayload, description = "JSON request payload to update an event webhook", content_type = "application/json"),
+    responses(
+        (status = 200, description = "The updated webhook", body = EventWebhook),
+        (status = 400, description = "Service error relating to updating the webhook", body = ErrorResponseBody),
+        (status = 404, description = "Event webhook not found", body = ErrorResponseBody),
+    ),
+    params(
+        ("TR-Dataset" = uuid::Uuid, Header, description = "The dataset id or tracking_id to use for the request. We assume you intend to use an id if the value is a valid uuid."),
+        ("webhook_id" = uuid::Uuid, Path, description = "The id of the webhook you want to update."),
+    ),
+    security(
+        ("ApiKey" = ["admin"]),
+    )
+)]
+pub async fn update_event_webhook(
+    webhook_id: web::Path<uuid::Uuid>,
+    data: web::Json<UpdateEventWebhookReqPayload>,
+    dataset_org_plan_sub: DatasetAndOrgWithSubAndPlan,
+    _user: AdminOnly,
+    pool: web::Data<Pool>,
+) -> Result<HttpResponse, ServiceError> {
+    let data = data.into_inner();
+
+    if let Some(webhook_url) = &data.url {
+        resolve_webhook_url(webhook_url).await?;
+    }
+
+    if data
+        .event_types
+        .as_ref()
+        .is_some_and(|event_types| event_types.is_empty())
+    {
+        return Err(ServiceError::BadRequest(
+            "At least one event type must be specified".to_string(),
+        ));
+    }
+
+    let webhook = update_event_webhook_query(
+        webhook_id.into_inner(),
+        dataset_org_plan_sub.dataset.id,
+        data.url,
+        data.event_types.map(|event_types| {
+            event_types
+                .iter()
+                .map(|event_type| event_type.to_string())
+                .collect()
+        }),
+        data.active,
+        pool,
+    )
+    .await?;
+
+    Ok(HttpResponse::Ok().json(webhook))
+}
+
+/// Delete Event Webhook
+///
+/// Stops delivering events to the webhook and deletes its delivery log. The auth'ed user must be an admin of the organization to delete an event webhook.
+#[utoipa::path(
+    delete,
+    path = "/dataset/event_webhooks/{webhook_id}",
+    context_path = "/api",
+    tag = "Dataset",
+    responses(
+        (status = 204, description = "Event webhook deleted"),
+        (status = 400, description = "Service error relating to deleting the webhook", body = ErrorResponseBody),
+        (status = 404, description = "Event webhook not found", body = ErrorResponseBody),
+    ),
+    params(
+        ("TR-Dataset" = uuid::Uuid, Header, description = "The dataset id or tracking_id to use for the request. We assume you intend to use an id if the value is a valid uuid."),
+        ("webhook_id" = uuid::Uuid, Path, description = "The id of the webhook you want to delete."),
+    ),
+    security(
+        ("ApiKey" = ["admin"]),
+    )
+)]
+pub async fn delete_event_webhook(
+    webhook_id: web::Path<uuid::Uuid>,
+    dataset_org_plan_sub: DatasetAndOrgWithSubAndPlan,
+    _user: AdminOnly,
+    pool: web::Data<Pool>,
+) -> Result<HttpResponse, ServiceError> {
+    delete_event_webhook_query(
+        webhook_id.into_inner(),
+        dataset_org_plan_sub.dataset.id,
+        pool,
+    )
+    .await?;
+
+    Ok(HttpResponse::NoContent().finish())
+}
+
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+pub struct GetEventWebhookDeliveriesQuery {
+    /// Only return deliveries with this status
+    pub status: Option<EventWebhookDeliveryStatus>,
+    /// Page number to return, 1-indexed. Default is 1.
+    pub page: Option<i64>,
+    /// Number of deliveries to return per page. Default is 20, maximum is 100.
+    pub page_size: Option<i64>,
+}
+
+/// Get Event Webhook Deliveries
+///
+/// Returns the deliveries queued for an event webhook, most recent first. Each entry has the event payload, its status, the number of attempts made, and the status code and error of the last attempt. The auth'ed user must be an admin of the organization to get event webhook deliveries.
+#[utoipa::path(
+    get,
+    path = "/dataset/event_webhooks/{webhook_id}/deliveries",
+    context_path = "/api",
+    tag = "Dataset",
+    responses(
+        (status = 200, description = "The webhook's deliveries", body = Vec<EventWebhookDelivery>),
+        (status = 400, description = "Service error relating to getting the deliveries", body = ErrorResponseBody),
+        (status = 404, description = "Event webhook not found", body = ErrorResponseBody),
+    ),
+    params(
+        ("TR-Dataset" = uuid::Uuid, Header, description = "The dataset id or tracking_id to use for the request. We assume you intend to use an id if the value is a valid uuid."),
+        ("webhook_id" = uuid::Uuid, Path, description = "The id of the webhook you want the deliveries of."),
+        ("status" = Option<EventWebhookDeliveryStatus>, Query, description = "Only return deliveries with this status."),
+        ("page" = Option<i64>, Query, description = "Page number to return, 1-indexed. Default is 1."),
+        ("page_size" = Option<i64>, Query, description = "Number of deliveries to return per page. Default is 20, maximum is 100."),
+    ),
+    security(
+        ("ApiKey" = ["admin"]),
+    )
+)]
+pub async fn get_event_webhook_deliveries(
+    webhook_id: web::Path<uuid::Uuid>,
+    query: web::Query<GetEventWebhookDeliveriesQuery>,
+    dataset_org_plan_sub: DatasetAndOrgWithSubAndPlan,
+    _user: AdminOnly,
+    pool: web::Data<Pool>,
+) -> Result<HttpResponse, ServiceError> {
+    let query = query.into_inner();
+    let webhook = get_event_webhook_query(
+        webhook_id.into_inner(),
+        dataset_org_plan_sub.dataset.id,
+        pool.clone(),
+    )
+    .await?;
+
+    let deliveries = get_event_webhook_deliveries_query(
+        webhook.id,
+        query.status,
+        query.page.unwrap_or(1),
+        query.page_size.unwrap_or(20).clamp(1, 100),
+        pool,
+    )
+    .await?;
+
+    Ok(HttpResponse::Ok().json(deliveries))
+}
+
+/// Test Event Webhook
+///
+/// Sends a signed `webhook_test` event to the webhook right away and returns the outcome of the delivery, so receivers can verify their url and signature check before real events arrive. Test deliveries are recorded in the delivery log but are not retried. The auth'ed user must be an admin of the organization to test an event webhook.
+#[utoipa::path(
+    post,
+    path = "/dataset/event_webhooks/{webhook_id}/test",
+    context_path = "/api",
+    tag = "Dataset",
+    responses(
+        (status = 200, description = "The test delivery, with the status code returned by the webhook url", body = EventWebhookDelivery),
+        (status = 400, description = "Service error relating to sending the test event", body = ErrorResponseBody),
+        (status = 404, description = "Event webhook not found", body = ErrorResponseBody),
+    ),
+    params(
+        ("TR-Dataset" = uuid::Uuid, Header, description = "The dataset id or tracking_id to use for the request. We assume you intend to use an id if the value is a valid uuid."),
+        ("webhook_id" = uuid::Uuid, Path, description = "The id of the webhook you want to test."),
+    ),
+    security(
+        ("ApiKey" = ["admin"]),
+    )
+)]
+pub async fn test_event_webhook(
+    webhook_id: web::Path<uuid::Uuid>,
+    dataset_org_plan_sub: DatasetAndOrgWithSubAndPlan,
+    _user: AdminOnly,
+    pool: web::Data<Pool>,
+) -> Result<HttpResponse, ServiceError> {
+    let webhook = get_event_webhook_query(
+        webhook_id.into_inner(),
+        dataset_org_plan_sub.dataset.id,
+        pool.clone(),
+    )
+    .await?;
+
+    let delivery = send_test_event_webhook(webhook, pool).await?;
+
+    Ok(HttpResponse::Ok().json(delivery))
+}
+
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+pub struct GetDatasetConfigVersionsQuery {
+    /// Page number to return, 1-indexed. Default is 1.
+    pub page: Option<i64>,
+    /// Number of versions to return per page. Default is 20, maximum is 100.
+    pub page_size: Option<i64>,
+}
+
+/// Get Dataset Configuration Versions
+///
+/// Lists the recorded versions of the dataset's server_configuration, most recent first. Each version has the full configuration after the change, the keys which changed, what made the change and who made it. The first version is the configuration the dataset had before its first recorded change. The auth'ed user must be an admin of the organization to list configuration versions.
+#[utoipa::path(
+    get,
+    path = "/dataset/config_versions",
+    context_path = "/api",
+    tag = "Dataset",
+    responses(
+        (status = 200, description = "The dataset's configuration versions", body = Vec<DatasetConfigVersion>),
+        (status = 400, description = "Service error relating to getting the versions", body = ErrorResponseBody),
+    ),
+    params(
+        ("TR-Dataset" = uuid::Uuid, Header, description = "The dataset id or tracking_id to use for the request. We assume you intend to use an id if the value is a valid uuid."),
+        ("page" = Option<i64>, Query, description = "Page number to return, 1-indexed. Default is 1."),
+        ("page_size" = Option<i64>, Query, description = "Number of versions to return per page. Default is 20, maximum is 100."),
+    ),
+    security(
+        ("ApiKey" = ["admin"]),
+    )
+)]
+pub async fn get_dataset_config_versions(
+    query: web::Query<GetDatasetConfigVersionsQuery>,
+    dataset_org_plan_sub: DatasetAndOrgWithSubAndPlan,
+    _user: AdminOnly,
+    pool: web::Data<Pool>,
+) -> Result<HttpResponse, ServiceError> {
+    let versions = get_dataset_config_versions_query(
+        dataset_org_plan_sub.dataset.id,
+        query.page.unwrap_or(1),
+        query.page_size.unwrap_or(20).clamp(1, 100),
+        pool,
+    )
+    .await?
+    .into_iter()
+    .map(|mut version| {
+        version.server_configuration = json!(DatasetConfiguration::from_json(
+            version.server_configuration
+        ));
+        version
+    })
+    .collect::<Vec<DatasetConfigVersion>>();
+
+    Ok(HttpResponse::Ok().json(versions))
+}
+
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+pub struct DiffDatasetConfigVersionsQuery {
+    /// Version to diff from
+    pub from_version: i32,
+    /// Version to diff to. Defaults to the latest version.
+    pub to_version: Option<i32>,
+}
+
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+#[schema(example = json!({
+    "from_version": 2,
+    "to_version": 4,
+    "diff": {
+        "TEMPERATURE": {
+            "from": 0.5,
+            "to": 0.2,
+        },
+    },
+}))]
+pub struct DatasetConfigDiff {
+    pub from_version: i32,
+    pub to_version: i32,
+    /// Keys which differ between the versions, each with the value `from` in the first and `to` in the second. Api keys are redacted.
+    pub diff: serde_json::Value,
+}
+
+/// Diff Dataset Configuration Versions
+///
+/// Returns the keys which differ between two versions of the dataset's server_configuration. The auth'ed user must be an admin of the organization to diff configuration versions.
+#[utoipa::path(
+    get,
+    path = "/dataset/config_versions/diff",
+    context_path = "/api",
+    tag = "Dataset",
+    responses(
+        (status = 200, description = "The keys which differ between the versions", body = DatasetConfigDiff),
+        (status = 400, description = "Service error relating to diffing the versions", body = ErrorResponseBody),
+        (status = 404, description = "Configuration version not found", body = ErrorResponseBody),
+    ),
+    params(
+        ("TR-Dataset" = uuid::Uuid, Header, description = "The dataset id or tracking_id to use for the request. We assume you intend to use an id if the value is a valid uuid."),
+        ("from_version" = i32, Query, description = "Version to diff from."),
+        ("to_version" = Option<i32>, Query, description = "Version to diff to. Defaults to the latest version."),
+    ),
+    security(
+        ("ApiKey" = ["admin"]),
+    )
+)]
+pub async fn diff_dataset_config_versions(
+    query: web::Query<DiffDatasetConfigVersionsQuery>,
+    dataset_org_plan_sub: DatasetAndOrgWithSubAndPlan,
+    _user: AdminOnly,
+    pool: web::Data<Pool>,
+) -> Result<HttpResponse, ServiceError> {
+    let dataset_id = dataset_org_plan_sub.dataset.id;
+
+    let from_version =
+        get_dataset_config_version_query(dataset_id, Some(query.from_version), pool.clone())
+            .await?;
+    let to_version = get_dataset_config_version_query(dataset_id, query.to_version, pool).await?;
+
+    Ok(HttpResponse::Ok().json(DatasetConfigDiff {
+        from_version: from_version.version,
+        to_version: to_version.version,
+        diff: get_dataset_config_diff(
+            &DatasetConfiguration::from_json(from_version.server_configuration),
+            &DatasetConfiguration::from_json(to_version.server_configuration),
+        ),
+    }))
+}
+
+#[derive(Serialize, Deserialize, Debug, ToSchema, Clone)]
+#[schema(example = json!({
+    "acknowledge_reindex_required": false,
+}))]
+pub struct RollbackDatasetConfigReqPayload {
+    /// Rollbacks which change keys the dataset's vectors were computed with (EMBEDDING_SIZE, DISTANCE_METRIC, EMBEDDING_MODEL_NAME, BM25_B, BM25_K and BM25_AVG_LEN) are rejected unless this is true. Defaults to false.
+    pub acknowledge_reindex_required: Option<bool>,
+}
+
+/// Rollback Dataset Configuration
+///
+/// Restores the dataset's server_configuration to a previous version. The rollback is recorded as a new version, so it can itself be rolled back. The dataset's current LLM and reranker api keys are kept, as the keys of an old version may have been revoked. The auth'ed user must be an owner of the organization to rollback the configuration.
+#[utoipa::path(
+    post,
+    path = "/dataset/config_versions/{version}/rollback",
+    context_path = "/api",
+    tag = "Dataset",
+    request_body(content = RollbackDatasetConfigReqPayload, description = "JSON request payload to rollback the dataset configuration", content_type = "application/json"),
+    responses(
+        (status = 200, description = "The dataset with the restored configuration", body = Dataset),
+        (status = 400, description = "Service error relating to rolling back the configuration", body = ErrorResponseBody),
+        (status = 404, description = "Configuration version not found", body = ErrorResponseBody),
+    ),
+    params(
+        ("TR-Dataset" = uuid::Uuid, Header, description = "The dataset id or tracking_id to use for the request. We assume you intend to use an id if the value is a valid uuid."),
+        ("version" = i32, Path, description = "The version to restore."),
+    ),
+    security(
+        ("ApiKey" = ["owner"]),
+    )
+)]
+pub async fn rollback_dataset_config(
+    version: web::Path<i32>,
+    data: web::Json<RollbackDatasetConfigReqPayload>,
+    dataset_org_plan_sub: DatasetAndOrgWithSubAndPlan,
+    user: OwnerOnly,
+    pool: web::Data<Pool>,
+) -> Result<HttpResponse, ServiceError> {
+    let dataset = dataset_org_plan_sub.dataset;
+    let config_version =
+        get_dataset_config_version_query(dataset.id, Some(version.into_inner()), pool.clone())
+            .await?;
+
+    let curr_dataset_config = DatasetConfiguration::from_json(dataset.server_configuration);
+    let mut restored_dataset_config =
+        DatasetConfiguration::from_json(config_version.server_configuration);
+    restored_dataset_config.LLM_API_KEY = curr_dataset_config.LLM_API_KEY.clone();
+    restored_dataset_config.RERANKER_API_KEY = curr_dataset_config.RERANKER_API_KEY.clone();
+
+    check_reindex_required_config_changes(
+        &curr_dataset_config,
+        &restored_dataset_config,
+        data.acknowledge_reindex_required.unwrap_or(false),
+    )?;
+
+    let mut d = update_dataset_query(
+        dataset.id,
+        dataset.name,
+        restored_dataset_config,
+        None,
+        DatasetConfigVersionSource::Rollback,
+        Some(user.0.id),
+        Some(config_version.version),
+        pool,
+    )
+    .await?;
+
+    d.server_configuration = json!(DatasetConfiguration::from_json(d.server_configuration));
+
+    Ok(HttpResponse::Ok().json(d))
+}
+
+#[derive(Serialize, Deserialize, Debug, ToSchema, Clone)]
+#[schema(example = json!({
+    "name": "production",
+    "dataset_id": "e3e3e3e3-e3e3-e3e3-e3e3-e3e3e3e3e3e3",
+}))]
+pub struct CreateDatasetAliasReqPayload {
+    /// Name of the alias. It can be used in place of a dataset id or tracking id anywhere a dataset is specified, e.g. the TR-Dataset header. It cannot be a uuid or the tracking id of a dataset in the organization.
+    pub name: String,
+    /// Id of the dataset the alias should point at.
+    pub dataset_id: uuid::Uuid,
+}
+
+#[derive(Serialize, Deserialize, Debug, ToSchema, Clone)]
+#[schema(example = json!({
+    "name": "production",
+    "dataset_id": "e3e3e3e3-e3e3-e3e3-e3e3-e3e3e3e3e3e3",
+}))]
+pub struct SwapDatasetAliasReqPayload {
+    /// Name of the alias to swap.
+    pub name: String,
+    /// Id of the dataset the alias should point at after the swap. If not specified, the alias flips back to the dataset it pointed at before the last swap.
+    pub dataset_id: Option<uuid::Uuid>,
+}
+
+async fn get_alias_target_dataset(
+    dataset_id: uuid::Uuid,
+    organization_id: uuid::Uuid,
+    pool: web::Data<Pool>,
+) -> Result<Dataset, ServiceError> {
+    let dataset = get_dataset_by_id_query(dataset_id, pool).await?;
+
+    if dataset.organization_id != organization_id {
+        return Err(ServiceError::NotFound("Could not find dataset".to_string()));
+    }
+
+    Ok(dataset)
+}
+
+/// Create Dataset Alias
+///
+/// Creates an alias which points at a dataset of the organization specified via the TR-Organization header. Requests made with the alias as the dataset tracking id are routed to the dataset it points at, so a rebuilt dataset can be swapped in without redeploying clients. Auth'ed user must be an owner of the organization to create an alias.
+#[utoipa::path(
+    post,
+    path = "/dataset/alias",
+    context_path = "/api",
+    tag = "Dataset",
+    request_body(content = CreateDatasetAliasReqPayload, description = "JSON request payload to create a dataset alias", content_type = "application/json"),
+    responses(
+        (status = 200, description = "The created alias", body = DatasetAlias),
+        (status = 400, description = "Service error relating to creating the alias", body = ErrorResponseBody),
+        (status = 404, description = "Dataset not found", body = ErrorResponseBody),
+    ),
+    params(
+        ("TR-Organization" = uuid::Uuid, Header, description = "The organization id to use for the request"),
+    ),
+    security(
+        ("ApiKey" = ["owner"]),
+    )
+)]
+pub async fn create_dataset_alias(
+    data: web::Json<CreateDatasetAliasReqPayload>,
+    org_with_sub_and_plan: OrganizationWithSubAndPlan,
+    _user: OwnerOnly,
+    pool: web::Data<Pool>,
+) -> Result<HttpResponse, ServiceError> {
+    let organization_id = org_with_sub_and_plan.organization.id;
+    let name = data.name.trim().to_string();
+
+    if name.is_empty() {
+        return Err(ServiceError::BadRequest(
+            "Alias name cannot be empty".to_string(),
+        ));
+    }
+
+    if name.parse::<uuid::Uuid>().is_ok() {
+        return Err(ServiceError::BadRequest(
+            "Alias name cannot be a uuid".to_string(),
+        ));
+    }
+
+    if get_dataset_by_tracking_id_query(name.clone(), organization_id, pool.clone())
+        .await
+        .is_ok()
+    {
+        return Err(ServiceError::BadRequest(format!(
+            "A dataset with the tracking id {} already exists in this organization",
+            name
+        )));
+    }
+
+    let dataset = get_alias_target_dataset(data.dataset_id, organization_id, pool.clone()).await?;
+
+    let dataset_alias = create_dataset_alias_query(
+        DatasetAlias::from_details(organization_id, name, dataset.id),
+        pool,
+    )
+    .await?;
+
+    Ok(HttpResponse::Ok().json(dataset_alias))
+}
+
+/// Get Dataset Aliases
+///
+/// Returns all of the dataset aliases of the organization specified via the TR-Organization header.
+#[utoipa::path(
+    get,
+    path = "/dataset/alias",
+    context_path = "/api",
+    tag = "Dataset",
+    responses(
+        (status = 200, description = "The aliases of the organization", body = Vec<DatasetAlias>),
+        (status = 400, description = "Service error relating to getting the aliases", body = ErrorResponseBody),
+    ),
+    params(
+        ("TR-Organization" = uuid::Uuid, Header, description = "The organization id to use for the request"),
+    ),
+    security(
+        ("ApiKey" = ["readonly"]),
+    )
+)]
+pub async fn get_dataset_aliases(
+    org_with_sub_and_plan: OrganizationWithSubAndPlan,
+    _user: LoggedUser,
+    pool: web::Data<Pool>,
+) -> Result<HttpResponse, ServiceError> {
+    let dataset_aliases =
+        get_dataset_aliases_query(org_with_sub_and_plan.organization.id, pool).await?;
+
+    Ok(HttpResponse::Ok().json(dataset_aliases))
+}
+
+/// Swap Dataset Alias
+///
+/// Atomically points an alias at another dataset of the organization. Every request made with the alias after the swap goes to the new dataset. Swapping without a dataset_id flips the alias back to the dataset it pointed at before. Auth'ed user must be an owner of the organization to swap an alias.
+#[utoipa::path(
+    put,
+    path = "/dataset/alias/swap",
+    context_path = "/api",
+    tag = "Dataset",
+    request_body(content = SwapDatasetAliasReqPayload, description = "JSON request payload to swap a dataset alias", content_type = "application/json"),
+    responses(
+        (status = 200, description = "The alias after the swap", body = DatasetAlias),
+        (status = 400, description = "Service error relating to swapping the alias", body = ErrorResponseBody),
+        (status = 404, description = "Alias or dataset not found", body = ErrorResponseBody),
+    ),
+    params(
+        ("TR-Organization" = uuid::Uuid, Header, description = "The organization id to use for the request"),
+    ),
+    security(
+        ("ApiKey" = ["owner"]),
+    )
+)]
+pub async fn swap_dataset_alias(
+    data: web::Json<SwapDatasetAliasReqPayload>,
+    org_with_sub_and_plan: OrganizationWithSubAndPlan,
+    _user: OwnerOnly,
+    pool: web::Data<Pool>,
+) -> Result<HttpResponse, ServiceError> {
+    let organization_id = org_with_sub_and_plan.organization.id;
+    let data = data.into_inner();
+
+    if let Some(dataset_id) = data.dataset_id {
+        get_alias_target_dataset(dataset_id, organization_id, pool.clone()).await?;
+    }
+
+    let dataset_alias =
+        swap_dataset_alias_query(data.name, organization_id, data.dataset_id, pool).await?;
+
+    Ok(HttpResponse::Ok().json(dataset_alias))
+}
+
+/// Delete Dataset Alias
+///
+/// Deletes an alias of the organization specified via the TR-Organization header. The dataset it points at is not affected. Auth'ed user must be an owner of the organization to delete an alias.
+#[utoipa::path(
+    delete,
+    path = "/dataset/alias/{alias_name}",
+    context_path = "/api",
+    tag = "Dataset",
+    responses(
+        (status = 204, description = "Alias deleted successfully"),
+        (status = 400, description = "Service error relating to deleting the alias", body = ErrorResponseBody),
+        (status = 404, description = "Alias not found", body = ErrorResponseBody),
+    ),
+    params(
+        ("TR-Organization" = uuid::Uuid, Header, description = "The organization id to use for the request"),
+        ("alias_name" = String, Path, description = "The name of the alias to delete."),
+    ),
+    security(
+        ("ApiKey" = ["owner"]),
+    )
+)]
+pub async fn delete_dataset_alias(
+    alias_name: web::Path<String>,
+    org_with_sub_and_plan: OrganizationWithSubAndPlan,
+    _user: OwnerOnly,
+    pool: web::Data<Pool>,
+) -> Result<HttpResponse, ServiceError> {
+    delete_dataset_alias_query(
+        alias_name.into_inner(),
+        org_with_sub_and_plan.organization.id,
+        pool,
+    )
+    .await?;
+
+    Ok(HttpResponse::NoContent().finish())
+}
+
+/// Delete Dataset by Tracking ID
+///
+/// Auth'ed user must be an owner of the organization to delete a dataset.
+#[utoipa::path(
+    delete,
+    path = "/dataset/tracking_id/{tracking_id}",
+    context_path = "/api",
+    tag = "Dataset",
+    responses(
+        (status = 204, description = "Dataset deleted successfully"),
+        (status = 400, description = "Service error relating to deleting the dataset", body = ErrorResponseBody),
+        (status = 404, description = "Dataset not found", body = ErrorResponseBody)
+    ),
+    params(
+        ("TR-Dataset" = uuid::Uuid, Header, description = "The dataset id or tracking_id to use for the request. We assume you intend to use an id if the value is a valid uuid."),
+        ("tracking_id" = String, Path, description = "The tracking id of the dataset you want to delete."),
+    ),
+    security(
+        ("ApiKey" = ["owner"]),
+    )
+)]
+pub async fn delete_dataset_by_tracking_id(
+    tracking_id: web::Path<String>,
+    pool: web::Data<Pool>,
+    redis_pool: web::Data<RedisPool>,
+    _user: OwnerOnly,
+    org_with_plan_and_sub: OrganizationWithSubAndPlan,
+) -> Result<HttpResponse, ServiceError> {
+    let dataset = get_dataset_by_tracking_id_query(
+        tracking_id.into_inner(),
+        org_with_plan_and_sub.organization.id,
+        pool.clone(),
+    )
+    .await?;
+
+    let config = DatasetConfiguration::from_json(dataset.server_configuration);
+
+    soft_delete_dataset_by_id_query(dataset.id, config, pool, redis_pool).await?;
+
+    Ok(HttpResponse::NoContent().finish())
+}
+
+/// Get Dataset By ID
+///
+/// Auth'ed user or api key must have an admin or owner role for the specified dataset's organization.
+#[utoipa::path(
+    get,
+    path = "/dataset/{dataset_id}",
+    context_path = "/api",
+    tag = "Dataset",
+    responses(
+        (status = 200, description = "Dataset retrieved successfully", body = Dataset),
+        (status = 400, description = "Service error relating to retrieving the dataset", body = ErrorResponseBody),
+        (status = 404, description = "Dataset not found", body = ErrorResponseBody)
+    ),
+    params(
+        ("TR-Dataset" = uuid::Uuid, Header, description = "The dataset id or tracking_id to use for the request. We assume you intend to use an id if the value is a valid uuid."),
+        ("dataset_id" = uuid, Path, description = "The id of the dataset you want to retrieve."),
+    ),
+    security(
+        ("ApiKey" = ["admin"]),
+    )
+)]
+pub async fn get_dataset(
+    pool: web::Data<Pool>,
+    dataset_id: web::Path<uuid::Uuid>,
+    _user: AdminOnly,
+) -> Result<HttpResponse, ServiceError> {
+    let mut dataset = get_dataset_by_id_query(dataset_id.into_inner(), pool).await?;
+
+    dataset.server_configuration = json!(DatasetConfiguration::from_json(
+        dataset.server_configuration
+    ));
+
+    Ok(HttpResponse::Ok().json(dataset))
+}
+
+/// Get Usage By Dataset ID
+///
+/// Auth'ed user or api key must have an admin or owner role for the specified dataset's organization.
+#[utoipa::path(
+    get,
+    path = "/dataset/usage/{dataset_id}",
+    context_path = "/api",
+    tag = "Dataset",
+    responses(
+        (status = 200, description = "Dataset usage retrieved successfully", body = DatasetUsageCount),
+        (status = 400, description = "Service error relating to retrieving the dataset usage", body = ErrorResponseBody),
+        (status = 404, description = "Dataset not found", body = ErrorResponseBody)
+    ),
+    params(
+        ("TR-Dataset" = uuid::Uuid, Header, description = "The dataset id or tracking_id to use for the request. We assume you intend to use an id if the value is a valid uuid."),
+        ("dataset_id" = uuid, Path, description = "The id of the dataset you want to retrieve usage for."),
+    ),
+    security(
+        ("ApiKey" = ["admin"]),
+    )
+)]
+pub async fn get_usage_by_dataset_id(
+    pool: web::Data<Pool>,
+    dataset_id: web::Path<uuid::Uuid>,
+    _user: AdminOnly,
+) -> Result<HttpResponse, ServiceError> {
+    let usage = get_dataset_usage_query(dataset_id.into_inner(), pool)
+        .await
+        .map_err(|e| ServiceError::InternalServerError(e.to_string()))?;
+
+    Ok(HttpResponse::Ok().json(usage))
+}
+
+/// Get Dataset by Tracking ID
+///
+/// Auth'ed user or api key must have an admin or owner role for the specified dataset's organization.
+#[utoipa::path(
+    get,
+    path = "/dataset/tracking_id/{tracking_id}",
+    context_path = "/api",
+    tag = "Dataset",
+    responses(
+        (status = 200, description = "Dataset retrieved successfully", body = Dataset),
+        (status = 400, description = "Service error relating to retrieving the dataset", body = ErrorResponseBody),
+        (status = 404, description = "Dataset not found", body = ErrorResponseBody)
+    ),
+    params(
+        ("TR-Organization" = uuid::Uuid, Header, description = "The organization id to use for the request"),
+        ("tracking_id" = String, Path, description = "The tracking id of the dataset you want to retrieve."),
+    ),
+    security(
+        ("ApiKey" = ["admin"]),
+    )
+)]
+pub async fn get_dataset_by_tracking_id(
+    tracking_id: web::Path<String>,
+    pool: web::Data<Pool>,
+    _user: AdminOnly,
+    org_with_plan_and_sub: OrganizationWithSubAndPlan,
+) -> Result<HttpResponse, ServiceError> {
+    let mut dataset = get_dataset_by_tracking_id_query(
+        tracking_id.into_inner(),
+        org_with_plan_and_sub.organization.id,
+        pool,
+    )
+    .await
+    .map_err(|e| ServiceError::InternalServerError(e.to_string()))?;
+
+    dataset.server_configuration = json!(DatasetConfiguration::from_json(
+        dataset.server_configuration
+    ));
+
+    Ok(HttpResponse::Ok().json(dataset))
+}
+
+#[derive(Debug, Deserialize, Serialize, ToSchema, Default)]
+pub struct GetDatasetsPagination {
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+}
+
+/// Get Datasets from Organization
+///
+/// Auth'ed user or api key must have an admin or owner role for the specified dataset's organization.
+#[utoipa::path(
+    get,
+    path = "/dataset/organization/{organization_id}",
+    context_path = "/api",
+    tag = "Dataset",
+    responses(
+        (status = 200, description = "Datasets retrieved successfully", body = Vec<DatasetAndUsage>),
+        (status = 400, description = "Service error relating to retrieving the dataset", body = ErrorResponseBody),
+        (status = 404, description = "Could not find organization", body = ErrorResponseBody)
+    ),
+    params(
+        ("TR-Organization" = uuid::Uuid, Header, description = "The organization id to use for the request"),
+        ("organization_id" = uuid, Path, description = "id of the organization you want to retrieve datasets for"),
+        ("limit" = Option<i64>, Query, description = "The number of records to return"),
+        ("offset" = Option<i64>, Query, description = "The number of records to skip"),
+    ),
+    security(
+        ("ApiKey" = ["admin"]),
+    )
+)]
+pub async fn get_datasets_from_organization(
+    organization_id: web::Path<uuid::Uuid>,
+    pagination: web::Query<GetDatasetsPagination>,
+    user: AdminOnly,
+    pool: web::Data<Pool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let pagination = pagination.into_inner();
+    let organization_id = organization_id.into_inner();
+    user.0
+        .user_orgs
+        .iter()
+        .find(|org| org.organization_id == organization_id)
+        .ok_or(ServiceError::Forbidden)?;
+
+    // If offset is set, limit must also be set and vice versa
+    if pagination.offset.is_some() != pagination.limit.is_some() {
+        return Err(ServiceError::BadRequest(
             "Pagination requires both offset and limit.".to_string(),
         )
         .into());
@@ -906,7 +2741,7 @@ pub async fn get_all_tags(
 pub struct CreateBatchDataset {
     /// Name of the dataset.
     pub dataset_name: String,
-    /// Optional tracking ID for the dataset. Can be used to track the dataset in external systems. Must be unique within the organization. Strongly recommended to not use a valid uuid value as that will not work with the TR-Dataset header.
+    /// Optional tracking ID for the dataset. Can be used to track the dataset in external systems. Must be unique within the organization and cannot be the name of a dataset alias. Strongly recommended to not use a valid uuid value as that will not work with the TR-Dataset header.
     pub tracking_id: Option<String>,
     /// The configuration of the dataset. See the example request payload for the potential keys which can be set. It is possible to break your dataset's functionality by erroneously setting this field. We recommend setting through creating a dataset at dashboard.trieve.ai and managing it's settings there.
     pub server_configuration: Option<DatasetConfigurationDTO>,
@@ -950,6 +2785,16 @@ pub async fn batch_create_datasets(
     pool: web::Data<Pool>,
     org_with_sub_and_plan: OrganizationWithSubAndPlan,
 ) -> Result<HttpResponse, ServiceError> {
+    check_tracking_ids_not_aliases(
+        data.datasets
+            .iter()
+            .filter_map(|d| d.tracking_id.clone())
+            .collect(),
+        org_with_sub_and_plan.organization.id,
+        pool.clone(),
+    )
+    .await?;
+
     let datasets = data
         .datasets
         .iter()
@@ -1,20 +1,29 @@
 use super::{
     auth_handler::{AdminOnly, LoggedUser},
+    chunk_handler::ChunkReqPayload,
     group_handler::DeleteGroupData,
 };
 use crate::{
     data::models::{
-        ChunkReqPayloadMappings, CsvJsonlWorkerMessage, DatasetAndOrgWithSubAndPlan,
-        DatasetConfiguration, File, FileAndGroupId, FileWithChunkGroups, FileWorkerMessage, Pool,
-        RedisPool,
+        ChunkReqPayloadMappings, CsvJsonlFileFormat, CsvJsonlRowFailure, CsvJsonlRowFilter,
+        CsvJsonlWorkerMessage, DatasetAndOrgWithSubAndPlan, DatasetConfiguration, File,
+        FileAndGroupId, FileVersion, FileWithChunkGroups, FileWorkerMessage, Pool, RedisPool,
+        SpreadsheetOptions,
     },
     errors::ServiceError,
     operators::{
+        chunking_operator::ChunkingStrategy,
         crawl_operator::{process_crawl_doc, Document},
+        csv_jsonl_operator::{iter_file_rows, CsvJsonlConversionOptions, CsvJsonlRowConverter},
+        extraction_operator::SpreadsheetChunking,
         file_operator::{
-            create_file_query, delete_file_query, get_aws_bucket, get_csvjsonl_aws_bucket,
-            get_dataset_files_and_group_ids_query, get_file_query, get_files_query,
+            create_file_query, create_file_version_query, delete_file_query, get_aws_bucket,
+            get_csvjsonl_aws_bucket, get_dataset_files_and_group_ids_query, get_file_by_id_query,
+            get_file_by_tracking_id_query, get_file_group_id_query, get_file_query,
+            get_file_versions_query, get_files_query, release_file_reprocess_query,
+            reserve_file_reprocess_query, restore_file_version_query,
         },
+        group_operator::get_group_by_id_query,
         organization_operator::{get_file_size_sum_org, hash_function},
     },
 };
@@ -508,12 +517,53 @@ pub struct UploadFileReqPayload {
     pub group_tracking_id: Option<String>,
     /// The request payload to use for the Chunkr API create task endpoint.
     pub chunkr_create_task_req_payload: Option<CreateFormWithoutFile>,
-    /// Parameter to use pdf2md_ocr. If true, the file will be converted to markdown using gpt-4o. Default is false.
+    /// Parameter to use pdf2md_ocr. If true, the file will be converted to markdown using gpt-4o. Default is false. Chunks created from pdf2md or Chunkr pages have the page they came from in `metadata.page_num`, which can be used in filters, and the bounding boxes of the page segments they contain in `metadata.bounding_boxes`.
     pub pdf2md_options: Option<Pdf2MdOptions>,
     /// Split average will automatically split your file into multiple chunks and average all of the resulting vectors into a single output chunk. Default is false. Explicitly enabling this will cause each file to only produce a single chunk.
     pub split_avg: Option<bool>,
     /// Optional webhook URL to receive notifications for each page processed.
     pub webhook_url: Option<String>,
+    /// Chunking strategy used to split the extracted text into chunks. When set, it replaces the `split_delimiters` and `target_splits_per_chunk` based chunking and is also applied to each page produced by pdf2md or Chunkr. Each chunk will have the path of headings it was found under added to its metadata as `heading_path`.
+    pub chunking_strategy: Option<ChunkingStrategy>,
+    /// How rows of XLSX, ODS and CSV files are grouped into chunks. Defaults to `row`, which creates one chunk per row with each value labeled by its column header. Ignored when `split_avg` or `chunking_strategy` is set.
+    pub spreadsheet_chunking: Option<SpreadsheetChunking>,
+    /// Id of an existing file to upload a new version of. The previous contents are kept as a version which can be restored, and the file's group is reused such that chunks whose content did not change keep their ids, chunks with the tracking id of a previous chunk update it in place, and removed ones are deleted once the new chunks have been ingested. A new version cannot be uploaded until the new chunks of the previous version or reprocess of the file have been ingested.
+    pub target_file_id: Option<uuid::Uuid>,
+    /// Tracking id of the file. If a file with this tracking id already exists in the dataset and no `target_file_id` is specified, a new version of that file is uploaded instead of creating a new file.
+    pub file_tracking_id: Option<String>,
+}
+
+impl UploadFileReqPayload {
+    pub fn validate(&self) -> Result<(), ServiceError> {
+        // Disallow split_avg with pdf2md
+        if let Some(Pdf2MdOptions { use_pdf2md_ocr, .. }) = self.pdf2md_options {
+            if use_pdf2md_ocr && self.split_avg.unwrap_or(false) {
+                return Err(ServiceError::BadRequest(
+                    "split_avg is not supported with pdf2md".to_string(),
+                ));
+            }
+        }
+
+        if let Some(chunking_strategy) = self.chunking_strategy.as_ref() {
+            if self.split_avg.unwrap_or(false) {
+                return Err(ServiceError::BadRequest(
+                    "split_avg is not supported with chunking_strategy".to_string(),
+                ));
+            }
+            if self
+                .pdf2md_options
+                .as_ref()
+                .is_some_and(|options| options.split_headings.unwrap_or(false))
+            {
+                return Err(ServiceError::BadRequest(
+                    "pdf2md split_headings is not supported with chunking_strategy".to_string(),
+                ));
+            }
+            chunking_strategy.validate()?;
+        }
+
+        Ok(())
+    }
 }
 
 /// We plan to deprecate pdf2md in favor of chunkr.ai. This is a legacy option for using a vision LLM to convert a given file into markdown and then ingest it.
@@ -536,9 +586,10 @@ pub struct UploadFileResponseBody {
 pub async fn upload_file_helper(
     upload_file_data: UploadFileReqPayload,
     file_data: Vec<u8>,
+    target_file: Option<File>,
     pool: web::Data<Pool>,
     dataset_org_plan_sub: DatasetAndOrgWithSubAndPlan,
-) -> Result<uuid::Uuid, ServiceError> {
+) -> Result<File, ServiceError> {
     let file_size_sum_pool = pool.clone();
     let file_size_sum = get_file_size_sum_org(
         dataset_org_plan_sub.organization.organization.id,
@@ -560,6 +611,20 @@ pub async fn upload_file_helper(
         ));
     }
 
+    let file_size_mb = (file_data.len() as f64 / 1024.0).ceil() as i64;
+
+    if let Some(target_file) = target_file {
+        return create_file_version_query(
+            target_file.id,
+            file_size_mb,
+            file_data.as_slice(),
+            upload_file_data,
+            dataset_org_plan_sub.dataset.id,
+            pool,
+        )
+        .await;
+    }
+
     let file_id = uuid::Uuid::new_v4();
 
     let bucket = get_aws_bucket()?;
@@ -586,8 +651,6 @@ pub async fn upload_file_helper(
             })?;
     }
 
-    let file_size_mb = (file_data.len() as f64 / 1024.0).ceil() as i64;
-
     create_file_query(
         file_id,
         file_size_mb,
@@ -595,14 +658,12 @@ pub async fn upload_file_helper(
         dataset_org_plan_sub.dataset.id,
         pool.clone(),
     )
-    .await?;
-
-    Ok(file_id)
+    .await
 }
 
 /// Upload File
 ///
-/// Upload a file to S3 bucket attached to your dataset. You can select between a naive chunking strategy where the text is extracted with Apache Tika and split into segments with a target number of segments per chunk OR you can use a vision LLM to convert the file to markdown and create chunks per page. You must specifically use a base64url encoding. Auth'ed user must be an admin or owner of the dataset's organization to upload a file.
+/// Upload a file to S3 bucket attached to your dataset. You can select between a naive chunking strategy where the text is extracted natively for DOCX, PPTX, XLSX, CSV, EPUB, HTML, Markdown and text files or with Apache Tika for other files and split into segments with a target number of segments per chunk OR you can use a vision LLM to convert the file to markdown and create chunks per page. You must specifically use a base64url encoding. Auth'ed user must be an admin or owner of the dataset's organization to upload a file.
 #[utoipa::path(
     post,
     path = "/file",
@@ -623,19 +684,12 @@ pub async fn upload_file_helper(
 pub async fn upload_file_handler(
     data: web::Json<UploadFileReqPayload>,
     pool: web::Data<Pool>,
+    redis_pool: web::Data<RedisPool>,
     _user: AdminOnly,
     dataset_org_plan_sub: DatasetAndOrgWithSubAndPlan,
     broccoli_queue: web::Data<BroccoliQueue>,
 ) -> Result<HttpResponse, actix_web::Error> {
-    // Disallow split_avg with pdf2md
-    if let Some(Pdf2MdOptions { use_pdf2md_ocr, .. }) = data.pdf2md_options {
-        if use_pdf2md_ocr && data.split_avg.unwrap_or(false) {
-            return Err(ServiceError::BadRequest(
-                "split_avg is not supported with pdf2md".to_string(),
-            )
-            .into());
-        }
-    }
+    data.validate()?;
 
     let upload_file_data = data.into_inner();
 
@@ -660,26 +714,203 @@ pub async fn upload_file_handler(
             })?,
     };
 
-    let file_id = upload_file_helper(
-        upload_file_data.clone(),
-        decoded_file_data.clone(),
-        pool,
-        dataset_org_plan_sub.clone(),
-    )
-    .await?;
+    let target_file = match (
+        upload_file_data.target_file_id,
+        upload_file_data.file_tracking_id.as_ref(),
+    ) {
+        (Some(target_file_id), _) => Some(
+            get_file_by_id_query(
+                target_file_id,
+                dataset_org_plan_sub.dataset.id,
+                pool.clone(),
+            )
+            .await?,
+        ),
+        (None, Some(file_tracking_id)) => {
+            get_file_by_tracking_id_query(
+                file_tracking_id,
+                dataset_org_plan_sub.dataset.id,
+                pool.clone(),
+            )
+            .await?
+        }
+        (None, None) => None,
+    };
+
+    if target_file.is_some()
+        && upload_file_data
+            .pdf2md_options
+            .as_ref()
+            .is_some_and(|options| options.split_headings.unwrap_or(false))
+    {
+        return Err(ServiceError::BadRequest(
+            "pdf2md split_headings is not supported when uploading a new version of a file"
+                .to_string(),
+        )
+        .into());
+    }
+    let reprocess = target_file.is_some();
+    let target_file_id = target_file.as_ref().map(|target_file| target_file.id);
+
+    // A new version replaces the chunks of the file like a reprocess does
+    if let Some(target_file_id) = target_file_id {
+        reserve_file_reprocess_query(target_file_id, redis_pool.clone()).await?;
+    }
+
+    let queued_file = async {
+        let file = upload_file_helper(
+            upload_file_data.clone(),
+            decoded_file_data,
+            target_file,
+            pool,
+            dataset_org_plan_sub.clone(),
+        )
+        .await?;
+
+        let message = FileWorkerMessage {
+            file_id: file.id,
+            dataset_id: dataset_org_plan_sub.dataset.id,
+            organization_id: dataset_org_plan_sub.organization.organization.id,
+            upload_file_data: upload_file_data.clone(),
+            attempt_number: 0,
+            reprocess,
+        };
+
+        broccoli_queue
+            .publish(
+                "file_ingestion",
+                Some(dataset_org_plan_sub.dataset.id.to_string()),
+                &message,
+                None,
+            )
+            .await
+            .map_err(|e| {
+                log::error!("Could not publish message: {:?}", e);
+                ServiceError::BadRequest("Could not publish message".to_string())
+            })?;
+
+        Ok::<File, ServiceError>(file)
+    }
+    .await;
+
+    let file = match (queued_file, target_file_id) {
+        (Ok(file), _) => file,
+        (Err(err), Some(target_file_id)) => {
+            release_file_reprocess_query(target_file_id, redis_pool).await?;
+            return Err(err.into());
+        }
+        (Err(err), None) => return Err(err.into()),
+    };
+
+    let result = UploadFileResponseBody {
+        file_metadata: file,
+    };
+
+    Ok(HttpResponse::Ok().json(result))
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema, Default)]
+#[schema(example = json!({
+    "split_delimiters": [",",".","\n"],
+    "target_splits_per_chunk": 20,
+    "rebalance_chunks": true
+}))]
+pub struct ReprocessFileReqPayload {
+    /// Rebalance chunks is an optional field which allows you to specify whether or not to rebalance the chunks created from the file. If not specified, the default true is used.
+    pub rebalance_chunks: Option<bool>,
+    /// Split delimiters is an optional field which allows you to specify the delimiters to use when splitting the file before chunking the text. If not specified, the default [.!?\n] are used to split into sentences.
+    pub split_delimiters: Option<Vec<String>>,
+    /// Target splits per chunk. This is an optional field which allows you to specify the number of splits you want per chunk. If not specified, the default 20 is used.
+    pub target_splits_per_chunk: Option<usize>,
+    /// The request payload to use for the Chunkr API create task endpoint.
+    pub chunkr_create_task_req_payload: Option<CreateFormWithoutFile>,
+    /// Parameter to use pdf2md_ocr. `split_headings` is not supported when reprocessing since it creates a group per page.
+    pub pdf2md_options: Option<Pdf2MdOptions>,
+    /// Split average will automatically split your file into multiple chunks and average all of the resulting vectors into a single output chunk. Default is false.
+    pub split_avg: Option<bool>,
+    /// Optional webhook URL to receive notifications for each page processed.
+    pub webhook_url: Option<String>,
+    /// Chunking strategy used to split the extracted text into chunks. When set, it replaces the `split_delimiters` and `target_splits_per_chunk` based chunking.
+    pub chunking_strategy: Option<ChunkingStrategy>,
+    /// How rows of XLSX, ODS and CSV files are grouped into chunks. Defaults to `row`.
+    pub spreadsheet_chunking: Option<SpreadsheetChunking>,
+}
+
+impl ReprocessFileReqPayload {
+    pub fn validate(&self) -> Result<(), ServiceError> {
+        if self
+            .pdf2md_options
+            .as_ref()
+            .is_some_and(|options| options.split_headings.unwrap_or(false))
+        {
+            return Err(ServiceError::BadRequest(
+                "pdf2md split_headings is not supported when reprocessing a file".to_string(),
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+/// Queues a file which was already uploaded to be chunked again from its contents in S3 with the
+/// given options. The file keeps its group and the details of the group are carried over.
+async fn queue_file_reprocess(
+    file: &File,
+    reprocess_data: ReprocessFileReqPayload,
+    dataset_org_plan_sub: &DatasetAndOrgWithSubAndPlan,
+    pool: web::Data<Pool>,
+    broccoli_queue: &BroccoliQueue,
+) -> Result<(), ServiceError> {
+    let dataset_id = dataset_org_plan_sub.dataset.id;
+
+    let group = match get_file_group_id_query(file.id, pool.clone()).await? {
+        Some(group_id) => Some(get_group_by_id_query(group_id, dataset_id, pool.clone()).await?),
+        None => None,
+    };
+
+    let upload_file_data = UploadFileReqPayload {
+        base64_file: "".to_string(),
+        file_name: file.file_name.clone(),
+        tag_set: file
+            .tag_set
+            .clone()
+            .map(|tag_set| tag_set.into_iter().flatten().collect()),
+        description: group
+            .as_ref()
+            .map(|group| group.description.clone())
+            .filter(|description| !description.is_empty()),
+        link: file.link.clone(),
+        time_stamp: file.time_stamp.map(|time_stamp| time_stamp.to_string()),
+        metadata: file.metadata.clone(),
+        create_chunks: Some(true),
+        rebalance_chunks: reprocess_data.rebalance_chunks,
+        split_delimiters: reprocess_data.split_delimiters,
+        target_splits_per_chunk: reprocess_data.target_splits_per_chunk,
+        group_tracking_id: group.and_then(|group| group.tracking_id),
+        chunkr_create_task_req_payload: reprocess_data.chunkr_create_task_req_payload,
+        pdf2md_options: reprocess_data.pdf2md_options,
+        split_avg: reprocess_data.split_avg,
+        webhook_url: reprocess_data.webhook_url,
+        chunking_strategy: reprocess_data.chunking_strategy,
+        spreadsheet_chunking: reprocess_data.spreadsheet_chunking,
+        target_file_id: None,
+        file_tracking_id: None,
+    };
+    upload_file_data.validate()?;
 
     let message = FileWorkerMessage {
-        file_id,
-        dataset_id: dataset_org_plan_sub.dataset.id,
+        file_id: file.id,
+        dataset_id,
         organization_id: dataset_org_plan_sub.organization.organization.id,
-        upload_file_data: upload_file_data.clone(),
+        upload_file_data,
         attempt_number: 0,
+        reprocess: true,
     };
 
     broccoli_queue
         .publish(
             "file_ingestion",
-            Some(dataset_org_plan_sub.dataset.id.to_string()),
+            Some(dataset_id.to_string()),
             &message,
             None,
         )
@@ -689,22 +920,182 @@ pub async fn upload_file_handler(
             ServiceError::BadRequest("Could not publish message".to_string())
         })?;
 
-    let result = UploadFileResponseBody {
-        file_metadata: File::from_details(
-            Some(file_id),
-            &upload_file_data.file_name,
-            decoded_file_data.len().try_into().unwrap_or_default(),
-            upload_file_data
-                .tag_set
-                .map(|t| t.into_iter().map(Some).collect()),
-            upload_file_data.metadata.clone(),
-            upload_file_data.link.clone(),
-            upload_file_data.time_stamp.clone(),
+    Ok(())
+}
+
+/// Reprocess File
+///
+/// Re-run chunking for a file which was already uploaded using the original file stored in S3 and new chunking options. The file keeps its id, group and metadata. Chunks whose content did not change keep their ids, new chunks are created and the chunks created from the file whose content is no longer produced are deleted once the new chunks have been ingested. Chunks added to the file's group by hand are kept. A file cannot be reprocessed again until the new chunks of its previous reprocess have been ingested. Auth'ed user or api key must have an admin or owner role for the specified dataset's organization.
+#[utoipa::path(
+    post,
+    path = "/file/{file_id}/reprocess",
+    context_path = "/api",
+    tag = "File",
+    request_body(content = ReprocessFileReqPayload, description = "JSON request payload with the chunking options to reprocess the file with", content_type = "application/json"),
+    responses(
+        (status = 200, description = "Confirmation that the file is being reprocessed", body = UploadFileResponseBody),
+        (status = 400, description = "Service error relating to reprocessing the file, or the file is still being processed", body = ErrorResponseBody),
+        (status = 404, description = "File not found", body = ErrorResponseBody),
+    ),
+    params(
+        ("TR-Dataset" = uuid::Uuid, Header, description = "The dataset id or tracking_id to use for the request. We assume you intend to use an id if the value is a valid uuid."),
+        ("file_id" = uuid::Uuid, description = "The id of the file to reprocess"),
+    ),
+    security(
+        ("ApiKey" = ["admin"]),
+    )
+)]
+pub async fn reprocess_file_handler(
+    file_id: web::Path<uuid::Uuid>,
+    data: web::Json<ReprocessFileReqPayload>,
+    pool: web::Data<Pool>,
+    redis_pool: web::Data<RedisPool>,
+    _user: AdminOnly,
+    dataset_org_plan_sub: DatasetAndOrgWithSubAndPlan,
+    broccoli_queue: web::Data<BroccoliQueue>,
+) -> Result<HttpResponse, actix_web::Error> {
+    data.validate()?;
+
+    let file = get_file_by_id_query(
+        file_id.into_inner(),
+        dataset_org_plan_sub.dataset.id,
+        pool.clone(),
+    )
+    .await?;
+
+    reserve_file_reprocess_query(file.id, redis_pool.clone()).await?;
+
+    if let Err(err) = queue_file_reprocess(
+        &file,
+        data.into_inner(),
+        &dataset_org_plan_sub,
+        pool,
+        &broccoli_queue,
+    )
+    .await
+    {
+        release_file_reprocess_query(file.id, redis_pool).await?;
+        return Err(err.into());
+    }
+
+    Ok(HttpResponse::Ok().json(UploadFileResponseBody {
+        file_metadata: file,
+    }))
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+pub struct GetFileVersionsResponseBody {
+    /// The file as of its current version.
+    pub file: File,
+    /// The previous versions of the file, newest first.
+    pub versions: Vec<FileVersion>,
+}
+
+/// Get File Versions
+///
+/// Get the previous versions of a file. Uploading a new version of a file or restoring a version keeps the contents it replaces as a previous version. Auth'ed user or api key must have a read role for the specified dataset's organization.
+#[utoipa::path(
+    get,
+    path = "/file/{file_id}/versions",
+    context_path = "/api",
+    tag = "File",
+    responses(
+        (status = 200, description = "The file and its previous versions", body = GetFileVersionsResponseBody),
+        (status = 400, description = "Service error relating to getting the file versions", body = ErrorResponseBody),
+        (status = 404, description = "File not found", body = ErrorResponseBody),
+    ),
+    params(
+        ("TR-Dataset" = uuid::Uuid, Header, description = "The dataset id or tracking_id to use for the request. We assume you intend to use an id if the value is a valid uuid."),
+        ("file_id" = uuid::Uuid, description = "The id of the file to get the versions of"),
+    ),
+    security(
+        ("ApiKey" = ["readonly"]),
+    )
+)]
+pub async fn get_file_versions_handler(
+    file_id: web::Path<uuid::Uuid>,
+    pool: web::Data<Pool>,
+    _user: LoggedUser,
+    dataset_org_plan_sub: DatasetAndOrgWithSubAndPlan,
+) -> Result<HttpResponse, actix_web::Error> {
+    let file_id = file_id.into_inner();
+    let dataset_id = dataset_org_plan_sub.dataset.id;
+
+    let file = get_file_by_id_query(file_id, dataset_id, pool.clone()).await?;
+    let versions = get_file_versions_query(file_id, dataset_id, pool).await?;
+
+    Ok(HttpResponse::Ok().json(GetFileVersionsResponseBody { file, versions }))
+}
+
+/// Restore File Version
+///
+/// Restore a previous version of a file. The contents and details of the version become a new version of the file, keeping the current contents as a previous version, and the file is reprocessed with the given chunking options such that only the chunks whose content changed are replaced. Auth'ed user or api key must have an admin or owner role for the specified dataset's organization.
+#[utoipa::path(
+    post,
+    path = "/file/{file_id}/versions/{version}/restore",
+    context_path = "/api",
+    tag = "File",
+    request_body(content = ReprocessFileReqPayload, description = "JSON request payload with the chunking options to reprocess the restored file with", content_type = "application/json"),
+    responses(
+        (status = 200, description = "The file as of the restored version", body = UploadFileResponseBody),
+        (status = 400, description = "Service error relating to restoring the file version, or the file is still being processed", body = ErrorResponseBody),
+        (status = 404, description = "File or version not found", body = ErrorResponseBody),
+    ),
+    params(
+        ("TR-Dataset" = uuid::Uuid, Header, description = "The dataset id or tracking_id to use for the request. We assume you intend to use an id if the value is a valid uuid."),
+        ("file_id" = uuid::Uuid, description = "The id of the file to restore a version of"),
+        ("version" = i32, description = "The version of the file to restore"),
+    ),
+    security(
+        ("ApiKey" = ["admin"]),
+    )
+)]
+pub async fn restore_file_version_handler(
+    path: web::Path<(uuid::Uuid, i32)>,
+    data: web::Json<ReprocessFileReqPayload>,
+    pool: web::Data<Pool>,
+    redis_pool: web::Data<RedisPool>,
+    _user: AdminOnly,
+    dataset_org_plan_sub: DatasetAndOrgWithSubAndPlan,
+    broccoli_queue: web::Data<BroccoliQueue>,
+) -> Result<HttpResponse, actix_web::Error> {
+    data.validate()?;
+
+    let (file_id, version) = path.into_inner();
+
+    reserve_file_reprocess_query(file_id, redis_pool.clone()).await?;
+
+    let restored_file = async {
+        let file = restore_file_version_query(
+            file_id,
+            version,
             dataset_org_plan_sub.dataset.id,
-        ),
-    };
+            pool.clone(),
+        )
+        .await?;
 
-    Ok(HttpResponse::Ok().json(result))
+        queue_file_reprocess(
+            &file,
+            data.into_inner(),
+            &dataset_org_plan_sub,
+            pool,
+            &broccoli_queue,
+        )
+        .await?;
+
+        Ok::<File, ServiceError>(file)
+    }
+    .await;
+
+    match restored_file {
+        Ok(file) => Ok(HttpResponse::Ok().json(UploadFileResponseBody {
+            file_metadata: file,
+        })),
+        Err(err) => {
+            release_file_reprocess_query(file_id, redis_pool).await?;
+            Err(err.into())
+        }
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
@@ -713,11 +1104,13 @@ pub struct UploadHtmlPageReqPayload {
     pub data: Document,
     pub metadata: serde_json::Value,
     pub scrape_id: uuid::Uuid,
+    /// Chunking strategy used to split the page. If not specified, the page is chunked by its headings.
+    pub chunking_strategy: Option<ChunkingStrategy>,
 }
 
 /// Upload HTML Page
 ///
-/// Chunk HTML by headings and queue for indexing into the specified dataset.
+/// Chunk HTML by headings, or with the specified chunking strategy, and queue for indexing into the specified dataset.
 #[utoipa::path(
     post,
     path = "/file/html_page",
@@ -792,6 +1185,7 @@ pub async fn upload_html_page(
             dataset_id,
             req_payload.scrape_id,
             req_payload.data,
+            req_payload.chunking_strategy,
             broccoli_queue,
             pool,
         )
@@ -866,8 +1260,12 @@ pub async fn get_file_handler(
     },
 }))]
 pub struct CreatePresignedUrlForCsvJsonlReqPayload {
-    /// Name of the file being uploaded, including the extension. Will be used to determine CSV or JSONL for processing.
+    /// Name of the file being uploaded, including the extension. Will be used to determine whether the file is CSV, JSONL, Parquet, XLSX or ODS if `file_format` is not specified.
     pub file_name: String,
+    /// Format of the file. Defaults to the format matching the extension of `file_name`, falling back to CSV.
+    pub file_format: Option<CsvJsonlFileFormat>,
+    /// Sheet and header row to read for XLSX and ODS files. Defaults to the first sheet with the column names in the first row.
+    pub spreadsheet_options: Option<SpreadsheetOptions>,
     /// Tag set is a comma separated list of tags which will be passed down to the chunks made from the file. Each tag will be joined with what's creatd per row of the CSV or JSONL file.
     pub tag_set: Option<Vec<String>>,
     /// Description is an optional convience field so you do not have to remember what the file contains or is about. It will be included on the group resulting from the file which will hold its chunk.
@@ -882,6 +1280,8 @@ pub struct CreatePresignedUrlForCsvJsonlReqPayload {
     pub group_tracking_id: Option<String>,
     /// Specify all of the mappings between columns or fields in a CSV or JSONL file and keys in the ChunkReqPayload. Array fields like tag_set and image_urls can have multiple mappings. Boost phrase can also have multiple mappings which get concatenated. Other fields can only have one mapping and only the last mapping will be used.
     pub mappings: Option<ChunkReqPayloadMappings>,
+    /// Rows or objects which do not satisfy all of the row filters will be skipped and no chunk will be created for them.
+    pub row_filters: Option<Vec<CsvJsonlRowFilter>>,
     /// Upsert by tracking_id. If true, chunks will be upserted by tracking_id. If false, chunks with the same tracking_id as another already existing chunk will be ignored. Defaults to true.
     pub upsert_by_tracking_id: Option<bool>,
     /// Amount to multiplicatevly increase the frequency of the tokens in the boost phrase for each row's chunk by. Applies to fulltext (SPLADE) and keyword (BM25) search.
@@ -890,6 +1290,27 @@ pub struct CreatePresignedUrlForCsvJsonlReqPayload {
     pub semantic_boost_factor: Option<f64>,
 }
 
+impl CreatePresignedUrlForCsvJsonlReqPayload {
+    pub fn conversion_options(&self) -> CsvJsonlConversionOptions {
+        CsvJsonlConversionOptions {
+            mappings: self
+                .mappings
+                .clone()
+                .map(|mappings| mappings.0)
+                .unwrap_or_default(),
+            row_filters: self.row_filters.clone().unwrap_or_default(),
+            upsert_by_tracking_id: self.upsert_by_tracking_id,
+            fulltext_boost_factor: self.fulltext_boost_factor,
+            semantic_boost_factor: self.semantic_boost_factor,
+        }
+    }
+
+    pub fn get_file_format(&self) -> CsvJsonlFileFormat {
+        self.file_format
+            .unwrap_or_else(|| CsvJsonlFileFormat::from_file_name(&self.file_name))
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
 pub struct CreatePresignedUrlForCsvJsonResponseBody {
     /// File object information. Id, name, tag_set, etc.
@@ -900,7 +1321,7 @@ pub struct CreatePresignedUrlForCsvJsonResponseBody {
 
 /// Create Presigned CSV/JSONL S3 PUT URL
 ///
-/// This route is useful for uploading very large CSV or JSONL files. Once you have completed the upload, chunks will be automatically created from the file for each line in the CSV or JSONL file. The chunks will be indexed and searchable. Auth'ed user must be an admin or owner of the dataset's organization to upload a file.
+/// This route is useful for uploading very large CSV, JSONL, Parquet, XLSX or ODS files. Once you have completed the upload, chunks will be automatically created from the file for each line in the CSV or JSONL file, each record in the Parquet file or each row in the selected sheet of the spreadsheet. Rows which cannot be processed are reported with a `csv_jsonl_rows_failed` event. The chunks will be indexed and searchable. Auth'ed user must be an admin or owner of the dataset's organization to upload a file.
 #[utoipa::path(
     post,
     path = "/file/csv_or_jsonl",
@@ -931,6 +1352,8 @@ pub async fn create_presigned_url_for_csv_jsonl(
 
     let create_presigned_put_url_data = data.into_inner();
 
+    CsvJsonlRowConverter::new(create_presigned_put_url_data.conversion_options())?;
+
     let file_id = uuid::Uuid::new_v4();
 
     let bucket = get_csvjsonl_aws_bucket()?;
@@ -945,6 +1368,7 @@ pub async fn create_presigned_url_for_csv_jsonl(
     let message = CsvJsonlWorkerMessage {
         file_id,
         dataset_id: dataset_org_plan_sub.dataset.id,
+        file_format: create_presigned_put_url_data.get_file_format(),
         create_presigned_put_url_data: create_presigned_put_url_data.clone(),
         created_at: chrono::Utc::now().naive_utc(),
         attempt_number: 0,
@@ -981,6 +1405,154 @@ pub async fn create_presigned_url_for_csv_jsonl(
     Ok(HttpResponse::Ok().json(result))
 }
 
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+#[schema(example = json!({
+    "base64_file": "<base64_encoded_file>",
+    "mappings": [
+        {
+            "chunk_req_payload_field": "chunk_html",
+            "template": "<h1>{{ title }}</h1><p>{{ body }}</p>"
+        },
+        {
+            "csv_jsonl_field": "price",
+            "chunk_req_payload_field": "metadata",
+            "transform": { "type": "float" }
+        }
+    ],
+    "row_filters": [
+        {
+            "field": "status",
+            "operator": "eq",
+            "value": "published"
+        }
+    ],
+    "limit": 5
+}))]
+pub struct PreviewCsvJsonlReqPayload {
+    /// Base64 encoded file content. For CSV and JSONL files only the beginning of the file is needed. You must specifically use a base64url encoding.
+    pub base64_file: String,
+    /// Format of the file. Defaults to CSV, JSONL is detected automatically.
+    pub file_format: Option<CsvJsonlFileFormat>,
+    /// Sheet and header row to read for XLSX and ODS files.
+    pub spreadsheet_options: Option<SpreadsheetOptions>,
+    /// Mappings between columns or fields in the file and keys in the ChunkReqPayload. Same as the mappings for the CSV or JSONL upload route.
+    pub mappings: Option<ChunkReqPayloadMappings>,
+    /// Rows or objects which do not satisfy all of the row filters will be skipped.
+    pub row_filters: Option<Vec<CsvJsonlRowFilter>>,
+    /// Upsert by tracking_id. Defaults to true.
+    pub upsert_by_tracking_id: Option<bool>,
+    /// Amount to multiplicatevly increase the frequency of the tokens in the boost phrase by.
+    pub fulltext_boost_factor: Option<f64>,
+    /// Multiplicative factor to apply to the semantic boost phrase vector.
+    pub semantic_boost_factor: Option<f64>,
+    /// Number of chunks to generate. Defaults to 10 and cannot be greater than 100.
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+pub struct PreviewCsvJsonlResponseBody {
+    /// The chunks which would be created for the first rows or objects of the file.
+    pub chunks: Vec<ChunkReqPayload>,
+    /// Number of rows or objects read from the file, not counting the CSV header.
+    pub rows_read: usize,
+    /// Number of rows or objects which were skipped because of the row filters.
+    pub rows_filtered: usize,
+    /// Rows or objects which could not be parsed.
+    pub failed_rows: Vec<CsvJsonlRowFailure>,
+}
+
+/// Preview CSV/JSONL Chunks
+///
+/// Dry run the conversion of a CSV, JSONL, Parquet, XLSX or ODS file into chunks. Returns the ChunkReqPayloads which would be created for the first rows or objects of the file with the given mappings and row filters without creating anything. Useful for checking mappings before uploading a large file with the Create Presigned CSV/JSONL S3 PUT URL route. Auth'ed user must be an admin or owner of the dataset's organization.
+#[utoipa::path(
+    post,
+    path = "/file/csv_or_jsonl/preview",
+    context_path = "/api",
+    tag = "File",
+    request_body(content = PreviewCsvJsonlReqPayload, description = "JSON request payload to preview the chunks created from a CSV or JSONL file", content_type = "application/json"),
+    responses(
+        (status = 200, description = "Chunks which would be created from the file", body = PreviewCsvJsonlResponseBody),
+        (status = 400, description = "Service error relating to parsing the file or mappings", body = ErrorResponseBody),
+    ),
+    params(
+        ("TR-Dataset" = uuid::Uuid, Header, description = "The dataset id or tracking_id to use for the request. We assume you intend to use an id if the value is a valid uuid."),
+    ),
+    security(
+        ("ApiKey" = ["admin"]),
+    )
+)]
+#[tracing::instrument(skip_all)]
+pub async fn preview_csv_jsonl(
+    data: web::Json<PreviewCsvJsonlReqPayload>,
+    _user: AdminOnly,
+    _dataset_org_plan_sub: DatasetAndOrgWithSubAndPlan,
+) -> Result<HttpResponse, actix_web::Error> {
+    let preview_data = data.into_inner();
+    let limit = preview_data.limit.unwrap_or(10);
+    if limit > 100 {
+        return Err(
+            ServiceError::BadRequest("Limit cannot be greater than 100".to_string()).into(),
+        );
+    }
+
+    let row_converter = CsvJsonlRowConverter::new(CsvJsonlConversionOptions {
+        mappings: preview_data
+            .mappings
+            .map(|mappings| mappings.0)
+            .unwrap_or_default(),
+        row_filters: preview_data.row_filters.unwrap_or_default(),
+        upsert_by_tracking_id: preview_data.upsert_by_tracking_id,
+        fulltext_boost_factor: preview_data.fulltext_boost_factor,
+        semantic_boost_factor: preview_data.semantic_boost_factor,
+    })?;
+
+    let base64_engine = engine::GeneralPurpose::new(&alphabet::URL_SAFE, general_purpose::NO_PAD);
+    let decoded_file_data = base64_engine
+        .decode(preview_data.base64_file.trim_end_matches('='))
+        .map_err(|_e| ServiceError::BadRequest("Could not decode base64 file".to_string()))?;
+
+    let file_format = preview_data.file_format.unwrap_or_default();
+    let spreadsheet_options = preview_data.spreadsheet_options.unwrap_or_default();
+    let (chunks, rows_read, rows_filtered, failed_rows) = web::block(move || {
+        let rows = iter_file_rows(decoded_file_data, file_format, &spreadsheet_options)?;
+
+        let mut chunks = vec![];
+        let mut failed_rows = vec![];
+        let mut rows_read = 0;
+        let mut rows_filtered = 0;
+        for (row, object) in rows {
+            if chunks.len() >= limit {
+                break;
+            }
+            rows_read += 1;
+
+            let object = match object {
+                Ok(object) => object,
+                Err(error) => {
+                    failed_rows.push(CsvJsonlRowFailure { row, error });
+                    continue;
+                }
+            };
+
+            match row_converter.convert(object, None) {
+                Some(chunk) => chunks.push(chunk),
+                None => rows_filtered += 1,
+            }
+        }
+
+        Ok::<_, ServiceError>((chunks, rows_read, rows_filtered, failed_rows))
+    })
+    .await
+    .map_err(|err| ServiceError::InternalServerError(format!("Thread error {:?}", err)))??;
+
+    Ok(HttpResponse::Ok().json(PreviewCsvJsonlResponseBody {
+        chunks,
+        rows_read,
+        rows_filtered,
+        failed_rows,
+    }))
+}
+
 #[derive(Deserialize, Debug, Serialize, ToSchema)]
 pub struct DatasetFilePathParams {
     pub dataset_id: uuid::Uuid,
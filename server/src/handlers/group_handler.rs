@@ -6,24 +6,38 @@ use crate::{
     data::models::{
         escape_quotes, ChunkGroup, ChunkGroupAndFileId, ChunkGroupBookmark, ChunkMetadata,
         ChunkMetadataStringTagSet, DatasetAndOrgWithSubAndPlan, DatasetConfiguration,
-        HighlightOptions, Pool, QueryTypes, RecommendType, RecommendationEventClickhouse,
-        RecommendationStrategy, RedisPool, ScoreChunk, ScoreChunkDTO, SearchMethod,
-        SearchQueryEventClickhouse, SortOptions, TypoOptions, UnifiedId,
+        GroupAggregate, GroupAggregateFunction, HighlightOptions, Pool, QdrantSortBy, QueryTypes,
+        RecommendType, RecommendationEventClickhouse, RecommendationStrategy, RedisPool,
+        ScoreChunk, ScoreChunkDTO, SearchMethod, SearchQueryEventClickhouse, SortOptions,
+        SortOrder, TypoOptions, UnifiedId,
     },
     errors::ServiceError,
     middleware::api_version::APIVersion,
     operators::{
         chunk_operator::get_metadata_from_tracking_id_query,
         clickhouse_operator::{get_latency_from_header, ClickHouseEvent, EventQueue},
+        group_aggregate_operator::{
+            create_group_aggregate_query, delete_group_aggregate_query,
+            get_group_aggregate_by_name_query, get_group_aggregate_field_path,
+            get_group_aggregate_filter_query, get_group_aggregate_name, get_group_aggregates_query,
+            get_groups_for_dataset_by_aggregate_query, mark_group_aggregates_stale_query,
+            refresh_group_aggregate_values_query, sort_groups_by_aggregate_query,
+        },
+        group_centroid_operator::{
+            mark_dataset_group_centroids_stale_query, mark_group_centroids_stale_query,
+        },
         group_operator::*,
+        group_summary_operator::mark_group_summaries_stale_query,
         qdrant_operator::{
-            add_bookmark_to_qdrant_query, recommend_qdrant_groups_query,
-            remove_bookmark_from_qdrant_query,
+            add_bookmark_to_qdrant_query, get_group_centroid_query, recommend_qdrant_groups_query,
+            remove_bookmark_from_qdrant_query, search_group_centroids_query, GroupCentroidQuery,
         },
         search_operator::{
-            get_metadata_from_groups, hybrid_search_over_groups, parse_query, search_groups_query,
-            search_hybrid_groups, search_over_groups_query, GroupScoreChunk,
-            SearchOverGroupsQueryResult, SearchOverGroupsResults,
+            get_metadata_from_groups, get_reordered_group_candidate_count,
+            hybrid_search_over_groups, paginate_group_results, parse_query,
+            roll_up_groups_to_level, search_groups_query, search_hybrid_groups,
+            search_over_groups_query, GroupScoreChunk, SearchOverGroupsQueryResult,
+            SearchOverGroupsResults,
         },
     },
 };
@@ -31,7 +45,7 @@ use crate::{
     data::models::{MultiQuery, SearchModalities},
     operators::{
         chunk_operator::get_metadata_from_tracking_ids_query,
-        model_operator::count_tokens,
+        model_operator::{count_tokens, get_dense_vector},
         search_operator::{autocomplete_search_over_groups_query, ParsedQuery, ParsedQueryTypes},
     },
 };
@@ -87,6 +101,10 @@ pub struct CreateSingleChunkGroupReqPayload {
     pub tag_set: Option<Vec<String>>,
     /// Upsert when a chunk_group with the same tracking_id exists. By default this is false, and the request will fail if a chunk_group with the same tracking_id exists. If this is true, the chunk_group will be updated if a chunk_group with the same tracking_id exists.
     pub upsert_by_tracking_id: Option<bool>,
+    /// Optional id of an existing chunk_group to nest this chunk_group under. Only one of parent_id or parent_tracking_id can be specified.
+    pub parent_id: Option<uuid::Uuid>,
+    /// Optional tracking_id of an existing chunk_group to nest this chunk_group under. Only one of parent_id or parent_tracking_id can be specified.
+    pub parent_tracking_id: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
@@ -202,20 +220,33 @@ pub async fn create_chunk_group(
         .into());
     }
 
-    let (upsert_payloads, non_upsert_payloads) = payloads
-        .into_iter()
-        .map(|payload| {
-            let non_empty_tracking_id = payload.tracking_id.clone().filter(|id| !id.is_empty());
+    let mut payloads_with_parents = vec![];
+    for payload in payloads {
+        let non_empty_tracking_id = payload.tracking_id.clone().filter(|id| !id.is_empty());
+        let parent_id = get_parent_group_id_query(
+            payload.parent_id,
+            payload.parent_tracking_id.clone(),
+            dataset_org_plan_sub.dataset.id,
+            pool.clone(),
+        )
+        .await?;
+
+        payloads_with_parents.push((
             CreateSingleChunkGroupReqPayload {
                 tracking_id: non_empty_tracking_id,
                 ..payload
-            }
-        })
-        .partition::<Vec<_>, _>(|payload| payload.upsert_by_tracking_id.unwrap_or(false));
+            },
+            parent_id,
+        ));
+    }
+
+    let (upsert_payloads, non_upsert_payloads) = payloads_with_parents
+        .into_iter()
+        .partition::<Vec<_>, _>(|(payload, _)| payload.upsert_by_tracking_id.unwrap_or(false));
 
     let tracking_ids = upsert_payloads
         .iter()
-        .filter_map(|payload| payload.tracking_id.clone())
+        .filter_map(|(payload, _)| payload.tracking_id.clone())
         .collect::<Vec<String>>();
     if tracking_ids.len()
         != tracking_ids
@@ -229,9 +260,41 @@ pub async fn create_chunk_group(
         .into());
     }
 
+    // Upserts can re-parent existing groups, so those must not end up beneath themselves
+    let reparented_tracking_ids = upsert_payloads
+        .iter()
+        .filter(|(_, parent_id)| parent_id.is_some())
+        .filter_map(|(payload, _)| payload.tracking_id.clone())
+        .collect::<Vec<String>>();
+    if !reparented_tracking_ids.is_empty() {
+        let existing_groups = get_group_ids_from_tracking_ids_query(
+            reparented_tracking_ids,
+            dataset_org_plan_sub.dataset.id,
+            pool.clone(),
+        )
+        .await?;
+
+        for (payload, parent_id) in upsert_payloads.iter() {
+            let existing_group_id = existing_groups
+                .iter()
+                .find(|(_, tracking_id)| *tracking_id == payload.tracking_id)
+                .map(|(id, _)| *id);
+
+            if let (Some(group_id), Some(parent_id)) = (existing_group_id, parent_id) {
+                check_group_parent_query(
+                    group_id,
+                    *parent_id,
+                    dataset_org_plan_sub.dataset.id,
+                    pool.clone(),
+                )
+                .await?;
+            }
+        }
+    }
+
     let upsert_groups = upsert_payloads
         .into_iter()
-        .map(|payload| {
+        .map(|(payload, parent_id)| {
             let group_tag_set = payload.tag_set.clone().map(|tag_set| {
                 tag_set
                     .into_iter()
@@ -239,20 +302,23 @@ pub async fn create_chunk_group(
                     .collect::<Vec<Option<String>>>()
             });
 
-            ChunkGroup::from_details(
-                payload.name.clone(),
-                payload.description.clone(),
-                dataset_org_plan_sub.dataset.id,
-                payload.tracking_id.clone(),
-                payload.metadata.clone(),
-                group_tag_set,
-            )
+            ChunkGroup {
+                parent_id,
+                ..ChunkGroup::from_details(
+                    payload.name.clone(),
+                    payload.description.clone(),
+                    dataset_org_plan_sub.dataset.id,
+                    payload.tracking_id.clone(),
+                    payload.metadata.clone(),
+                    group_tag_set,
+                )
+            }
         })
         .collect::<Vec<ChunkGroup>>();
 
     let non_upsert_groups = non_upsert_payloads
         .into_iter()
-        .map(|payload| {
+        .map(|(payload, parent_id)| {
             let group_tag_set = payload.tag_set.clone().map(|tag_set| {
                 tag_set
                     .into_iter()
@@ -260,14 +326,17 @@ pub async fn create_chunk_group(
                     .collect::<Vec<Option<String>>>()
             });
 
-            ChunkGroup::from_details(
-                payload.name.clone(),
-                payload.description.clone(),
-                dataset_org_plan_sub.dataset.id,
-                payload.tracking_id.clone(),
-                payload.metadata.clone(),
-                group_tag_set,
-            )
+            ChunkGroup {
+                parent_id,
+                ..ChunkGroup::from_details(
+                    payload.name.clone(),
+                    payload.description.clone(),
+                    dataset_org_plan_sub.dataset.id,
+                    payload.tracking_id.clone(),
+                    payload.metadata.clone(),
+                    group_tag_set,
+                )
+            }
         })
         .collect::<Vec<ChunkGroup>>();
 
@@ -306,6 +375,12 @@ pub struct DatasetGroupCursorQuery {
     /// Group ids are compared to the cursor using a greater than or equal to. This is used to paginate through files.
     pub cursor: Option<uuid::Uuid>,
     pub use_cursor: Option<bool>,
+    /// Name of the group aggregate to sort the groups by. Not supported in cursor mode.
+    pub sort_by: Option<String>,
+    /// Direction to sort the groups by `sort_by` in. Defaults to desc.
+    pub sort_order: Option<SortOrder>,
+    /// JSON encoded ChunkFilter over `group_aggregates.<name>` fields to restrict the groups to. Not supported in cursor mode.
+    pub aggregate_filters: Option<String>,
 }
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -331,7 +406,10 @@ pub struct DatasetGroupQuery {
         ("dataset_id" = uuid::Uuid, description = "The id of the dataset to fetch groups for."),
         ("page" = Option<i64>, description = "The page of groups to fetch. Page is 1-indexed. Only used if `use_cursor` = `false`."),
         ("use_cursor" = Option<bool>, Query, description = "Flag to enable `cursor` mode, this runs faster for large scroll operations. Defaults to false"),
-        ("cursor" = Option<uuid::Uuid>, Query, description = "The cursor offset for. Requires `use_cursor` = True. Defaults to `00000000-00000000-00000000-00000000`. Group ids are compared to the cursor using a greater than or equal to.")
+        ("cursor" = Option<uuid::Uuid>, Query, description = "The cursor offset for. Requires `use_cursor` = True. Defaults to `00000000-00000000-00000000-00000000`. Group ids are compared to the cursor using a greater than or equal to."),
+        ("sort_by" = Option<String>, Query, description = "Name of the group aggregate to sort the groups by, groups without a value last. Only used if `use_cursor` = `false`."),
+        ("sort_order" = Option<SortOrder>, Query, description = "Direction to sort the groups by `sort_by` in. Defaults to desc."),
+        ("aggregate_filters" = Option<String>, Query, description = "JSON encoded ChunkFilter whose conditions reference group aggregates as `group_aggregates.<name>`. Only groups matching the filter are returned. Only used if `use_cursor` = `false`.")
     ),
     security(
         ("ApiKey" = ["readonly"]),
@@ -360,6 +438,52 @@ pub async fn get_groups_for_dataset(
                 next_cursor,
             }))
         }
+        _ if query_params.sort_by.is_some() || query_params.aggregate_filters.is_some() => {
+            let dataset_id = dataset_org_plan_sub.dataset.id;
+
+            let sort_by = match query_params.sort_by.as_deref() {
+                Some(name) => Some((
+                    get_group_aggregate_by_name_query(name, dataset_id, pool.clone())
+                        .await?
+                        .id,
+                    query_params.sort_order.clone().unwrap_or(SortOrder::Desc),
+                )),
+                None => None,
+            };
+
+            let filter = match query_params.aggregate_filters.as_deref() {
+                Some(aggregate_filters) => {
+                    let chunk_filter = serde_json::from_str::<ChunkFilter>(aggregate_filters)
+                        .map_err(|err| {
+                            ServiceError::BadRequest(format!(
+                                "aggregate_filters is not a valid ChunkFilter: {}",
+                                err
+                            ))
+                        })?;
+                    Some(
+                        get_group_aggregate_filter_query(chunk_filter, dataset_id, pool.clone())
+                            .await?,
+                    )
+                }
+                None => None,
+            };
+
+            let (groups, group_count) = get_groups_for_dataset_by_aggregate_query(
+                dataset_id,
+                dataset_and_page.page.unwrap_or(1),
+                sort_by,
+                filter,
+                pool,
+            )
+            .await?;
+            let pages = (group_count as u32).div_ceil(10);
+
+            Ok(HttpResponse::Ok().json(GroupData {
+                groups,
+                total_pages: pages,
+                next_cursor: None,
+            }))
+        }
         _ => {
             let (groups, group_count) = get_groups_for_dataset_page_query(
                 dataset_and_page.page.unwrap_or(1),
@@ -464,6 +588,598 @@ pub async fn get_chunk_group(
     Ok(HttpResponse::Ok().json(group))
 }
 
+#[derive(Debug, Deserialize, Serialize, ToSchema, IntoParams)]
+#[into_params(style = Form, parameter_in = Query)]
+pub struct GetGroupChildrenQuery {
+    /// The page of child groups to fetch. Page is 1-indexed. Defaults to 1.
+    pub page: Option<u64>,
+    /// The number of child groups to fetch per page. Defaults to 10 and cannot exceed 100.
+    pub page_size: Option<u64>,
+}
+
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+pub struct GetGroupChildrenResponse {
+    /// The groups nested directly beneath the group, oldest first.
+    pub children: Vec<ChunkGroupAndFileId>,
+    /// Total number of pages of child groups.
+    pub total_pages: u64,
+}
+
+/// Get Group Children
+///
+/// Fetch the groups nested directly beneath the group with the given id. Children of children are not included; call this route on a child to walk further down the hierarchy.
+#[utoipa::path(
+    get,
+    path = "/chunk_group/{group_id}/children",
+    context_path = "/api",
+    tag = "Chunk Group",
+    responses(
+        (status = 200, description = "JSON body representing the groups nested directly beneath the group", body = GetGroupChildrenResponse),
+        (status = 400, description = "Service error relating to getting the children of the group", body = ErrorResponseBody),
+        (status = 404, description = "Group not found", body = ErrorResponseBody)
+    ),
+    params(
+        ("TR-Dataset" = uuid::Uuid, Header, description = "The dataset id or tracking_id to use for the request. We assume you intend to use an id if the value is a valid uuid."),
+        ("group_id" = uuid::Uuid, Path, description = "Id of the group whose children you want to fetch."),
+        GetGroupChildrenQuery,
+    ),
+    security(
+        ("ApiKey" = ["readonly"]),
+    )
+)]
+pub async fn get_group_children(
+    group_id: web::Path<uuid::Uuid>,
+    query: web::Query<GetGroupChildrenQuery>,
+    dataset_org_plan_sub: DatasetAndOrgWithSubAndPlan,
+    _user: LoggedUser,
+    pool: web::Data<Pool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let group_id = group_id.into_inner();
+    let page = query.page.unwrap_or(1);
+    let page_size = query.page_size.unwrap_or(10);
+    if page_size == 0 || page_size > 100 {
+        return Err(ServiceError::BadRequest("page_size must be between 1 and 100".into()).into());
+    }
+
+    let group = dataset_owns_group(
+        UnifiedId::TrieveUuid(group_id),
+        dataset_org_plan_sub.dataset.id,
+        pool.clone(),
+    )
+    .await?;
+
+    let (children, children_count) = get_group_children_query(
+        group.id,
+        dataset_org_plan_sub.dataset.id,
+        page,
+        page_size,
+        pool,
+    )
+    .await?;
+
+    Ok(HttpResponse::Ok().json(GetGroupChildrenResponse {
+        children,
+        total_pages: (children_count as u64).div_ceil(page_size),
+    }))
+}
+
+/// Get Group Ancestors
+///
+/// Fetch the groups which the group with the given id is nested beneath, ordered from its direct parent up to the top-level group. Top-level groups have no ancestors.
+#[utoipa::path(
+    get,
+    path = "/chunk_group/{group_id}/ancestors",
+    context_path = "/api",
+    tag = "Chunk Group",
+    responses(
+        (status = 200, description = "JSON body representing the ancestors of the group, from its parent up to the top-level group", body = Vec<ChunkGroupAndFileId>),
+        (status = 400, description = "Service error relating to getting the ancestors of the group", body = ErrorResponseBody),
+        (status = 404, description = "Group not found", body = ErrorResponseBody)
+    ),
+    params(
+        ("TR-Dataset" = uuid::Uuid, Header, description = "The dataset id or tracking_id to use for the request. We assume you intend to use an id if the value is a valid uuid."),
+        ("group_id" = uuid::Uuid, Path, description = "Id of the group whose ancestors you want to fetch."),
+    ),
+    security(
+        ("ApiKey" = ["readonly"]),
+    )
+)]
+pub async fn get_group_ancestors(
+    group_id: web::Path<uuid::Uuid>,
+    dataset_org_plan_sub: DatasetAndOrgWithSubAndPlan,
+    _user: LoggedUser,
+    pool: web::Data<Pool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let group = dataset_owns_group(
+        UnifiedId::TrieveUuid(group_id.into_inner()),
+        dataset_org_plan_sub.dataset.id,
+        pool.clone(),
+    )
+    .await?;
+
+    let ancestors =
+        get_group_ancestors_query(group.id, dataset_org_plan_sub.dataset.id, pool).await?;
+
+    Ok(HttpResponse::Ok().json(ancestors))
+}
+
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+#[schema(example = json!({
+    "name": "max_price",
+    "field": "metadata.price",
+    "aggregation": "max",
+}))]
+pub struct CreateGroupAggregateReqPayload {
+    /// Name of the aggregate. Filters and sorts reference it as `group_aggregates.<name>`. May only contain letters, numbers and underscores.
+    pub name: String,
+    /// The field of the member chunks to aggregate. Either `num_value` or a `metadata.` prefixed key such as `metadata.price`.
+    pub field: String,
+    /// How the field is aggregated over the chunks in each group. `any` is 1 if any chunk's field is truthy and 0 otherwise.
+    pub aggregation: GroupAggregateFunction,
+}
+
+/// Create Group Aggregate
+///
+/// Define a value which is computed for every group in the dataset from the chunks it contains. Aggregates are kept up to date as chunks are added to, removed from or updated in groups and can be used to sort and filter groups in group search and when listing the groups of a dataset. Auth'ed user or api key must have an admin or owner role for the specified dataset's organization.
+#[utoipa::path(
+    post,
+    path = "/chunk_group/aggregates",
+    context_path = "/api",
+    tag = "Chunk Group",
+    request_body(content = CreateGroupAggregateReqPayload, description = "JSON request payload to create a group aggregate", content_type = "application/json"),
+    responses(
+        (status = 200, description = "JSON body representing the created group aggregate", body = GroupAggregate),
+        (status = 400, description = "Service error relating to creating the group aggregate", body = ErrorResponseBody),
+    ),
+    params(
+        ("TR-Dataset" = uuid::Uuid, Header, description = "The dataset id or tracking_id to use for the request. We assume you intend to use an id if the value is a valid uuid."),
+    ),
+    security(
+        ("ApiKey" = ["admin"]),
+    )
+)]
+pub async fn create_group_aggregate(
+    data: web::Json<CreateGroupAggregateReqPayload>,
+    dataset_org_plan_sub: DatasetAndOrgWithSubAndPlan,
+    _user: AdminOnly,
+    pool: web::Data<Pool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let data = data.into_inner();
+    let dataset_id = dataset_org_plan_sub.dataset.id;
+
+    if data.name.is_empty()
+        || !data
+            .name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_')
+    {
+        return Err(ServiceError::BadRequest(
+            "Group aggregate name must be non-empty and may only contain letters, numbers and underscores".to_string(),
+        )
+        .into());
+    }
+
+    get_group_aggregate_field_path(&data.field)?;
+
+    let group_aggregate = create_group_aggregate_query(
+        GroupAggregate::from_details(dataset_id, data.name, data.field, data.aggregation),
+        pool.clone(),
+    )
+    .await?;
+
+    refresh_group_aggregate_values_query(vec![group_aggregate.clone()], dataset_id, None, pool)
+        .await?;
+
+    Ok(HttpResponse::Ok().json(group_aggregate))
+}
+
+/// Get Group Aggregates
+///
+/// Fetch the group aggregates defined for the dataset.
+#[utoipa::path(
+    get,
+    path = "/chunk_group/aggregates",
+    context_path = "/api",
+    tag = "Chunk Group",
+    responses(
+        (status = 200, description = "JSON body representing the group aggregates of the dataset", body = Vec<GroupAggregate>),
+        (status = 400, description = "Service error relating to getting the group aggregates", body = ErrorResponseBody),
+    ),
+    params(
+        ("TR-Dataset" = uuid::Uuid, Header, description = "The dataset id or tracking_id to use for the request. We assume you intend to use an id if the value is a valid uuid."),
+    ),
+    security(
+        ("ApiKey" = ["readonly"]),
+    )
+)]
+pub async fn get_group_aggregates(
+    dataset_org_plan_sub: DatasetAndOrgWithSubAndPlan,
+    _user: LoggedUser,
+    pool: web::Data<Pool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let group_aggregates =
+        get_group_aggregates_query(dataset_org_plan_sub.dataset.id, pool).await?;
+
+    Ok(HttpResponse::Ok().json(group_aggregates))
+}
+
+/// Delete Group Aggregate
+///
+/// Delete a group aggregate and its values for every group. Filters and sorts which reference it will no longer match. Auth'ed user or api key must have an admin or owner role for the specified dataset's organization.
+#[utoipa::path(
+    delete,
+    path = "/chunk_group/aggregates/{aggregate_id}",
+    context_path = "/api",
+    tag = "Chunk Group",
+    responses(
+        (status = 204, description = "Confirmation that the group aggregate was deleted"),
+        (status = 400, description = "Service error relating to deleting the group aggregate", body = ErrorResponseBody),
+        (status = 404, description = "Group aggregate not found", body = ErrorResponseBody),
+    ),
+    params(
+        ("TR-Dataset" = uuid::Uuid, Header, description = "The dataset id or tracking_id to use for the request. We assume you intend to use an id if the value is a valid uuid."),
+        ("aggregate_id" = uuid::Uuid, Path, description = "Id of the group aggregate to delete."),
+    ),
+    security(
+        ("ApiKey" = ["admin"]),
+    )
+)]
+pub async fn delete_group_aggregate(
+    aggregate_id: web::Path<uuid::Uuid>,
+    dataset_org_plan_sub: DatasetAndOrgWithSubAndPlan,
+    _user: AdminOnly,
+    pool: web::Data<Pool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    delete_group_aggregate_query(
+        aggregate_id.into_inner(),
+        dataset_org_plan_sub.dataset.id,
+        pool,
+    )
+    .await?;
+
+    Ok(HttpResponse::NoContent().finish())
+}
+
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+pub struct GroupCentroid {
+    /// Id of the group the centroid belongs to
+    pub group_id: uuid::Uuid,
+    /// Mean of the dense vectors of the chunks in the group
+    pub vector: Vec<f32>,
+    /// Number of chunks the centroid was computed from
+    pub member_count: u64,
+}
+
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+pub struct GroupCentroidScore {
+    pub group: ChunkGroupAndFileId,
+    /// Similarity of the group's centroid to the query in the dataset's distance metric
+    pub score: f32,
+}
+
+fn check_group_centroids_enabled(
+    dataset_config: &DatasetConfiguration,
+) -> Result<(), ServiceError> {
+    if dataset_config.GROUP_CENTROIDS.is_none() {
+        return Err(ServiceError::BadRequest(
+            "Group centroids are not enabled for this dataset. Set GROUP_CENTROIDS in the dataset's server_configuration to enable them.".to_string(),
+        ));
+    }
+
+    Ok(())
+}
+
+/// Attaches the groups to centroid search results, dropping groups which no longer exist
+async fn get_group_centroid_scores(
+    nearest_groups: Vec<(uuid::Uuid, f32)>,
+    dataset_id: uuid::Uuid,
+    pool: web::Data<Pool>,
+) -> Result<Vec<GroupCentroidScore>, ServiceError> {
+    let groups = get_groups_from_group_ids_query(
+        nearest_groups
+            .iter()
+            .map(|(group_id, _)| *group_id)
+            .collect(),
+        pool,
+    )
+    .await?;
+
+    Ok(nearest_groups
+        .into_iter()
+        .filter_map(|(group_id, score)| {
+            let group = groups
+                .iter()
+                .find(|group| group.id == group_id && group.dataset_id == dataset_id)?;
+            Some(GroupCentroidScore {
+                group: group.clone(),
+                score,
+            })
+        })
+        .collect())
+}
+
+/// Get Group Centroid
+///
+/// Fetch the centroid of a group, which is the mean of the dense vectors of the chunks in it. Centroids are maintained by the group update worker when GROUP_CENTROIDS is set in the dataset's server_configuration, so they may briefly lag behind changes to the group's chunks.
+#[utoipa::path(
+    get,
+    path = "/chunk_group/{group_id}/centroid",
+    context_path = "/api",
+    tag = "Chunk Group",
+    responses(
+        (status = 200, description = "JSON body representing the centroid of the group", body = GroupCentroid),
+        (status = 400, description = "Service error relating to getting the centroid of the group", body = ErrorResponseBody),
+        (status = 404, description = "Group or centroid not found", body = ErrorResponseBody)
+    ),
+    params(
+        ("TR-Dataset" = uuid::Uuid, Header, description = "The dataset id or tracking_id to use for the request. We assume you intend to use an id if the value is a valid uuid."),
+        ("group_id" = uuid::Uuid, Path, description = "Id of the group whose centroid you want to fetch."),
+    ),
+    security(
+        ("ApiKey" = ["readonly"]),
+    )
+)]
+pub async fn get_group_centroid(
+    group_id: web::Path<uuid::Uuid>,
+    dataset_org_plan_sub: DatasetAndOrgWithSubAndPlan,
+    _user: LoggedUser,
+    pool: web::Data<Pool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let dataset_config =
+        DatasetConfiguration::from_json(dataset_org_plan_sub.dataset.server_configuration.clone());
+    check_group_centroids_enabled(&dataset_config)?;
+
+    let group = dataset_owns_group(
+        UnifiedId::TrieveUuid(group_id.into_inner()),
+        dataset_org_plan_sub.dataset.id,
+        pool,
+    )
+    .await?;
+
+    let (vector, member_count) = get_group_centroid_query(group.id, &dataset_config)
+        .await?
+        .ok_or(ServiceError::NotFound(
+            "Group has no centroid yet. Groups without chunks have no centroid.".to_string(),
+        ))?;
+
+    Ok(HttpResponse::Ok().json(GroupCentroid {
+        group_id: group.id,
+        vector,
+        member_count,
+    }))
+}
+
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+#[schema(example = json!({
+    "limit": 10,
+    "score_threshold": 0.5,
+    "tag_set": ["tag1"],
+}))]
+pub struct GetSimilarGroupsReqPayload {
+    /// Number of similar groups to return. Defaults to 10.
+    pub limit: Option<u64>,
+    /// Only return groups whose centroids score at least this high against the group's centroid.
+    pub score_threshold: Option<f32>,
+    /// Only return groups with at least one of these tags.
+    pub tag_set: Option<Vec<String>>,
+}
+
+/// Get Similar Groups
+///
+/// Fetch the groups whose centroids are nearest to the centroid of the given group. Requires GROUP_CENTROIDS to be set in the dataset's server_configuration.
+#[utoipa::path(
+    post,
+    path = "/chunk_group/{group_id}/similar",
+    context_path = "/api",
+    tag = "Chunk Group",
+    request_body(content = GetSimilarGroupsReqPayload, description = "JSON request payload to get groups similar to the given group", content_type = "application/json"),
+    responses(
+        (status = 200, description = "JSON body representing the groups most similar to the given group", body = Vec<GroupCentroidScore>),
+        (status = 400, description = "Service error relating to getting similar groups", body = ErrorResponseBody),
+        (status = 404, description = "Group not found", body = ErrorResponseBody)
+    ),
+    params(
+        ("TR-Dataset" = uuid::Uuid, Header, description = "The dataset id or tracking_id to use for the request. We assume you intend to use an id if the value is a valid uuid."),
+        ("group_id" = uuid::Uuid, Path, description = "Id of the group to find similar groups for."),
+    ),
+    security(
+        ("ApiKey" = ["readonly"]),
+    )
+)]
+pub async fn get_similar_groups(
+    group_id: web::Path<uuid::Uuid>,
+    data: web::Json<GetSimilarGroupsReqPayload>,
+    dataset_org_plan_sub: DatasetAndOrgWithSubAndPlan,
+    _user: LoggedUser,
+    pool: web::Data<Pool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let data = data.into_inner();
+    let dataset_id = dataset_org_plan_sub.dataset.id;
+    let dataset_config =
+        DatasetConfiguration::from_json(dataset_org_plan_sub.dataset.server_configuration.clone());
+    check_group_centroids_enabled(&dataset_config)?;
+
+    let group = dataset_owns_group(
+        UnifiedId::TrieveUuid(group_id.into_inner()),
+        dataset_id,
+        pool.clone(),
+    )
+    .await?;
+
+    let nearest_groups = search_group_centroids_query(
+        GroupCentroidQuery::Group(group.id),
+        data.tag_set,
+        data.limit.unwrap_or(10),
+        data.score_threshold,
+        dataset_id,
+        &dataset_config,
+    )
+    .await?;
+
+    Ok(HttpResponse::Ok().json(get_group_centroid_scores(nearest_groups, dataset_id, pool).await?))
+}
+
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+#[schema(example = json!({
+    "query": "Some search query",
+    "limit": 10,
+    "score_threshold": 0.5,
+    "tag_set": ["tag1"],
+}))]
+pub struct SearchGroupCentroidsReqPayload {
+    /// Query to embed and compare against the centroids of the dataset's groups.
+    pub query: String,
+    /// Number of groups to return. Defaults to 10.
+    pub limit: Option<u64>,
+    /// Only return groups whose centroids score at least this high against the query.
+    pub score_threshold: Option<f32>,
+    /// Only return groups with at least one of these tags.
+    pub tag_set: Option<Vec<String>>,
+}
+
+/// Search Group Centroids
+///
+/// Semantic search over the groups of a dataset by comparing the query's embedding to each group's centroid. Unlike searching over groups, this ranks groups as a whole and does not fetch the chunks in them. Requires GROUP_CENTROIDS to be set in the dataset's server_configuration.
+#[utoipa::path(
+    post,
+    path = "/chunk_group/centroid_search",
+    context_path = "/api",
+    tag = "Chunk Group",
+    request_body(content = SearchGroupCentroidsReqPayload, description = "JSON request payload to search the centroids of the dataset's groups", content_type = "application/json"),
+    responses(
+        (status = 200, description = "JSON body representing the groups whose centroids are nearest to the query", body = Vec<GroupCentroidScore>),
+        (status = 400, description = "Service error relating to searching group centroids", body = ErrorResponseBody),
+    ),
+    params(
+        ("TR-Dataset" = uuid::Uuid, Header, description = "The dataset id or tracking_id to use for the request. We assume you intend to use an id if the value is a valid uuid."),
+    ),
+    security(
+        ("ApiKey" = ["readonly"]),
+    )
+)]
+pub async fn search_group_centroids(
+    data: web::Json<SearchGroupCentroidsReqPayload>,
+    dataset_org_plan_sub: DatasetAndOrgWithSubAndPlan,
+    _user: LoggedUser,
+    pool: web::Data<Pool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let data = data.into_inner();
+    let dataset_id = dataset_org_plan_sub.dataset.id;
+    let dataset_config =
+        DatasetConfiguration::from_json(dataset_org_plan_sub.dataset.server_configuration.clone());
+    check_group_centroids_enabled(&dataset_config)?;
+
+    if data.query.trim().is_empty() {
+        return Err(ServiceError::BadRequest("Query cannot be empty".to_string()).into());
+    }
+
+    let query_vector = get_dense_vector(data.query, None, "query", dataset_config.clone()).await?;
+
+    let nearest_groups = search_group_centroids_query(
+        GroupCentroidQuery::Vector(query_vector),
+        data.tag_set,
+        data.limit.unwrap_or(10),
+        data.score_threshold,
+        dataset_id,
+        &dataset_config,
+    )
+    .await?;
+
+    Ok(HttpResponse::Ok().json(get_group_centroid_scores(nearest_groups, dataset_id, pool).await?))
+}
+
+/// Rebuild Group Centroids
+///
+/// Queue the centroid of every group in the dataset to be recomputed by the group update worker. Centroids are kept up to date automatically, but this is useful after bulk deletes or after the dataset is reindexed with a different embedding model. Auth'ed user or api key must have an admin or owner role for the specified dataset's organization.
+#[utoipa::path(
+    post,
+    path = "/chunk_group/centroids/rebuild",
+    context_path = "/api",
+    tag = "Chunk Group",
+    responses(
+        (status = 204, description = "Confirmation that the centroids of the dataset's groups were queued to be rebuilt"),
+        (status = 400, description = "Service error relating to rebuilding group centroids", body = ErrorResponseBody),
+    ),
+    params(
+        ("TR-Dataset" = uuid::Uuid, Header, description = "The dataset id or tracking_id to use for the request. We assume you intend to use an id if the value is a valid uuid."),
+    ),
+    security(
+        ("ApiKey" = ["admin"]),
+    )
+)]
+pub async fn rebuild_group_centroids(
+    dataset_org_plan_sub: DatasetAndOrgWithSubAndPlan,
+    _user: AdminOnly,
+    pool: web::Data<Pool>,
+    redis_pool: web::Data<RedisPool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let dataset_config =
+        DatasetConfiguration::from_json(dataset_org_plan_sub.dataset.server_configuration.clone());
+    check_group_centroids_enabled(&dataset_config)?;
+
+    mark_dataset_group_centroids_stale_query(
+        &dataset_config,
+        dataset_org_plan_sub.dataset.id,
+        pool,
+        redis_pool,
+    )
+    .await?;
+
+    Ok(HttpResponse::NoContent().finish())
+}
+
+/// Regenerate Group Summary
+///
+/// Queue the summary of a group to be regenerated by the group update worker. Summaries are regenerated automatically when chunks are added to or removed from the group, but this is useful for groups created before GROUP_SUMMARIES was set or after changing the summary prompt. Auth'ed user or api key must have an admin or owner role for the specified dataset's organization.
+#[utoipa::path(
+    post,
+    path = "/chunk_group/{group_id}/summary",
+    context_path = "/api",
+    tag = "Chunk Group",
+    responses(
+        (status = 204, description = "Confirmation that the group's summary was queued to be regenerated"),
+        (status = 400, description = "Service error relating to regenerating the group's summary", body = ErrorResponseBody),
+        (status = 404, description = "Group not found", body = ErrorResponseBody)
+    ),
+    params(
+        ("TR-Dataset" = uuid::Uuid, Header, description = "The dataset id or tracking_id to use for the request. We assume you intend to use an id if the value is a valid uuid."),
+        ("group_id" = uuid::Uuid, Path, description = "Id of the group whose summary you want to regenerate."),
+    ),
+    security(
+        ("ApiKey" = ["admin"]),
+    )
+)]
+pub async fn regenerate_group_summary(
+    group_id: web::Path<uuid::Uuid>,
+    dataset_org_plan_sub: DatasetAndOrgWithSubAndPlan,
+    _user: AdminOnly,
+    pool: web::Data<Pool>,
+    redis_pool: web::Data<RedisPool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let dataset_id = dataset_org_plan_sub.dataset.id;
+    let dataset_config =
+        DatasetConfiguration::from_json(dataset_org_plan_sub.dataset.server_configuration.clone());
+
+    if dataset_config.GROUP_SUMMARIES.is_none() {
+        return Err(ServiceError::BadRequest(
+            "Group summaries are not enabled for this dataset. Set GROUP_SUMMARIES in the dataset's server_configuration to enable them.".to_string(),
+        )
+        .into());
+    }
+
+    let group = dataset_owns_group(
+        UnifiedId::TrieveUuid(group_id.into_inner()),
+        dataset_id,
+        pool,
+    )
+    .await?;
+
+    mark_group_summaries_stale_query(&dataset_config, dataset_id, vec![group.id], redis_pool)
+        .await?;
+
+    Ok(HttpResponse::NoContent().finish())
+}
+
 #[derive(Debug, Deserialize, Serialize, ToSchema)]
 pub struct GetChunkGroupCountRequest {
     /// The Id of the group to get the count for, is not required if group_tracking_id is provided.
@@ -756,6 +1472,12 @@ pub struct UpdateChunkGroupReqPayload {
     /// Flag to update the chunks in the group. If true, each chunk in the group will be updated
     /// by appending the group's tags to the chunk's tags. Default is false.
     pub update_chunks: Option<bool>,
+    /// Id of the chunk_group to nest this chunk_group under. The parent cannot be this chunk_group or one of its descendants. If not provided, the parent will not be updated.
+    pub parent_id: Option<uuid::Uuid>,
+    /// Tracking id of the chunk_group to nest this chunk_group under. Only one of parent_id or parent_tracking_id can be specified.
+    pub parent_tracking_id: Option<String>,
+    /// Set to true to make this chunk_group a top-level group without a parent. Cannot be combined with parent_id or parent_tracking_id.
+    pub remove_parent: Option<bool>,
 }
 
 /// Update Group
@@ -815,15 +1537,46 @@ pub async fn update_chunk_group(
         return Err(ServiceError::BadRequest("No group id or tracking id provided".into()).into());
     };
 
-    let new_chunk_group = ChunkGroup::from_details_with_id(
-        group.id,
-        name.unwrap_or(group.name.clone()),
-        description.or(Some(group.description.clone())),
+    let new_parent_id = get_parent_group_id_query(
+        data.parent_id,
+        data.parent_tracking_id.clone(),
         dataset_org_plan_sub.dataset.id,
-        data.tracking_id.clone(),
-        data.metadata.clone(),
-        group_tag_set.or(group.tag_set.clone()),
-    );
+        pool.clone(),
+    )
+    .await?;
+    let parent_id = match (new_parent_id, data.remove_parent.unwrap_or(false)) {
+        (Some(_), true) => {
+            return Err(ServiceError::BadRequest(
+                "remove_parent cannot be combined with parent_id or parent_tracking_id".into(),
+            )
+            .into());
+        }
+        (Some(parent_id), false) => {
+            check_group_parent_query(
+                group.id,
+                parent_id,
+                dataset_org_plan_sub.dataset.id,
+                pool.clone(),
+            )
+            .await?;
+            Some(parent_id)
+        }
+        (None, true) => None,
+        (None, false) => group.parent_id,
+    };
+
+    let new_chunk_group = ChunkGroup {
+        parent_id,
+        ..ChunkGroup::from_details_with_id(
+            group.id,
+            name.unwrap_or(group.name.clone()),
+            description.or(Some(group.description.clone())),
+            dataset_org_plan_sub.dataset.id,
+            data.tracking_id.clone(),
+            data.metadata.clone(),
+            group_tag_set.or(group.tag_set.clone()),
+        )
+    };
 
     update_chunk_group_query(new_chunk_group.clone(), pool).await?;
 
@@ -874,6 +1627,7 @@ pub async fn add_chunk_to_group(
     group_id: web::Path<uuid::Uuid>,
     dataset_org_plan_sub: DatasetAndOrgWithSubAndPlan,
     pool: web::Data<Pool>,
+    redis_pool: web::Data<RedisPool>,
     _user: AdminOnly,
 ) -> Result<HttpResponse, actix_web::Error> {
     let group_id = group_id.into_inner();
@@ -897,7 +1651,18 @@ pub async fn add_chunk_to_group(
         create_chunk_bookmark_query(pool, ChunkGroupBookmark::from_details(group_id, chunk_id))
             .await?;
 
-    add_bookmark_to_qdrant_query(qdrant_point_id, group_id, dataset_config).await?;
+    add_bookmark_to_qdrant_query(qdrant_point_id, group_id, dataset_config.clone()).await?;
+
+    mark_group_aggregates_stale_query(dataset_id, vec![group_id], redis_pool.clone()).await?;
+    mark_group_centroids_stale_query(
+        &dataset_config,
+        dataset_id,
+        vec![group_id],
+        redis_pool.clone(),
+    )
+    .await?;
+    mark_group_summaries_stale_query(&dataset_config, dataset_id, vec![group_id], redis_pool)
+        .await?;
 
     Ok(HttpResponse::NoContent().finish())
 }
@@ -928,6 +1693,7 @@ pub async fn add_chunk_to_group_by_tracking_id(
     tracking_id: web::Path<String>,
     dataset_org_plan_sub: DatasetAndOrgWithSubAndPlan,
     pool: web::Data<Pool>,
+    redis_pool: web::Data<RedisPool>,
     _user: AdminOnly,
 ) -> Result<HttpResponse, actix_web::Error> {
     let dataset_id = dataset_org_plan_sub.dataset.id;
@@ -956,7 +1722,18 @@ pub async fn add_chunk_to_group_by_tracking_id(
         create_chunk_bookmark_query(pool, ChunkGroupBookmark::from_details(group_id, chunk_id))
             .await?;
 
-    add_bookmark_to_qdrant_query(qdrant_point_id, group_id, dataset_config).await?;
+    add_bookmark_to_qdrant_query(qdrant_point_id, group_id, dataset_config.clone()).await?;
+
+    mark_group_aggregates_stale_query(dataset_id, vec![group_id], redis_pool.clone()).await?;
+    mark_group_centroids_stale_query(
+        &dataset_config,
+        dataset_id,
+        vec![group_id],
+        redis_pool.clone(),
+    )
+    .await?;
+    mark_group_summaries_stale_query(&dataset_config, dataset_id, vec![group_id], redis_pool)
+        .await?;
 
     Ok(HttpResponse::NoContent().finish())
 }
@@ -1206,6 +1983,7 @@ pub async fn remove_chunk_from_group(
     body: Option<web::Json<RemoveChunkFromGroupReqPayload>>,
     query: Option<web::Query<RemoveChunkFromGroupReqPayload>>,
     pool: web::Data<Pool>,
+    redis_pool: web::Data<RedisPool>,
     _user: AdminOnly,
     dataset_org_plan_sub: DatasetAndOrgWithSubAndPlan,
 ) -> Result<HttpResponse, actix_web::Error> {
@@ -1228,7 +2006,18 @@ pub async fn remove_chunk_from_group(
 
     let qdrant_point_id = delete_chunk_from_group_query(chunk_id, group_id, pool).await?;
 
-    remove_bookmark_from_qdrant_query(qdrant_point_id, group_id, dataset_config).await?;
+    remove_bookmark_from_qdrant_query(qdrant_point_id, group_id, dataset_config.clone()).await?;
+
+    mark_group_aggregates_stale_query(dataset_id, vec![group_id], redis_pool.clone()).await?;
+    mark_group_centroids_stale_query(
+        &dataset_config,
+        dataset_id,
+        vec![group_id],
+        redis_pool.clone(),
+    )
+    .await?;
+    mark_group_summaries_stale_query(&dataset_config, dataset_id, vec![group_id], redis_pool)
+        .await?;
 
     Ok(HttpResponse::NoContent().finish())
 }
@@ -1578,6 +2367,7 @@ impl From<SearchWithinGroupReqPayload> for SearchChunksReqPayload {
             user_id: search_within_group_data.user_id,
             typo_options: search_within_group_data.typo_options,
             metadata: search_within_group_data.metadata,
+            context_window: None,
         }
     }
 }
@@ -1837,7 +2627,7 @@ pub struct SearchOverGroupsReqPayload {
     pub slim_chunks: Option<bool>,
     /// If true, quoted and - prefixed words will be parsed from the queries and used as required and negated words respectively. Default is false.
     pub use_quote_negated_terms: Option<bool>,
-    /// Sort Options lets you specify different methods to rerank the chunks in the result set. If not specified, this defaults to the score of the chunks.
+    /// Sort Options lets you specify different methods to rerank the chunks in the result set. If not specified, this defaults to the score of the chunks. Sorting by a `group_aggregates.<name>` field orders the best matching 1000 groups before paging, deeper pages may contain fewer than page_size groups.
     pub sort_options: Option<SortOptions>,
     /// If true, stop words (specified in server/src/stop-words.txt in the git repo) will be removed. Queries that are entirely stop words will be
     /// preserved.
@@ -1847,6 +2637,8 @@ pub struct SearchOverGroupsReqPayload {
     pub typo_options: Option<TypoOptions>,
     /// Metadata is any metadata you want to associate w/ the event that is created from this request
     pub metadata: Option<serde_json::Value>,
+    /// Group_level merges each matching group into its ancestor at that depth of the group hierarchy before returning results, where 0 is the top-level groups. Groups nested less deeply than group_level are returned as they are. Merged groups keep their group_size best scoring chunks. Groups are merged before paging, pages deeper than the first 1000 matching groups may contain fewer than page_size groups and total pages are estimated unless every matching group was merged. If not specified, results are grouped by the groups the chunks belong to directly.
+    pub group_level: Option<u32>,
 }
 
 /// Search Over Groups
@@ -1931,14 +2723,48 @@ pub async fn search_over_groups(
         return Err(ServiceError::BadRequest("Query cannot be empty".to_string()).into());
     }
 
+    let aggregate_sort = match data
+        .sort_options
+        .as_ref()
+        .and_then(|sort_options| sort_options.sort_by.as_ref())
+    {
+        Some(QdrantSortBy::Field(sort_by_field)) => get_group_aggregate_name(&sort_by_field.field)
+            .map(|name| {
+                (
+                    name.to_string(),
+                    sort_by_field.direction.clone().unwrap_or(SortOrder::Desc),
+                )
+            }),
+        _ => None,
+    };
+
+    // Group aggregates are not indexed in qdrant, so the groups are sorted by them after the search
+    let mut search_data = data.clone();
+    if aggregate_sort.is_some() {
+        if let Some(sort_options) = search_data.sort_options.as_mut() {
+            sort_options.sort_by = None;
+        }
+    }
+
+    // Rolled up and aggregate sorted groups are paged after merging and sorting, so enough groups
+    // to fill every page up to the requested one are fetched as a single page
+    let page = data.page.unwrap_or(1);
+    let page_size = data.page_size.unwrap_or(10);
+    let candidate_count = (data.group_level.is_some() || aggregate_sort.is_some())
+        .then(|| get_reordered_group_candidate_count(page, page_size));
+    if let Some(candidate_count) = candidate_count {
+        search_data.page = Some(1);
+        search_data.page_size = Some(candidate_count);
+    }
+
     let mut timer = Timer::new();
 
     let result_chunks = match data.search_type {
         SearchMethod::Hybrid => {
             hybrid_search_over_groups(
-                data.clone(),
+                search_data,
                 parsed_query.to_parsed_query()?,
-                pool,
+                pool.clone(),
                 redis_pool,
                 dataset_org_plan_sub.dataset.clone(),
                 &dataset_config,
@@ -1948,9 +2774,9 @@ pub async fn search_over_groups(
         }
         _ => {
             search_over_groups_query(
-                data.clone(),
+                search_data,
                 parsed_query,
-                pool,
+                pool.clone(),
                 redis_pool,
                 dataset_org_plan_sub.dataset.clone(),
                 &dataset_config,
@@ -1961,6 +2787,51 @@ pub async fn search_over_groups(
     };
     timer.add("search_chunks");
 
+    let fetched_count = result_chunks.group_chunks.len();
+
+    let result_chunks = match data.group_level {
+        Some(group_level) => {
+            let result_chunks = roll_up_groups_to_level(
+                result_chunks,
+                group_level,
+                data.group_size.unwrap_or(3) as usize,
+                dataset_org_plan_sub.dataset.id,
+                pool.clone(),
+            )
+            .await?;
+            timer.add("roll_up_groups");
+            result_chunks
+        }
+        None => result_chunks,
+    };
+
+    let result_chunks = match aggregate_sort {
+        Some((name, direction)) => {
+            let result_chunks = sort_groups_by_aggregate_query(
+                result_chunks,
+                &name,
+                direction,
+                dataset_org_plan_sub.dataset.id,
+                pool,
+            )
+            .await?;
+            timer.add("sort_by_group_aggregate");
+            result_chunks
+        }
+        None => result_chunks,
+    };
+
+    let result_chunks = match candidate_count {
+        Some(candidate_count) => paginate_group_results(
+            result_chunks,
+            fetched_count,
+            candidate_count,
+            page,
+            page_size,
+        ),
+        None => result_chunks,
+    };
+
     let search_id = uuid::Uuid::new_v4();
 
     if !dataset_config.DISABLE_ANALYTICS {
@@ -2122,6 +2993,7 @@ impl From<AutocompleteSearchOverGroupsReqPayload> for SearchOverGroupsReqPayload
             metadata: value.metadata,
             get_total_pages: Some(false),
             search_type: value.search_type,
+            group_level: None,
         }
     }
 }
@@ -4,8 +4,9 @@ use super::{
 };
 use crate::{
     data::models::{
-        self, ContextOptions, DatasetAndOrgWithSubAndPlan, DatasetConfiguration, HighlightOptions,
-        LLMOptions, Pool, RedisPool, SearchMethod, SortOptions, SuggestType, TypoOptions,
+        self, ContextOptions, ContextWindow, DatasetAndOrgWithSubAndPlan, DatasetConfiguration,
+        HighlightOptions, LLMOptions, Pool, RedisPool, SearchMethod, SortOptions, SuggestType,
+        TypoOptions,
     },
     errors::ServiceError,
     get_env,
@@ -140,6 +141,10 @@ pub struct CreateMessageReqPayload {
     pub number_of_messages_to_include: Option<u64>,
     /// Model name to use for the completion. If not specified, this defaults to the dataset's model.
     pub model: Option<String>,
+    /// Context window lets you include the chunks surrounding each retrieved chunk in the RAG context. Retrieved chunks whose windows overlap are merged and only included once. If not specified, only the retrieved chunks are included.
+    pub context_window: Option<ContextWindow>,
+    /// If true, the summary chunk of each group a retrieved chunk belongs to is included in the RAG context ahead of the group's first retrieved chunk. Summaries are only available for datasets with GROUP_SUMMARIES set. If not specified, this defaults to false.
+    pub include_group_summaries: Option<bool>,
 }
 
 /// Create message
@@ -455,6 +460,10 @@ pub struct RegenerateMessageReqPayload {
     pub number_of_messages_to_include: Option<u64>,
     /// Model name to use for the completion. If not specified, this defaults to the dataset's model.
     pub model: Option<String>,
+    /// Context window lets you include the chunks surrounding each retrieved chunk in the RAG context. Retrieved chunks whose windows overlap are merged and only included once. If not specified, only the retrieved chunks are included.
+    pub context_window: Option<ContextWindow>,
+    /// If true, the summary chunk of each group a retrieved chunk belongs to is included in the RAG context ahead of the group's first retrieved chunk. Summaries are only available for datasets with GROUP_SUMMARIES set. If not specified, this defaults to false.
+    pub include_group_summaries: Option<bool>,
 }
 
 #[derive(Serialize, Debug, ToSchema)]
@@ -515,6 +524,10 @@ pub struct EditMessageReqPayload {
     pub number_of_messages_to_include: Option<u64>,
     /// Model name to use for the completion. If not specified, this defaults to the dataset's model.
     pub model: Option<String>,
+    /// Context window lets you include the chunks surrounding each retrieved chunk in the RAG context. Retrieved chunks whose windows overlap are merged and only included once. If not specified, only the retrieved chunks are included.
+    pub context_window: Option<ContextWindow>,
+    /// If true, the summary chunk of each group a retrieved chunk belongs to is included in the RAG context ahead of the group's first retrieved chunk. Summaries are only available for datasets with GROUP_SUMMARIES set. If not specified, this defaults to false.
+    pub include_group_summaries: Option<bool>,
 }
 
 impl From<EditMessageReqPayload> for CreateMessageReqPayload {
@@ -547,6 +560,8 @@ impl From<EditMessageReqPayload> for CreateMessageReqPayload {
             only_include_docs_used: data.only_include_docs_used,
             number_of_messages_to_include: data.number_of_messages_to_include,
             model: data.model,
+            context_window: data.context_window,
+            include_group_summaries: data.include_group_summaries,
         }
     }
 }
@@ -581,6 +596,8 @@ impl From<RegenerateMessageReqPayload> for CreateMessageReqPayload {
             only_include_docs_used: data.only_include_docs_used,
             number_of_messages_to_include: data.number_of_messages_to_include,
             model: data.model,
+            context_window: data.context_window,
+            include_group_summaries: data.include_group_summaries,
         }
     }
 }
@@ -420,11 +420,13 @@ pub struct UpdateAllOrgDatasetConfigsReqPayload {
     pub match_configuration: Option<serde_json::Value>,
     /// The new configuration for all datasets in the organization. Only the specified keys in the configuration object will be changed per dataset such that you can preserve dataset unique values.
     pub to_configuration: serde_json::Value,
+    /// Changes to keys the dataset's vectors were computed with (EMBEDDING_SIZE, DISTANCE_METRIC, EMBEDDING_MODEL_NAME, BM25_B, BM25_K and BM25_AVG_LEN) are rejected unless this is true, as the datasets need to be reindexed for search to work with the new values. Defaults to false.
+    pub acknowledge_reindex_required: Option<bool>,
 }
 
 /// Update All Dataset Configurations
 ///
-/// Update the configurations for all datasets in an organization. Only the specified keys in the configuration object will be changed per dataset such that you can preserve dataset unique values. Auth'ed user or api key must have an owner role for the specified organization.
+/// Update the configurations for all datasets in an organization. Only the specified keys in the configuration object will be changed per dataset such that you can preserve dataset unique values. Each changed dataset gets a new configuration version which can be rolled back. Auth'ed user or api key must have an owner role for the specified organization.
 #[utoipa::path(
     post,
     path = "/organization/update_dataset_configs",
@@ -446,7 +448,7 @@ pub async fn update_all_org_dataset_configs(
     req_payload: web::Json<UpdateAllOrgDatasetConfigsReqPayload>,
     pool: web::Data<Pool>,
     org_with_plan_and_sub: OrganizationWithSubAndPlan,
-    _user: OwnerOnly,
+    user: OwnerOnly,
 ) -> Result<HttpResponse, actix_web::Error> {
     let organization_id = org_with_plan_and_sub.organization.id;
 
@@ -459,6 +461,8 @@ pub async fn update_all_org_dataset_configs(
         organization_id,
         new_dataset_config,
         match_configuration,
+        req_payload.acknowledge_reindex_required.unwrap_or(false),
+        Some(user.0.id),
         pool,
     )
     .await?;
@@ -190,11 +190,13 @@ impl Modify for SecurityAddon {
         handlers::message_handler::generate_message_completions,
         handlers::chunk_handler::create_chunk,
         handlers::chunk_handler::update_chunk,
+        handlers::chunk_handler::patch_chunk,
         handlers::chunk_handler::delete_chunk,
         handlers::chunk_handler::split_html_content,
         handlers::chunk_handler::get_recommended_chunks,
         handlers::chunk_handler::update_chunk_by_tracking_id,
         handlers::chunk_handler::search_chunks,
+        handlers::chunk_handler::federated_search_chunks,
         handlers::chunk_handler::count_chunks,
         handlers::chunk_handler::generate_off_chunks,
         handlers::chunk_handler::get_chunk_by_tracking_id,
@@ -222,6 +224,16 @@ impl Modify for SecurityAddon {
         handlers::group_handler::update_chunk_group,
         handlers::group_handler::add_chunk_to_group,
         handlers::group_handler::get_chunk_group,
+        handlers::group_handler::get_group_children,
+        handlers::group_handler::get_group_ancestors,
+        handlers::group_handler::create_group_aggregate,
+        handlers::group_handler::get_group_aggregates,
+        handlers::group_handler::delete_group_aggregate,
+        handlers::group_handler::get_group_centroid,
+        handlers::group_handler::get_similar_groups,
+        handlers::group_handler::search_group_centroids,
+        handlers::group_handler::rebuild_group_centroids,
+        handlers::group_handler::regenerate_group_summary,
         handlers::group_handler::remove_chunk_from_group,
         handlers::group_handler::get_chunks_in_group,
         handlers::group_handler::get_groups_for_chunks,
@@ -234,9 +246,13 @@ impl Modify for SecurityAddon {
         handlers::file_handler::get_dataset_files_and_group_ids_handler,
         handlers::file_handler::get_files_cursor_handler,
         handlers::file_handler::upload_file_handler,
+        handlers::file_handler::reprocess_file_handler,
+        handlers::file_handler::get_file_versions_handler,
+        handlers::file_handler::restore_file_version_handler,
         handlers::file_handler::get_file_handler,
         handlers::file_handler::delete_file_handler,
         handlers::file_handler::create_presigned_url_for_csv_jsonl,
+        handlers::file_handler::preview_csv_jsonl,
         handlers::file_handler::upload_html_page,
         handlers::event_handler::get_events,
         handlers::crawl_handler::create_crawl,
@@ -260,8 +276,35 @@ impl Modify for SecurityAddon {
         handlers::dataset_handler::get_datasets_from_organization,
         handlers::dataset_handler::create_pagefind_index_for_dataset,
         handlers::dataset_handler::get_pagefind_index_for_dataset,
+        handlers::dataset_handler::create_dataset_export,
+        handlers::dataset_handler::get_dataset_export,
+        handlers::dataset_handler::create_dataset_import,
+        handlers::dataset_handler::get_dataset_import,
+        handlers::dataset_handler::create_dataset_alias,
+        handlers::dataset_handler::get_dataset_aliases,
+        handlers::dataset_handler::swap_dataset_alias,
+        handlers::dataset_handler::delete_dataset_alias,
+        handlers::dataset_handler::create_dataset_reindex,
+        handlers::dataset_handler::get_dataset_reindex,
+        handlers::dataset_handler::cancel_dataset_reindex,
+        handlers::dataset_handler::stream_chunk_changes,
+        handlers::dataset_handler::create_change_webhook,
+        handlers::dataset_handler::get_change_webhooks,
+        handlers::dataset_handler::update_change_webhook,
+        handlers::dataset_handler::delete_change_webhook,
+        handlers::dataset_handler::get_change_webhook_deliveries,
+        handlers::dataset_handler::create_event_webhook,
+        handlers::dataset_handler::get_event_webhooks,
+        handlers::dataset_handler::update_event_webhook,
+        handlers::dataset_handler::delete_event_webhook,
+        handlers::dataset_handler::get_event_webhook_deliveries,
+        handlers::dataset_handler::test_event_webhook,
+        handlers::dataset_handler::get_dataset_config_versions,
+        handlers::dataset_handler::diff_dataset_config_versions,
+        handlers::dataset_handler::rollback_dataset_config,
         handlers::dataset_handler::clear_dataset,
         handlers::dataset_handler::clone_dataset,
+        handlers::dataset_handler::get_dataset_clone_status,
         handlers::dataset_handler::get_dataset_queue_lengths,
         handlers::payment_handler::direct_to_payment_link,
         handlers::payment_handler::cancel_subscription,
@@ -331,9 +374,13 @@ impl Modify for SecurityAddon {
             handlers::chunk_handler::CreateSingleChunkReqPayload,
             handlers::chunk_handler::SearchResponseBody,
             handlers::chunk_handler::SearchResponseTypes,
+            handlers::chunk_handler::FederatedSearchResponseBody,
+            handlers::chunk_handler::FederatedScoreChunk,
             handlers::chunk_handler::CreateBatchChunkReqPayload,
             handlers::chunk_handler::SingleQueuedChunkResponse,
             handlers::chunk_handler::ChunkHtmlContentReqPayload,
+            operators::chunking_operator::ChunkingStrategy,
+            operators::extraction_operator::SpreadsheetChunking,
             handlers::chunk_handler::SplitHtmlResponse,
             handlers::chunk_handler::ChunkedContent,
             handlers::chunk_handler::BatchQueuedChunkResponse,
@@ -341,11 +388,14 @@ impl Modify for SecurityAddon {
             handlers::chunk_handler::RecommendChunksResponseBody,
             handlers::chunk_handler::RecommendResponseTypes,
             handlers::chunk_handler::UpdateChunkReqPayload,
+            handlers::chunk_handler::PatchChunkReqPayload,
+            handlers::chunk_handler::JsonPatchOperation,
             handlers::chunk_handler::RecommendChunksRequest,
             handlers::chunk_handler::UpdateChunkByTrackingIdData,
             handlers::chunk_handler::SearchChunkQueryResponseBody,
             handlers::chunk_handler::GenerateOffChunksReqPayload,
             handlers::chunk_handler::SearchChunksReqPayload,
+            handlers::chunk_handler::FederatedSearchReqPayload,
             handlers::chunk_handler::CountChunksReqPayload,
             handlers::chunk_handler::CountChunkQueryResponseBody,
             handlers::chunk_handler::AutocompleteReqPayload,
@@ -366,6 +416,26 @@ impl Modify for SecurityAddon {
             handlers::dataset_handler::GetAllTagsResponse,
             handlers::dataset_handler::Datasets,
             handlers::dataset_handler::GetPagefindIndexResponse,
+            handlers::dataset_handler::CreateDatasetExportReqPayload,
+            handlers::dataset_handler::DatasetExportResponse,
+            handlers::dataset_handler::CreateDatasetImportReqPayload,
+            handlers::dataset_handler::CreateDatasetAliasReqPayload,
+            handlers::dataset_handler::SwapDatasetAliasReqPayload,
+            handlers::dataset_handler::CreateDatasetReindexReqPayload,
+            handlers::dataset_handler::DatasetReindexResponse,
+            handlers::dataset_handler::StreamChunkChangesQuery,
+            handlers::dataset_handler::CreateChangeWebhookReqPayload,
+            handlers::dataset_handler::CreateChangeWebhookResponse,
+            handlers::dataset_handler::UpdateChangeWebhookReqPayload,
+            handlers::dataset_handler::GetChangeWebhookDeliveriesQuery,
+            handlers::dataset_handler::CreateEventWebhookReqPayload,
+            handlers::dataset_handler::CreateEventWebhookResponse,
+            handlers::dataset_handler::UpdateEventWebhookReqPayload,
+            handlers::dataset_handler::GetEventWebhookDeliveriesQuery,
+            handlers::dataset_handler::GetDatasetConfigVersionsQuery,
+            handlers::dataset_handler::DiffDatasetConfigVersionsQuery,
+            handlers::dataset_handler::DatasetConfigDiff,
+            handlers::dataset_handler::RollbackDatasetConfigReqPayload,
             handlers::dataset_handler::DatasetQueueLengthsResponse,
             handlers::crawl_handler::GetCrawlRequestsReqPayload,
             handlers::crawl_handler::CreateCrawlReqPayload,
@@ -378,6 +448,12 @@ impl Modify for SecurityAddon {
             handlers::group_handler::SearchGroupResponseTypes,
             handlers::group_handler::SearchWithinGroupResults,
             handlers::group_handler::GroupData,
+            handlers::group_handler::GetGroupChildrenResponse,
+            handlers::group_handler::CreateGroupAggregateReqPayload,
+            handlers::group_handler::GroupCentroid,
+            handlers::group_handler::GroupCentroidScore,
+            handlers::group_handler::GetSimilarGroupsReqPayload,
+            handlers::group_handler::SearchGroupCentroidsReqPayload,
             handlers::group_handler::CreateChunkGroupReqPayloadEnum,
             handlers::group_handler::CreateBatchChunkGroupReqPayload,
             handlers::group_handler::CreateSingleChunkGroupReqPayload,
@@ -423,8 +499,12 @@ impl Modify for SecurityAddon {
             handlers::file_handler::CreateFormWithoutFile,
             handlers::file_handler::UploadFileReqPayload,
             handlers::file_handler::UploadFileResponseBody,
+            handlers::file_handler::ReprocessFileReqPayload,
+            handlers::file_handler::GetFileVersionsResponseBody,
             handlers::file_handler::CreatePresignedUrlForCsvJsonlReqPayload,
             handlers::file_handler::CreatePresignedUrlForCsvJsonResponseBody,
+            handlers::file_handler::PreviewCsvJsonlReqPayload,
+            handlers::file_handler::PreviewCsvJsonlResponseBody,
             handlers::file_handler::UploadHtmlPageReqPayload,
             handlers::file_handler::FileData,
             handlers::file_handler::Pdf2MdOptions,
@@ -526,6 +606,30 @@ impl Modify for SecurityAddon {
             data::models::ChunkReqPayloadFields,
             data::models::ChunkReqPayloadMapping,
             data::models::ChunkReqPayloadMappings,
+            data::models::CsvJsonlFieldTransform,
+            data::models::CsvJsonlFileFormat,
+            data::models::CsvJsonlRowFailure,
+            data::models::DatasetExport,
+            data::models::DatasetExportFormat,
+            data::models::DatasetExportStatus,
+            data::models::DatasetImport,
+            data::models::DatasetClone,
+            data::models::DatasetCloneStatus,
+            data::models::DatasetAlias,
+            data::models::DatasetReindex,
+            data::models::DatasetReindexStatus,
+            data::models::ChunkChange,
+            data::models::ChunkChangeOperation,
+            data::models::ChangeWebhook,
+            data::models::ChangeWebhookDelivery,
+            data::models::EventWebhook,
+            data::models::EventWebhookDelivery,
+            data::models::EventWebhookDeliveryStatus,
+            data::models::DatasetConfigVersion,
+            data::models::DatasetConfigVersionSource,
+            data::models::SpreadsheetOptions,
+            data::models::CsvJsonlRowFilter,
+            data::models::CsvJsonlRowFilterOperator,
             data::models::DatasetConfigurationDTO,
             data::models::ScrapeOptions,
             data::models::CrawlShopifyOptions,
@@ -599,6 +703,7 @@ impl Modify for SecurityAddon {
             data::models::PopularFilters,
             data::models::RecommendationStrategy,
             data::models::ScoreChunk,
+            data::models::ExpandedContent,
             data::models::Granularity,
             data::models::RAGSortBy,
             data::models::SearchSortBy,
@@ -616,6 +721,7 @@ impl Modify for SecurityAddon {
             data::models::LLMOptions,
             data::models::ImageConfig,
             data::models::HighlightOptions,
+            data::models::ContextWindow,
             data::models::TypoOptions,
             data::models::TypoRange,
             data::models::SortByField,
@@ -629,7 +735,10 @@ impl Modify for SecurityAddon {
             data::models::WorkerEvent,
             data::models::ChunkGroup,
             data::models::ChunkGroupAndFileId,
+            data::models::GroupAggregate,
+            data::models::GroupAggregateFunction,
             data::models::File,
+            data::models::FileVersion,
             data::models::FileWithChunkGroups,
             data::models::FileAndGroupId,
             data::models::FileDTO,
@@ -694,6 +803,12 @@ impl Modify for SecurityAddon {
             data::models::TopPages,
             data::models::QueryToolOptions,
             data::models::ToolConfiguration,
+            data::models::DedupPolicy,
+            data::models::GroupCentroidOptions,
+            data::models::GroupSummaryOptions,
+            data::models::DedupAction,
+            data::models::DuplicateChunk,
+            data::models::DuplicateMatch,
             data::models::FloatRange,
             data::models::RecommendationUsageGraphResponse,
             data::models::RecommendationsPerUserResponse,
@@ -875,7 +990,7 @@ pub fn main() -> std::io::Result<()> {
         }
 
 
-        let (clickhouse_client, event_queue) = if std::env::var("USE_ANALYTICS").unwrap_or("false".to_string()).parse().unwrap_or(false) {
+        let (clickhouse_client, mut event_queue) = if std::env::var("USE_ANALYTICS").unwrap_or("false".to_string()).parse().unwrap_or(false) {
             log::info!("Analytics enabled");
 
             let args  = SetupArgs {
@@ -905,6 +1020,7 @@ pub fn main() -> std::io::Result<()> {
             log::info!("Analytics disabled");
             (clickhouse::Client::default(), EventQueue::default())
         };
+        event_queue.start_webhook_service();
 
         BKTreeCache::enforce_cache_ttl();
 
@@ -1089,6 +1205,92 @@ pub fn main() -> std::io::Result<()> {
                                         .route(web::post().to(handlers::dataset_handler::create_pagefind_index_for_dataset))
                                         .route(web::get().to(handlers::dataset_handler::get_pagefind_index_for_dataset))
                                 )
+                                .service(
+                                    web::resource("/export")
+                                        .route(web::post().to(handlers::dataset_handler::create_dataset_export))
+                                )
+                                .service(
+                                    web::resource("/export/{export_id}")
+                                        .route(web::get().to(handlers::dataset_handler::get_dataset_export))
+                                )
+                                .service(
+                                    web::resource("/import")
+                                        .route(web::post().to(handlers::dataset_handler::create_dataset_import))
+                                )
+                                .service(
+                                    web::resource("/import/{import_id}")
+                                        .route(web::get().to(handlers::dataset_handler::get_dataset_import))
+                                )
+                                .service(
+                                    web::resource("/alias")
+                                        .route(web::post().to(handlers::dataset_handler::create_dataset_alias))
+                                        .route(web::get().to(handlers::dataset_handler::get_dataset_aliases))
+                                )
+                                .service(
+                                    web::resource("/alias/swap")
+                                        .route(web::put().to(handlers::dataset_handler::swap_dataset_alias))
+                                )
+                                .service(
+                                    web::resource("/alias/{alias_name}")
+                                        .route(web::delete().to(handlers::dataset_handler::delete_dataset_alias))
+                                )
+                                .service(
+                                    web::resource("/reindex")
+                                        .route(web::post().to(handlers::dataset_handler::create_dataset_reindex))
+                                )
+                                .service(
+                                    web::resource("/reindex/{reindex_id}")
+                                        .route(web::get().to(handlers::dataset_handler::get_dataset_reindex))
+                                        .route(web::delete().to(handlers::dataset_handler::cancel_dataset_reindex))
+                                )
+                                .service(
+                                    web::resource("/changes")
+                                        .route(web::get().to(handlers::dataset_handler::stream_chunk_changes))
+                                )
+                                .service(
+                                    web::resource("/change_webhooks")
+                                        .route(web::post().to(handlers::dataset_handler::create_change_webhook))
+                                        .route(web::get().to(handlers::dataset_handler::get_change_webhooks))
+                                )
+                                .service(
+                                    web::resource("/change_webhooks/{webhook_id}")
+                                        .route(web::put().to(handlers::dataset_handler::update_change_webhook))
+                                        .route(web::delete().to(handlers::dataset_handler::delete_change_webhook))
+                                )
+                                .service(
+                                    web::resource("/change_webhooks/{webhook_id}/deliveries")
+                                        .route(web::get().to(handlers::dataset_handler::get_change_webhook_deliveries))
+                                )
+                                .service(
+                                    web::resource("/event_webhooks")
+                                        .route(web::post().to(handlers::dataset_handler::create_event_webhook))
+                                        .route(web::get().to(handlers::dataset_handler::get_event_webhooks))
+                                )
+                                .service(
+                                    web::resource("/event_webhooks/{webhook_id}")
+                                        .route(web::put().to(handlers::dataset_handler::update_event_webhook))
+                                        .route(web::delete().to(handlers::dataset_handler::delete_event_webhook))
+                                )
+                                .service(
+                                    web::resource("/event_webhooks/{webhook_id}/deliveries")
+                                        .route(web::get().to(handlers::dataset_handler::get_event_webhook_deliveries))
+                                )
+                                .service(
+                                    web::resource("/event_webhooks/{webhook_id}/test")
+                                        .route(web::post().to(handlers::dataset_handler::test_event_webhook))
+                                )
+                                .service(
+                                    web::resource("/config_versions")
+                                        .route(web::get().to(handlers::dataset_handler::get_dataset_config_versions))
+                                )
+                                .service(
+                                    web::resource("/config_versions/diff")
+                                        .route(web::get().to(handlers::dataset_handler::diff_dataset_config_versions))
+                                )
+                                .service(
+                                    web::resource("/config_versions/{version}/rollback")
+                                        .route(web::post().to(handlers::dataset_handler::rollback_dataset_config))
+                                )
                                 .service(
                                     web::resource("/batch_create_datasets").route(
                                         web::post().to(handlers::dataset_handler::batch_create_datasets),
@@ -1118,6 +1320,7 @@ pub fn main() -> std::io::Result<()> {
                                         .route(web::post().to(handlers::event_handler::get_events)),
                                 )
                                 .route("/clone", web::post().to(handlers::dataset_handler::clone_dataset))
+                                .route("/clone_status", web::get().to(handlers::dataset_handler::get_dataset_clone_status))
                                 .route("/scroll_files", web::get().to(handlers::file_handler::get_files_cursor_handler))
                                 .service(
                                     web::resource("/{dataset_id}")
@@ -1245,6 +1448,7 @@ pub fn main() -> std::io::Result<()> {
                                     web::resource("")
                                         .route(web::post().to(handlers::chunk_handler::create_chunk))
                                         .route(web::put().to(handlers::chunk_handler::update_chunk))
+                                        .route(web::patch().to(handlers::chunk_handler::patch_chunk))
                                         .route(web::delete().to(handlers::chunk_handler::bulk_delete_chunk)),
                                 )
                                 .service(
@@ -1267,6 +1471,11 @@ pub fn main() -> std::io::Result<()> {
                                         .wrap(Compress::default())
                                         .route(web::post().to(handlers::chunk_handler::search_chunks)),
                                 )
+                                .service(
+                                    web::resource("/federated_search")
+                                        .wrap(Compress::default())
+                                        .route(web::post().to(handlers::chunk_handler::federated_search_chunks)),
+                                )
                                 .service(
                                     web::resource("/count")
                                         .route(web::post().to(handlers::chunk_handler::count_chunks)),
@@ -1357,6 +1566,23 @@ pub fn main() -> std::io::Result<()> {
                                     )
                                     .wrap(Compress::default()),
                                 )
+                                .service(
+                                    web::resource("/aggregates")
+                                        .route(web::post().to(handlers::group_handler::create_group_aggregate))
+                                        .route(web::get().to(handlers::group_handler::get_group_aggregates)),
+                                )
+                                .service(
+                                    web::resource("/aggregates/{aggregate_id}")
+                                        .route(web::delete().to(handlers::group_handler::delete_group_aggregate)),
+                                )
+                                .service(
+                                    web::resource("/centroid_search")
+                                        .route(web::post().to(handlers::group_handler::search_group_centroids)),
+                                )
+                                .service(
+                                    web::resource("/centroids/rebuild")
+                                        .route(web::post().to(handlers::group_handler::rebuild_group_centroids)),
+                                )
                                 .service(
                                     web::resource("/chunk/{chunk_group_id}")
                                         .route(
@@ -1401,6 +1627,26 @@ pub fn main() -> std::io::Result<()> {
                                                 .route(web::get().to(handlers::group_handler::get_chunk_group))
                                                 .route(web::delete().to(handlers::group_handler::delete_chunk_group)),
                                         )
+                                        .service(
+                                            web::resource("/children")
+                                                .route(web::get().to(handlers::group_handler::get_group_children)),
+                                        )
+                                        .service(
+                                            web::resource("/ancestors")
+                                                .route(web::get().to(handlers::group_handler::get_group_ancestors)),
+                                        )
+                                        .service(
+                                            web::resource("/centroid")
+                                                .route(web::get().to(handlers::group_handler::get_group_centroid)),
+                                        )
+                                        .service(
+                                            web::resource("/similar")
+                                                .route(web::post().to(handlers::group_handler::get_similar_groups)),
+                                        )
+                                        .service(
+                                            web::resource("/summary")
+                                                .route(web::post().to(handlers::group_handler::regenerate_group_summary)),
+                                        )
                                         .service(
                                             web::resource("/{page}")
                                                 .route(web::get().to(handlers::group_handler::get_chunks_in_group)),
@@ -1424,6 +1670,22 @@ pub fn main() -> std::io::Result<()> {
                                     web::resource("/csv_or_jsonl")
                                         .route(web::post().to(handlers::file_handler::create_presigned_url_for_csv_jsonl)),
                                 )
+                                .service(
+                                    web::resource("/csv_or_jsonl/preview")
+                                        .route(web::post().to(handlers::file_handler::preview_csv_jsonl)),
+                                )
+                                .service(
+                                    web::resource("/{file_id}/reprocess")
+                                        .route(web::post().to(handlers::file_handler::reprocess_file_handler)),
+                                )
+                                .service(
+                                    web::resource("/{file_id}/versions")
+                                        .route(web::get().to(handlers::file_handler::get_file_versions_handler)),
+                                )
+                                .service(
+                                    web::resource("/{file_id}/versions/{version}/restore")
+                                        .route(web::post().to(handlers::file_handler::restore_file_version_handler)),
+                                )
                                 .service(
                                     web::resource("/{file_id}")
                                         .route(web::get().to(handlers::file_handler::get_file_handler))
@@ -5,7 +5,10 @@ use crate::{
     errors::ServiceError,
     handlers::{
         auth_handler::{AdminOnly, LoggedUser, OrganizationRole, OwnerOnly},
-        chunk_handler::{AutocompleteReqPayload, ScrollChunksReqPayload, SearchChunksReqPayload},
+        chunk_handler::{
+            AutocompleteReqPayload, FederatedSearchReqPayload, ScrollChunksReqPayload,
+            SearchChunksReqPayload,
+        },
         group_handler::{
             AutocompleteSearchOverGroupsReqPayload, SearchOverGroupsReqPayload,
             SearchWithinGroupReqPayload,
@@ -67,6 +70,10 @@ where
                 req.extensions_mut().insert(user);
             }
 
+            if let Some(user_api_key) = api_key.clone() {
+                req.extensions_mut().insert(user_api_key);
+            }
+
             let org_id = match get_dataset_id_from_headers(req.headers()) {
                 Some(dataset_id) => {
                     let dataset_org_plan_sub = match dataset_id.parse::<uuid::Uuid>() {
@@ -101,27 +108,12 @@ where
                     };
 
                     if let Some(user_api_key) = api_key {
-                        if let Some(api_key_org_ids) = user_api_key.organization_ids {
-                            if !api_key_org_ids.is_empty()
-                                && !api_key_org_ids.contains(&Some(
-                                    dataset_org_plan_sub
-                                        .organization
-                                        .organization
-                                        .id
-                                        .to_string(),
-                                ))
-                            {
-                                return Err(ServiceError::Unauthorized.into());
-                            }
-                        }
-
-                        if let Some(api_key_dataset_ids) = user_api_key.dataset_ids {
-                            if !api_key_dataset_ids.is_empty()
-                                && !api_key_dataset_ids
-                                    .contains(&Some(dataset_org_plan_sub.dataset.id.to_string()))
-                            {
-                                return Err(ServiceError::Unauthorized.into());
-                            }
+                        if !verify_api_key_dataset_access(
+                            &user_api_key,
+                            dataset_org_plan_sub.dataset.id,
+                            dataset_org_plan_sub.organization.organization.id,
+                        ) {
+                            return Err(ServiceError::Unauthorized.into());
                         }
 
                         let route = format!("{} {}", req.method(), req.match_info().as_str());
@@ -402,6 +394,13 @@ pub async fn insert_api_key_payload(
             let body_bytes = serde_json::to_vec(&web::Json(new_body)).unwrap();
             req.set_payload(bytes_to_payload(body_bytes.into()));
         }
+        "/api/chunk/federated_search" => {
+            let body = req.extract::<Json<FederatedSearchReqPayload>>().await?;
+            let mut new_body = body.into_inner();
+            new_body.search = api_key_params.combine_with_search_chunks(new_body.search);
+            let body_bytes = serde_json::to_vec(&web::Json(new_body)).unwrap();
+            req.set_payload(bytes_to_payload(body_bytes.into()));
+        }
         "/api/chunk_group/group_oriented_search" => {
             let body = req.extract::<Json<SearchOverGroupsReqPayload>>().await?;
             let new_body = api_key_params.combine_with_search_over_groups(body.into_inner());
@@ -485,6 +484,31 @@ pub fn verify_admin(user: &AdminOnly, org_id: &uuid::Uuid) -> bool {
     false
 }
 
+/// Checks the dataset and organization restrictions of an API key. Empty lists place no restriction.
+pub fn verify_api_key_dataset_access(
+    user_api_key: &UserApiKey,
+    dataset_id: uuid::Uuid,
+    organization_id: uuid::Uuid,
+) -> bool {
+    if let Some(api_key_org_ids) = user_api_key.organization_ids.as_ref() {
+        if !api_key_org_ids.is_empty()
+            && !api_key_org_ids.contains(&Some(organization_id.to_string()))
+        {
+            return false;
+        }
+    }
+
+    if let Some(api_key_dataset_ids) = user_api_key.dataset_ids.as_ref() {
+        if !api_key_dataset_ids.is_empty()
+            && !api_key_dataset_ids.contains(&Some(dataset_id.to_string()))
+        {
+            return false;
+        }
+    }
+
+    true
+}
+
 pub fn verify_member(user: &LoggedUser, org_id: &uuid::Uuid) -> bool {
     if let Some(user_role) = get_role_for_org(user, org_id) {
         return user_role >= UserRole::User;
@@ -0,0 +1,812 @@
+use actix_web::web;
+use diesel::dsl::sql;
+use diesel::prelude::*;
+use diesel::sql_types;
+use diesel_async::scoped_futures::ScopedFutureExt;
+use diesel_async::{AsyncConnection, RunQueryDsl};
+use hmac::{Hmac, Mac};
+use itertools::Itertools;
+use rand::{distributions::Alphanumeric, Rng};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::net::SocketAddr;
+
+use crate::data::models::{
+    ChangeWebhook, ChangeWebhookDelivery, ChunkChange, ChunkChangeOperation, ChunkData,
+    ChunkMetadata, NewChunkChange, Pool,
+};
+use crate::errors::ServiceError;
+use crate::operators::import_operator::resolve_import_url;
+
+/// Maximum number of changes sent in a single webhook delivery
+pub const CHANGE_WEBHOOK_BATCH_SIZE: i64 = 100;
+/// Webhooks are deactivated after this many deliveries in a row have failed
+pub const CHANGE_WEBHOOK_MAX_CONSECUTIVE_FAILURES: i32 = 20;
+
+/// Records chunk changes. Readers page through a dataset's changes with a `seq > cursor` filter,
+/// which would skip a change whose sequence number was allocated before, but committed after, a
+/// higher one. Inserts into the same dataset are therefore serialized with a transaction scoped
+/// advisory lock, so sequence numbers of a dataset become visible in order.
+pub async fn insert_chunk_changes_query(
+    changes: Vec<NewChunkChange>,
+    pool: web::Data<Pool>,
+) -> Result<(), ServiceError> {
+    use crate::data::schema::chunk_changes::dsl as chunk_changes_columns;
+
+    if changes.is_empty() {
+        return Ok(());
+    }
+
+    let mut conn = pool.get().await.map_err(|_e| {
+        ServiceError::InternalServerError("Failed to get postgres connection".to_string())
+    })?;
+
+    // Locks are always taken in the same order so concurrent inserts cannot deadlock
+    let dataset_ids = changes
+        .iter()
+        .map(|change| change.dataset_id)
+        .sorted()
+        .dedup()
+        .collect::<Vec<uuid::Uuid>>();
+
+    conn.transaction::<_, diesel::result::Error, _>(|conn| {
+        async move {
+            for dataset_id in dataset_ids {
+                diesel::sql_query("SELECT pg_advisory_xact_lock(hashtextextended($1::text, 0))")
+                    .bind::<sql_types::Uuid, _>(dataset_id)
+                    .execute(conn)
+                    .await?;
+            }
+
+            diesel::insert_into(chunk_changes_columns::chunk_changes)
+                .values(&changes)
+                .execute(conn)
+                .await?;
+
+            Ok(())
+        }
+        .scope_boxed()
+    })
+    .await
+    .map_err(|err| {
+        log::error!("Failed to record chunk changes: {:?}", err);
+        ServiceError::InternalServerError("Failed to record chunk changes".to_string())
+    })
+}
+
+pub async fn get_chunk_changes_query(
+    dataset_id: uuid::Uuid,
+    after_seq: i64,
+    limit: i64,
+    pool: web::Data<Pool>,
+) -> Result<Vec<ChunkChange>, ServiceError> {
+    use crate::data::schema::chunk_changes::dsl as chunk_changes_columns;
+
+    let mut conn = pool.get().await.map_err(|_e| {
+        ServiceError::InternalServerError("Failed to get postgres connection".to_string())
+    })?;
+
+    chunk_changes_columns::chunk_changes
+        .filter(chunk_changes_columns::dataset_id.eq(dataset_id))
+        .filter(chunk_changes_columns::seq.gt(after_seq))
+        .order_by(chunk_changes_columns::seq.asc())
+        .limit(limit)
+        .select(ChunkChange::as_select())
+        .load::<ChunkChange>(&mut conn)
+        .await
+        .map_err(|err| {
+            log::error!("Failed to get chunk changes: {:?}", err);
+            ServiceError::InternalServerError("Failed to get chunk changes".to_string())
+        })
+}
+
+/// Returns the sequence number of the most recent change in the dataset, or 0 if it has none
+pub async fn get_latest_chunk_change_seq_query(
+    dataset_id: uuid::Uuid,
+    pool: web::Data<Pool>,
+) -> Result<i64, ServiceError> {
+    use crate::data::schema::chunk_changes::dsl as chunk_changes_columns;
+
+    let mut conn = pool.get().await.map_err(|_e| {
+        ServiceError::InternalServerError("Failed to get postgres connection".to_string())
+    })?;
+
+    let latest_seq = chunk_changes_columns::chunk_changes
+        .filter(chunk_changes_columns::dataset_id.eq(dataset_id))
+        .select(diesel::dsl::max(chunk_changes_columns::seq))
+        .first::<Option<i64>>(&mut conn)
+        .await
+        .map_err(|err| {
+            log::error!("Failed to get latest chunk change: {:?}", err);
+            ServiceError::InternalServerError("Failed to get latest chunk change".to_string())
+        })?;
+
+    Ok(latest_seq.unwrap_or(0))
+}
+
+pub async fn delete_chunk_changes_before_query(
+    before: chrono::NaiveDateTime,
+    pool: web::Data<Pool>,
+) -> Result<usize, ServiceError> {
+    use crate::data::schema::chunk_changes::dsl as chunk_changes_columns;
+
+    let mut conn = pool.get().await.map_err(|_e| {
+        ServiceError::InternalServerError("Failed to get postgres connection".to_string())
+    })?;
+
+    diesel::delete(
+        chunk_changes_columns::chunk_changes.filter(chunk_changes_columns::created_at.lt(before)),
+    )
+    .execute(&mut conn)
+    .await
+    .map_err(|err| {
+        log::error!("Failed to delete old chunk changes: {:?}", err);
+        ServiceError::InternalServerError("Failed to delete old chunk changes".to_string())
+    })
+}
+
+/// Names of the fields which are set on a newly created chunk
+pub fn get_chunk_fields_set(chunk: &ChunkMetadata, group_ids_set: bool) -> Vec<String> {
+    let mut fields = vec![];
+
+    if chunk.chunk_html.is_some() {
+        fields.push("chunk_html");
+    }
+    if chunk.link.is_some() {
+        fields.push("link");
+    }
+    if chunk.metadata.is_some() {
+        fields.push("metadata");
+    }
+    if chunk.tracking_id.is_some() {
+        fields.push("tracking_id");
+    }
+    if chunk.time_stamp.is_some() {
+        fields.push("time_stamp");
+    }
+    if chunk.location.is_some() {
+        fields.push("location");
+    }
+    if chunk.image_urls.is_some() {
+        fields.push("image_urls");
+    }
+    if chunk.tag_set.is_some() {
+        fields.push("tag_set");
+    }
+    if chunk.num_value.is_some() {
+        fields.push("num_value");
+    }
+    if chunk.weight != 0.0 {
+        fields.push("weight");
+    }
+    if group_ids_set {
+        fields.push("group_ids");
+    }
+
+    fields.into_iter().map(|field| field.to_string()).collect()
+}
+
+/// Changes for chunks written by the ingestion worker
+pub fn get_ingested_chunk_changes(
+    dataset_id: uuid::Uuid,
+    ingested_chunks: &[ChunkData],
+) -> Vec<NewChunkChange> {
+    ingested_chunks
+        .iter()
+        .map(|chunk_data| {
+            NewChunkChange::from_details(
+                dataset_id,
+                if chunk_data.upsert_by_tracking_id {
+                    ChunkChangeOperation::Upsert
+                } else {
+                    ChunkChangeOperation::Create
+                },
+                Some(chunk_data.chunk_metadata.id),
+                chunk_data.chunk_metadata.tracking_id.clone(),
+                get_chunk_fields_set(
+                    &chunk_data.chunk_metadata,
+                    chunk_data
+                        .group_ids
+                        .as_ref()
+                        .is_some_and(|group_ids| !group_ids.is_empty()),
+                ),
+            )
+        })
+        .collect()
+}
+
+/// Names of the fields which differ between two versions of a chunk
+pub fn get_chunk_fields_changed(
+    prev_chunk: &ChunkMetadata,
+    new_chunk: &ChunkMetadata,
+) -> Vec<String> {
+    let mut fields = vec![];
+
+    if prev_chunk.chunk_html != new_chunk.chunk_html {
+        fields.push("chunk_html");
+    }
+    if prev_chunk.link != new_chunk.link {
+        fields.push("link");
+    }
+    if prev_chunk.metadata != new_chunk.metadata {
+        fields.push("metadata");
+    }
+    if prev_chunk.tracking_id != new_chunk.tracking_id {
+        fields.push("tracking_id");
+    }
+    if prev_chunk.time_stamp != new_chunk.time_stamp {
+        fields.push("time_stamp");
+    }
+    if prev_chunk.location != new_chunk.location {
+        fields.push("location");
+    }
+    if prev_chunk.image_urls != new_chunk.image_urls {
+        fields.push("image_urls");
+    }
+    if prev_chunk.tag_set != new_chunk.tag_set {
+        fields.push("tag_set");
+    }
+    if prev_chunk.num_value != new_chunk.num_value {
+        fields.push("num_value");
+    }
+    if prev_chunk.weight != new_chunk.weight {
+        fields.push("weight");
+    }
+
+    fields.into_iter().map(|field| field.to_string()).collect()
+}
+
+pub async fn create_change_webhook_query(
+    change_webhook: ChangeWebhook,
+    pool: web::Data<Pool>,
+) -> Result<ChangeWebhook, ServiceError> {
+    use crate::data::schema::change_webhooks::dsl as change_webhooks_columns;
+
+    let mut conn = pool.get().await.map_err(|_e| {
+        ServiceError::InternalServerError("Failed to get postgres connection".to_string())
+    })?;
+
+    diesel::insert_into(change_webhooks_columns::change_webhooks)
+        .values(&change_webhook)
+        .get_result::<ChangeWebhook>(&mut conn)
+        .await
+        .map_err(|err| {
+            log::error!("Failed to create change webhook: {:?}", err);
+            ServiceError::InternalServerError("Failed to create change webhook".to_string())
+        })
+}
+
+pub async fn get_change_webhooks_query(
+    dataset_id: uuid::Uuid,
+    pool: web::Data<Pool>,
+) -> Result<Vec<ChangeWebhook>, ServiceError> {
+    use crate::data::schema::change_webhooks::dsl as change_webhooks_columns;
+
+    let mut conn = pool.get().await.map_err(|_e| {
+        ServiceError::InternalServerError("Failed to get postgres connection".to_string())
+    })?;
+
+    change_webhooks_columns::change_webhooks
+        .filter(change_webhooks_columns::dataset_id.eq(dataset_id))
+        .order_by(change_webhooks_columns::created_at.asc())
+        .select(ChangeWebhook::as_select())
+        .load::<ChangeWebhook>(&mut conn)
+        .await
+        .map_err(|err| {
+            log::error!("Failed to get change webhooks: {:?}", err);
+            ServiceError::InternalServerError("Failed to get change webhooks".to_string())
+        })
+}
+
+pub async fn get_change_webhook_query(
+    webhook_id: uuid::Uuid,
+    dataset_id: uuid::Uuid,
+    pool: web::Data<Pool>,
+) -> Result<ChangeWebhook, ServiceError> {
+    use crate::data::schema::change_webhooks::dsl as change_webhooks_columns;
+
+    let mut conn = pool.get().await.map_err(|_e| {
+        ServiceError::InternalServerError("Failed to get postgres connection".to_string())
+    })?;
+
+    change_webhooks_columns::change_webhooks
+        .filter(change_webhooks_columns::id.eq(webhook_id))
+        .filter(change_webhooks_columns::dataset_id.eq(dataset_id))
+        .select(ChangeWebhook::as_select())
+        .first::<ChangeWebhook>(&mut conn)
+        .await
+        .map_err(|_| ServiceError::NotFound("Change webhook not found".to_string()))
+}
+
+/// Setting `active` resets the failure count so a deactivated webhook is retried right away
+pub async fn update_change_webhook_query(
+    webhook_id: uuid::Uuid,
+    dataset_id: uuid::Uuid,
+    url: Option<String>,
+    operations: Option<Vec<String>>,
+    active: Option<bool>,
+    pool: web::Data<Pool>,
+) -> Result<ChangeWebhook, ServiceError> {
+    use crate::data::schema::change_webhooks::dsl as change_webhooks_columns;
+
+    let prev_webhook = get_change_webhook_query(webhook_id, dataset_id, pool.clone()).await?;
+
+    let mut conn = pool.get().await.map_err(|_e| {
+        ServiceError::InternalServerError("Failed to get postgres connection".to_string())
+    })?;
+
+    let reactivated = active.unwrap_or(prev_webhook.active) && !prev_webhook.active;
+
+    diesel::update(
+        change_webhooks_columns::change_webhooks
+            .filter(change_webhooks_columns::id.eq(webhook_id))
+            .filter(change_webhooks_columns::dataset_id.eq(dataset_id)),
+    )
+    .set((
+        change_webhooks_columns::url.eq(url.unwrap_or(prev_webhook.url)),
+        change_webhooks_columns::operations.eq(operations.or(prev_webhook.operations)),
+        change_webhooks_columns::active.eq(active.unwrap_or(prev_webhook.active)),
+        change_webhooks_columns::consecutive_failures.eq(if reactivated {
+            0
+        } else {
+            prev_webhook.consecutive_failures
+        }),
+        change_webhooks_columns::next_attempt_at.eq(if reactivated {
+            chrono::Utc::now().naive_local()
+        } else {
+            prev_webhook.next_attempt_at
+        }),
+        change_webhooks_columns::updated_at.eq(chrono::Utc::now().naive_local()),
+    ))
+    .get_result::<ChangeWebhook>(&mut conn)
+    .await
+    .map_err(|err| {
+        log::error!("Failed to update change webhook: {:?}", err);
+        ServiceError::InternalServerError("Failed to update change webhook".to_string())
+    })
+}
+
+pub async fn delete_change_webhook_query(
+    webhook_id: uuid::Uuid,
+    dataset_id: uuid::Uuid,
+    pool: web::Data<Pool>,
+) -> Result<(), ServiceError> {
+    use crate::data::schema::change_webhooks::dsl as change_webhooks_columns;
+
+    let mut conn = pool.get().await.map_err(|_e| {
+        ServiceError::InternalServerError("Failed to get postgres connection".to_string())
+    })?;
+
+    let deleted = diesel::delete(
+        change_webhooks_columns::change_webhooks
+            .filter(change_webhooks_columns::id.eq(webhook_id))
+            .filter(change_webhooks_columns::dataset_id.eq(dataset_id)),
+    )
+    .execute(&mut conn)
+    .await
+    .map_err(|err| {
+        log::error!("Failed to delete change webhook: {:?}", err);
+        ServiceError::InternalServerError("Failed to delete change webhook".to_string())
+    })?;
+
+    if deleted == 0 {
+        return Err(ServiceError::NotFound(
+            "Change webhook not found".to_string(),
+        ));
+    }
+
+    Ok(())
+}
+
+pub async fn get_change_webhook_deliveries_query(
+    webhook_id: uuid::Uuid,
+    page: i64,
+    page_size: i64,
+    pool: web::Data<Pool>,
+) -> Result<Vec<ChangeWebhookDelivery>, ServiceError> {
+    use crate::data::schema::change_webhook_deliveries::dsl as change_webhook_deliveries_columns;
+
+    let mut conn = pool.get().await.map_err(|_e| {
+        ServiceError::InternalServerError("Failed to get postgres connection".to_string())
+    })?;
+
+    change_webhook_deliveries_columns::change_webhook_deliveries
+        .filter(change_webhook_deliveries_columns::webhook_id.eq(webhook_id))
+        .order_by(change_webhook_deliveries_columns::created_at.desc())
+        .offset((page - 1).max(0) * page_size)
+        .limit(page_size)
+        .select(ChangeWebhookDelivery::as_select())
+        .load::<ChangeWebhookDelivery>(&mut conn)
+        .await
+        .map_err(|err| {
+            log::error!("Failed to get change webhook deliveries: {:?}", err);
+            ServiceError::InternalServerError("Failed to get change webhook deliveries".to_string())
+        })
+}
+
+/// Claims active webhooks which have undelivered changes and are due for an attempt. Claimed
+/// webhooks have their next attempt pushed back by `lease_secs` so concurrent workers skip them.
+pub async fn lease_due_change_webhooks_query(
+    limit: i64,
+    lease_secs: i64,
+    pool: web::Data<Pool>,
+) -> Result<Vec<ChangeWebhook>, ServiceError> {
+    use crate::data::schema::change_webhooks::dsl as change_webhooks_columns;
+
+    let mut conn = pool.get().await.map_err(|_e| {
+        ServiceError::InternalServerError("Failed to get postgres connection".to_string())
+    })?;
+
+    conn.transaction::<_, diesel::result::Error, _>(|conn| {
+        async move {
+            let now = chrono::Utc::now().naive_local();
+
+            let webhooks = change_webhooks_columns::change_webhooks
+                .filter(change_webhooks_columns::active.eq(true))
+                .filter(change_webhooks_columns::next_attempt_at.le(now))
+                .filter(sql::<sql_types::Bool>(
+                    "EXISTS (SELECT 1 FROM chunk_changes WHERE chunk_changes.dataset_id = change_webhooks.dataset_id AND chunk_changes.seq > change_webhooks.last_delivered_seq)",
+                ))
+                .order_by(change_webhooks_columns::next_attempt_at.asc())
+                .limit(limit)
+                .for_update()
+                .skip_locked()
+                .select(ChangeWebhook::as_select())
+                .load::<ChangeWebhook>(conn)
+                .await?;
+
+            diesel::update(
+                change_webhooks_columns::change_webhooks.filter(
+                    change_webhooks_columns::id
+                        .eq_any(webhooks.iter().map(|webhook| webhook.id).collect::<Vec<_>>()),
+                ),
+            )
+            .set(
+                change_webhooks_columns::next_attempt_at
+                    .eq(now + chrono::Duration::seconds(lease_secs)),
+            )
+            .execute(conn)
+            .await?;
+
+            Ok(webhooks)
+        }
+        .scope_boxed()
+    })
+    .await
+    .map_err(|err| {
+        log::error!("Failed to lease change webhooks: {:?}", err);
+        ServiceError::InternalServerError("Failed to lease change webhooks".to_string())
+    })
+}
+
+async fn record_change_webhook_result_query(
+    webhook: &ChangeWebhook,
+    delivery: ChangeWebhookDelivery,
+    pool: web::Data<Pool>,
+) -> Result<(), ServiceError> {
+    use crate::data::schema::change_webhook_deliveries::dsl as change_webhook_deliveries_columns;
+    use crate::data::schema::change_webhooks::dsl as change_webhooks_columns;
+
+    let mut conn = pool.get().await.map_err(|_e| {
+        ServiceError::InternalServerError("Failed to get postgres connection".to_string())
+    })?;
+
+    let webhook_id = webhook.id;
+    let now = chrono::Utc::now().naive_local();
+    let (last_delivered_seq, consecutive_failures, next_attempt_at) = if delivery.success {
+        (delivery.last_seq, 0, now)
+    } else {
+        let consecutive_failures = webhook.consecutive_failures + 1;
+        let backoff_secs = (10_i64 << consecutive_failures.min(9)).min(3600);
+
+        (
+            webhook.last_delivered_seq,
+            consecutive_failures,
+            now + chrono::Duration::seconds(backoff_secs),
+        )
+    };
+
+    if !delivery.success && consecutive_failures >= CHANGE_WEBHOOK_MAX_CONSECUTIVE_FAILURES {
+        log::warn!(
+            "Deactivating change webhook {} after {} failed deliveries",
+            webhook.id,
+            consecutive_failures
+        );
+    }
+
+    conn.transaction::<_, diesel::result::Error, _>(|conn| {
+        async move {
+            diesel::insert_into(change_webhook_deliveries_columns::change_webhook_deliveries)
+                .values(&delivery)
+                .execute(conn)
+                .await?;
+
+            diesel::update(
+                change_webhooks_columns::change_webhooks
+                    .filter(change_webhooks_columns::id.eq(webhook_id)),
+            )
+            .set((
+                change_webhooks_columns::last_delivered_seq.eq(last_delivered_seq),
+                change_webhooks_columns::consecutive_failures.eq(consecutive_failures),
+                change_webhooks_columns::next_attempt_at.eq(next_attempt_at),
+                change_webhooks_columns::active
+                    .eq(consecutive_failures < CHANGE_WEBHOOK_MAX_CONSECUTIVE_FAILURES),
+            ))
+            .execute(conn)
+            .await?;
+
+            Ok(())
+        }
+        .scope_boxed()
+    })
+    .await
+    .map_err(|err| {
+        log::error!("Failed to record change webhook delivery: {:?}", err);
+        ServiceError::InternalServerError("Failed to record change webhook delivery".to_string())
+    })
+}
+
+/// Moves the webhook's cursor past changes it is not subscribed to without recording a delivery
+async fn skip_change_webhook_changes_query(
+    webhook_id: uuid::Uuid,
+    last_seq: i64,
+    pool: web::Data<Pool>,
+) -> Result<(), ServiceError> {
+    use crate::data::schema::change_webhooks::dsl as change_webhooks_columns;
+
+    let mut conn = pool.get().await.map_err(|_e| {
+        ServiceError::InternalServerError("Failed to get postgres connection".to_string())
+    })?;
+
+    diesel::update(
+        change_webhooks_columns::change_webhooks.filter(change_webhooks_columns::id.eq(webhook_id)),
+    )
+    .set((
+        change_webhooks_columns::last_delivered_seq.eq(last_seq),
+        change_webhooks_columns::next_attempt_at.eq(chrono::Utc::now().naive_local()),
+    ))
+    .execute(&mut conn)
+    .await
+    .map_err(|err| {
+        log::error!("Failed to skip change webhook changes: {:?}", err);
+        ServiceError::InternalServerError("Failed to skip change webhook changes".to_string())
+    })?;
+
+    Ok(())
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ChangeWebhookPayload {
+    pub webhook_id: uuid::Uuid,
+    pub dataset_id: uuid::Uuid,
+    pub changes: Vec<ChunkChange>,
+}
+
+pub fn generate_webhook_secret() -> String {
+    format!(
+        "whsec_{}",
+        rand::thread_rng()
+            .sample_iter(&Alphanumeric)
+            .take(32)
+            .map(char::from)
+            .collect::<String>()
+    )
+}
+
+/// Hex encoded HMAC-SHA256 of `{timestamp}.{body}` keyed with the webhook's secret
+pub fn sign_webhook_payload(
+    secret: &str,
+    timestamp: i64,
+    body: &str,
+) -> Result<String, ServiceError> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).map_err(|_| {
+        ServiceError::InternalServerError("Failed to create webhook signer".to_string())
+    })?;
+    mac.update(format!("{}.{}", timestamp, body).as_bytes());
+
+    Ok(mac
+        .finalize()
+        .into_bytes()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect())
+}
+
+/// Resolves a webhook url, only https urls whose host resolves to public addresses are accepted
+/// so webhooks cannot be pointed at internal services.
+pub async fn resolve_webhook_url(
+    webhook_url: &str,
+) -> Result<(reqwest::Url, Vec<SocketAddr>), ServiceError> {
+    resolve_import_url(webhook_url).await.map_err(|_| {
+        ServiceError::BadRequest(
+            "Webhook url must be an https url which resolves to a public address".to_string(),
+        )
+    })
+}
+
+/// Builds the client for a single delivery. The url is re-resolved and its addresses pinned, and
+/// redirects are not followed, so neither a DNS change nor a redirect can send the delivery to an
+/// internal service.
+pub async fn build_webhook_client(
+    webhook_url: &str,
+) -> Result<(reqwest::Url, reqwest::Client), ServiceError> {
+    let (parsed_url, addrs) = resolve_webhook_url(webhook_url).await?;
+
+    let mut client_builder = reqwest::Client::builder().redirect(reqwest::redirect::Policy::none());
+    if let Some(domain) = parsed_url.domain() {
+        client_builder = client_builder.resolve_to_addrs(domain, &addrs);
+    }
+    let client = client_builder.build().map_err(|err| {
+        log::error!("Failed to build webhook client: {:?}", err);
+        ServiceError::InternalServerError("Failed to build webhook client".to_string())
+    })?;
+
+    Ok((parsed_url, client))
+}
+
+/// Sends the next batch of undelivered changes to the webhook and records the outcome. Changes
+/// are delivered at least once and in order, a failed batch is retried with exponential backoff.
+pub async fn deliver_change_webhook(
+    webhook: ChangeWebhook,
+    pool: web::Data<Pool>,
+) -> Result<(), ServiceError> {
+    let changes = get_chunk_changes_query(
+        webhook.dataset_id,
+        webhook.last_delivered_seq,
+        CHANGE_WEBHOOK_BATCH_SIZE,
+        pool.clone(),
+    )
+    .await?;
+
+    let (first_seq, last_seq) = match (changes.first(), changes.last()) {
+        (Some(first), Some(last)) => (first.seq, last.seq),
+        _ => return Ok(()),
+    };
+
+    let changes = changes
+        .into_iter()
+        .filter(|change| {
+            webhook
+                .operations
+                .as_ref()
+                .map_or(true, |operations| operations.contains(&change.operation))
+        })
+        .collect::<Vec<ChunkChange>>();
+
+    if changes.is_empty() {
+        return skip_change_webhook_changes_query(webhook.id, last_seq, pool).await;
+    }
+
+    let body = serde_json::to_string(&ChangeWebhookPayload {
+        webhook_id: webhook.id,
+        dataset_id: webhook.dataset_id,
+        changes,
+    })
+    .map_err(|_| ServiceError::BadRequest("Failed to serialize changes".to_string()))?;
+
+    let timestamp = chrono::Utc::now().timestamp();
+    let signature = sign_webhook_payload(&webhook.secret, timestamp, &body)?;
+
+    let (status_code, error) = match build_webhook_client(&webhook.url).await {
+        Ok((webhook_url, reqwest_client)) => {
+            send_webhook_request(
+                reqwest_client
+                    .post(webhook_url)
+                    .header("Content-Type", "application/json")
+                    .header("X-Trieve-Webhook-Id", webhook.id.to_string())
+                    .header("X-Trieve-Timestamp", timestamp.to_string())
+                    .header("X-Trieve-Signature", format!("sha256={}", signature))
+                    .timeout(std::time::Duration::from_secs(10))
+                    .body(body),
+            )
+            .await
+        }
+        Err(ServiceError::BadRequest(message)) => (None, Some(message)),
+        Err(err) => return Err(err),
+    };
+
+    let delivery = ChangeWebhookDelivery::from_details(
+        webhook.id,
+        first_seq,
+        last_seq,
+        webhook.consecutive_failures + 1,
+        status_code,
+        error,
+    );
+
+    record_change_webhook_result_query(&webhook, delivery, pool).await
+}
+
+/// Sends a delivery, returning the status code the webhook responded with and an error if the
+/// delivery did not succeed
+pub async fn send_webhook_request(
+    request: reqwest::RequestBuilder,
+) -> (Option<i32>, Option<String>) {
+    match request.send().await {
+        Ok(response) if response.status().is_success() => {
+            (Some(response.status().as_u16() as i32), None)
+        }
+        Ok(response) => (
+            Some(response.status().as_u16() as i32),
+            Some(format!(
+                "Webhook responded with status {}",
+                response.status()
+            )),
+        ),
+        Err(err) => (
+            err.status().map(|status| status.as_u16() as i32),
+            Some(err.to_string()),
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chunk(chunk_html: &str) -> ChunkMetadata {
+        ChunkMetadata::from_details(
+            &Some(chunk_html.to_string()),
+            &None,
+            &None,
+            uuid::Uuid::new_v4(),
+            None,
+            None,
+            None,
+            None,
+            None,
+            uuid::Uuid::new_v4(),
+            0.0,
+            None,
+        )
+    }
+
+    #[test]
+    fn test_get_chunk_fields_set() {
+        let mut new_chunk = chunk("<p>Hello</p>");
+        assert_eq!(get_chunk_fields_set(&new_chunk, false), vec!["chunk_html"]);
+
+        new_chunk.tracking_id = Some("chunk-1".to_string());
+        new_chunk.tag_set = Some(vec![Some("docs".to_string())]);
+        new_chunk.weight = 2.0;
+        assert_eq!(
+            get_chunk_fields_set(&new_chunk, true),
+            vec![
+                "chunk_html",
+                "tracking_id",
+                "tag_set",
+                "weight",
+                "group_ids"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_get_chunk_fields_changed() {
+        let prev_chunk = chunk("<p>Hello</p>");
+        assert!(get_chunk_fields_changed(&prev_chunk, &prev_chunk.clone()).is_empty());
+
+        let mut new_chunk = prev_chunk.clone();
+        new_chunk.chunk_html = Some("<p>Goodbye</p>".to_string());
+        new_chunk.metadata = Some(serde_json::json!({"key": "value"}));
+        new_chunk.num_value = Some(1.0);
+        assert_eq!(
+            get_chunk_fields_changed(&prev_chunk, &new_chunk),
+            vec!["chunk_html", "metadata", "num_value"]
+        );
+    }
+
+    #[test]
+    fn test_sign_webhook_payload() {
+        let signature = sign_webhook_payload("whsec_test", 1700000000, "{\"changes\":[]}").unwrap();
+
+        assert_eq!(
+            signature,
+            "a4fc8f32792a74a56e1d536ebc3dacdc16611ade5555bd951270fcddc1c98065"
+        );
+        assert_ne!(
+            signature,
+            sign_webhook_payload("whsec_test", 1700000001, "{\"changes\":[]}").unwrap()
+        );
+    }
+}
@@ -1,11 +1,13 @@
 use crate::data::models::{
-    uuid_between, ChunkBoost, ChunkBoostChangeset, ChunkData, ChunkGroup, ChunkGroupAndFileId,
-    ChunkGroupBookmark, ChunkMetadataTable, ChunkMetadataTags, ChunkMetadataTypes,
-    ContentChunkMetadata, Dataset, DatasetConfiguration, DatasetTags, DatasetUsageCount,
-    IngestSpecificChunkMetadata, SlimChunkMetadata, SlimChunkMetadataTable, UnifiedId,
+    uuid_between, ChunkBoost, ChunkBoostChangeset, ChunkChangeOperation, ChunkData, ChunkGroup,
+    ChunkGroupAndFileId, ChunkGroupBookmark, ChunkMetadataTable, ChunkMetadataTags,
+    ChunkMetadataTypes, ContentChunkMetadata, ContextWindow, Dataset, DatasetConfiguration,
+    DatasetTags, DatasetUsageCount, ExpandedContent, IngestSpecificChunkMetadata, NewChunkChange,
+    ScoreChunk, SlimChunkMetadata, SlimChunkMetadataTable, UnifiedId,
 };
 use crate::handlers::chunk_handler::{BulkUploadIngestionMessage, ChunkReqPayload};
-use crate::handlers::chunk_handler::{ChunkFilter, UploadIngestionMessage};
+use crate::handlers::chunk_handler::{ChunkFilter, JsonPatchOperation, UploadIngestionMessage};
+use crate::operators::change_feed_operator::insert_chunk_changes_query;
 use crate::operators::parse_operator::convert_html_to_text;
 use crate::operators::qdrant_operator::{
     delete_points_from_qdrant, get_qdrant_collection_from_dataset_config, scroll_dataset_points,
@@ -534,6 +536,8 @@ pub async fn get_metadata_from_tracking_ids_query(
     Ok(chunk_metadatas)
 }
 
+/// Deletes the chunks matching the filter which were created before `deleted_at`. Returns the
+/// ids of the groups the deleted chunks belonged to.
 #[tracing::instrument(skip_all)]
 pub async fn bulk_delete_chunks_query(
     filter: Option<ChunkFilter>,
@@ -541,7 +545,8 @@ pub async fn bulk_delete_chunks_query(
     dataset_id: uuid::Uuid,
     dataset_config: DatasetConfiguration,
     pool: web::Data<Pool>,
-) -> Result<(), ServiceError> {
+) -> Result<Vec<uuid::Uuid>, ServiceError> {
+    use crate::data::schema::chunk_group_bookmarks::dsl as chunk_group_bookmarks_columns;
     use crate::data::schema::chunk_metadata::dsl as chunk_metadata_columns;
 
     if dataset_config.LOCKED {
@@ -559,6 +564,7 @@ pub async fn bulk_delete_chunks_query(
         .expect("Failed to get connection to db");
     let mut offset: Option<uuid::Uuid> = None;
     let mut first_iteration = true;
+    let mut deleted_group_ids: Vec<uuid::Uuid> = vec![];
 
     while offset.is_some() || first_iteration {
         let (search_results, offset_id) =
@@ -579,28 +585,49 @@ pub async fn bulk_delete_chunks_query(
                 .transaction::<_, diesel::result::Error, _>(|conn| {
                     async move {
                         {
-                            let deleted_ids_uuids: Vec<(uuid::Uuid, uuid::Uuid)> = diesel::delete(
-                                chunk_metadata_columns::chunk_metadata
-                                    .filter(
-                                        chunk_metadata_columns::qdrant_point_id
-                                            .eq_any(qdrant_point_ids.clone()),
-                                    )
-                                    .filter(chunk_metadata_columns::dataset_id.eq(dataset_id))
-                                    .filter(chunk_metadata_columns::created_at.le(deleted_at)),
-                            )
-                            .returning((
-                                chunk_metadata_columns::id,
-                                chunk_metadata_columns::qdrant_point_id,
-                            ))
-                            .get_results::<(uuid::Uuid, uuid::Uuid)>(conn)
-                            .await?;
-
-                            let (_deleted_ids, deleted_point_ids): (
-                                Vec<uuid::Uuid>,
-                                Vec<uuid::Uuid>,
-                            ) = deleted_ids_uuids.into_iter().unzip();
-
-                            Ok(deleted_point_ids)
+                            // Memberships are removed along with the chunks, so their groups are
+                            // read first
+                            let group_ids = chunk_group_bookmarks_columns::chunk_group_bookmarks
+                                .filter(
+                                    chunk_group_bookmarks_columns::chunk_metadata_id.eq_any(
+                                        chunk_metadata_columns::chunk_metadata
+                                            .filter(
+                                                chunk_metadata_columns::qdrant_point_id
+                                                    .eq_any(qdrant_point_ids.clone()),
+                                            )
+                                            .filter(
+                                                chunk_metadata_columns::dataset_id.eq(dataset_id),
+                                            )
+                                            .filter(
+                                                chunk_metadata_columns::created_at.le(deleted_at),
+                                            )
+                                            .select(chunk_metadata_columns::id),
+                                    ),
+                                )
+                                .select(chunk_group_bookmarks_columns::group_id)
+                                .distinct()
+                                .load::<uuid::Uuid>(conn)
+                                .await?;
+
+                            let deleted_ids_uuids: Vec<(uuid::Uuid, uuid::Uuid, Option<String>)> =
+                                diesel::delete(
+                                    chunk_metadata_columns::chunk_metadata
+                                        .filter(
+                                            chunk_metadata_columns::qdrant_point_id
+                                                .eq_any(qdrant_point_ids.clone()),
+                                        )
+                                        .filter(chunk_metadata_columns::dataset_id.eq(dataset_id))
+                                        .filter(chunk_metadata_columns::created_at.le(deleted_at)),
+                                )
+                                .returning((
+                                    chunk_metadata_columns::id,
+                                    chunk_metadata_columns::qdrant_point_id,
+                                    chunk_metadata_columns::tracking_id,
+                                ))
+                                .get_results::<(uuid::Uuid, uuid::Uuid, Option<String>)>(conn)
+                                .await?;
+
+                            Ok((deleted_ids_uuids, group_ids))
                         }
                     }
                     .scope_boxed()
@@ -608,8 +635,34 @@ pub async fn bulk_delete_chunks_query(
                 .await;
 
             match deleted_point_ids {
-                Ok(point_ids) => {
-                    delete_points_from_qdrant(point_ids, qdrant_collection.clone()).await?;
+                Ok((deleted_ids_uuids, group_ids)) => {
+                    deleted_group_ids.extend(group_ids);
+
+                    delete_points_from_qdrant(
+                        deleted_ids_uuids
+                            .iter()
+                            .map(|(_, point_id, _)| *point_id)
+                            .collect(),
+                        qdrant_collection.clone(),
+                    )
+                    .await?;
+
+                    insert_chunk_changes_query(
+                        deleted_ids_uuids
+                            .into_iter()
+                            .map(|(chunk_id, _, tracking_id)| {
+                                NewChunkChange::from_details(
+                                    dataset_id,
+                                    ChunkChangeOperation::Delete,
+                                    Some(chunk_id),
+                                    tracking_id,
+                                    vec![],
+                                )
+                            })
+                            .collect(),
+                        pool.clone(),
+                    )
+                    .await?;
                 }
                 Err(e) => {
                     log::error!("Failed to delete chunks: {:?}", e);
@@ -619,15 +672,50 @@ pub async fn bulk_delete_chunks_query(
                 }
             }
         } else {
+            deleted_group_ids.extend(search_results.iter().flat_map(|search_result| {
+                search_result
+                    .payload
+                    .get("group_ids")
+                    .and_then(|group_ids| group_ids.as_list())
+                    .unwrap_or_default()
+                    .iter()
+                    .filter_map(|group_id| group_id.as_str()?.parse::<uuid::Uuid>().ok())
+                    .collect::<Vec<uuid::Uuid>>()
+            }));
+
             delete_points_from_qdrant(qdrant_point_ids.clone(), qdrant_collection.clone()).await?;
             update_dataset_chunk_count(dataset_id, -(qdrant_point_ids.len() as i32), pool.clone())
                 .await?;
+
+            insert_chunk_changes_query(
+                search_results
+                    .iter()
+                    .map(|search_result| {
+                        NewChunkChange::from_details(
+                            dataset_id,
+                            ChunkChangeOperation::Delete,
+                            Some(search_result.point_id),
+                            search_result
+                                .payload
+                                .get("tracking_id")
+                                .and_then(|tracking_id| tracking_id.as_str().cloned()),
+                            vec![],
+                        )
+                    })
+                    .collect(),
+                pool.clone(),
+            )
+            .await?;
         }
 
         offset = offset_id;
         first_iteration = false;
     }
-    Ok(())
+
+    deleted_group_ids.sort();
+    deleted_group_ids.dedup();
+
+    Ok(deleted_group_ids)
 }
 
 /// Only inserts, does not try to upsert data
@@ -1468,17 +1556,21 @@ pub async fn delete_chunk_metadata_query(
         .transaction::<_, diesel::result::Error, _>(|conn| {
             async move {
                 {
-                    let deleted_points = diesel::delete(
+                    let deleted_chunks = diesel::delete(
                         chunk_metadata_columns::chunk_metadata
                             .filter(chunk_metadata_columns::id.eq_any(chunk_uuid.clone()))
                             .filter(chunk_metadata_columns::dataset_id.eq(dataset.id))
                             .filter(chunk_metadata_columns::created_at.le(deleted_at)),
                     )
-                    .returning(chunk_metadata_columns::qdrant_point_id)
-                    .get_results::<uuid::Uuid>(conn)
+                    .returning((
+                        chunk_metadata_columns::id,
+                        chunk_metadata_columns::qdrant_point_id,
+                        chunk_metadata_columns::tracking_id,
+                    ))
+                    .get_results::<(uuid::Uuid, uuid::Uuid, Option<String>)>(conn)
                     .await?;
 
-                    Ok(deleted_points)
+                    Ok(deleted_chunks)
                 }
             }
             .scope_boxed()
@@ -1488,12 +1580,36 @@ pub async fn delete_chunk_metadata_query(
     let qdrant_collection = get_qdrant_collection_from_dataset_config(&dataset_config);
 
     match transaction_result {
-        Ok(deleted_points) => delete_points_from_qdrant(deleted_points, qdrant_collection)
+        Ok(deleted_chunks) => {
+            delete_points_from_qdrant(
+                deleted_chunks
+                    .iter()
+                    .map(|(_, point_id, _)| *point_id)
+                    .collect(),
+                qdrant_collection,
+            )
             .await
             .map_err(|_e| {
                 ServiceError::BadRequest("Failed to delete chunk from qdrant".to_string())
-            })
-            .map(|_| ()),
+            })?;
+
+            insert_chunk_changes_query(
+                deleted_chunks
+                    .into_iter()
+                    .map(|(chunk_id, _, tracking_id)| {
+                        NewChunkChange::from_details(
+                            dataset.id,
+                            ChunkChangeOperation::Delete,
+                            Some(chunk_id),
+                            tracking_id,
+                            vec![],
+                        )
+                    })
+                    .collect(),
+                pool,
+            )
+            .await
+        }
         Err(_) => Err(ServiceError::BadRequest(
             "Failed to delete chunk data".to_string(),
         )),
@@ -2870,6 +2986,7 @@ pub async fn scroll_chunks_with_boosts_and_groups(
                 tag_set: group.tag_set,
                 metadata: group.metadata,
                 file_id,
+                parent_id: group.parent_id,
                 created_at: group.created_at,
                 updated_at: group.updated_at,
             };
@@ -3014,3 +3131,704 @@ pub async fn get_chunk_queue_length(
         + regular_ingestion_queue_status.size as i64
         + premium_ingestion_queue_status.size as i64)
 }
+
+/// Applies an RFC 7396 JSON merge patch to `target`. Keys set to `null` in the patch are removed.
+pub fn apply_json_merge_patch(target: &mut serde_json::Value, patch: &serde_json::Value) {
+    match patch {
+        serde_json::Value::Object(patch_map) => {
+            if !target.is_object() {
+                *target = serde_json::Value::Object(serde_json::Map::new());
+            }
+
+            if let serde_json::Value::Object(target_map) = target {
+                for (key, value) in patch_map {
+                    if value.is_null() {
+                        target_map.remove(key);
+                    } else {
+                        apply_json_merge_patch(
+                            target_map
+                                .entry(key.clone())
+                                .or_insert(serde_json::Value::Null),
+                            value,
+                        );
+                    }
+                }
+            }
+        }
+        _ => *target = patch.clone(),
+    }
+}
+
+fn split_json_pointer(path: &str) -> Result<(&str, String), ServiceError> {
+    if !path.starts_with('/') {
+        return Err(ServiceError::BadRequest(format!(
+            "Invalid JSON pointer {:?}, paths must start with '/'",
+            path
+        )));
+    }
+
+    let split_index = path.rfind('/').unwrap_or(0);
+    let token = path[split_index + 1..]
+        .replace("~1", "/")
+        .replace("~0", "~");
+
+    Ok((&path[..split_index], token))
+}
+
+fn json_patch_add(
+    target: &mut serde_json::Value,
+    path: &str,
+    value: serde_json::Value,
+) -> Result<(), ServiceError> {
+    if path.is_empty() {
+        *target = value;
+        return Ok(());
+    }
+
+    let (parent_path, token) = split_json_pointer(path)?;
+    let parent = target.pointer_mut(parent_path).ok_or_else(|| {
+        ServiceError::BadRequest(format!("Parent of path {:?} does not exist", path))
+    })?;
+
+    match parent {
+        serde_json::Value::Object(map) => {
+            map.insert(token, value);
+            Ok(())
+        }
+        serde_json::Value::Array(array) => {
+            if token == "-" {
+                array.push(value);
+                return Ok(());
+            }
+
+            let index = token
+                .parse::<usize>()
+                .ok()
+                .filter(|index| *index <= array.len())
+                .ok_or_else(|| {
+                    ServiceError::BadRequest(format!("Invalid array index in path {:?}", path))
+                })?;
+            array.insert(index, value);
+            Ok(())
+        }
+        _ => Err(ServiceError::BadRequest(format!(
+            "Parent of path {:?} is not an object or array",
+            path
+        ))),
+    }
+}
+
+fn json_patch_remove(
+    target: &mut serde_json::Value,
+    path: &str,
+) -> Result<serde_json::Value, ServiceError> {
+    let (parent_path, token) = split_json_pointer(path)?;
+    let parent = target
+        .pointer_mut(parent_path)
+        .ok_or_else(|| ServiceError::BadRequest(format!("Path {:?} does not exist", path)))?;
+
+    match parent {
+        serde_json::Value::Object(map) => map
+            .remove(&token)
+            .ok_or_else(|| ServiceError::BadRequest(format!("Path {:?} does not exist", path))),
+        serde_json::Value::Array(array) => {
+            let index = token
+                .parse::<usize>()
+                .ok()
+                .filter(|index| *index < array.len())
+                .ok_or_else(|| {
+                    ServiceError::BadRequest(format!("Invalid array index in path {:?}", path))
+                })?;
+            Ok(array.remove(index))
+        }
+        _ => Err(ServiceError::BadRequest(format!(
+            "Path {:?} does not exist",
+            path
+        ))),
+    }
+}
+
+/// Applies a list of RFC 6902 JSON patch operations to `target`. Operations are applied to a copy
+/// so `target` is left untouched if any operation fails.
+pub fn apply_json_patch(
+    target: &mut serde_json::Value,
+    operations: &[JsonPatchOperation],
+) -> Result<(), ServiceError> {
+    let mut patched = target.clone();
+
+    for operation in operations {
+        match operation {
+            JsonPatchOperation::Add { path, value } => {
+                json_patch_add(&mut patched, path, value.clone())?;
+            }
+            JsonPatchOperation::Remove { path } => {
+                json_patch_remove(&mut patched, path)?;
+            }
+            JsonPatchOperation::Replace { path, value } => {
+                let existing = patched.pointer_mut(path).ok_or_else(|| {
+                    ServiceError::BadRequest(format!("Path {:?} does not exist", path))
+                })?;
+                *existing = value.clone();
+            }
+            JsonPatchOperation::Move { from, path } => {
+                if path.starts_with(&format!("{}/", from)) {
+                    return Err(ServiceError::BadRequest(format!(
+                        "Cannot move {:?} into one of its children",
+                        from
+                    )));
+                }
+                let value = json_patch_remove(&mut patched, from)?;
+                json_patch_add(&mut patched, path, value)?;
+            }
+            JsonPatchOperation::Copy { from, path } => {
+                let value = patched.pointer(from).cloned().ok_or_else(|| {
+                    ServiceError::BadRequest(format!("Path {:?} does not exist", from))
+                })?;
+                json_patch_add(&mut patched, path, value)?;
+            }
+            JsonPatchOperation::Test { path, value } => {
+                if patched.pointer(path) != Some(value) {
+                    return Err(ServiceError::BadRequest(format!(
+                        "Test operation failed for path {:?}",
+                        path
+                    )));
+                }
+            }
+        }
+    }
+
+    *target = patched;
+    Ok(())
+}
+
+/// Records where a chunk appeared in the document it was created from so that its neighbors can be
+/// fetched with a `ContextWindow`. Positions only need to be ordered, they do not have to be
+/// contiguous.
+pub fn metadata_with_source_position(
+    metadata: Option<serde_json::Value>,
+    source_document: &str,
+    position: i64,
+) -> Option<serde_json::Value> {
+    match metadata {
+        Some(serde_json::Value::Object(mut metadata)) => {
+            metadata.insert(
+                "source_document".to_string(),
+                serde_json::json!(source_document),
+            );
+            metadata.insert("source_position".to_string(), serde_json::json!(position));
+            Some(serde_json::Value::Object(metadata))
+        }
+        None => Some(serde_json::json!({
+            "source_document": source_document,
+            "source_position": position,
+        })),
+        metadata => metadata,
+    }
+}
+
+fn normalize_location_text(text: &str) -> String {
+    text.chars()
+        .map(|c| {
+            if c.is_alphanumeric() {
+                c.to_ascii_lowercase()
+            } else {
+                ' '
+            }
+        })
+        .collect::<String>()
+        .split_whitespace()
+        .join(" ")
+}
+
+fn segment_bounding_box(segment: &serde_json::Value) -> Option<serde_json::Value> {
+    let bbox = segment.get("bbox")?;
+    Some(serde_json::json!({
+        "page_num": segment.get("page_number"),
+        "left": bbox.get("left"),
+        "top": bbox.get("top"),
+        "width": bbox.get("width"),
+        "height": bbox.get("height"),
+        "page_width": segment.get("page_width"),
+        "page_height": segment.get("page_height"),
+    }))
+}
+
+/// Records the page location of a chunk created from a pdf2md or Chunkr page. The bounding boxes
+/// of the page's segments whose text appears in the chunk are stored as `bounding_boxes`, falling
+/// back to every segment on the page when none of them can be matched, and `page_num` is filled
+/// in from the segments if it is missing.
+pub fn metadata_with_page_location(
+    metadata: Option<serde_json::Value>,
+    chunk_html: &str,
+) -> Option<serde_json::Value> {
+    let mut metadata = match metadata {
+        Some(serde_json::Value::Object(metadata)) => metadata,
+        metadata => return metadata,
+    };
+
+    let Some(segments) = metadata
+        .get("segments")
+        .and_then(|segments| segments.as_array())
+        .filter(|segments| !segments.is_empty())
+        .cloned()
+    else {
+        return Some(serde_json::Value::Object(metadata));
+    };
+
+    let chunk_text = normalize_location_text(chunk_html);
+    let matched_bounding_boxes = segments
+        .iter()
+        .filter(|segment| {
+            let segment_text = normalize_location_text(
+                segment
+                    .get("content")
+                    .and_then(|content| content.as_str())
+                    .unwrap_or_default(),
+            );
+            segment_text.len() >= 8 && chunk_text.contains(&segment_text)
+        })
+        .filter_map(segment_bounding_box)
+        .collect::<Vec<serde_json::Value>>();
+
+    let bounding_boxes = if matched_bounding_boxes.is_empty() {
+        segments
+            .iter()
+            .filter_map(segment_bounding_box)
+            .collect::<Vec<serde_json::Value>>()
+    } else {
+        matched_bounding_boxes
+    };
+
+    if !metadata.contains_key("page_num") {
+        if let Some(page_num) = bounding_boxes
+            .iter()
+            .filter_map(|bounding_box| bounding_box["page_num"].as_i64())
+            .min()
+        {
+            metadata.insert("page_num".to_string(), serde_json::json!(page_num));
+        }
+    }
+    metadata.insert(
+        "bounding_boxes".to_string(),
+        serde_json::json!(bounding_boxes),
+    );
+
+    Some(serde_json::Value::Object(metadata))
+}
+
+#[derive(QueryableByName, Debug)]
+struct ContextWindowChunk {
+    /// Index of the search result whose window the chunk belongs to
+    #[diesel(sql_type = sql_types::Integer)]
+    result_index: i32,
+    #[diesel(sql_type = sql_types::Uuid)]
+    id: uuid::Uuid,
+    #[diesel(sql_type = sql_types::Nullable<sql_types::Text>)]
+    chunk_html: Option<String>,
+    #[diesel(sql_type = sql_types::BigInt)]
+    position: i64,
+}
+
+/// Loads the context windows of every result in a single query. Each result is a
+/// `(result_index, source_document, position, heading_path)` tuple, where a heading path is only
+/// given when the whole parent section should be returned instead of the neighboring chunks.
+#[tracing::instrument(skip_all)]
+async fn get_context_window_chunks_query(
+    results: Vec<(i32, String, i64, Option<serde_json::Value>)>,
+    context_window: &ContextWindow,
+    dataset_id: uuid::Uuid,
+    pool: web::Data<Pool>,
+) -> Result<Vec<ContextWindowChunk>, ServiceError> {
+    if results.is_empty() {
+        return Ok(vec![]);
+    }
+
+    let mut result_indices = vec![];
+    let mut source_documents = vec![];
+    let mut positions = vec![];
+    let mut heading_paths = vec![];
+    for (result_index, source_document, position, heading_path) in results {
+        result_indices.push(result_index);
+        source_documents.push(source_document);
+        positions.push(position);
+        heading_paths.push(heading_path);
+    }
+
+    let mut conn = pool.get().await.map_err(|_e| {
+        ServiceError::InternalServerError("Failed to get postgres connection".to_string())
+    })?;
+
+    diesel::sql_query(
+        "WITH results AS (
+            SELECT * FROM unnest($1::int4[], $2::text[], $3::int8[], $4::jsonb[])
+                AS results(result_index, source_document, position, heading_path)
+        )
+        SELECT results.result_index, window_chunks.id, window_chunks.chunk_html,
+            (window_chunks.metadata->>'source_position')::int8 AS position
+        FROM results
+        CROSS JOIN LATERAL (
+            (SELECT id, chunk_html, metadata FROM chunk_metadata
+            WHERE chunk_metadata.dataset_id = $5
+                AND chunk_metadata.metadata->>'source_document' = results.source_document
+                AND results.heading_path IS NOT NULL
+                AND chunk_metadata.metadata->'heading_path' = results.heading_path)
+            UNION ALL
+            (SELECT id, chunk_html, metadata FROM chunk_metadata
+            WHERE chunk_metadata.dataset_id = $5
+                AND chunk_metadata.metadata->>'source_document' = results.source_document
+                AND results.heading_path IS NULL
+                AND chunk_metadata.metadata->'source_position' < to_jsonb(results.position)
+            ORDER BY chunk_metadata.metadata->'source_position' DESC
+            LIMIT $6)
+            UNION ALL
+            (SELECT id, chunk_html, metadata FROM chunk_metadata
+            WHERE chunk_metadata.dataset_id = $5
+                AND chunk_metadata.metadata->>'source_document' = results.source_document
+                AND results.heading_path IS NULL
+                AND chunk_metadata.metadata->'source_position' >= to_jsonb(results.position)
+            ORDER BY chunk_metadata.metadata->'source_position' ASC
+            LIMIT $7)
+        ) window_chunks
+        WHERE jsonb_typeof(window_chunks.metadata->'source_position') = 'number'
+        ORDER BY results.result_index",
+    )
+    .bind::<sql_types::Array<sql_types::Integer>, _>(result_indices)
+    .bind::<sql_types::Array<sql_types::Text>, _>(source_documents)
+    .bind::<sql_types::Array<sql_types::BigInt>, _>(positions)
+    .bind::<sql_types::Array<sql_types::Nullable<sql_types::Jsonb>>, _>(heading_paths)
+    .bind::<sql_types::Uuid, _>(dataset_id)
+    .bind::<sql_types::BigInt, _>(context_window.before.unwrap_or(1) as i64)
+    .bind::<sql_types::BigInt, _>(context_window.after.unwrap_or(1) as i64 + 1)
+    .load::<ContextWindowChunk>(&mut conn)
+    .await
+    .map_err(|e| {
+        log::error!("Failed to load context window chunks {:?}", e);
+        ServiceError::BadRequest("Failed to load context window chunks".to_string())
+    })
+}
+
+/// Groups the loaded chunks into one window per result and merges the windows of results from the
+/// same document which overlap. Returns the indices of the results sharing each merged window.
+fn merge_context_windows(
+    window_chunks: Vec<ContextWindowChunk>,
+    source_documents: &HashMap<i32, String>,
+) -> Vec<(Vec<usize>, ExpandedContent)> {
+    // (source_document, index of the result, chunks in its window)
+    let mut windows: Vec<(String, usize, Vec<ContextWindowChunk>)> = window_chunks
+        .into_iter()
+        .into_group_map_by(|chunk| chunk.result_index)
+        .into_iter()
+        .filter_map(|(result_index, mut window)| {
+            window.sort_by_key(|chunk| chunk.position);
+            Some((
+                source_documents.get(&result_index)?.clone(),
+                result_index as usize,
+                window,
+            ))
+        })
+        .collect();
+
+    windows.sort_by_key(|(source_document, _, window)| {
+        (
+            source_document.clone(),
+            window.first().map(|chunk| chunk.position),
+        )
+    });
+
+    let mut merged_windows: Vec<(String, Vec<usize>, Vec<ContextWindowChunk>)> = vec![];
+    for (source_document, result_index, window) in windows {
+        let window_start = window.first().map(|chunk| chunk.position);
+        match merged_windows.last_mut() {
+            Some((merged_source, result_indices, merged_window))
+                if *merged_source == source_document
+                    && merged_window.last().map(|chunk| chunk.position) >= window_start =>
+            {
+                result_indices.push(result_index);
+                for chunk in window {
+                    if !merged_window.iter().any(|merged| merged.id == chunk.id) {
+                        merged_window.push(chunk);
+                    }
+                }
+                merged_window.sort_by_key(|chunk| chunk.position);
+            }
+            _ => merged_windows.push((source_document, vec![result_index], window)),
+        }
+    }
+
+    merged_windows
+        .into_iter()
+        .map(|(_, result_indices, merged_window)| {
+            (
+                result_indices,
+                ExpandedContent {
+                    chunk_ids: merged_window.iter().map(|chunk| chunk.id).collect(),
+                    content: merged_window
+                        .iter()
+                        .filter_map(|chunk| chunk.chunk_html.clone())
+                        .join("\n\n"),
+                },
+            )
+        })
+        .collect()
+}
+
+/// Expands each result with the chunks around it in the document it was created from. Results from
+/// the same document whose windows overlap share a single merged window.
+#[tracing::instrument(skip_all)]
+pub async fn expand_score_chunks_with_context_query(
+    score_chunks: &mut [ScoreChunk],
+    context_window: &ContextWindow,
+    dataset_id: uuid::Uuid,
+    pool: web::Data<Pool>,
+) -> Result<(), ServiceError> {
+    use crate::data::schema::chunk_metadata::dsl as chunk_metadata_columns;
+
+    let chunk_ids = score_chunks
+        .iter()
+        .map(|score_chunk| ChunkMetadata::from(score_chunk.chunk.clone()).id)
+        .collect::<Vec<uuid::Uuid>>();
+
+    let mut conn = pool.get().await.map_err(|_e| {
+        ServiceError::InternalServerError("Failed to get postgres connection".to_string())
+    })?;
+
+    let chunk_metadatas: HashMap<uuid::Uuid, serde_json::Value> =
+        chunk_metadata_columns::chunk_metadata
+            .filter(chunk_metadata_columns::id.eq_any(&chunk_ids))
+            .filter(chunk_metadata_columns::dataset_id.eq(dataset_id))
+            .select((chunk_metadata_columns::id, chunk_metadata_columns::metadata))
+            .load::<(uuid::Uuid, Option<serde_json::Value>)>(&mut conn)
+            .await
+            .map_err(|e| {
+                log::error!("Failed to load chunk metadata for context window {:?}", e);
+                ServiceError::BadRequest(
+                    "Failed to load chunk metadata for context window".to_string(),
+                )
+            })?
+            .into_iter()
+            .filter_map(|(id, metadata)| metadata.map(|metadata| (id, metadata)))
+            .collect();
+    drop(conn);
+
+    let parent_section = context_window.parent_section.unwrap_or(false);
+    let mut source_documents: HashMap<i32, String> = HashMap::new();
+    let mut results = vec![];
+    for (i, chunk_id) in chunk_ids.iter().enumerate() {
+        let Some(metadata) = chunk_metadatas.get(chunk_id) else {
+            continue;
+        };
+        let (Some(source_document), Some(position)) = (
+            metadata
+                .get("source_document")
+                .and_then(|value| value.as_str()),
+            metadata
+                .get("source_position")
+                .and_then(|value| value.as_i64()),
+        ) else {
+            continue;
+        };
+
+        let heading_path = if parent_section {
+            metadata
+                .get("heading_path")
+                .filter(|heading_path| !heading_path.is_null())
+                .cloned()
+        } else {
+            None
+        };
+
+        source_documents.insert(i as i32, source_document.to_string());
+        results.push((
+            i as i32,
+            source_document.to_string(),
+            position,
+            heading_path,
+        ));
+    }
+
+    let window_chunks =
+        get_context_window_chunks_query(results, context_window, dataset_id, pool).await?;
+
+    for (result_indices, expanded_content) in
+        merge_context_windows(window_chunks, &source_documents)
+    {
+        for result_index in result_indices {
+            score_chunks[result_index].expanded_content = Some(expanded_content.clone());
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    pub fn test_json_merge_patch() {
+        let mut target = json!({"price": 10, "stock": {"sf": 3, "nyc": 0}, "color": "red"});
+        apply_json_merge_patch(
+            &mut target,
+            &json!({"price": 12, "stock": {"nyc": 5}, "color": null}),
+        );
+
+        assert_eq!(target, json!({"price": 12, "stock": {"sf": 3, "nyc": 5}}));
+    }
+
+    #[test]
+    pub fn test_json_patch() {
+        let mut target = json!({"price": 10, "sizes": ["s", "m"]});
+        let operations: Vec<JsonPatchOperation> = serde_json::from_value(json!([
+            {"op": "test", "path": "/price", "value": 10},
+            {"op": "replace", "path": "/price", "value": 12},
+            {"op": "add", "path": "/sizes/-", "value": "l"},
+            {"op": "move", "from": "/price", "path": "/sale_price"},
+        ]))
+        .unwrap();
+
+        apply_json_patch(&mut target, &operations).unwrap();
+        assert_eq!(target, json!({"sale_price": 12, "sizes": ["s", "m", "l"]}));
+
+        let failing: Vec<JsonPatchOperation> = serde_json::from_value(json!([
+            {"op": "remove", "path": "/sizes/0"},
+            {"op": "test", "path": "/sale_price", "value": 10},
+        ]))
+        .unwrap();
+
+        assert!(apply_json_patch(&mut target, &failing).is_err());
+        assert_eq!(target, json!({"sale_price": 12, "sizes": ["s", "m", "l"]}));
+    }
+
+    fn location_segment(content: &str, page_number: i64, top: f64) -> serde_json::Value {
+        json!({
+            "content": content,
+            "page_number": page_number,
+            "page_width": 612.0,
+            "page_height": 792.0,
+            "bbox": {"left": 72.0, "top": top, "width": 468.0, "height": 24.0},
+        })
+    }
+
+    fn location_box(page_number: i64, top: f64) -> serde_json::Value {
+        json!({
+            "page_num": page_number,
+            "left": 72.0,
+            "top": top,
+            "width": 468.0,
+            "height": 24.0,
+            "page_width": 612.0,
+            "page_height": 792.0,
+        })
+    }
+
+    #[test]
+    pub fn test_metadata_with_page_location_matched_box() {
+        let metadata = json!({
+            "segments": [
+                location_segment("Quarterly revenue grew", 2, 100.0),
+                location_segment("Unrelated footer text", 3, 700.0),
+            ],
+        });
+
+        let metadata = metadata_with_page_location(
+            Some(metadata),
+            "<p>Quarterly <b>revenue</b> grew by 10%</p>",
+        )
+        .unwrap();
+
+        assert_eq!(metadata["bounding_boxes"], json!([location_box(2, 100.0)]));
+        assert_eq!(metadata["page_num"], json!(2));
+    }
+
+    #[test]
+    pub fn test_metadata_with_page_location_fallback_box() {
+        let metadata = json!({
+            "page_num": 7,
+            "segments": [
+                location_segment("Unrelated footer text", 3, 700.0),
+                location_segment("Table of contents", 2, 100.0),
+            ],
+        });
+
+        let metadata =
+            metadata_with_page_location(Some(metadata), "<p>Nothing in common here</p>").unwrap();
+
+        assert_eq!(
+            metadata["bounding_boxes"],
+            json!([location_box(3, 700.0), location_box(2, 100.0)])
+        );
+        assert_eq!(metadata["page_num"], json!(7));
+    }
+
+    #[test]
+    pub fn test_metadata_with_page_location_fills_page_num() {
+        let metadata = json!({
+            "segments": [
+                location_segment("Unrelated footer text", 3, 700.0),
+                location_segment("Table of contents", 2, 100.0),
+            ],
+        });
+
+        let metadata =
+            metadata_with_page_location(Some(metadata), "<p>Nothing in common here</p>").unwrap();
+
+        assert_eq!(metadata["page_num"], json!(2));
+        assert_eq!(
+            metadata_with_page_location(Some(json!({"page_num": 1})), "text"),
+            Some(json!({"page_num": 1}))
+        );
+    }
+
+    fn window_chunk(result_index: i32, position: i64) -> ContextWindowChunk {
+        ContextWindowChunk {
+            result_index,
+            id: uuid::Uuid::from_u128(position as u128),
+            chunk_html: Some(format!("chunk {}", position)),
+            position,
+        }
+    }
+
+    #[test]
+    pub fn test_merge_context_windows() {
+        let source_documents = HashMap::from([
+            (0, "a.pdf".to_string()),
+            (1, "a.pdf".to_string()),
+            (2, "a.pdf".to_string()),
+            (3, "b.pdf".to_string()),
+        ]);
+
+        let merged = merge_context_windows(
+            vec![
+                window_chunk(1, 5),
+                window_chunk(1, 4),
+                window_chunk(1, 6),
+                window_chunk(0, 3),
+                window_chunk(0, 4),
+                window_chunk(0, 5),
+                window_chunk(2, 9),
+                window_chunk(2, 10),
+                window_chunk(3, 4),
+            ],
+            &source_documents,
+        );
+
+        assert_eq!(merged.len(), 3);
+
+        let (result_indices, expanded_content) = &merged[0];
+        assert_eq!(result_indices, &vec![0, 1]);
+        assert_eq!(
+            expanded_content.chunk_ids,
+            (3..=6).map(uuid::Uuid::from_u128).collect::<Vec<_>>()
+        );
+        assert_eq!(
+            expanded_content.content,
+            "chunk 3\n\nchunk 4\n\nchunk 5\n\nchunk 6"
+        );
+
+        assert_eq!(merged[1].0, vec![2]);
+        assert_eq!(merged[1].1.content, "chunk 9\n\nchunk 10");
+        assert_eq!(merged[2].0, vec![3]);
+        assert_eq!(merged[2].1.content, "chunk 4");
+    }
+}
@@ -0,0 +1,818 @@
+use super::{
+    model_operator::{get_dense_vectors, get_embedding_token_offsets},
+    parse_operator::coarse_remove_large_chunks,
+};
+use crate::{data::models::DatasetConfiguration, errors::ServiceError};
+use itertools::Itertools;
+use regex::Regex;
+use regex_split::RegexSplit;
+use scraper::{Html, Selector};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use utoipa::ToSchema;
+
+/// Strategy used to split a document into chunks. Every chunk produced by a strategy carries the
+/// path of headings it was found under, which is stored in the chunk metadata as `heading_path`.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, ToSchema)]
+#[serde(tag = "type", rename_all = "snake_case")]
+#[schema(example = json!({
+    "type": "token_window",
+    "max_tokens": 512,
+    "overlap_tokens": 64
+}))]
+pub enum ChunkingStrategy {
+    /// Create one chunk per heading section. Sections larger than 10,000 characters are split evenly.
+    Heading,
+    /// Sliding windows of tokens counted with the tokenizer of the dataset's embedding model. The
+    /// embedding server must expose a `/tokenize` route, otherwise chunking fails.
+    TokenWindow {
+        /// Maximum number of tokens per chunk. Defaults to 512.
+        max_tokens: Option<usize>,
+        /// Number of tokens shared between consecutive chunks. Defaults to 0.
+        overlap_tokens: Option<usize>,
+    },
+    /// Split on the first separator which appears in the text and recurse into the remaining
+    /// separators for pieces which are still too large.
+    RecursiveCharacter {
+        /// Maximum number of characters per chunk. Defaults to 2000.
+        chunk_size: Option<usize>,
+        /// Number of characters shared between consecutive chunks. Defaults to 0.
+        chunk_overlap: Option<usize>,
+        /// Separators to try in order. Defaults to paragraphs, lines, sentences, words and characters.
+        separators: Option<Vec<String>>,
+    },
+    /// Pack whole markdown blocks (paragraphs, lists, tables and code blocks) into chunks. Tables and
+    /// code blocks are never split, even if they are larger than `max_chunk_size`.
+    Markdown {
+        /// Maximum number of characters per chunk. Defaults to 2000.
+        max_chunk_size: Option<usize>,
+    },
+    /// Embed every sentence and start a new chunk wherever the similarity between adjacent sentences
+    /// drops below the threshold.
+    Semantic {
+        /// Cosine similarity below which a new chunk is started. Defaults to one standard deviation
+        /// below the mean similarity of adjacent sentences in the document.
+        similarity_threshold: Option<f32>,
+        /// Maximum number of sentences per chunk. Defaults to 30.
+        max_sentences: Option<usize>,
+    },
+}
+
+impl ChunkingStrategy {
+    pub fn validate(&self) -> Result<(), ServiceError> {
+        match self {
+            ChunkingStrategy::Heading => Ok(()),
+            ChunkingStrategy::TokenWindow {
+                max_tokens,
+                overlap_tokens,
+            } => {
+                let max_tokens = max_tokens.unwrap_or(DEFAULT_MAX_TOKENS);
+                if max_tokens == 0 || overlap_tokens.unwrap_or(0) >= max_tokens {
+                    return Err(ServiceError::BadRequest(
+                        "max_tokens must be greater than 0 and greater than overlap_tokens"
+                            .to_string(),
+                    ));
+                }
+                Ok(())
+            }
+            ChunkingStrategy::RecursiveCharacter {
+                chunk_size,
+                chunk_overlap,
+                ..
+            } => {
+                let chunk_size = chunk_size.unwrap_or(DEFAULT_CHUNK_SIZE);
+                if chunk_size == 0 || chunk_overlap.unwrap_or(0) >= chunk_size {
+                    return Err(ServiceError::BadRequest(
+                        "chunk_size must be greater than 0 and greater than chunk_overlap"
+                            .to_string(),
+                    ));
+                }
+                Ok(())
+            }
+            ChunkingStrategy::Markdown { max_chunk_size } => {
+                if max_chunk_size.is_some_and(|size| size == 0) {
+                    return Err(ServiceError::BadRequest(
+                        "max_chunk_size must be greater than 0".to_string(),
+                    ));
+                }
+                Ok(())
+            }
+            ChunkingStrategy::Semantic {
+                similarity_threshold,
+                max_sentences,
+            } => {
+                if similarity_threshold.is_some_and(|threshold| !(-1.0..=1.0).contains(&threshold))
+                {
+                    return Err(ServiceError::BadRequest(
+                        "similarity_threshold must be between -1 and 1".to_string(),
+                    ));
+                }
+                if max_sentences.is_some_and(|max| max == 0) {
+                    return Err(ServiceError::BadRequest(
+                        "max_sentences must be greater than 0".to_string(),
+                    ));
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+const DEFAULT_MAX_TOKENS: usize = 512;
+const DEFAULT_CHUNK_SIZE: usize = 2000;
+const DEFAULT_MAX_SENTENCES: usize = 30;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DocumentFormat {
+    Html,
+    Markdown,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DocumentChunk {
+    /// The headings the chunk was found under, outermost first
+    pub heading_path: Vec<String>,
+    pub content: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Block {
+    Text(String),
+    /// Tables and code blocks which must not be split
+    Atomic(String),
+}
+
+impl Block {
+    fn content(&self) -> &str {
+        match self {
+            Block::Text(text) | Block::Atomic(text) => text,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+struct Section {
+    heading_path: Vec<String>,
+    blocks: Vec<Block>,
+}
+
+impl Section {
+    fn text(&self) -> String {
+        self.blocks.iter().map(|block| block.content()).join("\n\n")
+    }
+}
+
+/// Splits a document into chunks using the given strategy. Text is extracted from HTML documents
+/// before splitting so every returned chunk is plain text or markdown.
+pub async fn chunk_document(
+    document: &str,
+    format: DocumentFormat,
+    strategy: &ChunkingStrategy,
+    dataset_config: &DatasetConfiguration,
+) -> Result<Vec<DocumentChunk>, ServiceError> {
+    strategy.validate()?;
+
+    let sections = match format {
+        DocumentFormat::Html => html_sections(document),
+        DocumentFormat::Markdown => markdown_sections(document),
+    };
+
+    let chunks = match strategy {
+        ChunkingStrategy::Heading => sections
+            .into_iter()
+            .flat_map(|section| {
+                coarse_remove_large_chunks(vec![section.text()])
+                    .into_iter()
+                    .map(move |content| DocumentChunk {
+                        heading_path: section.heading_path.clone(),
+                        content,
+                    })
+            })
+            .collect(),
+        ChunkingStrategy::TokenWindow {
+            max_tokens,
+            overlap_tokens,
+        } => {
+            token_window_chunks(
+                sections,
+                max_tokens.unwrap_or(DEFAULT_MAX_TOKENS),
+                overlap_tokens.unwrap_or(0),
+                dataset_config,
+            )
+            .await?
+        }
+        ChunkingStrategy::RecursiveCharacter {
+            chunk_size,
+            chunk_overlap,
+            separators,
+        } => {
+            let separators = separators.clone().unwrap_or_else(default_separators);
+            sections
+                .into_iter()
+                .flat_map(|section| {
+                    recursive_character_split(
+                        &section.text(),
+                        &separators,
+                        chunk_size.unwrap_or(DEFAULT_CHUNK_SIZE),
+                        chunk_overlap.unwrap_or(0),
+                    )
+                    .into_iter()
+                    .map(move |content| DocumentChunk {
+                        heading_path: section.heading_path.clone(),
+                        content,
+                    })
+                })
+                .collect()
+        }
+        ChunkingStrategy::Markdown { max_chunk_size } => sections
+            .into_iter()
+            .flat_map(|section| {
+                pack_blocks(
+                    &section.blocks,
+                    max_chunk_size.unwrap_or(DEFAULT_CHUNK_SIZE),
+                )
+                .into_iter()
+                .map(move |content| DocumentChunk {
+                    heading_path: section.heading_path.clone(),
+                    content,
+                })
+            })
+            .collect(),
+        ChunkingStrategy::Semantic {
+            similarity_threshold,
+            max_sentences,
+        } => {
+            semantic_chunks(
+                sections,
+                *similarity_threshold,
+                max_sentences.unwrap_or(DEFAULT_MAX_SENTENCES),
+                dataset_config,
+            )
+            .await?
+        }
+    };
+
+    Ok(chunks
+        .into_iter()
+        .filter(|chunk| !chunk.content.trim().is_empty())
+        .collect())
+}
+
+/// Adds the heading path of a chunk to its metadata. Metadata which is not a JSON object is left
+/// untouched.
+pub fn metadata_with_heading_path(
+    metadata: Option<serde_json::Value>,
+    heading_path: &[String],
+) -> Option<serde_json::Value> {
+    match metadata {
+        Some(serde_json::Value::Object(mut metadata)) => {
+            metadata.insert("heading_path".to_string(), serde_json::json!(heading_path));
+            Some(serde_json::Value::Object(metadata))
+        }
+        None => Some(serde_json::json!({ "heading_path": heading_path })),
+        metadata => metadata,
+    }
+}
+
+fn update_heading_stack(stack: &mut Vec<(usize, String)>, level: usize, heading: String) {
+    stack.retain(|(cur_level, _)| *cur_level < level);
+    if !heading.is_empty() {
+        stack.push((level, heading));
+    }
+}
+
+fn push_section(
+    sections: &mut Vec<Section>,
+    section: &mut Section,
+    next_heading_path: Vec<String>,
+) {
+    let finished = std::mem::replace(
+        section,
+        Section {
+            heading_path: next_heading_path,
+            blocks: vec![],
+        },
+    );
+
+    if !finished.blocks.is_empty() {
+        sections.push(finished);
+    }
+}
+
+fn flush_lines(lines: &mut Vec<&str>, blocks: &mut Vec<Block>, atomic: bool) {
+    if lines.is_empty() {
+        return;
+    }
+    let content = lines.join("\n");
+    lines.clear();
+    blocks.push(if atomic {
+        Block::Atomic(content)
+    } else {
+        Block::Text(content)
+    });
+}
+
+fn markdown_sections(markdown: &str) -> Vec<Section> {
+    let heading_re =
+        Regex::new(r"^ {0,3}(#{1,6})\s+(.*?)\s*#*\s*$").expect("regex is always correct");
+
+    let mut sections = vec![];
+    let mut heading_stack: Vec<(usize, String)> = vec![];
+    let mut section = Section::default();
+    let mut paragraph: Vec<&str> = vec![];
+    let mut table: Vec<&str> = vec![];
+    let mut fence: Option<(String, Vec<&str>)> = None;
+
+    for line in markdown.lines() {
+        let trimmed = line.trim_start();
+
+        if let Some((marker, lines)) = fence.as_mut() {
+            lines.push(line);
+            if trimmed.starts_with(marker.as_str()) {
+                section.blocks.push(Block::Atomic(lines.join("\n")));
+                fence = None;
+            }
+            continue;
+        }
+
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            flush_lines(&mut paragraph, &mut section.blocks, false);
+            flush_lines(&mut table, &mut section.blocks, true);
+            fence = Some((trimmed[..3].to_string(), vec![line]));
+            continue;
+        }
+
+        if trimmed.starts_with('|') {
+            flush_lines(&mut paragraph, &mut section.blocks, false);
+            table.push(line);
+            continue;
+        }
+        flush_lines(&mut table, &mut section.blocks, true);
+
+        if let Some(captures) = heading_re.captures(line) {
+            flush_lines(&mut paragraph, &mut section.blocks, false);
+            update_heading_stack(
+                &mut heading_stack,
+                captures[1].len(),
+                captures[2].trim().to_string(),
+            );
+            let heading_path = heading_stack
+                .iter()
+                .map(|(_, heading)| heading.clone())
+                .collect();
+            push_section(&mut sections, &mut section, heading_path);
+            continue;
+        }
+
+        if trimmed.is_empty() {
+            flush_lines(&mut paragraph, &mut section.blocks, false);
+        } else {
+            paragraph.push(line);
+        }
+    }
+
+    if let Some((_, lines)) = fence {
+        section.blocks.push(Block::Atomic(lines.join("\n")));
+    }
+    flush_lines(&mut table, &mut section.blocks, true);
+    flush_lines(&mut paragraph, &mut section.blocks, false);
+    push_section(&mut sections, &mut section, vec![]);
+
+    sections
+}
+
+fn html_to_text(html: &str) -> String {
+    Html::parse_fragment(html)
+        .root_element()
+        .text()
+        .collect::<String>()
+}
+
+fn html_table_to_text(html: &str) -> String {
+    let fragment = Html::parse_fragment(html);
+    let row_selector = Selector::parse("tr").expect("selector is always correct");
+    let cell_selector = Selector::parse("th, td").expect("selector is always correct");
+
+    fragment
+        .select(&row_selector)
+        .map(|row| {
+            row.select(&cell_selector)
+                .map(|cell| cell.text().collect::<String>().split_whitespace().join(" "))
+                .join(" | ")
+        })
+        .filter(|row| !row.is_empty())
+        .join("\n")
+}
+
+fn html_blocks(html: &str) -> Vec<Block> {
+    let atomic_re =
+        Regex::new(r"(?is)<(pre|table)\b.*?</(?:pre|table)\s*>").expect("regex is always correct");
+    let block_end_re = Regex::new(
+        r"(?i)</(?:p|div|li|ul|ol|dl|dd|dt|section|article|blockquote|tr|header|footer)\s*>|<br\s*/?>",
+    )
+    .expect("regex is always correct");
+    let paragraph_re = Regex::new(r"\n\s*\n").expect("regex is always correct");
+
+    let mut blocks = vec![];
+    let push_text = |html: &str, blocks: &mut Vec<Block>| {
+        let text = html_to_text(&block_end_re.replace_all(html, "$0\n\n"));
+        for paragraph in paragraph_re.split(&text) {
+            let paragraph = paragraph.split_whitespace().join(" ");
+            if !paragraph.is_empty() {
+                blocks.push(Block::Text(paragraph));
+            }
+        }
+    };
+
+    let mut last_end = 0;
+    for captures in atomic_re.captures_iter(html) {
+        let atomic_match = captures.get(0).expect("capture 0 always exists");
+        push_text(&html[last_end..atomic_match.start()], &mut blocks);
+
+        let content = if captures[1].eq_ignore_ascii_case("table") {
+            html_table_to_text(atomic_match.as_str())
+        } else {
+            html_to_text(atomic_match.as_str())
+                .trim_matches('\n')
+                .to_string()
+        };
+        if !content.trim().is_empty() {
+            blocks.push(Block::Atomic(content));
+        }
+        last_end = atomic_match.end();
+    }
+    push_text(&html[last_end..], &mut blocks);
+
+    blocks
+}
+
+fn html_sections(html: &str) -> Vec<Section> {
+    let heading_re =
+        Regex::new(r"(?is)<h([1-6])\b[^>]*>(.*?)</h[1-6]\s*>").expect("regex is always correct");
+
+    let mut sections = vec![];
+    let mut heading_stack: Vec<(usize, String)> = vec![];
+    let mut section = Section::default();
+    let mut last_end = 0;
+
+    for captures in heading_re.captures_iter(html) {
+        let heading_match = captures.get(0).expect("capture 0 always exists");
+        section
+            .blocks
+            .extend(html_blocks(&html[last_end..heading_match.start()]));
+
+        update_heading_stack(
+            &mut heading_stack,
+            captures[1].parse::<usize>().unwrap_or(1),
+            html_to_text(&captures[2]).split_whitespace().join(" "),
+        );
+        let heading_path = heading_stack
+            .iter()
+            .map(|(_, heading)| heading.clone())
+            .collect();
+        push_section(&mut sections, &mut section, heading_path);
+        last_end = heading_match.end();
+    }
+
+    section.blocks.extend(html_blocks(&html[last_end..]));
+    push_section(&mut sections, &mut section, vec![]);
+
+    sections
+}
+
+fn default_separators() -> Vec<String> {
+    ["\n\n", "\n", ". ", " ", ""]
+        .iter()
+        .map(|separator| separator.to_string())
+        .collect()
+}
+
+/// Combines consecutive pieces into chunks of at most `chunk_size` characters, carrying up to
+/// `overlap` characters of trailing pieces over into the next chunk.
+fn merge_pieces(pieces: Vec<String>, chunk_size: usize, overlap: usize) -> Vec<String> {
+    let mut chunks = vec![];
+    let mut current: Vec<(String, usize)> = vec![];
+    let mut current_len = 0;
+
+    for piece in pieces {
+        let piece_len = piece.chars().count();
+
+        if current_len + piece_len > chunk_size && !current.is_empty() {
+            chunks.push(
+                current
+                    .iter()
+                    .map(|(piece, _)| piece.as_str())
+                    .collect::<String>(),
+            );
+
+            while !current.is_empty()
+                && (current_len > overlap || current_len + piece_len > chunk_size)
+            {
+                let (_, removed_len) = current.remove(0);
+                current_len -= removed_len;
+            }
+        }
+
+        current_len += piece_len;
+        current.push((piece, piece_len));
+    }
+
+    if !current.is_empty() {
+        chunks.push(
+            current
+                .iter()
+                .map(|(piece, _)| piece.as_str())
+                .collect::<String>(),
+        );
+    }
+
+    chunks
+        .into_iter()
+        .map(|chunk| chunk.trim().to_string())
+        .filter(|chunk| !chunk.is_empty())
+        .collect()
+}
+
+fn split_into_pieces(text: &str, separators: &[String], chunk_size: usize) -> Vec<String> {
+    if text.chars().count() <= chunk_size {
+        return vec![text.to_string()];
+    }
+
+    let Some(separator_index) = separators
+        .iter()
+        .position(|separator| separator.is_empty() || text.contains(separator.as_str()))
+    else {
+        return vec![text.to_string()];
+    };
+    let separator = &separators[separator_index];
+
+    if separator.is_empty() {
+        return text
+            .chars()
+            .chunks(chunk_size)
+            .into_iter()
+            .map(|chars| chars.collect::<String>())
+            .collect();
+    }
+
+    text.split_inclusive(separator.as_str())
+        .flat_map(|split| {
+            if split.chars().count() > chunk_size {
+                split_into_pieces(split, &separators[separator_index + 1..], chunk_size)
+            } else {
+                vec![split.to_string()]
+            }
+        })
+        .collect()
+}
+
+fn recursive_character_split(
+    text: &str,
+    separators: &[String],
+    chunk_size: usize,
+    overlap: usize,
+) -> Vec<String> {
+    merge_pieces(
+        split_into_pieces(text, separators, chunk_size),
+        chunk_size,
+        overlap,
+    )
+}
+
+fn pack_blocks(blocks: &[Block], max_chunk_size: usize) -> Vec<String> {
+    let mut chunks = vec![];
+    let mut current: Vec<&str> = vec![];
+    let mut current_len = 0;
+
+    let flush = |chunks: &mut Vec<String>, current: &mut Vec<&str>, current_len: &mut usize| {
+        if !current.is_empty() {
+            chunks.push(current.join("\n\n"));
+            current.clear();
+            *current_len = 0;
+        }
+    };
+
+    for block in blocks {
+        let content = block.content();
+        let block_len = content.chars().count();
+
+        if let Block::Text(text) = block {
+            if block_len > max_chunk_size {
+                flush(&mut chunks, &mut current, &mut current_len);
+                chunks.extend(recursive_character_split(
+                    text,
+                    &default_separators(),
+                    max_chunk_size,
+                    0,
+                ));
+                continue;
+            }
+        }
+
+        if current_len + block_len > max_chunk_size {
+            flush(&mut chunks, &mut current, &mut current_len);
+        }
+        current.push(content);
+        current_len += block_len + 2;
+    }
+    flush(&mut chunks, &mut current, &mut current_len);
+
+    chunks
+}
+
+async fn token_window_chunks(
+    sections: Vec<Section>,
+    max_tokens: usize,
+    overlap_tokens: usize,
+    dataset_config: &DatasetConfiguration,
+) -> Result<Vec<DocumentChunk>, ServiceError> {
+    let step = max_tokens - overlap_tokens;
+    let mut chunks = vec![];
+
+    for section in sections {
+        let text = section.text();
+        let offsets = get_embedding_token_offsets(&text, dataset_config)
+            .await
+            .map_err(|err| {
+                log::error!("Failed to tokenize text for token window chunking {:?}", err);
+                ServiceError::BadRequest(format!(
+                    "Token window chunking requires the dataset's embedding server to support tokenization: {}",
+                    err
+                ))
+            })?;
+
+        let mut window_start = 0;
+        while window_start < offsets.len() {
+            let window_end = (window_start + max_tokens).min(offsets.len());
+            chunks.push(DocumentChunk {
+                heading_path: section.heading_path.clone(),
+                content: text[offsets[window_start].0..offsets[window_end - 1].1]
+                    .trim()
+                    .to_string(),
+            });
+
+            if window_end == offsets.len() {
+                break;
+            }
+            window_start += step;
+        }
+    }
+
+    Ok(chunks)
+}
+
+fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+    let dot = a.iter().zip(b).map(|(x, y)| x * y).sum::<f32>();
+    let norm_a = a.iter().map(|x| x * x).sum::<f32>().sqrt();
+    let norm_b = b.iter().map(|x| x * x).sum::<f32>().sqrt();
+
+    if norm_a == 0.0 || norm_b == 0.0 {
+        0.0
+    } else {
+        dot / (norm_a * norm_b)
+    }
+}
+
+async fn semantic_chunks(
+    sections: Vec<Section>,
+    similarity_threshold: Option<f32>,
+    max_sentences: usize,
+    dataset_config: &DatasetConfiguration,
+) -> Result<Vec<DocumentChunk>, ServiceError> {
+    let sentence_re = Regex::new(r"[.!?]\s+|\n").expect("regex is always correct");
+
+    let section_sentences: Vec<(Vec<String>, Vec<String>)> = sections
+        .into_iter()
+        .map(|section| {
+            let sentences = section
+                .blocks
+                .iter()
+                .flat_map(|block| match block {
+                    Block::Atomic(content) => vec![content.clone()],
+                    Block::Text(text) => sentence_re
+                        .split_inclusive(text)
+                        .map(|sentence| sentence.trim().to_string())
+                        .filter(|sentence| !sentence.is_empty())
+                        .collect(),
+                })
+                .collect();
+            (section.heading_path, sentences)
+        })
+        .collect();
+
+    let all_sentences = section_sentences
+        .iter()
+        .flat_map(|(_, sentences)| sentences.iter().map(|sentence| (sentence.clone(), None)))
+        .collect::<Vec<_>>();
+
+    let reqwest_client = reqwest::Client::new();
+    let mut embeddings = vec![];
+    for sentence_batch in all_sentences.chunks(300) {
+        embeddings.extend(
+            get_dense_vectors(
+                sentence_batch.to_vec(),
+                "doc",
+                dataset_config.clone(),
+                reqwest_client.clone(),
+            )
+            .await?,
+        );
+    }
+
+    if embeddings.len() != all_sentences.len() {
+        return Err(ServiceError::InternalServerError(
+            "Embedding server did not return an embedding for every sentence".to_string(),
+        ));
+    }
+
+    let mut similarities_by_section = vec![];
+    let mut embedding_index = 0;
+    for (_, sentences) in section_sentences.iter() {
+        let section_embeddings = &embeddings[embedding_index..embedding_index + sentences.len()];
+        similarities_by_section.push(
+            section_embeddings
+                .windows(2)
+                .map(|pair| cosine_similarity(&pair[0], &pair[1]))
+                .collect::<Vec<f32>>(),
+        );
+        embedding_index += sentences.len();
+    }
+
+    let threshold = similarity_threshold.unwrap_or_else(|| {
+        let similarities = similarities_by_section.iter().flatten().collect::<Vec<_>>();
+        if similarities.is_empty() {
+            return 0.0;
+        }
+        let mean = similarities.iter().copied().sum::<f32>() / similarities.len() as f32;
+        let variance = similarities
+            .iter()
+            .map(|similarity| (*similarity - mean).powi(2))
+            .sum::<f32>()
+            / similarities.len() as f32;
+        mean - variance.sqrt()
+    });
+
+    let mut chunks = vec![];
+    for ((heading_path, sentences), similarities) in
+        section_sentences.into_iter().zip(similarities_by_section)
+    {
+        let mut current: Vec<String> = vec![];
+        for (i, sentence) in sentences.into_iter().enumerate() {
+            let breaks_topic = i > 0 && similarities[i - 1] < threshold;
+            if !current.is_empty() && (breaks_topic || current.len() >= max_sentences) {
+                chunks.push(DocumentChunk {
+                    heading_path: heading_path.clone(),
+                    content: current.join(" "),
+                });
+                current.clear();
+            }
+            current.push(sentence);
+        }
+
+        if !current.is_empty() {
+            chunks.push(DocumentChunk {
+                heading_path,
+                content: current.join(" "),
+            });
+        }
+    }
+
+    Ok(chunks)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_markdown_sections_keep_code_and_tables_intact() {
+        let markdown = "# Guide\n\nIntro text.\n\n## Install\n\n```sh\ncargo build\n\n# not a heading\n```\n\n| a | b |\n|---|---|\n| 1 | 2 |\n\n# Other\n\nMore text.";
+
+        let sections = markdown_sections(markdown);
+
+        assert_eq!(sections.len(), 3);
+        assert_eq!(sections[0].heading_path, vec!["Guide"]);
+        assert_eq!(sections[1].heading_path, vec!["Guide", "Install"]);
+        assert_eq!(
+            sections[1].blocks,
+            vec![
+                Block::Atomic("```sh\ncargo build\n\n# not a heading\n```".to_string()),
+                Block::Atomic("| a | b |\n|---|---|\n| 1 | 2 |".to_string()),
+            ]
+        );
+        assert_eq!(sections[2].heading_path, vec!["Other"]);
+    }
+
+    #[test]
+    fn test_recursive_character_split() {
+        let text = "first sentence. second sentence. third sentence.";
+
+        let chunks = recursive_character_split(text, &default_separators(), 20, 0);
+
+        assert_eq!(
+            chunks,
+            vec!["first sentence.", "second sentence.", "third sentence."]
+        );
+        assert!(chunks.iter().all(|chunk| chunk.chars().count() <= 20));
+    }
+}
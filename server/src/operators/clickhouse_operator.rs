@@ -9,6 +9,7 @@ use crate::{
     },
     errors::ServiceError,
     handlers::analytics_handler::RateQueryRequest,
+    operators::event_webhook_operator::push_event_webhook_events_query,
 };
 
 #[derive(Debug, Clone)]
@@ -252,6 +253,8 @@ pub async fn send_to_clickhouse(
 #[derive(Default, Clone)]
 pub struct EventQueue {
     sender: Option<mpsc::Sender<ClickHouseEvent>>,
+    webhook_sender: Option<mpsc::Sender<WorkerEventClickhouse>>,
+    webhook_redis_client: Option<redis::Client>,
     clickhouse_client: clickhouse::Client,
 }
 
@@ -259,6 +262,8 @@ impl EventQueue {
     pub fn new(clickhouse_client: clickhouse::Client) -> Self {
         Self {
             sender: None,
+            webhook_sender: None,
+            webhook_redis_client: None,
             clickhouse_client,
         }
     }
@@ -301,7 +306,108 @@ impl EventQueue {
         });
     }
 
+    /// Forwards every worker event sent through the queue to the redis queue the event webhook
+    /// worker creates deliveries from. This runs independently of analytics so webhooks work
+    /// without clickhouse.
+    pub fn start_webhook_service(&mut self) {
+        let redis_url = match std::env::var("REDIS_URL") {
+            Ok(redis_url) => redis_url,
+            Err(_) => {
+                log::error!("REDIS_URL is not set, event webhooks are disabled");
+                return;
+            }
+        };
+        let redis_client = match redis::Client::open(redis_url) {
+            Ok(redis_client) => redis_client,
+            Err(e) => {
+                log::error!("Error opening redis client for event webhooks: {:?}", e);
+                return;
+            }
+        };
+        let queue_length = std::env::var("EVENT_WEBHOOK_QUEUE_LENGTH")
+            .unwrap_or("10000".to_string())
+            .parse()
+            .unwrap_or(10000);
+        let (sender, mut reciever) = mpsc::channel(queue_length);
+        self.webhook_sender = Some(sender);
+        self.webhook_redis_client = Some(redis_client.clone());
+
+        tokio::spawn(async move {
+            let mut redis_conn: Option<redis::aio::MultiplexedConnection> = None;
+            while let Some(event) = reciever.recv().await {
+                let mut events = vec![event];
+                while events.len() < 500 {
+                    match reciever.try_recv() {
+                        Ok(event) => events.push(event),
+                        Err(_) => break,
+                    }
+                }
+
+                // Keep retrying the batch while redis is unavailable, events sent meanwhile
+                // fill the channel and are pushed directly by `send`
+                loop {
+                    let mut conn = match redis_conn.clone() {
+                        Some(conn) => conn,
+                        None => match redis_client.get_multiplexed_async_connection().await {
+                            Ok(conn) => {
+                                redis_conn = Some(conn.clone());
+                                conn
+                            }
+                            Err(e) => {
+                                log::error!(
+                                    "Error connecting to redis for event webhooks: {:?}",
+                                    e
+                                );
+                                tokio::time::sleep(Duration::from_secs(1)).await;
+                                continue;
+                            }
+                        },
+                    };
+
+                    match push_event_webhook_events_query(&events, &mut conn).await {
+                        Ok(()) => break,
+                        Err(e) => {
+                            log::error!("Error queueing event webhook events: {:?}", e);
+                            redis_conn = None;
+                            tokio::time::sleep(Duration::from_secs(1)).await;
+                        }
+                    }
+                }
+            }
+        });
+    }
+
     pub async fn send(&self, event: ClickHouseEvent) {
+        if let (Some(webhook_sender), ClickHouseEvent::WorkerEvent(worker_event)) =
+            (&self.webhook_sender, &event)
+        {
+            if let Err(e) = webhook_sender.try_send(worker_event.clone()) {
+                let worker_event = match e {
+                    mpsc::error::TrySendError::Full(worker_event)
+                    | mpsc::error::TrySendError::Closed(worker_event) => worker_event,
+                };
+
+                // Push the event to redis directly instead of waiting for room in the channel
+                if let Some(redis_client) = self.webhook_redis_client.clone() {
+                    tokio::spawn(async move {
+                        let result = match redis_client.get_multiplexed_async_connection().await {
+                            Ok(mut conn) => {
+                                push_event_webhook_events_query(&[worker_event], &mut conn).await
+                            }
+                            Err(e) => Err(ServiceError::InternalServerError(format!(
+                                "Error connecting to redis: {:?}",
+                                e
+                            ))),
+                        };
+
+                        if let Err(e) = result {
+                            log::error!("Error sending event to webhook queue: {:?}", e);
+                        }
+                    });
+                }
+            }
+        }
+
         if let Some(sender) = &self.sender {
             let _ = sender.send(event).await.map_err(|e| {
                 log::error!("Error sending event to clickhouse: {:?}", e);
@@ -0,0 +1,191 @@
+use actix_web::web;
+use diesel::prelude::*;
+use diesel_async::RunQueryDsl;
+
+use crate::data::models::{
+    ChunkGroup, ChunkGroupAndFileId, DatasetClone, DatasetCloneStatus, File, Pool,
+};
+use crate::errors::ServiceError;
+
+pub async fn create_dataset_clone_query(
+    dataset_clone: DatasetClone,
+    pool: web::Data<Pool>,
+) -> Result<DatasetClone, ServiceError> {
+    use crate::data::schema::dataset_clones::dsl as dataset_clones_columns;
+
+    let mut conn = pool.get().await.map_err(|_e| {
+        ServiceError::InternalServerError("Failed to get postgres connection".to_string())
+    })?;
+
+    diesel::insert_into(dataset_clones_columns::dataset_clones)
+        .values(&dataset_clone)
+        .get_result::<DatasetClone>(&mut conn)
+        .await
+        .map_err(|err| {
+            log::error!("Failed to create dataset clone: {:?}", err);
+            ServiceError::InternalServerError("Failed to create dataset clone".to_string())
+        })
+}
+
+pub async fn get_dataset_clone_query(
+    clone_id: uuid::Uuid,
+    pool: web::Data<Pool>,
+) -> Result<DatasetClone, ServiceError> {
+    use crate::data::schema::dataset_clones::dsl as dataset_clones_columns;
+
+    let mut conn = pool.get().await.map_err(|_e| {
+        ServiceError::InternalServerError("Failed to get postgres connection".to_string())
+    })?;
+
+    dataset_clones_columns::dataset_clones
+        .filter(dataset_clones_columns::id.eq(clone_id))
+        .select(DatasetClone::as_select())
+        .first::<DatasetClone>(&mut conn)
+        .await
+        .map_err(|_| ServiceError::NotFound("Dataset clone not found".to_string()))
+}
+
+/// Gets the most recent clone into a dataset
+pub async fn get_latest_dataset_clone_query(
+    dataset_id: uuid::Uuid,
+    pool: web::Data<Pool>,
+) -> Result<DatasetClone, ServiceError> {
+    use crate::data::schema::dataset_clones::dsl as dataset_clones_columns;
+
+    let mut conn = pool.get().await.map_err(|_e| {
+        ServiceError::InternalServerError("Failed to get postgres connection".to_string())
+    })?;
+
+    dataset_clones_columns::dataset_clones
+        .filter(dataset_clones_columns::dataset_id.eq(dataset_id))
+        .order_by(dataset_clones_columns::created_at.desc())
+        .select(DatasetClone::as_select())
+        .first::<DatasetClone>(&mut conn)
+        .await
+        .map_err(|_| ServiceError::NotFound("Dataset was not created by a clone".to_string()))
+}
+
+pub async fn update_dataset_clone_status_query(
+    clone_id: uuid::Uuid,
+    status: DatasetCloneStatus,
+    error: Option<String>,
+    pool: web::Data<Pool>,
+) -> Result<(), ServiceError> {
+    use crate::data::schema::dataset_clones::dsl as dataset_clones_columns;
+
+    let mut conn = pool.get().await.map_err(|_e| {
+        ServiceError::InternalServerError("Failed to get postgres connection".to_string())
+    })?;
+
+    diesel::update(
+        dataset_clones_columns::dataset_clones.filter(dataset_clones_columns::id.eq(clone_id)),
+    )
+    .set((
+        dataset_clones_columns::status.eq(status.to_string()),
+        dataset_clones_columns::error.eq(error),
+        dataset_clones_columns::updated_at.eq(chrono::Utc::now().naive_local()),
+    ))
+    .execute(&mut conn)
+    .await
+    .map_err(|err| {
+        log::error!("Failed to update dataset clone status: {:?}", err);
+        ServiceError::InternalServerError("Failed to update dataset clone status".to_string())
+    })?;
+
+    Ok(())
+}
+
+pub async fn update_dataset_clone_progress_query(
+    dataset_clone: &DatasetClone,
+    pool: web::Data<Pool>,
+) -> Result<(), ServiceError> {
+    use crate::data::schema::dataset_clones::dsl as dataset_clones_columns;
+
+    let mut conn = pool.get().await.map_err(|_e| {
+        ServiceError::InternalServerError("Failed to get postgres connection".to_string())
+    })?;
+
+    diesel::update(
+        dataset_clones_columns::dataset_clones
+            .filter(dataset_clones_columns::id.eq(dataset_clone.id)),
+    )
+    .set((
+        dataset_clones_columns::vectors_reused.eq(dataset_clone.vectors_reused),
+        dataset_clones_columns::groups_cloned.eq(dataset_clone.groups_cloned),
+        dataset_clones_columns::files_cloned.eq(dataset_clone.files_cloned),
+        dataset_clones_columns::chunks_cloned.eq(dataset_clone.chunks_cloned),
+        dataset_clones_columns::updated_at.eq(chrono::Utc::now().naive_local()),
+    ))
+    .execute(&mut conn)
+    .await
+    .map_err(|err| {
+        log::error!("Failed to update dataset clone progress: {:?}", err);
+        ServiceError::InternalServerError("Failed to update dataset clone progress".to_string())
+    })?;
+
+    Ok(())
+}
+
+/// Loads the groups of the source dataset with the given ids along with the file each was created from, if any.
+pub async fn get_groups_to_clone_query(
+    group_ids: Vec<uuid::Uuid>,
+    dataset_id: uuid::Uuid,
+    pool: web::Data<Pool>,
+) -> Result<Vec<ChunkGroupAndFileId>, ServiceError> {
+    use crate::data::schema::chunk_group::dsl as chunk_group_columns;
+    use crate::data::schema::groups_from_files::dsl as groups_from_files_columns;
+
+    let mut conn = pool.get().await.map_err(|_e| {
+        ServiceError::InternalServerError("Failed to get postgres connection".to_string())
+    })?;
+
+    let groups: Vec<(ChunkGroup, Option<uuid::Uuid>)> = chunk_group_columns::chunk_group
+        .left_join(
+            groups_from_files_columns::groups_from_files
+                .on(groups_from_files_columns::group_id.eq(chunk_group_columns::id)),
+        )
+        .filter(chunk_group_columns::dataset_id.eq(dataset_id))
+        .filter(chunk_group_columns::id.eq_any(&group_ids))
+        .select((
+            ChunkGroup::as_select(),
+            groups_from_files_columns::file_id.nullable(),
+        ))
+        .load::<(ChunkGroup, Option<uuid::Uuid>)>(&mut conn)
+        .await
+        .map_err(|err| {
+            log::error!("Error loading groups to clone {:?}", err);
+            ServiceError::BadRequest("Error loading groups to clone".to_string())
+        })?;
+
+    Ok(groups
+        .into_iter()
+        .map(|(group, file_id)| ChunkGroupAndFileId::from_group(group, file_id))
+        .collect())
+}
+
+pub async fn get_files_to_clone_query(
+    file_ids: Vec<uuid::Uuid>,
+    dataset_id: uuid::Uuid,
+    pool: web::Data<Pool>,
+) -> Result<Vec<File>, ServiceError> {
+    use crate::data::schema::files::dsl as files_columns;
+
+    if file_ids.is_empty() {
+        return Ok(vec![]);
+    }
+
+    let mut conn = pool.get().await.map_err(|_e| {
+        ServiceError::InternalServerError("Failed to get postgres connection".to_string())
+    })?;
+
+    files_columns::files
+        .filter(files_columns::dataset_id.eq(dataset_id))
+        .filter(files_columns::id.eq_any(&file_ids))
+        .select(File::as_select())
+        .load::<File>(&mut conn)
+        .await
+        .map_err(|err| {
+            log::error!("Error loading files to clone {:?}", err);
+            ServiceError::BadRequest("Error loading files to clone".to_string())
+        })
+}
@@ -1,6 +1,7 @@
 use crate::data::models::CrawlOptions;
 use crate::data::models::CrawlStatus;
 use crate::data::models::CrawlType;
+use crate::data::models::DatasetConfiguration;
 use crate::data::models::FirecrawlCrawlRequest;
 use crate::handlers::chunk_handler::ChunkReqPayload;
 use crate::handlers::chunk_handler::CrawlInterval;
@@ -23,7 +24,11 @@ use scraper::Selector;
 use serde::{Deserialize, Serialize};
 use utoipa::ToSchema;
 
-use super::chunk_operator::create_chunk_metadata;
+use super::chunk_operator::{create_chunk_metadata, metadata_with_source_position};
+use super::chunking_operator::{
+    chunk_document, metadata_with_heading_path, ChunkingStrategy, DocumentFormat,
+};
+use super::dataset_operator::get_dataset_by_id_query;
 use super::organization_operator::hash_function;
 use super::parse_operator::convert_html_to_text;
 
@@ -712,6 +717,7 @@ pub async fn process_crawl_doc(
     dataset_id: uuid::Uuid,
     scrape_id: uuid::Uuid,
     crawl_doc: Document,
+    chunking_strategy: Option<ChunkingStrategy>,
     broccoli_queue: web::Data<BroccoliQueue>,
     pool: web::Data<Pool>,
 ) -> Result<(), ServiceError> {
@@ -770,10 +776,36 @@ pub async fn process_crawl_doc(
     let mut page_tags = get_tags(page_link.clone());
     page_tags.push(crawl_req.url.clone());
 
-    let chunked_html = chunk_html(&page_html.clone(), None, None);
+    let chunked_html: Vec<(String, String, Option<Vec<String>>)> = match chunking_strategy {
+        Some(chunking_strategy) => {
+            let dataset = get_dataset_by_id_query(dataset_id, pool.clone()).await?;
+            let dataset_config = DatasetConfiguration::from_json(dataset.server_configuration);
+
+            chunk_document(
+                &page_html,
+                DocumentFormat::Html,
+                &chunking_strategy,
+                &dataset_config,
+            )
+            .await?
+            .into_iter()
+            .map(|chunk| {
+                (
+                    chunk.heading_path.join("\n"),
+                    chunk.content,
+                    Some(chunk.heading_path),
+                )
+            })
+            .collect()
+        }
+        None => chunk_html(&page_html.clone(), None, None)
+            .into_iter()
+            .map(|(heading, chunk_html)| (heading, chunk_html, None))
+            .collect(),
+    };
     let mut chunks = vec![];
 
-    for chunk in chunked_html {
+    for (i, chunk) in chunked_html.into_iter().enumerate() {
         let heading = chunk.0.clone();
         let chunk_html = chunk.1.clone();
 
@@ -807,11 +839,18 @@ pub async fn process_crawl_doc(
             metadata["description"] = serde_json::json!(page_description.clone());
         }
 
-        let tracking_hash_val = if heading.is_empty() {
-            chunk_html.clone()
-        } else {
-            heading.clone()
+        // Strategies can produce several chunks under the same heading
+        let tracking_hash_val = match chunk.2 {
+            Some(heading_path) => {
+                metadata =
+                    metadata_with_heading_path(Some(metadata), &heading_path).unwrap_or_default();
+                format!("{}|{}", heading, i)
+            }
+            None if heading.is_empty() => chunk_html.clone(),
+            None => heading.clone(),
         };
+        metadata =
+            metadata_with_source_position(Some(metadata), &page_link, i as i64).unwrap_or_default();
 
         let chunk = ChunkReqPayload {
             chunk_html: Some(chunk_html.clone()),
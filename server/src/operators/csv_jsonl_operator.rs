@@ -0,0 +1,1012 @@
+use crate::{
+    data::models::{
+        ChunkReqPayloadFields, ChunkReqPayloadMapping, CsvJsonlFieldTransform, CsvJsonlFileFormat,
+        CsvJsonlRowFilter, CsvJsonlRowFilterOperator, GeoInfo, GeoTypes, SpreadsheetOptions,
+    },
+    errors::ServiceError,
+    handlers::chunk_handler::{ChunkReqPayload, FullTextBoost, SemanticBoost},
+};
+use calamine::{open_workbook_auto_from_rs, Data, Reader};
+use chrono::{DateTime, NaiveDate, NaiveDateTime};
+use dateparser::DateTimeUtc;
+use minijinja::Environment;
+use parquet::file::{
+    reader::{ChunkReader, FileReader, RowGroupReader},
+    serialized_reader::SerializedFileReader,
+};
+
+pub fn parse_csv_line(line: &str) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut current_field = String::new();
+    let mut in_quotes = false;
+    let mut chars = line.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => {
+                if in_quotes {
+                    if chars.peek() == Some(&'"') {
+                        current_field.push('"');
+                        chars.next();
+                    } else {
+                        in_quotes = false;
+                    }
+                } else {
+                    in_quotes = true;
+                }
+            }
+            ',' => {
+                if in_quotes {
+                    current_field.push(',');
+                } else {
+                    fields.push(current_field.clone());
+                    current_field.clear();
+                }
+            }
+            _ => {
+                current_field.push(c);
+            }
+        }
+    }
+
+    fields.push(current_field);
+
+    fields
+}
+
+fn get_array_from_string(string: &str) -> Option<Vec<String>> {
+    if string.starts_with('[') && string.ends_with(']') {
+        let line = &string[1..string.len() - 1];
+        let values = parse_csv_line(line);
+        Some(values)
+    } else {
+        None
+    }
+}
+
+fn parse_csv_value(val: &str) -> serde_json::Value {
+    match val.trim() {
+        "null" => serde_json::Value::Null,
+        "None" => serde_json::Value::Null,
+        val => {
+            let number_value = if let Ok(int_val) = val.parse::<i64>() {
+                serde_json::Number::from_f64(int_val as f64).map(serde_json::Value::Number)
+            } else if let Ok(float_val) = val.parse::<f64>() {
+                serde_json::Number::from_f64(float_val).map(serde_json::Value::Number)
+            } else {
+                None
+            };
+
+            let bool_value = match val.to_lowercase().as_str() {
+                "true" => Some(serde_json::Value::Bool(true)),
+                "false" => Some(serde_json::Value::Bool(false)),
+                _ => None,
+            };
+
+            if let Some(int_value) = number_value {
+                int_value
+            } else if let Some(bool_value) = bool_value {
+                bool_value
+            } else {
+                serde_json::Value::String(val.to_string())
+            }
+        }
+    }
+}
+
+/// Parses a single line of a CSV or JSONL file into a JSON object. The first line which is not valid JSON is treated as the CSV header and stored in `columns`, in which case `None` is returned. Lines which look like JSON objects but cannot be parsed are returned as errors.
+pub fn parse_csv_jsonl_line(
+    line: &str,
+    columns: &mut Vec<String>,
+) -> Result<Option<serde_json::Value>, String> {
+    match serde_json::from_str::<serde_json::Value>(line) {
+        Ok(object) => Ok(Some(object)),
+        Err(err) => {
+            if line.trim_start().starts_with('{') {
+                return Err(format!("Invalid JSON: {}", err));
+            }
+
+            if columns.is_empty() {
+                *columns = parse_csv_line(line);
+                Ok(None)
+            } else {
+                let values = parse_csv_line(line);
+                if values.len() > columns.len() {
+                    return Err(format!(
+                        "Row has {} values but the header only has {} columns",
+                        values.len(),
+                        columns.len()
+                    ));
+                }
+
+                let mut new_object = serde_json::Map::new();
+                let mut line_vals_iter = values.into_iter();
+                for column in columns.iter() {
+                    let key = column.trim();
+                    let value = match line_vals_iter.next() {
+                        Some(val) => parse_csv_value(&val),
+                        None => serde_json::Value::Null,
+                    };
+                    new_object.insert(key.to_string(), value);
+                }
+
+                Ok(Some(serde_json::Value::Object(new_object)))
+            }
+        }
+    }
+}
+
+pub fn spreadsheet_cell_to_value(cell: &Data) -> serde_json::Value {
+    match cell {
+        Data::Int(val) => serde_json::Value::from(*val),
+        Data::Float(val) => serde_json::Number::from_f64(*val)
+            .map(serde_json::Value::Number)
+            .unwrap_or(serde_json::Value::Null),
+        Data::String(val) if val.trim().is_empty() => serde_json::Value::Null,
+        Data::String(val) => serde_json::Value::String(val.trim().to_string()),
+        Data::Bool(val) => serde_json::Value::Bool(*val),
+        Data::DateTime(val) => val
+            .as_datetime()
+            .map(|val| serde_json::Value::String(val.format("%Y-%m-%dT%H:%M:%S").to_string()))
+            .unwrap_or(serde_json::Value::Null),
+        Data::DateTimeIso(val) | Data::DurationIso(val) => serde_json::Value::String(val.clone()),
+        Data::Error(_) | Data::Empty => serde_json::Value::Null,
+    }
+}
+
+/// Reads the rows of a sheet of an XLSX or ODS file into JSON objects keyed by the header row. Returns the one-based row number of each row alongside it. Rows which are entirely empty are skipped.
+pub fn read_spreadsheet_rows(
+    data: Vec<u8>,
+    options: &SpreadsheetOptions,
+) -> Result<Vec<(usize, serde_json::Value)>, ServiceError> {
+    let mut workbook = open_workbook_auto_from_rs(std::io::Cursor::new(data))
+        .map_err(|err| ServiceError::BadRequest(format!("Could not open spreadsheet: {}", err)))?;
+
+    let range = match &options.sheet_name {
+        Some(sheet_name) => workbook.worksheet_range(sheet_name).map_err(|err| {
+            ServiceError::BadRequest(format!("Could not read sheet {}: {}", sheet_name, err))
+        })?,
+        None => {
+            let sheet_index = options.sheet_index.unwrap_or(0);
+            workbook
+                .worksheet_range_at(sheet_index)
+                .ok_or(ServiceError::BadRequest(format!(
+                    "Spreadsheet does not have a sheet at index {}",
+                    sheet_index
+                )))?
+                .map_err(|err| {
+                    ServiceError::BadRequest(format!(
+                        "Could not read sheet at index {}: {}",
+                        sheet_index, err
+                    ))
+                })?
+        }
+    };
+
+    let Some((start_row, _)) = range.start() else {
+        return Ok(vec![]);
+    };
+    let header_row = options.header_row.unwrap_or(1).max(1);
+    let header_offset = (header_row - 1).saturating_sub(start_row as usize);
+
+    let mut rows = range.rows().enumerate().skip(header_offset);
+    let Some((_, header)) = rows.next() else {
+        return Ok(vec![]);
+    };
+    let columns = header
+        .iter()
+        .enumerate()
+        .map(|(idx, cell)| match spreadsheet_cell_to_value(cell) {
+            serde_json::Value::Null => format!("column_{}", idx + 1),
+            serde_json::Value::String(val) => val,
+            val => val.to_string(),
+        })
+        .collect::<Vec<String>>();
+
+    Ok(rows
+        .filter(|(_, cells)| cells.iter().any(|cell| !matches!(cell, Data::Empty)))
+        .map(|(idx, cells)| {
+            let object = columns
+                .iter()
+                .zip(cells.iter())
+                .map(|(column, cell)| (column.clone(), spreadsheet_cell_to_value(cell)))
+                .collect::<serde_json::Map<String, serde_json::Value>>();
+
+            (
+                start_row as usize + idx + 1,
+                serde_json::Value::Object(object),
+            )
+        })
+        .collect())
+}
+
+/// Reads a single row group of a Parquet file into JSON objects. Rows which cannot be decoded are returned as errors so the rest of the row group can still be processed.
+pub fn read_parquet_row_group<R: ChunkReader + 'static>(
+    reader: &SerializedFileReader<R>,
+    row_group_index: usize,
+) -> Result<Vec<Result<serde_json::Value, String>>, ServiceError> {
+    let row_group = reader.get_row_group(row_group_index).map_err(|err| {
+        ServiceError::BadRequest(format!(
+            "Could not read row group {}: {}",
+            row_group_index, err
+        ))
+    })?;
+    let rows = row_group.get_row_iter(None).map_err(|err| {
+        ServiceError::BadRequest(format!(
+            "Could not read rows of row group {}: {}",
+            row_group_index, err
+        ))
+    })?;
+
+    Ok(rows
+        .map(|row| {
+            row.map(|row| row.to_json_value())
+                .map_err(|err| err.to_string())
+        })
+        .collect())
+}
+
+/// Iterates over the rows or objects of an in memory bulk upload file along with their one-based row number.
+pub fn iter_file_rows(
+    data: Vec<u8>,
+    file_format: CsvJsonlFileFormat,
+    spreadsheet_options: &SpreadsheetOptions,
+) -> Result<Box<dyn Iterator<Item = (usize, Result<serde_json::Value, String>)>>, ServiceError> {
+    match file_format {
+        CsvJsonlFileFormat::Csv | CsvJsonlFileFormat::Jsonl => {
+            let lines = String::from_utf8_lossy(&data)
+                .lines()
+                .map(|line| line.to_string())
+                .collect::<Vec<String>>();
+            let mut columns = vec![];
+
+            Ok(Box::new(
+                lines
+                    .into_iter()
+                    .enumerate()
+                    .filter(|(_, line)| !line.trim().is_empty())
+                    .filter_map(move |(idx, line)| {
+                        match parse_csv_jsonl_line(&line, &mut columns) {
+                            Ok(Some(object)) => Some((idx + 1, Ok(object))),
+                            Ok(None) => None,
+                            Err(err) => Some((idx + 1, Err(err))),
+                        }
+                    }),
+            ))
+        }
+        CsvJsonlFileFormat::Parquet => {
+            let reader = SerializedFileReader::new(bytes::Bytes::from(data)).map_err(|err| {
+                ServiceError::BadRequest(format!("Could not open parquet file: {}", err))
+            })?;
+            let num_row_groups = reader.metadata().num_row_groups();
+
+            Ok(Box::new(
+                (0..num_row_groups)
+                    .flat_map(move |row_group_index| {
+                        read_parquet_row_group(&reader, row_group_index)
+                            .unwrap_or_else(|err| vec![Err(err.to_string())])
+                    })
+                    .enumerate()
+                    .map(|(idx, row)| (idx + 1, row)),
+            ))
+        }
+        CsvJsonlFileFormat::Xlsx | CsvJsonlFileFormat::Ods => Ok(Box::new(
+            read_spreadsheet_rows(data, spreadsheet_options)?
+                .into_iter()
+                .map(|(row, object)| (row, Ok(object))),
+        )),
+    }
+}
+
+fn value_as_f64(value: &serde_json::Value) -> Option<f64> {
+    match value {
+        serde_json::Value::Number(num) => num.as_f64(),
+        serde_json::Value::String(val) => val.trim().parse::<f64>().ok(),
+        serde_json::Value::Bool(val) => Some(if *val { 1.0 } else { 0.0 }),
+        _ => None,
+    }
+}
+
+fn value_as_string(value: &serde_json::Value) -> String {
+    match value {
+        serde_json::Value::String(val) => val.clone(),
+        val => val.to_string(),
+    }
+}
+
+fn parse_date(value: &serde_json::Value, format: Option<&str>) -> Option<NaiveDateTime> {
+    match value {
+        serde_json::Value::Number(num) => num
+            .as_i64()
+            .and_then(|val| DateTime::from_timestamp(val, 0))
+            .map(|val| val.naive_utc()),
+        serde_json::Value::String(val) => {
+            let val = val.trim();
+            match format {
+                Some(format) => NaiveDateTime::parse_from_str(val, format).ok().or_else(|| {
+                    NaiveDate::parse_from_str(val, format)
+                        .ok()
+                        .and_then(|date| date.and_hms_opt(0, 0, 0))
+                }),
+                None => val.parse::<DateTimeUtc>().ok().map(|val| val.0.naive_utc()),
+            }
+        }
+        _ => None,
+    }
+}
+
+/// Coerces a value according to `transform`. Returns `None` if the value cannot be coerced.
+pub fn apply_field_transform(
+    value: &serde_json::Value,
+    transform: &CsvJsonlFieldTransform,
+) -> Option<serde_json::Value> {
+    match transform {
+        CsvJsonlFieldTransform::Int => value_as_f64(value)
+            .filter(|val| val.is_finite())
+            .map(|val| serde_json::Value::from(val.trunc() as i64)),
+        CsvJsonlFieldTransform::Float => value_as_f64(value)
+            .and_then(serde_json::Number::from_f64)
+            .map(serde_json::Value::Number),
+        CsvJsonlFieldTransform::Bool => match value {
+            serde_json::Value::Bool(val) => Some(serde_json::Value::Bool(*val)),
+            serde_json::Value::Number(num) => {
+                num.as_f64().map(|val| serde_json::Value::Bool(val != 0.0))
+            }
+            serde_json::Value::String(val) => match val.trim().to_lowercase().as_str() {
+                "true" | "yes" | "y" | "1" => Some(serde_json::Value::Bool(true)),
+                "false" | "no" | "n" | "0" => Some(serde_json::Value::Bool(false)),
+                _ => None,
+            },
+            _ => None,
+        },
+        CsvJsonlFieldTransform::Date { format } => parse_date(value, format.as_deref())
+            .map(|val| serde_json::Value::String(val.format("%Y-%m-%dT%H:%M:%S").to_string())),
+        CsvJsonlFieldTransform::List { delimiter } => match value {
+            serde_json::Value::Array(_) => Some(value.clone()),
+            serde_json::Value::Null => None,
+            val => {
+                let delimiter = delimiter.as_deref().unwrap_or(",");
+                Some(serde_json::Value::Array(
+                    value_as_string(val)
+                        .split(delimiter)
+                        .map(|item| item.trim())
+                        .filter(|item| !item.is_empty())
+                        .map(|item| serde_json::Value::String(item.to_string()))
+                        .collect(),
+                ))
+            }
+        },
+    }
+}
+
+fn compare_values(
+    left: &serde_json::Value,
+    right: &serde_json::Value,
+) -> Option<std::cmp::Ordering> {
+    value_as_f64(left)?.partial_cmp(&value_as_f64(right)?)
+}
+
+fn values_equal(left: &serde_json::Value, right: &serde_json::Value) -> bool {
+    if let Some(ordering) = compare_values(left, right) {
+        return ordering == std::cmp::Ordering::Equal;
+    }
+
+    value_as_string(left) == value_as_string(right)
+}
+
+fn row_matches_filter(row: &serde_json::Value, filter: &CsvJsonlRowFilter) -> bool {
+    let field_value = row.get(&filter.field).filter(|val| !val.is_null());
+    let filter_value = filter.value.as_ref().unwrap_or(&serde_json::Value::Null);
+
+    match filter.operator {
+        CsvJsonlRowFilterOperator::Exists => field_value.is_some(),
+        CsvJsonlRowFilterOperator::NotExists => field_value.is_none(),
+        CsvJsonlRowFilterOperator::Eq => {
+            field_value.is_some_and(|val| values_equal(val, filter_value))
+        }
+        CsvJsonlRowFilterOperator::Ne => {
+            !field_value.is_some_and(|val| values_equal(val, filter_value))
+        }
+        CsvJsonlRowFilterOperator::Gt => field_value
+            .and_then(|val| compare_values(val, filter_value))
+            .is_some_and(|ordering| ordering.is_gt()),
+        CsvJsonlRowFilterOperator::Gte => field_value
+            .and_then(|val| compare_values(val, filter_value))
+            .is_some_and(|ordering| ordering.is_ge()),
+        CsvJsonlRowFilterOperator::Lt => field_value
+            .and_then(|val| compare_values(val, filter_value))
+            .is_some_and(|ordering| ordering.is_lt()),
+        CsvJsonlRowFilterOperator::Lte => field_value
+            .and_then(|val| compare_values(val, filter_value))
+            .is_some_and(|ordering| ordering.is_le()),
+        CsvJsonlRowFilterOperator::Contains => match field_value {
+            Some(serde_json::Value::Array(arr)) => {
+                arr.iter().any(|val| values_equal(val, filter_value))
+            }
+            Some(val) => value_as_string(val).contains(&value_as_string(filter_value)),
+            None => false,
+        },
+    }
+}
+
+fn insert_metadata_value(
+    metadata: &mut serde_json::Map<String, serde_json::Value>,
+    key: &str,
+    value: serde_json::Value,
+) {
+    let mut current = metadata;
+    let mut parts = key.split('.').peekable();
+    while let Some(part) = parts.next() {
+        if parts.peek().is_none() {
+            current.insert(part.to_string(), value);
+            return;
+        }
+
+        let entry = current
+            .entry(part.to_string())
+            .or_insert_with(|| serde_json::Value::Object(serde_json::Map::new()));
+        if !entry.is_object() {
+            *entry = serde_json::Value::Object(serde_json::Map::new());
+        }
+        current = match entry {
+            serde_json::Value::Object(obj) => obj,
+            _ => return,
+        };
+    }
+}
+
+fn push_string_values(value: &serde_json::Value, values: &mut Vec<String>) {
+    if let Some(arr) = value.as_array() {
+        for item in arr {
+            if let Some(item) = item.as_str() {
+                values.push(item.to_string());
+            }
+        }
+    } else if let Some(arr) = get_array_from_string(value.as_str().unwrap_or("")) {
+        values.extend(arr);
+    } else if let Some(val) = value.as_str() {
+        values.push(val.to_string());
+    }
+}
+
+/// Options controlling how rows or objects of a CSV or JSONL file are converted into chunks.
+#[derive(Debug, Clone, Default)]
+pub struct CsvJsonlConversionOptions {
+    pub mappings: Vec<ChunkReqPayloadMapping>,
+    pub row_filters: Vec<CsvJsonlRowFilter>,
+    pub upsert_by_tracking_id: Option<bool>,
+    pub fulltext_boost_factor: Option<f64>,
+    pub semantic_boost_factor: Option<f64>,
+}
+
+/// Converts rows or objects of a CSV or JSONL file into ChunkReqPayloads. Templates are compiled once up front so invalid templates are rejected before any rows are processed.
+pub struct CsvJsonlRowConverter {
+    options: CsvJsonlConversionOptions,
+    templates: Environment<'static>,
+}
+
+impl CsvJsonlRowConverter {
+    pub fn new(options: CsvJsonlConversionOptions) -> Result<Self, ServiceError> {
+        let mut templates = Environment::new();
+        for (idx, mapping) in options.mappings.iter().enumerate() {
+            if let Some(template) = &mapping.template {
+                templates
+                    .add_template_owned(idx.to_string(), template.clone())
+                    .map_err(|err| {
+                        ServiceError::BadRequest(format!(
+                            "Invalid template for mapping {}: {}",
+                            idx, err
+                        ))
+                    })?;
+            } else if mapping.csv_jsonl_field.is_empty() {
+                return Err(ServiceError::BadRequest(format!(
+                    "Mapping {} must specify either csv_jsonl_field or template",
+                    idx
+                )));
+            }
+
+            if mapping.metadata_key.is_some()
+                && !matches!(
+                    mapping.chunk_req_payload_field,
+                    ChunkReqPayloadFields::Metadata
+                )
+            {
+                return Err(ServiceError::BadRequest(format!(
+                    "metadata_key can only be used with metadata mappings (mapping {})",
+                    idx
+                )));
+            }
+        }
+
+        Ok(CsvJsonlRowConverter { options, templates })
+    }
+
+    /// Returns true if the row satisfies all of the row filters.
+    pub fn keep_row(&self, row: &serde_json::Value) -> bool {
+        self.options
+            .row_filters
+            .iter()
+            .all(|filter| row_matches_filter(row, filter))
+    }
+
+    fn mapped_value(
+        &self,
+        idx: usize,
+        mapping: &ChunkReqPayloadMapping,
+        row: &serde_json::Value,
+    ) -> Option<serde_json::Value> {
+        let value = if mapping.template.is_some() {
+            let mut context = match row {
+                serde_json::Value::Object(obj) => obj.clone(),
+                _ => serde_json::Map::new(),
+            };
+            context.insert("row".to_string(), row.clone());
+
+            let rendered = self
+                .templates
+                .get_template(&idx.to_string())
+                .and_then(|template| template.render(&context))
+                .map_err(|err| {
+                    log::warn!("Failed to render template for mapping {}: {}", idx, err);
+                })
+                .ok()?;
+            serde_json::Value::String(rendered)
+        } else {
+            row.get(&mapping.csv_jsonl_field)
+                .filter(|val| !val.is_null())?
+                .clone()
+        };
+
+        match &mapping.transform {
+            Some(transform) => apply_field_transform(&value, transform),
+            None => Some(value),
+        }
+    }
+
+    /// Converts a row or object into a ChunkReqPayload. Returns `None` if the row is excluded by the row filters.
+    pub fn convert(
+        &self,
+        value: serde_json::Value,
+        group_id: Option<uuid::Uuid>,
+    ) -> Option<ChunkReqPayload> {
+        if !self.keep_row(&value) {
+            return None;
+        }
+
+        let cleaned_value = match value.clone() {
+            serde_json::Value::Object(obj) => {
+                let mut new_obj = serde_json::Map::new();
+                for (key, value) in obj {
+                    if value.is_null() {
+                        continue;
+                    }
+                    new_obj.insert(key, value);
+                }
+                serde_json::Value::Object(new_obj)
+            }
+            _ => value.clone(),
+        };
+        let chunk_html = serde_json::to_string(&cleaned_value).ok();
+        let mut chunk_req_payload = ChunkReqPayload {
+            chunk_html,
+            semantic_content: None,
+            fulltext_content: None,
+            link: None,
+            tag_set: None,
+            num_value: None,
+            metadata: Some(value.clone()),
+            tracking_id: None,
+            upsert_by_tracking_id: Some(self.options.upsert_by_tracking_id.unwrap_or(true)),
+            group_ids: Some(group_id.into_iter().collect()),
+            group_tracking_ids: None,
+            time_stamp: None,
+            location: None,
+            image_urls: None,
+            weight: None,
+            split_avg: None,
+            convert_html_to_text: None,
+            fulltext_boost: None,
+            semantic_boost: None,
+            high_priority: None,
+        };
+
+        let mut boost_phrase = String::new();
+        let mut semantic_boost_phrase = String::new();
+        let mut lat: Option<GeoTypes> = None;
+        let mut lon: Option<GeoTypes> = None;
+        let mut metadata: Option<serde_json::Map<String, serde_json::Value>> = None;
+
+        for (idx, mapping) in self.options.mappings.iter().enumerate() {
+            if matches!(
+                mapping.chunk_req_payload_field,
+                ChunkReqPayloadFields::Metadata
+            ) {
+                metadata.get_or_insert_with(serde_json::Map::new);
+            }
+
+            let Some(val) = self.mapped_value(idx, mapping, &value) else {
+                continue;
+            };
+
+            match mapping.chunk_req_payload_field {
+                ChunkReqPayloadFields::Link => {
+                    if let Some(val) = val.as_str() {
+                        chunk_req_payload.link = Some(val.to_string());
+                    }
+                }
+                ChunkReqPayloadFields::TagSet => {
+                    let mut cur_tag_set = chunk_req_payload.tag_set.clone().unwrap_or_default();
+                    push_string_values(&val, &mut cur_tag_set);
+                    chunk_req_payload.tag_set = Some(cur_tag_set);
+                }
+                ChunkReqPayloadFields::NumValue => {
+                    if let Some(val) = val.as_f64() {
+                        chunk_req_payload.num_value = Some(val);
+                    }
+                }
+                ChunkReqPayloadFields::TrackingId => {
+                    chunk_req_payload.tracking_id = Some(val.to_string());
+                }
+                ChunkReqPayloadFields::GroupTrackingIds => {
+                    let mut cur_group_tracking_ids = chunk_req_payload
+                        .group_tracking_ids
+                        .clone()
+                        .unwrap_or_default();
+                    push_string_values(&val, &mut cur_group_tracking_ids);
+                    chunk_req_payload.group_tracking_ids = Some(cur_group_tracking_ids);
+                }
+                ChunkReqPayloadFields::TimeStamp => match val {
+                    serde_json::Value::String(val) => {
+                        let _ = val
+                            .parse::<NaiveDateTime>()
+                            .map(|val| chunk_req_payload.time_stamp = Some(val.to_string()));
+                    }
+                    serde_json::Value::Number(val) => {
+                        let _ = val.as_i64().map(|val| {
+                            chunk_req_payload.time_stamp =
+                                DateTime::from_timestamp(val, 0).map(|val| {
+                                    val.with_timezone(&chrono::Local).naive_local().to_string()
+                                })
+                        });
+                    }
+                    _ => {}
+                },
+                ChunkReqPayloadFields::Lat => {
+                    if let Some(val) = val.as_f64() {
+                        lat = Some(GeoTypes::Float(val));
+                    }
+                }
+                ChunkReqPayloadFields::Lon => {
+                    if let Some(val) = val.as_f64() {
+                        lon = Some(GeoTypes::Float(val));
+                    }
+                }
+                ChunkReqPayloadFields::ImageUrls => {
+                    let mut cur_image_urls =
+                        chunk_req_payload.image_urls.clone().unwrap_or_default();
+                    push_string_values(&val, &mut cur_image_urls);
+                    chunk_req_payload.image_urls = Some(cur_image_urls);
+                }
+                ChunkReqPayloadFields::Weight => {
+                    if let Some(val) = val.as_f64() {
+                        chunk_req_payload.weight = Some(val);
+                    }
+                }
+                ChunkReqPayloadFields::BoostPhrase => {
+                    if let Some(val) = val.as_str() {
+                        boost_phrase.push_str(format!(" {}", val).as_str());
+                    }
+                }
+                ChunkReqPayloadFields::SemanticBoostPhrase => {
+                    if let Some(val) = val.as_str() {
+                        semantic_boost_phrase.push_str(format!(" {}", val).as_str());
+                    }
+                }
+                ChunkReqPayloadFields::ChunkHtml => {
+                    chunk_req_payload.chunk_html = Some(value_as_string(&val));
+                }
+                ChunkReqPayloadFields::SemanticContent => {
+                    chunk_req_payload.semantic_content = Some(value_as_string(&val));
+                }
+                ChunkReqPayloadFields::FulltextContent => {
+                    chunk_req_payload.fulltext_content = Some(value_as_string(&val));
+                }
+                ChunkReqPayloadFields::Metadata => {
+                    let key = mapping
+                        .metadata_key
+                        .as_deref()
+                        .unwrap_or(mapping.csv_jsonl_field.as_str());
+                    if key.is_empty() {
+                        continue;
+                    }
+                    if let Some(metadata) = metadata.as_mut() {
+                        insert_metadata_value(metadata, key, val);
+                    }
+                }
+            }
+        }
+
+        if let Some(metadata) = metadata {
+            chunk_req_payload.metadata = Some(serde_json::Value::Object(metadata));
+        }
+        if let Some(fulltext_boost_factor) = self.options.fulltext_boost_factor {
+            chunk_req_payload.fulltext_boost = Some(FullTextBoost {
+                phrase: boost_phrase.clone(),
+                boost_factor: fulltext_boost_factor,
+            });
+        }
+        if let Some(semantic_boost_factor) = self.options.semantic_boost_factor {
+            chunk_req_payload.semantic_boost = Some(SemanticBoost {
+                phrase: if semantic_boost_phrase.is_empty() {
+                    boost_phrase
+                } else {
+                    semantic_boost_phrase
+                },
+                distance_factor: semantic_boost_factor as f32,
+            });
+        }
+        if let Some(lat) = lat {
+            if let Some(lon) = lon {
+                chunk_req_payload.location = Some(GeoInfo { lat, lon });
+            }
+        }
+
+        Some(chunk_req_payload)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn mapping(
+        csv_jsonl_field: &str,
+        chunk_req_payload_field: ChunkReqPayloadFields,
+    ) -> ChunkReqPayloadMapping {
+        ChunkReqPayloadMapping {
+            csv_jsonl_field: csv_jsonl_field.to_string(),
+            chunk_req_payload_field,
+            template: None,
+            metadata_key: None,
+            transform: None,
+        }
+    }
+
+    fn filter(
+        field: &str,
+        operator: CsvJsonlRowFilterOperator,
+        value: Option<serde_json::Value>,
+    ) -> CsvJsonlRowFilter {
+        CsvJsonlRowFilter {
+            field: field.to_string(),
+            operator,
+            value,
+        }
+    }
+
+    #[test]
+    fn test_apply_field_transform() {
+        assert_eq!(
+            apply_field_transform(&json!("42.7"), &CsvJsonlFieldTransform::Int),
+            Some(json!(42))
+        );
+        assert_eq!(
+            apply_field_transform(&json!("abc"), &CsvJsonlFieldTransform::Int),
+            None
+        );
+        assert_eq!(
+            apply_field_transform(&json!(" 1.5 "), &CsvJsonlFieldTransform::Float),
+            Some(json!(1.5))
+        );
+        assert_eq!(
+            apply_field_transform(&json!("Yes"), &CsvJsonlFieldTransform::Bool),
+            Some(json!(true))
+        );
+        assert_eq!(
+            apply_field_transform(&json!(0), &CsvJsonlFieldTransform::Bool),
+            Some(json!(false))
+        );
+        assert_eq!(
+            apply_field_transform(&json!("maybe"), &CsvJsonlFieldTransform::Bool),
+            None
+        );
+        assert_eq!(
+            apply_field_transform(
+                &json!("31/12/2024"),
+                &CsvJsonlFieldTransform::Date {
+                    format: Some("%d/%m/%Y".to_string())
+                }
+            ),
+            Some(json!("2024-12-31T00:00:00"))
+        );
+        assert_eq!(
+            apply_field_transform(&json!(0), &CsvJsonlFieldTransform::Date { format: None }),
+            Some(json!("1970-01-01T00:00:00"))
+        );
+        assert_eq!(
+            apply_field_transform(
+                &json!("a; b;;c "),
+                &CsvJsonlFieldTransform::List {
+                    delimiter: Some(";".to_string())
+                }
+            ),
+            Some(json!(["a", "b", "c"]))
+        );
+        assert_eq!(
+            apply_field_transform(
+                &json!(["x"]),
+                &CsvJsonlFieldTransform::List { delimiter: None }
+            ),
+            Some(json!(["x"]))
+        );
+        assert_eq!(
+            apply_field_transform(
+                &serde_json::Value::Null,
+                &CsvJsonlFieldTransform::List { delimiter: None }
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn test_row_matches_filter() {
+        let row =
+            json!({"price": "10.5", "status": "published", "tags": ["a", "b"], "empty": null});
+
+        assert!(row_matches_filter(
+            &row,
+            &filter("price", CsvJsonlRowFilterOperator::Gt, Some(json!(10)))
+        ));
+        assert!(!row_matches_filter(
+            &row,
+            &filter("price", CsvJsonlRowFilterOperator::Lte, Some(json!(10)))
+        ));
+        assert!(row_matches_filter(
+            &row,
+            &filter("price", CsvJsonlRowFilterOperator::Eq, Some(json!(10.5)))
+        ));
+        assert!(row_matches_filter(
+            &row,
+            &filter(
+                "status",
+                CsvJsonlRowFilterOperator::Eq,
+                Some(json!("published"))
+            )
+        ));
+        assert!(row_matches_filter(
+            &row,
+            &filter(
+                "status",
+                CsvJsonlRowFilterOperator::Ne,
+                Some(json!("draft"))
+            )
+        ));
+        assert!(row_matches_filter(
+            &row,
+            &filter(
+                "missing",
+                CsvJsonlRowFilterOperator::Ne,
+                Some(json!("draft"))
+            )
+        ));
+        assert!(row_matches_filter(
+            &row,
+            &filter(
+                "tags",
+                CsvJsonlRowFilterOperator::Contains,
+                Some(json!("b"))
+            )
+        ));
+        assert!(row_matches_filter(
+            &row,
+            &filter(
+                "status",
+                CsvJsonlRowFilterOperator::Contains,
+                Some(json!("lish"))
+            )
+        ));
+        assert!(row_matches_filter(
+            &row,
+            &filter("status", CsvJsonlRowFilterOperator::Exists, None)
+        ));
+        assert!(row_matches_filter(
+            &row,
+            &filter("empty", CsvJsonlRowFilterOperator::NotExists, None)
+        ));
+        assert!(!row_matches_filter(
+            &row,
+            &filter("status", CsvJsonlRowFilterOperator::Gt, Some(json!(1)))
+        ));
+    }
+
+    #[test]
+    fn test_insert_metadata_value() {
+        let mut metadata = serde_json::Map::new();
+        insert_metadata_value(&mut metadata, "product.price.amount", json!(10));
+        insert_metadata_value(&mut metadata, "product.name", json!("shoe"));
+        insert_metadata_value(&mut metadata, "color", json!("red"));
+        assert_eq!(
+            serde_json::Value::Object(metadata.clone()),
+            json!({"product": {"price": {"amount": 10}, "name": "shoe"}, "color": "red"})
+        );
+
+        insert_metadata_value(&mut metadata, "color.hex", json!("#f00"));
+        assert_eq!(metadata["color"], json!({"hex": "#f00"}));
+    }
+
+    #[test]
+    fn test_convert_row() {
+        let mut price_mapping = mapping("price", ChunkReqPayloadFields::Metadata);
+        price_mapping.metadata_key = Some("pricing.amount".to_string());
+        price_mapping.transform = Some(CsvJsonlFieldTransform::Float);
+
+        let mut html_mapping = mapping("", ChunkReqPayloadFields::ChunkHtml);
+        html_mapping.template = Some("<h1>{{ title }}</h1>{{ row[\"Body Text\"] }}".to_string());
+
+        let converter = CsvJsonlRowConverter::new(CsvJsonlConversionOptions {
+            mappings: vec![
+                html_mapping,
+                mapping("tags", ChunkReqPayloadFields::TagSet),
+                price_mapping,
+            ],
+            row_filters: vec![filter(
+                "status",
+                CsvJsonlRowFilterOperator::Eq,
+                Some(json!("published")),
+            )],
+            upsert_by_tracking_id: None,
+            fulltext_boost_factor: None,
+            semantic_boost_factor: None,
+        })
+        .unwrap();
+
+        let group_id = uuid::Uuid::new_v4();
+        let chunk = converter
+            .convert(
+                json!({
+                    "id": "sku-1",
+                    "title": "Shoe",
+                    "Body Text": "Comfortable",
+                    "tags": "[red,blue]",
+                    "price": "19.99",
+                    "status": "published",
+                }),
+                Some(group_id),
+            )
+            .unwrap();
+
+        assert_eq!(
+            chunk.chunk_html.as_deref(),
+            Some("<h1>Shoe</h1>Comfortable")
+        );
+        assert_eq!(
+            chunk.tag_set,
+            Some(vec!["red".to_string(), "blue".to_string()])
+        );
+        assert_eq!(chunk.metadata, Some(json!({"pricing": {"amount": 19.99}})));
+        assert_eq!(chunk.group_ids, Some(vec![group_id]));
+        assert_eq!(chunk.upsert_by_tracking_id, Some(true));
+
+        assert!(converter
+            .convert(json!({"id": "sku-2", "status": "draft"}), None)
+            .is_none());
+    }
+
+    #[test]
+    fn test_converter_rejects_invalid_mappings() {
+        let mut metadata_key_mapping = mapping("price", ChunkReqPayloadFields::Link);
+        metadata_key_mapping.metadata_key = Some("price".to_string());
+
+        for invalid_mapping in [
+            mapping("", ChunkReqPayloadFields::Link),
+            metadata_key_mapping,
+        ] {
+            assert!(CsvJsonlRowConverter::new(CsvJsonlConversionOptions {
+                mappings: vec![invalid_mapping],
+                ..Default::default()
+            })
+            .is_err());
+        }
+    }
+}
@@ -0,0 +1,176 @@
+use actix_web::web;
+use diesel::prelude::*;
+use diesel::result::{DatabaseErrorKind, Error as DieselError};
+use diesel_async::RunQueryDsl;
+
+use crate::data::models::{DatasetAlias, Pool};
+use crate::errors::ServiceError;
+
+pub async fn create_dataset_alias_query(
+    dataset_alias: DatasetAlias,
+    pool: web::Data<Pool>,
+) -> Result<DatasetAlias, ServiceError> {
+    use crate::data::schema::dataset_aliases::dsl as dataset_aliases_columns;
+
+    let mut conn = pool.get().await.map_err(|_e| {
+        ServiceError::InternalServerError("Failed to get postgres connection".to_string())
+    })?;
+
+    diesel::insert_into(dataset_aliases_columns::dataset_aliases)
+        .values(&dataset_alias)
+        .get_result::<DatasetAlias>(&mut conn)
+        .await
+        .map_err(|err| match err {
+            DieselError::DatabaseError(DatabaseErrorKind::UniqueViolation, _) => {
+                ServiceError::BadRequest(format!(
+                    "An alias named {} already exists in this organization",
+                    dataset_alias.name
+                ))
+            }
+            _ => {
+                log::error!("Failed to create dataset alias: {:?}", err);
+                ServiceError::InternalServerError("Failed to create dataset alias".to_string())
+            }
+        })
+}
+
+pub async fn get_dataset_aliases_query(
+    organization_id: uuid::Uuid,
+    pool: web::Data<Pool>,
+) -> Result<Vec<DatasetAlias>, ServiceError> {
+    use crate::data::schema::dataset_aliases::dsl as dataset_aliases_columns;
+
+    let mut conn = pool.get().await.map_err(|_e| {
+        ServiceError::InternalServerError("Failed to get postgres connection".to_string())
+    })?;
+
+    dataset_aliases_columns::dataset_aliases
+        .filter(dataset_aliases_columns::organization_id.eq(organization_id))
+        .order(dataset_aliases_columns::name.asc())
+        .select(DatasetAlias::as_select())
+        .load::<DatasetAlias>(&mut conn)
+        .await
+        .map_err(|err| {
+            log::error!("Failed to get dataset aliases: {:?}", err);
+            ServiceError::InternalServerError("Failed to get dataset aliases".to_string())
+        })
+}
+
+/// Returns which of `names` are already taken by aliases of the organization. Aliases share the
+/// dataset tracking id namespace, so a tracking id cannot be set to one of these.
+pub async fn get_dataset_alias_names_in_use_query(
+    names: Vec<String>,
+    organization_id: uuid::Uuid,
+    pool: web::Data<Pool>,
+) -> Result<Vec<String>, ServiceError> {
+    use crate::data::schema::dataset_aliases::dsl as dataset_aliases_columns;
+
+    if names.is_empty() {
+        return Ok(vec![]);
+    }
+
+    let mut conn = pool.get().await.map_err(|_e| {
+        ServiceError::InternalServerError("Failed to get postgres connection".to_string())
+    })?;
+
+    dataset_aliases_columns::dataset_aliases
+        .filter(dataset_aliases_columns::organization_id.eq(organization_id))
+        .filter(dataset_aliases_columns::name.eq_any(names))
+        .select(dataset_aliases_columns::name)
+        .load::<String>(&mut conn)
+        .await
+        .map_err(|err| {
+            log::error!("Failed to get dataset alias names: {:?}", err);
+            ServiceError::InternalServerError("Failed to get dataset alias names".to_string())
+        })
+}
+
+/// Points the alias at `dataset_id` and remembers the dataset it pointed at before. Without a
+/// dataset id the alias flips back to its previous dataset. Both happen in a single update, so
+/// every request sees either the old or the new dataset.
+pub async fn swap_dataset_alias_query(
+    name: String,
+    organization_id: uuid::Uuid,
+    dataset_id: Option<uuid::Uuid>,
+    pool: web::Data<Pool>,
+) -> Result<DatasetAlias, ServiceError> {
+    use crate::data::schema::dataset_aliases::dsl as dataset_aliases_columns;
+
+    let mut conn = pool.get().await.map_err(|_e| {
+        ServiceError::InternalServerError("Failed to get postgres connection".to_string())
+    })?;
+
+    let alias_filter = dataset_aliases_columns::dataset_aliases
+        .filter(dataset_aliases_columns::name.eq(name.clone()))
+        .filter(dataset_aliases_columns::organization_id.eq(organization_id));
+
+    let swapped_alias = match dataset_id {
+        Some(dataset_id) => {
+            diesel::update(alias_filter)
+                .set((
+                    dataset_aliases_columns::previous_dataset_id
+                        .eq(dataset_aliases_columns::dataset_id.nullable()),
+                    dataset_aliases_columns::dataset_id.eq(dataset_id),
+                    dataset_aliases_columns::updated_at.eq(chrono::Utc::now().naive_local()),
+                ))
+                .get_result::<DatasetAlias>(&mut conn)
+                .await
+        }
+        None => {
+            diesel::update(
+                alias_filter.filter(dataset_aliases_columns::previous_dataset_id.is_not_null()),
+            )
+            .set((
+                dataset_aliases_columns::previous_dataset_id
+                    .eq(dataset_aliases_columns::dataset_id.nullable()),
+                dataset_aliases_columns::dataset_id
+                    .eq(dataset_aliases_columns::previous_dataset_id.assume_not_null()),
+                dataset_aliases_columns::updated_at.eq(chrono::Utc::now().naive_local()),
+            ))
+            .get_result::<DatasetAlias>(&mut conn)
+            .await
+        }
+    };
+
+    swapped_alias.map_err(|err| match err {
+        DieselError::NotFound if dataset_id.is_none() => ServiceError::BadRequest(format!(
+            "Alias {} does not exist or has no previous dataset to swap back to",
+            name
+        )),
+        DieselError::NotFound => ServiceError::NotFound(format!("Alias {} not found", name)),
+        _ => {
+            log::error!("Failed to swap dataset alias: {:?}", err);
+            ServiceError::InternalServerError("Failed to swap dataset alias".to_string())
+        }
+    })
+}
+
+pub async fn delete_dataset_alias_query(
+    name: String,
+    organization_id: uuid::Uuid,
+    pool: web::Data<Pool>,
+) -> Result<(), ServiceError> {
+    use crate::data::schema::dataset_aliases::dsl as dataset_aliases_columns;
+
+    let mut conn = pool.get().await.map_err(|_e| {
+        ServiceError::InternalServerError("Failed to get postgres connection".to_string())
+    })?;
+
+    let deleted = diesel::delete(
+        dataset_aliases_columns::dataset_aliases
+            .filter(dataset_aliases_columns::name.eq(name.clone()))
+            .filter(dataset_aliases_columns::organization_id.eq(organization_id)),
+    )
+    .execute(&mut conn)
+    .await
+    .map_err(|err| {
+        log::error!("Failed to delete dataset alias: {:?}", err);
+        ServiceError::InternalServerError("Failed to delete dataset alias".to_string())
+    })?;
+
+    if deleted == 0 {
+        return Err(ServiceError::NotFound(format!("Alias {} not found", name)));
+    }
+
+    Ok(())
+}
@@ -0,0 +1,223 @@
+use actix_web::web;
+use diesel::prelude::*;
+use diesel_async::RunQueryDsl;
+use serde_json::json;
+use std::collections::BTreeSet;
+
+use crate::data::models::{
+    DatasetConfigVersion, DatasetConfigVersionSource, DatasetConfiguration, Pool,
+};
+use crate::errors::ServiceError;
+
+/// Configuration keys which the dataset's existing vectors were computed with. Changing them
+/// without reindexing leaves search comparing against incompatible vectors.
+pub const REINDEX_REQUIRED_CONFIG_KEYS: [&str; 6] = [
+    "EMBEDDING_SIZE",
+    "DISTANCE_METRIC",
+    "EMBEDDING_MODEL_NAME",
+    "BM25_B",
+    "BM25_K",
+    "BM25_AVG_LEN",
+];
+
+/// Configuration keys whose values are never returned in diffs
+const REDACTED_CONFIG_KEYS: [&str; 2] = ["LLM_API_KEY", "RERANKER_API_KEY"];
+
+/// Keys which differ between two configurations, each with the value `from` before and `to` after
+pub fn get_dataset_config_diff(
+    prev_config: &DatasetConfiguration,
+    new_config: &DatasetConfiguration,
+) -> serde_json::Value {
+    let prev_json = prev_config.to_json();
+    let new_json = new_config.to_json();
+    let mut diff = serde_json::Map::new();
+
+    if let (Some(prev_fields), Some(new_fields)) = (prev_json.as_object(), new_json.as_object()) {
+        let keys = prev_fields
+            .keys()
+            .chain(new_fields.keys())
+            .collect::<BTreeSet<&String>>();
+
+        for key in keys {
+            let prev_value = prev_fields
+                .get(key)
+                .cloned()
+                .unwrap_or(serde_json::Value::Null);
+            let new_value = new_fields
+                .get(key)
+                .cloned()
+                .unwrap_or(serde_json::Value::Null);
+
+            if prev_value == new_value {
+                continue;
+            }
+
+            if REDACTED_CONFIG_KEYS.contains(&key.as_str()) {
+                diff.insert(
+                    key.clone(),
+                    json!({ "from": "[redacted]", "to": "[redacted]" }),
+                );
+            } else {
+                diff.insert(key.clone(), json!({ "from": prev_value, "to": new_value }));
+            }
+        }
+    }
+
+    serde_json::Value::Object(diff)
+}
+
+/// Rejects configuration changes which would require reindexing the dataset unless the caller has
+/// acknowledged that the existing vectors will no longer match the configuration
+pub fn check_reindex_required_config_changes(
+    prev_config: &DatasetConfiguration,
+    new_config: &DatasetConfiguration,
+    acknowledged: bool,
+) -> Result<(), ServiceError> {
+    if acknowledged {
+        return Ok(());
+    }
+
+    let prev_json = prev_config.to_json();
+    let new_json = new_config.to_json();
+
+    let changed_keys = REINDEX_REQUIRED_CONFIG_KEYS
+        .iter()
+        .filter(|key| prev_json.get(**key) != new_json.get(**key))
+        .copied()
+        .collect::<Vec<&str>>();
+
+    if !changed_keys.is_empty() {
+        return Err(ServiceError::BadRequest(format!(
+            "Changing {} requires reindexing the dataset. Use the reindex API to migrate the dataset's vectors, or set acknowledge_reindex_required to true to apply the change anyway.",
+            changed_keys.join(", ")
+        )));
+    }
+
+    Ok(())
+}
+
+/// Records a change to a dataset's configuration as a new version. The configuration from before
+/// the change is recorded as a baseline version first if the dataset has no versions yet. Returns
+/// None without recording anything if the configuration did not change. Runs on the connection
+/// of the transaction which updates the dataset so the version is committed with the change.
+pub async fn insert_dataset_config_version_query(
+    dataset_id: uuid::Uuid,
+    prev_config: &DatasetConfiguration,
+    new_config: &DatasetConfiguration,
+    source: DatasetConfigVersionSource,
+    changed_by: Option<uuid::Uuid>,
+    restored_version: Option<i32>,
+    conn: &mut diesel_async::AsyncPgConnection,
+) -> Result<Option<DatasetConfigVersion>, diesel::result::Error> {
+    use crate::data::schema::dataset_config_versions::dsl as dataset_config_versions_columns;
+    use crate::data::schema::datasets::dsl as datasets_columns;
+
+    let diff = get_dataset_config_diff(prev_config, new_config);
+    if diff.as_object().map_or(true, |diff| diff.is_empty()) {
+        return Ok(None);
+    }
+
+    // Locking the dataset serializes version numbers for concurrent changes
+    datasets_columns::datasets
+        .filter(datasets_columns::id.eq(dataset_id))
+        .select(datasets_columns::id)
+        .for_update()
+        .first::<uuid::Uuid>(conn)
+        .await?;
+
+    let latest_version = dataset_config_versions_columns::dataset_config_versions
+        .filter(dataset_config_versions_columns::dataset_id.eq(dataset_id))
+        .select(diesel::dsl::max(dataset_config_versions_columns::version))
+        .first::<Option<i32>>(conn)
+        .await?;
+
+    let mut versions = vec![];
+    let next_version = match latest_version {
+        Some(latest_version) => latest_version + 1,
+        None => {
+            versions.push(DatasetConfigVersion::from_details(
+                dataset_id,
+                1,
+                prev_config.to_json(),
+                json!({}),
+                DatasetConfigVersionSource::Baseline,
+                None,
+                None,
+            ));
+            2
+        }
+    };
+
+    versions.push(DatasetConfigVersion::from_details(
+        dataset_id,
+        next_version,
+        new_config.to_json(),
+        diff,
+        source,
+        changed_by,
+        restored_version,
+    ));
+
+    diesel::insert_into(dataset_config_versions_columns::dataset_config_versions)
+        .values(&versions)
+        .execute(conn)
+        .await?;
+
+    Ok(versions.pop())
+}
+
+pub async fn get_dataset_config_versions_query(
+    dataset_id: uuid::Uuid,
+    page: i64,
+    page_size: i64,
+    pool: web::Data<Pool>,
+) -> Result<Vec<DatasetConfigVersion>, ServiceError> {
+    use crate::data::schema::dataset_config_versions::dsl as dataset_config_versions_columns;
+
+    let mut conn = pool.get().await.map_err(|_e| {
+        ServiceError::InternalServerError("Failed to get postgres connection".to_string())
+    })?;
+
+    dataset_config_versions_columns::dataset_config_versions
+        .filter(dataset_config_versions_columns::dataset_id.eq(dataset_id))
+        .order_by(dataset_config_versions_columns::version.desc())
+        .offset((page - 1).max(0) * page_size)
+        .limit(page_size)
+        .select(DatasetConfigVersion::as_select())
+        .load::<DatasetConfigVersion>(&mut conn)
+        .await
+        .map_err(|err| {
+            log::error!("Failed to get dataset configuration versions: {:?}", err);
+            ServiceError::InternalServerError(
+                "Failed to get dataset configuration versions".to_string(),
+            )
+        })
+}
+
+/// Gets a version of the dataset's configuration, or the latest version if `version` is None
+pub async fn get_dataset_config_version_query(
+    dataset_id: uuid::Uuid,
+    version: Option<i32>,
+    pool: web::Data<Pool>,
+) -> Result<DatasetConfigVersion, ServiceError> {
+    use crate::data::schema::dataset_config_versions::dsl as dataset_config_versions_columns;
+
+    let mut conn = pool.get().await.map_err(|_e| {
+        ServiceError::InternalServerError("Failed to get postgres connection".to_string())
+    })?;
+
+    let mut query = dataset_config_versions_columns::dataset_config_versions
+        .filter(dataset_config_versions_columns::dataset_id.eq(dataset_id))
+        .into_boxed();
+
+    if let Some(version) = version {
+        query = query.filter(dataset_config_versions_columns::version.eq(version));
+    }
+
+    query
+        .order_by(dataset_config_versions_columns::version.desc())
+        .select(DatasetConfigVersion::as_select())
+        .first::<DatasetConfigVersion>(&mut conn)
+        .await
+        .map_err(|_| ServiceError::NotFound("Dataset configuration version not found".to_string()))
+}
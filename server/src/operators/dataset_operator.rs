@@ -1,16 +1,18 @@
 use crate::data::models::{
-    DatasetAndOrgWithSubAndPlan, DatasetAndUsage, DatasetConfiguration, DatasetUsageCount,
-    Organization, OrganizationWithSubAndPlan, RedisPool, StripePlan, StripeSubscription,
-    StripeUsageBasedPlan, StripeUsageBasedSubscription, TrievePlan, TrieveSubscription, UnifiedId,
-    WordDataset,
+    DatasetAndOrgWithSubAndPlan, DatasetAndUsage, DatasetConfigVersionSource, DatasetConfiguration,
+    DatasetUsageCount, Organization, OrganizationWithSubAndPlan, RedisPool, StripePlan,
+    StripeSubscription, StripeUsageBasedPlan, StripeUsageBasedSubscription, TrievePlan,
+    TrieveSubscription, UnifiedId, WordDataset,
 };
 use crate::handlers::chunk_handler::ChunkFilter;
 use crate::handlers::dataset_handler::{GetDatasetsPagination, TagsWithCount};
 use crate::operators::chunk_operator::bulk_delete_chunks_query;
 use crate::operators::clickhouse_operator::ClickHouseEvent;
+use crate::operators::dataset_config_version_operator::insert_dataset_config_version_query;
 use crate::operators::organization_operator::get_organization_from_dataset_id;
 use crate::operators::qdrant_operator::{
-    delete_points_from_qdrant, get_qdrant_collection_from_dataset_config,
+    delete_points_from_qdrant, get_group_centroid_collection_from_dataset_config,
+    get_qdrant_collection_from_dataset_config,
 };
 use crate::{
     data::models::{Dataset, EventType, Pool, WorkerEvent},
@@ -22,7 +24,8 @@ use diesel::dsl::count;
 use diesel::prelude::*;
 use diesel::result::{DatabaseErrorKind, Error as DBError};
 use diesel::upsert::excluded;
-use diesel_async::RunQueryDsl;
+use diesel_async::scoped_futures::ScopedFutureExt;
+use diesel_async::{AsyncConnection, RunQueryDsl};
 use itertools::Itertools;
 use serde::{Deserialize, Serialize};
 use time::{format_description, OffsetDateTime};
@@ -245,6 +248,7 @@ pub async fn get_dataset_and_organization_from_dataset_id_query(
     org_id: Option<uuid::Uuid>,
     pool: web::Data<Pool>,
 ) -> Result<DatasetAndOrgWithSubAndPlan, ServiceError> {
+    use crate::data::schema::dataset_aliases::dsl as dataset_aliases_columns;
     use crate::data::schema::datasets::dsl as datasets_columns;
     use crate::data::schema::organizations::dsl as organizations_columns;
     use crate::data::schema::stripe_plans::dsl as stripe_plans_columns;
@@ -295,6 +299,28 @@ pub async fn get_dataset_and_organization_from_dataset_id_query(
         .filter(datasets_columns::deleted.eq(0))
         .into_boxed();
 
+    // Aliases share the tracking id namespace and take precedence, so a swapped alias moves all
+    // traffic addressed by its name to the new dataset.
+    let id = match (id, org_id) {
+        (UnifiedId::TrackingId(tracking_id), Some(org_id)) => {
+            match dataset_aliases_columns::dataset_aliases
+                .filter(dataset_aliases_columns::name.eq(tracking_id.clone()))
+                .filter(dataset_aliases_columns::organization_id.eq(org_id))
+                .select(dataset_aliases_columns::dataset_id)
+                .first::<uuid::Uuid>(&mut conn)
+                .await
+                .optional()
+                .map_err(|err| {
+                    log::error!("Failed to resolve dataset alias: {:?}", err);
+                    ServiceError::InternalServerError("Failed to resolve dataset alias".to_string())
+                })? {
+                Some(dataset_id) => UnifiedId::TrieveUuid(dataset_id),
+                None => UnifiedId::TrackingId(tracking_id),
+            }
+        }
+        (id, _) => id,
+    };
+
     let (dataset, organization, stripe_plan, stripe_subscription, usage_plan, usage_subscription) = match id {
         UnifiedId::TrieveUuid(id) => query
             .filter(datasets_columns::id.eq(id))
@@ -569,6 +595,14 @@ pub async fn clear_dataset_query(
                 ServiceError::BadRequest("Could not delete chunk groups".to_string())
             })?;
 
+        if dataset_config.GROUP_CENTROIDS.is_some() {
+            delete_points_from_qdrant(
+                chunk_group_ids.clone(),
+                get_group_centroid_collection_from_dataset_config(&dataset_config),
+            )
+            .await?;
+        }
+
         log::info!(
             "Deleted {} chunk groups for dataset {}",
             chunk_group_ids.len(),
@@ -720,12 +754,18 @@ pub async fn delete_dataset_by_id_query(
     Ok(dataset)
 }
 
+/// Updates the dataset and records the configuration change as a new version in the same
+/// transaction
 #[tracing::instrument(skip_all)]
+#[allow(clippy::too_many_arguments)]
 pub async fn update_dataset_query(
     id: uuid::Uuid,
     name: String,
     server_configuration: DatasetConfiguration,
     new_tracking_id: Option<String>,
+    source: DatasetConfigVersionSource,
+    changed_by: Option<uuid::Uuid>,
+    restored_version: Option<i32>,
     pool: web::Data<Pool>,
 ) -> Result<Dataset, ServiceError> {
     use crate::data::schema::datasets::dsl as datasets_columns;
@@ -737,38 +777,63 @@ pub async fn update_dataset_query(
 
     let configuration = server_configuration.clone().to_json();
 
-    let new_dataset: Dataset = diesel::update(
-        datasets_columns::datasets
-            .filter(datasets_columns::id.eq(id))
-            .filter(datasets_columns::deleted.eq(0)),
-    )
-    .set((
-        new_tracking_id.map(|id| {
-            if id.is_empty() {
-                datasets_columns::tracking_id.eq(None)
-            } else {
-                datasets_columns::tracking_id.eq(Some(id))
+    let new_dataset: Dataset = conn
+        .transaction::<_, DBError, _>(|conn| {
+            async move {
+                let prev_dataset: Dataset = datasets_columns::datasets
+                    .filter(datasets_columns::id.eq(id))
+                    .filter(datasets_columns::deleted.eq(0))
+                    .for_update()
+                    .first(conn)
+                    .await?;
+
+                let new_dataset: Dataset = diesel::update(
+                    datasets_columns::datasets.filter(datasets_columns::id.eq(id)),
+                )
+                .set((
+                    new_tracking_id.map(|id| {
+                        if id.is_empty() {
+                            datasets_columns::tracking_id.eq(None)
+                        } else {
+                            datasets_columns::tracking_id.eq(Some(id))
+                        }
+                    }),
+                    datasets_columns::name.eq(name),
+                    datasets_columns::updated_at.eq(diesel::dsl::now),
+                    datasets_columns::server_configuration.eq(configuration),
+                ))
+                .get_result(conn)
+                .await?;
+
+                insert_dataset_config_version_query(
+                    id,
+                    &DatasetConfiguration::from_json(prev_dataset.server_configuration),
+                    &server_configuration,
+                    source,
+                    changed_by,
+                    restored_version,
+                    conn,
+                )
+                .await?;
+
+                Ok(new_dataset)
             }
-        }),
-        datasets_columns::name.eq(name),
-        datasets_columns::updated_at.eq(diesel::dsl::now),
-        datasets_columns::server_configuration.eq(configuration),
-    ))
-    .get_result(&mut conn)
-    .await
-    .map_err(|e: DBError| {
-        match e {
-        DBError::DatabaseError(db_error, _) => match db_error {
-            DatabaseErrorKind::UniqueViolation => {
-                ServiceError::BadRequest("Could not update tracking_id because a dataset with the same tracking_id already exists in the organization".to_string())
+            .scope_boxed()
+        })
+        .await
+        .map_err(|e: DBError| {
+            match e {
+            DBError::DatabaseError(db_error, _) => match db_error {
+                DatabaseErrorKind::UniqueViolation => {
+                    ServiceError::BadRequest("Could not update tracking_id because a dataset with the same tracking_id already exists in the organization".to_string())
+                }
+                _ => ServiceError::BadRequest("Failed to update dataset".to_string())
+            }
+            _ => {
+                ServiceError::BadRequest("Failed to update dataset".to_string())
             }
-            _ => ServiceError::BadRequest("Failed to update dataset".to_string())
-        }
-        _ => {
-            ServiceError::BadRequest("Failed to update dataset".to_string())
         }
-    }
-    })?;
+        })?;
 
     Ok(new_dataset)
 }
@@ -0,0 +1,628 @@
+use crate::data::models::{
+    ChunkData, ChunkFingerprint, ChunkGroupBookmark, DatasetConfiguration, DedupAction,
+    DedupPolicy, DuplicateChunk, DuplicateMatch, Pool,
+};
+use crate::errors::ServiceError;
+use crate::operators::chunk_operator::{get_metadata_from_id_query, update_chunk_metadata_query};
+use crate::operators::group_operator::create_chunk_bookmark_query;
+use crate::operators::model_operator::get_dense_vectors;
+use crate::operators::qdrant_operator::{
+    add_bookmark_to_qdrant_query, search_nearest_points_query, set_qdrant_point_payload_query,
+};
+use actix_web::web;
+use diesel::prelude::*;
+use diesel::upsert::excluded;
+use diesel_async::RunQueryDsl;
+use itertools::Itertools;
+use serde_json::json;
+use std::collections::HashMap;
+
+const SIMHASH_BAND_BITS: u32 = 16;
+const SIMHASH_BANDS: u32 = u64::BITS / SIMHASH_BAND_BITS;
+
+/// Lowercases and collapses whitespace so that formatting-only differences are ignored
+fn normalize_content(content: &str) -> String {
+    content
+        .split_whitespace()
+        .map(|word| word.to_lowercase())
+        .join(" ")
+}
+
+pub fn content_hash(content: &str) -> String {
+    blake3::hash(normalize_content(content).as_bytes())
+        .to_hex()
+        .to_string()
+}
+
+/// FNV-1a is used instead of the std hasher because fingerprints are persisted and must not change
+/// between builds
+fn fnv1a_64(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+/// 64 bit SimHash over word trigrams of the normalized content
+pub fn simhash(content: &str) -> u64 {
+    let normalized = normalize_content(content);
+    let words = normalized.split(' ').collect::<Vec<&str>>();
+    let shingles = if words.len() < 3 {
+        vec![normalized.clone()]
+    } else {
+        words.windows(3).map(|window| window.join(" ")).collect()
+    };
+
+    let mut weights = [0_i64; u64::BITS as usize];
+    for shingle in shingles {
+        let hash = fnv1a_64(shingle.as_bytes());
+        for (bit, weight) in weights.iter_mut().enumerate() {
+            if (hash >> bit) & 1 == 1 {
+                *weight += 1;
+            } else {
+                *weight -= 1;
+            }
+        }
+    }
+
+    weights
+        .iter()
+        .enumerate()
+        .filter(|(_, weight)| **weight > 0)
+        .fold(0_u64, |simhash, (bit, _)| simhash | (1 << bit))
+}
+
+/// Splits a SimHash into 16 bit bands tagged with their position. Two hashes within 3 bits of each
+/// other always share at least one band, which lets candidates be found with an array overlap.
+pub fn simhash_bands(simhash: u64) -> Vec<i32> {
+    (0..SIMHASH_BANDS)
+        .map(|band| {
+            let value = (simhash >> (band * SIMHASH_BAND_BITS)) & 0xFFFF;
+            ((band << SIMHASH_BAND_BITS) as u64 | value) as i32
+        })
+        .collect()
+}
+
+pub struct DedupResult {
+    /// Chunks which should still be ingested, including flagged duplicates
+    pub chunks: Vec<ChunkData>,
+    /// Duplicates which were skipped or merged
+    pub duplicates: Vec<DuplicateChunk>,
+    /// Dense vectors computed for the vector similarity check, in the same order as `chunks`
+    pub dense_vectors: Option<Vec<Vec<f32>>>,
+}
+
+struct Fingerprint {
+    content_hash: String,
+    simhash: u64,
+}
+
+impl Fingerprint {
+    fn new(content: &str) -> Self {
+        Fingerprint {
+            content_hash: content_hash(content),
+            simhash: simhash(content),
+        }
+    }
+}
+
+/// Finds chunks which duplicate another chunk in the batch or an already ingested chunk and applies
+/// the policy's action to them. Chunks upserted by tracking_id are updates and are never matched.
+#[tracing::instrument(skip_all)]
+pub async fn apply_dedup_policy(
+    chunks: Vec<ChunkData>,
+    dedup_policy: &DedupPolicy,
+    dataset_id: uuid::Uuid,
+    dataset_config: &DatasetConfiguration,
+    reqwest_client: reqwest::Client,
+    pool: web::Data<Pool>,
+) -> Result<DedupResult, ServiceError> {
+    let check_exact = dedup_policy.exact.unwrap_or(true);
+    let fingerprints = chunks
+        .iter()
+        .map(|chunk| Fingerprint::new(&chunk.content))
+        .collect::<Vec<Fingerprint>>();
+
+    let (existing_hashes, existing_simhashes) = if dataset_config.QDRANT_ONLY {
+        (HashMap::new(), vec![])
+    } else {
+        get_existing_fingerprints_query(
+            &fingerprints,
+            check_exact,
+            dedup_policy.simhash_max_distance.is_some(),
+            dataset_id,
+            pool.clone(),
+        )
+        .await?
+    };
+
+    let mut matches: Vec<Option<(MatchedChunk, DuplicateMatch)>> = chunks
+        .iter()
+        .zip(fingerprints.iter())
+        .map(|(chunk, fingerprint)| {
+            if chunk.upsert_by_tracking_id {
+                return None;
+            }
+
+            let exact_match = check_exact
+                .then(|| existing_hashes.get(&fingerprint.content_hash))
+                .flatten()
+                .map(|chunk_id| (MatchedChunk::Existing(*chunk_id), DuplicateMatch::Exact));
+
+            exact_match.or_else(|| {
+                let max_distance = dedup_policy.simhash_max_distance?;
+                existing_simhashes
+                    .iter()
+                    .map(|(other, chunk_id)| ((fingerprint.simhash ^ other).count_ones(), chunk_id))
+                    .filter(|(distance, _)| *distance <= max_distance)
+                    .min_by_key(|(distance, _)| *distance)
+                    .map(|(_, chunk_id)| {
+                        (
+                            MatchedChunk::Existing(*chunk_id),
+                            DuplicateMatch::NearDuplicate,
+                        )
+                    })
+            })
+        })
+        .collect();
+
+    let dense_vectors = match dedup_policy.vector_similarity_threshold {
+        Some(threshold) if dataset_config.SEMANTIC_ENABLED => {
+            let dense_vectors = get_dense_vectors(
+                chunks
+                    .iter()
+                    .map(|chunk| {
+                        (
+                            chunk.embedding_content.clone(),
+                            chunk.semantic_boost.clone(),
+                        )
+                    })
+                    .collect(),
+                "doc",
+                dataset_config.clone(),
+                reqwest_client,
+            )
+            .await?;
+
+            let unmatched_indices = matches
+                .iter()
+                .zip(chunks.iter())
+                .enumerate()
+                .filter(|(_, (matched, chunk))| matched.is_none() && !chunk.upsert_by_tracking_id)
+                .map(|(i, _)| i)
+                .collect::<Vec<usize>>();
+
+            let nearest_points = search_nearest_points_query(
+                unmatched_indices
+                    .iter()
+                    .map(|i| dense_vectors[*i].clone())
+                    .collect(),
+                threshold,
+                dataset_id,
+                dataset_config.clone(),
+            )
+            .await?;
+
+            let point_ids = nearest_points
+                .iter()
+                .flatten()
+                .map(|point| point.point_id)
+                .collect::<Vec<uuid::Uuid>>();
+            let chunk_ids_by_point_id = if dataset_config.QDRANT_ONLY {
+                point_ids.iter().map(|id| (*id, *id)).collect()
+            } else {
+                get_chunk_ids_from_point_ids_query(point_ids, dataset_id, pool.clone()).await?
+            };
+
+            for (i, nearest_point) in unmatched_indices.into_iter().zip(nearest_points) {
+                if let Some(chunk_id) =
+                    nearest_point.and_then(|point| chunk_ids_by_point_id.get(&point.point_id))
+                {
+                    matches[i] = Some((MatchedChunk::Existing(*chunk_id), DuplicateMatch::Vector));
+                }
+            }
+
+            Some(dense_vectors)
+        }
+        _ => None,
+    };
+
+    // Chunks which did not match anything already ingested are compared against the earlier chunks
+    // of the batch which were accepted
+    let mut batch_hashes: HashMap<&str, usize> = HashMap::new();
+    let mut batch_simhashes: Vec<(u64, usize)> = vec![];
+    for (i, (chunk, fingerprint)) in chunks.iter().zip(fingerprints.iter()).enumerate() {
+        if chunk.upsert_by_tracking_id || matches[i].is_some() {
+            continue;
+        }
+
+        let exact_match = check_exact
+            .then(|| batch_hashes.get(fingerprint.content_hash.as_str()))
+            .flatten()
+            .map(|index| (MatchedChunk::InBatch(*index), DuplicateMatch::Exact));
+
+        let batch_match = exact_match.or_else(|| {
+            let max_distance = dedup_policy.simhash_max_distance?;
+            batch_simhashes
+                .iter()
+                .find(|(other, _)| (fingerprint.simhash ^ other).count_ones() <= max_distance)
+                .map(|(_, index)| (MatchedChunk::InBatch(*index), DuplicateMatch::NearDuplicate))
+        });
+
+        if batch_match.is_none() {
+            batch_hashes.insert(fingerprint.content_hash.as_str(), i);
+            batch_simhashes.push((fingerprint.simhash, i));
+        }
+        matches[i] = batch_match;
+    }
+
+    let mut kept_chunks: Vec<ChunkData> = vec![];
+    let mut kept_vectors: Vec<Vec<f32>> = vec![];
+    // Index into kept_chunks for each chunk of the batch which was kept
+    let mut kept_indices: HashMap<usize, usize> = HashMap::new();
+    let mut duplicates: Vec<DuplicateChunk> = vec![];
+
+    for (i, (mut chunk, matched)) in chunks.into_iter().zip(matches).enumerate() {
+        let Some((matched, match_type)) = matched else {
+            kept_indices.insert(i, kept_chunks.len());
+            kept_chunks.push(chunk);
+            if let Some(dense_vectors) = dense_vectors.as_ref() {
+                kept_vectors.push(dense_vectors[i].clone());
+            }
+            continue;
+        };
+
+        let duplicate_of = match matched {
+            MatchedChunk::InBatch(index) => kept_chunks[kept_indices[&index]].chunk_metadata.id,
+            MatchedChunk::Existing(chunk_id) => chunk_id,
+        };
+
+        match dedup_policy.action {
+            DedupAction::Flag => {
+                let mut metadata = chunk.chunk_metadata.metadata.take().unwrap_or(json!({}));
+                if let Some(metadata) = metadata.as_object_mut() {
+                    metadata.insert("duplicate_of".to_string(), json!(duplicate_of));
+                    metadata.insert("duplicate_match".to_string(), json!(match_type));
+                }
+                chunk.chunk_metadata.metadata = Some(metadata);
+
+                kept_indices.insert(i, kept_chunks.len());
+                kept_chunks.push(chunk);
+                if let Some(dense_vectors) = dense_vectors.as_ref() {
+                    kept_vectors.push(dense_vectors[i].clone());
+                }
+                continue;
+            }
+            DedupAction::Merge => match matched {
+                MatchedChunk::InBatch(index) => {
+                    let kept_chunk = &mut kept_chunks[kept_indices[&index]];
+                    kept_chunk.chunk_metadata.tag_set = merge_tag_sets(
+                        kept_chunk.chunk_metadata.tag_set.clone(),
+                        chunk.chunk_metadata.tag_set.clone(),
+                    );
+                    kept_chunk.group_ids = Some(
+                        kept_chunk
+                            .group_ids
+                            .clone()
+                            .unwrap_or_default()
+                            .into_iter()
+                            .chain(chunk.group_ids.clone().unwrap_or_default())
+                            .unique()
+                            .collect(),
+                    );
+                }
+                MatchedChunk::Existing(chunk_id) => {
+                    merge_into_existing_chunk_query(
+                        chunk_id,
+                        &chunk,
+                        dataset_id,
+                        dataset_config.clone(),
+                        pool.clone(),
+                    )
+                    .await?;
+                }
+            },
+            DedupAction::Skip => {}
+        }
+
+        duplicates.push(DuplicateChunk {
+            chunk_id: chunk.chunk_metadata.id,
+            tracking_id: chunk.chunk_metadata.tracking_id,
+            duplicate_of,
+            match_type,
+            action: dedup_policy.action,
+        });
+    }
+
+    Ok(DedupResult {
+        chunks: kept_chunks,
+        duplicates,
+        dense_vectors: dense_vectors.map(|_| kept_vectors),
+    })
+}
+
+enum MatchedChunk {
+    /// Index of an earlier chunk in the same batch
+    InBatch(usize),
+    Existing(uuid::Uuid),
+}
+
+fn merge_tag_sets(
+    tag_set: Option<Vec<Option<String>>>,
+    other: Option<Vec<Option<String>>>,
+) -> Option<Vec<Option<String>>> {
+    if tag_set.is_none() && other.is_none() {
+        return None;
+    }
+
+    Some(
+        tag_set
+            .unwrap_or_default()
+            .into_iter()
+            .chain(other.unwrap_or_default())
+            .flatten()
+            .unique()
+            .map(Some)
+            .collect(),
+    )
+}
+
+/// Adds the tags and groups of `duplicate` to the existing chunk it duplicates
+#[tracing::instrument(skip_all)]
+async fn merge_into_existing_chunk_query(
+    chunk_id: uuid::Uuid,
+    duplicate: &ChunkData,
+    dataset_id: uuid::Uuid,
+    dataset_config: DatasetConfiguration,
+    pool: web::Data<Pool>,
+) -> Result<(), ServiceError> {
+    let mut existing_chunk = get_metadata_from_id_query(chunk_id, dataset_id, pool.clone()).await?;
+    let qdrant_point_id = existing_chunk.qdrant_point_id;
+
+    if duplicate
+        .chunk_metadata
+        .tag_set
+        .as_ref()
+        .is_some_and(|tag_set| !tag_set.is_empty())
+    {
+        let tag_set = merge_tag_sets(
+            existing_chunk.tag_set.clone(),
+            duplicate.chunk_metadata.tag_set.clone(),
+        );
+        existing_chunk.tag_set = tag_set.clone();
+
+        update_chunk_metadata_query(existing_chunk, None, dataset_id, pool.clone()).await?;
+        set_qdrant_point_payload_query(
+            qdrant_point_id,
+            json!({ "tag_set": tag_set }),
+            dataset_config.clone(),
+        )
+        .await?;
+    }
+
+    for group_id in duplicate.group_ids.clone().unwrap_or_default() {
+        create_chunk_bookmark_query(
+            pool.clone(),
+            ChunkGroupBookmark::from_details(group_id, chunk_id),
+        )
+        .await?;
+        add_bookmark_to_qdrant_query(qdrant_point_id, group_id, dataset_config.clone()).await?;
+    }
+
+    Ok(())
+}
+
+/// Loads the fingerprints of already ingested chunks which could match the given fingerprints
+#[tracing::instrument(skip_all)]
+async fn get_existing_fingerprints_query(
+    fingerprints: &[Fingerprint],
+    check_exact: bool,
+    check_simhash: bool,
+    dataset_id: uuid::Uuid,
+    pool: web::Data<Pool>,
+) -> Result<(HashMap<String, uuid::Uuid>, Vec<(u64, uuid::Uuid)>), ServiceError> {
+    use crate::data::schema::chunk_fingerprints::dsl as chunk_fingerprints_columns;
+
+    let mut conn = pool.get().await.map_err(|_e| {
+        ServiceError::InternalServerError("Failed to get postgres connection".to_string())
+    })?;
+
+    let existing_hashes = if check_exact {
+        chunk_fingerprints_columns::chunk_fingerprints
+            .filter(chunk_fingerprints_columns::dataset_id.eq(dataset_id))
+            .filter(
+                chunk_fingerprints_columns::content_hash.eq_any(
+                    fingerprints
+                        .iter()
+                        .map(|fingerprint| fingerprint.content_hash.clone())
+                        .collect::<Vec<String>>(),
+                ),
+            )
+            .order(chunk_fingerprints_columns::created_at.asc())
+            .select((
+                chunk_fingerprints_columns::content_hash,
+                chunk_fingerprints_columns::chunk_id,
+            ))
+            .load::<(String, uuid::Uuid)>(&mut conn)
+            .await
+            .map_err(|e| {
+                log::error!("Failed to load chunk fingerprints {:?}", e);
+                ServiceError::BadRequest("Failed to load chunk fingerprints".to_string())
+            })?
+            .into_iter()
+            .rev()
+            .collect::<HashMap<String, uuid::Uuid>>()
+    } else {
+        HashMap::new()
+    };
+
+    let existing_simhashes = if check_simhash {
+        chunk_fingerprints_columns::chunk_fingerprints
+            .filter(chunk_fingerprints_columns::dataset_id.eq(dataset_id))
+            .filter(
+                chunk_fingerprints_columns::simhash_bands.overlaps_with(
+                    fingerprints
+                        .iter()
+                        .flat_map(|fingerprint| simhash_bands(fingerprint.simhash))
+                        .unique()
+                        .collect::<Vec<i32>>(),
+                ),
+            )
+            .select((
+                chunk_fingerprints_columns::simhash,
+                chunk_fingerprints_columns::chunk_id,
+            ))
+            .load::<(i64, uuid::Uuid)>(&mut conn)
+            .await
+            .map_err(|e| {
+                log::error!("Failed to load chunk simhashes {:?}", e);
+                ServiceError::BadRequest("Failed to load chunk simhashes".to_string())
+            })?
+            .into_iter()
+            .map(|(simhash, chunk_id)| (simhash as u64, chunk_id))
+            .collect()
+    } else {
+        vec![]
+    };
+
+    Ok((existing_hashes, existing_simhashes))
+}
+
+#[tracing::instrument(skip_all)]
+async fn get_chunk_ids_from_point_ids_query(
+    point_ids: Vec<uuid::Uuid>,
+    dataset_id: uuid::Uuid,
+    pool: web::Data<Pool>,
+) -> Result<HashMap<uuid::Uuid, uuid::Uuid>, ServiceError> {
+    use crate::data::schema::chunk_metadata::dsl as chunk_metadata_columns;
+
+    if point_ids.is_empty() {
+        return Ok(HashMap::new());
+    }
+
+    let mut conn = pool.get().await.map_err(|_e| {
+        ServiceError::InternalServerError("Failed to get postgres connection".to_string())
+    })?;
+
+    let chunk_ids = chunk_metadata_columns::chunk_metadata
+        .filter(chunk_metadata_columns::dataset_id.eq(dataset_id))
+        .filter(chunk_metadata_columns::qdrant_point_id.eq_any(point_ids))
+        .select((
+            chunk_metadata_columns::qdrant_point_id,
+            chunk_metadata_columns::id,
+        ))
+        .load::<(uuid::Uuid, uuid::Uuid)>(&mut conn)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to load chunk ids for point ids {:?}", e);
+            ServiceError::BadRequest("Failed to load chunk ids for point ids".to_string())
+        })?
+        .into_iter()
+        .collect();
+
+    Ok(chunk_ids)
+}
+
+/// Stores fingerprints for newly ingested chunks so later uploads can be matched against them
+#[tracing::instrument(skip_all)]
+pub async fn insert_chunk_fingerprints_query(
+    chunks: &[ChunkData],
+    dataset_id: uuid::Uuid,
+    pool: web::Data<Pool>,
+) -> Result<(), ServiceError> {
+    let chunk_fingerprints = chunks
+        .iter()
+        .unique_by(|chunk| chunk.chunk_metadata.id)
+        .map(|chunk| chunk_fingerprint(chunk.chunk_metadata.id, dataset_id, &chunk.content))
+        .collect::<Vec<ChunkFingerprint>>();
+
+    upsert_chunk_fingerprints_query(chunk_fingerprints, pool).await
+}
+
+/// Replaces the fingerprint of a chunk whose content was updated, so it is matched against its
+/// new content rather than the content it was ingested with
+#[tracing::instrument(skip_all)]
+pub async fn update_chunk_fingerprint_query(
+    chunk_id: uuid::Uuid,
+    dataset_id: uuid::Uuid,
+    content: &str,
+    pool: web::Data<Pool>,
+) -> Result<(), ServiceError> {
+    upsert_chunk_fingerprints_query(vec![chunk_fingerprint(chunk_id, dataset_id, content)], pool)
+        .await
+}
+
+fn chunk_fingerprint(
+    chunk_id: uuid::Uuid,
+    dataset_id: uuid::Uuid,
+    content: &str,
+) -> ChunkFingerprint {
+    let fingerprint = Fingerprint::new(content);
+    ChunkFingerprint {
+        chunk_id,
+        dataset_id,
+        content_hash: fingerprint.content_hash,
+        simhash: fingerprint.simhash as i64,
+        simhash_bands: simhash_bands(fingerprint.simhash),
+        created_at: chrono::Utc::now().naive_local(),
+    }
+}
+
+async fn upsert_chunk_fingerprints_query(
+    chunk_fingerprints: Vec<ChunkFingerprint>,
+    pool: web::Data<Pool>,
+) -> Result<(), ServiceError> {
+    use crate::data::schema::chunk_fingerprints::dsl as chunk_fingerprints_columns;
+
+    if chunk_fingerprints.is_empty() {
+        return Ok(());
+    }
+
+    let mut conn = pool.get().await.map_err(|_e| {
+        ServiceError::InternalServerError("Failed to get postgres connection".to_string())
+    })?;
+
+    diesel::insert_into(chunk_fingerprints_columns::chunk_fingerprints)
+        .values(&chunk_fingerprints)
+        .on_conflict(chunk_fingerprints_columns::chunk_id)
+        .do_update()
+        .set((
+            chunk_fingerprints_columns::content_hash
+                .eq(excluded(chunk_fingerprints_columns::content_hash)),
+            chunk_fingerprints_columns::simhash.eq(excluded(chunk_fingerprints_columns::simhash)),
+            chunk_fingerprints_columns::simhash_bands
+                .eq(excluded(chunk_fingerprints_columns::simhash_bands)),
+        ))
+        .execute(&mut conn)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to insert chunk fingerprints {:?}", e);
+            ServiceError::BadRequest("Failed to insert chunk fingerprints".to_string())
+        })?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_simhash_near_duplicates() {
+        let original = "Trieve is a search and RAG API. Upload your documents, then search them \
+            with hybrid, semantic or fulltext search and generate answers grounded in them.";
+        let reformatted = "Trieve is a search and RAG API.\n\nUpload your documents, then search \
+            them with hybrid, semantic or fulltext search and generate answers grounded in them!";
+        let unrelated = "The ingestion worker reads batches of chunks from the queue, embeds them \
+            and writes the resulting points to the vector store.";
+
+        assert_eq!(content_hash("Hello   World"), content_hash("hello world"));
+        assert!((simhash(original) ^ simhash(reformatted)).count_ones() <= 3);
+        assert!((simhash(original) ^ simhash(unrelated)).count_ones() > 3);
+
+        let shared_bands = simhash_bands(simhash(original))
+            .into_iter()
+            .filter(|band| simhash_bands(simhash(reformatted)).contains(band))
+            .count();
+        assert!(shared_bands > 0);
+    }
+}
@@ -0,0 +1,699 @@
+use actix_web::web;
+use diesel::prelude::*;
+use diesel_async::scoped_futures::ScopedFutureExt;
+use diesel_async::{AsyncConnection, RunQueryDsl};
+use serde::{Deserialize, Serialize};
+
+use crate::data::models::{
+    EventWebhook, EventWebhookDelivery, EventWebhookDeliveryStatus, Pool, RedisPool, WorkerEvent,
+    WorkerEventClickhouse,
+};
+use crate::errors::ServiceError;
+use crate::operators::change_feed_operator::{
+    build_webhook_client, send_webhook_request, sign_webhook_payload,
+};
+
+/// Deliveries are marked as failed after this many attempts
+pub const EVENT_WEBHOOK_MAX_ATTEMPTS: i32 = 10;
+/// Event type of the synthetic events sent by the test delivery endpoint
+pub const EVENT_WEBHOOK_TEST_EVENT_TYPE: &str = "webhook_test";
+/// Redis list the worker events are pushed to until the event webhook worker creates their
+/// deliveries
+pub const EVENT_WEBHOOK_EVENTS_QUEUE: &str = "event_webhook_events";
+/// Redis list holding the events the event webhook worker is creating deliveries for
+pub const EVENT_WEBHOOK_EVENTS_PROCESSING: &str = "event_webhook_events_processing";
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct EventWebhookPayload {
+    /// Id of the event, the same for every delivery attempt
+    pub id: uuid::Uuid,
+    pub webhook_id: uuid::Uuid,
+    pub dataset_id: uuid::Uuid,
+    pub organization_id: Option<uuid::Uuid>,
+    pub event_type: String,
+    pub event_data: serde_json::Value,
+    pub created_at: String,
+}
+
+impl EventWebhookPayload {
+    pub fn from_worker_event(webhook_id: uuid::Uuid, event: WorkerEvent) -> Self {
+        EventWebhookPayload {
+            id: event.id,
+            webhook_id,
+            dataset_id: event.dataset_id,
+            organization_id: event.organization_id,
+            event_type: event.event_type,
+            event_data: serde_json::from_str(&event.event_data)
+                .unwrap_or(serde_json::Value::String(event.event_data)),
+            created_at: event.created_at,
+        }
+    }
+}
+
+pub async fn create_event_webhook_query(
+    event_webhook: EventWebhook,
+    pool: web::Data<Pool>,
+) -> Result<EventWebhook, ServiceError> {
+    use crate::data::schema::event_webhooks::dsl as event_webhooks_columns;
+
+    let mut conn = pool.get().await.map_err(|_e| {
+        ServiceError::InternalServerError("Failed to get postgres connection".to_string())
+    })?;
+
+    diesel::insert_into(event_webhooks_columns::event_webhooks)
+        .values(&event_webhook)
+        .get_result::<EventWebhook>(&mut conn)
+        .await
+        .map_err(|err| {
+            log::error!("Failed to create event webhook: {:?}", err);
+            ServiceError::InternalServerError("Failed to create event webhook".to_string())
+        })
+}
+
+pub async fn get_event_webhooks_query(
+    dataset_id: uuid::Uuid,
+    pool: web::Data<Pool>,
+) -> Result<Vec<EventWebhook>, ServiceError> {
+    use crate::data::schema::event_webhooks::dsl as event_webhooks_columns;
+
+    let mut conn = pool.get().await.map_err(|_e| {
+        ServiceError::InternalServerError("Failed to get postgres connection".to_string())
+    })?;
+
+    event_webhooks_columns::event_webhooks
+        .filter(event_webhooks_columns::dataset_id.eq(dataset_id))
+        .order_by(event_webhooks_columns::created_at.asc())
+        .select(EventWebhook::as_select())
+        .load::<EventWebhook>(&mut conn)
+        .await
+        .map_err(|err| {
+            log::error!("Failed to get event webhooks: {:?}", err);
+            ServiceError::InternalServerError("Failed to get event webhooks".to_string())
+        })
+}
+
+pub async fn get_event_webhook_query(
+    webhook_id: uuid::Uuid,
+    dataset_id: uuid::Uuid,
+    pool: web::Data<Pool>,
+) -> Result<EventWebhook, ServiceError> {
+    use crate::data::schema::event_webhooks::dsl as event_webhooks_columns;
+
+    let mut conn = pool.get().await.map_err(|_e| {
+        ServiceError::InternalServerError("Failed to get postgres connection".to_string())
+    })?;
+
+    event_webhooks_columns::event_webhooks
+        .filter(event_webhooks_columns::id.eq(webhook_id))
+        .filter(event_webhooks_columns::dataset_id.eq(dataset_id))
+        .select(EventWebhook::as_select())
+        .first::<EventWebhook>(&mut conn)
+        .await
+        .map_err(|_| ServiceError::NotFound("Event webhook not found".to_string()))
+}
+
+pub async fn update_event_webhook_query(
+    webhook_id: uuid::Uuid,
+    dataset_id: uuid::Uuid,
+    url: Option<String>,
+    event_types: Option<Vec<String>>,
+    active: Option<bool>,
+    pool: web::Data<Pool>,
+) -> Result<EventWebhook, ServiceError> {
+    use crate::data::schema::event_webhooks::dsl as event_webhooks_columns;
+
+    let prev_webhook = get_event_webhook_query(webhook_id, dataset_id, pool.clone()).await?;
+
+    let mut conn = pool.get().await.map_err(|_e| {
+        ServiceError::InternalServerError("Failed to get postgres connection".to_string())
+    })?;
+
+    diesel::update(
+        event_webhooks_columns::event_webhooks
+            .filter(event_webhooks_columns::id.eq(webhook_id))
+            .filter(event_webhooks_columns::dataset_id.eq(dataset_id)),
+    )
+    .set((
+        event_webhooks_columns::url.eq(url.unwrap_or(prev_webhook.url)),
+        event_webhooks_columns::event_types.eq(event_types.unwrap_or(prev_webhook.event_types)),
+        event_webhooks_columns::active.eq(active.unwrap_or(prev_webhook.active)),
+        event_webhooks_columns::updated_at.eq(chrono::Utc::now().naive_local()),
+    ))
+    .get_result::<EventWebhook>(&mut conn)
+    .await
+    .map_err(|err| {
+        log::error!("Failed to update event webhook: {:?}", err);
+        ServiceError::InternalServerError("Failed to update event webhook".to_string())
+    })
+}
+
+pub async fn delete_event_webhook_query(
+    webhook_id: uuid::Uuid,
+    dataset_id: uuid::Uuid,
+    pool: web::Data<Pool>,
+) -> Result<(), ServiceError> {
+    use crate::data::schema::event_webhooks::dsl as event_webhooks_columns;
+
+    let mut conn = pool.get().await.map_err(|_e| {
+        ServiceError::InternalServerError("Failed to get postgres connection".to_string())
+    })?;
+
+    let deleted = diesel::delete(
+        event_webhooks_columns::event_webhooks
+            .filter(event_webhooks_columns::id.eq(webhook_id))
+            .filter(event_webhooks_columns::dataset_id.eq(dataset_id)),
+    )
+    .execute(&mut conn)
+    .await
+    .map_err(|err| {
+        log::error!("Failed to delete event webhook: {:?}", err);
+        ServiceError::InternalServerError("Failed to delete event webhook".to_string())
+    })?;
+
+    if deleted == 0 {
+        return Err(ServiceError::NotFound(
+            "Event webhook not found".to_string(),
+        ));
+    }
+
+    Ok(())
+}
+
+pub async fn get_event_webhook_deliveries_query(
+    webhook_id: uuid::Uuid,
+    status: Option<EventWebhookDeliveryStatus>,
+    page: i64,
+    page_size: i64,
+    pool: web::Data<Pool>,
+) -> Result<Vec<EventWebhookDelivery>, ServiceError> {
+    use crate::data::schema::event_webhook_deliveries::dsl as event_webhook_deliveries_columns;
+
+    let mut conn = pool.get().await.map_err(|_e| {
+        ServiceError::InternalServerError("Failed to get postgres connection".to_string())
+    })?;
+
+    let mut query = event_webhook_deliveries_columns::event_webhook_deliveries
+        .filter(event_webhook_deliveries_columns::webhook_id.eq(webhook_id))
+        .into_boxed();
+
+    if let Some(status) = status {
+        query = query.filter(event_webhook_deliveries_columns::status.eq(status.to_string()));
+    }
+
+    query
+        .order_by(event_webhook_deliveries_columns::created_at.desc())
+        .offset((page - 1).max(0) * page_size)
+        .limit(page_size)
+        .select(EventWebhookDelivery::as_select())
+        .load::<EventWebhookDelivery>(&mut conn)
+        .await
+        .map_err(|err| {
+            log::error!("Failed to get event webhook deliveries: {:?}", err);
+            ServiceError::InternalServerError("Failed to get event webhook deliveries".to_string())
+        })
+}
+
+/// Queues a pending delivery of each event to every active webhook of its dataset which is
+/// subscribed to the event's type
+pub async fn create_event_webhook_deliveries_query(
+    events: Vec<WorkerEventClickhouse>,
+    pool: web::Data<Pool>,
+) -> Result<(), ServiceError> {
+    use crate::data::schema::event_webhook_deliveries::dsl as event_webhook_deliveries_columns;
+    use crate::data::schema::event_webhooks::dsl as event_webhooks_columns;
+
+    if events.is_empty() {
+        return Ok(());
+    }
+
+    let mut conn = pool.get().await.map_err(|_e| {
+        ServiceError::InternalServerError("Failed to get postgres connection".to_string())
+    })?;
+
+    let mut dataset_ids = events
+        .iter()
+        .map(|event| event.dataset_id)
+        .collect::<Vec<uuid::Uuid>>();
+    dataset_ids.sort();
+    dataset_ids.dedup();
+
+    let webhooks = event_webhooks_columns::event_webhooks
+        .filter(event_webhooks_columns::dataset_id.eq_any(dataset_ids))
+        .filter(event_webhooks_columns::active.eq(true))
+        .select(EventWebhook::as_select())
+        .load::<EventWebhook>(&mut conn)
+        .await
+        .map_err(|err| {
+            log::error!("Failed to get event webhooks: {:?}", err);
+            ServiceError::InternalServerError("Failed to get event webhooks".to_string())
+        })?;
+
+    if webhooks.is_empty() {
+        return Ok(());
+    }
+
+    let deliveries = events
+        .into_iter()
+        .flat_map(|event| {
+            let event = WorkerEvent::from(event);
+            webhooks
+                .iter()
+                .filter(|webhook| {
+                    webhook.dataset_id == event.dataset_id
+                        && webhook.event_types.contains(&event.event_type)
+                })
+                .map(|webhook| {
+                    let payload = EventWebhookPayload::from_worker_event(webhook.id, event.clone());
+                    EventWebhookDelivery::from_details(
+                        webhook.id,
+                        payload.id,
+                        payload.event_type.clone(),
+                        serde_json::to_value(payload).unwrap_or_default(),
+                    )
+                })
+                .collect::<Vec<EventWebhookDelivery>>()
+        })
+        .collect::<Vec<EventWebhookDelivery>>();
+
+    if deliveries.is_empty() {
+        return Ok(());
+    }
+
+    // Events are redelivered from the redis queue after a worker restart, skip the deliveries
+    // which already exist for them
+    diesel::insert_into(event_webhook_deliveries_columns::event_webhook_deliveries)
+        .values(&deliveries)
+        .on_conflict_do_nothing()
+        .execute(&mut conn)
+        .await
+        .map_err(|err| {
+            log::error!("Failed to create event webhook deliveries: {:?}", err);
+            ServiceError::InternalServerError(
+                "Failed to create event webhook deliveries".to_string(),
+            )
+        })?;
+
+    Ok(())
+}
+
+/// Pushes worker events onto the redis queue drained by the event webhook worker
+pub async fn push_event_webhook_events_query(
+    events: &[WorkerEventClickhouse],
+    redis_conn: &mut redis::aio::MultiplexedConnection,
+) -> Result<(), ServiceError> {
+    if events.is_empty() {
+        return Ok(());
+    }
+
+    let serialized_events = events
+        .iter()
+        .map(|event| serde_json::to_string(&WorkerEvent::from(event.clone())))
+        .collect::<Result<Vec<String>, _>>()
+        .map_err(|err| {
+            log::error!("Failed to serialize worker events: {:?}", err);
+            ServiceError::InternalServerError("Failed to serialize worker events".to_string())
+        })?;
+
+    redis::cmd("lpush")
+        .arg(EVENT_WEBHOOK_EVENTS_QUEUE)
+        .arg(serialized_events)
+        .query_async::<redis::aio::MultiplexedConnection, usize>(redis_conn)
+        .await
+        .map_err(|err| {
+            log::error!("Failed to push worker events to redis: {:?}", err);
+            ServiceError::InternalServerError("Failed to push worker events to redis".to_string())
+        })?;
+
+    Ok(())
+}
+
+/// Moves events left in the processing list by a stopped worker back onto the queue. Events
+/// another worker is still processing may be moved as well, their deliveries are only created
+/// once.
+pub async fn requeue_processing_event_webhook_events_query(
+    redis_pool: web::Data<RedisPool>,
+) -> Result<usize, ServiceError> {
+    let mut redis_conn = redis_pool
+        .get()
+        .await
+        .map_err(|err| ServiceError::BadRequest(err.to_string()))?;
+
+    let mut requeued = 0;
+    loop {
+        let event: Option<String> = redis::cmd("rpoplpush")
+            .arg(EVENT_WEBHOOK_EVENTS_PROCESSING)
+            .arg(EVENT_WEBHOOK_EVENTS_QUEUE)
+            .query_async(&mut *redis_conn)
+            .await
+            .map_err(|err| {
+                log::error!("Failed to requeue worker events: {:?}", err);
+                ServiceError::InternalServerError("Failed to requeue worker events".to_string())
+            })?;
+
+        if event.is_none() {
+            return Ok(requeued);
+        }
+        requeued += 1;
+    }
+}
+
+/// Takes up to `limit` of the oldest events off the redis queue and creates their deliveries.
+/// Events stay in the processing list until their deliveries exist and are put back on the
+/// queue if creating them fails.
+pub async fn create_queued_event_webhook_deliveries_query(
+    limit: usize,
+    redis_pool: web::Data<RedisPool>,
+    pool: web::Data<Pool>,
+) -> Result<usize, ServiceError> {
+    let mut redis_conn = redis_pool
+        .get()
+        .await
+        .map_err(|err| ServiceError::BadRequest(err.to_string()))?;
+
+    let mut serialized_events = vec![];
+    while serialized_events.len() < limit {
+        let event: Option<String> = redis::cmd("rpoplpush")
+            .arg(EVENT_WEBHOOK_EVENTS_QUEUE)
+            .arg(EVENT_WEBHOOK_EVENTS_PROCESSING)
+            .query_async(&mut *redis_conn)
+            .await
+            .map_err(|err| {
+                log::error!("Failed to pop worker events: {:?}", err);
+                ServiceError::InternalServerError("Failed to pop worker events".to_string())
+            })?;
+
+        match event {
+            Some(event) => serialized_events.push(event),
+            None => break,
+        }
+    }
+
+    if serialized_events.is_empty() {
+        return Ok(0);
+    }
+
+    let events = serialized_events
+        .iter()
+        .filter_map(|serialized_event| {
+            match serde_json::from_str::<WorkerEvent>(serialized_event) {
+                Ok(event) => Some(WorkerEventClickhouse::from(event)),
+                Err(err) => {
+                    log::error!("Dropping malformed worker event {:?}", err);
+                    None
+                }
+            }
+        })
+        .collect::<Vec<WorkerEventClickhouse>>();
+
+    let result = create_event_webhook_deliveries_query(events, pool).await;
+
+    let mut pipeline = redis::pipe();
+    for serialized_event in serialized_events.iter() {
+        pipeline
+            .cmd("lrem")
+            .arg(EVENT_WEBHOOK_EVENTS_PROCESSING)
+            .arg(1)
+            .arg(serialized_event)
+            .ignore();
+    }
+    if result.is_err() {
+        // The queue is popped from the right so the events are retried first
+        pipeline
+            .cmd("rpush")
+            .arg(EVENT_WEBHOOK_EVENTS_QUEUE)
+            .arg(serialized_events.iter().rev().collect::<Vec<&String>>())
+            .ignore();
+    }
+    pipeline
+        .query_async::<redis::aio::MultiplexedConnection, ()>(&mut *redis_conn)
+        .await
+        .map_err(|err| {
+            log::error!("Failed to acknowledge worker events: {:?}", err);
+            ServiceError::InternalServerError("Failed to acknowledge worker events".to_string())
+        })?;
+
+    result.map(|_| serialized_events.len())
+}
+
+/// Claims pending deliveries which are due for an attempt. Claimed deliveries have their next
+/// attempt pushed back by `lease_secs` so concurrent workers skip them.
+pub async fn lease_due_event_webhook_deliveries_query(
+    limit: i64,
+    lease_secs: i64,
+    pool: web::Data<Pool>,
+) -> Result<Vec<EventWebhookDelivery>, ServiceError> {
+    use crate::data::schema::event_webhook_deliveries::dsl as event_webhook_deliveries_columns;
+
+    let mut conn = pool.get().await.map_err(|_e| {
+        ServiceError::InternalServerError("Failed to get postgres connection".to_string())
+    })?;
+
+    conn.transaction::<_, diesel::result::Error, _>(|conn| {
+        async move {
+            let now = chrono::Utc::now().naive_local();
+
+            let deliveries = event_webhook_deliveries_columns::event_webhook_deliveries
+                .filter(
+                    event_webhook_deliveries_columns::status
+                        .eq(EventWebhookDeliveryStatus::Pending.to_string()),
+                )
+                .filter(event_webhook_deliveries_columns::next_attempt_at.le(now))
+                .order_by(event_webhook_deliveries_columns::next_attempt_at.asc())
+                .limit(limit)
+                .for_update()
+                .skip_locked()
+                .select(EventWebhookDelivery::as_select())
+                .load::<EventWebhookDelivery>(conn)
+                .await?;
+
+            diesel::update(
+                event_webhook_deliveries_columns::event_webhook_deliveries.filter(
+                    event_webhook_deliveries_columns::id.eq_any(
+                        deliveries
+                            .iter()
+                            .map(|delivery| delivery.id)
+                            .collect::<Vec<_>>(),
+                    ),
+                ),
+            )
+            .set(
+                event_webhook_deliveries_columns::next_attempt_at
+                    .eq(now + chrono::Duration::seconds(lease_secs)),
+            )
+            .execute(conn)
+            .await?;
+
+            Ok(deliveries)
+        }
+        .scope_boxed()
+    })
+    .await
+    .map_err(|err| {
+        log::error!("Failed to lease event webhook deliveries: {:?}", err);
+        ServiceError::InternalServerError("Failed to lease event webhook deliveries".to_string())
+    })
+}
+
+pub async fn delete_event_webhook_deliveries_before_query(
+    before: chrono::NaiveDateTime,
+    pool: web::Data<Pool>,
+) -> Result<usize, ServiceError> {
+    use crate::data::schema::event_webhook_deliveries::dsl as event_webhook_deliveries_columns;
+
+    let mut conn = pool.get().await.map_err(|_e| {
+        ServiceError::InternalServerError("Failed to get postgres connection".to_string())
+    })?;
+
+    diesel::delete(
+        event_webhook_deliveries_columns::event_webhook_deliveries
+            .filter(event_webhook_deliveries_columns::created_at.lt(before))
+            .filter(
+                event_webhook_deliveries_columns::status
+                    .ne(EventWebhookDeliveryStatus::Pending.to_string()),
+            ),
+    )
+    .execute(&mut conn)
+    .await
+    .map_err(|err| {
+        log::error!("Failed to delete old event webhook deliveries: {:?}", err);
+        ServiceError::InternalServerError(
+            "Failed to delete old event webhook deliveries".to_string(),
+        )
+    })
+}
+
+async fn get_event_webhook_by_id_query(
+    webhook_id: uuid::Uuid,
+    pool: web::Data<Pool>,
+) -> Result<EventWebhook, ServiceError> {
+    use crate::data::schema::event_webhooks::dsl as event_webhooks_columns;
+
+    let mut conn = pool.get().await.map_err(|_e| {
+        ServiceError::InternalServerError("Failed to get postgres connection".to_string())
+    })?;
+
+    event_webhooks_columns::event_webhooks
+        .filter(event_webhooks_columns::id.eq(webhook_id))
+        .select(EventWebhook::as_select())
+        .first::<EventWebhook>(&mut conn)
+        .await
+        .map_err(|_| ServiceError::NotFound("Event webhook not found".to_string()))
+}
+
+async fn update_event_webhook_delivery_query(
+    delivery: &EventWebhookDelivery,
+    pool: web::Data<Pool>,
+) -> Result<(), ServiceError> {
+    use crate::data::schema::event_webhook_deliveries::dsl as event_webhook_deliveries_columns;
+
+    let mut conn = pool.get().await.map_err(|_e| {
+        ServiceError::InternalServerError("Failed to get postgres connection".to_string())
+    })?;
+
+    diesel::update(
+        event_webhook_deliveries_columns::event_webhook_deliveries
+            .filter(event_webhook_deliveries_columns::id.eq(delivery.id)),
+    )
+    .set((
+        event_webhook_deliveries_columns::status.eq(&delivery.status),
+        event_webhook_deliveries_columns::attempts.eq(delivery.attempts),
+        event_webhook_deliveries_columns::next_attempt_at.eq(delivery.next_attempt_at),
+        event_webhook_deliveries_columns::last_status_code.eq(delivery.last_status_code),
+        event_webhook_deliveries_columns::last_error.eq(&delivery.last_error),
+        event_webhook_deliveries_columns::updated_at.eq(delivery.updated_at),
+    ))
+    .execute(&mut conn)
+    .await
+    .map_err(|err| {
+        log::error!("Failed to update event webhook delivery: {:?}", err);
+        ServiceError::InternalServerError("Failed to update event webhook delivery".to_string())
+    })?;
+
+    Ok(())
+}
+
+/// POSTs the delivery's payload to the webhook url, returning the status code it responded with
+/// and an error if the delivery did not succeed. Urls which do not resolve to a public address
+/// fail without being sent.
+async fn send_event_webhook_request(
+    webhook: &EventWebhook,
+    delivery: &EventWebhookDelivery,
+) -> Result<(Option<i32>, Option<String>), ServiceError> {
+    let body = serde_json::to_string(&delivery.payload)
+        .map_err(|_| ServiceError::BadRequest("Failed to serialize event".to_string()))?;
+
+    let timestamp = chrono::Utc::now().timestamp();
+    let signature = sign_webhook_payload(&webhook.secret, timestamp, &body)?;
+
+    let (webhook_url, reqwest_client) = match build_webhook_client(&webhook.url).await {
+        Ok(webhook_client) => webhook_client,
+        Err(ServiceError::BadRequest(message)) => return Ok((None, Some(message))),
+        Err(err) => return Err(err),
+    };
+
+    Ok(send_webhook_request(
+        reqwest_client
+            .post(webhook_url)
+            .header("Content-Type", "application/json")
+            .header("X-Trieve-Webhook-Id", webhook.id.to_string())
+            .header("X-Trieve-Event-Id", delivery.event_id.to_string())
+            .header("X-Trieve-Event-Type", delivery.event_type.clone())
+            .header("X-Trieve-Timestamp", timestamp.to_string())
+            .header("X-Trieve-Signature", format!("sha256={}", signature))
+            .timeout(std::time::Duration::from_secs(10))
+            .body(body),
+    )
+    .await)
+}
+
+/// Attempts a pending delivery and records the outcome. Failed attempts are retried with
+/// exponential backoff until `EVENT_WEBHOOK_MAX_ATTEMPTS` is reached. Deliveries to webhooks
+/// which were paused after the event was queued are marked as failed without being sent.
+pub async fn deliver_event_webhook(
+    mut delivery: EventWebhookDelivery,
+    pool: web::Data<Pool>,
+) -> Result<(), ServiceError> {
+    let webhook = get_event_webhook_by_id_query(delivery.webhook_id, pool.clone()).await?;
+    let now = chrono::Utc::now().naive_local();
+
+    if !webhook.active {
+        delivery.status = EventWebhookDeliveryStatus::Failed.to_string();
+        delivery.last_error = Some("Webhook is inactive".to_string());
+        delivery.updated_at = now;
+
+        return update_event_webhook_delivery_query(&delivery, pool).await;
+    }
+
+    let (status_code, error) = send_event_webhook_request(&webhook, &delivery).await?;
+
+    delivery.attempts += 1;
+    delivery.last_status_code = status_code;
+    delivery.updated_at = now;
+    delivery.status = if error.is_none() {
+        EventWebhookDeliveryStatus::Delivered.to_string()
+    } else if delivery.attempts >= EVENT_WEBHOOK_MAX_ATTEMPTS {
+        EventWebhookDeliveryStatus::Failed.to_string()
+    } else {
+        let backoff_secs = (30_i64 << (delivery.attempts - 1).min(10)).min(6 * 60 * 60);
+        delivery.next_attempt_at = now + chrono::Duration::seconds(backoff_secs);
+        EventWebhookDeliveryStatus::Pending.to_string()
+    };
+    delivery.last_error = error;
+
+    update_event_webhook_delivery_query(&delivery, pool).await
+}
+
+/// Sends a signed synthetic event to the webhook right away and records it in the webhook's
+/// delivery log. Test deliveries are not retried.
+pub async fn send_test_event_webhook(
+    webhook: EventWebhook,
+    pool: web::Data<Pool>,
+) -> Result<EventWebhookDelivery, ServiceError> {
+    use crate::data::schema::event_webhook_deliveries::dsl as event_webhook_deliveries_columns;
+
+    let payload = EventWebhookPayload {
+        id: uuid::Uuid::new_v4(),
+        webhook_id: webhook.id,
+        dataset_id: webhook.dataset_id,
+        organization_id: None,
+        event_type: EVENT_WEBHOOK_TEST_EVENT_TYPE.to_string(),
+        event_data: serde_json::json!({
+            "message": "This is a test event sent from Trieve",
+        }),
+        created_at: chrono::Utc::now().naive_local().to_string(),
+    };
+
+    let mut delivery = EventWebhookDelivery::from_details(
+        webhook.id,
+        payload.id,
+        payload.event_type.clone(),
+        serde_json::to_value(payload)
+            .map_err(|_| ServiceError::BadRequest("Failed to serialize event".to_string()))?,
+    );
+
+    let (status_code, error) = send_event_webhook_request(&webhook, &delivery).await?;
+
+    delivery.attempts = 1;
+    delivery.last_status_code = status_code;
+    delivery.status = if error.is_none() {
+        EventWebhookDeliveryStatus::Delivered.to_string()
+    } else {
+        EventWebhookDeliveryStatus::Failed.to_string()
+    };
+    delivery.last_error = error;
+
+    let mut conn = pool.get().await.map_err(|_e| {
+        ServiceError::InternalServerError("Failed to get postgres connection".to_string())
+    })?;
+
+    diesel::insert_into(event_webhook_deliveries_columns::event_webhook_deliveries)
+        .values(&delivery)
+        .get_result::<EventWebhookDelivery>(&mut conn)
+        .await
+        .map_err(|err| {
+            log::error!("Failed to record test event webhook delivery: {:?}", err);
+            ServiceError::InternalServerError(
+                "Failed to record test event webhook delivery".to_string(),
+            )
+        })
+}
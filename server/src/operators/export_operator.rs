@@ -0,0 +1,685 @@
+use std::collections::{HashMap, HashSet};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use actix_web::web;
+use diesel::dsl::sql;
+use diesel::prelude::*;
+use diesel::sql_types;
+use diesel_async::RunQueryDsl;
+use parquet::basic::{Compression, ZstdLevel};
+use parquet::data_type::{ByteArray, ByteArrayType};
+use parquet::file::properties::WriterProperties;
+use parquet::file::writer::SerializedFileWriter;
+use parquet::schema::parser::parse_message_type;
+use qdrant_client::qdrant::Filter;
+
+use crate::data::models::{
+    ChunkBoost, ChunkGroup, ChunkGroupAndFileId, ChunkMetadata, ChunkMetadataTable,
+    DatasetAndOrgWithSubAndPlan, DatasetConfiguration, DatasetConfigurationDTO, DatasetExport,
+    DatasetExportFormat, DatasetExportRecord, DatasetExportStatus, ExportedChunk, File, Pool,
+};
+use crate::errors::ServiceError;
+use crate::handlers::chunk_handler::ChunkFilter;
+
+use super::qdrant_operator::{get_point_vectors_query, scroll_dataset_points};
+use super::search_operator::assemble_qdrant_filter;
+
+const EXPORT_PAGE_SIZE: i64 = 500;
+const PARQUET_ROW_GROUP_SIZE: usize = 1000;
+
+pub async fn create_dataset_export_query(
+    dataset_export: DatasetExport,
+    pool: web::Data<Pool>,
+) -> Result<DatasetExport, ServiceError> {
+    use crate::data::schema::dataset_exports::dsl as dataset_exports_columns;
+
+    let mut conn = pool.get().await.map_err(|_e| {
+        ServiceError::InternalServerError("Failed to get postgres connection".to_string())
+    })?;
+
+    let dataset_export = diesel::insert_into(dataset_exports_columns::dataset_exports)
+        .values(&dataset_export)
+        .get_result::<DatasetExport>(&mut conn)
+        .await
+        .map_err(|err| {
+            log::error!("Failed to create dataset export: {:?}", err);
+            ServiceError::InternalServerError("Failed to create dataset export".to_string())
+        })?;
+
+    Ok(dataset_export)
+}
+
+pub async fn get_dataset_export_query(
+    export_id: uuid::Uuid,
+    dataset_id: uuid::Uuid,
+    pool: web::Data<Pool>,
+) -> Result<DatasetExport, ServiceError> {
+    use crate::data::schema::dataset_exports::dsl as dataset_exports_columns;
+
+    let mut conn = pool.get().await.map_err(|_e| {
+        ServiceError::InternalServerError("Failed to get postgres connection".to_string())
+    })?;
+
+    dataset_exports_columns::dataset_exports
+        .filter(dataset_exports_columns::id.eq(export_id))
+        .filter(dataset_exports_columns::dataset_id.eq(dataset_id))
+        .select(DatasetExport::as_select())
+        .first::<DatasetExport>(&mut conn)
+        .await
+        .map_err(|_| ServiceError::NotFound("Dataset export not found".to_string()))
+}
+
+pub async fn get_dataset_export_for_organization_query(
+    export_id: uuid::Uuid,
+    organization_id: uuid::Uuid,
+    pool: web::Data<Pool>,
+) -> Result<DatasetExport, ServiceError> {
+    use crate::data::schema::dataset_exports::dsl as dataset_exports_columns;
+    use crate::data::schema::datasets::dsl as datasets_columns;
+
+    let mut conn = pool.get().await.map_err(|_e| {
+        ServiceError::InternalServerError("Failed to get postgres connection".to_string())
+    })?;
+
+    dataset_exports_columns::dataset_exports
+        .inner_join(datasets_columns::datasets)
+        .filter(dataset_exports_columns::id.eq(export_id))
+        .filter(datasets_columns::organization_id.eq(organization_id))
+        .select(DatasetExport::as_select())
+        .first::<DatasetExport>(&mut conn)
+        .await
+        .map_err(|_| ServiceError::NotFound("Dataset export not found".to_string()))
+}
+
+pub async fn update_dataset_export_status_query(
+    export_id: uuid::Uuid,
+    status: DatasetExportStatus,
+    s3_key: Option<String>,
+    error: Option<String>,
+    pool: web::Data<Pool>,
+) -> Result<(), ServiceError> {
+    use crate::data::schema::dataset_exports::dsl as dataset_exports_columns;
+
+    let mut conn = pool.get().await.map_err(|_e| {
+        ServiceError::InternalServerError("Failed to get postgres connection".to_string())
+    })?;
+
+    diesel::update(
+        dataset_exports_columns::dataset_exports.filter(dataset_exports_columns::id.eq(export_id)),
+    )
+    .set((
+        dataset_exports_columns::status.eq(status.to_string()),
+        dataset_exports_columns::s3_key.eq(s3_key),
+        dataset_exports_columns::error.eq(error),
+        dataset_exports_columns::updated_at.eq(chrono::Utc::now().naive_local()),
+    ))
+    .execute(&mut conn)
+    .await
+    .map_err(|err| {
+        log::error!("Failed to update dataset export status: {:?}", err);
+        ServiceError::InternalServerError("Failed to update dataset export status".to_string())
+    })?;
+
+    Ok(())
+}
+
+pub async fn update_dataset_export_progress_query(
+    export_id: uuid::Uuid,
+    chunks_exported: i64,
+    pool: web::Data<Pool>,
+) -> Result<(), ServiceError> {
+    use crate::data::schema::dataset_exports::dsl as dataset_exports_columns;
+
+    let mut conn = pool.get().await.map_err(|_e| {
+        ServiceError::InternalServerError("Failed to get postgres connection".to_string())
+    })?;
+
+    diesel::update(
+        dataset_exports_columns::dataset_exports.filter(dataset_exports_columns::id.eq(export_id)),
+    )
+    .set((
+        dataset_exports_columns::chunks_exported.eq(chunks_exported),
+        dataset_exports_columns::updated_at.eq(chrono::Utc::now().naive_local()),
+    ))
+    .execute(&mut conn)
+    .await
+    .map_err(|err| {
+        log::error!("Failed to update dataset export progress: {:?}", err);
+        ServiceError::InternalServerError("Failed to update dataset export progress".to_string())
+    })?;
+
+    Ok(())
+}
+
+/// Scrolls the groups of a dataset, restricted to `group_ids` when they are given.
+pub async fn scroll_dataset_groups_query(
+    dataset_id: uuid::Uuid,
+    group_ids: Option<&[uuid::Uuid]>,
+    offset: uuid::Uuid,
+    limit: i64,
+    pool: web::Data<Pool>,
+) -> Result<Vec<ChunkGroupAndFileId>, ServiceError> {
+    use crate::data::schema::chunk_group::dsl as chunk_group_columns;
+    use crate::data::schema::groups_from_files::dsl as groups_from_files_columns;
+
+    let mut conn = pool.get().await.map_err(|_e| {
+        ServiceError::InternalServerError("Failed to get postgres connection".to_string())
+    })?;
+
+    let mut query = chunk_group_columns::chunk_group
+        .left_join(
+            groups_from_files_columns::groups_from_files
+                .on(groups_from_files_columns::group_id.eq(chunk_group_columns::id)),
+        )
+        .filter(chunk_group_columns::dataset_id.eq(dataset_id))
+        .filter(chunk_group_columns::id.gt(offset))
+        .select((
+            ChunkGroup::as_select(),
+            groups_from_files_columns::file_id.nullable(),
+        ))
+        .into_boxed();
+
+    if let Some(group_ids) = group_ids {
+        query = query.filter(chunk_group_columns::id.eq_any(group_ids));
+    }
+
+    let groups: Vec<(ChunkGroup, Option<uuid::Uuid>)> = query
+        .order_by(chunk_group_columns::id.asc())
+        .limit(limit)
+        .load::<(ChunkGroup, Option<uuid::Uuid>)>(&mut conn)
+        .await
+        .map_err(|err| {
+            log::error!("Error scrolling groups for export {:?}", err);
+            ServiceError::BadRequest("Error scrolling groups for dataset".to_string())
+        })?;
+
+    Ok(groups
+        .into_iter()
+        .map(|(group, file_id)| ChunkGroupAndFileId {
+            id: group.id,
+            dataset_id: group.dataset_id,
+            name: group.name,
+            description: group.description,
+            tracking_id: group.tracking_id,
+            tag_set: group.tag_set,
+            metadata: group.metadata,
+            file_id,
+            parent_id: group.parent_id,
+            created_at: group.created_at,
+            updated_at: group.updated_at,
+        })
+        .collect())
+}
+
+/// Scrolls the files of a dataset, restricted to `file_ids` when they are given.
+pub async fn scroll_dataset_files_query(
+    dataset_id: uuid::Uuid,
+    file_ids: Option<&[uuid::Uuid]>,
+    offset: uuid::Uuid,
+    limit: i64,
+    pool: web::Data<Pool>,
+) -> Result<Vec<File>, ServiceError> {
+    use crate::data::schema::files::dsl as files_columns;
+
+    let mut conn = pool.get().await.map_err(|_e| {
+        ServiceError::InternalServerError("Failed to get postgres connection".to_string())
+    })?;
+
+    let mut query = files_columns::files
+        .filter(files_columns::dataset_id.eq(dataset_id))
+        .filter(files_columns::id.gt(offset))
+        .select(File::as_select())
+        .into_boxed();
+
+    if let Some(file_ids) = file_ids {
+        query = query.filter(files_columns::id.eq_any(file_ids));
+    }
+
+    query
+        .order_by(files_columns::id.asc())
+        .limit(limit)
+        .load::<File>(&mut conn)
+        .await
+        .map_err(|err| {
+            log::error!("Error scrolling files for export {:?}", err);
+            ServiceError::BadRequest("Error scrolling files for dataset".to_string())
+        })
+}
+
+/// Loads the ids of the groups containing the chunks behind a page of qdrant points.
+pub async fn get_group_ids_from_point_ids_query(
+    point_ids: Vec<uuid::Uuid>,
+    pool: web::Data<Pool>,
+) -> Result<Vec<uuid::Uuid>, ServiceError> {
+    use crate::data::schema::chunk_group_bookmarks::dsl as chunk_group_bookmarks_columns;
+    use crate::data::schema::chunk_metadata::dsl as chunk_metadata_columns;
+
+    let mut conn = pool.get().await.map_err(|_e| {
+        ServiceError::InternalServerError("Failed to get postgres connection".to_string())
+    })?;
+
+    chunk_group_bookmarks_columns::chunk_group_bookmarks
+        .inner_join(
+            chunk_metadata_columns::chunk_metadata
+                .on(chunk_metadata_columns::id.eq(chunk_group_bookmarks_columns::chunk_metadata_id)),
+        )
+        .filter(chunk_metadata_columns::qdrant_point_id.eq_any(&point_ids))
+        .select(chunk_group_bookmarks_columns::group_id)
+        .distinct()
+        .load::<uuid::Uuid>(&mut conn)
+        .await
+        .map_err(|_| ServiceError::BadRequest("Failed to load group memberships".to_string()))
+}
+
+/// Loads the chunks behind a page of qdrant points along with their boosts and group memberships, keyed by qdrant point id.
+pub async fn get_exported_chunks_from_point_ids_query(
+    point_ids: Vec<uuid::Uuid>,
+    pool: web::Data<Pool>,
+) -> Result<HashMap<uuid::Uuid, ExportedChunk>, ServiceError> {
+    use crate::data::schema::chunk_boosts::dsl as chunk_boosts_columns;
+    use crate::data::schema::chunk_group_bookmarks::dsl as chunk_group_bookmarks_columns;
+    use crate::data::schema::chunk_metadata::dsl as chunk_metadata_columns;
+    use crate::data::schema::chunk_metadata_tags::dsl as chunk_metadata_tags_columns;
+    use crate::data::schema::dataset_tags::dsl as dataset_tags_columns;
+
+    let mut conn = pool.get().await.map_err(|_e| {
+        ServiceError::InternalServerError("Failed to get postgres connection".to_string())
+    })?;
+
+    let chunk_metadata_pairs: Vec<(ChunkMetadataTable, Option<Vec<String>>)> =
+        chunk_metadata_columns::chunk_metadata
+            .left_join(
+                chunk_metadata_tags_columns::chunk_metadata_tags
+                    .on(chunk_metadata_tags_columns::chunk_metadata_id
+                        .eq(chunk_metadata_columns::id)),
+            )
+            .left_join(
+                dataset_tags_columns::dataset_tags
+                    .on(dataset_tags_columns::id.eq(chunk_metadata_tags_columns::tag_id)),
+            )
+            .filter(chunk_metadata_columns::qdrant_point_id.eq_any(&point_ids))
+            .select((
+                ChunkMetadataTable::as_select(),
+                sql::<sql_types::Array<sql_types::Text>>(
+                    "array_remove(array_agg(dataset_tags.tag), null)",
+                )
+                .nullable(),
+            ))
+            .group_by(chunk_metadata_columns::id)
+            .load::<(ChunkMetadataTable, Option<Vec<String>>)>(&mut conn)
+            .await
+            .map_err(|_| ServiceError::BadRequest("Failed to load metadata".to_string()))?;
+
+    let chunk_ids = chunk_metadata_pairs
+        .iter()
+        .map(|(table, _)| table.id)
+        .collect::<Vec<uuid::Uuid>>();
+
+    let mut boost_map: HashMap<uuid::Uuid, ChunkBoost> = chunk_boosts_columns::chunk_boosts
+        .filter(chunk_boosts_columns::chunk_id.eq_any(&chunk_ids))
+        .select(ChunkBoost::as_select())
+        .load::<ChunkBoost>(&mut conn)
+        .await
+        .map_err(|_| ServiceError::BadRequest("Failed to load chunk boosts".to_string()))?
+        .into_iter()
+        .map(|boost| (boost.chunk_id, boost))
+        .collect();
+
+    let mut group_map: HashMap<uuid::Uuid, Vec<uuid::Uuid>> = HashMap::new();
+    chunk_group_bookmarks_columns::chunk_group_bookmarks
+        .filter(chunk_group_bookmarks_columns::chunk_metadata_id.eq_any(&chunk_ids))
+        .select((
+            chunk_group_bookmarks_columns::chunk_metadata_id,
+            chunk_group_bookmarks_columns::group_id,
+        ))
+        .load::<(uuid::Uuid, uuid::Uuid)>(&mut conn)
+        .await
+        .map_err(|_| ServiceError::BadRequest("Failed to load group memberships".to_string()))?
+        .into_iter()
+        .for_each(|(chunk_id, group_id)| group_map.entry(chunk_id).or_default().push(group_id));
+
+    Ok(chunk_metadata_pairs
+        .into_iter()
+        .map(|(table, tag_set)| {
+            let chunk = ChunkMetadata::from_table_and_tag_set(table, tag_set.unwrap_or_default());
+            (
+                chunk.qdrant_point_id,
+                ExportedChunk {
+                    boost: boost_map.remove(&chunk.id),
+                    group_ids: group_map.remove(&chunk.id).unwrap_or_default(),
+                    vectors: Default::default(),
+                    chunk,
+                },
+            )
+        })
+        .collect())
+}
+
+/// Writes export records to a local file in either of the supported formats.
+pub enum DatasetExportWriter {
+    Jsonl(BufWriter<std::fs::File>),
+    Parquet {
+        writer: Box<SerializedFileWriter<std::fs::File>>,
+        pending: Vec<DatasetExportRecord>,
+    },
+}
+
+impl DatasetExportWriter {
+    pub fn new(path: &Path, format: DatasetExportFormat) -> Result<Self, ServiceError> {
+        let file = std::fs::File::create(path).map_err(|err| {
+            log::error!("Failed to create export file: {:?}", err);
+            ServiceError::InternalServerError("Failed to create export file".to_string())
+        })?;
+
+        match format {
+            DatasetExportFormat::Jsonl => Ok(DatasetExportWriter::Jsonl(BufWriter::new(file))),
+            DatasetExportFormat::Parquet => {
+                let schema = parse_message_type(
+                    "message dataset_export {
+                        REQUIRED BINARY record_type (UTF8);
+                        REQUIRED BINARY id (UTF8);
+                        REQUIRED BINARY record (JSON);
+                    }",
+                )
+                .map_err(|err| ServiceError::InternalServerError(err.to_string()))?;
+                let properties = WriterProperties::builder()
+                    .set_compression(Compression::ZSTD(ZstdLevel::default()))
+                    .build();
+                let writer =
+                    SerializedFileWriter::new(file, Arc::new(schema), Arc::new(properties))
+                        .map_err(|err| ServiceError::InternalServerError(err.to_string()))?;
+
+                Ok(DatasetExportWriter::Parquet {
+                    writer: Box::new(writer),
+                    pending: Vec::with_capacity(PARQUET_ROW_GROUP_SIZE),
+                })
+            }
+        }
+    }
+
+    pub fn write(&mut self, record: DatasetExportRecord) -> Result<(), ServiceError> {
+        match self {
+            DatasetExportWriter::Jsonl(writer) => {
+                serde_json::to_writer(&mut *writer, &record)
+                    .map_err(|err| ServiceError::InternalServerError(err.to_string()))?;
+                writer
+                    .write_all(b"\n")
+                    .map_err(|err| ServiceError::InternalServerError(err.to_string()))
+            }
+            DatasetExportWriter::Parquet { pending, .. } => {
+                pending.push(record);
+                if pending.len() >= PARQUET_ROW_GROUP_SIZE {
+                    self.flush_row_group()?;
+                }
+                Ok(())
+            }
+        }
+    }
+
+    fn flush_row_group(&mut self) -> Result<(), ServiceError> {
+        let DatasetExportWriter::Parquet { writer, pending } = self else {
+            return Ok(());
+        };
+        if pending.is_empty() {
+            return Ok(());
+        }
+
+        let mut columns: [Vec<ByteArray>; 3] = Default::default();
+        for record in pending.drain(..) {
+            let json = serde_json::to_string(&record)
+                .map_err(|err| ServiceError::InternalServerError(err.to_string()))?;
+            columns[0].push(ByteArray::from(record.record_type()));
+            columns[1].push(ByteArray::from(record.id().to_string().as_str()));
+            columns[2].push(ByteArray::from(json.as_str()));
+        }
+
+        let mut row_group = writer
+            .next_row_group()
+            .map_err(|err| ServiceError::InternalServerError(err.to_string()))?;
+        for values in columns.iter() {
+            let mut column = row_group
+                .next_column()
+                .map_err(|err| ServiceError::InternalServerError(err.to_string()))?
+                .ok_or(ServiceError::InternalServerError(
+                    "Parquet export schema is missing a column".to_string(),
+                ))?;
+            column
+                .typed::<ByteArrayType>()
+                .write_batch(values, None, None)
+                .map_err(|err| ServiceError::InternalServerError(err.to_string()))?;
+            column
+                .close()
+                .map_err(|err| ServiceError::InternalServerError(err.to_string()))?;
+        }
+        row_group
+            .close()
+            .map_err(|err| ServiceError::InternalServerError(err.to_string()))?;
+
+        Ok(())
+    }
+
+    pub fn finish(mut self) -> Result<(), ServiceError> {
+        self.flush_row_group()?;
+        match self {
+            DatasetExportWriter::Jsonl(mut writer) => writer
+                .flush()
+                .map_err(|err| ServiceError::InternalServerError(err.to_string())),
+            DatasetExportWriter::Parquet { writer, .. } => writer
+                .close()
+                .map(|_| ())
+                .map_err(|err| ServiceError::InternalServerError(err.to_string())),
+        }
+    }
+
+    /// Creates the writer on a blocking thread so creating the file does not stall the runtime.
+    pub async fn create(path: PathBuf, format: DatasetExportFormat) -> Result<Self, ServiceError> {
+        web::block(move || DatasetExportWriter::new(&path, format))
+            .await
+            .map_err(|err| ServiceError::InternalServerError(format!("Thread error {:?}", err)))?
+    }
+
+    /// Writes a batch of records on a blocking thread, handing the writer back once done.
+    pub async fn write_batch(
+        mut self,
+        records: Vec<DatasetExportRecord>,
+    ) -> Result<Self, ServiceError> {
+        web::block(move || {
+            for record in records {
+                self.write(record)?;
+            }
+            Ok(self)
+        })
+        .await
+        .map_err(|err| ServiceError::InternalServerError(format!("Thread error {:?}", err)))?
+    }
+
+    /// Flushes the remaining records and closes the file on a blocking thread.
+    pub async fn finish_blocking(self) -> Result<(), ServiceError> {
+        web::block(move || self.finish())
+            .await
+            .map_err(|err| ServiceError::InternalServerError(format!("Thread error {:?}", err)))?
+    }
+}
+
+pub fn get_dataset_export_s3_key(dataset_export: &DatasetExport) -> String {
+    let extension = match DatasetExportFormat::from(dataset_export.format.clone()) {
+        DatasetExportFormat::Jsonl => "jsonl",
+        DatasetExportFormat::Parquet => "parquet",
+    };
+
+    format!(
+        "exports/{}/{}.{}",
+        dataset_export.dataset_id, dataset_export.id, extension
+    )
+}
+
+/// Scrolls the points matching a filtered export and collects the ids of the groups containing them.
+async fn get_exported_group_ids(
+    dataset_config: DatasetConfiguration,
+    qdrant_filter: Filter,
+    pool: web::Data<Pool>,
+) -> Result<Vec<uuid::Uuid>, ServiceError> {
+    let mut group_ids = HashSet::new();
+    let mut point_offset = None;
+    loop {
+        let (points, next_offset) = scroll_dataset_points(
+            EXPORT_PAGE_SIZE as u64,
+            point_offset,
+            None,
+            dataset_config.clone(),
+            qdrant_filter.clone(),
+        )
+        .await?;
+
+        let point_ids = points
+            .iter()
+            .map(|point| point.point_id)
+            .collect::<Vec<uuid::Uuid>>();
+        group_ids.extend(get_group_ids_from_point_ids_query(point_ids, pool.clone()).await?);
+
+        match next_offset {
+            Some(next_offset) if !points.is_empty() => point_offset = Some(next_offset),
+            _ => break,
+        }
+    }
+
+    Ok(group_ids.into_iter().collect())
+}
+
+/// Writes the dataset record, the groups, the files and then the chunks matching the export's filter to `path`. When a filter is set only the groups holding the exported chunks and the files behind those groups are written. Returns the number of chunks written.
+pub async fn write_dataset_export(
+    dataset_export: &DatasetExport,
+    dataset_org_plan_sub: &DatasetAndOrgWithSubAndPlan,
+    path: &Path,
+    pool: web::Data<Pool>,
+) -> Result<i64, ServiceError> {
+    let dataset = dataset_org_plan_sub.dataset.clone();
+    let dataset_config = DatasetConfiguration::from_json(dataset.server_configuration.clone());
+    if dataset_config.QDRANT_ONLY {
+        return Err(ServiceError::BadRequest(
+            "Datasets which only store chunks in qdrant cannot be exported".to_string(),
+        ));
+    }
+
+    let filter: Option<ChunkFilter> = dataset_export
+        .filter
+        .clone()
+        .map(serde_json::from_value)
+        .transpose()
+        .map_err(|err| ServiceError::BadRequest(format!("Invalid export filter: {}", err)))?;
+
+    let mut writer = DatasetExportWriter::create(
+        path.to_path_buf(),
+        DatasetExportFormat::from(dataset_export.format.clone()),
+    )
+    .await?;
+
+    writer = writer
+        .write_batch(vec![DatasetExportRecord::Dataset {
+            dataset: dataset.clone(),
+            server_configuration: DatasetConfigurationDTO::from(dataset_config.clone()),
+        }])
+        .await?;
+
+    let is_filtered = filter.is_some();
+    let qdrant_filter =
+        assemble_qdrant_filter(filter, None, None, dataset.id, pool.clone()).await?;
+
+    // A filtered export only carries the groups holding its chunks and the files behind them
+    let group_ids = if is_filtered {
+        Some(
+            get_exported_group_ids(dataset_config.clone(), qdrant_filter.clone(), pool.clone())
+                .await?,
+        )
+    } else {
+        None
+    };
+    let mut file_ids: Vec<uuid::Uuid> = vec![];
+
+    let mut group_offset = uuid::Uuid::nil();
+    loop {
+        let groups = scroll_dataset_groups_query(
+            dataset.id,
+            group_ids.as_deref(),
+            group_offset,
+            EXPORT_PAGE_SIZE,
+            pool.clone(),
+        )
+        .await?;
+        let Some(last_group) = groups.last() else {
+            break;
+        };
+        group_offset = last_group.id;
+        file_ids.extend(groups.iter().filter_map(|group| group.file_id));
+        writer = writer
+            .write_batch(groups.into_iter().map(DatasetExportRecord::Group).collect())
+            .await?;
+    }
+
+    let file_ids = is_filtered.then_some(file_ids);
+    let mut file_offset = uuid::Uuid::nil();
+    loop {
+        let files = scroll_dataset_files_query(
+            dataset.id,
+            file_ids.as_deref(),
+            file_offset,
+            EXPORT_PAGE_SIZE,
+            pool.clone(),
+        )
+        .await?;
+        let Some(last_file) = files.last() else {
+            break;
+        };
+        file_offset = last_file.id;
+        writer = writer
+            .write_batch(files.into_iter().map(DatasetExportRecord::File).collect())
+            .await?;
+    }
+
+    let mut chunks_exported: i64 = 0;
+    let mut point_offset = None;
+    loop {
+        let (points, next_offset) = scroll_dataset_points(
+            EXPORT_PAGE_SIZE as u64,
+            point_offset,
+            None,
+            dataset_config.clone(),
+            qdrant_filter.clone(),
+        )
+        .await?;
+
+        let point_ids = points
+            .iter()
+            .map(|point| point.point_id)
+            .collect::<Vec<uuid::Uuid>>();
+        let mut exported_chunks =
+            get_exported_chunks_from_point_ids_query(point_ids.clone(), pool.clone()).await?;
+        let mut point_vectors =
+            get_point_vectors_query(point_ids.clone(), dataset_config.clone()).await?;
+
+        let records = point_ids
+            .into_iter()
+            .filter_map(|point_id| {
+                let mut exported_chunk = exported_chunks.remove(&point_id)?;
+                exported_chunk.vectors = point_vectors.remove(&point_id).unwrap_or_default();
+                Some(DatasetExportRecord::Chunk(Box::new(exported_chunk)))
+            })
+            .collect::<Vec<DatasetExportRecord>>();
+        chunks_exported += records.len() as i64;
+        writer = writer.write_batch(records).await?;
+
+        update_dataset_export_progress_query(dataset_export.id, chunks_exported, pool.clone())
+            .await?;
+
+        match next_offset {
+            Some(next_offset) if !points.is_empty() => point_offset = Some(next_offset),
+            _ => break,
+        }
+    }
+
+    writer.finish_blocking().await?;
+
+    Ok(chunks_exported)
+}
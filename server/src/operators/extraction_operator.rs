@@ -0,0 +1,809 @@
+use super::csv_jsonl_operator::{parse_csv_line, spreadsheet_cell_to_value};
+use crate::errors::ServiceError;
+use calamine::{open_workbook_auto_from_rs, Reader as SpreadsheetReader};
+use itertools::Itertools;
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader as XmlReader;
+use regex::Regex;
+use scraper::Html;
+use serde::{Deserialize, Serialize};
+use std::io::{Cursor, Read};
+use std::sync::LazyLock;
+use utoipa::ToSchema;
+use zip::ZipArchive;
+
+/// Largest uncompressed size of a single entry read from a DOCX, PPTX or EPUB archive
+const MAX_ZIP_ENTRY_BYTES: u64 = 64 * 1024 * 1024;
+
+static MARKDOWN_HEADING_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?m)^#{1,6}\s+\S").expect("regex is always correct"));
+
+static PPTX_SLIDE_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^ppt/slides/slide(\d+)\.xml$").expect("regex is always correct"));
+
+static HTML_BLOCK_END_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"(?i)</(?:p|div|li|ul|ol|dl|dd|dt|section|article|blockquote|tr|table|pre|header|footer)\s*>|<br\s*/?>",
+    )
+    .expect("regex is always correct")
+});
+
+static HTML_CELL_END_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?i)</t[dh]\s*>").expect("regex is always correct"));
+
+static PARAGRAPH_BREAK_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\n\s*\n").expect("regex is always correct"));
+
+static HTML_IGNORED_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"(?is)<head\b.*?</head\s*>|<script\b.*?</script\s*>|<style\b.*?</style\s*>|<noscript\b.*?</noscript\s*>|<template\b.*?</template\s*>",
+    )
+    .expect("regex is always correct")
+});
+
+static HTML_HEADING_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?is)<h([1-6])\b[^>]*>(.*?)</h[1-6]\s*>").expect("regex is always correct")
+});
+
+/// How rows of XLSX, ODS and CSV files are grouped into chunks when they are extracted natively.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Default, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum SpreadsheetChunking {
+    /// Create one chunk per row with each value labeled by its column header.
+    #[default]
+    Row,
+    /// Create one chunk per sheet containing the sheet as a markdown table. Sheets larger than
+    /// 10,000 characters are split evenly.
+    Sheet,
+}
+
+/// File types which can be extracted without sending the file to Tika, pdf2md or Chunkr
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FileKind {
+    Docx,
+    Pptx,
+    Spreadsheet,
+    Csv,
+    Epub,
+    Html,
+    Markdown,
+    Text,
+}
+
+fn file_extension(file_name: &str) -> Option<String> {
+    file_name
+        .rsplit_once('.')
+        .map(|(_, extension)| extension.to_lowercase())
+}
+
+fn file_stem(file_name: &str) -> &str {
+    file_name
+        .rsplit_once('.')
+        .map(|(stem, _)| stem)
+        .unwrap_or(file_name)
+}
+
+fn decode_text(data: &[u8]) -> Option<&str> {
+    let data = data.strip_prefix(b"\xEF\xBB\xBF").unwrap_or(data);
+    std::str::from_utf8(data).ok()
+}
+
+fn sniff_zip_kind(data: &[u8]) -> Option<FileKind> {
+    let mut archive = ZipArchive::new(Cursor::new(data)).ok()?;
+
+    if let Ok(mut mimetype_file) = archive.by_name("mimetype") {
+        let mut mimetype = String::new();
+        (&mut mimetype_file)
+            .take(256)
+            .read_to_string(&mut mimetype)
+            .ok()?;
+        match mimetype.trim() {
+            "application/epub+zip" => return Some(FileKind::Epub),
+            "application/vnd.oasis.opendocument.spreadsheet" => return Some(FileKind::Spreadsheet),
+            _ => {}
+        }
+    }
+
+    let file_names = archive.file_names().collect::<Vec<&str>>();
+    if file_names.contains(&"word/document.xml") {
+        Some(FileKind::Docx)
+    } else if file_names.contains(&"ppt/presentation.xml") {
+        Some(FileKind::Pptx)
+    } else if file_names.contains(&"xl/workbook.xml") {
+        Some(FileKind::Spreadsheet)
+    } else {
+        None
+    }
+}
+
+fn looks_like_csv(text: &str) -> bool {
+    let field_counts = text
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .take(5)
+        .map(|line| parse_csv_line(line).len())
+        .collect::<Vec<usize>>();
+
+    field_counts.len() >= 3 && field_counts[0] > 1 && field_counts.iter().all_equal()
+}
+
+fn looks_like_markdown(text: &str) -> bool {
+    MARKDOWN_HEADING_RE.is_match(text) || text.contains("```") || text.contains("\n---\n")
+}
+
+/// Determines how a file should be extracted from its contents, using the extension of the file
+/// name only to break ties between text formats. Returns `None` for PDFs, legacy Office formats
+/// and any other file which should be sent to Tika instead.
+pub fn sniff_file_kind(file_name: &str, data: &[u8]) -> Option<FileKind> {
+    let extension = file_extension(file_name);
+
+    if data.starts_with(b"PK\x03\x04") {
+        return sniff_zip_kind(data);
+    }
+
+    if data.starts_with(b"\xD0\xCF\x11\xE0\xA1\xB1\x1A\xE1") {
+        // Legacy Office files share the OLE container so only spreadsheets can be told apart
+        return match extension.as_deref() {
+            Some("xls") => Some(FileKind::Spreadsheet),
+            _ => None,
+        };
+    }
+
+    let text = decode_text(data)?;
+    if text.contains('\0') || text.starts_with("%PDF") || text.starts_with("{\\rtf") {
+        return None;
+    }
+
+    let prefix = text
+        .trim_start()
+        .chars()
+        .take(1024)
+        .collect::<String>()
+        .to_lowercase();
+    if prefix.starts_with("<!doctype html")
+        || prefix.starts_with("<html")
+        || prefix.contains("<body")
+        || (prefix.starts_with("<?xml") && prefix.contains("<html"))
+    {
+        return Some(FileKind::Html);
+    }
+
+    match extension.as_deref() {
+        Some("html" | "htm" | "xhtml") => Some(FileKind::Html),
+        Some("md" | "markdown" | "mdx") => Some(FileKind::Markdown),
+        Some("csv") => Some(FileKind::Csv),
+        _ if prefix.starts_with("<?xml") => None,
+        Some("txt" | "text") if !looks_like_markdown(text) => Some(FileKind::Text),
+        _ if looks_like_markdown(text) => Some(FileKind::Markdown),
+        _ if looks_like_csv(text) => Some(FileKind::Csv),
+        _ => Some(FileKind::Text),
+    }
+}
+
+/// Reads an entry of an archive as text. Returns `None` if the entry does not exist or is not
+/// text and an error if it is larger than `MAX_ZIP_ENTRY_BYTES` once uncompressed.
+fn read_zip_entry(
+    archive: &mut ZipArchive<Cursor<&[u8]>>,
+    name: &str,
+) -> Result<Option<String>, ServiceError> {
+    let Ok(entry) = archive.by_name(name) else {
+        return Ok(None);
+    };
+
+    let too_large = || {
+        ServiceError::BadRequest(format!(
+            "{} is larger than {} bytes uncompressed",
+            name, MAX_ZIP_ENTRY_BYTES
+        ))
+    };
+    if entry.size() > MAX_ZIP_ENTRY_BYTES {
+        return Err(too_large());
+    }
+
+    // The declared size can not be trusted, so reading stops just past the limit
+    let mut content = String::new();
+    if entry
+        .take(MAX_ZIP_ENTRY_BYTES + 1)
+        .read_to_string(&mut content)
+        .is_err()
+    {
+        return Ok(None);
+    }
+    if content.len() as u64 > MAX_ZIP_ENTRY_BYTES {
+        return Err(too_large());
+    }
+
+    Ok(Some(content))
+}
+
+fn xml_error(err: quick_xml::Error) -> ServiceError {
+    ServiceError::BadRequest(format!("Could not parse XML in file: {}", err))
+}
+
+fn get_attribute(element: &BytesStart, name: &[u8]) -> Option<String> {
+    element
+        .attributes()
+        .flatten()
+        .find(|attribute| attribute.key.local_name().as_ref() == name)
+        .and_then(|attribute| attribute.unescape_value().ok())
+        .map(|value| value.to_string())
+}
+
+fn docx_heading_level(style: &str) -> Option<usize> {
+    let style = style.to_lowercase().replace(' ', "");
+    if style == "title" {
+        return Some(1);
+    }
+
+    style
+        .strip_prefix("heading")
+        .and_then(|level| level.parse::<usize>().ok())
+        .filter(|level| (1..=6).contains(level))
+}
+
+/// Converts the `word/document.xml` part of a DOCX file into markdown. Paragraphs using heading
+/// styles or outline levels become markdown headings and table rows are joined with `|`.
+fn docx_to_markdown(document_xml: &str) -> Result<String, ServiceError> {
+    let mut reader = XmlReader::from_str(document_xml);
+
+    let mut blocks: Vec<String> = vec![];
+    let mut paragraph = String::new();
+    let mut heading_level: Option<usize> = None;
+    let mut in_text = false;
+
+    let mut table_depth = 0;
+    let mut table_rows: Vec<String> = vec![];
+    let mut row_cells: Vec<String> = vec![];
+    let mut cell_paragraphs: Vec<String> = vec![];
+
+    loop {
+        match reader.read_event().map_err(xml_error)? {
+            Event::Start(element) if element.local_name().as_ref() == b"t" => in_text = true,
+            Event::Start(element) | Event::Empty(element) => match element.local_name().as_ref() {
+                b"tab" => paragraph.push(' '),
+                b"br" | b"cr" => paragraph.push('\n'),
+                b"tbl" => table_depth += 1,
+                b"pStyle" => {
+                    heading_level = get_attribute(&element, b"val")
+                        .and_then(|style| docx_heading_level(&style))
+                        .or(heading_level);
+                }
+                b"outlineLvl" if heading_level.is_none() => {
+                    heading_level = get_attribute(&element, b"val")
+                        .and_then(|level| level.parse::<usize>().ok())
+                        .filter(|level| *level < 6)
+                        .map(|level| level + 1);
+                }
+                _ => {}
+            },
+            Event::Text(text) if in_text => {
+                paragraph.push_str(&text.unescape().map_err(xml_error)?);
+            }
+            Event::End(element) => match element.local_name().as_ref() {
+                b"t" => in_text = false,
+                b"p" => {
+                    let text = paragraph.trim().to_string();
+                    if !text.is_empty() {
+                        if table_depth > 0 {
+                            cell_paragraphs.push(text.split_whitespace().join(" "));
+                        } else {
+                            match heading_level {
+                                Some(level) => blocks.push(format!(
+                                    "{} {}",
+                                    "#".repeat(level),
+                                    text.split_whitespace().join(" ")
+                                )),
+                                None => blocks.push(text),
+                            }
+                        }
+                    }
+                    paragraph.clear();
+                    heading_level = None;
+                }
+                b"tc" if table_depth == 1 => {
+                    row_cells.push(cell_paragraphs.drain(..).join(" "));
+                }
+                b"tr" if table_depth == 1 => {
+                    if row_cells.iter().any(|cell| !cell.is_empty()) {
+                        table_rows.push(row_cells.join(" | "));
+                    }
+                    row_cells.clear();
+                }
+                b"tbl" => {
+                    table_depth -= 1;
+                    if table_depth == 0 && !table_rows.is_empty() {
+                        blocks.push(table_rows.drain(..).join("\n"));
+                    }
+                }
+                _ => {}
+            },
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+
+    Ok(blocks.join("\n\n"))
+}
+
+fn docx_sections(data: &[u8]) -> Result<Vec<String>, ServiceError> {
+    let mut archive = ZipArchive::new(Cursor::new(data))
+        .map_err(|err| ServiceError::BadRequest(format!("Could not open DOCX file: {}", err)))?;
+    let document_xml = read_zip_entry(&mut archive, "word/document.xml")?.ok_or(
+        ServiceError::BadRequest("DOCX file does not have a document part".to_string()),
+    )?;
+
+    Ok(vec![docx_to_markdown(&document_xml)?])
+}
+
+/// Extracts the title and the remaining paragraphs of a PPTX slide. The title is taken from the
+/// shape using the title placeholder.
+fn pptx_slide_text(slide_xml: &str) -> Result<(Option<String>, Vec<String>), ServiceError> {
+    let mut reader = XmlReader::from_str(slide_xml);
+
+    let mut title: Option<String> = None;
+    let mut paragraphs: Vec<String> = vec![];
+    let mut shape_paragraphs: Vec<String> = vec![];
+    let mut paragraph = String::new();
+    let mut is_title_shape = false;
+    let mut in_text = false;
+
+    loop {
+        match reader.read_event().map_err(xml_error)? {
+            Event::Start(element) if element.local_name().as_ref() == b"t" => in_text = true,
+            Event::Start(element) | Event::Empty(element) => match element.local_name().as_ref() {
+                b"br" => paragraph.push(' '),
+                b"ph" => {
+                    is_title_shape = matches!(
+                        get_attribute(&element, b"type").as_deref(),
+                        Some("title" | "ctrTitle")
+                    );
+                }
+                _ => {}
+            },
+            Event::Text(text) if in_text => {
+                paragraph.push_str(&text.unescape().map_err(xml_error)?);
+            }
+            Event::End(element) => match element.local_name().as_ref() {
+                b"t" => in_text = false,
+                b"p" => {
+                    let text = paragraph.split_whitespace().join(" ");
+                    if !text.is_empty() {
+                        shape_paragraphs.push(text);
+                    }
+                    paragraph.clear();
+                }
+                b"sp" | b"graphicFrame" => {
+                    if is_title_shape && title.is_none() && !shape_paragraphs.is_empty() {
+                        title = Some(shape_paragraphs.drain(..).join(" "));
+                    } else {
+                        paragraphs.append(&mut shape_paragraphs);
+                    }
+                    is_title_shape = false;
+                }
+                _ => {}
+            },
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+    paragraphs.append(&mut shape_paragraphs);
+
+    Ok((title, paragraphs))
+}
+
+/// Creates one section per slide headed by the slide number and its title
+fn pptx_sections(data: &[u8]) -> Result<Vec<String>, ServiceError> {
+    let mut archive = ZipArchive::new(Cursor::new(data))
+        .map_err(|err| ServiceError::BadRequest(format!("Could not open PPTX file: {}", err)))?;
+
+    let slide_names = archive
+        .file_names()
+        .filter_map(|name| {
+            let slide_number = PPTX_SLIDE_RE.captures(name)?[1].parse::<usize>().ok()?;
+            Some((slide_number, name.to_string()))
+        })
+        .sorted()
+        .collect::<Vec<(usize, String)>>();
+
+    let mut sections = vec![];
+    for (slide_number, slide_name) in slide_names {
+        let Some(slide_xml) = read_zip_entry(&mut archive, &slide_name)? else {
+            continue;
+        };
+        let (title, paragraphs) = pptx_slide_text(&slide_xml)?;
+        if title.is_none() && paragraphs.is_empty() {
+            continue;
+        }
+
+        let heading = match title {
+            Some(title) => format!("# Slide {}: {}", slide_number, title),
+            None => format!("# Slide {}", slide_number),
+        };
+        sections.push(std::iter::once(heading).chain(paragraphs).join("\n\n"));
+    }
+
+    Ok(sections)
+}
+
+/// Creates sections from rows of a table where the first non-empty row is the header
+fn tabular_sections(
+    title: &str,
+    rows: Vec<Vec<String>>,
+    spreadsheet_chunking: SpreadsheetChunking,
+) -> Vec<String> {
+    let mut rows = rows
+        .into_iter()
+        .filter(|row| row.iter().any(|value| !value.trim().is_empty()));
+    let Some(header_row) = rows.next() else {
+        return vec![];
+    };
+
+    let headers = header_row
+        .iter()
+        .enumerate()
+        .map(|(i, header)| match header.trim() {
+            "" => format!("Column {}", i + 1),
+            header => header.to_string(),
+        })
+        .collect::<Vec<String>>();
+
+    match spreadsheet_chunking {
+        SpreadsheetChunking::Row => rows
+            .enumerate()
+            .map(|(i, row)| {
+                let fields = headers
+                    .iter()
+                    .zip(row.iter())
+                    .filter(|(_, value)| !value.trim().is_empty())
+                    .map(|(header, value)| format!("{}: {}", header, value.trim()));
+                std::iter::once(format!("## {} row {}", title, i + 1))
+                    .chain(fields)
+                    .join("\n")
+            })
+            .collect(),
+        SpreadsheetChunking::Sheet => {
+            let table_row = |values: &[String]| {
+                format!(
+                    "| {} |",
+                    values
+                        .iter()
+                        .map(|value| value.split_whitespace().join(" ").replace('|', "\\|"))
+                        .join(" | ")
+                )
+            };
+
+            let table = std::iter::once(table_row(&headers))
+                .chain(std::iter::once(format!(
+                    "|{}",
+                    " --- |".repeat(headers.len())
+                )))
+                .chain(rows.map(|row| table_row(&row)))
+                .join("\n");
+            vec![format!("# {}\n{}", title, table)]
+        }
+    }
+}
+
+fn spreadsheet_sections(
+    data: &[u8],
+    spreadsheet_chunking: SpreadsheetChunking,
+) -> Result<Vec<String>, ServiceError> {
+    let mut workbook = open_workbook_auto_from_rs(Cursor::new(data.to_vec()))
+        .map_err(|err| ServiceError::BadRequest(format!("Could not open spreadsheet: {}", err)))?;
+
+    let mut sections = vec![];
+    for sheet_name in workbook.sheet_names() {
+        let range = workbook.worksheet_range(&sheet_name).map_err(|err| {
+            ServiceError::BadRequest(format!("Could not read sheet {}: {}", sheet_name, err))
+        })?;
+
+        let rows = range
+            .rows()
+            .map(|row| {
+                row.iter()
+                    .map(|cell| match spreadsheet_cell_to_value(cell) {
+                        serde_json::Value::String(value) => value,
+                        serde_json::Value::Null => String::new(),
+                        value => value.to_string(),
+                    })
+                    .collect::<Vec<String>>()
+            })
+            .collect::<Vec<Vec<String>>>();
+
+        sections.extend(tabular_sections(&sheet_name, rows, spreadsheet_chunking));
+    }
+
+    Ok(sections)
+}
+
+fn csv_sections(
+    file_name: &str,
+    text: &str,
+    spreadsheet_chunking: SpreadsheetChunking,
+) -> Vec<String> {
+    let rows = text
+        .lines()
+        .map(parse_csv_line)
+        .collect::<Vec<Vec<String>>>();
+    tabular_sections(file_stem(file_name), rows, spreadsheet_chunking)
+}
+
+fn html_paragraphs(html: &str) -> Vec<String> {
+    let html = HTML_CELL_END_RE.replace_all(html, "$0 ");
+    let html = HTML_BLOCK_END_RE.replace_all(&html, "$0\n\n");
+    let text = Html::parse_fragment(&html)
+        .root_element()
+        .text()
+        .collect::<String>();
+
+    PARAGRAPH_BREAK_RE
+        .split(&text)
+        .map(|paragraph| paragraph.split_whitespace().join(" "))
+        .filter(|paragraph| !paragraph.is_empty())
+        .collect()
+}
+
+/// Converts HTML into markdown paragraphs, keeping `h1` to `h6` elements as markdown headings so
+/// the result can be split by heading
+pub fn html_to_markdown(html: &str) -> String {
+    let html = HTML_IGNORED_RE.replace_all(html, "");
+
+    let mut blocks = vec![];
+    let mut last_end = 0;
+    for captures in HTML_HEADING_RE.captures_iter(&html) {
+        let heading_match = captures.get(0).expect("capture 0 always exists");
+        blocks.extend(html_paragraphs(&html[last_end..heading_match.start()]));
+
+        let heading = html_paragraphs(&captures[2]).join(" ");
+        if !heading.is_empty() {
+            blocks.push(format!(
+                "{} {}",
+                "#".repeat(captures[1].parse::<usize>().unwrap_or(1)),
+                heading
+            ));
+        }
+        last_end = heading_match.end();
+    }
+    blocks.extend(html_paragraphs(&html[last_end..]));
+
+    blocks.join("\n\n")
+}
+
+fn resolve_epub_path(base_dir: &str, href: &str) -> String {
+    let href = href.split('#').next().unwrap_or_default();
+    let mut components: Vec<&str> = base_dir.split('/').filter(|c| !c.is_empty()).collect();
+    for component in href.split('/') {
+        match component {
+            "" | "." => {}
+            ".." => {
+                components.pop();
+            }
+            component => components.push(component),
+        }
+    }
+
+    components.join("/").replace("%20", " ")
+}
+
+/// Creates one section per chapter of an EPUB file in the reading order of the spine
+fn epub_sections(data: &[u8]) -> Result<Vec<String>, ServiceError> {
+    let mut archive = ZipArchive::new(Cursor::new(data))
+        .map_err(|err| ServiceError::BadRequest(format!("Could not open EPUB file: {}", err)))?;
+
+    let container_xml = read_zip_entry(&mut archive, "META-INF/container.xml")?.ok_or(
+        ServiceError::BadRequest("EPUB file does not have a container".to_string()),
+    )?;
+    let mut reader = XmlReader::from_str(&container_xml);
+    let mut package_path = None;
+    loop {
+        match reader.read_event().map_err(xml_error)? {
+            Event::Start(element) | Event::Empty(element)
+                if element.local_name().as_ref() == b"rootfile" =>
+            {
+                package_path = get_attribute(&element, b"full-path");
+                break;
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+    let package_path = package_path.ok_or(ServiceError::BadRequest(
+        "EPUB container does not reference a package".to_string(),
+    ))?;
+
+    let package_xml = read_zip_entry(&mut archive, &package_path)?.ok_or(
+        ServiceError::BadRequest("EPUB file does not have a package".to_string()),
+    )?;
+    let mut reader = XmlReader::from_str(&package_xml);
+    let mut manifest: Vec<(String, String, String)> = vec![];
+    let mut spine: Vec<String> = vec![];
+    loop {
+        match reader.read_event().map_err(xml_error)? {
+            Event::Start(element) | Event::Empty(element) => match element.local_name().as_ref() {
+                b"item" => {
+                    if let (Some(id), Some(href)) = (
+                        get_attribute(&element, b"id"),
+                        get_attribute(&element, b"href"),
+                    ) {
+                        let media_type = get_attribute(&element, b"media-type").unwrap_or_default();
+                        manifest.push((id, href, media_type));
+                    }
+                }
+                b"itemref" => {
+                    if let Some(idref) = get_attribute(&element, b"idref") {
+                        spine.push(idref);
+                    }
+                }
+                _ => {}
+            },
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+
+    let base_dir = package_path
+        .rsplit_once('/')
+        .map(|(dir, _)| dir)
+        .unwrap_or_default();
+
+    let mut sections = vec![];
+    for idref in spine {
+        let Some((_, href, media_type)) = manifest.iter().find(|(id, _, _)| *id == idref) else {
+            continue;
+        };
+        if !media_type.contains("html") {
+            continue;
+        }
+
+        let Some(chapter_html) = read_zip_entry(&mut archive, &resolve_epub_path(base_dir, href))?
+        else {
+            continue;
+        };
+        let chapter = html_to_markdown(&chapter_html);
+        if !chapter.is_empty() {
+            sections.push(chapter);
+        }
+    }
+
+    Ok(sections)
+}
+
+/// Extracts a file into markdown sections without an external service. Headings are preserved as
+/// markdown headings and no chunk should span more than one section, so slides, sheets, rows and
+/// chapters can be kept apart. Returns `None` if the file is not of a kind which can be extracted
+/// natively.
+pub fn extract_file_sections(
+    file_name: &str,
+    data: &[u8],
+    spreadsheet_chunking: SpreadsheetChunking,
+) -> Result<Option<Vec<String>>, ServiceError> {
+    let Some(file_kind) = sniff_file_kind(file_name, data) else {
+        return Ok(None);
+    };
+    log::info!("Extracting {:?} file {} natively", file_kind, file_name);
+
+    let text = || {
+        decode_text(data).ok_or(ServiceError::BadRequest(
+            "File is not valid UTF-8".to_string(),
+        ))
+    };
+
+    let sections = match file_kind {
+        FileKind::Docx => docx_sections(data)?,
+        FileKind::Pptx => pptx_sections(data)?,
+        FileKind::Spreadsheet => spreadsheet_sections(data, spreadsheet_chunking)?,
+        FileKind::Csv => csv_sections(file_name, text()?, spreadsheet_chunking),
+        FileKind::Epub => epub_sections(data)?,
+        FileKind::Html => vec![html_to_markdown(text()?)],
+        FileKind::Markdown | FileKind::Text => vec![text()?.to_string()],
+    };
+
+    Ok(Some(
+        sections
+            .into_iter()
+            .filter(|section| !section.trim().is_empty())
+            .collect(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use zip::write::SimpleFileOptions;
+
+    fn zip_with_entries(entries: &[(&str, &str)]) -> Vec<u8> {
+        let mut writer = zip::ZipWriter::new(Cursor::new(Vec::new()));
+        for (name, content) in entries {
+            writer
+                .start_file(*name, SimpleFileOptions::default())
+                .unwrap();
+            writer.write_all(content.as_bytes()).unwrap();
+        }
+        writer.finish().unwrap().into_inner()
+    }
+
+    #[test]
+    fn sniff_file_kind_uses_contents_before_extension() {
+        let docx = zip_with_entries(&[("word/document.xml", "<w:document/>")]);
+        assert_eq!(sniff_file_kind("report.pdf", &docx), Some(FileKind::Docx));
+
+        let pptx = zip_with_entries(&[("ppt/presentation.xml", "<p:presentation/>")]);
+        assert_eq!(sniff_file_kind("deck", &pptx), Some(FileKind::Pptx));
+
+        let epub = zip_with_entries(&[("mimetype", "application/epub+zip")]);
+        assert_eq!(sniff_file_kind("book.zip", &epub), Some(FileKind::Epub));
+
+        assert_eq!(
+            sniff_file_kind("page.txt", b"<!DOCTYPE html><html><body>Hi</body></html>"),
+            Some(FileKind::Html)
+        );
+        assert_eq!(sniff_file_kind("doc.txt", b"%PDF-1.7\n"), None);
+        assert_eq!(
+            sniff_file_kind("legacy.doc", b"\xD0\xCF\x11\xE0\xA1\xB1\x1A\xE1"),
+            None
+        );
+    }
+
+    #[test]
+    fn sniff_file_kind_detects_text_formats() {
+        assert_eq!(
+            sniff_file_kind("notes", b"# Title\n\nSome text"),
+            Some(FileKind::Markdown)
+        );
+        assert_eq!(
+            sniff_file_kind("data", b"a,b,c\n1,2,3\n4,5,6\n"),
+            Some(FileKind::Csv)
+        );
+        assert_eq!(
+            sniff_file_kind("plain.txt", b"Just some words."),
+            Some(FileKind::Text)
+        );
+        assert_eq!(sniff_file_kind("binary", b"abc\0def"), None);
+    }
+
+    #[test]
+    fn docx_to_markdown_keeps_headings_and_tables() {
+        let document_xml = r#"<w:document xmlns:w="w"><w:body>
+            <w:p><w:pPr><w:pStyle w:val="Heading2"/></w:pPr><w:r><w:t>Overview</w:t></w:r></w:p>
+            <w:p><w:r><w:t>First</w:t></w:r><w:r><w:tab/><w:t>paragraph</w:t></w:r></w:p>
+            <w:tbl>
+                <w:tr><w:tc><w:p><w:r><w:t>Name</w:t></w:r></w:p></w:tc><w:tc><w:p><w:r><w:t>Price</w:t></w:r></w:p></w:tc></w:tr>
+                <w:tr><w:tc><w:p><w:r><w:t>Apple</w:t></w:r></w:p></w:tc><w:tc><w:p><w:r><w:t>1</w:t></w:r></w:p></w:tc></w:tr>
+            </w:tbl>
+        </w:body></w:document>"#;
+
+        assert_eq!(
+            docx_to_markdown(document_xml).unwrap(),
+            "## Overview\n\nFirst paragraph\n\nName | Price\nApple | 1"
+        );
+    }
+
+    #[test]
+    fn pptx_slide_text_separates_title() {
+        let slide_xml = r#"<p:sld xmlns:p="p" xmlns:a="a"><p:cSld><p:spTree>
+            <p:sp><p:nvSpPr><p:nvPr><p:ph type="title"/></p:nvPr></p:nvSpPr>
+                <p:txBody><a:p><a:r><a:t>Quarterly</a:t></a:r><a:br/><a:r><a:t>results</a:t></a:r></a:p></p:txBody></p:sp>
+            <p:sp><p:txBody>
+                <a:p><a:r><a:t>Revenue grew</a:t></a:r></a:p>
+                <a:p><a:r><a:t>Costs fell</a:t></a:r></a:p>
+            </p:txBody></p:sp>
+        </p:spTree></p:cSld></p:sld>"#;
+
+        let (title, paragraphs) = pptx_slide_text(slide_xml).unwrap();
+        assert_eq!(title.as_deref(), Some("Quarterly results"));
+        assert_eq!(paragraphs, vec!["Revenue grew", "Costs fell"]);
+    }
+
+    #[test]
+    fn read_zip_entry_reads_text_entries() {
+        let data = zip_with_entries(&[("a.xml", "<a/>")]);
+        let mut archive = ZipArchive::new(Cursor::new(data.as_slice())).unwrap();
+
+        assert_eq!(
+            read_zip_entry(&mut archive, "a.xml").unwrap().as_deref(),
+            Some("<a/>")
+        );
+        assert_eq!(read_zip_entry(&mut archive, "missing.xml").unwrap(), None);
+    }
+}
@@ -1,11 +1,15 @@
-use super::chunk_operator::{create_chunk_metadata, get_row_count_for_organization_id_query};
+use super::chunk_operator::{
+    create_chunk_metadata, delete_chunk_metadata_query, get_row_count_for_organization_id_query,
+    metadata_with_page_location, metadata_with_source_position,
+};
+use super::dedup_operator::content_hash;
 use super::group_operator::{
     create_group_from_file_query, create_groups_query, delete_group_by_file_id_query,
 };
-use super::parse_operator::{build_chunking_regex, coarse_doc_chunker};
+use super::parse_operator::{build_chunking_regex, coarse_doc_chunker, coarse_remove_large_chunks};
 use crate::data::models::{
     ChunkGroup, Dataset, DatasetAndOrgWithSubAndPlan, DatasetConfiguration, File, FileDTO,
-    FileWithChunkGroups, Pool,
+    FileVersion, FileWithChunkGroups, Pool, RedisPool,
 };
 use crate::errors::ServiceError;
 use crate::get_env;
@@ -17,9 +21,11 @@ use diesel::dsl::sql;
 use diesel::pg::sql_types;
 use diesel::prelude::*;
 use diesel::sql_types::BigInt;
-use diesel_async::RunQueryDsl;
+use diesel_async::scoped_futures::ScopedFutureExt;
+use diesel_async::{AsyncConnection, RunQueryDsl};
 use regex::Regex;
 use s3::{creds::Credentials, Bucket, Region};
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use url::form_urlencoded;
 
@@ -158,18 +164,21 @@ pub async fn create_file_query(
         .await
         .map_err(|_| ServiceError::BadRequest("Could not get database connection".to_string()))?;
 
-    let new_file = File::from_details(
-        Some(file_id),
-        &upload_file_data.file_name,
-        file_size,
-        upload_file_data
-            .tag_set
-            .map(|tag_set| tag_set.into_iter().map(Some).collect()),
-        upload_file_data.metadata,
-        upload_file_data.link,
-        upload_file_data.time_stamp,
-        dataset_id,
-    );
+    let new_file = File {
+        tracking_id: upload_file_data.file_tracking_id,
+        ..File::from_details(
+            Some(file_id),
+            &upload_file_data.file_name,
+            file_size,
+            upload_file_data
+                .tag_set
+                .map(|tag_set| tag_set.into_iter().map(Some).collect()),
+            upload_file_data.metadata,
+            upload_file_data.link,
+            upload_file_data.time_stamp,
+            dataset_id,
+        )
+    };
 
     let created_file: File = diesel::insert_into(files_columns::files)
         .values(&new_file)
@@ -226,6 +235,35 @@ pub fn preprocess_file_to_chunks(
     Ok(chunk_htmls)
 }
 
+/// Chunks the markdown sections produced by native extraction. Sections with headings are split
+/// by heading while sections without any are chunked the same way as text extracted by Tika.
+pub fn preprocess_extracted_file_to_chunks(
+    sections: Vec<String>,
+    upload_file_data: UploadFileReqPayload,
+) -> Result<Vec<String>, ServiceError> {
+    let mut chunks = vec![];
+
+    for section in sections {
+        if section.lines().any(|line| line.trim().starts_with('#')) {
+            chunks.extend(coarse_remove_large_chunks(split_markdown_by_headings(
+                &section,
+            )));
+        } else {
+            let escaped_section = section
+                .replace('&', "&amp;")
+                .replace('<', "&lt;")
+                .replace('>', "&gt;");
+            chunks.extend(preprocess_file_to_chunks(
+                escaped_section,
+                upload_file_data.clone(),
+            )?);
+        }
+    }
+
+    chunks.retain(|chunk| !chunk.trim().is_empty());
+    Ok(chunks)
+}
+
 pub fn split_markdown_by_headings(markdown_text: &str) -> Vec<String> {
     let lines: Vec<&str> = markdown_text
         .trim()
@@ -281,6 +319,10 @@ pub fn split_markdown_by_headings(markdown_text: &str) -> Vec<String> {
     chunks
 }
 
+/// Queues the chunks created from a file for ingestion. When the file is being processed again,
+/// new chunks whose tracking id is held by one of the `previous_chunks` update that chunk in place
+/// if its content changed and are not created otherwise. New chunks without a tracking id whose
+/// content matches a previous chunk are not created either, so the existing chunk keeps its id.
 #[allow(clippy::too_many_arguments)]
 pub async fn create_file_chunks(
     created_file_id: uuid::Uuid,
@@ -288,10 +330,13 @@ pub async fn create_file_chunks(
     mut chunks: Vec<ChunkReqPayload>,
     dataset_org_plan_sub: DatasetAndOrgWithSubAndPlan,
     group_id: Option<uuid::Uuid>,
+    previous_chunks: Option<&mut PreviousFileChunks>,
     pool: web::Data<Pool>,
+    redis_pool: web::Data<RedisPool>,
     broccoli_queue: BroccoliQueue,
 ) -> Result<(), ServiceError> {
     let name = upload_file_data.file_name.clone();
+    let is_reprocess = previous_chunks.is_some();
 
     if upload_file_data
         .clone()
@@ -368,6 +413,30 @@ pub async fn create_file_chunks(
         });
     }
 
+    // pdf2md pages arrive across several calls, so chunks are ordered by page first and carry
+    // the location on the page they were found at
+    chunks.iter_mut().enumerate().for_each(|(i, chunk)| {
+        chunk.metadata = metadata_with_page_location(
+            chunk.metadata.clone(),
+            chunk.chunk_html.as_deref().unwrap_or_default(),
+        );
+        let page_num = chunk
+            .metadata
+            .as_ref()
+            .and_then(|metadata| metadata.get("page_num"))
+            .and_then(|page_num| page_num.as_i64())
+            .unwrap_or(0);
+        chunk.metadata = metadata_with_source_position(
+            chunk.metadata.clone(),
+            &created_file_id.to_string(),
+            page_num * 1_000_000 + i as i64,
+        );
+    });
+
+    if let Some(previous_chunks) = previous_chunks {
+        previous_chunks.retain_changed_chunks(&mut chunks);
+    }
+
     let chunk_count = get_row_count_for_organization_id_query(
         dataset_org_plan_sub.organization.organization.id,
         pool.clone(),
@@ -413,6 +482,18 @@ pub async fn create_file_chunks(
             continue;
         }
 
+        if is_reprocess {
+            add_pending_file_chunks_query(
+                created_file_id,
+                chunk_metadatas
+                    .iter()
+                    .map(|chunk_metadata| chunk_metadata.id)
+                    .collect(),
+                redis_pool.clone(),
+            )
+            .await?;
+        }
+
         broccoli_queue
             .publish(
                 "ingestion",
@@ -618,6 +699,15 @@ pub async fn delete_file_query(
         .await
         .map_err(|_| ServiceError::BadRequest("Could not delete file from S3".to_string()))?;
 
+    for file_version in get_file_versions_query(file_uuid, dataset.id, pool.clone()).await? {
+        bucket
+            .delete_object(file_version.id.to_string())
+            .await
+            .map_err(|_| {
+                ServiceError::BadRequest("Could not delete file version from S3".to_string())
+            })?;
+    }
+
     let dataset_id = dataset.clone().id;
 
     if delete_chunks.is_some_and(|delete_chunks| delete_chunks) {
@@ -647,6 +737,772 @@ pub async fn delete_file_query(
     Ok(())
 }
 
+/// Gets a file by its id without creating a signed url for it
+pub async fn get_file_by_id_query(
+    file_id: uuid::Uuid,
+    dataset_id: uuid::Uuid,
+    pool: web::Data<Pool>,
+) -> Result<File, ServiceError> {
+    use crate::data::schema::files::dsl as files_columns;
+
+    let mut conn = pool
+        .get()
+        .await
+        .map_err(|_| ServiceError::BadRequest("Could not get database connection".to_string()))?;
+
+    files_columns::files
+        .filter(files_columns::id.eq(file_id))
+        .filter(files_columns::dataset_id.eq(dataset_id))
+        .get_result(&mut conn)
+        .await
+        .map_err(|_| ServiceError::NotFound("File with specified id not found".to_string()))
+}
+
+/// Gets the group which was created for a file, which is the first group linked to it
+pub async fn get_file_group_id_query(
+    file_id: uuid::Uuid,
+    pool: web::Data<Pool>,
+) -> Result<Option<uuid::Uuid>, ServiceError> {
+    use crate::data::schema::groups_from_files::dsl as groups_from_files_columns;
+
+    let mut conn = pool
+        .get()
+        .await
+        .map_err(|_| ServiceError::BadRequest("Could not get database connection".to_string()))?;
+
+    groups_from_files_columns::groups_from_files
+        .filter(groups_from_files_columns::file_id.eq(file_id))
+        .select(groups_from_files_columns::group_id)
+        .order(groups_from_files_columns::created_at.asc())
+        .first::<uuid::Uuid>(&mut conn)
+        .await
+        .optional()
+        .map_err(|err| {
+            log::error!("Could not get group for file {:?}", err);
+            ServiceError::BadRequest("Could not get group for file".to_string())
+        })
+}
+
+/// The chunks a file held before it was processed again. Chunks with a tracking id are matched
+/// to new chunks by it, the others are grouped by the hash of their content so that chunks whose
+/// content did not change can be kept instead of being created again.
+#[derive(Debug, Default)]
+pub struct PreviousFileChunks {
+    chunk_ids_by_hash: HashMap<String, Vec<uuid::Uuid>>,
+    chunks_by_tracking_id: HashMap<String, (uuid::Uuid, String)>,
+}
+
+impl PreviousFileChunks {
+    fn from_chunks(
+        chunks: impl IntoIterator<Item = (uuid::Uuid, Option<String>, Option<String>)>,
+    ) -> Self {
+        let mut previous_chunks = PreviousFileChunks::default();
+        for (chunk_id, tracking_id, chunk_html) in chunks {
+            let hash = content_hash(chunk_html.as_deref().unwrap_or_default());
+            match tracking_id {
+                Some(tracking_id) => {
+                    previous_chunks
+                        .chunks_by_tracking_id
+                        .insert(tracking_id, (chunk_id, hash));
+                }
+                None => previous_chunks
+                    .chunk_ids_by_hash
+                    .entry(hash)
+                    .or_default()
+                    .push(chunk_id),
+            }
+        }
+
+        previous_chunks
+    }
+
+    /// Drops the new chunks which match a previous chunk, keeping that chunk. New chunks whose
+    /// tracking id is held by a previous chunk update it in place if their content changed.
+    pub fn retain_changed_chunks(&mut self, chunks: &mut Vec<ChunkReqPayload>) {
+        chunks.retain_mut(|chunk| {
+            let hash = content_hash(chunk.chunk_html.as_deref().unwrap_or_default());
+            match chunk.tracking_id.as_deref() {
+                Some(tracking_id) => match self.keep_tracked_chunk(tracking_id) {
+                    Some(previous_hash) => {
+                        chunk.upsert_by_tracking_id = Some(true);
+                        previous_hash != hash
+                    }
+                    None => true,
+                },
+                None => !self.keep_chunk(&hash),
+            }
+        });
+    }
+
+    /// Keeps one of the previous chunks without a tracking id with the given content hash.
+    /// Returns false if there are none left to keep.
+    fn keep_chunk(&mut self, hash: &str) -> bool {
+        self.chunk_ids_by_hash
+            .get_mut(hash)
+            .and_then(|chunk_ids| chunk_ids.pop())
+            .is_some()
+    }
+
+    /// Keeps the previous chunk with the given tracking id, returning the hash of its content.
+    /// Returns `None` if no previous chunk has the tracking id.
+    fn keep_tracked_chunk(&mut self, tracking_id: &str) -> Option<String> {
+        self.chunks_by_tracking_id
+            .remove(tracking_id)
+            .map(|(_, hash)| hash)
+    }
+
+    /// The ids of the previous chunks whose content is no longer in the file
+    pub fn removed_chunk_ids(self) -> Vec<uuid::Uuid> {
+        self.chunk_ids_by_hash
+            .into_values()
+            .flatten()
+            .chain(
+                self.chunks_by_tracking_id
+                    .into_values()
+                    .map(|(chunk_id, _)| chunk_id),
+            )
+            .collect()
+    }
+}
+
+/// Gets the chunks created from a file, which are the chunks in the groups linked to it whose
+/// `source_document` is the file. Chunks added to those groups by hand are left alone.
+pub async fn get_previous_file_chunks_query(
+    file_id: uuid::Uuid,
+    pool: web::Data<Pool>,
+) -> Result<PreviousFileChunks, ServiceError> {
+    use crate::data::schema::chunk_group_bookmarks::dsl as chunk_group_bookmarks_columns;
+    use crate::data::schema::chunk_metadata::dsl as chunk_metadata_columns;
+    use crate::data::schema::groups_from_files::dsl as groups_from_files_columns;
+
+    let mut conn = pool
+        .get()
+        .await
+        .map_err(|_| ServiceError::BadRequest("Could not get database connection".to_string()))?;
+
+    let chunks =
+        chunk_metadata_columns::chunk_metadata
+            .inner_join(chunk_group_bookmarks_columns::chunk_group_bookmarks.on(
+                chunk_group_bookmarks_columns::chunk_metadata_id.eq(chunk_metadata_columns::id),
+            ))
+            .filter(
+                chunk_group_bookmarks_columns::group_id.eq_any(
+                    groups_from_files_columns::groups_from_files
+                        .filter(groups_from_files_columns::file_id.eq(file_id))
+                        .select(groups_from_files_columns::group_id),
+                ),
+            )
+            .filter(
+                sql::<diesel::sql_types::Bool>("chunk_metadata.metadata->>'source_document' = ")
+                    .bind::<diesel::sql_types::Text, _>(file_id.to_string()),
+            )
+            .select((
+                chunk_metadata_columns::id,
+                chunk_metadata_columns::tracking_id,
+                chunk_metadata_columns::chunk_html,
+            ))
+            .distinct()
+            .load::<(uuid::Uuid, Option<String>, Option<String>)>(&mut conn)
+            .await
+            .map_err(|err| {
+                log::error!("Could not get chunks for file {:?}", err);
+                ServiceError::BadRequest("Could not get chunks for file".to_string())
+            })?;
+
+    Ok(PreviousFileChunks::from_chunks(chunks))
+}
+
+fn get_pending_file_chunks_key(file_id: uuid::Uuid) -> String {
+    format!("file_chunks_pending:{}", file_id)
+}
+
+fn get_removed_file_chunks_key(file_id: uuid::Uuid) -> String {
+    format!("file_chunks_removed:{}", file_id)
+}
+
+fn get_queued_file_reprocess_key(file_id: uuid::Uuid) -> String {
+    format!("file_reprocess_queued:{}", file_id)
+}
+
+/// Reprocesses which are never picked up by the file worker stop blocking new ones after a day
+const FILE_REPROCESS_QUEUED_TTL_SECONDS: u64 = 24 * 60 * 60;
+
+/// Reserves a file for reprocessing unless a reprocess of it is already queued or its new chunks
+/// are still being ingested
+const RESERVE_FILE_REPROCESS_SCRIPT: &str = r#"
+if redis.call('SCARD', KEYS[1]) > 0 or redis.call('EXISTS', KEYS[2]) == 1 then
+    return 0
+end
+redis.call('SET', KEYS[2], 1, 'EX', ARGV[1])
+return 1
+"#;
+
+/// Replacements which never finish ingesting are forgotten after a week, keeping the old chunks
+const FILE_CHUNK_REPLACEMENT_TTL_SECONDS: u64 = 7 * 24 * 60 * 60;
+
+/// Takes the chunks to remove for a file once none of its new chunks are left to ingest, so only
+/// one caller ever gets them
+const CLAIM_REMOVED_FILE_CHUNKS_SCRIPT: &str = r#"
+if redis.call('SCARD', KEYS[1]) > 0 then
+    return false
+end
+local removed = redis.call('GET', KEYS[2])
+if removed then
+    redis.call('DEL', KEYS[2])
+end
+return removed
+"#;
+
+#[derive(Debug, Serialize, Deserialize)]
+struct RemovedFileChunks {
+    chunk_ids: Vec<uuid::Uuid>,
+    reprocessed_at: chrono::NaiveDateTime,
+}
+
+/// Records new chunks of a reprocessed file which are about to be queued for ingestion, so the
+/// chunks they replace are only removed once all of them have been ingested
+async fn add_pending_file_chunks_query(
+    file_id: uuid::Uuid,
+    chunk_ids: Vec<uuid::Uuid>,
+    redis_pool: web::Data<RedisPool>,
+) -> Result<(), ServiceError> {
+    if chunk_ids.is_empty() {
+        return Ok(());
+    }
+
+    let mut redis_conn = redis_pool
+        .get()
+        .await
+        .map_err(|err| ServiceError::BadRequest(err.to_string()))?;
+
+    let pending_key = get_pending_file_chunks_key(file_id);
+    redis::pipe()
+        .atomic()
+        .cmd("SADD")
+        .arg(&pending_key)
+        .arg(
+            chunk_ids
+                .iter()
+                .map(|chunk_id| chunk_id.to_string())
+                .collect::<Vec<String>>(),
+        )
+        .ignore()
+        .cmd("EXPIRE")
+        .arg(&pending_key)
+        .arg(FILE_CHUNK_REPLACEMENT_TTL_SECONDS)
+        .ignore()
+        .query_async::<_, ()>(&mut *redis_conn)
+        .await
+        .map_err(|err| ServiceError::BadRequest(err.to_string()))?;
+
+    Ok(())
+}
+
+/// Reserves a file for reprocessing. A reprocess reads the chunks the file holds when it starts,
+/// so one which starts before the new chunks of the previous one are ingested would never see
+/// them and both sets of chunks would be kept. Fails while a reprocess of the file is queued or
+/// its new chunks are being ingested.
+pub async fn reserve_file_reprocess_query(
+    file_id: uuid::Uuid,
+    redis_pool: web::Data<RedisPool>,
+) -> Result<(), ServiceError> {
+    let mut redis_conn = redis_pool
+        .get()
+        .await
+        .map_err(|err| ServiceError::BadRequest(err.to_string()))?;
+
+    let reserved: bool = redis::Script::new(RESERVE_FILE_REPROCESS_SCRIPT)
+        .key(get_pending_file_chunks_key(file_id))
+        .key(get_queued_file_reprocess_key(file_id))
+        .arg(FILE_REPROCESS_QUEUED_TTL_SECONDS)
+        .invoke_async(&mut *redis_conn)
+        .await
+        .map_err(|err| ServiceError::BadRequest(err.to_string()))?;
+
+    if !reserved {
+        return Err(ServiceError::BadRequest(
+            "File is still being processed, try again once its chunks have been ingested"
+                .to_string(),
+        ));
+    }
+
+    Ok(())
+}
+
+/// Releases the reservation of a file for reprocessing. The file worker releases it once the new
+/// chunks are pending, from then on the pending chunks block new reprocesses until ingested.
+pub async fn release_file_reprocess_query(
+    file_id: uuid::Uuid,
+    redis_pool: web::Data<RedisPool>,
+) -> Result<(), ServiceError> {
+    let mut redis_conn = redis_pool
+        .get()
+        .await
+        .map_err(|err| ServiceError::BadRequest(err.to_string()))?;
+
+    redis::cmd("DEL")
+        .arg(get_queued_file_reprocess_key(file_id))
+        .query_async::<_, ()>(&mut *redis_conn)
+        .await
+        .map_err(|err| ServiceError::BadRequest(err.to_string()))?;
+
+    Ok(())
+}
+
+/// Deletes the chunks a reprocessed file no longer has if its new chunks have all been ingested
+/// and no other caller has deleted them already
+async fn delete_claimed_removed_file_chunks_query(
+    file_id: uuid::Uuid,
+    dataset: Dataset,
+    pool: web::Data<Pool>,
+    redis_pool: web::Data<RedisPool>,
+) -> Result<(), ServiceError> {
+    let mut redis_conn = redis_pool
+        .get()
+        .await
+        .map_err(|err| ServiceError::BadRequest(err.to_string()))?;
+
+    let removed_chunks: Option<String> = redis::Script::new(CLAIM_REMOVED_FILE_CHUNKS_SCRIPT)
+        .key(get_pending_file_chunks_key(file_id))
+        .key(get_removed_file_chunks_key(file_id))
+        .invoke_async(&mut *redis_conn)
+        .await
+        .map_err(|err| ServiceError::BadRequest(err.to_string()))?;
+    drop(redis_conn);
+
+    let Some(removed_chunks) = removed_chunks else {
+        return Ok(());
+    };
+    let removed_chunks: RemovedFileChunks =
+        serde_json::from_str(&removed_chunks).map_err(|err| {
+            ServiceError::InternalServerError(format!(
+                "Could not parse removed file chunks {:?}",
+                err
+            ))
+        })?;
+
+    log::info!(
+        "Deleting {} chunks no longer in file {}",
+        removed_chunks.chunk_ids.len(),
+        file_id
+    );
+
+    let dataset_config = DatasetConfiguration::from_json(dataset.server_configuration.clone());
+    for chunk_ids in removed_chunks.chunk_ids.chunks(1000) {
+        delete_chunk_metadata_query(
+            chunk_ids.to_vec(),
+            removed_chunks.reprocessed_at,
+            dataset.clone(),
+            pool.clone(),
+            dataset_config.clone(),
+        )
+        .await?;
+    }
+
+    Ok(())
+}
+
+/// Schedules the chunks a file held before it was processed again whose content is no longer in
+/// the file to be deleted once the file's new chunks have been ingested. They are deleted right
+/// away if the new chunks were already ingested.
+pub async fn delete_removed_file_chunks_query(
+    file_id: uuid::Uuid,
+    previous_chunks: PreviousFileChunks,
+    reprocessed_at: chrono::NaiveDateTime,
+    dataset: Dataset,
+    pool: web::Data<Pool>,
+    redis_pool: web::Data<RedisPool>,
+) -> Result<(), ServiceError> {
+    let removed_chunk_ids = previous_chunks.removed_chunk_ids();
+    if removed_chunk_ids.is_empty() {
+        return Ok(());
+    }
+
+    let removed_chunks = serde_json::to_string(&RemovedFileChunks {
+        chunk_ids: removed_chunk_ids,
+        reprocessed_at,
+    })
+    .map_err(|err| {
+        ServiceError::InternalServerError(format!(
+            "Could not serialize removed file chunks {:?}",
+            err
+        ))
+    })?;
+
+    let mut redis_conn = redis_pool
+        .get()
+        .await
+        .map_err(|err| ServiceError::BadRequest(err.to_string()))?;
+
+    redis::cmd("SET")
+        .arg(get_removed_file_chunks_key(file_id))
+        .arg(removed_chunks)
+        .arg("EX")
+        .arg(FILE_CHUNK_REPLACEMENT_TTL_SECONDS)
+        .query_async::<_, ()>(&mut *redis_conn)
+        .await
+        .map_err(|err| ServiceError::BadRequest(err.to_string()))?;
+    drop(redis_conn);
+
+    delete_claimed_removed_file_chunks_query(file_id, dataset, pool, redis_pool).await
+}
+
+/// Marks chunks created from a file as ingested. Once every new chunk of a reprocessed file has
+/// been ingested, the chunks it no longer has are deleted.
+pub async fn complete_pending_file_chunks_query(
+    file_id: uuid::Uuid,
+    chunk_ids: Vec<uuid::Uuid>,
+    dataset: Dataset,
+    pool: web::Data<Pool>,
+    redis_pool: web::Data<RedisPool>,
+) -> Result<(), ServiceError> {
+    let mut redis_conn = redis_pool
+        .get()
+        .await
+        .map_err(|err| ServiceError::BadRequest(err.to_string()))?;
+
+    let removed: usize = redis::cmd("SREM")
+        .arg(get_pending_file_chunks_key(file_id))
+        .arg(
+            chunk_ids
+                .iter()
+                .map(|chunk_id| chunk_id.to_string())
+                .collect::<Vec<String>>(),
+        )
+        .query_async(&mut *redis_conn)
+        .await
+        .map_err(|err| ServiceError::BadRequest(err.to_string()))?;
+    drop(redis_conn);
+
+    // Chunks of files which are not being reprocessed were never pending
+    if removed == 0 {
+        return Ok(());
+    }
+
+    delete_claimed_removed_file_chunks_query(file_id, dataset, pool, redis_pool).await
+}
+
+/// Gets a file by its tracking id
+pub async fn get_file_by_tracking_id_query(
+    tracking_id: &str,
+    dataset_id: uuid::Uuid,
+    pool: web::Data<Pool>,
+) -> Result<Option<File>, ServiceError> {
+    use crate::data::schema::files::dsl as files_columns;
+
+    let mut conn = pool
+        .get()
+        .await
+        .map_err(|_| ServiceError::BadRequest("Could not get database connection".to_string()))?;
+
+    files_columns::files
+        .filter(files_columns::tracking_id.eq(tracking_id))
+        .filter(files_columns::dataset_id.eq(dataset_id))
+        .select(File::as_select())
+        .first::<File>(&mut conn)
+        .await
+        .optional()
+        .map_err(|err| {
+            log::error!("Could not get file by tracking id {:?}", err);
+            ServiceError::BadRequest("Could not get file by tracking id".to_string())
+        })
+}
+
+/// Where the contents of a new version of a file come from
+enum FileVersionContents<'a> {
+    Upload(&'a [u8]),
+    Restore(uuid::Uuid),
+}
+
+/// Records the current contents and details of a file as a version before replacing them and
+/// bumping the file's version. A session advisory lock on the file keeps concurrent versions in
+/// order without holding a transaction open while S3 is written. The current contents are copied
+/// before the rows are written and replaced after, and everything is undone if a step fails.
+async fn create_next_file_version_query(
+    file_id: uuid::Uuid,
+    dataset_id: uuid::Uuid,
+    contents: FileVersionContents<'_>,
+    next_file: impl FnOnce(File) -> File + Send,
+    pool: web::Data<Pool>,
+) -> Result<File, ServiceError> {
+    let mut conn = pool
+        .get()
+        .await
+        .map_err(|_| ServiceError::BadRequest("Could not get database connection".to_string()))?;
+
+    let lock_key = format!("file_version:{}", file_id);
+    diesel::sql_query("SELECT pg_advisory_lock(hashtextextended($1::text, 0))")
+        .bind::<diesel::sql_types::Text, _>(lock_key.clone())
+        .execute(&mut conn)
+        .await
+        .map_err(|err| {
+            log::error!("Could not lock file for a new version {:?}", err);
+            ServiceError::BadRequest("Could not lock file for a new version".to_string())
+        })?;
+
+    let result =
+        create_locked_file_version_query(file_id, dataset_id, contents, next_file, &mut conn).await;
+
+    if let Err(err) = diesel::sql_query("SELECT pg_advisory_unlock(hashtextextended($1::text, 0))")
+        .bind::<diesel::sql_types::Text, _>(lock_key)
+        .execute(&mut conn)
+        .await
+    {
+        log::error!("Could not unlock file after a new version {:?}", err);
+    }
+
+    result
+}
+
+/// Creates the next version of a file while the version lock on the file is held
+async fn create_locked_file_version_query(
+    file_id: uuid::Uuid,
+    dataset_id: uuid::Uuid,
+    contents: FileVersionContents<'_>,
+    next_file: impl FnOnce(File) -> File + Send,
+    conn: &mut diesel_async::AsyncPgConnection,
+) -> Result<File, ServiceError> {
+    use crate::data::schema::file_versions::dsl as file_versions_columns;
+    use crate::data::schema::files::dsl as files_columns;
+
+    let bucket = get_aws_bucket()?;
+
+    let file = files_columns::files
+        .filter(files_columns::id.eq(file_id))
+        .filter(files_columns::dataset_id.eq(dataset_id))
+        .select(File::as_select())
+        .first::<File>(conn)
+        .await
+        .map_err(|_| ServiceError::NotFound("File with specified id not found".to_string()))?;
+
+    let file_version = FileVersion::from_file(&file);
+    bucket
+        .copy_object_internal(file.id.to_string(), file_version.id.to_string())
+        .await
+        .map_err(|e| {
+            log::error!("Could not copy file version in S3 {:?}", e);
+            ServiceError::BadRequest("Could not copy file version in S3".to_string())
+        })?;
+
+    let delete_version_contents = || async {
+        if let Err(e) = bucket.delete_object(file_version.id.to_string()).await {
+            log::error!("Could not delete contents of failed file version {:?}", e);
+        }
+    };
+
+    let next_file = next_file(file.clone());
+    let content_type = if next_file.file_name.ends_with(".pdf") {
+        "application/pdf"
+    } else {
+        "application/octet-stream"
+    };
+
+    let update_result = conn
+        .transaction::<_, ServiceError, _>(|conn| {
+            let file_version = file_version.clone();
+            let file = file.clone();
+            async move {
+                diesel::insert_into(file_versions_columns::file_versions)
+                    .values(&file_version)
+                    .execute(conn)
+                    .await?;
+
+                let updated_file = diesel::update(files_columns::files)
+                    .filter(files_columns::id.eq(file.id))
+                    .set((
+                        files_columns::file_name.eq(next_file.file_name),
+                        files_columns::size.eq(next_file.size),
+                        files_columns::metadata.eq(next_file.metadata),
+                        files_columns::link.eq(next_file.link),
+                        files_columns::time_stamp.eq(next_file.time_stamp),
+                        files_columns::tag_set.eq(next_file.tag_set),
+                        files_columns::tracking_id.eq(next_file.tracking_id),
+                        files_columns::version.eq(file.version + 1),
+                        files_columns::updated_at.eq(chrono::Utc::now().naive_local()),
+                    ))
+                    .get_result::<File>(conn)
+                    .await?;
+
+                Ok(updated_file)
+            }
+            .scope_boxed()
+        })
+        .await;
+
+    let updated_file = match update_result {
+        Ok(updated_file) => updated_file,
+        Err(err) => {
+            delete_version_contents().await;
+            return Err(err);
+        }
+    };
+
+    let put_result = match contents {
+        FileVersionContents::Upload(file_data) => bucket
+            .put_object_with_content_type(file.id.to_string(), file_data, content_type)
+            .await
+            .map(|_| ()),
+        FileVersionContents::Restore(version_id) => bucket
+            .copy_object_internal(version_id.to_string(), file.id.to_string())
+            .await
+            .map(|_| ()),
+    };
+
+    if let Err(e) = put_result {
+        log::error!("Could not upload file to S3 {:?}", e);
+
+        // The rows are put back to how they were so the file matches its unchanged contents
+        let revert_result = conn
+            .transaction::<_, ServiceError, _>(|conn| {
+                let file = file.clone();
+                let file_version_id = file_version.id;
+                async move {
+                    diesel::delete(
+                        file_versions_columns::file_versions
+                            .filter(file_versions_columns::id.eq(file_version_id)),
+                    )
+                    .execute(conn)
+                    .await?;
+
+                    diesel::update(files_columns::files)
+                        .filter(files_columns::id.eq(file.id))
+                        .set((
+                            files_columns::file_name.eq(file.file_name),
+                            files_columns::size.eq(file.size),
+                            files_columns::metadata.eq(file.metadata),
+                            files_columns::link.eq(file.link),
+                            files_columns::time_stamp.eq(file.time_stamp),
+                            files_columns::tag_set.eq(file.tag_set),
+                            files_columns::tracking_id.eq(file.tracking_id),
+                            files_columns::version.eq(file.version),
+                            files_columns::updated_at.eq(file.updated_at),
+                        ))
+                        .execute(conn)
+                        .await?;
+
+                    Ok(())
+                }
+                .scope_boxed()
+            })
+            .await;
+
+        match revert_result {
+            Ok(()) => delete_version_contents().await,
+            Err(err) => log::error!("Could not revert failed file version {:?}", err),
+        }
+
+        return Err(ServiceError::BadRequest(
+            "Could not upload file to S3".to_string(),
+        ));
+    }
+
+    Ok(updated_file)
+}
+
+/// Replaces the contents of a file with a new upload, keeping the previous contents as a version
+pub async fn create_file_version_query(
+    file_id: uuid::Uuid,
+    file_size: i64,
+    file_data: &[u8],
+    upload_file_data: UploadFileReqPayload,
+    dataset_id: uuid::Uuid,
+    pool: web::Data<Pool>,
+) -> Result<File, ServiceError> {
+    let uploaded_file = File::from_details(
+        Some(file_id),
+        &upload_file_data.file_name,
+        file_size,
+        upload_file_data
+            .tag_set
+            .map(|tag_set| tag_set.into_iter().map(Some).collect()),
+        upload_file_data.metadata,
+        upload_file_data.link,
+        upload_file_data.time_stamp,
+        dataset_id,
+    );
+
+    create_next_file_version_query(
+        file_id,
+        dataset_id,
+        FileVersionContents::Upload(file_data),
+        |file| File {
+            tracking_id: upload_file_data.file_tracking_id.or(file.tracking_id),
+            ..uploaded_file
+        },
+        pool,
+    )
+    .await
+}
+
+/// Restores a previous version of a file as a new version, keeping the current contents as a
+/// version as well
+pub async fn restore_file_version_query(
+    file_id: uuid::Uuid,
+    version: i32,
+    dataset_id: uuid::Uuid,
+    pool: web::Data<Pool>,
+) -> Result<File, ServiceError> {
+    use crate::data::schema::file_versions::dsl as file_versions_columns;
+
+    let mut conn = pool
+        .get()
+        .await
+        .map_err(|_| ServiceError::BadRequest("Could not get database connection".to_string()))?;
+
+    let file_version = file_versions_columns::file_versions
+        .filter(file_versions_columns::file_id.eq(file_id))
+        .filter(file_versions_columns::dataset_id.eq(dataset_id))
+        .filter(file_versions_columns::version.eq(version))
+        .select(FileVersion::as_select())
+        .first::<FileVersion>(&mut conn)
+        .await
+        .map_err(|_| ServiceError::NotFound("File version not found".to_string()))?;
+    drop(conn);
+
+    create_next_file_version_query(
+        file_id,
+        dataset_id,
+        FileVersionContents::Restore(file_version.id),
+        |file| File {
+            file_name: file_version.file_name,
+            size: file_version.size,
+            metadata: file_version.metadata,
+            link: file_version.link,
+            time_stamp: file_version.time_stamp,
+            tag_set: file_version.tag_set,
+            ..file
+        },
+        pool,
+    )
+    .await
+}
+
+/// Gets the previous versions of a file, newest first
+pub async fn get_file_versions_query(
+    file_id: uuid::Uuid,
+    dataset_id: uuid::Uuid,
+    pool: web::Data<Pool>,
+) -> Result<Vec<FileVersion>, ServiceError> {
+    use crate::data::schema::file_versions::dsl as file_versions_columns;
+
+    let mut conn = pool
+        .get()
+        .await
+        .map_err(|_| ServiceError::BadRequest("Could not get database connection".to_string()))?;
+
+    file_versions_columns::file_versions
+        .filter(file_versions_columns::file_id.eq(file_id))
+        .filter(file_versions_columns::dataset_id.eq(dataset_id))
+        .select(FileVersion::as_select())
+        .order(file_versions_columns::version.desc())
+        .load::<FileVersion>(&mut conn)
+        .await
+        .map_err(|err| {
+            log::error!("Could not get file versions {:?}", err);
+            ServiceError::BadRequest("Could not get file versions".to_string())
+        })
+}
+
 pub async fn put_file_in_s3_get_signed_url(
     file_id: uuid::Uuid,
     file_data: Vec<u8>,
@@ -688,3 +1544,109 @@ pub async fn get_file_queue_length(
 
     Ok(file_queue_status.size as i64)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn new_chunk(chunk_html: &str, tracking_id: Option<&str>) -> ChunkReqPayload {
+        ChunkReqPayload {
+            chunk_html: Some(chunk_html.to_string()),
+            tracking_id: tracking_id.map(|tracking_id| tracking_id.to_string()),
+            ..Default::default()
+        }
+    }
+
+    fn previous_chunk(
+        chunk_html: &str,
+        tracking_id: Option<&str>,
+    ) -> (uuid::Uuid, Option<String>, Option<String>) {
+        (
+            uuid::Uuid::new_v4(),
+            tracking_id.map(|tracking_id| tracking_id.to_string()),
+            Some(chunk_html.to_string()),
+        )
+    }
+
+    #[test]
+    fn test_unchanged_chunks_are_kept() {
+        let mut previous_chunks = PreviousFileChunks::from_chunks(vec![
+            previous_chunk("<p>Unchanged</p>", None),
+            previous_chunk("<p>Tracked</p>", Some("intro")),
+        ]);
+
+        let mut chunks = vec![
+            new_chunk("<p>Unchanged</p>", None),
+            new_chunk("<p>Tracked</p>", Some("intro")),
+        ];
+        previous_chunks.retain_changed_chunks(&mut chunks);
+
+        assert!(chunks.is_empty());
+        assert!(previous_chunks.removed_chunk_ids().is_empty());
+    }
+
+    #[test]
+    fn test_changed_tracked_chunk_is_updated_in_place() {
+        let tracked = previous_chunk("<p>Old price</p>", Some("price"));
+        let mut previous_chunks = PreviousFileChunks::from_chunks(vec![tracked]);
+
+        let mut chunks = vec![
+            new_chunk("<p>New price</p>", Some("price")),
+            new_chunk("<p>New section</p>", Some("section")),
+        ];
+        previous_chunks.retain_changed_chunks(&mut chunks);
+
+        assert_eq!(chunks.len(), 2);
+        assert_eq!(chunks[0].tracking_id.as_deref(), Some("price"));
+        assert_eq!(chunks[0].upsert_by_tracking_id, Some(true));
+        assert_eq!(chunks[1].tracking_id.as_deref(), Some("section"));
+        assert_eq!(chunks[1].upsert_by_tracking_id, None);
+        assert!(previous_chunks.removed_chunk_ids().is_empty());
+    }
+
+    #[test]
+    fn test_duplicate_content_is_matched_once_per_chunk() {
+        let first = previous_chunk("<p>Repeated</p>", None);
+        let second = previous_chunk("<p>Repeated</p>", None);
+        let mut previous_chunks =
+            PreviousFileChunks::from_chunks(vec![first.clone(), second.clone()]);
+
+        let mut chunks = vec![new_chunk("<p>Repeated</p>", None)];
+        previous_chunks.retain_changed_chunks(&mut chunks);
+
+        assert!(chunks.is_empty());
+        let removed_chunk_ids = previous_chunks.removed_chunk_ids();
+        assert_eq!(removed_chunk_ids.len(), 1);
+        assert!(removed_chunk_ids[0] == first.0 || removed_chunk_ids[0] == second.0);
+
+        let mut previous_chunks =
+            PreviousFileChunks::from_chunks(vec![previous_chunk("<p>Repeated</p>", None)]);
+        let mut chunks = vec![
+            new_chunk("<p>Repeated</p>", None),
+            new_chunk("<p>Repeated</p>", None),
+        ];
+        previous_chunks.retain_changed_chunks(&mut chunks);
+
+        assert_eq!(chunks.len(), 1);
+        assert!(previous_chunks.removed_chunk_ids().is_empty());
+    }
+
+    #[test]
+    fn test_chunks_no_longer_in_file_are_removed() {
+        let untracked = previous_chunk("<p>Removed paragraph</p>", None);
+        let tracked = previous_chunk("<p>Removed section</p>", Some("removed"));
+        let mut previous_chunks =
+            PreviousFileChunks::from_chunks(vec![untracked.clone(), tracked.clone()]);
+
+        let mut chunks = vec![new_chunk("<p>Added paragraph</p>", None)];
+        previous_chunks.retain_changed_chunks(&mut chunks);
+
+        assert_eq!(chunks.len(), 1);
+        assert_eq!(chunks[0].upsert_by_tracking_id, None);
+        let mut removed_chunk_ids = previous_chunks.removed_chunk_ids();
+        removed_chunk_ids.sort();
+        let mut expected_chunk_ids = vec![untracked.0, tracked.0];
+        expected_chunk_ids.sort();
+        assert_eq!(removed_chunk_ids, expected_chunk_ids);
+    }
+}
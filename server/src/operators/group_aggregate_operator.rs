@@ -0,0 +1,640 @@
+use std::collections::{HashMap, HashSet};
+
+use actix_web::web;
+use diesel::prelude::*;
+use diesel::result::{DatabaseErrorKind, Error as DieselError};
+use diesel_async::RunQueryDsl;
+
+use crate::data::models::{
+    ChunkGroup, ChunkGroupAndFileId, ConditionType, FieldCondition, GroupAggregate,
+    GroupAggregateFunction, Pool, RangeCondition, RedisPool, SortOrder,
+};
+use crate::errors::ServiceError;
+use crate::handlers::chunk_handler::ChunkFilter;
+use crate::operators::group_operator::refresh_stale_groups_query;
+use crate::operators::group_summary_operator::GROUP_SUMMARY_TRACKING_ID_PREFIX;
+use crate::operators::search_operator::DeprecatedSearchOverGroupsResponseBody;
+
+/// Prefix of filter and sort fields which reference a group aggregate by name
+pub const GROUP_AGGREGATE_FIELD_PREFIX: &str = "group_aggregates.";
+
+/// Redis set of `<dataset_id>:<group_id>` members whose aggregates need to be recomputed
+pub const STALE_GROUP_AGGREGATES_KEY: &str = "group_aggregates_stale";
+
+/// Gets the name of the aggregate referenced by a `group_aggregates.<name>` field, if it is one
+pub fn get_group_aggregate_name(field: &str) -> Option<&str> {
+    field.strip_prefix(GROUP_AGGREGATE_FIELD_PREFIX)
+}
+
+/// Splits an aggregate's chunk field into the path of its key within the chunk metadata. Returns
+/// None for `num_value`.
+pub fn get_group_aggregate_field_path(field: &str) -> Result<Option<Vec<String>>, ServiceError> {
+    if field == "num_value" {
+        return Ok(None);
+    }
+
+    match field.strip_prefix("metadata.") {
+        Some(path) if !path.is_empty() && path.split('.').all(|key| !key.is_empty()) => {
+            Ok(Some(path.split('.').map(|key| key.to_string()).collect()))
+        }
+        _ => Err(ServiceError::BadRequest(
+            "Group aggregate field must be num_value or a metadata. prefixed key".to_string(),
+        )),
+    }
+}
+
+pub async fn create_group_aggregate_query(
+    group_aggregate: GroupAggregate,
+    pool: web::Data<Pool>,
+) -> Result<GroupAggregate, ServiceError> {
+    use crate::data::schema::group_aggregates::dsl as group_aggregates_columns;
+
+    let mut conn = pool.get().await.map_err(|_e| {
+        ServiceError::InternalServerError("Failed to get postgres connection".to_string())
+    })?;
+
+    diesel::insert_into(group_aggregates_columns::group_aggregates)
+        .values(&group_aggregate)
+        .get_result::<GroupAggregate>(&mut conn)
+        .await
+        .map_err(|err| match err {
+            DieselError::DatabaseError(DatabaseErrorKind::UniqueViolation, _) => {
+                ServiceError::BadRequest(format!(
+                    "A group aggregate named {} already exists in this dataset",
+                    group_aggregate.name
+                ))
+            }
+            _ => {
+                log::error!("Failed to create group aggregate: {:?}", err);
+                ServiceError::InternalServerError("Failed to create group aggregate".to_string())
+            }
+        })
+}
+
+pub async fn get_group_aggregates_query(
+    dataset_id: uuid::Uuid,
+    pool: web::Data<Pool>,
+) -> Result<Vec<GroupAggregate>, ServiceError> {
+    use crate::data::schema::group_aggregates::dsl as group_aggregates_columns;
+
+    let mut conn = pool.get().await.map_err(|_e| {
+        ServiceError::InternalServerError("Failed to get postgres connection".to_string())
+    })?;
+
+    group_aggregates_columns::group_aggregates
+        .filter(group_aggregates_columns::dataset_id.eq(dataset_id))
+        .order_by(group_aggregates_columns::created_at.asc())
+        .select(GroupAggregate::as_select())
+        .load::<GroupAggregate>(&mut conn)
+        .await
+        .map_err(|err| {
+            log::error!("Failed to get group aggregates: {:?}", err);
+            ServiceError::InternalServerError("Failed to get group aggregates".to_string())
+        })
+}
+
+pub async fn get_group_aggregate_by_name_query(
+    name: &str,
+    dataset_id: uuid::Uuid,
+    pool: web::Data<Pool>,
+) -> Result<GroupAggregate, ServiceError> {
+    use crate::data::schema::group_aggregates::dsl as group_aggregates_columns;
+
+    let mut conn = pool.get().await.map_err(|_e| {
+        ServiceError::InternalServerError("Failed to get postgres connection".to_string())
+    })?;
+
+    group_aggregates_columns::group_aggregates
+        .filter(group_aggregates_columns::dataset_id.eq(dataset_id))
+        .filter(group_aggregates_columns::name.eq(name))
+        .select(GroupAggregate::as_select())
+        .first::<GroupAggregate>(&mut conn)
+        .await
+        .map_err(|_| ServiceError::NotFound(format!("Group aggregate {} not found", name)))
+}
+
+pub async fn delete_group_aggregate_query(
+    aggregate_id: uuid::Uuid,
+    dataset_id: uuid::Uuid,
+    pool: web::Data<Pool>,
+) -> Result<(), ServiceError> {
+    use crate::data::schema::group_aggregates::dsl as group_aggregates_columns;
+
+    let mut conn = pool.get().await.map_err(|_e| {
+        ServiceError::InternalServerError("Failed to get postgres connection".to_string())
+    })?;
+
+    let deleted = diesel::delete(
+        group_aggregates_columns::group_aggregates
+            .filter(group_aggregates_columns::id.eq(aggregate_id))
+            .filter(group_aggregates_columns::dataset_id.eq(dataset_id)),
+    )
+    .execute(&mut conn)
+    .await
+    .map_err(|err| {
+        log::error!("Failed to delete group aggregate: {:?}", err);
+        ServiceError::InternalServerError("Failed to delete group aggregate".to_string())
+    })?;
+
+    if deleted == 0 {
+        return Err(ServiceError::NotFound(
+            "Group aggregate not found".to_string(),
+        ));
+    }
+
+    Ok(())
+}
+
+/// SQL expression which aggregates the aggregate's field over the `chunk_metadata` rows in scope.
+/// The metadata path is bound as parameter `$4`.
+fn get_group_aggregate_sql(
+    aggregation: GroupAggregateFunction,
+    field_path: &Option<Vec<String>>,
+) -> String {
+    let (numeric_value, truthy_value) = match field_path {
+        None => (
+            "chunk_metadata.num_value".to_string(),
+            "COALESCE(chunk_metadata.num_value <> 0, false)".to_string(),
+        ),
+        Some(_) => (
+            "CASE WHEN jsonb_typeof(chunk_metadata.metadata #> $4) = 'number' THEN (chunk_metadata.metadata #>> $4)::float8 END".to_string(),
+            "CASE jsonb_typeof(chunk_metadata.metadata #> $4) WHEN 'boolean' THEN (chunk_metadata.metadata #>> $4)::boolean WHEN 'number' THEN (chunk_metadata.metadata #>> $4)::float8 <> 0 WHEN 'string' THEN lower(chunk_metadata.metadata #>> $4) NOT IN ('', 'false', '0', 'no') ELSE false END".to_string(),
+        ),
+    };
+
+    match aggregation {
+        GroupAggregateFunction::Min => format!("MIN({})", numeric_value),
+        GroupAggregateFunction::Max => format!("MAX({})", numeric_value),
+        GroupAggregateFunction::Avg => format!("AVG({})", numeric_value),
+        GroupAggregateFunction::Sum => format!("SUM({})", numeric_value),
+        GroupAggregateFunction::Any => {
+            format!("CASE WHEN bool_or({}) THEN 1.0 ELSE 0.0 END", truthy_value)
+        }
+    }
+}
+
+/// Recomputes the given aggregates from the current chunks of each group. Only the groups in
+/// `group_ids` are recomputed if specified, otherwise every group in the dataset is.
+pub async fn refresh_group_aggregate_values_query(
+    group_aggregates: Vec<GroupAggregate>,
+    dataset_id: uuid::Uuid,
+    group_ids: Option<Vec<uuid::Uuid>>,
+    pool: web::Data<Pool>,
+) -> Result<(), ServiceError> {
+    if group_aggregates.is_empty() || group_ids.as_ref().is_some_and(|ids| ids.is_empty()) {
+        return Ok(());
+    }
+
+    let mut conn = pool.get().await.map_err(|_e| {
+        ServiceError::InternalServerError("Failed to get postgres connection".to_string())
+    })?;
+
+    for group_aggregate in group_aggregates {
+        let aggregation = GroupAggregateFunction::from_name(&group_aggregate.aggregation).ok_or(
+            ServiceError::InternalServerError(format!(
+                "Group aggregate {} has an unknown aggregation",
+                group_aggregate.name
+            )),
+        )?;
+        let field_path = get_group_aggregate_field_path(&group_aggregate.field)?;
+
+        diesel::sql_query(format!(
+            "INSERT INTO group_aggregate_values (group_id, aggregate_id, dataset_id, value, updated_at)
+            SELECT chunk_group.id, $1, chunk_group.dataset_id, aggregated.value, NOW()
+            FROM chunk_group
+            LEFT JOIN LATERAL (
+                SELECT ({})::float8 AS value
+                FROM chunk_group_bookmarks
+                JOIN chunk_metadata ON chunk_metadata.id = chunk_group_bookmarks.chunk_metadata_id
+                WHERE chunk_group_bookmarks.group_id = chunk_group.id
+                    AND chunk_metadata.tracking_id IS DISTINCT FROM ($5 || chunk_group.id::text)
+            ) aggregated ON true
+            WHERE chunk_group.dataset_id = $2 AND ($3::uuid[] IS NULL OR chunk_group.id = ANY($3))
+            ON CONFLICT (group_id, aggregate_id)
+            DO UPDATE SET value = excluded.value, updated_at = excluded.updated_at",
+            get_group_aggregate_sql(aggregation, &field_path)
+        ))
+        .bind::<diesel::sql_types::Uuid, _>(group_aggregate.id)
+        .bind::<diesel::sql_types::Uuid, _>(dataset_id)
+        .bind::<diesel::sql_types::Nullable<diesel::sql_types::Array<diesel::sql_types::Uuid>>, _>(
+            group_ids.clone(),
+        )
+        .bind::<diesel::sql_types::Array<diesel::sql_types::Text>, _>(
+            field_path.unwrap_or_default(),
+        )
+        .bind::<diesel::sql_types::Text, _>(GROUP_SUMMARY_TRACKING_ID_PREFIX)
+        .execute(&mut conn)
+        .await
+        .map_err(|err| {
+            log::error!(
+                "Failed to refresh group aggregate {}: {:?}",
+                group_aggregate.name,
+                err
+            );
+            ServiceError::InternalServerError("Failed to refresh group aggregates".to_string())
+        })?;
+    }
+
+    Ok(())
+}
+
+/// Recomputes every aggregate defined on the dataset for the given groups
+pub async fn refresh_group_aggregates_query(
+    dataset_id: uuid::Uuid,
+    group_ids: Vec<uuid::Uuid>,
+    pool: web::Data<Pool>,
+) -> Result<(), ServiceError> {
+    if group_ids.is_empty() {
+        return Ok(());
+    }
+
+    let group_aggregates = get_group_aggregates_query(dataset_id, pool.clone()).await?;
+
+    refresh_group_aggregate_values_query(group_aggregates, dataset_id, Some(group_ids), pool).await
+}
+
+/// Queues the aggregates of groups whose chunks changed to be recomputed by the grupdate worker
+pub async fn mark_group_aggregates_stale_query(
+    dataset_id: uuid::Uuid,
+    group_ids: Vec<uuid::Uuid>,
+    redis_pool: web::Data<RedisPool>,
+) -> Result<(), ServiceError> {
+    if group_ids.is_empty() {
+        return Ok(());
+    }
+
+    let mut redis_conn = redis_pool
+        .get()
+        .await
+        .map_err(|err| ServiceError::BadRequest(err.to_string()))?;
+
+    redis::cmd("SADD")
+        .arg(STALE_GROUP_AGGREGATES_KEY)
+        .arg(
+            group_ids
+                .iter()
+                .map(|group_id| format!("{}:{}", dataset_id, group_id))
+                .collect::<Vec<String>>(),
+        )
+        .query_async::<_, ()>(&mut *redis_conn)
+        .await
+        .map_err(|err| ServiceError::BadRequest(err.to_string()))?;
+
+    Ok(())
+}
+
+/// Recomputes the aggregates of up to `batch_size` groups queued by
+/// `mark_group_aggregates_stale_query`. Returns the number of groups refreshed.
+pub async fn refresh_stale_group_aggregates_query(
+    batch_size: usize,
+    redis_pool: web::Data<RedisPool>,
+    pool: web::Data<Pool>,
+) -> Result<usize, ServiceError> {
+    refresh_stale_groups_query(
+        STALE_GROUP_AGGREGATES_KEY,
+        batch_size,
+        redis_pool,
+        |dataset_id, group_ids| refresh_group_aggregates_query(dataset_id, group_ids, pool.clone()),
+    )
+    .await
+}
+
+fn range_condition_to_f64(condition: &RangeCondition) -> f64 {
+    match condition {
+        RangeCondition::Float(float) => *float,
+        RangeCondition::Int(int) => *int as f64,
+    }
+}
+
+/// Gets the ids of the groups whose value for the aggregate referenced by a
+/// `group_aggregates.<name>` condition satisfies it
+pub async fn get_group_ids_matching_aggregate_condition_query(
+    condition: &FieldCondition,
+    dataset_id: uuid::Uuid,
+    pool: web::Data<Pool>,
+) -> Result<Vec<uuid::Uuid>, ServiceError> {
+    use crate::data::schema::group_aggregate_values::dsl as group_aggregate_values_columns;
+
+    let name = get_group_aggregate_name(&condition.field).ok_or(ServiceError::BadRequest(
+        format!("{} is not a group aggregate field", condition.field),
+    ))?;
+    let group_aggregate = get_group_aggregate_by_name_query(name, dataset_id, pool.clone()).await?;
+
+    let mut query = group_aggregate_values_columns::group_aggregate_values
+        .filter(group_aggregate_values_columns::aggregate_id.eq(group_aggregate.id))
+        .select(group_aggregate_values_columns::group_id)
+        .into_boxed();
+
+    if let Some(range) = &condition.range {
+        if let Some(gte) = &range.gte {
+            query =
+                query.filter(group_aggregate_values_columns::value.ge(range_condition_to_f64(gte)));
+        }
+        if let Some(gt) = &range.gt {
+            query =
+                query.filter(group_aggregate_values_columns::value.gt(range_condition_to_f64(gt)));
+        }
+        if let Some(lte) = &range.lte {
+            query =
+                query.filter(group_aggregate_values_columns::value.le(range_condition_to_f64(lte)));
+        }
+        if let Some(lt) = &range.lt {
+            query =
+                query.filter(group_aggregate_values_columns::value.lt(range_condition_to_f64(lt)));
+        }
+    } else if let Some(match_any) = &condition.match_any {
+        query = query.filter(
+            group_aggregate_values_columns::value.eq_any(
+                match_any
+                    .iter()
+                    .map(|value| value.to_f64())
+                    .collect::<Vec<f64>>(),
+            ),
+        );
+    } else if let Some(boolean) = condition.boolean {
+        query = if boolean {
+            query.filter(group_aggregate_values_columns::value.ne(0.0))
+        } else {
+            query.filter(
+                group_aggregate_values_columns::value
+                    .eq(0.0)
+                    .or(group_aggregate_values_columns::value.is_null()),
+            )
+        };
+    } else {
+        return Err(ServiceError::BadRequest(
+            "group_aggregates filters can only be used with range, match_any or boolean clauses"
+                .to_string(),
+        ));
+    }
+
+    let mut conn = pool.get().await.map_err(|_e| {
+        ServiceError::InternalServerError("Failed to get postgres connection".to_string())
+    })?;
+
+    query.load::<uuid::Uuid>(&mut conn).await.map_err(|err| {
+        log::error!("Failed to filter groups by aggregate: {:?}", err);
+        ServiceError::BadRequest("Failed to filter groups by aggregate".to_string())
+    })
+}
+
+/// Groups selected by a ChunkFilter made up of `group_aggregates.<name>` conditions
+pub struct GroupAggregateFilter {
+    /// Groups must be in this set if it is specified
+    pub include: Option<HashSet<uuid::Uuid>>,
+    /// Groups must not be in this set
+    pub exclude: HashSet<uuid::Uuid>,
+}
+
+fn get_group_aggregate_field_conditions(
+    conditions: Option<Vec<ConditionType>>,
+) -> Result<Vec<FieldCondition>, ServiceError> {
+    conditions
+        .unwrap_or_default()
+        .into_iter()
+        .map(|condition| match condition {
+            ConditionType::Field(field_condition)
+                if get_group_aggregate_name(&field_condition.field).is_some() =>
+            {
+                Ok(field_condition)
+            }
+            _ => Err(ServiceError::BadRequest(
+                "Groups can only be filtered by group_aggregates fields".to_string(),
+            )),
+        })
+        .collect()
+}
+
+pub async fn get_group_aggregate_filter_query(
+    filter: ChunkFilter,
+    dataset_id: uuid::Uuid,
+    pool: web::Data<Pool>,
+) -> Result<GroupAggregateFilter, ServiceError> {
+    let mut include: Option<HashSet<uuid::Uuid>> = None;
+
+    for condition in get_group_aggregate_field_conditions(filter.must)? {
+        let group_ids: HashSet<uuid::Uuid> =
+            get_group_ids_matching_aggregate_condition_query(&condition, dataset_id, pool.clone())
+                .await?
+                .into_iter()
+                .collect();
+
+        include = Some(match include {
+            Some(include) => include.intersection(&group_ids).copied().collect(),
+            None => group_ids,
+        });
+    }
+
+    let should_conditions = get_group_aggregate_field_conditions(filter.should)?;
+    if !should_conditions.is_empty() {
+        let mut should_group_ids = HashSet::new();
+        for condition in should_conditions {
+            should_group_ids.extend(
+                get_group_ids_matching_aggregate_condition_query(
+                    &condition,
+                    dataset_id,
+                    pool.clone(),
+                )
+                .await?,
+            );
+        }
+
+        include = Some(match include {
+            Some(include) => include.intersection(&should_group_ids).copied().collect(),
+            None => should_group_ids,
+        });
+    }
+
+    let mut exclude = HashSet::new();
+    for condition in get_group_aggregate_field_conditions(filter.must_not)? {
+        exclude.extend(
+            get_group_ids_matching_aggregate_condition_query(&condition, dataset_id, pool.clone())
+                .await?,
+        );
+    }
+
+    Ok(GroupAggregateFilter { include, exclude })
+}
+
+/// Pages through the groups of a dataset ordered by the value of an aggregate, groups without a
+/// value last, and restricted to the groups selected by `filter`
+pub async fn get_groups_for_dataset_by_aggregate_query(
+    dataset_id: uuid::Uuid,
+    page: u64,
+    sort_by: Option<(uuid::Uuid, SortOrder)>,
+    filter: Option<GroupAggregateFilter>,
+    pool: web::Data<Pool>,
+) -> Result<(Vec<ChunkGroupAndFileId>, i64), ServiceError> {
+    use crate::data::schema::chunk_group::dsl as chunk_group_columns;
+    use crate::data::schema::group_aggregate_values::dsl as group_aggregate_values_columns;
+    use crate::data::schema::groups_from_files::dsl as groups_from_files_columns;
+
+    let mut conn = pool.get().await.map_err(|_e| {
+        ServiceError::InternalServerError("Failed to get postgres connection".to_string())
+    })?;
+
+    let sort_aggregate_id = sort_by
+        .as_ref()
+        .map(|(aggregate_id, _)| *aggregate_id)
+        .unwrap_or_default();
+
+    let mut groups_query = chunk_group_columns::chunk_group
+        .left_join(
+            groups_from_files_columns::groups_from_files
+                .on(chunk_group_columns::id.eq(groups_from_files_columns::group_id)),
+        )
+        .left_join(
+            group_aggregate_values_columns::group_aggregate_values.on(
+                group_aggregate_values_columns::group_id
+                    .eq(chunk_group_columns::id)
+                    .and(group_aggregate_values_columns::aggregate_id.eq(sort_aggregate_id)),
+            ),
+        )
+        .filter(chunk_group_columns::dataset_id.eq(dataset_id))
+        .select((
+            ChunkGroup::as_select(),
+            groups_from_files_columns::file_id.nullable(),
+        ))
+        .into_boxed();
+
+    let mut count_query = chunk_group_columns::chunk_group
+        .filter(chunk_group_columns::dataset_id.eq(dataset_id))
+        .count()
+        .into_boxed();
+
+    if let Some(filter) = filter {
+        if let Some(include) = filter.include {
+            let include = include.into_iter().collect::<Vec<uuid::Uuid>>();
+            groups_query = groups_query.filter(chunk_group_columns::id.eq_any(include.clone()));
+            count_query = count_query.filter(chunk_group_columns::id.eq_any(include));
+        }
+
+        if !filter.exclude.is_empty() {
+            let exclude = filter.exclude.into_iter().collect::<Vec<uuid::Uuid>>();
+            groups_query = groups_query.filter(chunk_group_columns::id.ne_all(exclude.clone()));
+            count_query = count_query.filter(chunk_group_columns::id.ne_all(exclude));
+        }
+    }
+
+    groups_query = match sort_by {
+        Some((_, SortOrder::Asc)) => groups_query.order_by((
+            group_aggregate_values_columns::value.asc().nulls_last(),
+            chunk_group_columns::id.asc(),
+        )),
+        Some((_, SortOrder::Desc)) => groups_query.order_by((
+            group_aggregate_values_columns::value.desc().nulls_last(),
+            chunk_group_columns::id.asc(),
+        )),
+        None => groups_query.order_by(chunk_group_columns::created_at.desc()),
+    };
+
+    let groups = groups_query
+        .offset(((page.max(1) - 1) * 10) as i64)
+        .limit(10)
+        .load::<(ChunkGroup, Option<uuid::Uuid>)>(&mut conn)
+        .await
+        .map_err(|err| {
+            log::error!("Failed to get groups by aggregate: {:?}", err);
+            ServiceError::BadRequest("Failed to get groups for dataset".to_string())
+        })?;
+
+    let group_count = count_query
+        .get_result::<i64>(&mut conn)
+        .await
+        .map_err(|err| {
+            log::error!("Failed to count groups by aggregate: {:?}", err);
+            ServiceError::BadRequest("Failed to count groups for dataset".to_string())
+        })?;
+
+    Ok((
+        groups
+            .into_iter()
+            .map(|(group, file_id)| ChunkGroupAndFileId::from_group(group, file_id))
+            .collect(),
+        group_count,
+    ))
+}
+
+/// Reorders the groups in a group search result by the value of the named aggregate, groups
+/// without a value last
+pub async fn sort_groups_by_aggregate_query(
+    mut result: DeprecatedSearchOverGroupsResponseBody,
+    name: &str,
+    direction: SortOrder,
+    dataset_id: uuid::Uuid,
+    pool: web::Data<Pool>,
+) -> Result<DeprecatedSearchOverGroupsResponseBody, ServiceError> {
+    use crate::data::schema::group_aggregate_values::dsl as group_aggregate_values_columns;
+
+    let group_aggregate = get_group_aggregate_by_name_query(name, dataset_id, pool.clone()).await?;
+
+    let mut conn = pool.get().await.map_err(|_e| {
+        ServiceError::InternalServerError("Failed to get postgres connection".to_string())
+    })?;
+
+    let values: HashMap<uuid::Uuid, f64> = group_aggregate_values_columns::group_aggregate_values
+        .filter(group_aggregate_values_columns::aggregate_id.eq(group_aggregate.id))
+        .filter(
+            group_aggregate_values_columns::group_id.eq_any(
+                result
+                    .group_chunks
+                    .iter()
+                    .map(|group_chunk| group_chunk.group_id)
+                    .collect::<Vec<uuid::Uuid>>(),
+            ),
+        )
+        .filter(group_aggregate_values_columns::value.is_not_null())
+        .select((
+            group_aggregate_values_columns::group_id,
+            group_aggregate_values_columns::value.assume_not_null(),
+        ))
+        .load::<(uuid::Uuid, f64)>(&mut conn)
+        .await
+        .map_err(|err| {
+            log::error!("Failed to get group aggregate values: {:?}", err);
+            ServiceError::BadRequest("Failed to get group aggregate values".to_string())
+        })?
+        .into_iter()
+        .collect();
+
+    result.group_chunks.sort_by(
+        |a, b| match (values.get(&a.group_id), values.get(&b.group_id)) {
+            (Some(a_value), Some(b_value)) => match direction {
+                SortOrder::Asc => a_value.total_cmp(b_value),
+                SortOrder::Desc => b_value.total_cmp(a_value),
+            },
+            (Some(_), None) => std::cmp::Ordering::Less,
+            (None, Some(_)) => std::cmp::Ordering::Greater,
+            (None, None) => std::cmp::Ordering::Equal,
+        },
+    );
+
+    Ok(result)
+}
+
+/// Gets the ids of the groups which contain any of the given chunks
+pub async fn get_group_ids_for_chunks_query(
+    chunk_ids: Vec<uuid::Uuid>,
+    pool: web::Data<Pool>,
+) -> Result<Vec<uuid::Uuid>, ServiceError> {
+    use crate::data::schema::chunk_group_bookmarks::dsl as chunk_group_bookmarks_columns;
+
+    if chunk_ids.is_empty() {
+        return Ok(vec![]);
+    }
+
+    let mut conn = pool.get().await.map_err(|_e| {
+        ServiceError::InternalServerError("Failed to get postgres connection".to_string())
+    })?;
+
+    chunk_group_bookmarks_columns::chunk_group_bookmarks
+        .filter(chunk_group_bookmarks_columns::chunk_metadata_id.eq_any(chunk_ids))
+        .select(chunk_group_bookmarks_columns::group_id)
+        .distinct()
+        .load::<uuid::Uuid>(&mut conn)
+        .await
+        .map_err(|err| {
+            log::error!("Failed to get group ids for chunks: {:?}", err);
+            ServiceError::BadRequest("Failed to get group ids for chunks".to_string())
+        })
+}
@@ -0,0 +1,198 @@
+use actix_web::web;
+use diesel::prelude::*;
+use diesel_async::RunQueryDsl;
+
+use crate::data::models::{DatasetConfiguration, GroupCentroidOptions, Pool, RedisPool};
+use crate::errors::ServiceError;
+use crate::operators::dataset_operator::get_dataset_config_query;
+use crate::operators::group_operator::{
+    get_groups_from_group_ids_query, refresh_stale_groups_query,
+};
+use crate::operators::qdrant_operator::{
+    delete_points_from_qdrant, get_group_centroid_collection_from_dataset_config,
+    get_group_member_vectors_query, upsert_group_centroid_query,
+};
+
+/// Redis set of `<dataset_id>:<group_id>` members whose centroids need to be recomputed
+pub const STALE_GROUP_CENTROIDS_KEY: &str = "group_centroids_stale";
+
+/// Queues the centroids of the given groups to be recomputed by the group update worker. Does
+/// nothing if the dataset does not have group centroids enabled.
+pub async fn mark_group_centroids_stale_query(
+    dataset_config: &DatasetConfiguration,
+    dataset_id: uuid::Uuid,
+    group_ids: Vec<uuid::Uuid>,
+    redis_pool: web::Data<RedisPool>,
+) -> Result<(), ServiceError> {
+    if dataset_config.GROUP_CENTROIDS.is_none() || group_ids.is_empty() {
+        return Ok(());
+    }
+
+    let mut redis_conn = redis_pool
+        .get()
+        .await
+        .map_err(|err| ServiceError::BadRequest(err.to_string()))?;
+
+    redis::cmd("SADD")
+        .arg(STALE_GROUP_CENTROIDS_KEY)
+        .arg(
+            group_ids
+                .iter()
+                .map(|group_id| format!("{}:{}", dataset_id, group_id))
+                .collect::<Vec<String>>(),
+        )
+        .query_async::<_, ()>(&mut *redis_conn)
+        .await
+        .map_err(|err| ServiceError::BadRequest(err.to_string()))?;
+
+    Ok(())
+}
+
+/// Queues the centroid of every group in the dataset to be recomputed. Used when centroids are
+/// first enabled and after changes which may affect any group.
+pub async fn mark_dataset_group_centroids_stale_query(
+    dataset_config: &DatasetConfiguration,
+    dataset_id: uuid::Uuid,
+    pool: web::Data<Pool>,
+    redis_pool: web::Data<RedisPool>,
+) -> Result<(), ServiceError> {
+    use crate::data::schema::chunk_group::dsl as chunk_group_columns;
+
+    if dataset_config.GROUP_CENTROIDS.is_none() {
+        return Ok(());
+    }
+
+    let mut conn = pool.get().await.map_err(|_e| {
+        ServiceError::InternalServerError("Failed to get postgres connection".to_string())
+    })?;
+
+    let mut last_group_id = uuid::Uuid::nil();
+    loop {
+        let group_ids = chunk_group_columns::chunk_group
+            .filter(chunk_group_columns::dataset_id.eq(dataset_id))
+            .filter(chunk_group_columns::id.gt(last_group_id))
+            .select(chunk_group_columns::id)
+            .order(chunk_group_columns::id)
+            .limit(5000)
+            .load::<uuid::Uuid>(&mut conn)
+            .await
+            .map_err(|err| {
+                log::error!("Failed to get group ids for dataset: {:?}", err);
+                ServiceError::BadRequest("Failed to get group ids for dataset".to_string())
+            })?;
+
+        let Some(last) = group_ids.last() else {
+            break;
+        };
+        last_group_id = *last;
+
+        mark_group_centroids_stale_query(dataset_config, dataset_id, group_ids, redis_pool.clone())
+            .await?;
+    }
+
+    Ok(())
+}
+
+/// Averages the member vectors of a group. Chunk weights are used when the options ask for it,
+/// with chunks lacking a positive weight counting as 1.
+pub fn get_group_centroid(
+    member_vectors: &[(Vec<f32>, f64)],
+    options: &GroupCentroidOptions,
+) -> Option<Vec<f32>> {
+    let dimensions = member_vectors.first()?.0.len();
+    let weighted = options.weighted.unwrap_or(false);
+
+    let mut centroid = vec![0.0_f64; dimensions];
+    let mut total_weight = 0.0_f64;
+
+    for (vector, weight) in member_vectors {
+        if vector.len() != dimensions {
+            continue;
+        }
+
+        let weight = if weighted && weight.is_finite() && *weight > 0.0 {
+            *weight
+        } else {
+            1.0
+        };
+
+        for (sum, value) in centroid.iter_mut().zip(vector) {
+            *sum += *value as f64 * weight;
+        }
+        total_weight += weight;
+    }
+
+    if total_weight == 0.0 {
+        return None;
+    }
+
+    Some(
+        centroid
+            .into_iter()
+            .map(|sum| (sum / total_weight) as f32)
+            .collect(),
+    )
+}
+
+/// Recomputes the centroids of the given groups from their current chunks. Centroids of groups
+/// which have been deleted or have no chunks with a dense vector are removed.
+pub async fn refresh_group_centroids_query(
+    dataset_id: uuid::Uuid,
+    group_ids: Vec<uuid::Uuid>,
+    pool: web::Data<Pool>,
+) -> Result<(), ServiceError> {
+    if group_ids.is_empty() {
+        return Ok(());
+    }
+
+    let dataset_config = get_dataset_config_query(dataset_id, pool.clone()).await?;
+    let Some(options) = dataset_config.GROUP_CENTROIDS.clone() else {
+        return Ok(());
+    };
+
+    let groups = get_groups_from_group_ids_query(group_ids.clone(), pool)
+        .await?
+        .into_iter()
+        .filter(|group| group.dataset_id == dataset_id)
+        .collect::<Vec<_>>();
+
+    let mut removed_group_ids = group_ids
+        .into_iter()
+        .filter(|group_id| !groups.iter().any(|group| group.id == *group_id))
+        .collect::<Vec<uuid::Uuid>>();
+
+    for group in groups {
+        let member_vectors =
+            get_group_member_vectors_query(group.id, dataset_id, &dataset_config).await?;
+
+        match get_group_centroid(&member_vectors, &options) {
+            Some(centroid) => {
+                upsert_group_centroid_query(&group, centroid, member_vectors.len(), &dataset_config)
+                    .await?
+            }
+            None => removed_group_ids.push(group.id),
+        }
+    }
+
+    delete_points_from_qdrant(
+        removed_group_ids,
+        get_group_centroid_collection_from_dataset_config(&dataset_config),
+    )
+    .await
+}
+
+/// Recomputes the centroids of up to `batch_size` groups queued by
+/// `mark_group_centroids_stale_query`. Returns the number of groups refreshed.
+pub async fn refresh_stale_group_centroids_query(
+    batch_size: usize,
+    redis_pool: web::Data<RedisPool>,
+    pool: web::Data<Pool>,
+) -> Result<usize, ServiceError> {
+    refresh_stale_groups_query(
+        STALE_GROUP_CENTROIDS_KEY,
+        batch_size,
+        redis_pool,
+        |dataset_id, group_ids| refresh_group_centroids_query(dataset_id, group_ids, pool.clone()),
+    )
+    .await
+}
@@ -1,9 +1,12 @@
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 
-use crate::data::models::{ChunkMetadataTags, DatasetTags};
+use crate::data::models::{ChunkChangeOperation, ChunkMetadataTags, DatasetTags, NewChunkChange};
 use crate::errors::ServiceError;
 use crate::get_env;
+use crate::operators::change_feed_operator::insert_chunk_changes_query;
+use crate::operators::group_summary_operator::get_group_summary_tracking_id;
 use crate::operators::qdrant_operator::{
+    delete_points_from_qdrant, get_group_centroid_collection_from_dataset_config,
     get_qdrant_collection_from_dataset_config, get_qdrant_connection,
     remove_bookmark_from_qdrant_query, update_group_tag_sets_in_qdrant_query,
 };
@@ -20,6 +23,7 @@ use diesel::prelude::*;
 use diesel::upsert::excluded;
 use diesel_async::scoped_futures::ScopedFutureExt;
 use diesel_async::{AsyncConnection, RunQueryDsl};
+use itertools::Itertools;
 use qdrant_client::qdrant;
 use serde::{Deserialize, Serialize};
 use utoipa::ToSchema;
@@ -135,6 +139,11 @@ pub async fn create_groups_query(
                 chunk_group_columns::description.eq(excluded(chunk_group_columns::description)),
                 chunk_group_columns::metadata.eq(excluded(chunk_group_columns::metadata)),
                 chunk_group_columns::tag_set.eq(excluded(chunk_group_columns::tag_set)),
+                chunk_group_columns::parent_id.eq(diesel::dsl::sql::<
+                    diesel::sql_types::Nullable<diesel::sql_types::Uuid>,
+                >(
+                    "COALESCE(excluded.parent_id, chunk_group.parent_id)",
+                )),
             ))
             .returning(ChunkGroup::as_select())
             .get_results::<ChunkGroup>(&mut conn)
@@ -231,6 +240,7 @@ pub async fn get_groups_for_dataset_cursor_query(
                 tag_set: group.tag_set,
                 metadata: group.metadata,
                 file_id,
+                parent_id: group.parent_id,
                 created_at: group.created_at,
                 updated_at: group.updated_at,
             }
@@ -314,6 +324,7 @@ pub async fn get_groups_for_dataset_page_query(
                 tag_set: group.tag_set,
                 metadata: group.metadata,
                 file_id,
+                parent_id: group.parent_id,
                 created_at: group.created_at,
                 updated_at: group.updated_at,
             }
@@ -433,6 +444,33 @@ pub async fn delete_group_by_id_query(
         });
 
         futures::future::join_all(remove_chunks_from_groups_futures).await;
+
+        // The group's summary chunk only makes sense as part of the group
+        let summary_tracking_id = get_group_summary_tracking_id(group_id);
+        let summary_chunk_ids = chunks
+            .iter()
+            .filter(|chunk| chunk.tracking_id.as_ref() == Some(&summary_tracking_id))
+            .map(|chunk| chunk.id)
+            .collect::<Vec<uuid::Uuid>>();
+
+        if transaction_result.is_ok() && !summary_chunk_ids.is_empty() {
+            delete_chunk_metadata_query(
+                summary_chunk_ids,
+                deleted_at,
+                dataset.clone(),
+                pool.clone(),
+                dataset_config.clone(),
+            )
+            .await?;
+        }
+    }
+
+    if transaction_result.is_ok() && dataset_config.GROUP_CENTROIDS.is_some() {
+        delete_points_from_qdrant(
+            vec![group_id],
+            get_group_centroid_collection_from_dataset_config(&dataset_config),
+        )
+        .await?;
     }
 
     match transaction_result {
@@ -496,6 +534,7 @@ pub async fn update_chunk_group_query(
         chunk_group_columns::tracking_id.eq(group.tracking_id),
         chunk_group_columns::metadata.eq(group.metadata),
         chunk_group_columns::tag_set.eq(group.tag_set),
+        chunk_group_columns::parent_id.eq(group.parent_id),
     ))
     .get_result(&mut conn)
     .await
@@ -970,7 +1009,7 @@ pub async fn update_grouped_chunks_query(
     }
 
     loop {
-        let chunk_point_ids: Vec<(uuid::Uuid, uuid::Uuid)> =
+        let chunk_point_ids: Vec<(uuid::Uuid, uuid::Uuid, Option<String>)> =
             chunk_group_bookmarks_columns::chunk_group_bookmarks
                 .inner_join(chunk_metadata_columns::chunk_metadata.on(
                     chunk_metadata_columns::id.eq(chunk_group_bookmarks_columns::chunk_metadata_id),
@@ -982,12 +1021,16 @@ pub async fn update_grouped_chunks_query(
                 .select((
                     chunk_metadata_columns::id,
                     chunk_metadata_columns::qdrant_point_id,
+                    chunk_metadata_columns::tracking_id,
                 ))
-                .load::<(uuid::Uuid, uuid::Uuid)>(&mut conn)
+                .load::<(uuid::Uuid, uuid::Uuid, Option<String>)>(&mut conn)
                 .await
                 .map_err(|_| ServiceError::BadRequest("Failed to load chunks".to_string()))?;
-        let (chunk_ids, point_ids): (Vec<uuid::Uuid>, Vec<uuid::Uuid>) =
-            chunk_point_ids.iter().cloned().unzip();
+        let chunk_ids: Vec<uuid::Uuid> = chunk_point_ids.iter().map(|(id, _, _)| *id).collect();
+        let point_ids: Vec<uuid::Uuid> = chunk_point_ids
+            .iter()
+            .map(|(_, point_id, _)| *point_id)
+            .collect();
 
         if chunk_ids.is_empty() {
             break;
@@ -1079,6 +1122,25 @@ pub async fn update_grouped_chunks_query(
             point_ids,
         )
         .await?;
+
+        if prev_group_tag_set != new_group_tag_set {
+            insert_chunk_changes_query(
+                chunk_point_ids
+                    .into_iter()
+                    .map(|(chunk_id, _, tracking_id)| {
+                        NewChunkChange::from_details(
+                            dataset_id,
+                            ChunkChangeOperation::Update,
+                            Some(chunk_id),
+                            tracking_id,
+                            vec!["group_tag_set".to_string()],
+                        )
+                    })
+                    .collect(),
+                pool.clone(),
+            )
+            .await?;
+        }
     }
 
     Ok(())
@@ -1176,3 +1238,396 @@ pub async fn get_group_size_query(
         "Error getting group size".to_string(),
     ))
 }
+
+/// Upper bound on how many levels the group hierarchy queries will walk. Cycles are rejected when
+/// parents are set, so this only guards against unexpectedly deep trees.
+pub const MAX_GROUP_HIERARCHY_DEPTH: i32 = 64;
+
+#[derive(QueryableByName)]
+struct GroupAncestorRow {
+    #[diesel(sql_type = diesel::sql_types::Uuid)]
+    group_id: uuid::Uuid,
+    #[diesel(sql_type = diesel::sql_types::Uuid)]
+    ancestor_id: uuid::Uuid,
+}
+
+#[derive(QueryableByName)]
+struct GroupIdRow {
+    #[diesel(sql_type = diesel::sql_types::Uuid)]
+    id: uuid::Uuid,
+}
+
+/// Gets the ancestor ids of each of the given groups, ordered from the direct parent up to the root.
+/// Groups without a parent are not present in the returned map.
+pub async fn get_group_ancestor_ids_query(
+    group_ids: Vec<uuid::Uuid>,
+    dataset_id: uuid::Uuid,
+    pool: web::Data<Pool>,
+) -> Result<HashMap<uuid::Uuid, Vec<uuid::Uuid>>, ServiceError> {
+    if group_ids.is_empty() {
+        return Ok(HashMap::new());
+    }
+
+    let mut conn = pool.get().await.map_err(|_e| {
+        ServiceError::InternalServerError("Failed to get postgres connection".to_string())
+    })?;
+
+    let rows = diesel::sql_query(
+        "WITH RECURSIVE ancestors AS (
+            SELECT chunk_group.id AS group_id, chunk_group.parent_id AS ancestor_id, 1 AS depth
+            FROM chunk_group
+            WHERE chunk_group.id = ANY($1)
+                AND chunk_group.dataset_id = $2
+                AND chunk_group.parent_id IS NOT NULL
+            UNION ALL
+            SELECT ancestors.group_id, chunk_group.parent_id, ancestors.depth + 1
+            FROM ancestors
+            JOIN chunk_group ON chunk_group.id = ancestors.ancestor_id
+            WHERE chunk_group.parent_id IS NOT NULL AND ancestors.depth < $3
+        )
+        SELECT group_id, ancestor_id FROM ancestors ORDER BY group_id, depth",
+    )
+    .bind::<diesel::sql_types::Array<diesel::sql_types::Uuid>, _>(&group_ids)
+    .bind::<diesel::sql_types::Uuid, _>(dataset_id)
+    .bind::<diesel::sql_types::Integer, _>(MAX_GROUP_HIERARCHY_DEPTH)
+    .load::<GroupAncestorRow>(&mut conn)
+    .await
+    .map_err(|err| {
+        log::error!("Error getting group ancestors {:?}", err);
+        ServiceError::BadRequest("Error getting group ancestors".to_string())
+    })?;
+
+    let mut ancestor_ids: HashMap<uuid::Uuid, Vec<uuid::Uuid>> = HashMap::new();
+    for row in rows {
+        ancestor_ids
+            .entry(row.group_id)
+            .or_default()
+            .push(row.ancestor_id);
+    }
+
+    Ok(ancestor_ids)
+}
+
+/// Gets the ids of the given groups and every group nested beneath them
+pub async fn get_descendant_group_ids_query(
+    group_ids: Vec<uuid::Uuid>,
+    dataset_id: uuid::Uuid,
+    pool: web::Data<Pool>,
+) -> Result<Vec<uuid::Uuid>, ServiceError> {
+    if group_ids.is_empty() {
+        return Ok(vec![]);
+    }
+
+    let mut conn = pool.get().await.map_err(|_e| {
+        ServiceError::InternalServerError("Failed to get postgres connection".to_string())
+    })?;
+
+    let rows = diesel::sql_query(
+        "WITH RECURSIVE descendants AS (
+            SELECT chunk_group.id, 0 AS depth
+            FROM chunk_group
+            WHERE chunk_group.id = ANY($1) AND chunk_group.dataset_id = $2
+            UNION ALL
+            SELECT chunk_group.id, descendants.depth + 1
+            FROM chunk_group
+            JOIN descendants ON chunk_group.parent_id = descendants.id
+            WHERE descendants.depth < $3
+        )
+        SELECT DISTINCT id FROM descendants",
+    )
+    .bind::<diesel::sql_types::Array<diesel::sql_types::Uuid>, _>(&group_ids)
+    .bind::<diesel::sql_types::Uuid, _>(dataset_id)
+    .bind::<diesel::sql_types::Integer, _>(MAX_GROUP_HIERARCHY_DEPTH)
+    .load::<GroupIdRow>(&mut conn)
+    .await
+    .map_err(|err| {
+        log::error!("Error getting group descendants {:?}", err);
+        ServiceError::BadRequest("Error getting group descendants".to_string())
+    })?;
+
+    Ok(rows.into_iter().map(|row| row.id).collect())
+}
+
+/// Gets the ancestors of a group, ordered from the direct parent up to the root
+pub async fn get_group_ancestors_query(
+    group_id: uuid::Uuid,
+    dataset_id: uuid::Uuid,
+    pool: web::Data<Pool>,
+) -> Result<Vec<ChunkGroupAndFileId>, ServiceError> {
+    let ancestor_ids = get_group_ancestor_ids_query(vec![group_id], dataset_id, pool.clone())
+        .await?
+        .remove(&group_id)
+        .unwrap_or_default();
+
+    let mut ancestors = get_groups_from_group_ids_query(ancestor_ids.clone(), pool).await?;
+    ancestors.sort_by_key(|group| {
+        ancestor_ids
+            .iter()
+            .position(|ancestor_id| *ancestor_id == group.id)
+    });
+
+    Ok(ancestors)
+}
+
+pub async fn get_group_children_query(
+    group_id: uuid::Uuid,
+    dataset_id: uuid::Uuid,
+    page: u64,
+    page_size: u64,
+    pool: web::Data<Pool>,
+) -> Result<(Vec<ChunkGroupAndFileId>, i64), ServiceError> {
+    use crate::data::schema::chunk_group::dsl as chunk_group_columns;
+    use crate::data::schema::groups_from_files::dsl as groups_from_files_columns;
+
+    let mut conn = pool.get().await.map_err(|_e| {
+        ServiceError::InternalServerError("Failed to get postgres connection".to_string())
+    })?;
+
+    let children: Vec<(ChunkGroup, Option<uuid::Uuid>)> = chunk_group_columns::chunk_group
+        .left_join(
+            groups_from_files_columns::groups_from_files
+                .on(chunk_group_columns::id.eq(groups_from_files_columns::group_id)),
+        )
+        .filter(chunk_group_columns::dataset_id.eq(dataset_id))
+        .filter(chunk_group_columns::parent_id.eq(group_id))
+        .order_by((
+            chunk_group_columns::created_at.asc(),
+            chunk_group_columns::id.asc(),
+        ))
+        .offset(((page.max(1) - 1) * page_size) as i64)
+        .limit(page_size as i64)
+        .select((
+            ChunkGroup::as_select(),
+            groups_from_files_columns::file_id.nullable(),
+        ))
+        .load::<(ChunkGroup, Option<uuid::Uuid>)>(&mut conn)
+        .await
+        .map_err(|err| {
+            log::error!("Error getting group children {:?}", err);
+            ServiceError::BadRequest("Error getting group children".to_string())
+        })?;
+
+    let children_count = chunk_group_columns::chunk_group
+        .filter(chunk_group_columns::dataset_id.eq(dataset_id))
+        .filter(chunk_group_columns::parent_id.eq(group_id))
+        .count()
+        .get_result::<i64>(&mut conn)
+        .await
+        .map_err(|err| {
+            log::error!("Error counting group children {:?}", err);
+            ServiceError::BadRequest("Error counting group children".to_string())
+        })?;
+
+    Ok((
+        children
+            .into_iter()
+            .map(|(group, file_id)| ChunkGroupAndFileId::from_group(group, file_id))
+            .collect(),
+        children_count,
+    ))
+}
+
+/// Resolves the parent referenced by id or tracking_id to the id of a group in the dataset
+pub async fn get_parent_group_id_query(
+    parent_id: Option<uuid::Uuid>,
+    parent_tracking_id: Option<String>,
+    dataset_id: uuid::Uuid,
+    pool: web::Data<Pool>,
+) -> Result<Option<uuid::Uuid>, ServiceError> {
+    match (parent_id, parent_tracking_id) {
+        (Some(_), Some(_)) => Err(ServiceError::BadRequest(
+            "Only one of parent_id or parent_tracking_id can be specified".to_string(),
+        )),
+        (Some(parent_id), None) => {
+            let existing_group_ids =
+                check_group_ids_exist_query(vec![parent_id], dataset_id, pool).await?;
+            if existing_group_ids.is_empty() {
+                return Err(ServiceError::NotFound(format!(
+                    "Parent group with id {} not found",
+                    parent_id
+                )));
+            }
+
+            Ok(Some(parent_id))
+        }
+        (None, Some(parent_tracking_id)) => {
+            let parent = get_group_from_tracking_id_query(parent_tracking_id, dataset_id, pool)
+                .await
+                .map_err(|_| ServiceError::NotFound("Parent group not found".to_string()))?;
+
+            Ok(Some(parent.id))
+        }
+        (None, None) => Ok(None),
+    }
+}
+
+/// Rejects making `parent_id` the parent of `group_id` when the group is the parent itself or one
+/// of its ancestors, as the hierarchy would then contain a cycle
+pub async fn check_group_parent_query(
+    group_id: uuid::Uuid,
+    parent_id: uuid::Uuid,
+    dataset_id: uuid::Uuid,
+    pool: web::Data<Pool>,
+) -> Result<(), ServiceError> {
+    if group_id == parent_id {
+        return Err(ServiceError::BadRequest(
+            "A group cannot be its own parent".to_string(),
+        ));
+    }
+
+    let parent_ancestor_ids = get_group_ancestor_ids_query(vec![parent_id], dataset_id, pool)
+        .await?
+        .remove(&parent_id)
+        .unwrap_or_default();
+
+    if parent_ancestor_ids.contains(&group_id) {
+        return Err(ServiceError::BadRequest(format!(
+            "Group {} is a descendant of group {}, so setting it as the parent would create a cycle",
+            parent_id, group_id
+        )));
+    }
+
+    if parent_ancestor_ids.len() as i32 + 1 >= MAX_GROUP_HIERARCHY_DEPTH {
+        return Err(ServiceError::BadRequest(format!(
+            "Groups cannot be nested more than {} levels deep",
+            MAX_GROUP_HIERARCHY_DEPTH
+        )));
+    }
+
+    Ok(())
+}
+
+/// Moves up to ARGV[1] members of the stale group set KEYS[1] into its processing set KEYS[2]
+const POP_STALE_GROUPS_SCRIPT: &str = r"
+local members = redis.call('SPOP', KEYS[1], ARGV[1])
+if #members > 0 then
+    redis.call('SADD', KEYS[2], unpack(members))
+end
+return members
+";
+
+fn get_stale_groups_processing_key(key: &str) -> String {
+    format!("{}_processing", key)
+}
+
+/// Refreshes up to `batch_size` groups of a redis set of stale `<dataset_id>:<group_id>` members,
+/// calling `refresh` once per dataset. Members are kept in a processing set until they are
+/// refreshed and are queued again if refreshing their dataset fails. Returns the number of groups
+/// refreshed.
+pub async fn refresh_stale_groups_query<F, Fut>(
+    key: &str,
+    batch_size: usize,
+    redis_pool: web::Data<RedisPool>,
+    mut refresh: F,
+) -> Result<usize, ServiceError>
+where
+    F: FnMut(uuid::Uuid, Vec<uuid::Uuid>) -> Fut,
+    Fut: std::future::Future<Output = Result<(), ServiceError>>,
+{
+    let processing_key = get_stale_groups_processing_key(key);
+
+    let mut redis_conn = redis_pool
+        .get()
+        .await
+        .map_err(|err| ServiceError::BadRequest(err.to_string()))?;
+
+    let members: Vec<String> = redis::Script::new(POP_STALE_GROUPS_SCRIPT)
+        .key(key)
+        .key(&processing_key)
+        .arg(batch_size)
+        .invoke_async(&mut *redis_conn)
+        .await
+        .map_err(|err| ServiceError::BadRequest(err.to_string()))?;
+    drop(redis_conn);
+
+    if members.is_empty() {
+        return Ok(0);
+    }
+
+    let members_by_dataset_id = members
+        .iter()
+        .filter_map(|member| {
+            let (dataset_id, group_id) = member.split_once(':')?;
+            Some((
+                uuid::Uuid::parse_str(dataset_id).ok()?,
+                (uuid::Uuid::parse_str(group_id).ok()?, member.clone()),
+            ))
+        })
+        .into_group_map();
+
+    let mut refreshed = 0;
+    let mut failed_members: Vec<String> = vec![];
+    let mut refresh_error = None;
+    for (dataset_id, dataset_members) in members_by_dataset_id {
+        let (group_ids, dataset_members): (Vec<uuid::Uuid>, Vec<String>) =
+            dataset_members.into_iter().unzip();
+
+        match refresh(dataset_id, group_ids).await {
+            Ok(()) => refreshed += dataset_members.len(),
+            Err(err) => {
+                log::error!(
+                    "Failed to refresh stale groups of dataset {}: {:?}",
+                    dataset_id,
+                    err
+                );
+                failed_members.extend(dataset_members);
+                refresh_error = Some(err);
+            }
+        }
+    }
+
+    let mut redis_conn = redis_pool
+        .get()
+        .await
+        .map_err(|err| ServiceError::BadRequest(err.to_string()))?;
+
+    let mut pipeline = redis::pipe();
+    pipeline.atomic();
+    if !failed_members.is_empty() {
+        pipeline.cmd("SADD").arg(key).arg(&failed_members).ignore();
+    }
+    pipeline
+        .cmd("SREM")
+        .arg(&processing_key)
+        .arg(&members)
+        .ignore();
+    pipeline
+        .query_async::<_, ()>(&mut *redis_conn)
+        .await
+        .map_err(|err| ServiceError::BadRequest(err.to_string()))?;
+
+    match refresh_error {
+        Some(err) => Err(err),
+        None => Ok(refreshed),
+    }
+}
+
+/// Moves the members a stopped worker left in the processing set of a stale group set back into
+/// the set. Members another worker is still refreshing may be moved too and get refreshed twice.
+pub async fn requeue_processing_stale_groups_query(
+    key: &str,
+    redis_pool: web::Data<RedisPool>,
+) -> Result<(), ServiceError> {
+    let processing_key = get_stale_groups_processing_key(key);
+
+    let mut redis_conn = redis_pool
+        .get()
+        .await
+        .map_err(|err| ServiceError::BadRequest(err.to_string()))?;
+
+    redis::pipe()
+        .atomic()
+        .cmd("SUNIONSTORE")
+        .arg(key)
+        .arg(key)
+        .arg(&processing_key)
+        .ignore()
+        .cmd("DEL")
+        .arg(&processing_key)
+        .ignore()
+        .query_async::<_, ()>(&mut *redis_conn)
+        .await
+        .map_err(|err| ServiceError::BadRequest(err.to_string()))?;
+
+    Ok(())
+}
@@ -0,0 +1,571 @@
+use std::collections::HashMap;
+
+use actix_web::web;
+use broccoli_queue::queue::BroccoliQueue;
+use diesel::dsl::sql;
+use diesel::prelude::*;
+use diesel::sql_types;
+use diesel_async::RunQueryDsl;
+use itertools::Itertools;
+use openai_dive::v1::{
+    api::Client,
+    resources::chat::{ChatCompletionParameters, ChatMessage, ChatMessageContent},
+};
+
+use crate::data::models::{
+    ChunkGroupAndFileId, DatasetConfiguration, GroupSummaryOptions, Pool, RedisPool,
+};
+use crate::errors::ServiceError;
+use crate::handlers::chunk_handler::ChunkReqPayload;
+use crate::operators::chunk_operator::create_chunk_metadata;
+use crate::operators::dataset_operator::get_dataset_config_query;
+use crate::operators::group_operator::get_groups_from_group_ids_query;
+use crate::operators::message_operator::get_llm_api_key;
+use crate::operators::parse_operator::convert_html_to_text;
+
+/// Redis sorted set of `<dataset_id>:<group_id>` members scored by the unix timestamp after which
+/// the group's summary should be regenerated
+const STALE_GROUP_SUMMARIES_KEY: &str = "group_summaries_stale";
+
+/// Redis hash of `<dataset_id>:<group_id>` members to the number of failed attempts to summarize
+/// the group since it last changed
+const GROUP_SUMMARY_ATTEMPTS_KEY: &str = "group_summaries_attempts";
+
+/// Groups whose summary failed this many times in a row are dropped until they change again
+const MAX_GROUP_SUMMARY_ATTEMPTS: i64 = 5;
+
+pub const GROUP_SUMMARY_TRACKING_ID_PREFIX: &str = "group_summary_";
+
+const DEFAULT_GROUP_SUMMARY_PROMPT: &str = "Write a concise overview of the following document in 3-5 sentences. Describe what the document is about and the main points it covers. Respond with only the overview.";
+
+pub fn get_group_summary_tracking_id(group_id: uuid::Uuid) -> String {
+    format!("{}{}", GROUP_SUMMARY_TRACKING_ID_PREFIX, group_id)
+}
+
+pub fn is_group_summary_tracking_id(tracking_id: Option<&str>) -> bool {
+    tracking_id.is_some_and(|tracking_id| tracking_id.starts_with(GROUP_SUMMARY_TRACKING_ID_PREFIX))
+}
+
+/// Queues the summaries of the given groups to be regenerated by the group update worker once
+/// the groups have gone `settle_seconds` without further changes. Does nothing if the dataset does
+/// not have group summaries enabled.
+pub async fn mark_group_summaries_stale_query(
+    dataset_config: &DatasetConfiguration,
+    dataset_id: uuid::Uuid,
+    group_ids: Vec<uuid::Uuid>,
+    redis_pool: web::Data<RedisPool>,
+) -> Result<(), ServiceError> {
+    let Some(options) = dataset_config.GROUP_SUMMARIES.as_ref() else {
+        return Ok(());
+    };
+    if group_ids.is_empty() {
+        return Ok(());
+    }
+
+    let due_at = chrono::Utc::now().timestamp() + options.settle_seconds.unwrap_or(60) as i64;
+
+    let mut redis_conn = redis_pool
+        .get()
+        .await
+        .map_err(|err| ServiceError::BadRequest(err.to_string()))?;
+
+    let members = group_ids
+        .iter()
+        .map(|group_id| format!("{}:{}", dataset_id, group_id))
+        .collect::<Vec<String>>();
+
+    // A change to the group starts its failed attempts over
+    let mut zadd = redis::cmd("ZADD");
+    zadd.arg(STALE_GROUP_SUMMARIES_KEY);
+    for member in &members {
+        zadd.arg(due_at).arg(member);
+    }
+
+    redis::pipe()
+        .atomic()
+        .add_command(zadd)
+        .ignore()
+        .cmd("HDEL")
+        .arg(GROUP_SUMMARY_ATTEMPTS_KEY)
+        .arg(&members)
+        .ignore()
+        .query_async::<_, ()>(&mut *redis_conn)
+        .await
+        .map_err(|err| ServiceError::BadRequest(err.to_string()))?;
+
+    Ok(())
+}
+
+/// Queues a group whose summary failed to be retried, doubling the settle time with every failed
+/// attempt. The group is dropped after `MAX_GROUP_SUMMARY_ATTEMPTS` failures.
+async fn retry_group_summary_query(
+    member: &str,
+    settle_seconds: u64,
+    redis_pool: web::Data<RedisPool>,
+) -> Result<(), ServiceError> {
+    let mut redis_conn = redis_pool
+        .get()
+        .await
+        .map_err(|err| ServiceError::BadRequest(err.to_string()))?;
+
+    let attempts: i64 = redis::cmd("HINCRBY")
+        .arg(GROUP_SUMMARY_ATTEMPTS_KEY)
+        .arg(member)
+        .arg(1)
+        .query_async(&mut *redis_conn)
+        .await
+        .map_err(|err| ServiceError::BadRequest(err.to_string()))?;
+
+    if attempts >= MAX_GROUP_SUMMARY_ATTEMPTS {
+        log::warn!(
+            "Giving up on summarizing group {} after {} attempts",
+            member,
+            attempts
+        );
+        return redis::cmd("HDEL")
+            .arg(GROUP_SUMMARY_ATTEMPTS_KEY)
+            .arg(member)
+            .query_async::<_, ()>(&mut *redis_conn)
+            .await
+            .map_err(|err| ServiceError::BadRequest(err.to_string()));
+    }
+
+    let due_at =
+        chrono::Utc::now().timestamp() + (settle_seconds.max(1) << attempts.min(10)) as i64;
+
+    redis::cmd("ZADD")
+        .arg(STALE_GROUP_SUMMARIES_KEY)
+        .arg(due_at)
+        .arg(member)
+        .query_async::<_, ()>(&mut *redis_conn)
+        .await
+        .map_err(|err| ServiceError::BadRequest(err.to_string()))
+}
+
+async fn clear_group_summary_attempts_query(
+    member: &str,
+    redis_pool: web::Data<RedisPool>,
+) -> Result<(), ServiceError> {
+    let mut redis_conn = redis_pool
+        .get()
+        .await
+        .map_err(|err| ServiceError::BadRequest(err.to_string()))?;
+
+    redis::cmd("HDEL")
+        .arg(GROUP_SUMMARY_ATTEMPTS_KEY)
+        .arg(member)
+        .query_async::<_, ()>(&mut *redis_conn)
+        .await
+        .map_err(|err| ServiceError::BadRequest(err.to_string()))
+}
+
+/// Only groups made from an uploaded file or a crawled page are summarized. Crawled page groups
+/// are recognized by their chunks carrying the `url` metadata the crawl worker sets.
+async fn is_file_or_crawl_group_query(
+    group: &ChunkGroupAndFileId,
+    pool: web::Data<Pool>,
+) -> Result<bool, ServiceError> {
+    use crate::data::schema::chunk_group_bookmarks::dsl as chunk_group_bookmarks_columns;
+    use crate::data::schema::chunk_metadata::dsl as chunk_metadata_columns;
+    use crate::data::schema::crawl_requests::dsl as crawl_requests_columns;
+
+    if group.file_id.is_some() {
+        return Ok(true);
+    }
+
+    let mut conn = pool.get().await.map_err(|_e| {
+        ServiceError::InternalServerError("Failed to get postgres connection".to_string())
+    })?;
+
+    let has_crawl = crawl_requests_columns::crawl_requests
+        .filter(crawl_requests_columns::dataset_id.eq(group.dataset_id))
+        .select(crawl_requests_columns::id)
+        .first::<uuid::Uuid>(&mut conn)
+        .await
+        .optional()
+        .map_err(|err| {
+            log::error!("Failed to check for dataset crawls: {:?}", err);
+            ServiceError::BadRequest("Failed to check for dataset crawls".to_string())
+        })?
+        .is_some();
+
+    if !has_crawl {
+        return Ok(false);
+    }
+
+    let crawled_chunk = chunk_group_bookmarks_columns::chunk_group_bookmarks
+        .inner_join(chunk_metadata_columns::chunk_metadata)
+        .filter(chunk_group_bookmarks_columns::group_id.eq(group.id))
+        .filter(sql::<sql_types::Bool>("chunk_metadata.metadata ? 'url'"))
+        .select(chunk_metadata_columns::id)
+        .first::<uuid::Uuid>(&mut conn)
+        .await
+        .optional()
+        .map_err(|err| {
+            log::error!("Failed to check for crawled chunks: {:?}", err);
+            ServiceError::BadRequest("Failed to check for crawled chunks".to_string())
+        })?;
+
+    Ok(crawled_chunk.is_some())
+}
+
+/// Loads the text of the first `max_chunks` chunks of the group in document order, leaving out
+/// the group's own summary chunk
+async fn get_group_summary_input_query(
+    group_id: uuid::Uuid,
+    max_chunks: u64,
+    pool: web::Data<Pool>,
+) -> Result<Vec<String>, ServiceError> {
+    use crate::data::schema::chunk_group_bookmarks::dsl as chunk_group_bookmarks_columns;
+    use crate::data::schema::chunk_metadata::dsl as chunk_metadata_columns;
+
+    let mut conn = pool.get().await.map_err(|_e| {
+        ServiceError::InternalServerError("Failed to get postgres connection".to_string())
+    })?;
+
+    let chunk_htmls =
+        chunk_group_bookmarks_columns::chunk_group_bookmarks
+            .inner_join(chunk_metadata_columns::chunk_metadata)
+            .filter(chunk_group_bookmarks_columns::group_id.eq(group_id))
+            .filter(chunk_metadata_columns::tracking_id.is_null().or(
+                chunk_metadata_columns::tracking_id.ne(get_group_summary_tracking_id(group_id)),
+            ))
+            .select(chunk_metadata_columns::chunk_html)
+            .order((
+                sql::<sql_types::Jsonb>("chunk_metadata.metadata->'source_position'").asc(),
+                chunk_metadata_columns::created_at.asc(),
+            ))
+            .limit(max_chunks as i64)
+            .load::<Option<String>>(&mut conn)
+            .await
+            .map_err(|err| {
+                log::error!("Failed to load chunks to summarize: {:?}", err);
+                ServiceError::BadRequest("Failed to load chunks to summarize".to_string())
+            })?;
+
+    Ok(chunk_htmls
+        .into_iter()
+        .flatten()
+        .map(|chunk_html| convert_html_to_text(&chunk_html))
+        .filter(|text| !text.trim().is_empty())
+        .collect())
+}
+
+async fn get_group_summary_completion(
+    group: &ChunkGroupAndFileId,
+    chunk_texts: Vec<String>,
+    options: &GroupSummaryOptions,
+    dataset_config: &DatasetConfiguration,
+) -> Result<String, ServiceError> {
+    let prompt = options
+        .prompt
+        .clone()
+        .unwrap_or(DEFAULT_GROUP_SUMMARY_PROMPT.to_string());
+
+    let llm_api_version = dataset_config.LLM_API_VERSION.clone();
+    let parameters = ChatCompletionParameters {
+        model: options
+            .model
+            .clone()
+            .unwrap_or(dataset_config.LLM_DEFAULT_MODEL.clone()),
+        messages: vec![ChatMessage::User {
+            content: ChatMessageContent::Text(format!(
+                "{}\n\nTitle: {}\n\n{}",
+                prompt,
+                group.name,
+                chunk_texts.join("\n\n")
+            )),
+            name: None,
+        }],
+        stream: Some(false),
+        temperature: dataset_config.TEMPERATURE.map(|temp| temp as f32),
+        max_completion_tokens: dataset_config.MAX_TOKENS.map(|max| max as u32),
+        query_params: llm_api_version.as_ref().map(|version| {
+            let mut map = std::collections::HashMap::new();
+            map.insert("api-version".to_string(), version.clone());
+            map
+        }),
+        ..Default::default()
+    };
+
+    let base_url = dataset_config.LLM_BASE_URL.clone();
+    let base_url = if base_url.is_empty() {
+        "https://openrouter.ai/api/v1".into()
+    } else {
+        base_url
+    };
+
+    let client = Client {
+        headers: None,
+        api_key: get_llm_api_key(dataset_config),
+        project: None,
+        http_client: reqwest::Client::new(),
+        base_url,
+        organization: None,
+    };
+
+    let completion = client.chat().create(parameters).await.map_err(|err| {
+        log::error!("No LLM completion for group summary: {:?}", err);
+        ServiceError::BadRequest(format!("No LLM completion for group summary {:?}", err))
+    })?;
+
+    let summary = match &completion
+        .choices
+        .first()
+        .ok_or(ServiceError::BadRequest(
+            "No response for LLM completion".to_string(),
+        ))?
+        .message
+    {
+        ChatMessage::Assistant {
+            content: Some(ChatMessageContent::Text(summary)),
+            ..
+        } => summary.trim().to_string(),
+        _ => "".to_string(),
+    };
+
+    Ok(summary)
+}
+
+/// Stores the summary under `summary` in the group's metadata, keeping the group's other keys
+async fn set_group_summary_metadata_query(
+    group_id: uuid::Uuid,
+    summary: &str,
+    pool: web::Data<Pool>,
+) -> Result<(), ServiceError> {
+    use crate::data::schema::chunk_group::dsl as chunk_group_columns;
+
+    let mut conn = pool.get().await.map_err(|_e| {
+        ServiceError::InternalServerError("Failed to get postgres connection".to_string())
+    })?;
+
+    diesel::update(chunk_group_columns::chunk_group.filter(chunk_group_columns::id.eq(group_id)))
+        .set(
+            chunk_group_columns::metadata.eq(sql::<sql_types::Nullable<sql_types::Jsonb>>(
+                "coalesce(chunk_group.metadata, '{}'::jsonb) || ",
+            )
+            .bind::<sql_types::Jsonb, _>(serde_json::json!({
+                "summary": summary,
+                "summary_updated_at": chrono::Utc::now().naive_local(),
+            }))),
+        )
+        .execute(&mut conn)
+        .await
+        .map_err(|err| {
+            log::error!("Failed to set group summary: {:?}", err);
+            ServiceError::BadRequest("Failed to set group summary".to_string())
+        })?;
+
+    Ok(())
+}
+
+/// Generates the summary of a group from its current chunks, then stores it in the group's
+/// metadata and queues the group's summary chunk to be upserted by the ingestion worker.
+pub async fn refresh_group_summary_query(
+    group: ChunkGroupAndFileId,
+    options: &GroupSummaryOptions,
+    dataset_config: &DatasetConfiguration,
+    pool: web::Data<Pool>,
+    broccoli_queue: web::Data<BroccoliQueue>,
+) -> Result<(), ServiceError> {
+    let chunk_texts =
+        get_group_summary_input_query(group.id, options.max_chunks.unwrap_or(50), pool.clone())
+            .await?;
+    if chunk_texts.is_empty() {
+        return Ok(());
+    }
+
+    let summary =
+        get_group_summary_completion(&group, chunk_texts, options, dataset_config).await?;
+    if summary.is_empty() {
+        return Err(ServiceError::BadRequest(
+            "LLM returned an empty group summary".to_string(),
+        ));
+    }
+
+    set_group_summary_metadata_query(group.id, &summary, pool).await?;
+
+    let summary_chunk = ChunkReqPayload {
+        chunk_html: Some(summary),
+        tag_set: group
+            .tag_set
+            .clone()
+            .map(|tag_set| tag_set.into_iter().flatten().collect()),
+        metadata: Some(serde_json::json!({
+            "group_summary": true,
+            "group_id": group.id,
+            "title": group.name,
+        })),
+        tracking_id: Some(get_group_summary_tracking_id(group.id)),
+        upsert_by_tracking_id: Some(true),
+        group_ids: Some(vec![group.id]),
+        ..Default::default()
+    };
+
+    let (ingestion_message, _) = create_chunk_metadata(vec![summary_chunk], group.dataset_id)?;
+
+    broccoli_queue
+        .publish(
+            "ingestion",
+            Some(group.dataset_id.to_string()),
+            &ingestion_message,
+            None,
+        )
+        .await
+        .map_err(|err| {
+            log::error!("Could not publish group summary chunk {:?}", err);
+            ServiceError::InternalServerError("Could not publish group summary chunk".to_string())
+        })?;
+
+    Ok(())
+}
+
+/// Regenerates the summaries of up to `batch_size` groups queued by
+/// `mark_group_summaries_stale_query` whose chunks have settled. Returns the number of groups
+/// summarized.
+pub async fn refresh_settled_group_summaries_query(
+    batch_size: usize,
+    redis_pool: web::Data<RedisPool>,
+    pool: web::Data<Pool>,
+    broccoli_queue: web::Data<BroccoliQueue>,
+) -> Result<usize, ServiceError> {
+    let mut redis_conn = redis_pool
+        .get()
+        .await
+        .map_err(|err| ServiceError::BadRequest(err.to_string()))?;
+
+    let members: Vec<String> = redis::cmd("ZRANGEBYSCORE")
+        .arg(STALE_GROUP_SUMMARIES_KEY)
+        .arg("-inf")
+        .arg(chrono::Utc::now().timestamp())
+        .arg("LIMIT")
+        .arg(0)
+        .arg(batch_size)
+        .query_async(&mut *redis_conn)
+        .await
+        .map_err(|err| ServiceError::BadRequest(err.to_string()))?;
+
+    if members.is_empty() {
+        return Ok(0);
+    }
+
+    let parsed_members = members
+        .iter()
+        .filter_map(|member| {
+            let (dataset_id, group_id) = member.split_once(':')?;
+            Some((
+                member.clone(),
+                uuid::Uuid::parse_str(dataset_id).ok()?,
+                uuid::Uuid::parse_str(group_id).ok()?,
+            ))
+        })
+        .collect::<Vec<(String, uuid::Uuid, uuid::Uuid)>>();
+
+    // Configs are loaded before the members leave the set. Members of datasets whose config
+    // fails to load stay queued and are retried later.
+    let mut dataset_configs = HashMap::new();
+    for dataset_id in parsed_members
+        .iter()
+        .map(|(_, dataset_id, _)| *dataset_id)
+        .unique()
+    {
+        let dataset_config = match get_dataset_config_query(dataset_id, pool.clone()).await {
+            Ok(dataset_config) => Some(dataset_config),
+            Err(err) => {
+                log::error!("Failed to get config of dataset {}: {:?}", dataset_id, err);
+                None
+            }
+        };
+        dataset_configs.insert(dataset_id, dataset_config);
+    }
+
+    let (parsed_members, failed_members): (Vec<_>, Vec<_>) =
+        parsed_members.into_iter().partition(|(_, dataset_id, _)| {
+            dataset_configs
+                .get(dataset_id)
+                .is_some_and(|dataset_config| dataset_config.is_some())
+        });
+
+    let removed_members = members
+        .iter()
+        .filter(|member| {
+            !failed_members
+                .iter()
+                .any(|(failed_member, _, _)| failed_member == *member)
+        })
+        .collect::<Vec<&String>>();
+
+    if !removed_members.is_empty() {
+        redis::cmd("ZREM")
+            .arg(STALE_GROUP_SUMMARIES_KEY)
+            .arg(removed_members)
+            .query_async::<_, ()>(&mut *redis_conn)
+            .await
+            .map_err(|err| ServiceError::BadRequest(err.to_string()))?;
+    }
+    drop(redis_conn);
+
+    for (member, _, _) in failed_members {
+        if let Err(err) = retry_group_summary_query(&member, 60, redis_pool.clone()).await {
+            log::error!("Failed to queue group {} for retry: {:?}", member, err);
+        }
+    }
+
+    let mut summarized = 0;
+    for (member, dataset_id, group_id) in parsed_members {
+        let Some(dataset_config) = dataset_configs.get(&dataset_id).and_then(Option::as_ref) else {
+            continue;
+        };
+        let Some(options) = dataset_config.GROUP_SUMMARIES.clone() else {
+            continue;
+        };
+
+        let summary_result = async {
+            let Some(group) = get_groups_from_group_ids_query(vec![group_id], pool.clone())
+                .await?
+                .into_iter()
+                .find(|group| group.dataset_id == dataset_id)
+            else {
+                return Ok(false);
+            };
+
+            if !is_file_or_crawl_group_query(&group, pool.clone()).await? {
+                return Ok(false);
+            }
+
+            refresh_group_summary_query(
+                group,
+                &options,
+                dataset_config,
+                pool.clone(),
+                broccoli_queue.clone(),
+            )
+            .await?;
+
+            Ok::<bool, ServiceError>(true)
+        }
+        .await;
+
+        match summary_result {
+            Ok(true) => summarized += 1,
+            Ok(false) => {}
+            Err(err) => {
+                log::error!("Failed to summarize group {}: {:?}", group_id, err);
+                if let Err(err) = retry_group_summary_query(
+                    &member,
+                    options.settle_seconds.unwrap_or(60),
+                    redis_pool.clone(),
+                )
+                .await
+                {
+                    log::error!("Failed to queue group {} for retry: {:?}", group_id, err);
+                }
+                continue;
+            }
+        }
+
+        let _ = clear_group_summary_attempts_query(&member, redis_pool.clone()).await;
+    }
+
+    Ok(summarized)
+}
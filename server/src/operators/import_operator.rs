@@ -0,0 +1,788 @@
+use std::collections::HashMap;
+use std::io::BufRead;
+use std::net::{IpAddr, SocketAddr};
+use std::path::{Path, PathBuf};
+
+use actix_web::web;
+use diesel::prelude::*;
+use diesel_async::RunQueryDsl;
+use itertools::Itertools;
+use parquet::file::reader::{FileReader, SerializedFileReader};
+use parquet::record::reader::RowIter;
+use parquet::record::RowAccessor;
+use qdrant_client::qdrant::{PointStruct, Vector};
+
+use crate::data::models::{
+    ChunkData, ChunkGroup, ChunkGroupAndFileId, ChunkMetadata, DatasetAndOrgWithSubAndPlan,
+    DatasetConfiguration, DatasetExportFormat, DatasetExportRecord, DatasetImport,
+    DatasetImportStatus, ExportedChunk, File, Pool, QdrantPayload,
+};
+use crate::errors::ServiceError;
+use crate::handlers::chunk_handler::{ChunkReqPayload, FullTextBoost, SemanticBoost};
+
+use super::chunk_operator::{
+    bulk_insert_chunk_metadata_query, get_row_count_for_organization_id_query,
+};
+use super::group_operator::{create_group_from_file_query, create_groups_query};
+use super::qdrant_operator::bulk_upsert_qdrant_points_query;
+
+pub async fn create_dataset_import_query(
+    dataset_import: DatasetImport,
+    pool: web::Data<Pool>,
+) -> Result<DatasetImport, ServiceError> {
+    use crate::data::schema::dataset_imports::dsl as dataset_imports_columns;
+
+    let mut conn = pool.get().await.map_err(|_e| {
+        ServiceError::InternalServerError("Failed to get postgres connection".to_string())
+    })?;
+
+    diesel::insert_into(dataset_imports_columns::dataset_imports)
+        .values(&dataset_import)
+        .get_result::<DatasetImport>(&mut conn)
+        .await
+        .map_err(|err| {
+            log::error!("Failed to create dataset import: {:?}", err);
+            ServiceError::InternalServerError("Failed to create dataset import".to_string())
+        })
+}
+
+pub async fn get_dataset_import_query(
+    import_id: uuid::Uuid,
+    dataset_id: uuid::Uuid,
+    pool: web::Data<Pool>,
+) -> Result<DatasetImport, ServiceError> {
+    use crate::data::schema::dataset_imports::dsl as dataset_imports_columns;
+
+    let mut conn = pool.get().await.map_err(|_e| {
+        ServiceError::InternalServerError("Failed to get postgres connection".to_string())
+    })?;
+
+    dataset_imports_columns::dataset_imports
+        .filter(dataset_imports_columns::id.eq(import_id))
+        .filter(dataset_imports_columns::dataset_id.eq(dataset_id))
+        .select(DatasetImport::as_select())
+        .first::<DatasetImport>(&mut conn)
+        .await
+        .map_err(|_| ServiceError::NotFound("Dataset import not found".to_string()))
+}
+
+pub async fn update_dataset_import_status_query(
+    import_id: uuid::Uuid,
+    status: DatasetImportStatus,
+    error: Option<String>,
+    pool: web::Data<Pool>,
+) -> Result<(), ServiceError> {
+    use crate::data::schema::dataset_imports::dsl as dataset_imports_columns;
+
+    let mut conn = pool.get().await.map_err(|_e| {
+        ServiceError::InternalServerError("Failed to get postgres connection".to_string())
+    })?;
+
+    diesel::update(
+        dataset_imports_columns::dataset_imports.filter(dataset_imports_columns::id.eq(import_id)),
+    )
+    .set((
+        dataset_imports_columns::status.eq(status.to_string()),
+        dataset_imports_columns::error.eq(error),
+        dataset_imports_columns::updated_at.eq(chrono::Utc::now().naive_local()),
+    ))
+    .execute(&mut conn)
+    .await
+    .map_err(|err| {
+        log::error!("Failed to update dataset import status: {:?}", err);
+        ServiceError::InternalServerError("Failed to update dataset import status".to_string())
+    })?;
+
+    Ok(())
+}
+
+pub async fn update_dataset_import_progress_query(
+    dataset_import: &DatasetImport,
+    pool: web::Data<Pool>,
+) -> Result<(), ServiceError> {
+    use crate::data::schema::dataset_imports::dsl as dataset_imports_columns;
+
+    let mut conn = pool.get().await.map_err(|_e| {
+        ServiceError::InternalServerError("Failed to get postgres connection".to_string())
+    })?;
+
+    diesel::update(
+        dataset_imports_columns::dataset_imports
+            .filter(dataset_imports_columns::id.eq(dataset_import.id)),
+    )
+    .set((
+        dataset_imports_columns::vectors_reused.eq(dataset_import.vectors_reused),
+        dataset_imports_columns::groups_imported.eq(dataset_import.groups_imported),
+        dataset_imports_columns::files_imported.eq(dataset_import.files_imported),
+        dataset_imports_columns::chunks_imported.eq(dataset_import.chunks_imported),
+        dataset_imports_columns::updated_at.eq(chrono::Utc::now().naive_local()),
+    ))
+    .execute(&mut conn)
+    .await
+    .map_err(|err| {
+        log::error!("Failed to update dataset import progress: {:?}", err);
+        ServiceError::InternalServerError("Failed to update dataset import progress".to_string())
+    })?;
+
+    Ok(())
+}
+
+/// Whether an import archive may be downloaded from `ip`. Private, loopback, link-local and other non-routable ranges are rejected so import urls cannot reach internal services.
+pub fn is_public_ip_address(ip: &IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            let [a, b, c, _] = ip.octets();
+            !(ip.is_private()
+                || ip.is_loopback()
+                || ip.is_link_local()
+                || ip.is_unspecified()
+                || ip.is_broadcast()
+                || ip.is_documentation()
+                || ip.is_multicast()
+                || a == 0
+                || a >= 240
+                || (a == 100 && (64..128).contains(&b))
+                || (a == 192 && b == 0 && c == 0)
+                || (a == 198 && (18..20).contains(&b)))
+        }
+        IpAddr::V6(ip) => {
+            if let Some(ip) = ip.to_ipv4_mapped() {
+                return is_public_ip_address(&IpAddr::V4(ip));
+            }
+            let [first, second, ..] = ip.segments();
+            !(ip.is_loopback()
+                || ip.is_unspecified()
+                || ip.is_multicast()
+                || (first & 0xfe00) == 0xfc00
+                || (first & 0xffc0) == 0xfe80
+                || (first == 0x2001 && second == 0x0db8))
+        }
+    }
+}
+
+/// Parses an import url, only https urls with a host are accepted.
+pub fn parse_import_url(url: &str) -> Result<reqwest::Url, ServiceError> {
+    let parsed_url = reqwest::Url::parse(url)
+        .map_err(|_| ServiceError::BadRequest("Invalid import url".to_string()))?;
+
+    if parsed_url.scheme() != "https" {
+        return Err(ServiceError::BadRequest(
+            "Import url must use https".to_string(),
+        ));
+    }
+    if parsed_url.host_str().is_none() {
+        return Err(ServiceError::BadRequest(
+            "Import url must have a host".to_string(),
+        ));
+    }
+
+    Ok(parsed_url)
+}
+
+/// Parses an import url and resolves its host. Fails if any resolved address is not public. The returned addresses should be pinned when downloading so the host cannot be re-resolved to an internal address.
+pub async fn resolve_import_url(
+    url: &str,
+) -> Result<(reqwest::Url, Vec<SocketAddr>), ServiceError> {
+    let parsed_url = parse_import_url(url)?;
+    let host = parsed_url
+        .host_str()
+        .unwrap_or_default()
+        .trim_start_matches('[')
+        .trim_end_matches(']')
+        .to_string();
+    let port = parsed_url.port_or_known_default().unwrap_or(443);
+
+    let addrs = tokio::net::lookup_host((host.as_str(), port))
+        .await
+        .map_err(|_| ServiceError::BadRequest("Failed to resolve import url".to_string()))?
+        .collect::<Vec<SocketAddr>>();
+
+    if addrs.is_empty() || addrs.iter().any(|addr| !is_public_ip_address(&addr.ip())) {
+        return Err(ServiceError::BadRequest(
+            "Import url must resolve to a public address".to_string(),
+        ));
+    }
+
+    Ok((parsed_url, addrs))
+}
+
+pub type DatasetExportRecords =
+    Box<dyn Iterator<Item = Result<DatasetExportRecord, ServiceError>> + Send>;
+
+/// Reads the records of an export archive in the order they were written.
+pub fn read_dataset_export_records(
+    path: &Path,
+    format: DatasetExportFormat,
+) -> Result<DatasetExportRecords, ServiceError> {
+    let file = std::fs::File::open(path).map_err(|err| {
+        log::error!("Failed to open import file: {:?}", err);
+        ServiceError::InternalServerError("Failed to open import file".to_string())
+    })?;
+
+    match format {
+        DatasetExportFormat::Jsonl => Ok(Box::new(
+            std::io::BufReader::new(file)
+                .lines()
+                .enumerate()
+                .filter(|(_, line)| line.as_ref().map_or(true, |line| !line.trim().is_empty()))
+                .map(|(line_number, line)| {
+                    let line = line.map_err(|err| {
+                        ServiceError::BadRequest(format!("Failed to read import file: {}", err))
+                    })?;
+                    serde_json::from_str::<DatasetExportRecord>(&line).map_err(|err| {
+                        ServiceError::BadRequest(format!(
+                            "Invalid record on line {}: {}",
+                            line_number + 1,
+                            err
+                        ))
+                    })
+                }),
+        )),
+        DatasetExportFormat::Parquet => {
+            let reader = SerializedFileReader::new(file).map_err(|err| {
+                ServiceError::BadRequest(format!("Invalid parquet file: {}", err))
+            })?;
+            let record_column_index = reader
+                .metadata()
+                .file_metadata()
+                .schema_descr()
+                .columns()
+                .iter()
+                .position(|column| column.name() == "record")
+                .ok_or(ServiceError::BadRequest(
+                    "Parquet file is missing the record column".to_string(),
+                ))?;
+
+            Ok(Box::new(
+                RowIter::from_file_into(Box::new(reader)).enumerate().map(
+                    move |(row_number, row)| {
+                        let row = row.map_err(|err| {
+                            ServiceError::BadRequest(format!(
+                                "Failed to read row {}: {}",
+                                row_number + 1,
+                                err
+                            ))
+                        })?;
+                        let record = row.get_string(record_column_index).map_err(|err| {
+                            ServiceError::BadRequest(format!(
+                                "Invalid record in row {}: {}",
+                                row_number + 1,
+                                err
+                            ))
+                        })?;
+                        serde_json::from_str::<DatasetExportRecord>(record).map_err(|err| {
+                            ServiceError::BadRequest(format!(
+                                "Invalid record in row {}: {}",
+                                row_number + 1,
+                                err
+                            ))
+                        })
+                    },
+                ),
+            ))
+        }
+    }
+}
+
+/// Opens an export archive on a blocking thread so reading the Parquet footer does not stall the runtime.
+pub async fn open_dataset_export_records(
+    path: PathBuf,
+    format: DatasetExportFormat,
+) -> Result<DatasetExportRecords, ServiceError> {
+    web::block(move || read_dataset_export_records(&path, format))
+        .await
+        .map_err(|err| ServiceError::InternalServerError(format!("Thread error {:?}", err)))?
+}
+
+/// Reads up to `batch_size` records on a blocking thread and hands the reader back alongside them. An empty batch means every record has been read.
+pub async fn read_dataset_export_record_batch(
+    mut records: DatasetExportRecords,
+    batch_size: usize,
+) -> Result<
+    (
+        DatasetExportRecords,
+        Vec<Result<DatasetExportRecord, ServiceError>>,
+    ),
+    ServiceError,
+> {
+    web::block(move || {
+        let batch = records.by_ref().take(batch_size).collect::<Vec<_>>();
+        (records, batch)
+    })
+    .await
+    .map_err(|err| ServiceError::InternalServerError(format!("Thread error {:?}", err)))
+}
+
+/// Exported vectors can only be upserted as-is when both datasets embed with the same model into the same qdrant vector.
+pub fn can_reuse_exported_vectors(
+    source_config: &DatasetConfiguration,
+    target_config: &DatasetConfiguration,
+) -> bool {
+    source_config.EMBEDDING_MODEL_NAME == target_config.EMBEDDING_MODEL_NAME
+        && source_config.EMBEDDING_SIZE == target_config.EMBEDDING_SIZE
+        && source_config.DISTANCE_METRIC == target_config.DISTANCE_METRIC
+}
+
+/// Recreates exported groups in the target dataset, preserving tracking ids. Groups without a tracking id use their exported id as one. Returns the new groups keyed by their exported id.
+pub async fn import_groups_query(
+    groups: Vec<ChunkGroupAndFileId>,
+    dataset_id: uuid::Uuid,
+    pool: web::Data<Pool>,
+) -> Result<HashMap<uuid::Uuid, ChunkGroup>, ServiceError> {
+    let exported_ids_by_tracking_id: HashMap<String, uuid::Uuid> = groups
+        .iter()
+        .map(|group| {
+            (
+                group.tracking_id.clone().unwrap_or(group.id.to_string()),
+                group.id,
+            )
+        })
+        .collect();
+
+    let created_groups = create_groups_query(
+        groups
+            .iter()
+            .map(|group| group.clone_group(dataset_id))
+            .collect(),
+        true,
+        pool,
+    )
+    .await?;
+
+    Ok(created_groups
+        .into_iter()
+        .filter_map(|group| {
+            let exported_id = exported_ids_by_tracking_id.get(group.tracking_id.as_ref()?)?;
+            Some((*exported_id, group))
+        })
+        .collect())
+}
+
+/// Recreates an exported file in the target dataset and links it to the group created for it. Archives do not carry file contents, so the object is only copied when the exported file still exists in a dataset of `organization_id`; ids of files owned by other organizations are skipped. Returns whether the file was imported.
+pub async fn import_file_query(
+    file: File,
+    dataset_id: uuid::Uuid,
+    organization_id: uuid::Uuid,
+    group_id: Option<uuid::Uuid>,
+    bucket: &s3::Bucket,
+    pool: web::Data<Pool>,
+) -> Result<bool, ServiceError> {
+    use crate::data::schema::datasets::dsl as datasets_columns;
+    use crate::data::schema::files::dsl as files_columns;
+
+    let mut conn = pool.get().await.map_err(|_e| {
+        ServiceError::InternalServerError("Failed to get postgres connection".to_string())
+    })?;
+
+    let source_file_id: Option<uuid::Uuid> = files_columns::files
+        .inner_join(datasets_columns::datasets)
+        .filter(files_columns::id.eq(file.id))
+        .filter(files_columns::dataset_id.eq(file.dataset_id))
+        .filter(datasets_columns::organization_id.eq(organization_id))
+        .select(files_columns::id)
+        .first(&mut conn)
+        .await
+        .optional()
+        .map_err(|err| {
+            log::error!("Failed to get source file: {:?}", err);
+            ServiceError::InternalServerError("Failed to get source file".to_string())
+        })?;
+    drop(conn);
+
+    if source_file_id.is_none() {
+        log::info!(
+            "Skipping file {:?} as it does not belong to a dataset of organization {:?}",
+            file.id,
+            organization_id
+        );
+        return Ok(false);
+    }
+
+    let new_file = File {
+        id: uuid::Uuid::new_v4(),
+        dataset_id,
+        ..file.clone()
+    };
+
+    if let Err(err) = bucket
+        .copy_object_internal(file.id.to_string(), new_file.id.to_string())
+        .await
+    {
+        log::info!(
+            "Skipping file {:?} as its object could not be copied: {:?}",
+            file.id,
+            err
+        );
+        return Ok(false);
+    }
+
+    let mut conn = pool.get().await.map_err(|_e| {
+        ServiceError::InternalServerError("Failed to get postgres connection".to_string())
+    })?;
+
+    diesel::insert_into(files_columns::files)
+        .values(&new_file)
+        .execute(&mut conn)
+        .await
+        .map_err(|err| ServiceError::BadRequest(format!("Could not create file {:?}", err)))?;
+
+    if let Some(group_id) = group_id {
+        create_group_from_file_query(group_id, new_file.id, pool.clone()).await?;
+    }
+
+    Ok(true)
+}
+
+fn get_imported_group_ids(
+    chunk: &ExportedChunk,
+    groups: &HashMap<uuid::Uuid, ChunkGroup>,
+) -> Option<Vec<uuid::Uuid>> {
+    let group_ids = chunk
+        .group_ids
+        .iter()
+        .filter_map(|group_id| groups.get(group_id).map(|group| group.id))
+        .collect::<Vec<uuid::Uuid>>();
+
+    if group_ids.is_empty() {
+        None
+    } else {
+        Some(group_ids)
+    }
+}
+
+/// Rejects adding `chunk_count` chunks to the dataset when the organization would exceed the
+/// chunk limit of its plan
+pub async fn check_import_chunk_limit_query(
+    dataset_org_plan_sub: &DatasetAndOrgWithSubAndPlan,
+    chunk_count: usize,
+    pool: web::Data<Pool>,
+) -> Result<(), ServiceError> {
+    let unlimited = std::env::var("UNLIMITED").unwrap_or("false".to_string());
+    if unlimited != "false" {
+        return Ok(());
+    }
+
+    let organization_chunk_count = get_row_count_for_organization_id_query(
+        dataset_org_plan_sub.organization.organization.id,
+        pool,
+    )
+    .await?;
+
+    if organization_chunk_count + chunk_count
+        > dataset_org_plan_sub
+            .organization
+            .plan
+            .clone()
+            .unwrap_or_default()
+            .chunk_count() as usize
+    {
+        return Err(ServiceError::BadRequest(
+            "Must upgrade your plan to add more chunks".to_string(),
+        ));
+    }
+
+    Ok(())
+}
+
+/// Inserts exported chunks into postgres and upserts their exported vectors directly into qdrant without re-embedding. Chunks are upserted by tracking id. Returns the number of chunks imported.
+pub async fn import_chunks_with_vectors_query(
+    chunks: Vec<ExportedChunk>,
+    groups: &HashMap<uuid::Uuid, ChunkGroup>,
+    dataset_id: uuid::Uuid,
+    dataset_config: DatasetConfiguration,
+    pool: web::Data<Pool>,
+) -> Result<usize, ServiceError> {
+    if chunks.is_empty() {
+        return Ok(0);
+    }
+
+    let mut vectors_by_id = HashMap::new();
+    let mut vectors_by_tracking_id = HashMap::new();
+    let chunk_datas = chunks
+        .into_iter()
+        .map(|exported_chunk| {
+            let group_ids = get_imported_group_ids(&exported_chunk, groups);
+            let chunk_metadata = ChunkMetadata {
+                id: uuid::Uuid::new_v4(),
+                qdrant_point_id: uuid::Uuid::new_v4(),
+                dataset_id,
+                ..exported_chunk.chunk
+            };
+
+            if let Some(tracking_id) = chunk_metadata.tracking_id.clone() {
+                vectors_by_tracking_id.insert(tracking_id, exported_chunk.vectors.clone());
+            }
+            vectors_by_id.insert(chunk_metadata.id, exported_chunk.vectors);
+
+            let boost = exported_chunk.boost;
+            ChunkData {
+                chunk_metadata,
+                content: String::new(),
+                embedding_content: String::new(),
+                fulltext_content: String::new(),
+                group_ids,
+                upsert_by_tracking_id: true,
+                fulltext_boost: boost.as_ref().and_then(|boost| {
+                    boost
+                        .fulltext_boost_phrase
+                        .clone()
+                        .map(|phrase| FullTextBoost {
+                            phrase,
+                            boost_factor: boost.fulltext_boost_factor.unwrap_or(0.0),
+                        })
+                }),
+                semantic_boost: boost.as_ref().and_then(|boost| {
+                    boost
+                        .semantic_boost_phrase
+                        .clone()
+                        .map(|phrase| SemanticBoost {
+                            phrase,
+                            distance_factor: boost.semantic_boost_factor.unwrap_or(0.0) as f32,
+                        })
+                }),
+            }
+        })
+        .collect::<Vec<ChunkData>>();
+
+    let inserted_chunks =
+        bulk_insert_chunk_metadata_query(chunk_datas, dataset_id, true, pool.clone()).await?;
+
+    let qdrant_points = inserted_chunks
+        .into_iter()
+        .map(|chunk_data| {
+            let vectors = vectors_by_id
+                .remove(&chunk_data.chunk_metadata.id)
+                .or_else(|| {
+                    chunk_data
+                        .chunk_metadata
+                        .tracking_id
+                        .as_ref()
+                        .and_then(|tracking_id| vectors_by_tracking_id.remove(tracking_id))
+                })
+                .unwrap_or_default();
+
+            let group_tag_set: Option<Vec<Option<String>>> =
+                chunk_data.group_ids.as_ref().map(|group_ids| {
+                    groups
+                        .values()
+                        .filter(|group| group_ids.contains(&group.id))
+                        .filter_map(|group| group.tag_set.clone())
+                        .flatten()
+                        .dedup()
+                        .collect()
+                });
+
+            let mut vector_payload: HashMap<String, Vector> = vectors
+                .dense_vectors
+                .into_iter()
+                .map(|(name, vector)| (name, Vector::from(vector)))
+                .collect();
+            for (name, sparse_vector) in vectors.sparse_vectors {
+                vector_payload.insert(
+                    name,
+                    Vector::from(
+                        sparse_vector
+                            .indices
+                            .into_iter()
+                            .zip(sparse_vector.values)
+                            .collect::<Vec<(u32, f32)>>(),
+                    ),
+                );
+            }
+            vector_payload
+                .entry("sparse_vectors".to_string())
+                .or_insert_with(|| Vector::from(vec![(0, 0.0)]));
+
+            PointStruct::new(
+                chunk_data.chunk_metadata.qdrant_point_id.to_string(),
+                vector_payload,
+                QdrantPayload::new(
+                    chunk_data.chunk_metadata,
+                    chunk_data.group_ids,
+                    None,
+                    group_tag_set,
+                ),
+            )
+        })
+        .collect::<Vec<PointStruct>>();
+
+    let imported_chunks = qdrant_points.len();
+    if imported_chunks > 0 {
+        bulk_upsert_qdrant_points_query(qdrant_points, dataset_config).await?;
+    }
+
+    Ok(imported_chunks)
+}
+
+/// Converts an exported chunk into a create payload so it can be re-embedded by the ingestion worker.
+pub fn exported_chunk_to_chunk_req_payload(
+    exported_chunk: ExportedChunk,
+    groups: &HashMap<uuid::Uuid, ChunkGroup>,
+) -> ChunkReqPayload {
+    let group_ids = get_imported_group_ids(&exported_chunk, groups);
+    let chunk = exported_chunk.chunk;
+    let boost = exported_chunk.boost;
+
+    ChunkReqPayload {
+        chunk_html: chunk.chunk_html,
+        link: chunk.link,
+        tag_set: chunk
+            .tag_set
+            .map(|tag_set| tag_set.into_iter().flatten().collect()),
+        num_value: chunk.num_value,
+        metadata: chunk.metadata,
+        tracking_id: chunk.tracking_id,
+        upsert_by_tracking_id: Some(true),
+        group_ids,
+        time_stamp: chunk.time_stamp.map(|ts| ts.to_string()),
+        location: chunk.location,
+        image_urls: chunk
+            .image_urls
+            .map(|urls| urls.into_iter().flatten().collect()),
+        weight: Some(chunk.weight),
+        fulltext_boost: boost.as_ref().and_then(|boost| {
+            boost
+                .fulltext_boost_phrase
+                .clone()
+                .map(|phrase| FullTextBoost {
+                    phrase,
+                    boost_factor: boost.fulltext_boost_factor.unwrap_or(0.0),
+                })
+        }),
+        semantic_boost: boost.as_ref().and_then(|boost| {
+            boost
+                .semantic_boost_phrase
+                .clone()
+                .map(|phrase| SemanticBoost {
+                    phrase,
+                    distance_factor: boost.semantic_boost_factor.unwrap_or(0.0) as f32,
+                })
+        }),
+        ..Default::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::operators::export_operator::DatasetExportWriter;
+
+    fn export_records(dataset_id: uuid::Uuid) -> Vec<DatasetExportRecord> {
+        let file = File::from_details(
+            None,
+            "handbook.pdf",
+            1024,
+            Some(vec![Some("docs".to_string())]),
+            Some(serde_json::json!({"pages": 3})),
+            Some("https://example.com/handbook.pdf".to_string()),
+            None,
+            dataset_id,
+        );
+        let now = chrono::Utc::now().naive_local();
+
+        let mut records = vec![DatasetExportRecord::File(file.clone())];
+        records.extend((0..3).map(|i| {
+            DatasetExportRecord::Group(ChunkGroupAndFileId {
+                id: uuid::Uuid::new_v4(),
+                dataset_id,
+                name: format!("group {}", i),
+                description: String::new(),
+                tracking_id: Some(format!("group-{}", i)),
+                tag_set: None,
+                metadata: Some(serde_json::json!({"position": i})),
+                file_id: (i == 0).then_some(file.id),
+                parent_id: None,
+                created_at: now,
+                updated_at: now,
+            })
+        }));
+        records
+    }
+
+    fn round_trip(format: DatasetExportFormat) {
+        let records = export_records(uuid::Uuid::new_v4());
+        let path = std::env::temp_dir().join(format!("{}.{}", uuid::Uuid::new_v4(), format));
+
+        let mut writer = DatasetExportWriter::new(&path, format).expect("Failed to create writer");
+        for record in records.clone() {
+            writer.write(record).expect("Failed to write record");
+        }
+        writer.finish().expect("Failed to finish export");
+
+        let read_records = read_dataset_export_records(&path, format)
+            .expect("Failed to open export")
+            .collect::<Result<Vec<_>, _>>();
+        std::fs::remove_file(&path).expect("Failed to remove export");
+
+        let read_records = read_records.expect("Failed to read records");
+        assert_eq!(
+            read_records
+                .iter()
+                .map(|record| serde_json::to_value(record).unwrap())
+                .collect::<Vec<_>>(),
+            records
+                .iter()
+                .map(|record| serde_json::to_value(record).unwrap())
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_jsonl_export_round_trip() {
+        round_trip(DatasetExportFormat::Jsonl);
+    }
+
+    #[test]
+    fn test_parquet_export_round_trip() {
+        round_trip(DatasetExportFormat::Parquet);
+    }
+
+    #[test]
+    fn test_is_public_ip_address() {
+        for ip in ["93.184.216.34", "2606:2800:220:1:248:1893:25c8:1946"] {
+            assert!(is_public_ip_address(&ip.parse().unwrap()), "{}", ip);
+        }
+
+        for ip in [
+            "127.0.0.1",
+            "10.0.0.1",
+            "172.16.0.1",
+            "192.168.1.1",
+            "169.254.169.254",
+            "100.64.0.1",
+            "0.0.0.0",
+            "::1",
+            "::",
+            "fd00::1",
+            "fe80::1",
+            "::ffff:127.0.0.1",
+            "::ffff:169.254.169.254",
+        ] {
+            assert!(!is_public_ip_address(&ip.parse().unwrap()), "{}", ip);
+        }
+    }
+
+    #[test]
+    fn test_parse_import_url() {
+        assert!(parse_import_url("https://example.com/export.parquet").is_ok());
+        assert!(parse_import_url("http://example.com/export.jsonl").is_err());
+        assert!(parse_import_url("file:///etc/passwd").is_err());
+        assert!(parse_import_url("not a url").is_err());
+    }
+
+    #[test]
+    fn test_read_invalid_jsonl_record() {
+        let path = std::env::temp_dir().join(format!("{}.jsonl", uuid::Uuid::new_v4()));
+        std::fs::write(&path, "\n{\"record_type\": \"unknown\"}\n").expect("Failed to write file");
+
+        let read_records = read_dataset_export_records(&path, DatasetExportFormat::Jsonl)
+            .expect("Failed to open export")
+            .collect::<Vec<_>>();
+        std::fs::remove_file(&path).expect("Failed to remove file");
+
+        assert_eq!(read_records.len(), 1);
+        assert!(
+            matches!(&read_records[0], Err(ServiceError::BadRequest(message)) if message.starts_with("Invalid record on line 2"))
+        );
+    }
+}
@@ -4,6 +4,7 @@ use itertools::Itertools;
 use openai_dive::v1::models::TranscriptionModel::Whisper1;
 use simple_server_timing_header::Timer;
 use simsearch::SimSearch;
+use std::collections::HashSet;
 use std::sync::atomic::{AtomicBool, AtomicU16, Ordering};
 use std::sync::{Arc, Mutex};
 
@@ -11,10 +12,10 @@ use std::sync::{Arc, Mutex};
 use crate::data::models::DummyHallucinationScore;
 use crate::data::models::{
     self, escape_quotes, ChunkMetadata, ChunkMetadataStringTagSet,
-    ChunkMetadataStringTagSetWithHighlightsScore, ChunkMetadataTypes, ConditionType, Dataset,
-    DatasetConfiguration, FieldCondition, LLMOptions, MultiQuery, QdrantChunkMetadata, QueryTypes,
-    RagQueryEventClickhouse, Range, RangeCondition, RedisPool, ScoreChunk, SearchMethod,
-    SearchModalities, SuggestType,
+    ChunkMetadataStringTagSetWithHighlightsScore, ChunkMetadataTypes, ConditionType, ContextWindow,
+    Dataset, DatasetConfiguration, FieldCondition, LLMOptions, MultiQuery, NewChunkMetadataTypes,
+    QdrantChunkMetadata, QueryTypes, RagQueryEventClickhouse, Range, RangeCondition, RedisPool,
+    ScoreChunk, SearchMethod, SearchModalities, SuggestType,
 };
 use crate::diesel::prelude::*;
 use crate::get_env;
@@ -56,8 +57,13 @@ use regex::Regex;
 use serde::{Deserialize, Serialize};
 use ureq::json;
 
-use super::chunk_operator::get_chunk_metadatas_from_point_ids;
+use super::chunk_operator::{
+    expand_score_chunks_with_context_query, get_chunk_metadatas_from_point_ids,
+    get_metadata_from_tracking_ids_query,
+};
 use super::clickhouse_operator::{get_latency_from_header, EventQueue};
+use super::group_operator::get_groups_for_bookmark_query;
+use super::group_summary_operator::get_group_summary_tracking_id;
 use super::model_operator::{count_message_tokens, count_tokens};
 use super::search_operator::{
     assemble_qdrant_filter, hybrid_search_over_groups, search_chunks_query, search_hybrid_chunks,
@@ -397,6 +403,21 @@ pub async fn delete_message_query(
     Ok(())
 }
 
+/// Describes where a chunk created from a PDF page was found, e.g. "file X, page 12", so RAG
+/// answers can cite it
+fn get_chunk_page_citation(chunk: &ChunkMetadata) -> Option<String> {
+    let metadata = chunk.metadata.as_ref()?;
+    let page_num = metadata.get("page_num")?.as_i64()?;
+
+    match metadata
+        .get("file_name")
+        .and_then(|file_name| file_name.as_str())
+    {
+        Some(file_name) => Some(format!("{}, page {}", file_name, page_num)),
+        None => Some(format!("page {}", page_num)),
+    }
+}
+
 #[allow(clippy::too_many_arguments)]
 #[tracing::instrument(skip_all)]
 pub async fn get_rag_chunks_query(
@@ -419,6 +440,10 @@ pub async fn get_rag_chunks_query(
 
     let use_message_to_query_prompt = dataset_config.USE_MESSAGE_TO_QUERY_PROMPT;
     let llm_api_version = dataset_config.LLM_API_VERSION.clone();
+    let context_window = create_message_req_payload.context_window.clone();
+    let include_group_summaries = create_message_req_payload
+        .include_group_summaries
+        .unwrap_or(false);
 
     if create_message_req_payload.search_query.is_none() && use_message_to_query_prompt {
         let message_to_query_prompt = dataset_config.MESSAGE_TO_QUERY_PROMPT.clone();
@@ -650,19 +675,31 @@ pub async fn get_rag_chunks_query(
                 ))
                 .await;
         }
+        let score_chunks = result_groups
+            .group_chunks
+            .into_iter()
+            .flat_map(|group_score_chunk| {
+                group_score_chunk
+                    .metadata
+                    .into_iter()
+                    .map(ScoreChunk::from)
+                    .collect::<Vec<ScoreChunk>>()
+            })
+            .collect::<Vec<ScoreChunk>>();
+
+        let score_chunks =
+            expand_rag_chunks_with_context(score_chunks, context_window, dataset.id, pool.clone())
+                .await?;
+
         Ok((
             clickhouse_search_event,
-            result_groups
-                .group_chunks
-                .into_iter()
-                .flat_map(|group_score_chunk| {
-                    group_score_chunk
-                        .metadata
-                        .into_iter()
-                        .map(ScoreChunk::from)
-                        .collect::<Vec<ScoreChunk>>()
-                })
-                .collect::<Vec<ScoreChunk>>(),
+            add_group_summaries_to_rag_chunks(
+                score_chunks,
+                include_group_summaries,
+                dataset.id,
+                pool,
+            )
+            .await?,
         ))
     } else {
         let search_chunk_data = SearchChunksReqPayload {
@@ -756,17 +793,124 @@ pub async fn get_rag_chunks_query(
                 ))
                 .await;
         }
+        let score_chunks = result_chunks
+            .score_chunks
+            .into_iter()
+            .map(ScoreChunk::from)
+            .collect::<Vec<ScoreChunk>>();
+
+        let score_chunks =
+            expand_rag_chunks_with_context(score_chunks, context_window, dataset.id, pool.clone())
+                .await?;
+
         Ok((
             clickhouse_search_event,
-            result_chunks
-                .score_chunks
-                .into_iter()
-                .map(ScoreChunk::from)
-                .collect::<Vec<ScoreChunk>>(),
+            add_group_summaries_to_rag_chunks(
+                score_chunks,
+                include_group_summaries,
+                dataset.id,
+                pool,
+            )
+            .await?,
         ))
     }
 }
 
+/// Attaches the surrounding chunks to each retrieved chunk and drops hits whose window was
+/// already included through an earlier, higher ranked hit.
+async fn expand_rag_chunks_with_context(
+    mut score_chunks: Vec<ScoreChunk>,
+    context_window: Option<ContextWindow>,
+    dataset_id: uuid::Uuid,
+    pool: web::Data<Pool>,
+) -> Result<Vec<ScoreChunk>, ServiceError> {
+    let Some(context_window) = context_window else {
+        return Ok(score_chunks);
+    };
+
+    expand_score_chunks_with_context_query(&mut score_chunks, &context_window, dataset_id, pool)
+        .await?;
+
+    let mut seen_windows: HashSet<Vec<uuid::Uuid>> = HashSet::new();
+    score_chunks.retain(|score_chunk| match &score_chunk.expanded_content {
+        Some(expanded_content) => seen_windows.insert(expanded_content.chunk_ids.clone()),
+        None => true,
+    });
+
+    Ok(score_chunks)
+}
+
+/// Places the summary chunk of each group a retrieved chunk belongs to ahead of the group's first
+/// retrieved chunk. Summaries which were retrieved on their own are not repeated.
+async fn add_group_summaries_to_rag_chunks(
+    score_chunks: Vec<ScoreChunk>,
+    include_group_summaries: bool,
+    dataset_id: uuid::Uuid,
+    pool: web::Data<Pool>,
+) -> Result<Vec<ScoreChunk>, ServiceError> {
+    if !include_group_summaries || score_chunks.is_empty() {
+        return Ok(score_chunks);
+    }
+
+    let chunk_ids = score_chunks
+        .iter()
+        .map(|score_chunk| ChunkMetadata::from(score_chunk.chunk.clone()).id)
+        .collect::<Vec<uuid::Uuid>>();
+
+    let groups_for_chunks =
+        get_groups_for_bookmark_query(chunk_ids.clone(), dataset_id, pool.clone()).await?;
+
+    let summary_tracking_ids = groups_for_chunks
+        .iter()
+        .flat_map(|groups_for_chunk| {
+            groups_for_chunk
+                .slim_groups
+                .iter()
+                .map(|group| get_group_summary_tracking_id(group.id))
+        })
+        .unique()
+        .collect::<Vec<String>>();
+    if summary_tracking_ids.is_empty() {
+        return Ok(score_chunks);
+    }
+
+    let summary_chunks =
+        get_metadata_from_tracking_ids_query(summary_tracking_ids, dataset_id, pool).await?;
+
+    let mut included_chunk_ids: HashSet<uuid::Uuid> = chunk_ids.iter().copied().collect();
+    let mut rag_chunks = Vec::with_capacity(score_chunks.len() + summary_chunks.len());
+    for (chunk_id, score_chunk) in chunk_ids.into_iter().zip(score_chunks) {
+        let groups = groups_for_chunks
+            .iter()
+            .find(|groups_for_chunk| groups_for_chunk.chunk_uuid == chunk_id)
+            .map(|groups_for_chunk| groups_for_chunk.slim_groups.as_slice())
+            .unwrap_or_default();
+
+        for group in groups {
+            let summary_tracking_id = get_group_summary_tracking_id(group.id);
+            let Some(summary_chunk) = summary_chunks
+                .iter()
+                .find(|chunk| chunk.tracking_id.as_ref() == Some(&summary_tracking_id))
+            else {
+                continue;
+            };
+
+            if included_chunk_ids.insert(summary_chunk.id) {
+                rag_chunks.push(ScoreChunk {
+                    chunk: NewChunkMetadataTypes::Metadata(summary_chunk.clone()),
+                    highlights: None,
+                    score: score_chunk.score,
+                    expanded_content: None,
+                });
+            }
+        }
+
+        rag_chunks.push(score_chunk);
+    }
+
+    Ok(rag_chunks)
+}
+
 pub fn clean_markdown(markdown_text: &str) -> String {
     let mut text = markdown_text.to_string();
 
@@ -911,13 +1055,19 @@ pub async fn stream_response(
             .iter()
             .enumerate()
             .map(|(idx, score_chunk)| {
-                json!({
+                let mut doc = json!({
                     "doc": idx + 1,
-                    "text": convert_html_to_text(&(ChunkMetadata::from(score_chunk.chunk.clone()).chunk_html.clone().unwrap_or_default())),
+                    "text": convert_html_to_text(&(match &score_chunk.expanded_content {
+                        Some(expanded_content) => expanded_content.content.clone(),
+                        None => ChunkMetadata::from(score_chunk.chunk.clone()).chunk_html.clone().unwrap_or_default(),
+                    })),
                     "num_value": ChunkMetadata::from(score_chunk.chunk.clone()).num_value.map(|x| format!("{} {}", create_message_req_payload.currency.clone().unwrap_or("".to_string()), x)).unwrap_or("".to_string()),
                     "link": ChunkMetadata::from(score_chunk.chunk.clone()).link.clone().unwrap_or_default()
-                })
-                .to_string()
+                });
+                if let Some(source) = get_chunk_page_citation(&ChunkMetadata::from(score_chunk.chunk.clone())) {
+                    doc["source"] = json!(source);
+                }
+                doc.to_string()
             })
             .collect::<Vec<String>>()
             .join("\n\n")
@@ -1,15 +1,29 @@
 pub mod analytics_operator;
+pub mod change_feed_operator;
 pub mod chunk_operator;
+pub mod chunking_operator;
 pub mod clickhouse_operator;
+pub mod clone_operator;
 pub mod crawl_operator;
+pub mod csv_jsonl_operator;
+pub mod dataset_alias_operator;
+pub mod dataset_config_version_operator;
 pub mod dataset_operator;
+pub mod dedup_operator;
 pub mod dittofeed_operator;
 pub mod email_operator;
 pub mod etl_operator;
 pub mod event_operator;
+pub mod event_webhook_operator;
 pub mod experiment_operator;
+pub mod export_operator;
+pub mod extraction_operator;
 pub mod file_operator;
+pub mod group_aggregate_operator;
+pub mod group_centroid_operator;
 pub mod group_operator;
+pub mod group_summary_operator;
+pub mod import_operator;
 pub mod invitation_operator;
 pub mod message_operator;
 pub mod model_operator;
@@ -18,6 +32,7 @@ pub mod pagefind_operator;
 pub mod parse_operator;
 pub mod payment_operator;
 pub mod qdrant_operator;
+pub mod reindex_operator;
 pub mod search_operator;
 pub mod topic_operator;
 pub mod typo_operator;
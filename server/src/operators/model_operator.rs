@@ -58,40 +58,7 @@ pub async fn get_dense_vector(
     _embed_type: &str,
     dataset_config: DatasetConfiguration,
 ) -> Result<Vec<f32>, ServiceError> {
-    let embedding_api_key = get_env!("OPENAI_API_KEY", "OPENAI_API_KEY should be set");
-    let config_embedding_base_url = dataset_config.EMBEDDING_BASE_URL;
-
-    let embedding_base_url = match config_embedding_base_url.as_str() {
-        "" => get_env!("OPENAI_BASE_URL", "OPENAI_BASE_URL must be set").to_string(),
-        "https://api.openai.com/v1" => {
-            get_env!("OPENAI_BASE_URL", "OPENAI_BASE_URL must be set").to_string()
-        }
-        "https://embedding.trieve.ai" => std::env::var("EMBEDDING_SERVER_ORIGIN")
-            .ok()
-            .filter(|s| !s.is_empty())
-            .unwrap_or("https://embedding.trieve.ai".to_string()),
-        "https://embedding.trieve.ai/bge-m3" => std::env::var("EMBEDDING_SERVER_ORIGIN_BGEM3")
-            .ok()
-            .filter(|s| !s.is_empty())
-            .unwrap_or("https://embedding.trieve.ai/bge-m3".to_string()),
-        "https://embedding.trieve.ai/jina-code" => {
-            std::env::var("EMBEDDING_SERVER_ORIGIN_JINA_CODE")
-                .ok()
-                .filter(|s| !s.is_empty())
-                .unwrap_or("https://embedding.trieve.ai/jina-code".to_string())
-        }
-        _ => config_embedding_base_url.clone(),
-    };
-
-    let embedding_api_key =
-        if config_embedding_base_url.as_str() == "https://embedding.trieve.ai/jina-code" {
-            std::env::var("JINA_CODE_API_KEY")
-                .ok()
-                .filter(|s| !s.is_empty())
-                .unwrap_or(embedding_api_key.to_string())
-        } else {
-            embedding_api_key.to_string()
-        };
+    let (embedding_base_url, embedding_api_key) = get_embedding_base_url_and_key(&dataset_config);
 
     let clipped_message: String = message.chars().take(20000).collect();
     let mut messages = vec![format!(
@@ -348,41 +315,7 @@ pub async fn get_dense_vectors(
     dataset_config: DatasetConfiguration,
     reqwest_client: reqwest::Client,
 ) -> Result<Vec<Vec<f32>>, ServiceError> {
-    let embedding_api_key = get_env!("OPENAI_API_KEY", "OPENAI_API_KEY should be set");
-    let config_embedding_base_url = dataset_config.EMBEDDING_BASE_URL;
-    let embedding_base_url = match config_embedding_base_url.as_str() {
-        "" => get_env!("OPENAI_BASE_URL", "OPENAI_BASE_URL must be set").to_string(),
-        "https://api.openai.com/v1" => {
-            get_env!("OPENAI_BASE_URL", "OPENAI_BASE_URL must be set").to_string()
-        }
-        "https://embedding.trieve.ai" => std::env::var("EMBEDDING_SERVER_ORIGIN")
-            .ok()
-            .filter(|s| !s.is_empty())
-            .unwrap_or("https://embedding.trieve.ai".to_string()),
-        "https://embedding.trieve.ai/bge-m3" => std::env::var("EMBEDDING_SERVER_ORIGIN_BGEM3")
-            .ok()
-            .filter(|s| !s.is_empty())
-            .unwrap_or("https://embedding.trieve.ai/bge-m3".to_string())
-            .to_string(),
-        "https://embedding.trieve.ai/jina-code" => {
-            std::env::var("EMBEDDING_SERVER_ORIGIN_JINA_CODE")
-                .ok()
-                .filter(|s| !s.is_empty())
-                .unwrap_or("https://embedding.trieve.ai/jina-code".to_string())
-                .to_string()
-        }
-        _ => config_embedding_base_url.clone(),
-    };
-
-    let embedding_api_key =
-        if config_embedding_base_url.as_str() == "https://embedding.trieve.ai/jina-code" {
-            std::env::var("JINA_CODE_API_KEY")
-                .ok()
-                .filter(|s| !s.is_empty())
-                .unwrap_or(embedding_api_key.to_string())
-        } else {
-            embedding_api_key.to_string()
-        };
+    let (embedding_base_url, embedding_api_key) = get_embedding_base_url_and_key(&dataset_config);
 
     let (contents, distance_phrases): (Vec<_>, Vec<_>) =
         content_and_distances.clone().into_iter().unzip();
@@ -1361,6 +1294,114 @@ pub fn count_tokens(text: &str) -> u64 {
     tokenize(text).len() as u64
 }
 
+fn get_embedding_base_url_and_key(dataset_config: &DatasetConfiguration) -> (String, String) {
+    let embedding_api_key = get_env!("OPENAI_API_KEY", "OPENAI_API_KEY should be set");
+    let config_embedding_base_url = dataset_config.EMBEDDING_BASE_URL.clone();
+
+    let embedding_base_url = match config_embedding_base_url.as_str() {
+        "" => get_env!("OPENAI_BASE_URL", "OPENAI_BASE_URL must be set").to_string(),
+        "https://api.openai.com/v1" => {
+            get_env!("OPENAI_BASE_URL", "OPENAI_BASE_URL must be set").to_string()
+        }
+        "https://embedding.trieve.ai" => std::env::var("EMBEDDING_SERVER_ORIGIN")
+            .ok()
+            .filter(|s| !s.is_empty())
+            .unwrap_or("https://embedding.trieve.ai".to_string()),
+        "https://embedding.trieve.ai/bge-m3" => std::env::var("EMBEDDING_SERVER_ORIGIN_BGEM3")
+            .ok()
+            .filter(|s| !s.is_empty())
+            .unwrap_or("https://embedding.trieve.ai/bge-m3".to_string()),
+        "https://embedding.trieve.ai/jina-code" => {
+            std::env::var("EMBEDDING_SERVER_ORIGIN_JINA_CODE")
+                .ok()
+                .filter(|s| !s.is_empty())
+                .unwrap_or("https://embedding.trieve.ai/jina-code".to_string())
+        }
+        _ => config_embedding_base_url.clone(),
+    };
+
+    let embedding_api_key =
+        if config_embedding_base_url.as_str() == "https://embedding.trieve.ai/jina-code" {
+            std::env::var("JINA_CODE_API_KEY")
+                .ok()
+                .filter(|s| !s.is_empty())
+                .unwrap_or(embedding_api_key.to_string())
+        } else {
+            embedding_api_key.to_string()
+        };
+
+    (embedding_base_url, embedding_api_key)
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct EmbeddingServerToken {
+    start: Option<usize>,
+    stop: Option<usize>,
+    special: bool,
+}
+
+/// Returns the byte offsets of each token in `text` as produced by the tokenizer of the dataset's
+/// embedding model. Only embedding servers exposing a `/tokenize` route (text-embeddings-inference)
+/// support this, otherwise an error is returned.
+pub async fn get_embedding_token_offsets(
+    text: &str,
+    dataset_config: &DatasetConfiguration,
+) -> Result<Vec<(usize, usize)>, ServiceError> {
+    let (embedding_base_url, embedding_api_key) = get_embedding_base_url_and_key(dataset_config);
+
+    let tokens = reqwest::Client::new()
+        .post(format!(
+            "{}/tokenize",
+            embedding_base_url
+                .trim_end_matches("/v1")
+                .trim_end_matches('/')
+        ))
+        .header("Authorization", &format!("Bearer {}", &embedding_api_key))
+        .header("api-key", &embedding_api_key)
+        .header("Content-Type", "application/json")
+        .timeout(std::time::Duration::from_secs(90))
+        .json(&serde_json::json!({
+            "inputs": text,
+            "add_special_tokens": false,
+        }))
+        .send()
+        .await
+        .map_err(|_| {
+            ServiceError::BadRequest("Failed to send message to embedding server".to_string())
+        })?
+        .error_for_status()
+        .map_err(|err| {
+            ServiceError::BadRequest(format!("Embedding server could not tokenize text {}", err))
+        })?
+        .json::<Vec<Vec<EmbeddingServerToken>>>()
+        .await
+        .map_err(|err| {
+            ServiceError::BadRequest(format!(
+                "Failed to get tokens from embedding server {}",
+                err
+            ))
+        })?
+        .pop()
+        .unwrap_or_default();
+
+    let offsets = tokens
+        .into_iter()
+        .filter(|token| !token.special)
+        .filter_map(|token| Some((token.start?, token.stop?)))
+        .collect::<Vec<(usize, usize)>>();
+
+    if offsets
+        .iter()
+        .any(|(start, stop)| !text.is_char_boundary(*start) || !text.is_char_boundary(*stop))
+    {
+        return Err(ServiceError::BadRequest(
+            "Embedding server returned token offsets which do not match the text".to_string(),
+        ));
+    }
+
+    Ok(offsets)
+}
+
 pub fn count_message_tokens(messages: Vec<ChatMessage>) -> u64 {
     messages
         .clone()
@@ -1,15 +1,21 @@
 use crate::{
     data::models::{
-        ApiKeyRespBody, Dataset, DatasetConfiguration, DateRange, Organization, OrganizationApiKey,
-        OrganizationUsageCount, OrganizationWithSubAndPlan, Pool, RedisPool, SlimUser, StripePlan,
-        StripeSubscription, StripeUsageBasedPlan, StripeUsageBasedSubscription, TrievePlan,
-        TrieveSubscription, User, UserApiKey, UserOrganization,
+        ApiKeyRespBody, Dataset, DatasetConfigVersionSource, DatasetConfiguration, DateRange,
+        Organization, OrganizationApiKey, OrganizationUsageCount, OrganizationWithSubAndPlan, Pool,
+        RedisPool, SlimUser, StripePlan, StripeSubscription, StripeUsageBasedPlan,
+        StripeUsageBasedSubscription, TrievePlan, TrieveSubscription, User, UserApiKey,
+        UserOrganization,
     },
     errors::ServiceError,
     handlers::organization_handler::{
         CreateApiKeyReqPayload, ExtendedOrganizationUsageCount, GetOrganizationApiKeysResponse,
     },
-    operators::dataset_operator::soft_delete_dataset_by_id_query,
+    operators::{
+        dataset_config_version_operator::{
+            check_reindex_required_config_changes, insert_dataset_config_version_query,
+        },
+        dataset_operator::soft_delete_dataset_by_id_query,
+    },
     utils::randutil,
 };
 use actix_web::{web, HttpRequest};
@@ -20,11 +26,13 @@ use diesel::{
     prelude::*, result::DatabaseErrorKind, sql_query, ExpressionMethods, JoinOnDsl,
     NullableExpressionMethods, SelectableHelper, Table,
 };
-use diesel_async::RunQueryDsl;
+use diesel_async::scoped_futures::ScopedFutureExt;
+use diesel_async::{AsyncConnection, RunQueryDsl};
 use itertools::Itertools;
 use rand::{distributions::Alphanumeric, Rng};
 use redis::AsyncCommands;
 use serde::Deserialize;
+use std::collections::HashMap;
 
 /// Creates a dataset from Name if it doesn't conflict. If it does, then it creates a random name
 /// for the user
@@ -987,16 +995,18 @@ pub async fn delete_actual_organization_query(
 
     Ok(())
 }
+/// Merges `new_config` into the configuration of every dataset in the organization which matches
+/// `match_config`. Each changed configuration is recorded as a new version of its dataset in the
+/// same transaction.
 pub async fn update_all_org_dataset_configs_query(
     org_id: uuid::Uuid,
     new_config: serde_json::Value,
     match_config: Option<serde_json::Value>,
+    acknowledge_reindex_required: bool,
+    changed_by: Option<uuid::Uuid>,
     pool: web::Data<Pool>,
 ) -> Result<(), ServiceError> {
-    let mut concat_configs_raw_query = format!(
-        "UPDATE datasets SET server_configuration = server_configuration || '{}' WHERE organization_id = '{}'",
-        new_config.to_string().replace('\'', "''"), org_id
-    );
+    let mut where_clause = format!("organization_id = '{}'", org_id);
 
     if let Some(match_config) = match_config {
         let match_config_query = match_config
@@ -1013,27 +1023,96 @@ pub async fn update_all_org_dataset_configs_query(
             .collect::<Vec<String>>()
             .join(" AND ");
 
-        concat_configs_raw_query.push_str(&format!(" AND ({})", match_config_query));
+        where_clause.push_str(&format!(" AND ({})", match_config_query));
     }
 
-    concat_configs_raw_query.push(';');
-
     let mut conn = pool.get().await.map_err(|_e| {
         ServiceError::InternalServerError("Failed to get postgres connection".to_string())
     })?;
 
-    sql_query(concat_configs_raw_query)
-        .execute(&mut conn)
-        .await
-        .map_err(|e| {
-            log::error!(
-                "Error updating datasets in update_all_org_dataset_configs: {:?}",
-                e
+    conn.transaction::<_, ServiceError, _>(|conn| {
+        async move {
+            let prev_datasets = sql_query(format!(
+                "SELECT * FROM datasets WHERE {} FOR UPDATE;",
+                where_clause
+            ))
+            .load::<Dataset>(conn)
+            .await
+            .map_err(|e| {
+                log::error!(
+                    "Error getting datasets in update_all_org_dataset_configs: {:?}",
+                    e
+                );
+                ServiceError::BadRequest("Error getting datasets".to_string())
+            })?;
+
+            let prev_configs = prev_datasets
+                .into_iter()
+                .map(|dataset| {
+                    (
+                        dataset.id,
+                        DatasetConfiguration::from_json(dataset.server_configuration),
+                    )
+                })
+                .collect::<HashMap<uuid::Uuid, DatasetConfiguration>>();
+
+            for prev_config in prev_configs.values() {
+                let mut merged_config = prev_config.to_json();
+                if let (Some(merged_fields), Some(new_fields)) =
+                    (merged_config.as_object_mut(), new_config.as_object())
+                {
+                    merged_fields.extend(new_fields.clone());
+                }
+
+                check_reindex_required_config_changes(
+                    prev_config,
+                    &DatasetConfiguration::from_json(merged_config),
+                    acknowledge_reindex_required,
+                )?;
+            }
+
+            let concat_configs_raw_query = format!(
+                "UPDATE datasets SET server_configuration = server_configuration || '{}' WHERE {} RETURNING *;",
+                new_config.to_string().replace('\'', "''"), where_clause
             );
-            ServiceError::BadRequest("Error updating datasets".to_string())
-        })?;
 
-    Ok(())
+            let updated_datasets = sql_query(concat_configs_raw_query)
+                .load::<Dataset>(conn)
+                .await
+                .map_err(|e| {
+                    log::error!(
+                        "Error updating datasets in update_all_org_dataset_configs: {:?}",
+                        e
+                    );
+                    ServiceError::BadRequest("Error updating datasets".to_string())
+                })?;
+
+            for dataset in updated_datasets {
+                if let Some(prev_config) = prev_configs.get(&dataset.id) {
+                    insert_dataset_config_version_query(
+                        dataset.id,
+                        prev_config,
+                        &DatasetConfiguration::from_json(dataset.server_configuration),
+                        DatasetConfigVersionSource::UpdateOrgDatasetConfigs,
+                        changed_by,
+                        None,
+                        conn,
+                    )
+                    .await
+                    .map_err(|e| {
+                        log::error!("Failed to record dataset configuration version: {:?}", e);
+                        ServiceError::InternalServerError(
+                            "Failed to record dataset configuration version".to_string(),
+                        )
+                    })?;
+                }
+            }
+
+            Ok(())
+        }
+        .scope_boxed()
+    })
+    .await
 }
 
 pub fn generate_api_key() -> String {
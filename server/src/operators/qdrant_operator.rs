@@ -1,10 +1,12 @@
 use super::{
     group_operator::get_groups_from_group_ids_query,
+    group_summary_operator::get_group_summary_tracking_id,
     search_operator::{assemble_qdrant_filter, SearchResult, SearchResultTrait},
 };
 use crate::{
     data::models::{
-        ChunkMetadata, DatasetConfiguration, DistanceMetric, Pool, QdrantPayload, RecommendType,
+        ChunkGroupAndFileId, ChunkMetadata, DatasetConfiguration, DistanceMetric,
+        ExportedPointVectors, ExportedSparseVector, Pool, QdrantPayload, RecommendType,
         RecommendationStrategy, SortByField, SortOrder,
     },
     errors::ServiceError,
@@ -17,16 +19,17 @@ use itertools::Itertools;
 use qdrant_client::{
     qdrant::{
         group_id::Kind, point_id::PointIdOptions, quantization_config::Quantization, query,
-        vectors::VectorsOptions, BinaryQuantization, CreateCollectionBuilder,
+        value::Kind as ValueKind, vectors::VectorsOptions, with_payload_selector,
+        with_vectors_selector, BinaryQuantization, Condition, CreateCollectionBuilder,
         CreateFieldIndexCollectionBuilder, DeleteFieldIndexCollectionBuilder, DeletePointsBuilder,
-        Distance, FieldType, Filter, GetPointsBuilder, HnswConfigDiff, OrderBy, PointId,
-        PointStruct, PrefetchQuery, QuantizationConfig, Query, QueryBatchPoints, QueryPointGroups,
-        QueryPoints, RecommendPointGroups, RecommendPoints, RecommendStrategy, RetrievedPoint,
-        ScrollPointsBuilder, SearchBatchPoints, SearchParams, SearchPointGroups, SearchPoints,
-        SetPayloadPointsBuilder, SparseIndexConfig, SparseVectorConfig, SparseVectorParams,
-        TextIndexParamsBuilder, TokenizerType, UpsertPointsBuilder, UuidIndexParamsBuilder, Value,
-        Vector, VectorInput, VectorParams, VectorParamsMap, VectorsConfig, WithPayloadSelector,
-        WithVectorsSelector,
+        Distance, FieldType, Filter, GetPointsBuilder, HnswConfigDiff, OrderBy,
+        PayloadIncludeSelector, PointId, PointStruct, PrefetchQuery, QuantizationConfig, Query,
+        QueryBatchPoints, QueryPointGroups, QueryPoints, RecommendPointGroups, RecommendPoints,
+        RecommendStrategy, RetrievedPoint, ScrollPointsBuilder, SearchBatchPoints, SearchParams,
+        SearchPointGroups, SearchPoints, SetPayloadPointsBuilder, SparseIndexConfig,
+        SparseVectorConfig, SparseVectorParams, TextIndexParamsBuilder, TokenizerType,
+        UpsertPointsBuilder, UuidIndexParamsBuilder, Value, Vector, VectorInput, VectorParams,
+        VectorParamsMap, VectorsConfig, VectorsSelector, WithPayloadSelector, WithVectorsSelector,
     },
     Payload, Qdrant,
 };
@@ -71,6 +74,16 @@ pub fn get_qdrant_collection_from_dataset_config(dataset_config: &DatasetConfigu
     }
 }
 
+/// Group centroids are kept in a collection alongside the one holding the dataset's chunks
+pub fn get_group_centroid_collection_from_dataset_config(
+    dataset_config: &DatasetConfiguration,
+) -> String {
+    format!(
+        "{}_group_centroids",
+        get_qdrant_collection_from_dataset_config(dataset_config)
+    )
+}
+
 /// Create Qdrant collection and indexes needed
 #[tracing::instrument(skip_all)]
 pub async fn create_new_qdrant_collection_query(
@@ -386,11 +399,112 @@ pub async fn create_new_qdrant_collection_query(
             ))
             .await
             .map_err(|_| ServiceError::BadRequest("Failed to create index".into()))?;
+
+        qdrant_client
+            .create_field_index(CreateFieldIndexCollectionBuilder::new(
+                collection_name.clone(),
+                "metadata.page_num",
+                FieldType::Integer,
+            ))
+            .await
+            .map_err(|_| ServiceError::BadRequest("Failed to create index".into()))?;
+
+        create_group_centroid_collection_query(
+            &qdrant_client,
+            &collection_name,
+            size,
+            distance,
+            replication_factor,
+            shard_number,
+        )
+        .await?;
     }
 
     Ok(())
 }
 
+/// Create the collection holding the centroids of the groups whose chunks are in `chunk_collection_name`
+async fn create_group_centroid_collection_query(
+    qdrant_client: &Qdrant,
+    chunk_collection_name: &str,
+    size: u64,
+    distance: Distance,
+    replication_factor: u32,
+    shard_number: u32,
+) -> Result<(), ServiceError> {
+    let collection_name = format!("{}_group_centroids", chunk_collection_name);
+
+    let collection = qdrant_client
+        .collection_exists(collection_name.clone())
+        .await
+        .map_err(|e| {
+            ServiceError::BadRequest(format!("Failed to see if collection exists {}", e))
+        })?;
+
+    if collection {
+        log::info!("Avoided creating group centroid collection as it already exists");
+    } else {
+        let vectors_hash_map = HashMap::from([(
+            format!("{}_vectors", size),
+            VectorParams {
+                size,
+                distance: distance.into(),
+                ..Default::default()
+            },
+        )]);
+
+        qdrant_client
+            .create_collection(
+                CreateCollectionBuilder::new(collection_name.clone())
+                    .vectors_config(VectorsConfig {
+                        config: Some(qdrant_client::qdrant::vectors_config::Config::ParamsMap(
+                            VectorParamsMap {
+                                map: vectors_hash_map,
+                            },
+                        )),
+                    })
+                    .write_consistency_factor(1)
+                    .replication_factor(replication_factor)
+                    .shard_number(shard_number),
+            )
+            .await
+            .map_err(|err| {
+                if err.to_string().contains("already exists") {
+                    return ServiceError::BadRequest("Collection already exists".into());
+                }
+                ServiceError::BadRequest(err.to_string())
+            })?;
+    }
+
+    qdrant_client
+        .create_field_index(
+            CreateFieldIndexCollectionBuilder::new(
+                collection_name.clone(),
+                "dataset_id",
+                FieldType::Uuid,
+            )
+            .field_index_params(
+                UuidIndexParamsBuilder::default()
+                    .is_tenant(true)
+                    .on_disk(false)
+                    .build(),
+            ),
+        )
+        .await
+        .map_err(|_| ServiceError::BadRequest("Failed to create index".into()))?;
+
+    qdrant_client
+        .create_field_index(CreateFieldIndexCollectionBuilder::new(
+            collection_name.clone(),
+            "tag_set",
+            FieldType::Keyword,
+        ))
+        .await
+        .map_err(|_| ServiceError::BadRequest("Failed to create index".into()))?;
+
+    Ok(())
+}
+
 #[tracing::instrument(skip_all)]
 pub async fn bulk_upsert_qdrant_points_query(
     points: Vec<PointStruct>,
@@ -609,6 +723,39 @@ pub async fn update_qdrant_point_query(
     Ok(())
 }
 
+/// Merges the given top-level keys into the payload of an existing point without touching its vectors
+#[tracing::instrument(skip_all)]
+pub async fn set_qdrant_point_payload_query(
+    point_id: uuid::Uuid,
+    payload: serde_json::Value,
+    dataset_config: DatasetConfiguration,
+) -> Result<(), ServiceError> {
+    let qdrant_collection = get_qdrant_collection_from_dataset_config(&dataset_config);
+
+    let qdrant_client = get_qdrant_connection(
+        Some(get_env!("QDRANT_URL", "QDRANT_URL should be set")),
+        Some(get_env!("QDRANT_API_KEY", "QDRANT_API_KEY should be set")),
+    )
+    .await?;
+
+    let payload: Payload = payload
+        .try_into()
+        .map_err(|_| ServiceError::BadRequest("Payload must be a JSON object".to_string()))?;
+
+    qdrant_client
+        .set_payload(
+            SetPayloadPointsBuilder::new(qdrant_collection, payload)
+                .points_selector(vec![PointId::from(point_id.to_string())]),
+        )
+        .await
+        .map_err(|err| {
+            log::error!("Failed setting chunk payload in qdrant {:?}", err);
+            ServiceError::BadRequest("Failed setting chunk payload in qdrant".to_string())
+        })?;
+
+    Ok(())
+}
+
 #[tracing::instrument(skip_all)]
 pub async fn add_bookmark_to_qdrant_query(
     point_id: uuid::Uuid,
@@ -1327,6 +1474,94 @@ pub async fn recommend_qdrant_query(
     Ok(recommended_point_ids)
 }
 
+/// Returns the closest point in the dataset scoring at least `score_threshold` for each vector, in
+/// the same order as `vectors`.
+#[tracing::instrument(skip_all)]
+pub async fn search_nearest_points_query(
+    vectors: Vec<Vec<f32>>,
+    score_threshold: f32,
+    dataset_id: uuid::Uuid,
+    dataset_config: DatasetConfiguration,
+) -> Result<Vec<Option<QdrantRecommendResult>>, ServiceError> {
+    if vectors.is_empty() {
+        return Ok(vec![]);
+    }
+
+    let qdrant_collection = get_qdrant_collection_from_dataset_config(&dataset_config);
+
+    let vector_name = match dataset_config.EMBEDDING_SIZE {
+        384 => "384_vectors",
+        512 => "512_vectors",
+        768 => "768_vectors",
+        1024 => "1024_vectors",
+        3072 => "3072_vectors",
+        1536 => "1536_vectors",
+        _ => {
+            return Err(ServiceError::BadRequest(
+                "Invalid embedding vector size".to_string(),
+            ))
+        }
+    };
+
+    let qdrant_client = get_qdrant_connection(
+        Some(get_env!("QDRANT_URL", "QDRANT_URL should be set")),
+        Some(get_env!("QDRANT_API_KEY", "QDRANT_API_KEY should be set")),
+    )
+    .await?;
+
+    let query_points = vectors
+        .into_iter()
+        .map(|vector| QueryPoints {
+            collection_name: qdrant_collection.clone(),
+            query: Some(Query::new_nearest(VectorInput::new_dense(vector))),
+            using: Some(vector_name.to_string()),
+            filter: Some(Filter::must([Condition::matches(
+                "dataset_id",
+                dataset_id.to_string(),
+            )])),
+            limit: Some(1),
+            score_threshold: Some(score_threshold),
+            with_payload: Some(WithPayloadSelector::from(false)),
+            with_vectors: Some(WithVectorsSelector::from(false)),
+            params: Some(SearchParams {
+                exact: Some(false),
+                indexed_only: Some(dataset_config.INDEXED_ONLY),
+                ..Default::default()
+            }),
+            timeout: Some(60),
+            ..Default::default()
+        })
+        .collect::<Vec<QueryPoints>>();
+
+    let nearest_points = qdrant_client
+        .query_batch(QueryBatchPoints {
+            collection_name: qdrant_collection,
+            query_points,
+            timeout: Some(60),
+            ..Default::default()
+        })
+        .await
+        .map_err(|err| {
+            log::error!("Failed to search nearest points on Qdrant {:?}", err);
+            ServiceError::BadRequest("Failed to search nearest points on Qdrant".to_string())
+        })?
+        .result
+        .into_iter()
+        .map(|batch_result| {
+            let point = batch_result.result.first()?;
+            match point.id.clone()?.point_id_options? {
+                PointIdOptions::Uuid(id) => Some(QdrantRecommendResult {
+                    point_id: uuid::Uuid::parse_str(&id).ok()?,
+                    score: point.score,
+                }),
+                PointIdOptions::Num(_) => None,
+            }
+        })
+        .collect();
+
+    Ok(nearest_points)
+}
+
 #[allow(clippy::too_many_arguments)]
 #[tracing::instrument(skip_all)]
 pub async fn recommend_qdrant_groups_query(
@@ -2058,3 +2293,354 @@ pub async fn scroll_dataset_points(
             }),
     ))
 }
+
+#[tracing::instrument(skip_all)]
+pub async fn get_point_vectors_query(
+    point_ids: Vec<uuid::Uuid>,
+    dataset_config: DatasetConfiguration,
+) -> Result<HashMap<uuid::Uuid, ExportedPointVectors>, ServiceError> {
+    if point_ids.is_empty() {
+        return Ok(HashMap::new());
+    }
+
+    let qdrant_collection = get_qdrant_collection_from_dataset_config(&dataset_config);
+
+    let qdrant_client = get_qdrant_connection(
+        Some(get_env!("QDRANT_URL", "QDRANT_URL should be set")),
+        Some(get_env!("QDRANT_API_KEY", "QDRANT_API_KEY should be set")),
+    )
+    .await?;
+
+    let qdrant_point_ids: Vec<PointId> = point_ids
+        .iter()
+        .map(|point_id| point_id.to_string().into())
+        .collect();
+
+    let points = qdrant_client
+        .get_points(
+            GetPointsBuilder::new(qdrant_collection, qdrant_point_ids)
+                .with_payload(false)
+                .with_vectors(true)
+                .build(),
+        )
+        .await
+        .map_err(|err| {
+            log::error!("Failed to get point vectors from qdrant: {:?}", err);
+            ServiceError::BadRequest("Failed to get point vectors from qdrant".to_string())
+        })?
+        .result;
+
+    let point_vectors = points
+        .into_iter()
+        .filter_map(|point| {
+            let point_id = match point.id?.point_id_options? {
+                PointIdOptions::Uuid(id) => uuid::Uuid::parse_str(&id).ok()?,
+                PointIdOptions::Num(_) => return None,
+            };
+
+            let mut exported_vectors = ExportedPointVectors::default();
+            if let Some(VectorsOptions::Vectors(named_vectors)) =
+                point.vectors.and_then(|v| v.vectors_options)
+            {
+                for (name, vector) in named_vectors.vectors {
+                    match vector.indices {
+                        Some(indices) => {
+                            exported_vectors.sparse_vectors.insert(
+                                name,
+                                ExportedSparseVector {
+                                    indices: indices.data,
+                                    values: vector.data,
+                                },
+                            );
+                        }
+                        None => {
+                            exported_vectors.dense_vectors.insert(name, vector.data);
+                        }
+                    }
+                }
+            }
+
+            Some((point_id, exported_vectors))
+        })
+        .collect();
+
+    Ok(point_vectors)
+}
+
+/// Gets the dense vector and weight of every chunk in a group
+#[tracing::instrument(skip_all)]
+pub async fn get_group_member_vectors_query(
+    group_id: uuid::Uuid,
+    dataset_id: uuid::Uuid,
+    dataset_config: &DatasetConfiguration,
+) -> Result<Vec<(Vec<f32>, f64)>, ServiceError> {
+    let qdrant_collection = get_qdrant_collection_from_dataset_config(dataset_config);
+    let vector_name = format!("{}_vectors", dataset_config.EMBEDDING_SIZE);
+
+    let qdrant_client = get_qdrant_connection(
+        Some(get_env!("QDRANT_URL", "QDRANT_URL should be set")),
+        Some(get_env!("QDRANT_API_KEY", "QDRANT_API_KEY should be set")),
+    )
+    .await?;
+
+    let mut member_vectors = vec![];
+    let mut offset: Option<PointId> = None;
+
+    loop {
+        let mut scroll_points_params = ScrollPointsBuilder::new(qdrant_collection.clone())
+            .filter(Filter {
+                must: vec![
+                    Condition::matches("dataset_id", dataset_id.to_string()),
+                    Condition::matches("group_ids", group_id.to_string()),
+                ],
+                must_not: vec![Condition::matches(
+                    "tracking_id",
+                    get_group_summary_tracking_id(group_id),
+                )],
+                ..Default::default()
+            })
+            .limit(1000)
+            .with_payload(WithPayloadSelector {
+                selector_options: Some(with_payload_selector::SelectorOptions::Include(
+                    PayloadIncludeSelector {
+                        fields: vec!["weight".to_string()],
+                    },
+                )),
+            })
+            .with_vectors(WithVectorsSelector {
+                selector_options: Some(with_vectors_selector::SelectorOptions::Include(
+                    VectorsSelector {
+                        names: vec![vector_name.clone()],
+                    },
+                )),
+            });
+
+        if let Some(offset) = offset.take() {
+            scroll_points_params = scroll_points_params.offset(offset);
+        }
+
+        let scroll_response = qdrant_client
+            .scroll(scroll_points_params)
+            .await
+            .map_err(|err| {
+                log::error!(
+                    "Failed to scroll group member vectors from qdrant: {:?}",
+                    err
+                );
+                ServiceError::BadRequest(
+                    "Failed to scroll group member vectors from qdrant".to_string(),
+                )
+            })?;
+
+        for point in scroll_response.result {
+            let vector = match point.vectors.and_then(|v| v.vectors_options) {
+                Some(VectorsOptions::Vectors(named_vectors)) => named_vectors
+                    .vectors
+                    .get(&vector_name)
+                    .map(|vector| vector.data.clone()),
+                Some(VectorsOptions::Vector(vector)) => Some(vector.data),
+                None => None,
+            };
+
+            let weight = match point.payload.get("weight").and_then(|w| w.kind.clone()) {
+                Some(ValueKind::DoubleValue(weight)) => weight,
+                Some(ValueKind::IntegerValue(weight)) => weight as f64,
+                _ => 1.0,
+            };
+
+            if let Some(vector) = vector {
+                member_vectors.push((vector, weight));
+            }
+        }
+
+        match scroll_response.next_page_offset {
+            Some(next_page_offset) => offset = Some(next_page_offset),
+            None => break,
+        }
+    }
+
+    Ok(member_vectors)
+}
+
+#[tracing::instrument(skip_all)]
+pub async fn upsert_group_centroid_query(
+    group: &ChunkGroupAndFileId,
+    centroid: Vec<f32>,
+    member_count: usize,
+    dataset_config: &DatasetConfiguration,
+) -> Result<(), ServiceError> {
+    let qdrant_collection = get_group_centroid_collection_from_dataset_config(dataset_config);
+
+    let payload: Payload = serde_json::json!({
+        "dataset_id": group.dataset_id.to_string(),
+        "tag_set": group
+            .tag_set
+            .clone()
+            .unwrap_or_default()
+            .into_iter()
+            .flatten()
+            .collect::<Vec<String>>(),
+        "member_count": member_count,
+    })
+    .try_into()
+    .map_err(|_| {
+        ServiceError::InternalServerError("Failed to create group centroid payload".to_string())
+    })?;
+
+    let point = PointStruct::new(
+        group.id.to_string(),
+        HashMap::from([(
+            format!("{}_vectors", dataset_config.EMBEDDING_SIZE),
+            Vector::from(centroid),
+        )]),
+        payload,
+    );
+
+    let qdrant_client = get_qdrant_connection(
+        Some(get_env!("QDRANT_URL", "QDRANT_URL should be set")),
+        Some(get_env!("QDRANT_API_KEY", "QDRANT_API_KEY should be set")),
+    )
+    .await?;
+
+    qdrant_client
+        .upsert_points(UpsertPointsBuilder::new(qdrant_collection, vec![point]))
+        .await
+        .map_err(|err| {
+            log::error!("Failed upserting group centroid to qdrant {:?}", err);
+            ServiceError::BadRequest("Failed upserting group centroid to qdrant".to_string())
+        })?;
+
+    Ok(())
+}
+
+/// Gets the centroid of a group along with the number of chunks it was computed from
+#[tracing::instrument(skip_all)]
+pub async fn get_group_centroid_query(
+    group_id: uuid::Uuid,
+    dataset_config: &DatasetConfiguration,
+) -> Result<Option<(Vec<f32>, u64)>, ServiceError> {
+    let qdrant_collection = get_group_centroid_collection_from_dataset_config(dataset_config);
+    let vector_name = format!("{}_vectors", dataset_config.EMBEDDING_SIZE);
+
+    let qdrant_client = get_qdrant_connection(
+        Some(get_env!("QDRANT_URL", "QDRANT_URL should be set")),
+        Some(get_env!("QDRANT_API_KEY", "QDRANT_API_KEY should be set")),
+    )
+    .await?;
+
+    let point = qdrant_client
+        .get_points(
+            GetPointsBuilder::new(qdrant_collection, vec![group_id.to_string().into()])
+                .with_payload(true)
+                .with_vectors(true)
+                .build(),
+        )
+        .await
+        .map_err(|err| {
+            log::error!("Failed to get group centroid from qdrant: {:?}", err);
+            ServiceError::BadRequest("Failed to get group centroid from qdrant".to_string())
+        })?
+        .result
+        .into_iter()
+        .next();
+
+    let Some(point) = point else {
+        return Ok(None);
+    };
+
+    let member_count = match point
+        .payload
+        .get("member_count")
+        .and_then(|c| c.kind.clone())
+    {
+        Some(ValueKind::IntegerValue(member_count)) => member_count.max(0) as u64,
+        Some(ValueKind::DoubleValue(member_count)) => member_count.max(0.0) as u64,
+        _ => 0,
+    };
+
+    let centroid = match point.vectors.and_then(|v| v.vectors_options) {
+        Some(VectorsOptions::Vectors(named_vectors)) => named_vectors
+            .vectors
+            .get(&vector_name)
+            .map(|vector| vector.data.clone()),
+        Some(VectorsOptions::Vector(vector)) => Some(vector.data),
+        None => None,
+    };
+
+    Ok(centroid.map(|centroid| (centroid, member_count)))
+}
+
+/// What to find the nearest group centroids to
+pub enum GroupCentroidQuery {
+    Vector(Vec<f32>),
+    /// The centroid of another group, which is excluded from the results
+    Group(uuid::Uuid),
+}
+
+/// Finds the groups of a dataset whose centroids are nearest to the query. Returns group ids and
+/// scores ordered by score.
+#[tracing::instrument(skip_all)]
+pub async fn search_group_centroids_query(
+    query: GroupCentroidQuery,
+    tag_set: Option<Vec<String>>,
+    limit: u64,
+    score_threshold: Option<f32>,
+    dataset_id: uuid::Uuid,
+    dataset_config: &DatasetConfiguration,
+) -> Result<Vec<(uuid::Uuid, f32)>, ServiceError> {
+    let qdrant_collection = get_group_centroid_collection_from_dataset_config(dataset_config);
+
+    let mut filter = Filter::must([Condition::matches("dataset_id", dataset_id.to_string())]);
+    if let Some(tag_set) = tag_set.filter(|tag_set| !tag_set.is_empty()) {
+        filter.must.push(Condition::matches("tag_set", tag_set));
+    }
+
+    let query = match query {
+        GroupCentroidQuery::Vector(vector) => VectorInput::new_dense(vector),
+        GroupCentroidQuery::Group(group_id) => {
+            filter
+                .must_not
+                .push(Condition::has_id([PointId::from(group_id.to_string())]));
+            VectorInput::new_id(PointId::from(group_id.to_string()))
+        }
+    };
+
+    let qdrant_client = get_qdrant_connection(
+        Some(get_env!("QDRANT_URL", "QDRANT_URL should be set")),
+        Some(get_env!("QDRANT_API_KEY", "QDRANT_API_KEY should be set")),
+    )
+    .await?;
+
+    let nearest_groups = qdrant_client
+        .query(QueryPoints {
+            collection_name: qdrant_collection,
+            query: Some(Query::new_nearest(query)),
+            using: Some(format!("{}_vectors", dataset_config.EMBEDDING_SIZE)),
+            filter: Some(filter),
+            limit: Some(limit),
+            score_threshold,
+            with_payload: Some(WithPayloadSelector::from(false)),
+            with_vectors: Some(WithVectorsSelector::from(false)),
+            params: Some(SearchParams {
+                exact: Some(false),
+                indexed_only: Some(dataset_config.INDEXED_ONLY),
+                ..Default::default()
+            }),
+            timeout: Some(60),
+            ..Default::default()
+        })
+        .await
+        .map_err(|err| {
+            log::error!("Failed to search group centroids on qdrant {:?}", err);
+            ServiceError::BadRequest("Failed to search group centroids on qdrant".to_string())
+        })?
+        .result
+        .into_iter()
+        .filter_map(|point| match point.id?.point_id_options? {
+            PointIdOptions::Uuid(id) => Some((uuid::Uuid::parse_str(&id).ok()?, point.score)),
+            PointIdOptions::Num(_) => None,
+        })
+        .collect();
+
+    Ok(nearest_groups)
+}
@@ -0,0 +1,579 @@
+use std::collections::HashSet;
+
+use actix_web::web;
+use diesel::dsl::sql;
+use diesel::prelude::*;
+use diesel::sql_types;
+use diesel_async::RunQueryDsl;
+use qdrant_client::qdrant::{Condition, Filter};
+
+use crate::data::models::{
+    DatasetConfigVersionSource, DatasetConfiguration, DatasetReindex, DatasetReindexStatus,
+    MigrationMode, Pool, RedisPool,
+};
+use crate::errors::ServiceError;
+
+use super::dataset_operator::{
+    get_dataset_by_id_query, get_dataset_config_query, update_dataset_query,
+};
+use super::group_centroid_operator::mark_dataset_group_centroids_stale_query;
+use super::qdrant_operator::{delete_points_from_qdrant, scroll_qdrant_collection_ids};
+
+pub async fn create_dataset_reindex_query(
+    dataset_reindex: DatasetReindex,
+    pool: web::Data<Pool>,
+) -> Result<DatasetReindex, ServiceError> {
+    use crate::data::schema::dataset_reindexes::dsl as dataset_reindexes_columns;
+
+    let mut conn = pool.get().await.map_err(|_e| {
+        ServiceError::InternalServerError("Failed to get postgres connection".to_string())
+    })?;
+
+    diesel::insert_into(dataset_reindexes_columns::dataset_reindexes)
+        .values(&dataset_reindex)
+        .get_result::<DatasetReindex>(&mut conn)
+        .await
+        .map_err(|err| {
+            log::error!("Failed to create dataset reindex: {:?}", err);
+            ServiceError::InternalServerError("Failed to create dataset reindex".to_string())
+        })
+}
+
+pub async fn get_dataset_reindex_query(
+    reindex_id: uuid::Uuid,
+    dataset_id: uuid::Uuid,
+    pool: web::Data<Pool>,
+) -> Result<DatasetReindex, ServiceError> {
+    use crate::data::schema::dataset_reindexes::dsl as dataset_reindexes_columns;
+
+    let mut conn = pool.get().await.map_err(|_e| {
+        ServiceError::InternalServerError("Failed to get postgres connection".to_string())
+    })?;
+
+    dataset_reindexes_columns::dataset_reindexes
+        .filter(dataset_reindexes_columns::id.eq(reindex_id))
+        .filter(dataset_reindexes_columns::dataset_id.eq(dataset_id))
+        .select(DatasetReindex::as_select())
+        .first::<DatasetReindex>(&mut conn)
+        .await
+        .map_err(|_| ServiceError::NotFound("Dataset reindex not found".to_string()))
+}
+
+pub async fn get_dataset_reindex_by_id_query(
+    reindex_id: uuid::Uuid,
+    pool: web::Data<Pool>,
+) -> Result<DatasetReindex, ServiceError> {
+    use crate::data::schema::dataset_reindexes::dsl as dataset_reindexes_columns;
+
+    let mut conn = pool.get().await.map_err(|_e| {
+        ServiceError::InternalServerError("Failed to get postgres connection".to_string())
+    })?;
+
+    dataset_reindexes_columns::dataset_reindexes
+        .filter(dataset_reindexes_columns::id.eq(reindex_id))
+        .select(DatasetReindex::as_select())
+        .first::<DatasetReindex>(&mut conn)
+        .await
+        .map_err(|_| ServiceError::NotFound("Dataset reindex not found".to_string()))
+}
+
+pub async fn get_active_dataset_reindex_query(
+    dataset_id: uuid::Uuid,
+    pool: web::Data<Pool>,
+) -> Result<Option<DatasetReindex>, ServiceError> {
+    use crate::data::schema::dataset_reindexes::dsl as dataset_reindexes_columns;
+
+    let mut conn = pool.get().await.map_err(|_e| {
+        ServiceError::InternalServerError("Failed to get postgres connection".to_string())
+    })?;
+
+    dataset_reindexes_columns::dataset_reindexes
+        .filter(dataset_reindexes_columns::dataset_id.eq(dataset_id))
+        .filter(dataset_reindexes_columns::status.eq_any(vec![
+            DatasetReindexStatus::Pending.to_string(),
+            DatasetReindexStatus::Processing.to_string(),
+        ]))
+        .select(DatasetReindex::as_select())
+        .first::<DatasetReindex>(&mut conn)
+        .await
+        .optional()
+        .map_err(|err| {
+            log::error!("Failed to get active dataset reindex: {:?}", err);
+            ServiceError::InternalServerError("Failed to get active dataset reindex".to_string())
+        })
+}
+
+pub async fn update_dataset_reindex_status_query(
+    reindex_id: uuid::Uuid,
+    status: DatasetReindexStatus,
+    error: Option<String>,
+    pool: web::Data<Pool>,
+) -> Result<(), ServiceError> {
+    use crate::data::schema::dataset_reindexes::dsl as dataset_reindexes_columns;
+
+    let mut conn = pool.get().await.map_err(|_e| {
+        ServiceError::InternalServerError("Failed to get postgres connection".to_string())
+    })?;
+
+    diesel::update(
+        dataset_reindexes_columns::dataset_reindexes
+            .filter(dataset_reindexes_columns::id.eq(reindex_id)),
+    )
+    .set((
+        dataset_reindexes_columns::status.eq(status.to_string()),
+        dataset_reindexes_columns::error.eq(error),
+        dataset_reindexes_columns::updated_at.eq(chrono::Utc::now().naive_local()),
+    ))
+    .execute(&mut conn)
+    .await
+    .map_err(|err| {
+        log::error!("Failed to update dataset reindex status: {:?}", err);
+        ServiceError::InternalServerError("Failed to update dataset reindex status".to_string())
+    })?;
+
+    Ok(())
+}
+
+/// Moves a pending reindex into processing and records when it started, which is the watermark
+/// writes are replayed from once the migration completes. Returns whether this call started it.
+pub async fn start_dataset_reindex_query(
+    reindex_id: uuid::Uuid,
+    pool: web::Data<Pool>,
+) -> Result<bool, ServiceError> {
+    use crate::data::schema::dataset_reindexes::dsl as dataset_reindexes_columns;
+
+    let mut conn = pool.get().await.map_err(|_e| {
+        ServiceError::InternalServerError("Failed to get postgres connection".to_string())
+    })?;
+
+    let started = diesel::update(
+        dataset_reindexes_columns::dataset_reindexes
+            .filter(dataset_reindexes_columns::id.eq(reindex_id))
+            .filter(
+                dataset_reindexes_columns::status.eq(DatasetReindexStatus::Pending.to_string()),
+            ),
+    )
+    .set((
+        dataset_reindexes_columns::status.eq(DatasetReindexStatus::Processing.to_string()),
+        dataset_reindexes_columns::started_at.eq(Some(chrono::Utc::now().naive_local())),
+        dataset_reindexes_columns::updated_at.eq(chrono::Utc::now().naive_local()),
+    ))
+    .execute(&mut conn)
+    .await
+    .map_err(|err| {
+        log::error!("Failed to start dataset reindex: {:?}", err);
+        ServiceError::InternalServerError("Failed to start dataset reindex".to_string())
+    })?;
+
+    Ok(started > 0)
+}
+
+/// Cancels a reindex which has not finished yet. Returns the cancelled reindex.
+pub async fn cancel_dataset_reindex_query(
+    reindex_id: uuid::Uuid,
+    dataset_id: uuid::Uuid,
+    pool: web::Data<Pool>,
+) -> Result<DatasetReindex, ServiceError> {
+    use crate::data::schema::dataset_reindexes::dsl as dataset_reindexes_columns;
+
+    let mut conn = pool.get().await.map_err(|_e| {
+        ServiceError::InternalServerError("Failed to get postgres connection".to_string())
+    })?;
+
+    diesel::update(
+        dataset_reindexes_columns::dataset_reindexes
+            .filter(dataset_reindexes_columns::id.eq(reindex_id))
+            .filter(dataset_reindexes_columns::dataset_id.eq(dataset_id))
+            .filter(dataset_reindexes_columns::status.eq_any(vec![
+                DatasetReindexStatus::Pending.to_string(),
+                DatasetReindexStatus::Processing.to_string(),
+            ])),
+    )
+    .set((
+        dataset_reindexes_columns::status.eq(DatasetReindexStatus::Cancelled.to_string()),
+        dataset_reindexes_columns::updated_at.eq(chrono::Utc::now().naive_local()),
+    ))
+    .get_result::<DatasetReindex>(&mut conn)
+    .await
+    .map_err(|_| {
+        ServiceError::BadRequest("Dataset reindex not found or already finished".to_string())
+    })
+}
+
+pub async fn set_dataset_reindex_total_query(
+    reindex_id: uuid::Uuid,
+    points_total: i64,
+    pool: web::Data<Pool>,
+) -> Result<(), ServiceError> {
+    use crate::data::schema::dataset_reindexes::dsl as dataset_reindexes_columns;
+
+    let mut conn = pool.get().await.map_err(|_e| {
+        ServiceError::InternalServerError("Failed to get postgres connection".to_string())
+    })?;
+
+    diesel::update(
+        dataset_reindexes_columns::dataset_reindexes
+            .filter(dataset_reindexes_columns::id.eq(reindex_id)),
+    )
+    .set((
+        dataset_reindexes_columns::points_total.eq(Some(points_total)),
+        dataset_reindexes_columns::updated_at.eq(chrono::Utc::now().naive_local()),
+    ))
+    .execute(&mut conn)
+    .await
+    .map_err(|err| {
+        log::error!("Failed to set dataset reindex total: {:?}", err);
+        ServiceError::InternalServerError("Failed to set dataset reindex total".to_string())
+    })?;
+
+    Ok(())
+}
+
+pub async fn add_dataset_reindex_progress_query(
+    reindex_id: uuid::Uuid,
+    points_migrated: i64,
+    points_failed: i64,
+    pool: web::Data<Pool>,
+) -> Result<(), ServiceError> {
+    use crate::data::schema::dataset_reindexes::dsl as dataset_reindexes_columns;
+
+    let mut conn = pool.get().await.map_err(|_e| {
+        ServiceError::InternalServerError("Failed to get postgres connection".to_string())
+    })?;
+
+    diesel::update(
+        dataset_reindexes_columns::dataset_reindexes
+            .filter(dataset_reindexes_columns::id.eq(reindex_id)),
+    )
+    .set((
+        dataset_reindexes_columns::points_migrated
+            .eq(dataset_reindexes_columns::points_migrated + points_migrated),
+        dataset_reindexes_columns::points_failed
+            .eq(dataset_reindexes_columns::points_failed + points_failed),
+        dataset_reindexes_columns::updated_at.eq(chrono::Utc::now().naive_local()),
+    ))
+    .execute(&mut conn)
+    .await
+    .map_err(|err| {
+        log::error!("Failed to update dataset reindex progress: {:?}", err);
+        ServiceError::InternalServerError("Failed to update dataset reindex progress".to_string())
+    })?;
+
+    Ok(())
+}
+
+/// Moves a reindex whose points have all been processed out of the processing state. Only one
+/// caller can win the update, so the configuration is flipped exactly once. Returns the reindex
+/// if this call finished it.
+pub async fn finish_dataset_reindex_query(
+    reindex_id: uuid::Uuid,
+    pool: web::Data<Pool>,
+) -> Result<Option<DatasetReindex>, ServiceError> {
+    use crate::data::schema::dataset_reindexes::dsl as dataset_reindexes_columns;
+
+    let mut conn = pool.get().await.map_err(|_e| {
+        ServiceError::InternalServerError("Failed to get postgres connection".to_string())
+    })?;
+
+    let finished_filter = dataset_reindexes_columns::dataset_reindexes
+        .filter(dataset_reindexes_columns::id.eq(reindex_id))
+        .filter(dataset_reindexes_columns::status.eq(DatasetReindexStatus::Processing.to_string()))
+        .filter(dataset_reindexes_columns::points_total.is_not_null())
+        .filter(sql::<sql_types::Bool>(
+            "points_migrated + points_failed >= points_total",
+        ));
+
+    let completed_reindex = diesel::update(
+        finished_filter
+            .clone()
+            .filter(dataset_reindexes_columns::points_failed.eq(0)),
+    )
+    .set((
+        dataset_reindexes_columns::status.eq(DatasetReindexStatus::Completed.to_string()),
+        dataset_reindexes_columns::updated_at.eq(chrono::Utc::now().naive_local()),
+    ))
+    .get_result::<DatasetReindex>(&mut conn)
+    .await
+    .optional()
+    .map_err(|err| {
+        log::error!("Failed to finish dataset reindex: {:?}", err);
+        ServiceError::InternalServerError("Failed to finish dataset reindex".to_string())
+    })?;
+
+    if completed_reindex.is_some() {
+        return Ok(completed_reindex);
+    }
+
+    diesel::update(finished_filter.filter(dataset_reindexes_columns::points_failed.gt(0)))
+        .set((
+            dataset_reindexes_columns::status.eq(DatasetReindexStatus::Failed.to_string()),
+            dataset_reindexes_columns::error.eq(Some(
+                "Some points failed to migrate, the dataset configuration was not changed"
+                    .to_string(),
+            )),
+            dataset_reindexes_columns::updated_at.eq(chrono::Utc::now().naive_local()),
+        ))
+        .get_result::<DatasetReindex>(&mut conn)
+        .await
+        .optional()
+        .map_err(|err| {
+            log::error!("Failed to finish dataset reindex: {:?}", err);
+            ServiceError::InternalServerError("Failed to finish dataset reindex".to_string())
+        })
+}
+
+pub fn apply_migration_mode(
+    mut dataset_config: DatasetConfiguration,
+    mode: &MigrationMode,
+) -> DatasetConfiguration {
+    match mode {
+        MigrationMode::BM25 { average_len, k, b } => {
+            dataset_config.BM25_AVG_LEN = *average_len;
+            dataset_config.BM25_K = *k;
+            dataset_config.BM25_B = *b;
+        }
+        MigrationMode::Reembed {
+            embedding_model_name,
+            embedding_base_url,
+            embedding_size,
+        } => {
+            dataset_config.EMBEDDING_MODEL_NAME = embedding_model_name.clone();
+            dataset_config.EMBEDDING_BASE_URL = embedding_base_url.clone();
+            dataset_config.EMBEDDING_SIZE = *embedding_size;
+        }
+    }
+
+    dataset_config
+}
+
+/// Points the dataset at the migrated vectors once a reindex has completed. Returns the time of
+/// the switch, writes made before it went to the old vectors and have to be replayed.
+pub async fn apply_dataset_reindex_configuration_query(
+    dataset_reindex: &DatasetReindex,
+    pool: web::Data<Pool>,
+) -> Result<chrono::NaiveDateTime, ServiceError> {
+    let applied_at = chrono::Utc::now().naive_local();
+    let dataset = get_dataset_by_id_query(dataset_reindex.dataset_id, pool.clone()).await?;
+    let dataset_config = apply_migration_mode(
+        DatasetConfiguration::from_json(dataset.server_configuration.clone()),
+        &dataset_reindex.migration_mode()?,
+    );
+
+    update_dataset_query(
+        dataset.id,
+        dataset.name,
+        dataset_config,
+        None,
+        DatasetConfigVersionSource::Reindex,
+        None,
+        None,
+        pool,
+    )
+    .await?;
+
+    Ok(applied_at)
+}
+
+/// Removes a dataset's points from a collection it no longer uses.
+pub async fn delete_dataset_points_from_collection_query(
+    dataset_id: uuid::Uuid,
+    collection: String,
+) -> Result<(), ServiceError> {
+    loop {
+        let (point_ids, _) = scroll_qdrant_collection_ids(
+            collection.clone(),
+            None,
+            Some(1000),
+            Some(Filter::must([Condition::matches(
+                "dataset_id",
+                dataset_id.to_string(),
+            )])),
+        )
+        .await?;
+
+        if point_ids.is_empty() {
+            return Ok(());
+        }
+
+        delete_points_from_qdrant(point_ids, collection.clone()).await?;
+    }
+}
+
+/// Removes the points a reindex wrote to its target collection. Used when a re-embedding is
+/// cancelled or fails, BM25 recomputations write in place and leave nothing to remove.
+pub async fn delete_dataset_reindex_points_query(
+    dataset_reindex: &DatasetReindex,
+) -> Result<(), ServiceError> {
+    if dataset_reindex.from_collection == dataset_reindex.to_collection {
+        return Ok(());
+    }
+
+    delete_dataset_points_from_collection_query(
+        dataset_reindex.dataset_id,
+        dataset_reindex.to_collection.clone(),
+    )
+    .await
+}
+
+/// Group centroids are averaged from the dataset's dense vectors, so after a re-embedding every
+/// centroid is queued to be recomputed from the new vectors and the centroids left in the old
+/// centroid collection are removed.
+pub async fn migrate_dataset_group_centroids_query(
+    dataset_reindex: &DatasetReindex,
+    pool: web::Data<Pool>,
+    redis_pool: web::Data<RedisPool>,
+) -> Result<(), ServiceError> {
+    if !matches!(
+        dataset_reindex.migration_mode()?,
+        MigrationMode::Reembed { .. }
+    ) {
+        return Ok(());
+    }
+
+    let dataset_config = get_dataset_config_query(dataset_reindex.dataset_id, pool.clone()).await?;
+    mark_dataset_group_centroids_stale_query(
+        &dataset_config,
+        dataset_reindex.dataset_id,
+        pool,
+        redis_pool,
+    )
+    .await?;
+
+    if dataset_reindex.from_collection == dataset_reindex.to_collection {
+        return Ok(());
+    }
+
+    delete_dataset_points_from_collection_query(
+        dataset_reindex.dataset_id,
+        format!("{}_group_centroids", dataset_reindex.from_collection),
+    )
+    .await
+}
+
+/// Returns a page of the qdrant point ids of chunks created or updated within `[since, until)`,
+/// ordered by chunk id, along with the offset of the next page.
+pub async fn get_chunk_point_ids_updated_between_query(
+    dataset_id: uuid::Uuid,
+    since: chrono::NaiveDateTime,
+    until: chrono::NaiveDateTime,
+    offset: Option<uuid::Uuid>,
+    limit: i64,
+    pool: web::Data<Pool>,
+) -> Result<(Vec<uuid::Uuid>, Option<uuid::Uuid>), ServiceError> {
+    use crate::data::schema::chunk_metadata::dsl as chunk_metadata_columns;
+
+    let mut conn = pool.get().await.map_err(|_e| {
+        ServiceError::InternalServerError("Failed to get postgres connection".to_string())
+    })?;
+
+    let mut query = chunk_metadata_columns::chunk_metadata
+        .filter(chunk_metadata_columns::dataset_id.eq(dataset_id))
+        .filter(chunk_metadata_columns::updated_at.ge(since))
+        .filter(chunk_metadata_columns::updated_at.lt(until))
+        .into_boxed();
+
+    if let Some(offset) = offset {
+        query = query.filter(chunk_metadata_columns::id.gt(offset));
+    }
+
+    let chunks: Vec<(uuid::Uuid, uuid::Uuid)> = query
+        .select((
+            chunk_metadata_columns::id,
+            chunk_metadata_columns::qdrant_point_id,
+        ))
+        .order(chunk_metadata_columns::id.asc())
+        .limit(limit)
+        .load(&mut conn)
+        .await
+        .map_err(|err| {
+            log::error!("Failed to get updated chunks: {:?}", err);
+            ServiceError::InternalServerError("Failed to get updated chunks".to_string())
+        })?;
+
+    let next_offset = chunks.last().map(|(id, _)| *id);
+
+    Ok((
+        chunks
+            .into_iter()
+            .map(|(_, qdrant_point_id)| qdrant_point_id)
+            .collect(),
+        next_offset,
+    ))
+}
+
+/// Removes the dataset's points from a collection whose chunks no longer exist, i.e. chunks
+/// deleted from the old vectors after their points were migrated.
+pub async fn delete_orphaned_dataset_points_query(
+    dataset_id: uuid::Uuid,
+    collection: String,
+    pool: web::Data<Pool>,
+) -> Result<(), ServiceError> {
+    use crate::data::schema::chunk_metadata::dsl as chunk_metadata_columns;
+
+    let mut offset = None;
+    loop {
+        let (point_ids, next_offset) = scroll_qdrant_collection_ids(
+            collection.clone(),
+            offset,
+            Some(1000),
+            Some(Filter::must([Condition::matches(
+                "dataset_id",
+                dataset_id.to_string(),
+            )])),
+        )
+        .await?;
+
+        if !point_ids.is_empty() {
+            let mut conn = pool.get().await.map_err(|_e| {
+                ServiceError::InternalServerError("Failed to get postgres connection".to_string())
+            })?;
+
+            let existing_point_ids: HashSet<uuid::Uuid> = chunk_metadata_columns::chunk_metadata
+                .filter(chunk_metadata_columns::qdrant_point_id.eq_any(&point_ids))
+                .select(chunk_metadata_columns::qdrant_point_id)
+                .load::<uuid::Uuid>(&mut conn)
+                .await
+                .map_err(|err| {
+                    log::error!("Failed to get chunk point ids: {:?}", err);
+                    ServiceError::InternalServerError("Failed to get chunk point ids".to_string())
+                })?
+                .into_iter()
+                .collect();
+
+            delete_points_from_qdrant(
+                point_ids
+                    .into_iter()
+                    .filter(|point_id| !existing_point_ids.contains(point_id))
+                    .collect(),
+                collection.clone(),
+            )
+            .await?;
+        }
+
+        offset = next_offset.filter(|next_offset| !next_offset.is_empty());
+        if offset.is_none() {
+            return Ok(());
+        }
+    }
+}
+
+/// Estimates the seconds left in a reindex from its throughput so far.
+pub fn get_dataset_reindex_eta(dataset_reindex: &DatasetReindex) -> Option<i64> {
+    if dataset_reindex.status != DatasetReindexStatus::Processing.to_string() {
+        return None;
+    }
+
+    let points_total = dataset_reindex.points_total?;
+    let points_processed = dataset_reindex.points_migrated + dataset_reindex.points_failed;
+    if points_processed == 0 {
+        return None;
+    }
+
+    let started_at = dataset_reindex.started_at?;
+    let elapsed_seconds = (chrono::Utc::now().naive_local() - started_at)
+        .num_seconds()
+        .max(1);
+    let points_remaining = (points_total - points_processed).max(0);
+
+    Some(points_remaining * elapsed_seconds / points_processed)
+}
@@ -3,7 +3,11 @@ use super::chunk_operator::{
     get_highlights, get_highlights_with_exact_match, get_qdrant_ids_from_chunk_ids_query,
     get_slim_chunks_from_point_ids_query, get_stop_words, HighlightStrategy,
 };
+use super::group_aggregate_operator::{
+    get_group_aggregate_name, get_group_ids_matching_aggregate_condition_query,
+};
 use super::group_operator::{
+    get_descendant_group_ids_query, get_group_ancestor_ids_query,
     get_group_ids_from_tracking_ids_query, get_groups_from_group_ids_query,
 };
 use super::message_operator::{get_text_from_audio, get_text_from_image};
@@ -169,6 +173,83 @@ async fn convert_group_tracking_ids_to_group_ids(
                     .to_string(),
             ))?
         }
+    } else if condition.field == "descendant_of_group_ids"
+        || condition.field == "descendant_of_group_tracking_ids"
+    {
+        let match_any = condition
+            .r#match_any
+            .ok_or(ServiceError::BadRequest(format!(
+                "{} filter can only be used with match_any clauses",
+                condition.field
+            )))?;
+        let matches = match_any
+            .iter()
+            .map(|item| item.to_string())
+            .collect::<Vec<String>>();
+
+        let root_group_ids = if condition.field == "descendant_of_group_tracking_ids" {
+            get_group_ids_from_tracking_ids_query(matches, dataset_id, pool.clone())
+                .await?
+                .into_iter()
+                .map(|(id, _)| id)
+                .collect::<Vec<uuid::Uuid>>()
+        } else {
+            matches
+                .iter()
+                .map(|id| {
+                    uuid::Uuid::parse_str(id).map_err(|_| {
+                        ServiceError::BadRequest(format!(
+                            "descendant_of_group_ids filter contains invalid group id {}",
+                            id
+                        ))
+                    })
+                })
+                .collect::<Result<Vec<uuid::Uuid>, ServiceError>>()?
+        };
+
+        let mut correct_matches: Vec<MatchCondition> =
+            get_descendant_group_ids_query(root_group_ids, dataset_id, pool.clone())
+                .await?
+                .iter()
+                .map(|id| MatchCondition::Text(id.to_string()))
+                .collect();
+        if correct_matches.is_empty() {
+            correct_matches.push(MatchCondition::Text(uuid::Uuid::default().to_string()));
+        }
+
+        Ok(FieldCondition {
+            field: "group_ids".to_string(),
+            match_any: Some(correct_matches),
+            match_all: None,
+            date_range: None,
+            range: None,
+            boolean: None,
+            geo_bounding_box: None,
+            geo_polygon: None,
+            geo_radius: None,
+        })
+    } else if get_group_aggregate_name(&condition.field).is_some() {
+        let mut correct_matches: Vec<MatchCondition> =
+            get_group_ids_matching_aggregate_condition_query(&condition, dataset_id, pool.clone())
+                .await?
+                .iter()
+                .map(|id| MatchCondition::Text(id.to_string()))
+                .collect();
+        if correct_matches.is_empty() {
+            correct_matches.push(MatchCondition::Text(uuid::Uuid::default().to_string()));
+        }
+
+        Ok(FieldCondition {
+            field: "group_ids".to_string(),
+            match_any: Some(correct_matches),
+            match_all: None,
+            date_range: None,
+            range: None,
+            boolean: None,
+            geo_bounding_box: None,
+            geo_polygon: None,
+            geo_radius: None,
+        })
     } else {
         Ok(condition)
     }
@@ -1249,6 +1330,7 @@ pub struct GroupScoreChunk {
     pub group_metadata: Option<serde_json::Value>,
     pub group_tag_set: Option<Vec<Option<String>>>,
     pub group_dataset_id: uuid::Uuid,
+    pub group_parent_id: Option<uuid::Uuid>,
     pub metadata: Vec<ScoreChunkDTO>,
     pub file_id: Option<uuid::Uuid>,
 }
@@ -1275,6 +1357,7 @@ impl From<GroupScoreChunk> for SearchOverGroupsResults {
                 tracking_id: val.group_tracking_id,
                 metadata: val.group_metadata,
                 tag_set: val.group_tag_set,
+                parent_id: val.group_parent_id,
             },
             chunks: val
                 .metadata
@@ -1310,6 +1393,133 @@ impl DeprecatedSearchOverGroupsResponseBody {
     }
 }
 
+/// Groups fetched per requested group when results are rolled up or sorted after the search,
+/// merging leaves fewer groups than were fetched
+const REORDERED_GROUP_CANDIDATE_FACTOR: u64 = 5;
+/// Upper bound on the groups fetched to roll up or sort, deep pages may come back short past it
+const MAX_REORDERED_GROUP_CANDIDATES: u64 = 1000;
+
+/// Number of groups to fetch as the first page of results so the groups rolled up or sorted from
+/// them fill the requested page
+pub fn get_reordered_group_candidate_count(page: u64, page_size: u64) -> u64 {
+    (page.max(1) * page_size * REORDERED_GROUP_CANDIDATE_FACTOR)
+        .min(MAX_REORDERED_GROUP_CANDIDATES)
+        .max(page_size)
+}
+
+/// Cuts the requested page out of group results which were fetched as a single page of
+/// `candidate_count` groups. Total pages are exact once every candidate was returned, otherwise
+/// they are estimated from the total before the groups were merged.
+pub fn paginate_group_results(
+    result: DeprecatedSearchOverGroupsResponseBody,
+    fetched_count: usize,
+    candidate_count: u64,
+    page: u64,
+    page_size: u64,
+) -> DeprecatedSearchOverGroupsResponseBody {
+    let page_size = page_size.max(1);
+    let total_chunk_pages = if (fetched_count as u64) < candidate_count {
+        (result.group_chunks.len() as u64).div_ceil(page_size) as i64
+    } else {
+        (result.total_chunk_pages.max(0) as u64 * candidate_count).div_ceil(page_size) as i64
+    };
+
+    let group_chunks = result
+        .group_chunks
+        .into_iter()
+        .skip(((page.max(1) - 1) * page_size) as usize)
+        .take(page_size as usize)
+        .collect_vec();
+
+    DeprecatedSearchOverGroupsResponseBody {
+        group_chunks,
+        total_chunk_pages,
+        ..result
+    }
+}
+
+/// Merges each group in the results into its ancestor at `group_level` of the hierarchy, where 0 is
+/// the top-level groups. Groups nested less deeply than `group_level` are kept as they are. Merged
+/// groups keep the rank of their best ranked member and their `group_size` best scoring chunks.
+#[tracing::instrument(skip_all)]
+pub async fn roll_up_groups_to_level(
+    result: DeprecatedSearchOverGroupsResponseBody,
+    group_level: u32,
+    group_size: usize,
+    dataset_id: uuid::Uuid,
+    pool: web::Data<Pool>,
+) -> Result<DeprecatedSearchOverGroupsResponseBody, ServiceError> {
+    let group_ids = result
+        .group_chunks
+        .iter()
+        .map(|group_chunk| group_chunk.group_id)
+        .collect_vec();
+    let ancestor_ids = get_group_ancestor_ids_query(group_ids, dataset_id, pool.clone()).await?;
+
+    let level_group_id = |group_id: uuid::Uuid| -> uuid::Uuid {
+        let mut path = ancestor_ids.get(&group_id).cloned().unwrap_or_default();
+        path.reverse();
+        path.push(group_id);
+        path.get(group_level as usize).copied().unwrap_or(group_id)
+    };
+
+    let mut rolled_up_ids: Vec<uuid::Uuid> = vec![];
+    let mut rolled_up_chunks: HashMap<uuid::Uuid, Vec<ScoreChunkDTO>> = HashMap::new();
+    for group_chunk in result.group_chunks.iter() {
+        let level_group_id = level_group_id(group_chunk.group_id);
+        if !rolled_up_chunks.contains_key(&level_group_id) {
+            rolled_up_ids.push(level_group_id);
+        }
+
+        rolled_up_chunks
+            .entry(level_group_id)
+            .or_default()
+            .extend(group_chunk.metadata.clone());
+    }
+
+    let level_groups = get_groups_from_group_ids_query(rolled_up_ids.clone(), pool).await?;
+
+    let group_chunks = rolled_up_ids
+        .into_iter()
+        .filter_map(|group_id| {
+            let group = level_groups.iter().find(|group| group.id == group_id)?;
+            let score_chunks = rolled_up_chunks
+                .remove(&group_id)
+                .unwrap_or_default()
+                .into_iter()
+                .sorted_by(|a, b| b.score.partial_cmp(&a.score).unwrap_or(Ordering::Equal))
+                .unique_by(|score_chunk| {
+                    score_chunk
+                        .metadata
+                        .first()
+                        .map(|chunk| chunk.metadata().id)
+                })
+                .take(group_size)
+                .collect_vec();
+
+            Some(GroupScoreChunk {
+                group_id: group.id,
+                group_name: Some(group.name.clone()),
+                group_description: Some(group.description.clone()),
+                group_created_at: group.created_at,
+                group_updated_at: group.updated_at,
+                group_tracking_id: group.tracking_id.clone(),
+                group_metadata: group.metadata.clone(),
+                group_tag_set: group.tag_set.clone(),
+                group_dataset_id: group.dataset_id,
+                group_parent_id: group.parent_id,
+                metadata: score_chunks,
+                file_id: group.file_id,
+            })
+        })
+        .collect_vec();
+
+    Ok(DeprecatedSearchOverGroupsResponseBody {
+        group_chunks,
+        ..result
+    })
+}
+
 #[derive(Serialize, Deserialize, ToSchema)]
 #[schema(title = "V2")]
 pub struct SearchOverGroupsResponseBody {
@@ -1456,6 +1666,7 @@ pub async fn retrieve_chunks_for_groups(
                 group_metadata: group_data.and_then(|group| group.metadata.clone()),
                 group_tag_set: group_data.and_then(|group| group.tag_set.clone()),
                 group_dataset_id: group_data.map(|group| group.dataset_id).unwrap_or_default(),
+                group_parent_id: group_data.and_then(|group| group.parent_id),
                 metadata: score_chunks,
                 file_id: group_data.and_then(|group| group.file_id),
             }
@@ -1538,6 +1749,7 @@ pub async fn get_metadata_from_groups(
                 group_metadata: group_data.and_then(|group| group.metadata.clone()),
                 group_tag_set: group_data.and_then(|group| group.tag_set.clone()),
                 group_dataset_id: group_data.map(|group| group.dataset_id).unwrap_or_default(),
+                group_parent_id: group_data.and_then(|group| group.parent_id),
                 metadata: score_chunk,
                 file_id: group_data.and_then(|group| group.file_id),
             }
@@ -2167,7 +2379,7 @@ pub fn rerank_groups(
     reranked_groups
 }
 
-async fn get_qdrant_vector(
+pub async fn get_qdrant_vector(
     search_type: SearchMethod,
     parsed_query: ParsedQueryTypes,
     scoring_options: Option<ScoringOptions>,
@@ -2346,6 +2558,27 @@ pub async fn search_chunks_query(
 
     timer.add("computed query vector");
 
+    let mut result_chunks =
+        search_chunks_with_vector_query(data, parsed_query, vector, pool, dataset, config, timer)
+            .await?;
+
+    result_chunks.corrected_query = corrected_query.map(|c| c.query);
+
+    Ok(result_chunks)
+}
+
+/// Runs a non-hybrid search against a dataset with an already computed query vector. This lets
+/// callers searching several datasets which share an embedding model reuse a single embedding.
+#[tracing::instrument(skip_all)]
+pub async fn search_chunks_with_vector_query(
+    data: SearchChunksReqPayload,
+    parsed_query: ParsedQueryTypes,
+    vector: VectorType,
+    pool: web::Data<Pool>,
+    dataset: Dataset,
+    config: &DatasetConfiguration,
+    timer: &mut Timer,
+) -> Result<SearchChunkQueryResponseBody, actix_web::Error> {
     let (sort_by, rerank_by) = match data.sort_options.as_ref().map(|d| d.sort_by.clone()) {
         Some(Some(sort_by)) => match sort_by {
             QdrantSortBy::Field(field) => (Some(field.clone()), None),
@@ -2426,8 +2659,6 @@ pub async fn search_chunks_query(
 
     timer.add("reranking");
 
-    result_chunks.corrected_query = corrected_query.map(|c| c.query);
-
     Ok(result_chunks)
 }
 
@@ -2490,6 +2721,36 @@ pub async fn search_hybrid_chunks(
 
     timer.add("computed sparse and dense embeddings");
 
+    let mut reranked_chunks = search_hybrid_chunks_with_vectors_query(
+        data,
+        parsed_query,
+        dense_vector,
+        sparse_vector,
+        pool,
+        dataset,
+        config,
+        timer,
+    )
+    .await?;
+
+    reranked_chunks.corrected_query = corrected_query.map(|c| c.query);
+
+    Ok(reranked_chunks)
+}
+
+/// Runs a hybrid search against a dataset with already computed dense and sparse query vectors.
+#[allow(clippy::too_many_arguments)]
+#[tracing::instrument(skip_all)]
+pub async fn search_hybrid_chunks_with_vectors_query(
+    data: SearchChunksReqPayload,
+    parsed_query: ParsedQuery,
+    dense_vector: Vec<f32>,
+    sparse_vector: Vec<(u32, f32)>,
+    pool: web::Data<Pool>,
+    dataset: Dataset,
+    config: &DatasetConfiguration,
+    timer: &mut Timer,
+) -> Result<SearchChunkQueryResponseBody, actix_web::Error> {
     let (sort_by, rerank_by) = match data.sort_options.as_ref().map(|d| d.sort_by.clone()) {
         Some(Some(sort_by)) => match sort_by {
             QdrantSortBy::Field(field) => (Some(field.clone()), None),
@@ -2584,7 +2845,7 @@ pub async fn search_hybrid_chunks(
 
         SearchChunkQueryResponseBody {
             score_chunks: reranked_chunks,
-            corrected_query: corrected_query.map(|c| c.query),
+            corrected_query: None,
             total_chunk_pages: result_chunks.total_chunk_pages,
         }
     };
@@ -2616,6 +2877,286 @@ pub async fn search_hybrid_chunks(
     Ok(reranked_chunks)
 }
 
+pub struct FederatedSearchDataset {
+    pub dataset: Dataset,
+    pub config: DatasetConfiguration,
+    pub data: SearchChunksReqPayload,
+    pub parsed_query: ParsedQueryTypes,
+}
+
+#[derive(Debug, Clone)]
+pub struct FederatedScoreChunkDTO {
+    pub dataset_id: uuid::Uuid,
+    pub score_chunk: ScoreChunkDTO,
+}
+
+#[derive(Debug, Clone)]
+enum FederatedQueryVector {
+    Single(VectorType),
+    Hybrid(Vec<f32>, Vec<(u32, f32)>),
+}
+
+/// Datasets with the same key produce identical query vectors, so the embedding only has to be
+/// computed once for all of them.
+fn get_federated_vector_key(federated_dataset: &FederatedSearchDataset) -> String {
+    let config = &federated_dataset.config;
+    let model_key = match federated_dataset.data.search_type {
+        SearchMethod::Semantic | SearchMethod::Hybrid => format!(
+            "{}:{}:{}:{}:{}",
+            config.SEMANTIC_ENABLED,
+            config.EMBEDDING_BASE_URL,
+            config.EMBEDDING_MODEL_NAME,
+            config.EMBEDDING_QUERY_PREFIX,
+            config.EMBEDDING_SIZE
+        ),
+        SearchMethod::BM25 => format!(
+            "{}:{}:{}",
+            config.BM25_AVG_LEN, config.BM25_B, config.BM25_K
+        ),
+        SearchMethod::FullText => config.FULLTEXT_ENABLED.to_string(),
+    };
+
+    format!(
+        "{}:{}:{:?}",
+        federated_dataset.data.search_type, model_key, federated_dataset.parsed_query
+    )
+}
+
+/// Min-max normalizes the scores of a single dataset's results to the range 0 to 1 so they can be
+/// compared with the results of other datasets. Results are returned best first, so a list whose
+/// scores increase is scored by distance and gets inverted.
+fn normalize_federated_scores(score_chunks: &mut [ScoreChunkDTO]) {
+    let (Some(first), Some(last)) = (score_chunks.first(), score_chunks.last()) else {
+        return;
+    };
+    let lower_is_better = first.score < last.score;
+
+    let min_score = score_chunks
+        .iter()
+        .map(|chunk| chunk.score)
+        .fold(f64::INFINITY, f64::min);
+    let max_score = score_chunks
+        .iter()
+        .map(|chunk| chunk.score)
+        .fold(f64::NEG_INFINITY, f64::max);
+    let range = max_score - min_score;
+
+    for score_chunk in score_chunks.iter_mut() {
+        let normalized_score = if range <= f64::EPSILON {
+            1.0
+        } else {
+            (score_chunk.score - min_score) / range
+        };
+
+        score_chunk.score = if lower_is_better {
+            1.0 - normalized_score
+        } else {
+            normalized_score
+        };
+    }
+}
+
+#[tracing::instrument(skip_all)]
+pub async fn search_federated_chunks_query(
+    federated_datasets: Vec<FederatedSearchDataset>,
+    rerank: bool,
+    page_size: u64,
+    pool: web::Data<Pool>,
+    redis_pool: web::Data<RedisPool>,
+    timer: &mut Timer,
+) -> Result<Vec<FederatedScoreChunkDTO>, actix_web::Error> {
+    let rerank_target = federated_datasets
+        .first()
+        .map(|federated_dataset| {
+            (
+                federated_dataset.parsed_query.clone(),
+                federated_dataset.config.clone(),
+            )
+        })
+        .ok_or(ServiceError::BadRequest(
+            "At least one dataset must be specified".to_string(),
+        ))?;
+
+    timer.add("start to create query vectors");
+
+    // Typo correction rewrites the query per dataset, so shared vectors are only computed when it
+    // is disabled.
+    let vector_futures = federated_datasets
+        .iter()
+        .filter(|federated_dataset| federated_dataset.data.typo_options.is_none())
+        .map(|federated_dataset| {
+            (
+                get_federated_vector_key(federated_dataset),
+                federated_dataset,
+            )
+        })
+        .unique_by(|(key, _)| key.clone())
+        .map(|(key, federated_dataset)| async move {
+            let data = &federated_dataset.data;
+            let vector = match data.search_type {
+                SearchMethod::Hybrid => {
+                    let parsed_query = federated_dataset.parsed_query.to_parsed_query()?;
+                    let semantic_boost = data
+                        .scoring_options
+                        .clone()
+                        .and_then(|options| options.semantic_boost);
+                    let fulltext_boost = data
+                        .scoring_options
+                        .clone()
+                        .and_then(|options| options.fulltext_boost);
+
+                    let (dense_vector, sparse_vector) = futures::try_join!(
+                        get_dense_vector(
+                            parsed_query.query.clone(),
+                            semantic_boost,
+                            "query",
+                            federated_dataset.config.clone(),
+                        ),
+                        get_sparse_vector(parsed_query.query.clone(), fulltext_boost, "query")
+                    )?;
+
+                    FederatedQueryVector::Hybrid(dense_vector, sparse_vector)
+                }
+                _ => FederatedQueryVector::Single(
+                    get_qdrant_vector(
+                        data.search_type.clone(),
+                        federated_dataset.parsed_query.clone(),
+                        data.scoring_options.clone(),
+                        &federated_dataset.config,
+                    )
+                    .await?,
+                ),
+            };
+
+            Ok((key, vector)) as Result<(String, FederatedQueryVector), ServiceError>
+        });
+
+    let vectors: HashMap<String, FederatedQueryVector> =
+        futures::future::try_join_all(vector_futures)
+            .await?
+            .into_iter()
+            .collect();
+
+    timer.add("computed query vectors");
+
+    let search_futures = federated_datasets.into_iter().map(|federated_dataset| {
+        let vector = vectors
+            .get(&get_federated_vector_key(&federated_dataset))
+            .cloned();
+        let pool = pool.clone();
+        let redis_pool = redis_pool.clone();
+
+        async move {
+            let mut dataset_timer = Timer::new();
+            let FederatedSearchDataset {
+                dataset,
+                config,
+                data,
+                parsed_query,
+            } = federated_dataset;
+
+            let result_chunks = match (data.search_type.clone(), vector) {
+                (_, Some(FederatedQueryVector::Hybrid(dense_vector, sparse_vector))) => {
+                    search_hybrid_chunks_with_vectors_query(
+                        data,
+                        parsed_query.to_parsed_query()?,
+                        dense_vector,
+                        sparse_vector,
+                        pool,
+                        dataset.clone(),
+                        &config,
+                        &mut dataset_timer,
+                    )
+                    .await?
+                }
+                (_, Some(FederatedQueryVector::Single(vector))) => {
+                    search_chunks_with_vector_query(
+                        data,
+                        parsed_query,
+                        vector,
+                        pool,
+                        dataset.clone(),
+                        &config,
+                        &mut dataset_timer,
+                    )
+                    .await?
+                }
+                (SearchMethod::Hybrid, None) => {
+                    search_hybrid_chunks(
+                        data,
+                        parsed_query.to_parsed_query()?,
+                        pool,
+                        redis_pool,
+                        dataset.clone(),
+                        &config,
+                        &mut dataset_timer,
+                    )
+                    .await?
+                }
+                (_, None) => {
+                    search_chunks_query(
+                        data,
+                        parsed_query,
+                        pool,
+                        redis_pool,
+                        dataset.clone(),
+                        &config,
+                        &mut dataset_timer,
+                    )
+                    .await?
+                }
+            };
+
+            Ok((dataset.id, result_chunks.score_chunks)) as Result<_, actix_web::Error>
+        }
+    });
+
+    let dataset_results = futures::future::try_join_all(search_futures).await?;
+
+    timer.add("searched datasets");
+
+    let mut dataset_ids_by_chunk_id = HashMap::new();
+    let mut merged_chunks = dataset_results
+        .into_iter()
+        .flat_map(|(dataset_id, mut score_chunks)| {
+            normalize_federated_scores(&mut score_chunks);
+            for score_chunk in score_chunks.iter() {
+                if let Some(metadata) = score_chunk.metadata.first() {
+                    dataset_ids_by_chunk_id.insert(metadata.metadata().id, dataset_id);
+                }
+            }
+            score_chunks
+        })
+        .collect_vec();
+
+    if rerank {
+        let (parsed_query, config) = rerank_target;
+        merged_chunks = cross_encoder(
+            parsed_query.to_parsed_query()?.query,
+            page_size,
+            merged_chunks,
+            &config,
+        )
+        .await?;
+
+        timer.add("reranked merged results");
+    }
+
+    merged_chunks.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap_or(Ordering::Equal));
+    merged_chunks.truncate(page_size as usize);
+
+    Ok(merged_chunks
+        .into_iter()
+        .filter_map(|score_chunk| {
+            let chunk_id = score_chunk.metadata.first()?.metadata().id;
+            Some(FederatedScoreChunkDTO {
+                dataset_id: *dataset_ids_by_chunk_id.get(&chunk_id)?,
+                score_chunk,
+            })
+        })
+        .collect())
+}
+
 #[allow(clippy::too_many_arguments)]
 pub async fn search_groups_query(
     mut data: SearchWithinGroupReqPayload,